@@ -0,0 +1,35 @@
+use penlai::monitoring::capacity::{plan_capacity, CapacityPlanInput, ModelPricing};
+use penlai::monitoring::monitoring::{MonitoringEvent, MonitoringSystem};
+
+/// 用法: cargo run --example capacity_plan -- <目标QPS> <每请求上下文数> <输入单价/千token> <输出单价/千token>
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    let arg = |index: usize, default: f64| args.get(index).and_then(|v| v.parse().ok()).unwrap_or(default);
+
+    // 示例中使用模拟的监控数据；部署时传入运行中的MonitoringSystem
+    let monitor = MonitoringSystem::new();
+    for i in 0..200 {
+        monitor
+            .log_event(MonitoringEvent::RequestProcessed {
+                user_id: format!("user_{}", i % 20),
+                session_id: format!("session_{}", i % 50),
+                duration_ms: 150.0 + (i % 40) as f64 * 10.0,
+            })
+            .await;
+        monitor
+            .log_event(MonitoringEvent::CacheAccess { hit: i % 3 != 0, key_type: "selection".to_string() })
+            .await;
+    }
+
+    let pricing = ModelPricing {
+        model: "default".to_string(),
+        input_per_1k_tokens: arg(3, 0.0005),
+        output_per_1k_tokens: arg(4, 0.0015),
+    };
+    let input = CapacityPlanInput::new(arg(1, 50.0), arg(2, 3.0), pricing);
+    let report = plan_capacity(&monitor, &input).await;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
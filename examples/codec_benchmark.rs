@@ -0,0 +1,38 @@
+use penlai::context::codec::{codec_for, SerializationFormat};
+use penlai::context::fixtures::{load_fixtures, FixtureGenerator};
+use penlai::context::llm_context::ContextManager;
+use std::time::Instant;
+
+const CONTEXTS: usize = 200;
+const ITERATIONS: usize = 200;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    // 构造典型的上下文负载
+    let manager = ContextManager::new(100, 3600);
+    load_fixtures(&manager, CONTEXTS / FixtureGenerator::supported_domains().len()).await?;
+    let contexts = manager.get_all_contexts().await;
+
+    println!("上下文数量: {}, 迭代次数: {}", contexts.len(), ITERATIONS);
+    println!("{:<12} {:>10} {:>14} {:>14}", "格式", "字节数", "编码(µs/次)", "解码(µs/次)");
+    for format in [SerializationFormat::Json, SerializationFormat::MessagePack, SerializationFormat::Protobuf] {
+        let codec = codec_for(format);
+        let bytes = codec.encode(&contexts)?;
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            codec.encode(&contexts)?;
+        }
+        let encode = start.elapsed().as_micros() as f64 / ITERATIONS as f64;
+
+        let start = Instant::now();
+        for _ in 0..ITERATIONS {
+            codec.decode(&bytes)?;
+        }
+        let decode = start.elapsed().as_micros() as f64 / ITERATIONS as f64;
+
+        println!("{:<12} {:>10} {:>14.1} {:>14.1}", format!("{:?}", format), bytes.len(), encode, decode);
+    }
+
+    Ok(())
+}
@@ -1,10 +1,13 @@
+use penlai::cache::cache::CacheManager;
 use penlai::context::llm_context::ContextManager;
+use penlai::context::fixtures::{FixtureConfig, FixtureGenerator};
 use penlai::selection::async_context_selector::ContextSelector;
 use penlai::processing::concurrent_processor::RequestProcessor;
 use penlai::monitoring::monitoring::MonitoringSystem;
-use penlai::utils::ai_client::{AIClient, ChatMessage};
+use penlai::utils::ai_client::ChatMessage;
 use penlai::utils::ai_integration::AIIntegration;
 use std::sync::Arc;
+use std::time::Instant;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -200,6 +203,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("     - 最大并发请求数: {}", proc_stats.max_concurrent_requests);
     println!("     - 用户跟踪数: {}", proc_stats.total_users_tracked);
 
+    println!("\n7. 读穿缓存性能对比...");
+
+    let cache_manager = Arc::new(CacheManager::new());
+    let cached_manager = ContextManager::new(100, 3600).with_cache(cache_manager.clone());
+    let uncached_manager = ContextManager::new(100, 3600);
+    let fixtures = FixtureConfig {
+        domains: vec!["medical".to_string()],
+        contexts_per_domain: 200,
+        sessions: 10,
+        ..FixtureConfig::default()
+    };
+    for manager in [&cached_manager, &uncached_manager] {
+        FixtureGenerator::new(fixtures.clone())
+            .load_into(manager)
+            .await
+            .map_err(|e| format!("Failed to load benchmark contexts: {}", e))?;
+    }
+
+    let lookups = 1000;
+    for (label, manager) in [("无缓存", &uncached_manager), ("读穿缓存", &cached_manager)] {
+        let start = Instant::now();
+        for i in 0..lookups {
+            manager.get_domain_contexts("medical").await;
+            manager.get_session_contexts(&format!("session_{}", i % 10)).await;
+        }
+        println!("   ✓ {}: {}次领域+会话查询耗时 {:?}", label, lookups, start.elapsed());
+    }
+
+    let cache_stats = cache_manager.get_stats().await;
+    println!(
+        "   ✓ 缓存统计: 命中 {}, 未命中 {}, 命中率 {:.2}%",
+        cache_stats.hit_count,
+        cache_stats.miss_count,
+        cache_stats.hit_rate * 100.0
+    );
+
     println!("\n=== 综合测试完成！ ===");
     println!("Penlai企业级异步上下文管理系统所有功能模块均正常工作：");
     println!("✓ 上下文管理 - 创建、存储、检索、过期管理");
@@ -208,6 +247,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("✓ AI集成 - 与AI服务集成并使用上下文信息");
     println!("✓ 监控系统 - 实时性能监控和警报");
     println!("✓ 系统统计 - 全面的系统状态监控");
+    println!("✓ 读穿缓存 - 领域和会话查询自动缓存与失效");
 
     Ok(())
 }
\ No newline at end of file
@@ -1,6 +1,5 @@
 use penlai::context::llm_context::ContextManager;
 use penlai::selection::async_context_selector::ContextSelector;
-use penlai::utils::ai_client::{AIClient, ChatMessage};
 use penlai::utils::ai_integration::AIIntegration;
 use std::sync::Arc;
 
@@ -12,8 +11,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let ai_integration = Arc::new(AIIntegration::new()?);
 
     // 创建多个用户和会话进行并发测试
-    let users = vec!["user_001", "user_002", "user_003"];
-    let sessions = vec!["session_001", "session_002", "session_003"];
+    let users = ["user_001", "user_002", "user_003"];
+    let sessions = ["session_001", "session_002", "session_003"];
     
     // 创建多个任务进行并发处理
     let mut handles = vec![];
@@ -21,7 +20,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     for (i, &user) in users.iter().enumerate() {
         let cm = context_manager.clone();
         let cs = context_selector.clone();
-        let ai = ai_integration.clone();
+        let _ai = ai_integration.clone();
         let session = sessions[i];
         
         let handle = tokio::spawn(async move {
@@ -1,4 +1,4 @@
-use penlai::domain::domain_classifier::{DomainClassifier, Domain};
+use penlai::domain::domain_classifier::DomainClassifier;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
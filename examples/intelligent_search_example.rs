@@ -1,4 +1,5 @@
 use penlai::context::context_management::ContextManager;
+use penlai::utils::search_options::SearchOptions;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -17,7 +18,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 1: Perform a code-related search (should route to GitHub)
     println!("\n=== Code-Related Search (Rust async) ===");
-    match context_manager.intelligent_search("Rust async programming examples").await {
+    match context_manager.intelligent_search("Rust async programming examples", &SearchOptions::default()).await {
         Ok(results) => {
             println!("Found {} results:", results.len());
             for (i, result) in results.iter().enumerate() {
@@ -34,7 +35,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 2: Perform a general search (should route to Bing)
     println!("\n=== General Search (天气) ===");
-    match context_manager.intelligent_search("今天北京天气如何").await {
+    match context_manager.intelligent_search("今天北京天气如何", &SearchOptions::default()).await {
         Ok(results) => {
             println!("Found {} results:", results.len());
             for (i, result) in results.iter().enumerate() {
@@ -71,7 +72,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // Technical query
     println!("Technical query: 'React hooks best practices'");
-    match context_manager.intelligent_search("React hooks best practices").await {
+    match context_manager.intelligent_search("React hooks best practices", &SearchOptions::default()).await {
         Ok(results) => {
             println!("  Intelligent search found {} results", results.len());
             if !results.is_empty() {
@@ -83,7 +84,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     // General query
     println!("\nGeneral query: 'healthy breakfast ideas'");
-    match context_manager.intelligent_search("healthy breakfast ideas").await {
+    match context_manager.intelligent_search("healthy breakfast ideas", &SearchOptions::default()).await {
         Ok(results) => {
             println!("  Intelligent search found {} results", results.len());
             if !results.is_empty() {
@@ -1,7 +1,6 @@
 use penlai::context::llm_context::ContextManager;
 use penlai::selection::async_context_selector::ContextSelector;
 use std::sync::Arc;
-use tokio;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
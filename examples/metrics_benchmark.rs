@@ -0,0 +1,55 @@
+use penlai::monitoring::monitoring::{MonitoringSystem, PerformanceMetric};
+use std::sync::Arc;
+use std::time::Instant;
+
+const TASKS: usize = 32;
+const UPDATES_PER_TASK: usize = 10_000;
+
+#[tokio::main]
+async fn main() {
+    let monitor = Arc::new(MonitoringSystem::new());
+
+    // 基于写锁和Vec的record_metric
+    let start = Instant::now();
+    let mut handles = Vec::new();
+    for _ in 0..TASKS {
+        let monitor = monitor.clone();
+        handles.push(tokio::spawn(async move {
+            for i in 0..UPDATES_PER_TASK {
+                monitor.record_metric("request_latency", PerformanceMetric::RequestLatency(i as f64)).await;
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    let locked = start.elapsed();
+
+    // 原子计数器和直方图
+    let registry = monitor.registry();
+    let start = Instant::now();
+    let mut handles = Vec::new();
+    for _ in 0..TASKS {
+        let registry = registry.clone();
+        handles.push(tokio::spawn(async move {
+            let requests = registry.counter("requests");
+            let latency = registry.histogram("request_latency_ms");
+            for i in 0..UPDATES_PER_TASK {
+                requests.inc();
+                latency.observe(i as f64);
+            }
+        }));
+    }
+    for handle in handles {
+        handle.await.unwrap();
+    }
+    let atomic = start.elapsed();
+
+    let snapshot = monitor.take_snapshot().await;
+    let total = (TASKS * UPDATES_PER_TASK) as f64;
+    println!("并发任务: {}, 每任务更新: {}", TASKS, UPDATES_PER_TASK);
+    println!("record_metric:   {:?} ({:.0} 次/秒)", locked, total / locked.as_secs_f64());
+    println!("原子指标:        {:?} ({:.0} 次/秒)", atomic, total / atomic.as_secs_f64());
+    println!("快照计数: {}", snapshot.counters["requests"]);
+    println!("P95延迟桶: {}ms", snapshot.histograms["request_latency_ms"].quantile(0.95));
+}
@@ -1,4 +1,5 @@
 use penlai::context::context_management::ContextManager;
+use penlai::utils::search_options::{Freshness, SearchOptions};
 use penlai::utils::web_search::SearchResult;
 
 #[tokio::main]
@@ -19,7 +20,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 1: Perform a direct web search
     println!("\n=== Direct Web Search ===");
-    match context_manager.web_search("Rust programming latest features").await {
+    match context_manager.web_search("Rust programming latest features", &SearchOptions::for_domain("technical").with_freshness(Freshness::Month)).await {
         Ok(results) => {
             println!("Found {} search results:", results.len());
             for (i, result) in results.iter().enumerate() {
@@ -59,7 +60,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         "Rust performance"
     ];
     
-    match context_manager.aggregate_web_search(&queries).await {
+    match context_manager.aggregate_web_search(&queries, &SearchOptions::default()).await {
         Ok(results) => {
             println!("Aggregate search returned {} results:", results.len());
             for (i, result) in results.iter().enumerate() {
@@ -74,7 +75,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Example 4: Show how to integrate search results with AI processing
     println!("\n=== Integrating Search Results with AI ===");
-    match context_manager.web_search("Rust language benefits").await {
+    match context_manager.web_search("Rust language benefits", &SearchOptions::default().with_market("en-US")).await {
         Ok(results) => {
             if !results.is_empty() {
                 // Format results for AI consumption
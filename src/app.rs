@@ -0,0 +1,583 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::context::expiry::{ExpiryNoticeConfig, ExpiryNotifier};
+use crate::context::ingestion::IngestionService;
+use crate::context::llm_context::ContextManager;
+use crate::context::id_strategy::IdStrategy;
+use crate::context::replica::{HttpSnapshotSource, ReplicaConfig, ReplicaSync};
+use crate::domain::domain_classifier::DomainClassifier;
+use crate::domain::keyword_store::{keywords_path, KeywordStore};
+use crate::monitoring::export::{analytics_sink_from_env, AnalyticsSink, EventExporter, ExportConfig};
+use crate::monitoring::monitoring::MonitoringSystem;
+use crate::monitoring::notify::WebhookSink;
+use crate::monitoring::webhook::{DeadLetterStore, WebhookDelivery};
+use crate::maintenance::{snapshot_dir, MaintenanceMode, MaintenanceStatus};
+use crate::monitoring::quota::QuotaWarner;
+use crate::monitoring::reports::ReportGenerator;
+use crate::processing::feedback::FeedbackStore;
+use crate::processing::concurrent_processor::{RequestProcessor, RequestProcessorConfig};
+use crate::selection::async_context_selector::{ContextSelector, ContextSelectorConfig};
+use crate::utils::ai_client::AIClient;
+use crate::utils::search_budget::SearchBudget;
+use crate::utils::outbound_scheduler::OutboundScheduler;
+use crate::utils::provider_health::{ProviderHealthChecker, ProviderStatus};
+use crate::utils::tokenizer::{tokenizer_from_spec, Tokenizer, WhitespaceTokenizer};
+
+/// 检查项的重要程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum CheckSeverity {
+    Critical,   // 失败时拒绝启动
+    Warning,    // 失败时降级运行
+}
+
+/// 检查结果状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,    // 未配置，不执行
+}
+
+/// 单项自检结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SelfTestCheck {
+    pub name: String,
+    pub severity: CheckSeverity,
+    pub status: CheckStatus,
+    pub duration_ms: f64,
+    pub detail: Option<String>,     // 失败原因或附加信息
+}
+
+/// 自检选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SelfTestOptions {
+    pub require_ai_endpoint: bool,  // AI端点不可用时是否拒绝启动
+    pub check_search: bool,         // 是否检查搜索服务
+    pub timeout_ms: u64,            // 单项网络检查超时
+}
+
+impl Default for SelfTestOptions {
+    fn default() -> Self {
+        Self {
+            require_ai_endpoint: true,
+            check_search: true,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+/// 就绪报告
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReadinessReport {
+    pub ready: bool,                // 所有关键检查均通过
+    pub degraded: bool,             // 存在失败的非关键检查
+    pub checks: Vec<SelfTestCheck>,
+    pub warmed_contexts: usize,     // 预热的上下文数量
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceStatus>, // 处于维护模式时由健康检查填入
+}
+
+impl ReadinessReport {
+    /// 失败的检查项
+    pub fn failures(&self) -> Vec<&SelfTestCheck> {
+        self.checks.iter().filter(|c| c.status == CheckStatus::Failed).collect()
+    }
+}
+
+impl std::fmt::Display for ReadinessReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Readiness: {} ({} checks, {:.0}ms)",
+            if self.ready { if self.degraded { "READY (degraded)" } else { "READY" } } else { "NOT READY" },
+            self.checks.len(),
+            self.duration_ms
+        )?;
+        for check in &self.checks {
+            write!(f, "  [{:?}] {} ({:?}, {:.0}ms)", check.status, check.name, check.severity, check.duration_ms)?;
+            if let Some(ref detail) = check.detail {
+                write!(f, ": {}", detail)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  Warmed contexts: {}", self.warmed_contexts)
+    }
+}
+
+/// 外部服务地址 - 启动时读取环境变量，可通过配置热加载的[providers]表切换
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProvidersConfig {
+    pub ai_base_url: Option<String>,        // AI端点地址（AI_BASE_URL）
+    pub bing_search_url: Option<String>,    // Bing搜索地址（BING_SEARCH_URL）
+    pub quota_webhook_url: Option<String>,  // 配额预警Webhook（PENLAI_QUOTA_WEBHOOK_URL）
+    pub report_webhook_url: Option<String>, // 定时报告Webhook（PENLAI_REPORT_WEBHOOK_URL）
+    pub expiry_webhook_url: Option<String>, // 过期提醒Webhook（PENLAI_EXPIRY_WEBHOOK_URL）
+}
+
+impl ProvidersConfig {
+    /// 从环境变量读取
+    pub fn from_env() -> Self {
+        Self {
+            ai_base_url: std::env::var("AI_BASE_URL").ok(),
+            bing_search_url: std::env::var("BING_SEARCH_URL").ok(),
+            quota_webhook_url: std::env::var("PENLAI_QUOTA_WEBHOOK_URL").ok(),
+            report_webhook_url: std::env::var("PENLAI_REPORT_WEBHOOK_URL").ok(),
+            expiry_webhook_url: std::env::var("PENLAI_EXPIRY_WEBHOOK_URL").ok(),
+        }
+    }
+}
+
+/// Penlai服务组件集合
+pub struct Penlai {
+    pub context_manager: Arc<ContextManager>,
+    pub context_selector: Arc<ContextSelector>,
+    pub request_processor: Arc<RequestProcessor>,
+    pub monitoring: Arc<MonitoringSystem>,
+    pub provider_health: Arc<ProviderHealthChecker>,
+    pub search_budget: Arc<SearchBudget>,
+    pub outbound: Arc<OutboundScheduler>,     // AI和搜索外呼的共享调度器
+    pub ingestion: Arc<IngestionService>,
+    pub reports: Arc<ReportGenerator>,
+    pub replica: Option<Arc<ReplicaSync>>,    // 以只读副本运行时的快照同步任务
+    pub replication_token: Option<String>,    // 向副本发布快照所需的令牌
+    pub expiry_notifier: Arc<ExpiryNotifier>, // 高优先级上下文的过期提醒
+    pub keyword_store: Option<Arc<KeywordStore>>, // 领域关键词管理，关键词文件无法加载时为空
+    pub webhooks: Arc<WebhookDelivery>,       // 出站Webhook事件的签名、重试和死信
+    pub maintenance: Arc<MaintenanceMode>,    // 维护模式开关，由请求处理器和后台任务共享
+    pub quota_warner: Arc<QuotaWarner>,       // 速率限制、令牌预算和搜索预算接近上限时的预警
+    pub exporter: Option<Arc<EventExporter>>, // 监控数据导出器，未配置分析数据库时为空
+    pub admin_token: Option<String>,          // 访问/api/admin/*等管理接口所需的令牌，未配置时管理接口不可用
+    pub snapshot_dir: PathBuf,                // 维护快照只能写入该目录
+    providers: tokio::sync::RwLock<ProvidersConfig>, // 当前使用的外部服务地址
+    analytics_sink: Option<Arc<dyn AnalyticsSink>>,  // 导出器写入的分析数据库
+}
+
+impl Penlai {
+    /// 使用默认配置创建所有组件
+    pub fn new(max_concurrent: usize, context_ttl_seconds: u64) -> Self {
+        let search_budget = Arc::new(SearchBudget::new());
+        let mut monitoring = MonitoringSystem::new().with_search_budget(search_budget.clone());
+        // 配置了分析数据库时导出监控事件和指标快照，队列满时丢弃的记录计入指标
+        let analytics_sink = match analytics_sink_from_env() {
+            Some(Ok(sink)) => Some(sink),
+            Some(Err(e)) => {
+                log::warn!("Invalid analytics export config, export disabled: {}", e);
+                None
+            }
+            None => None,
+        };
+        let exporter = analytics_sink
+            .as_ref()
+            .map(|_| Arc::new(EventExporter::new(ExportConfig::default()).with_registry(monitoring.registry())));
+        if let Some(ref exporter) = exporter {
+            monitoring = monitoring.with_exporter(exporter.clone());
+        }
+        let monitoring = Arc::new(monitoring);
+        let maintenance = Arc::new(MaintenanceMode::new());
+        let mut context_manager = ContextManager::new(max_concurrent, context_ttl_seconds)
+            .with_metrics_registry(monitoring.registry())
+            .with_maintenance(maintenance.clone());
+        if let Ok(name) = std::env::var("PENLAI_ID_STRATEGY") {
+            match IdStrategy::parse(&name) {
+                Some(strategy) => context_manager = context_manager.with_id_strategy(strategy),
+                None => log::warn!("Unknown PENLAI_ID_STRATEGY '{}', using random UUIDv4", name),
+            }
+        }
+        let context_manager = Arc::new(context_manager);
+        let replication_token = std::env::var("PENLAI_REPLICATION_TOKEN").ok();
+        // 配置了主实例地址时以只读副本运行
+        let replica = std::env::var("PENLAI_REPLICA_OF").ok().map(|primary| {
+            let mut source = HttpSnapshotSource::new(&primary);
+            if let Some(ref token) = replication_token {
+                source = source.with_token(token);
+            }
+            Arc::new(ReplicaSync::new(context_manager.clone(), Arc::new(source), ReplicaConfig::default()).with_maintenance(maintenance.clone()))
+        });
+        let outbound = Arc::new(OutboundScheduler::new());
+        let providers = ProvidersConfig::from_env();
+        // 选择、提示词装填和入库分块共用同一个分词器
+        let tokenizer: Arc<dyn Tokenizer> = match std::env::var("PENLAI_TOKENIZER") {
+            Ok(spec) => tokenizer_from_spec(&spec).unwrap_or_else(|e| {
+                log::warn!("Invalid PENLAI_TOKENIZER '{}', using whitespace tokenizer: {}", spec, e);
+                Arc::new(WhitespaceTokenizer)
+            }),
+            Err(_) => Arc::new(WhitespaceTokenizer),
+        };
+        let mut context_selector = ContextSelector::new(context_manager.clone())
+            .with_tokenizer(tokenizer.clone())
+            .with_scheduler(outbound.clone());
+        // AI客户端作为检索增强的文本生成器，经外呼调度器限速；请求费用按其模型计价
+        let mut processor_config = RequestProcessorConfig::default();
+        if let Ok(ai_client) = AIClient::new() {
+            processor_config.pricing.default_model = Some(ai_client.model().to_string());
+            context_selector = context_selector.with_text_generator(Arc::new(ai_client.with_scheduler(outbound.clone())));
+        }
+        let context_selector = Arc::new(context_selector);
+        let keyword_store = match KeywordStore::load(keywords_path()) {
+            Ok(store) => Some(Arc::new(store)),
+            Err(e) => {
+                log::warn!("Failed to load domain keywords from {}, keyword management disabled: {}", keywords_path().display(), e);
+                None
+            }
+        };
+        let mut webhooks = WebhookDelivery::new();
+        if let Ok(secret) = std::env::var("PENLAI_EVENT_SIGNING_SECRET") {
+            webhooks = webhooks.with_secret(&secret);
+        }
+        if let Ok(path) = std::env::var("PENLAI_WEBHOOK_DEAD_LETTERS") {
+            match DeadLetterStore::open(&path, 10_000) {
+                Ok(store) => webhooks = webhooks.with_dead_letters(Arc::new(store)),
+                Err(e) => log::warn!("Failed to open webhook dead letter file '{}', keeping dead letters in memory: {}", path, e),
+            }
+        }
+        let webhooks = Arc::new(webhooks);
+        let mut quota_warner = QuotaWarner::new().with_monitoring(monitoring.clone());
+        if let Some(ref url) = providers.quota_webhook_url {
+            quota_warner = quota_warner.with_sink(Arc::new(WebhookSink::new(url).with_delivery(webhooks.clone())));
+        }
+        let quota_warner = Arc::new(quota_warner);
+        search_budget.attach_quota_warner(quota_warner.clone());
+        let mut request_processor =
+            RequestProcessor::new(context_manager.clone(), context_selector.clone())
+                .with_config(processor_config)
+                .with_search_budget(search_budget.clone())
+                .with_maintenance(maintenance.clone())
+                .with_tokenizer(tokenizer.clone())
+                .with_quota_warner(quota_warner.clone());
+        if let Some(ref replica) = replica {
+            request_processor = request_processor.with_replica(replica.clone());
+        }
+        if let Some(ref store) = keyword_store {
+            request_processor = request_processor.with_keyword_store(store.clone());
+        }
+        if let Ok(path) = std::env::var("PENLAI_FEEDBACK_LOG") {
+            match FeedbackStore::open(&path, 100_000) {
+                Ok(store) => request_processor = request_processor.with_feedback_store(Arc::new(store)),
+                Err(e) => log::warn!("Failed to open feedback log '{}', keeping feedback in memory: {}", path, e),
+            }
+        }
+        let request_processor = Arc::new(request_processor);
+        let ingestion = Arc::new(
+            IngestionService::new(context_manager.clone())
+                .with_tokenizer(tokenizer)
+                .with_maintenance(maintenance.clone())
+                .with_scheduler(outbound.clone()),
+        );
+        let mut reports = ReportGenerator::new(monitoring.clone())
+            .with_context_manager(context_manager.clone())
+            .with_maintenance(maintenance.clone());
+        if let Some(ref url) = providers.report_webhook_url {
+            reports = reports.with_sink(Arc::new(WebhookSink::new(url).with_delivery(webhooks.clone())));
+        }
+        let mut expiry_notifier =
+            ExpiryNotifier::new(context_manager.clone(), ExpiryNoticeConfig::default()).with_maintenance(maintenance.clone());
+        if let Some(ref url) = providers.expiry_webhook_url {
+            expiry_notifier = expiry_notifier.with_sink(Arc::new(WebhookSink::new(url).with_delivery(webhooks.clone())));
+        }
+        Self {
+            context_manager,
+            context_selector,
+            request_processor,
+            monitoring,
+            provider_health: Arc::new(
+                ProviderHealthChecker::new()
+                    .with_scheduler(outbound.clone())
+                    .with_budget(search_budget.clone()),
+            ),
+            search_budget,
+            outbound,
+            ingestion,
+            reports: Arc::new(reports),
+            replica,
+            replication_token,
+            expiry_notifier: Arc::new(expiry_notifier),
+            keyword_store,
+            webhooks,
+            maintenance,
+            quota_warner,
+            exporter,
+            admin_token: std::env::var("PENLAI_ADMIN_TOKEN").ok(),
+            snapshot_dir: snapshot_dir(),
+            providers: tokio::sync::RwLock::new(providers),
+            analytics_sink,
+        }
+    }
+
+    /// 启动监控数据导出任务；未配置分析数据库或已启动时返回None
+    pub async fn start_analytics_export(&self) -> Option<tokio::task::JoinHandle<()>> {
+        let (exporter, sink) = (self.exporter.as_ref()?, self.analytics_sink.clone()?);
+        exporter.start(sink).await
+    }
+
+    /// 获取当前使用的外部服务地址
+    pub async fn get_providers_config(&self) -> ProvidersConfig {
+        self.providers.read().await.clone()
+    }
+
+    /// 切换外部服务地址：AI端点重建文本生成器，Bing地址用于健康探测，Webhook地址替换对应的通知渠道
+    pub async fn update_providers_config(&self, new_config: ProvidersConfig) {
+        let mut current = self.providers.write().await;
+        if new_config.ai_base_url != current.ai_base_url {
+            if let Some(ref url) = new_config.ai_base_url {
+                // AIClient::new的错误类型不是Send，先取出客户端再等待
+                let client = AIClient::new()
+                    .map_err(|e| log::warn!("Failed to create AI client for {}, keeping current generator: {}", url, e))
+                    .ok();
+                if let Some(client) = client {
+                    let client = client.with_base_url(url).with_scheduler(self.outbound.clone());
+                    self.context_selector.set_text_generator(Arc::new(client)).await;
+                }
+            }
+        }
+        if new_config.bing_search_url != current.bing_search_url {
+            self.provider_health.set_bing_search_url(new_config.bing_search_url.clone()).await;
+        }
+        let webhook = |url: &str| Arc::new(WebhookSink::new(url).with_delivery(self.webhooks.clone()));
+        if let Some(ref url) = new_config.quota_webhook_url {
+            self.quota_warner.replace_sink(webhook(url));
+        }
+        if let Some(ref url) = new_config.report_webhook_url {
+            self.reports.replace_sink(webhook(url));
+        }
+        if let Some(ref url) = new_config.expiry_webhook_url {
+            self.expiry_notifier.replace_sink(webhook(url));
+        }
+        *current = new_config;
+    }
+
+    /// 启动前自检：校验配置、检查AI端点和搜索服务、加载分类器、预热缓存
+    pub async fn self_test(&self, options: &SelfTestOptions) -> ReadinessReport {
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let timeout = Duration::from_millis(options.timeout_ms);
+        let mut checks = Vec::new();
+
+        let processor_config = self.request_processor.get_config().await;
+        checks.push(run_check("processor_config", CheckSeverity::Critical, async {
+            validate_processor_config(&processor_config)
+        }).await);
+
+        let selector_config = self.context_selector.get_config().await;
+        checks.push(run_check("selector_config", CheckSeverity::Critical, async {
+            validate_selector_config(&selector_config)
+        }).await);
+
+        let ai_severity = if options.require_ai_endpoint { CheckSeverity::Critical } else { CheckSeverity::Warning };
+        let ai_base_url = self.get_providers_config().await.ai_base_url;
+        checks.push(run_check("ai_endpoint", ai_severity, async {
+            let mut client = AIClient::new().map_err(|e| format!("Failed to create AI client: {}", e))?;
+            if let Some(ref url) = ai_base_url {
+                client = client.with_base_url(url);
+            }
+            match tokio::time::timeout(timeout, client.ping()).await {
+                Ok(Ok(())) => Ok(Some(format!("{} ({})", client.base_url(), client.model()))),
+                Ok(Err(e)) => Err(format!("{} unreachable: {}", client.base_url(), e)),
+                Err(_) => Err(format!("{} timed out after {:?}", client.base_url(), timeout)),
+            }
+        }).await);
+
+        if options.check_search {
+            for health in self.provider_health.check_all_with_timeout(timeout).await {
+                let name = format!("{}_search", health.provider);
+                let detail = health.detail.clone().unwrap_or_default();
+                checks.push(match health.status {
+                    ProviderStatus::Configured => run_check(&name, CheckSeverity::Warning, async { Ok(None) }).await,
+                    ProviderStatus::Unconfigured => skipped(&name, &detail),
+                    status => run_check(&name, CheckSeverity::Warning, async {
+                        Err(format!("{:?}: {}", status, detail))
+                    }).await,
+                });
+            }
+            self.provider_health.report_to(&self.monitoring).await;
+        }
+
+        // 关键词文件缺失时分类器会回退到内置关键词，因此只作为警告
+        checks.push(run_check("domain_classifier", CheckSeverity::Warning, async {
+            let classifier = DomainClassifier::new().map_err(|e| format!("Failed to load keywords: {}", e))?;
+            let domain = classifier.classify_domain("pneumonia treatment with antibiotics");
+            Ok(Some(format!("sample classified as {:?}", domain)))
+        }).await);
+
+        let mut warmed_contexts = 0;
+        checks.push(run_check("cache_warmup", CheckSeverity::Warning, async {
+            warmed_contexts = self.warm_caches().await?;
+            Ok(Some(format!("{} contexts", warmed_contexts)))
+        }).await);
+
+        let ready = checks
+            .iter()
+            .all(|c| c.severity != CheckSeverity::Critical || c.status != CheckStatus::Failed);
+        let degraded = checks
+            .iter()
+            .any(|c| c.severity == CheckSeverity::Warning && c.status == CheckStatus::Failed);
+
+        ReadinessReport {
+            ready,
+            degraded,
+            checks,
+            warmed_contexts,
+            started_at,
+            duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+            maintenance: None,
+        }
+    }
+
+    /// 为现有上下文预计算评分特征和向量
+    async fn warm_caches(&self) -> Result<usize, String> {
+        let scoring_cache = self.context_selector.get_scoring_cache();
+        let embedder = self.context_selector.get_embedder().await;
+        let tokenizer = self.context_selector.get_tokenizer();
+        let contexts = self.context_manager.get_all_contexts().await;
+        for context in &contexts {
+            scoring_cache.get_features(context, tokenizer.as_ref()).await;
+            scoring_cache
+                .get_embedding(context, embedder.as_ref())
+                .await
+                .map_err(|e| format!("Failed to embed context {}: {}", context.id, e))?;
+        }
+        Ok(contexts.len())
+    }
+}
+
+/// 执行单项检查并计时
+async fn run_check<F>(name: &str, severity: CheckSeverity, check: F) -> SelfTestCheck
+where
+    F: Future<Output = Result<Option<String>, String>>,
+{
+    let start = Instant::now();
+    let outcome = check.await;
+    let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
+    let (status, detail) = match outcome {
+        Ok(detail) => (CheckStatus::Passed, detail),
+        Err(error) => (CheckStatus::Failed, Some(error)),
+    };
+    SelfTestCheck { name: name.to_string(), severity, status, duration_ms, detail }
+}
+
+fn skipped(name: &str, reason: &str) -> SelfTestCheck {
+    SelfTestCheck {
+        name: name.to_string(),
+        severity: CheckSeverity::Warning,
+        status: CheckStatus::Skipped,
+        duration_ms: 0.0,
+        detail: Some(reason.to_string()),
+    }
+}
+
+/// 校验请求处理器配置
+pub(crate) fn validate_processor_config(config: &RequestProcessorConfig) -> Result<Option<String>, String> {
+    if config.max_concurrent_requests == 0 {
+        return Err("max_concurrent_requests must be greater than 0".to_string());
+    }
+    if config.request_timeout_seconds == 0 {
+        return Err("request_timeout_seconds must be greater than 0".to_string());
+    }
+    if config.context_load_timeout_seconds > config.request_timeout_seconds {
+        return Err("context_load_timeout_seconds exceeds request_timeout_seconds".to_string());
+    }
+    if config.context_selection_timeout_seconds > config.request_timeout_seconds {
+        return Err("context_selection_timeout_seconds exceeds request_timeout_seconds".to_string());
+    }
+    if config.generation_timeout_seconds > config.request_timeout_seconds {
+        return Err("generation_timeout_seconds exceeds request_timeout_seconds".to_string());
+    }
+    if config.enable_rate_limiting && config.max_requests_per_minute == 0 {
+        return Err("max_requests_per_minute must be greater than 0 when rate limiting is enabled".to_string());
+    }
+    config.stage_budget_weights.validate()?;
+    config.pricing.validate()?;
+    Ok(None)
+}
+
+/// 校验外部服务地址：设置的地址必须是http(s) URL
+pub(crate) fn validate_providers_config(config: &ProvidersConfig) -> Result<Option<String>, String> {
+    let urls = [
+        ("ai_base_url", &config.ai_base_url),
+        ("bing_search_url", &config.bing_search_url),
+        ("quota_webhook_url", &config.quota_webhook_url),
+        ("report_webhook_url", &config.report_webhook_url),
+        ("expiry_webhook_url", &config.expiry_webhook_url),
+    ];
+    for (key, url) in urls {
+        let Some(url) = url else { continue };
+        match url::Url::parse(url) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+            Ok(parsed) => return Err(format!("{} has unsupported scheme '{}'", key, parsed.scheme())),
+            Err(e) => return Err(format!("{} '{}' is not a valid URL: {}", key, url, e)),
+        }
+    }
+    Ok(None)
+}
+
+/// 校验上下文选择器配置
+pub(crate) fn validate_selector_config(config: &ContextSelectorConfig) -> Result<Option<String>, String> {
+    if config.max_contexts_to_return == 0 {
+        return Err("max_contexts_to_return must be greater than 0".to_string());
+    }
+    if !(0.0..=1.0).contains(&config.min_relevance_score) {
+        return Err(format!("min_relevance_score {} is outside 0-1", config.min_relevance_score));
+    }
+    if let Some((domain, threshold)) = config
+        .domain_relevance_thresholds
+        .iter()
+        .find(|(_, threshold)| !(0.0..=1.0).contains(*threshold))
+    {
+        return Err(format!("relevance threshold {} for domain '{}' is outside 0-1", threshold, domain));
+    }
+    if config.multi_query.variations == 0 || config.multi_query.rrf_k <= 0.0 {
+        return Err("multi_query requires at least one variation and a positive rrf_k".to_string());
+    }
+    if !(0.0..=1.0).contains(&config.hyde.min_similarity) {
+        return Err(format!("hyde.min_similarity {} is outside 0-1", config.hyde.min_similarity));
+    }
+    config.post_filters.validate()?;
+    for (domain, filters) in &config.domain_post_filters {
+        filters.validate().map_err(|reason| format!("post filters for domain '{}': {}", domain, reason))?;
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_self_test_report() {
+        let penlai = Penlai::new(10, 3600);
+        penlai
+            .context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia".to_string(), 5)
+            .await
+            .unwrap();
+
+        // 无效配置导致拒绝启动
+        let mut config = penlai.request_processor.get_config().await;
+        config.max_concurrent_requests = 0;
+        penlai.request_processor.update_config(config).await;
+
+        let options = SelfTestOptions {
+            require_ai_endpoint: false,
+            check_search: false,
+            timeout_ms: 200,
+        };
+        let report = penlai.self_test(&options).await;
+        assert!(!report.ready);
+        assert_eq!(report.warmed_contexts, 1);
+        assert_eq!(report.failures()[0].name, "processor_config");
+        let ai = report.checks.iter().find(|c| c.name == "ai_endpoint").unwrap();
+        assert_eq!(ai.severity, CheckSeverity::Warning);
+        assert!(report.to_string().contains("NOT READY"));
+
+        // 自检使用自己的探测超时，不改写健康检查配置
+        let configured = penlai.provider_health.get_config().await.timeout_ms;
+        penlai.self_test(&SelfTestOptions { check_search: true, ..options }).await;
+        assert_eq!(penlai.provider_health.get_config().await.timeout_ms, configured);
+    }
+}
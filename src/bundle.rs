@@ -0,0 +1,465 @@
+//! 上下文包 - 将选定领域的上下文连同领域关键词、安全策略模板和上下文向量打包为签名文件，
+//! 供无法联网（没有网络搜索）的隔离环境校验后安装；包用Ed25519私钥签名，安装实例只需配置公钥
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use crate::app::Penlai;
+use crate::context::llm_context::LLMContext;
+use crate::domain::domain_classifier::Domain;
+use crate::domain::keyword_store::{validate_keyword, WeightedKeyword};
+use crate::processing::safety_policy::DomainSafetyPolicy;
+
+/// 当前的包格式版本
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// 签名字段的算法前缀
+const SIGNATURE_PREFIX: &str = "ed25519=";
+
+/// 打包实例的签名私钥（PENLAI_BUNDLE_SIGNING_KEY，32字节种子的十六进制），只配置在打包的实例上
+pub fn bundle_signing_key() -> Result<Option<SigningKey>, BundleError> {
+    match std::env::var("PENLAI_BUNDLE_SIGNING_KEY") {
+        Ok(key) if !key.is_empty() => parse_signing_key(&key).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// 安装实例的验签公钥（PENLAI_BUNDLE_PUBLIC_KEY，32字节的十六进制）
+pub fn bundle_verifying_key() -> Result<Option<VerifyingKey>, BundleError> {
+    match std::env::var("PENLAI_BUNDLE_PUBLIC_KEY") {
+        Ok(key) if !key.is_empty() => parse_verifying_key(&key).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// 生成新的签名密钥对
+pub fn generate_signing_key() -> SigningKey {
+    SigningKey::generate(&mut rand::rngs::OsRng)
+}
+
+/// 解析十六进制的签名私钥
+pub fn parse_signing_key(key: &str) -> Result<SigningKey, BundleError> {
+    Ok(SigningKey::from_bytes(&decode_key(key, "signing")?))
+}
+
+/// 解析十六进制的验签公钥
+pub fn parse_verifying_key(key: &str) -> Result<VerifyingKey, BundleError> {
+    VerifyingKey::from_bytes(&decode_key(key, "public")?).map_err(|e| BundleError::InvalidKey(e.to_string()))
+}
+
+fn decode_key(key: &str, kind: &str) -> Result<[u8; 32], BundleError> {
+    hex::decode(key.trim())
+        .ok()
+        .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+        .ok_or_else(|| BundleError::InvalidKey(format!("{} key must be 32 hex-encoded bytes", kind)))
+}
+
+/// 打包范围
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct BundleSpec {
+    pub domains: Vec<String>,           // 打包这些领域的全部上下文、关键词和安全策略
+    #[serde(default)]
+    pub context_ids: Vec<Uuid>,         // 额外打包的上下文
+    #[serde(default)]
+    pub include_embeddings: bool,       // 附带当前嵌入模型计算的上下文向量
+    pub description: Option<String>,
+}
+
+/// 上下文包内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextBundle {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    pub description: Option<String>,
+    pub domains: Vec<String>,
+    pub contexts: Vec<LLMContext>,
+    pub keywords: BTreeMap<String, Vec<WeightedKeyword>>,
+    pub safety_policies: BTreeMap<String, DomainSafetyPolicy>,
+    pub embedding_model: Option<String>,            // 上下文向量使用的嵌入模型
+    pub embeddings: BTreeMap<Uuid, Vec<f32>>,
+}
+
+/// 包清单（不含上下文内容）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct BundleManifest {
+    pub format_version: u32,
+    pub created_at: DateTime<Utc>,
+    pub description: Option<String>,
+    pub domains: Vec<String>,
+    pub contexts: usize,
+    pub keywords: usize,
+    pub safety_policies: usize,
+    pub embedding_model: Option<String>,
+    pub embeddings: usize,
+}
+
+/// 签名的包文件：包内容的JSON原文和它的Ed25519签名（`ed25519=<hex>`）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SignedBundle {
+    pub payload: String,
+    pub signature: String,
+}
+
+/// 安装选项
+#[derive(Debug, Clone, Copy, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BundleInstallOptions {
+    /// 用包中的安全策略替换本实例已有的不同策略（默认保留本实例的策略）
+    #[serde(default)]
+    pub replace_safety_policies: bool,
+}
+
+/// 安装结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BundleInstallReport {
+    pub manifest: BundleManifest,
+    pub contexts_installed: usize,
+    pub keywords_installed: usize,
+    pub keywords_skipped: usize,        // 关键词存储不可用或领域未知
+    pub safety_policies_installed: usize,
+    pub safety_policies_replaced: Vec<String>,  // 被包中策略覆盖的本实例策略所属领域
+    pub safety_policies_kept: Vec<String>,      // 与包中策略不同、因未选择替换而保留的领域
+    pub embeddings_installed: usize,
+    pub embeddings_skipped: usize,      // 嵌入模型与本实例不同，查询时重新计算
+}
+
+/// 包校验错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleError {
+    InvalidSignature,
+    InvalidKey(String),
+    UnsupportedVersion(u32),
+    Malformed(String),
+    Io(String),
+}
+
+impl std::fmt::Display for BundleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BundleError::InvalidSignature => write!(f, "Bundle signature does not match; it was modified or signed with another key"),
+            BundleError::InvalidKey(reason) => write!(f, "Invalid bundle key: {}", reason),
+            BundleError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported bundle format version {} (this instance reads version {})", version, BUNDLE_FORMAT_VERSION)
+            }
+            BundleError::Malformed(reason) => write!(f, "Malformed bundle: {}", reason),
+            BundleError::Io(reason) => write!(f, "Bundle file error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+impl ContextBundle {
+    /// 包清单
+    pub fn manifest(&self) -> BundleManifest {
+        BundleManifest {
+            format_version: self.format_version,
+            created_at: self.created_at,
+            description: self.description.clone(),
+            domains: self.domains.clone(),
+            contexts: self.contexts.len(),
+            keywords: self.keywords.values().map(Vec::len).sum(),
+            safety_policies: self.safety_policies.len(),
+            embedding_model: self.embedding_model.clone(),
+            embeddings: self.embeddings.len(),
+        }
+    }
+
+    /// 用私钥签名
+    pub fn sign(&self, key: &SigningKey) -> Result<SignedBundle, BundleError> {
+        let payload = serde_json::to_string(self).map_err(|e| BundleError::Malformed(e.to_string()))?;
+        Ok(SignedBundle {
+            signature: format!("{}{}", SIGNATURE_PREFIX, hex::encode(key.sign(payload.as_bytes()).to_bytes())),
+            payload,
+        })
+    }
+}
+
+impl SignedBundle {
+    /// 读取包文件
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, BundleError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path).map_err(|e| BundleError::Io(format!("{}: {}", path.display(), e)))?;
+        serde_json::from_str(&text).map_err(|e| BundleError::Malformed(e.to_string()))
+    }
+
+    /// 写入包文件（先写临时文件再改名）
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), BundleError> {
+        let path = path.as_ref();
+        let text = serde_json::to_string_pretty(self).map_err(|e| BundleError::Malformed(e.to_string()))?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, text).map_err(|e| BundleError::Io(format!("{}: {}", tmp.display(), e)))?;
+        std::fs::rename(&tmp, path).map_err(|e| BundleError::Io(format!("{}: {}", path.display(), e)))
+    }
+
+    /// 用公钥校验签名和格式版本，返回包内容
+    pub fn verify(&self, key: &VerifyingKey) -> Result<ContextBundle, BundleError> {
+        let signature = self
+            .signature
+            .strip_prefix(SIGNATURE_PREFIX)
+            .and_then(|signature| hex::decode(signature).ok())
+            .and_then(|bytes| <[u8; 64]>::try_from(bytes).ok())
+            .ok_or(BundleError::InvalidSignature)?;
+        key.verify_strict(self.payload.as_bytes(), &Signature::from_bytes(&signature))
+            .map_err(|_| BundleError::InvalidSignature)?;
+        let bundle: ContextBundle = serde_json::from_str(&self.payload).map_err(|e| BundleError::Malformed(e.to_string()))?;
+        if bundle.format_version != BUNDLE_FORMAT_VERSION {
+            return Err(BundleError::UnsupportedVersion(bundle.format_version));
+        }
+        Ok(bundle)
+    }
+}
+
+impl Penlai {
+    /// 按打包范围收集上下文、关键词、安全策略和（可选的）上下文向量
+    pub async fn create_bundle(&self, spec: &BundleSpec) -> Result<ContextBundle, Box<dyn std::error::Error + Send + Sync>> {
+        let mut contexts: BTreeMap<Uuid, LLMContext> = BTreeMap::new();
+        for domain in &spec.domains {
+            contexts.extend(self.context_manager.get_domain_contexts(domain).await.into_iter().map(|context| (context.id, context)));
+        }
+        for id in &spec.context_ids {
+            let context = self.context_manager.get_context(*id).await.ok_or_else(|| format!("Context {} not found", id))?;
+            contexts.insert(context.id, context);
+        }
+
+        let all_keywords = match self.keyword_store {
+            Some(ref store) => store.list_keywords().await,
+            None => BTreeMap::new(),
+        };
+        let keywords = all_keywords.into_iter().filter(|(domain, _)| spec.domains.contains(domain)).collect();
+        let safety_policies = self
+            .request_processor
+            .get_safety_policy()
+            .get_config()
+            .await
+            .domains
+            .into_iter()
+            .filter(|(domain, _)| spec.domains.contains(domain))
+            .collect();
+
+        let mut embedding_model = None;
+        let mut embeddings = BTreeMap::new();
+        if spec.include_embeddings {
+            let embedder = self.context_selector.get_embedder().await;
+            let cache = self.context_selector.get_scoring_cache();
+            for context in contexts.values() {
+                let embedding = cache.get_embedding(context, embedder.as_ref()).await?;
+                embeddings.insert(context.id, embedding.as_ref().clone());
+            }
+            embedding_model = Some(embedder.model_name().to_string());
+        }
+
+        Ok(ContextBundle {
+            format_version: BUNDLE_FORMAT_VERSION,
+            created_at: Utc::now(),
+            description: spec.description.clone(),
+            domains: spec.domains.clone(),
+            contexts: contexts.into_values().collect(),
+            keywords,
+            safety_policies,
+            embedding_model,
+            embeddings,
+        })
+    }
+
+    /// 安装已校验的包：写入上下文（ID相同的覆盖）、合并关键词，安装本实例没有的安全策略
+    /// （已有的不同策略只在选择替换时覆盖），嵌入模型一致时预置上下文向量；
+    /// 关键词在写入任何内容之前校验，之后写入失败时错误信息中报告已写入的部分
+    pub async fn install_bundle(&self, bundle: ContextBundle, options: BundleInstallOptions) -> Result<BundleInstallReport, Box<dyn std::error::Error + Send + Sync>> {
+        let manifest = bundle.manifest();
+        let mut keywords_skipped = 0;
+        let mut keyword_batches = Vec::new();
+        for (name, keywords) in &bundle.keywords {
+            match (self.keyword_store.as_ref(), Domain::parse(name)) {
+                (Some(store), Some(domain)) => {
+                    let entries = keywords
+                        .iter()
+                        .map(|keyword| validate_keyword(&keyword.keyword, keyword.weight))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|e| BundleError::Malformed(format!("keywords for domain '{}': {}", name, e)))?;
+                    keyword_batches.push((store, domain, entries));
+                }
+                _ => {
+                    log::warn!("Skipping {} bundled keyword(s) for domain '{}'", keywords.len(), name);
+                    keywords_skipped += keywords.len();
+                }
+            }
+        }
+
+        // 只读副本或维护期间写入上下文失败时不改动关键词和安全策略
+        let contexts_installed = self.context_manager.insert_contexts(bundle.contexts.clone()).await?;
+
+        let mut keywords_installed = 0;
+        for (store, domain, entries) in keyword_batches {
+            let count = entries.len();
+            if let Err(e) = store.add_keywords(domain.clone(), entries).await {
+                return Err(format!(
+                    "Bundle partially installed: {} context(s) and {} keyword(s) were written before storing keywords for domain '{}' failed: {}",
+                    contexts_installed, keywords_installed, domain, e
+                )
+                .into());
+            }
+            keywords_installed += count;
+        }
+
+        let safety_policy = self.request_processor.get_safety_policy();
+        let mut config = safety_policy.get_config().await;
+        let (mut safety_policies_installed, mut safety_policies_replaced, mut safety_policies_kept) = (0, Vec::new(), Vec::new());
+        for (domain, policy) in bundle.safety_policies {
+            match config.domains.get(&domain) {
+                Some(existing) if *existing == policy => {}
+                Some(_) if !options.replace_safety_policies => {
+                    log::warn!("Keeping the local safety policy for domain '{}'; the bundled policy differs", domain);
+                    safety_policies_kept.push(domain);
+                    continue;
+                }
+                Some(_) => {
+                    log::warn!("Replacing the local safety policy for domain '{}' with the bundled policy", domain);
+                    safety_policies_replaced.push(domain.clone());
+                }
+                None => {}
+            }
+            config.domains.insert(domain, policy);
+            safety_policies_installed += 1;
+        }
+        safety_policy.update_config(config).await;
+
+        let embedder = self.context_selector.get_embedder().await;
+        let mut embeddings_installed = 0;
+        let embeddings_skipped = if bundle.embedding_model.as_deref() == Some(embedder.model_name()) {
+            let cache = self.context_selector.get_scoring_cache();
+            let mut embeddings = bundle.embeddings;
+            for context in &bundle.contexts {
+                if let Some(embedding) = embeddings.remove(&context.id) {
+                    cache.insert_embedding(context, embedder.model_name(), embedding).await;
+                    embeddings_installed += 1;
+                }
+            }
+            embeddings.len()
+        } else {
+            bundle.embeddings.len()
+        };
+
+        log::info!(
+            "Installed bundle with {} context(s) for domains {:?} ({} keyword(s), {} safety policies, {} embedding(s))",
+            contexts_installed,
+            manifest.domains,
+            keywords_installed,
+            safety_policies_installed,
+            embeddings_installed
+        );
+        Ok(BundleInstallReport {
+            manifest,
+            contexts_installed,
+            keywords_installed,
+            keywords_skipped,
+            safety_policies_installed,
+            safety_policies_replaced,
+            safety_policies_kept,
+            embeddings_installed,
+            embeddings_skipped,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::domain::keyword_store::KeywordStore;
+
+    #[tokio::test]
+    async fn test_bundle_round_trips_to_air_gapped_instance() {
+        let keywords_path = |name: &str| std::env::temp_dir().join(format!("penlai-bundle-{}-{}.json", name, Uuid::new_v4()));
+        let (source_keywords, target_keywords) = (keywords_path("source"), keywords_path("target"));
+        std::fs::write(&source_keywords, r#"{"medical": ["pneumonia", {"keyword": "chest x ray", "weight": 2.0}], "legal": ["contract"]}"#).unwrap();
+        std::fs::write(&target_keywords, r#"{"medical": ["fever"]}"#).unwrap();
+
+        let mut source = Penlai::new(10, 3600);
+        source.keyword_store = Some(Arc::new(KeywordStore::load(&source_keywords).unwrap()));
+        let medical = source
+            .context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia is treated with antibiotics".to_string(), 8)
+            .await
+            .unwrap();
+        let legal = source
+            .context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "legal".to_string(), "Contracts need two signatures".to_string(), 5)
+            .await
+            .unwrap();
+        source
+            .context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "technical".to_string(), "Rust futures are lazy".to_string(), 5)
+            .await
+            .unwrap();
+
+        // 打包医疗领域和单独指定的法律上下文
+        let spec = BundleSpec {
+            domains: vec!["medical".to_string()],
+            context_ids: vec![legal.id],
+            include_embeddings: true,
+            description: Some("Clinic offline pack".to_string()),
+        };
+        // 打包实例上修改过医疗领域的免责声明
+        let source_policy = source.request_processor.get_safety_policy();
+        let mut source_config = source_policy.get_config().await;
+        source_config.domains.get_mut("medical").unwrap().disclaimer = Some("Clinic-approved disclaimer".to_string());
+        source_policy.update_config(source_config).await;
+
+        let signing_key = generate_signing_key();
+        let public_key = parse_verifying_key(&hex::encode(signing_key.verifying_key().to_bytes())).unwrap();
+        let signed = source.create_bundle(&spec).await.unwrap().sign(&signing_key).unwrap();
+        let path = std::env::temp_dir().join(format!("penlai-bundle-{}.json", Uuid::new_v4()));
+        signed.write(&path).unwrap();
+
+        // 篡改或换用密钥时拒绝安装；安装实例只持有公钥
+        let read = SignedBundle::read(&path).unwrap();
+        assert_eq!(read.verify(&generate_signing_key().verifying_key()).unwrap_err(), BundleError::InvalidSignature);
+        let tampered = SignedBundle { payload: read.payload.replace("antibiotics", "homeopathy"), ..read.clone() };
+        assert_eq!(tampered.verify(&public_key).unwrap_err(), BundleError::InvalidSignature);
+        assert!(matches!(parse_verifying_key("abcd"), Err(BundleError::InvalidKey(_))));
+        let bundle = read.verify(&public_key).unwrap();
+        let manifest = bundle.manifest();
+        assert_eq!((manifest.contexts, manifest.keywords, manifest.safety_policies, manifest.embeddings), (2, 2, 1, 2));
+
+        // 隔离实例上安装：上下文保留ID，关键词合并，向量直接命中缓存
+        let mut target = Penlai::new(10, 3600);
+        target.keyword_store = Some(Arc::new(KeywordStore::load(&target_keywords).unwrap()));
+
+        // 关键词不合法时什么都不写入
+        let mut invalid = bundle.clone();
+        invalid.keywords.get_mut("medical").unwrap()[0].weight = -1.0;
+        assert!(target.install_bundle(invalid, BundleInstallOptions::default()).await.is_err());
+        assert!(target.context_manager.get_context(medical.id).await.is_none());
+
+        // 默认保留本实例不同的安全策略，并在报告中列出
+        let safety_policy = target.request_processor.get_safety_policy();
+        let local_medical = safety_policy.get_config().await.domains["medical"].clone();
+        let report = target.install_bundle(bundle.clone(), BundleInstallOptions::default()).await.unwrap();
+        assert_eq!((report.contexts_installed, report.keywords_installed, report.embeddings_installed), (2, 2, 2));
+        assert_eq!((report.safety_policies_installed, report.safety_policies_kept.clone()), (0, vec!["medical".to_string()]));
+        assert_eq!(safety_policy.get_config().await.domains["medical"], local_medical);
+        assert_eq!(target.context_manager.get_context(medical.id).await.unwrap().context_data, medical.context_data);
+        assert!(target.context_manager.get_domain_contexts("technical").await.is_empty());
+        let installed_keywords = target.keyword_store.as_ref().unwrap().list_keywords().await;
+        assert_eq!(installed_keywords["medical"].len(), 3);
+
+        // 选择替换时覆盖并报告
+        let report = target.install_bundle(bundle, BundleInstallOptions { replace_safety_policies: true }).await.unwrap();
+        assert_eq!(report.safety_policies_replaced, vec!["medical".to_string()]);
+        assert_eq!(safety_policy.get_config().await.domains["medical"].disclaimer.as_deref(), Some("Clinic-approved disclaimer"));
+
+        let cache = target.context_selector.get_scoring_cache();
+        let embedder = target.context_selector.get_embedder().await;
+        cache.get_embedding(&medical, embedder.as_ref()).await.unwrap();
+        assert_eq!(cache.get_stats().await.embedding_misses, 0);
+
+        for file in [path, source_keywords, target_keywords] {
+            std::fs::remove_file(file).unwrap();
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::collections::hash_map::DefaultHasher;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{Mutex, RwLock};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 布隆过滤器配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloomFilterConfig {
+    pub expected_items: usize,          // 预期元素数量
+    pub false_positive_rate: f64,       // 目标误判率（0-1）
+    pub rebuild_interval_seconds: u64,  // 定期重建间隔（秒），用于清除已删除元素
+}
+
+impl Default for BloomFilterConfig {
+    fn default() -> Self {
+        Self {
+            expected_items: 100_000,
+            false_positive_rate: 0.01,
+            rebuild_interval_seconds: 300,
+        }
+    }
+}
+
+/// 布隆过滤器 - 判断元素"一定不存在"或"可能存在"
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    item_count: usize,
+}
+
+impl BloomFilter {
+    /// 按预期元素数和目标误判率计算位数组大小和哈希函数个数
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let n = expected_items.max(1) as f64;
+        let p = false_positive_rate.clamp(1e-9, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = ((-n * p.ln()) / (ln2 * ln2)).ceil().max(64.0) as u64;
+        let num_hashes = ((num_bits as f64 / n) * ln2).round().clamp(1.0, 32.0) as u32;
+
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            item_count: 0,
+        }
+    }
+
+    /// 从配置创建
+    pub fn from_config(config: &BloomFilterConfig) -> Self {
+        Self::new(config.expected_items, config.false_positive_rate)
+    }
+
+    /// 元素的两个基础哈希（与过滤器大小无关，可在不同大小的过滤器之间重放）
+    fn item_hashes<T: Hash + ?Sized>(item: &T) -> (u64, u64) {
+        let mut first = DefaultHasher::new();
+        0u8.hash(&mut first);
+        item.hash(&mut first);
+
+        let mut second = DefaultHasher::new();
+        1u8.hash(&mut second);
+        item.hash(&mut second);
+        (first.finish(), second.finish() | 1)
+    }
+
+    /// 双重哈希生成第i个位下标
+    fn bit_indexes((h1, h2): (u64, u64), num_bits: u64, num_hashes: u32) -> impl Iterator<Item = u64> {
+        (0..num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+
+    /// 插入元素
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        self.insert_hashes(Self::item_hashes(item));
+    }
+
+    fn insert_hashes(&mut self, hashes: (u64, u64)) {
+        for index in Self::bit_indexes(hashes, self.num_bits, self.num_hashes) {
+            self.bits[(index / 64) as usize] |= 1 << (index % 64);
+        }
+        self.item_count += 1;
+    }
+
+    /// 元素可能存在时返回true；返回false时元素一定不存在
+    pub fn might_contain<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        Self::bit_indexes(Self::item_hashes(item), self.num_bits, self.num_hashes)
+            .all(|index| self.bits[(index / 64) as usize] & (1 << (index % 64)) != 0)
+    }
+
+    /// 清空过滤器
+    pub fn clear(&mut self) {
+        self.bits.iter_mut().for_each(|word| *word = 0);
+        self.item_count = 0;
+    }
+
+    /// 已插入元素数量（含重复插入）
+    pub fn len(&self) -> usize {
+        self.item_count
+    }
+
+    /// 是否为空
+    pub fn is_empty(&self) -> bool {
+        self.item_count == 0
+    }
+
+    /// 位数组大小
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// 哈希函数个数
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    /// 按当前元素数估算误判率
+    pub fn estimated_false_positive_rate(&self) -> f64 {
+        let k = self.num_hashes as f64;
+        let exponent = -k * self.item_count as f64 / self.num_bits as f64;
+        (1.0 - exponent.exp()).powf(k)
+    }
+}
+
+/// 布隆过滤器守卫统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BloomGuardStats {
+    pub lookups: u64,                       // 总查询次数
+    pub skipped: u64,                       // 判定一定不存在而跳过存储查询的次数
+    pub false_positives: u64,               // 判定可能存在但实际不存在的次数
+    pub item_count: usize,                  // 当前过滤器元素数
+    pub num_bits: u64,
+    pub num_hashes: u32,
+    pub estimated_false_positive_rate: f64, // 估算误判率
+    pub last_rebuilt_at: Option<DateTime<Utc>>,
+}
+
+/// 布隆过滤器守卫 - 在存储查询前过滤一定不存在的键，并支持按当前键集合重建
+pub struct BloomFilterGuard {
+    config: RwLock<BloomFilterConfig>,
+    filter: RwLock<BloomFilter>,
+    rebuild_lock: Mutex<()>,                            // 同一时间只进行一次重建
+    inserted_during_rebuild: std::sync::Mutex<Option<Vec<(u64, u64)>>>, // 重建期间插入的键，替换前重放到新过滤器
+    last_rebuilt_at: RwLock<Option<DateTime<Utc>>>,
+    lookups: AtomicU64,
+    skipped: AtomicU64,
+    false_positives: AtomicU64,
+}
+
+impl Default for BloomFilterGuard {
+    fn default() -> Self {
+        Self::new(BloomFilterConfig::default())
+    }
+}
+
+impl BloomFilterGuard {
+    /// 按配置创建守卫
+    pub fn new(config: BloomFilterConfig) -> Self {
+        Self {
+            filter: RwLock::new(BloomFilter::from_config(&config)),
+            rebuild_lock: Mutex::new(()),
+            inserted_during_rebuild: std::sync::Mutex::new(None),
+            config: RwLock::new(config),
+            last_rebuilt_at: RwLock::new(None),
+            lookups: AtomicU64::new(0),
+            skipped: AtomicU64::new(0),
+            false_positives: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录新键
+    pub async fn insert<T: Hash + ?Sized>(&self, key: &T) {
+        let hashes = BloomFilter::item_hashes(key);
+        let mut filter = self.filter.write().await;
+        filter.insert_hashes(hashes);
+        // 持有过滤器写锁时记录，重建替换过滤器前一定能看到这次插入
+        if let Some(ref mut inserted) = *self.inserted_during_rebuild.lock().unwrap_or_else(|e| e.into_inner()) {
+            inserted.push(hashes);
+        }
+    }
+
+    /// 检查键是否可能存在，返回false时调用方可跳过存储查询
+    pub async fn might_contain<T: Hash + ?Sized>(&self, key: &T) -> bool {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        let present = self.filter.read().await.might_contain(key);
+        if !present {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+        }
+        present
+    }
+
+    /// 记录一次误判（过滤器判定可能存在，但存储中没有）
+    pub fn record_false_positive(&self) {
+        self.false_positives.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 用当前全部键重建过滤器，容量取配置值和实际键数的较大者
+    pub async fn rebuild<T, I>(&self, keys: I)
+    where
+        T: Hash,
+        I: IntoIterator<Item = T>,
+    {
+        self.rebuild_from(async { keys }).await;
+    }
+
+    /// 用snapshot读取的键重建过滤器；从开始读取到替换过滤器之间插入的键会重放到新过滤器，
+    /// 因此只要调用方在键写入存储之前（或写入的同时）调用`insert`，重建就不会产生漏判
+    pub async fn rebuild_from<T, I, F>(&self, snapshot: F)
+    where
+        T: Hash,
+        I: IntoIterator<Item = T>,
+        F: Future<Output = I>,
+    {
+        let _rebuilding = self.rebuild_lock.lock().await;
+        *self.inserted_during_rebuild.lock().unwrap_or_else(|e| e.into_inner()) = Some(Vec::new());
+        let keys: Vec<T> = snapshot.await.into_iter().collect();
+        let config = self.config.read().await.clone();
+        let mut rebuilt = BloomFilter::new(config.expected_items.max(keys.len()), config.false_positive_rate);
+        for key in &keys {
+            rebuilt.insert(key);
+        }
+
+        let mut filter = self.filter.write().await;
+        let inserted = self.inserted_during_rebuild.lock().unwrap_or_else(|e| e.into_inner()).take().unwrap_or_default();
+        for hashes in inserted {
+            rebuilt.insert_hashes(hashes);
+        }
+        *filter = rebuilt;
+        drop(filter);
+        *self.last_rebuilt_at.write().await = Some(Utc::now());
+    }
+
+    /// 获取统计
+    pub async fn get_stats(&self) -> BloomGuardStats {
+        let filter = self.filter.read().await;
+        BloomGuardStats {
+            lookups: self.lookups.load(Ordering::Relaxed),
+            skipped: self.skipped.load(Ordering::Relaxed),
+            false_positives: self.false_positives.load(Ordering::Relaxed),
+            item_count: filter.len(),
+            num_bits: filter.num_bits(),
+            num_hashes: filter.num_hashes(),
+            estimated_false_positive_rate: filter.estimated_false_positive_rate(),
+            last_rebuilt_at: *self.last_rebuilt_at.read().await,
+        }
+    }
+
+    /// 更新配置（下次重建时生效）
+    pub async fn update_config(&self, new_config: BloomFilterConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> BloomFilterConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_bloom_filter_guard() {
+        let guard = BloomFilterGuard::new(BloomFilterConfig {
+            expected_items: 1000,
+            false_positive_rate: 0.01,
+            rebuild_interval_seconds: 60,
+        });
+
+        let present: Vec<Uuid> = (0..1000).map(|_| Uuid::new_v4()).collect();
+        for id in &present {
+            guard.insert(id).await;
+        }
+        // 不存在漏判
+        for id in &present {
+            assert!(guard.might_contain(id).await);
+        }
+
+        // 误判率接近目标值
+        let mut false_positives = 0;
+        for _ in 0..10_000 {
+            if guard.might_contain(&Uuid::new_v4()).await {
+                false_positives += 1;
+            }
+        }
+        assert!(false_positives < 300, "false positives: {}", false_positives);
+
+        // 重建后删除的键被清除
+        guard.rebuild(present[..10].iter().copied()).await;
+        assert!(guard.might_contain(&present[0]).await);
+        let stats = guard.get_stats().await;
+        assert_eq!(stats.item_count, 10);
+        assert!(stats.skipped >= 9_700);
+        assert!(stats.last_rebuilt_at.is_some());
+
+        // 读取快照之后、替换之前插入的键不会被漏判
+        let late = Uuid::new_v4();
+        guard
+            .rebuild_from(async {
+                let keys = present[..10].to_vec();
+                guard.insert(&late).await;
+                keys
+            })
+            .await;
+        assert!(guard.might_contain(&late).await);
+        assert_eq!(guard.get_stats().await.item_count, 11);
+    }
+}
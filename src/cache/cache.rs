@@ -1,4 +1,5 @@
 use moka::future::Cache;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use crate::context::llm_context::LLMContext as Context;
 
@@ -7,8 +8,58 @@ use crate::context::llm_context::LLMContext as Context;
 pub enum CacheKey {
     Domain(String),           // 按领域缓存
     UserId(String),           // 按用户ID缓存
+    SessionId(String),        // 按会话ID缓存
     Query(String),            // 按查询缓存
     ContextId(uuid::Uuid),    // 按上下文ID缓存
+    Composite(Vec<String>),   // 多维组合键，按层级排列（如 租户/领域/查询）；应通过`composite`创建，各段已转义
+}
+
+/// 模式中匹配任意段的通配符
+pub const KEY_SEGMENT_WILDCARD: &str = "*";
+
+/// 转义组合键的一段：以`*`或`\`开头的段加`\`前缀，使取值为`*`的段（如租户名）不会被当作通配符
+fn escape_segment(segment: &str) -> String {
+    if segment.starts_with('*') || segment.starts_with('\\') {
+        format!("\\{}", segment)
+    } else {
+        segment.to_string()
+    }
+}
+
+impl CacheKey {
+    /// 创建组合键（各段转义后保存）
+    pub fn composite(segments: &[&str]) -> Self {
+        CacheKey::Composite(segments.iter().map(|segment| escape_segment(segment)).collect())
+    }
+
+    /// 创建 租户+领域+查询 组合键
+    pub fn scoped(tenant: &str, domain: &str, query: &str) -> Self {
+        Self::composite(&[tenant, domain, query])
+    }
+
+    /// 检查组合键是否匹配模式：模式逐段比较，`*`匹配任意段，其他段按字面比较，模式短于键时按前缀匹配
+    pub fn matches_pattern<S: AsRef<str>>(&self, pattern: &[S]) -> bool {
+        match self {
+            CacheKey::Composite(segments) => {
+                pattern.len() <= segments.len()
+                    && pattern.iter().zip(segments).all(|(p, segment)| {
+                        p.as_ref() == KEY_SEGMENT_WILDCARD || escape_segment(p.as_ref()) == *segment
+                    })
+            }
+            _ => false,
+        }
+    }
+
+    /// 检查组合键是否以给定的段开头（逐段按字面比较，`*`不是通配符）
+    pub fn matches_prefix<S: AsRef<str>>(&self, prefix: &[S]) -> bool {
+        match self {
+            CacheKey::Composite(segments) => {
+                prefix.len() <= segments.len()
+                    && prefix.iter().zip(segments).all(|(p, segment)| escape_segment(p.as_ref()) == *segment)
+            }
+            _ => false,
+        }
+    }
 }
 
 /// 缓存策略枚举
@@ -30,6 +81,16 @@ pub struct CacheManager {
     
     /// 缓存策略
     strategy: CacheStrategy,
+
+    /// 命中和未命中计数
+    hit_count: AtomicU64,
+    miss_count: AtomicU64,
+}
+
+impl Default for CacheManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CacheManager {
@@ -42,16 +103,25 @@ impl CacheManager {
             l1_cache: Cache::builder()
                 .max_capacity(l1_max_capacity)
                 .time_to_live(l1_ttl)
+                .support_invalidation_closures()
                 .build(),
             l1_max_capacity,
             l1_ttl,
             strategy: CacheStrategy::Ttl,
+            hit_count: AtomicU64::new(0),
+            miss_count: AtomicU64::new(0),
         }
     }
 
     /// 获取缓存的上下文
     pub async fn get_context(&self, key: &CacheKey) -> Option<Vec<Context>> {
-        self.l1_cache.get(key).await
+        let cached = self.l1_cache.get(key).await;
+        if cached.is_some() {
+            self.hit_count.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.miss_count.fetch_add(1, Ordering::Relaxed);
+        }
+        cached
     }
 
     /// 存储上下文到缓存
@@ -64,6 +134,27 @@ impl CacheManager {
         self.l1_cache.invalidate(key).await;
     }
 
+    /// 使前缀匹配的组合键失效，如 `invalidate_prefix(&[tenant])` 清除租户的全部缓存；
+    /// 各段按字面比较，空前缀（会清除全部组合键）返回错误
+    pub fn invalidate_prefix(&self, prefix: &[&str]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if prefix.is_empty() {
+            return Err("Cache key prefix must not be empty".into());
+        }
+        let prefix: Vec<String> = prefix.iter().map(|p| p.to_string()).collect();
+        self.l1_cache.invalidate_entries_if(move |key, _| key.matches_prefix(&prefix))?;
+        Ok(())
+    }
+
+    /// 使匹配模式的组合键失效，如 `invalidate_pattern(&["*", "medical"])` 清除所有租户的医疗领域缓存；空模式返回错误
+    pub fn invalidate_pattern(&self, pattern: &[&str]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if pattern.is_empty() {
+            return Err("Cache key pattern must not be empty".into());
+        }
+        let pattern: Vec<String> = pattern.iter().map(|p| p.to_string()).collect();
+        self.l1_cache.invalidate_entries_if(move |key, _| key.matches_pattern(&pattern))?;
+        Ok(())
+    }
+
     /// 清空所有缓存
     pub async fn clear_all(&self) {
         self.l1_cache.invalidate_all();
@@ -76,12 +167,10 @@ impl CacheManager {
 
     /// 获取缓存统计信息
     pub async fn get_stats(&self) -> CacheStats {
+        self.l1_cache.run_pending_tasks().await;
         let entry_count = self.l1_cache.entry_count();
-        
-        // Moka cache doesn't expose hit/miss count directly in the async version
-        // We'll return placeholder values for now
-        let hit_count = 0; // Placeholder - moka doesn't expose hit count directly
-        let miss_count = 0; // Placeholder - moka doesn't expose miss count directly
+        let hit_count = self.hit_count.load(Ordering::Relaxed);
+        let miss_count = self.miss_count.load(Ordering::Relaxed);
         
         let hit_rate = if hit_count + miss_count > 0 {
             hit_count as f64 / (hit_count + miss_count) as f64
@@ -131,6 +220,7 @@ impl std::fmt::Display for CacheStats {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::schema_migration::CURRENT_SCHEMA_VERSION;
     use std::collections::HashMap;
 
     #[tokio::test]
@@ -153,6 +243,9 @@ mod tests {
                 version: 1,
                 tags: vec!["test".to_string(), "medical".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             }
         ];
 
@@ -194,6 +287,9 @@ mod tests {
                 version: 1,
                 tags: vec!["test".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             }
         ];
 
@@ -210,6 +306,50 @@ mod tests {
         println!("{}", stats);
         
         // 验证统计信息
-        assert!(stats.entry_count >= 0);
+        assert!(stats.hit_rate >= 0.0 && stats.hit_rate <= 1.0);
+        assert_eq!(stats.hit_count, 1);
+        assert_eq!(stats.miss_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_prefix_invalidation() {
+        let cache_manager = CacheManager::new();
+        let keys = [
+            CacheKey::scoped("tenant_a", "medical", "flu"),
+            CacheKey::scoped("tenant_a", "legal", "contract"),
+            CacheKey::scoped("tenant_b", "medical", "flu"),
+            CacheKey::Domain("medical".to_string()),
+        ];
+        for key in &keys {
+            cache_manager.put_context(key.clone(), Vec::new()).await;
+        }
+
+        assert!(keys[0].matches_pattern(&["tenant_a"]));
+        assert!(keys[2].matches_pattern(&["*", "medical"]));
+        assert!(!keys[1].matches_pattern(&["*", "medical"]));
+        assert!(!keys[3].matches_pattern(&["*"]));
+
+        // 清除所有租户的医疗领域缓存
+        cache_manager.invalidate_pattern(&["*", "medical"]).unwrap();
+        assert!(cache_manager.get_context(&keys[0]).await.is_none());
+        assert!(cache_manager.get_context(&keys[1]).await.is_some());
+        assert!(cache_manager.get_context(&keys[2]).await.is_none());
+
+        // 租户下线
+        cache_manager.invalidate_prefix(&["tenant_a"]).unwrap();
+        assert!(cache_manager.get_context(&keys[1]).await.is_none());
+        assert!(cache_manager.get_context(&keys[3]).await.is_some());
+
+        // 名为"*"的租户按字面匹配，不会清除其他租户；空前缀被拒绝
+        let star = CacheKey::scoped("*", "legal", "contract");
+        let other = CacheKey::scoped("tenant_c", "legal", "contract");
+        cache_manager.put_context(star.clone(), Vec::new()).await;
+        cache_manager.put_context(other.clone(), Vec::new()).await;
+        assert!(cache_manager.invalidate_prefix(&[]).is_err());
+        assert!(cache_manager.invalidate_pattern(&[]).is_err());
+        cache_manager.invalidate_prefix(&["*"]).unwrap();
+        assert!(cache_manager.get_context(&star).await.is_none());
+        assert!(cache_manager.get_context(&other).await.is_some());
+        assert!(!CacheKey::scoped("\\*", "legal", "q").matches_prefix(&["*"]));
     }
 }
\ No newline at end of file
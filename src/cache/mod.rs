@@ -1 +1,3 @@
-pub mod cache;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod cache;
+pub mod bloom;
\ No newline at end of file
@@ -0,0 +1,516 @@
+//! 配置热加载 - 收到SIGHUP或检测到`penlai.toml`变化时重新读取配置，经校验后通过各组件的更新接口应用到运行中的服务，
+//! 变更内容逐项记录到审计日志
+//!
+//! 文件中每个表对应一个组件（processor、selector、token_budget、search_budget、outbound、provider_health、
+//! ingestion、reports、expiry、quota_warnings），providers表切换AI端点、Bing搜索和通知Webhook的地址；
+//! 只需写出要修改的键，未写出的键保持当前值：
+//!
+//! ```toml
+//! [processor]
+//! max_requests_per_minute = 500
+//!
+//! [selector.multi_query]
+//! variations = 4
+//! ```
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::SystemTime;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::app::{validate_processor_config, validate_providers_config, validate_selector_config, Penlai};
+use crate::context::audit::{AuditAction, AuditLog};
+use crate::context::expiry::ExpiryNoticeConfig;
+use crate::context::ingestion::IngestionConfig;
+use crate::monitoring::quota::QuotaWarningConfig;
+use crate::monitoring::reports::ReportConfig;
+use crate::processing::token_budget::TokenBudgetConfig;
+use crate::utils::outbound_scheduler::OutboundSchedulerConfig;
+use crate::utils::provider_health::ProviderHealthConfig;
+use crate::utils::search_budget::SearchBudgetConfig;
+
+/// 默认配置文件
+pub const DEFAULT_CONFIG_PATH: &str = "penlai.toml";
+
+/// 键名包含这些片段时在日志和审计记录中隐藏取值
+const SENSITIVE_KEY_PARTS: [&str; 4] = ["secret", "token", "password", "api_key"];
+
+/// 当前配置的配置文件（PENLAI_CONFIG，未设置时使用默认路径）
+pub fn config_path() -> PathBuf {
+    std::env::var("PENLAI_CONFIG")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_CONFIG_PATH))
+}
+
+/// 触发重新加载的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReloadTrigger {
+    FileChanged,    // 文件修改时间变化（包括启动后首次读取）
+    Signal,         // 收到SIGHUP
+    Manual,         // 调用方主动触发
+}
+
+impl ReloadTrigger {
+    fn actor(&self) -> &'static str {
+        match self {
+            ReloadTrigger::FileChanged => "config:file",
+            ReloadTrigger::Signal => "config:sighup",
+            ReloadTrigger::Manual => "config:manual",
+        }
+    }
+}
+
+/// 一项配置变更
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConfigChange {
+    pub key: String,    // 含组件名的完整路径，如processor.max_requests_per_minute
+    pub old: Value,
+    pub new: Value,
+}
+
+impl std::fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lower = self.key.to_lowercase();
+        if SENSITIVE_KEY_PARTS.iter().any(|part| lower.contains(part)) {
+            write!(f, "{}: *** -> ***", self.key)
+        } else {
+            write!(f, "{}: {} -> {}", self.key, self.old, self.new)
+        }
+    }
+}
+
+/// 一次重新加载的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReloadReport {
+    pub trigger: ReloadTrigger,
+    pub changes: Vec<ConfigChange>,     // 为空表示文件与当前配置一致
+    pub applied_at: DateTime<Utc>,
+}
+
+/// 重新加载失败的原因；失败时不应用任何变更
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigReloadError {
+    Read(String),
+    Parse(String),
+    UnknownSection(String),
+    UnknownKey(String),
+    Invalid { section: String, reason: String },
+}
+
+impl std::fmt::Display for ConfigReloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigReloadError::Read(e) => write!(f, "Failed to read config: {}", e),
+            ConfigReloadError::Parse(e) => write!(f, "Failed to parse config: {}", e),
+            ConfigReloadError::UnknownSection(section) => write!(f, "Unknown config section '{}'", section),
+            ConfigReloadError::UnknownKey(key) => write!(f, "Unknown config key '{}'", key),
+            ConfigReloadError::Invalid { section, reason } => write!(f, "Invalid [{}] config: {}", section, reason),
+        }
+    }
+}
+
+impl std::error::Error for ConfigReloadError {}
+
+/// 配置监视器
+pub struct ConfigWatcher {
+    app: Arc<Penlai>,
+    path: PathBuf,
+    audit_log: Arc<AuditLog>,
+    poll_interval: std::time::Duration,
+    last_modified: Arc<RwLock<Option<SystemTime>>>,  // 最近一次读取时的文件修改时间
+}
+
+impl ConfigWatcher {
+    /// 创建监视器（每5秒检查一次文件修改时间）
+    pub fn new(app: Arc<Penlai>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            app,
+            path: path.into(),
+            audit_log: Arc::new(AuditLog::default()),
+            poll_interval: std::time::Duration::from_secs(5),
+            last_modified: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// 写入指定的审计日志（如审批流程的审计日志）
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// 设置文件检查间隔
+    pub fn with_poll_interval(mut self, poll_interval: std::time::Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// 获取审计日志
+    pub fn get_audit_log(&self) -> Arc<AuditLog> {
+        self.audit_log.clone()
+    }
+
+    /// 文件修改时间变化时重新加载；文件不存在或未变化时返回None
+    pub async fn reload_if_changed(&self) -> Option<Result<ReloadReport, ConfigReloadError>> {
+        let modified = tokio::fs::metadata(&self.path).await.and_then(|metadata| metadata.modified()).ok()?;
+        if *self.last_modified.read().await == Some(modified) {
+            return None;
+        }
+        *self.last_modified.write().await = Some(modified);
+        Some(self.reload(ReloadTrigger::FileChanged).await)
+    }
+
+    /// 读取配置文件，校验全部组件的新配置后再统一应用
+    pub async fn reload(&self, trigger: ReloadTrigger) -> Result<ReloadReport, ConfigReloadError> {
+        let text = tokio::fs::read_to_string(&self.path).await.map_err(|e| ConfigReloadError::Read(format!("{}: {}", self.path.display(), e)))?;
+        let table: toml::Table = toml::from_str(&text).map_err(|e| ConfigReloadError::Parse(e.to_string()))?;
+        let Value::Object(mut sections) = serde_json::to_value(table).map_err(|e| ConfigReloadError::Parse(e.to_string()))? else {
+            return Err(ConfigReloadError::Parse("Top level must be a table".to_string()));
+        };
+
+        let app = &self.app;
+        let mut changes = Vec::new();
+        let token_budget = app.request_processor.get_token_budget();
+        let processor = stage(&mut sections, "processor", app.request_processor.get_config().await, &mut changes)?;
+        let selector = stage(&mut sections, "selector", app.context_selector.get_config().await, &mut changes)?;
+        let token_budget_config = stage(&mut sections, "token_budget", token_budget.get_config().await, &mut changes)?;
+        let search_budget = stage(&mut sections, "search_budget", app.search_budget.get_config().await, &mut changes)?;
+        let outbound = stage(&mut sections, "outbound", app.outbound.get_config().await, &mut changes)?;
+        let provider_health = stage(&mut sections, "provider_health", app.provider_health.get_config().await, &mut changes)?;
+        let ingestion = stage(&mut sections, "ingestion", app.ingestion.get_config().await, &mut changes)?;
+        let reports = stage(&mut sections, "reports", app.reports.get_config().await, &mut changes)?;
+        let expiry = stage(&mut sections, "expiry", app.expiry_notifier.get_config().await, &mut changes)?;
+        let quota_warnings = stage(&mut sections, "quota_warnings", app.quota_warner.get_config().await, &mut changes)?;
+        let providers = stage(&mut sections, "providers", app.get_providers_config().await, &mut changes)?;
+        if let Some(section) = sections.keys().next() {
+            return Err(ConfigReloadError::UnknownSection(section.clone()));
+        }
+
+        // 全部组件校验通过后才应用，任一失败时不修改任何配置
+        check("processor", &processor, |config| validate_processor_config(config).map(|_| ()))?;
+        check("selector", &selector, |config| validate_selector_config(config).map(|_| ()))?;
+        check("token_budget", &token_budget_config, validate_token_budget)?;
+        check("search_budget", &search_budget, validate_search_budget)?;
+        check("outbound", &outbound, validate_outbound)?;
+        check("provider_health", &provider_health, validate_provider_health)?;
+        check("ingestion", &ingestion, validate_ingestion)?;
+        check("reports", &reports, validate_reports)?;
+        check("expiry", &expiry, validate_expiry)?;
+        check("quota_warnings", &quota_warnings, validate_quota_warnings)?;
+        check("providers", &providers, |config| validate_providers_config(config).map(|_| ()))?;
+
+        if let Some(config) = processor {
+            app.request_processor.update_config(config).await;
+        }
+        if let Some(config) = selector {
+            app.context_selector.update_config(config).await;
+        }
+        if let Some(config) = token_budget_config {
+            token_budget.update_config(config).await;
+        }
+        if let Some(config) = search_budget {
+            app.search_budget.update_config(config).await;
+        }
+        if let Some(config) = outbound {
+            app.outbound.update_config(config).await;
+        }
+        if let Some(config) = provider_health {
+            app.provider_health.update_config(config).await;
+        }
+        if let Some(config) = ingestion {
+            app.ingestion.update_config(config).await;
+        }
+        if let Some(config) = reports {
+            app.reports.update_config(config).await;
+        }
+        if let Some(config) = expiry {
+            app.expiry_notifier.update_config(config).await;
+        }
+        if let Some(config) = quota_warnings {
+            app.quota_warner.update_config(config).await;
+        }
+        if let Some(config) = providers {
+            app.update_providers_config(config).await;
+        }
+
+        if !changes.is_empty() {
+            let summary = changes.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ");
+            log::info!("Applied {} config change(s) from {}: {}", changes.len(), self.path.display(), summary);
+            self.audit_log
+                .record(Uuid::nil(), trigger.actor(), AuditAction::ConfigChanged, None, None, Some(summary))
+                .await;
+        }
+        Ok(ReloadReport { trigger, changes, applied_at: Utc::now() })
+    }
+
+    /// 启动后台任务：定期检查文件修改时间，收到SIGHUP时立即重新加载
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut hangup = hangup_signal();
+            loop {
+                let outcome = tokio::select! {
+                    _ = tokio::time::sleep(self.poll_interval) => self.reload_if_changed().await,
+                    _ = wait_for_hangup(&mut hangup) => Some(self.reload(ReloadTrigger::Signal).await),
+                };
+                if let Some(Err(e)) = outcome {
+                    log::warn!("Config reload from {} rejected, keeping current config: {}", self.path.display(), e);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(unix)]
+type HangupSignal = Option<tokio::signal::unix::Signal>;
+#[cfg(not(unix))]
+type HangupSignal = ();
+
+#[cfg(unix)]
+fn hangup_signal() -> HangupSignal {
+    match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => Some(signal),
+        Err(e) => {
+            log::warn!("Failed to install SIGHUP handler, config reloads on file change only: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn hangup_signal() -> HangupSignal {}
+
+#[cfg(unix)]
+async fn wait_for_hangup(signal: &mut HangupSignal) {
+    match signal {
+        Some(signal) => {
+            signal.recv().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_hangup(_signal: &mut HangupSignal) {
+    std::future::pending().await
+}
+
+/// 把文件中的一个表合并到组件当前配置上，返回新配置（表不存在或没有变化时为None）并追加变更项
+fn stage<C: Serialize + DeserializeOwned>(
+    sections: &mut serde_json::Map<String, Value>,
+    section: &str,
+    current: C,
+    changes: &mut Vec<ConfigChange>,
+) -> Result<Option<C>, ConfigReloadError> {
+    let Some(overlay) = sections.remove(section) else {
+        return Ok(None);
+    };
+    let invalid = |reason: String| ConfigReloadError::Invalid { section: section.to_string(), reason };
+    let old = serde_json::to_value(&current).map_err(|e| invalid(e.to_string()))?;
+    let mut merged = old.clone();
+    merge(&mut merged, overlay.clone());
+    let config: C = serde_json::from_value(merged).map_err(|e| invalid(e.to_string()))?;
+    let new = serde_json::to_value(&config).map_err(|e| invalid(e.to_string()))?;
+
+    // 反序列化时被忽略的键视为拼写错误
+    let (mut overlay_keys, mut new_keys, mut old_keys) = (BTreeMap::new(), BTreeMap::new(), BTreeMap::new());
+    flatten(section, &overlay, &mut overlay_keys);
+    flatten(section, &new, &mut new_keys);
+    flatten(section, &old, &mut old_keys);
+    if let Some(key) = overlay_keys.keys().find(|key| !new_keys.contains_key(*key) && !is_prefix_of_any(key, &new_keys)) {
+        return Err(ConfigReloadError::UnknownKey(key.clone()));
+    }
+
+    let before = changes.len();
+    for (key, value) in &new_keys {
+        let previous = old_keys.get(key).cloned().unwrap_or(Value::Null);
+        if &previous != value {
+            changes.push(ConfigChange { key: key.clone(), old: previous, new: value.clone() });
+        }
+    }
+    Ok((changes.len() > before).then_some(config))
+}
+
+/// 校验一个已暂存的表，失败时带上表名
+fn check<C>(section: &str, config: &Option<C>, validate: impl Fn(&C) -> Result<(), String>) -> Result<(), ConfigReloadError> {
+    match config {
+        Some(config) => validate(config).map_err(|reason| ConfigReloadError::Invalid { section: section.to_string(), reason }),
+        None => Ok(()),
+    }
+}
+
+fn validate_token_budget(config: &TokenBudgetConfig) -> Result<(), String> {
+    if !(0.0..=1.0).contains(&config.soft_limit_ratio) {
+        return Err(format!("soft_limit_ratio {} is outside 0-1", config.soft_limit_ratio));
+    }
+    Ok(())
+}
+
+fn validate_search_budget(config: &SearchBudgetConfig) -> Result<(), String> {
+    match config.cost_per_query.iter().find(|(_, cost)| !cost.is_finite() || **cost < 0.0) {
+        Some((provider, cost)) => Err(format!("cost_per_query {} for '{}' must not be negative", cost, provider)),
+        None => Ok(()),
+    }
+}
+
+fn validate_outbound(config: &OutboundSchedulerConfig) -> Result<(), String> {
+    if let Some((provider, qps)) = config.provider_qps.iter().find(|(_, qps)| !qps.is_finite() || **qps <= 0.0) {
+        return Err(format!("provider_qps {} for '{}' must be greater than 0", qps, provider));
+    }
+    if !(0.0..=1.0).contains(&config.background_share) {
+        return Err(format!("background_share {} is outside 0-1", config.background_share));
+    }
+    Ok(())
+}
+
+fn validate_provider_health(config: &ProviderHealthConfig) -> Result<(), String> {
+    if config.timeout_ms == 0 || config.check_interval_seconds == 0 {
+        return Err("timeout_ms and check_interval_seconds must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+fn validate_ingestion(config: &IngestionConfig) -> Result<(), String> {
+    if config.max_documents_per_request == 0 || config.max_jobs_retained == 0 {
+        return Err("max_documents_per_request and max_jobs_retained must be greater than 0".to_string());
+    }
+    if config.max_signature_age_seconds <= 0 {
+        return Err(format!("max_signature_age_seconds {} must be greater than 0", config.max_signature_age_seconds));
+    }
+    Ok(())
+}
+
+fn validate_reports(config: &ReportConfig) -> Result<(), String> {
+    if config.delivery_hour_utc > 23 {
+        return Err(format!("delivery_hour_utc {} is outside 0-23", config.delivery_hour_utc));
+    }
+    if !config.cost_per_1k_tokens.is_finite() || config.cost_per_1k_tokens < 0.0 {
+        return Err(format!("cost_per_1k_tokens {} must not be negative", config.cost_per_1k_tokens));
+    }
+    Ok(())
+}
+
+fn validate_expiry(config: &ExpiryNoticeConfig) -> Result<(), String> {
+    if config.scan_interval_seconds == 0 {
+        return Err("scan_interval_seconds must be greater than 0".to_string());
+    }
+    Ok(())
+}
+
+fn validate_quota_warnings(config: &QuotaWarningConfig) -> Result<(), String> {
+    match config.thresholds_percent.iter().find(|percent| !(1..=100).contains(*percent)) {
+        Some(percent) => Err(format!("threshold {}% is outside 1-100", percent)),
+        None => Ok(()),
+    }
+}
+
+/// 递归合并：对象逐键合并，其他值直接替换
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// 展开为"a.b.c" -> 值（数组作为整体）
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten(&format!("{}.{}", prefix, key), value, out);
+            }
+        }
+        _ => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// 文件中写的是空表而当前配置在该路径下有子键
+fn is_prefix_of_any(key: &str, keys: &BTreeMap<String, Value>) -> bool {
+    let prefix = format!("{}.", key);
+    keys.range(prefix.clone()..).next().map(|(next, _)| next.starts_with(&prefix)).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hot_reload_applies_validated_changes() {
+        let app = Arc::new(Penlai::new(10, 3600));
+        let path = std::env::temp_dir().join(format!("penlai-{}.toml", Uuid::new_v4()));
+        let watcher = ConfigWatcher::new(app.clone(), &path);
+        assert!(watcher.reload_if_changed().await.is_none());
+
+        std::fs::write(
+            &path,
+            "[processor]\nmax_requests_per_minute = 50\n\n[selector.multi_query]\nvariations = 4\n\n[ingestion]\nshared_secret = \"rotated\"\n",
+        )
+        .unwrap();
+        let report = watcher.reload_if_changed().await.unwrap().unwrap();
+        assert_eq!(report.trigger, ReloadTrigger::FileChanged);
+        let keys: Vec<&str> = report.changes.iter().map(|change| change.key.as_str()).collect();
+        assert!(keys.contains(&"processor.max_requests_per_minute"));
+        assert!(keys.contains(&"selector.multi_query.variations"));
+        assert_eq!(app.request_processor.get_config().await.max_requests_per_minute, 50);
+        assert_eq!(app.context_selector.get_config().await.multi_query.variations, 4);
+        assert_eq!(app.ingestion.get_config().await.shared_secret.as_deref(), Some("rotated"));
+        // 未修改的文件不会重复加载
+        assert!(watcher.reload_if_changed().await.is_none());
+
+        // 变更记录到审计日志，敏感值被隐藏
+        let audit = watcher.get_audit_log().get_recent(1).await;
+        assert_eq!(audit[0].action, AuditAction::ConfigChanged);
+        let note = audit[0].note.clone().unwrap();
+        assert!(note.contains("processor.max_requests_per_minute: 1000 -> 50"));
+        assert!(note.contains("ingestion.shared_secret: *** -> ***") && !note.contains("rotated"));
+
+        // 任一组件校验失败时全部不应用
+        std::fs::write(&path, "[processor]\nmax_concurrent_requests = 0\n\n[reports]\ntop_domains_limit = 9\n").unwrap();
+        assert!(matches!(watcher.reload(ReloadTrigger::Signal).await, Err(ConfigReloadError::Invalid { .. })));
+        assert_eq!(app.reports.get_config().await.top_domains_limit, 5);
+        // 处理器和选择器以外的组件同样先校验：负的QPS拒绝整个文件
+        std::fs::write(&path, "[reports]\ntop_domains_limit = 9\n\n[outbound.provider_qps]\nbing = -1.0\n").unwrap();
+        assert!(matches!(
+            watcher.reload(ReloadTrigger::Manual).await,
+            Err(ConfigReloadError::Invalid { ref section, .. }) if section == "outbound"
+        ));
+        assert_eq!(app.reports.get_config().await.top_domains_limit, 5);
+        std::fs::write(&path, "[providers]\nbing_search_url = \"ftp://search.example.com\"\n").unwrap();
+        assert!(matches!(watcher.reload(ReloadTrigger::Manual).await, Err(ConfigReloadError::Invalid { .. })));
+
+        // 外部服务地址可热切换
+        std::fs::write(&path, "[providers]\nreport_webhook_url = \"http://127.0.0.1:9/reports\"\n").unwrap();
+        let report = watcher.reload(ReloadTrigger::Manual).await.unwrap();
+        assert_eq!(report.changes[0].key, "providers.report_webhook_url");
+        assert_eq!(app.get_providers_config().await.report_webhook_url.as_deref(), Some("http://127.0.0.1:9/reports"));
+
+        std::fs::write(&path, "[processor]\nmax_request_per_minute = 10\n").unwrap();
+        assert_eq!(
+            watcher.reload(ReloadTrigger::Manual).await.unwrap_err(),
+            ConfigReloadError::UnknownKey("processor.max_request_per_minute".to_string())
+        );
+        std::fs::write(&path, "[cache]\nttl = 10\n").unwrap();
+        assert_eq!(watcher.reload(ReloadTrigger::Manual).await.unwrap_err(), ConfigReloadError::UnknownSection("cache".to_string()));
+
+        // 内容与当前配置一致时没有变更，也不写审计日志
+        std::fs::write(&path, "[processor]\nmax_requests_per_minute = 50\n").unwrap();
+        assert!(watcher.reload(ReloadTrigger::Manual).await.unwrap().changes.is_empty());
+        assert_eq!(watcher.get_audit_log().get_recent(10).await.len(), 2);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,227 @@
+//! 上下文访问控制 - 可见范围（个人、会话、团队、租户、全局）及成员目录
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use crate::context::llm_context::LLMContext;
+
+/// 上下文可见范围（作者始终可见自己的上下文）
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Visibility {
+    Private,        // 仅作者可见
+    Session,        // 同一会话可见
+    Team(String),   // 指定团队成员可见
+    Tenant(String), // 指定租户内用户可见
+    #[default]
+    Global,         // 所有用户可见（未设置范围的上下文默认为此值）
+}
+
+impl Visibility {
+    /// 序列化名称（团队和租户带上标识，如team:oncology）
+    pub fn as_string(&self) -> String {
+        match self {
+            Visibility::Private => "private".to_string(),
+            Visibility::Session => "session".to_string(),
+            Visibility::Team(team) => format!("team:{}", team),
+            Visibility::Tenant(tenant) => format!("tenant:{}", tenant),
+            Visibility::Global => "global".to_string(),
+        }
+    }
+
+    /// 由序列化名称解析，未知或空值返回None（调用方应按最严格的范围处理，不能放宽为全局可见）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.split_once(':') {
+            Some(("team", team)) if !team.is_empty() => Some(Visibility::Team(team.to_string())),
+            Some(("tenant", tenant)) if !tenant.is_empty() => Some(Visibility::Tenant(tenant.to_string())),
+            Some(_) => None,
+            None => match name {
+                "private" => Some(Visibility::Private),
+                "session" => Some(Visibility::Session),
+                "global" => Some(Visibility::Global),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// 检索上下文的用户身份
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Viewer {
+    pub user_id: Option<String>,    // 用户ID，未设置时视为匿名
+    pub session_id: Option<String>, // 当前会话ID
+}
+
+impl Viewer {
+    /// 创建指定用户和会话的身份
+    pub fn new(user_id: Option<&str>, session_id: Option<&str>) -> Self {
+        Self {
+            user_id: user_id.map(str::to_string),
+            session_id: session_id.map(str::to_string),
+        }
+    }
+
+    /// 匿名身份，只能看到全局上下文
+    pub fn anonymous() -> Self {
+        Self::default()
+    }
+}
+
+/// 成员目录 - 记录用户所属的团队和租户
+#[derive(Default)]
+pub struct AccessDirectory {
+    teams: Arc<RwLock<HashMap<String, HashSet<String>>>>, // 用户 -> 所属团队
+    tenants: Arc<RwLock<HashMap<String, String>>>,        // 用户 -> 所属租户
+}
+
+impl AccessDirectory {
+    /// 创建空的成员目录
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 将用户加入团队
+    pub async fn add_team_member(&self, team: &str, user_id: &str) {
+        let mut teams = self.teams.write().await;
+        teams.entry(user_id.to_string()).or_default().insert(team.to_string());
+    }
+
+    /// 将用户移出团队
+    pub async fn remove_team_member(&self, team: &str, user_id: &str) -> bool {
+        let mut teams = self.teams.write().await;
+        teams.get_mut(user_id).map(|user_teams| user_teams.remove(team)).unwrap_or(false)
+    }
+
+    /// 设置用户所属租户
+    pub async fn set_tenant(&self, user_id: &str, tenant: &str) {
+        let mut tenants = self.tenants.write().await;
+        tenants.insert(user_id.to_string(), tenant.to_string());
+    }
+
+    /// 移除用户的全部团队和租户成员关系，返回是否存在成员关系；dry_run时只检查
+    pub async fn remove_user(&self, user_id: &str, dry_run: bool) -> bool {
+        let mut teams = self.teams.write().await;
+        let mut tenants = self.tenants.write().await;
+        let present = teams.contains_key(user_id) || tenants.contains_key(user_id);
+        if !dry_run {
+            teams.remove(user_id);
+            tenants.remove(user_id);
+        }
+        present
+    }
+
+    /// 获取用户所属团队
+    pub async fn teams_of(&self, user_id: &str) -> HashSet<String> {
+        self.teams.read().await.get(user_id).cloned().unwrap_or_default()
+    }
+
+    /// 获取用户所属租户
+    pub async fn tenant_of(&self, user_id: &str) -> Option<String> {
+        self.tenants.read().await.get(user_id).cloned()
+    }
+
+    /// 上下文对指定身份是否可见
+    pub async fn can_view(&self, context: &LLMContext, viewer: &Viewer) -> bool {
+        let user_id = viewer.user_id.as_deref();
+        if user_id == Some(context.user_id.as_str()) {
+            return true;
+        }
+        match context.visibility {
+            Visibility::Global => true,
+            Visibility::Private => false,
+            Visibility::Session => viewer.session_id.as_deref() == Some(context.session_id.as_str()),
+            Visibility::Team(ref team) => match user_id {
+                Some(user_id) => self.teams.read().await.get(user_id).map(|t| t.contains(team)).unwrap_or(false),
+                None => false,
+            },
+            Visibility::Tenant(ref tenant) => match user_id {
+                Some(user_id) => self.tenants.read().await.get(user_id) == Some(tenant),
+                None => false,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::selection::hybrid_search::HybridQuery;
+
+    #[tokio::test]
+    async fn test_visibility_scopes() {
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let directory = manager.get_access_directory();
+        directory.add_team_member("oncology", "alice").await;
+        directory.add_team_member("oncology", "bob").await;
+        directory.set_tenant("alice", "hospital_a").await;
+        directory.set_tenant("carol", "hospital_a").await;
+
+        for (data, visibility) in [
+            ("pneumonia personal notes", Visibility::Private),
+            ("pneumonia session scratch", Visibility::Session),
+            ("pneumonia team protocol", Visibility::Team("oncology".to_string())),
+            ("pneumonia tenant guideline", Visibility::Tenant("hospital_a".to_string())),
+            ("pneumonia public reference", Visibility::Global),
+        ] {
+            manager
+                .create_context_with_visibility(
+                    "s1".to_string(),
+                    "alice".to_string(),
+                    "medical".to_string(),
+                    data.to_string(),
+                    5,
+                    HashMap::new(),
+                    visibility,
+                )
+                .await
+                .unwrap();
+        }
+
+        let selector = ContextSelector::new(manager.clone());
+        let visible_to = |user: &'static str, session: &'static str| {
+            let selector = &selector;
+            async move {
+                let mut data: Vec<String> = selector
+                    .select_contexts(user, session, "pneumonia", "medical")
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|ctx| ctx.context_data)
+                    .collect();
+                data.sort();
+                data
+            }
+        };
+
+        assert_eq!(visible_to("alice", "s9").await.len(), 5);
+        assert_eq!(visible_to("bob", "s9").await, vec!["pneumonia public reference", "pneumonia team protocol"]);
+        assert_eq!(visible_to("carol", "s9").await, vec!["pneumonia public reference", "pneumonia tenant guideline"]);
+        assert_eq!(visible_to("dave", "s1").await, vec!["pneumonia public reference", "pneumonia session scratch"]);
+
+        // 匿名混合检索只能看到全局上下文
+        let results = selector.hybrid_search(HybridQuery::new("pneumonia")).await.unwrap();
+        assert_eq!(results.len(), 1);
+
+        // 成员变更后缓存的选择结果不再包含无权访问的上下文
+        directory.remove_team_member("oncology", "bob").await;
+        assert_eq!(visible_to("bob", "s9").await, vec!["pneumonia public reference"]);
+    }
+
+    #[test]
+    fn test_visibility_parse() {
+        for visibility in [
+            Visibility::Private,
+            Visibility::Session,
+            Visibility::Team("a:b".to_string()),
+            Visibility::Tenant("t1".to_string()),
+            Visibility::Global,
+        ] {
+            assert_eq!(Visibility::parse(&visibility.as_string()), Some(visibility));
+        }
+        for unknown in ["", "public", "team:", "org:acme"] {
+            assert_eq!(Visibility::parse(unknown), None);
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! 上下文审批流程 - 受监管领域的上下文需经过草稿→审核→批准后才能参与选择
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use crate::context::audit::{AuditAction, AuditLog};
+use crate::context::llm_context::LLMContext;
+
+/// 审批状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ApprovalState {
+    Draft,          // 草稿，不参与选择
+    InReview,       // 待审核，不参与选择
+    #[default]
+    Approved,       // 已批准（不需要审批的领域默认为此状态）
+    Rejected,       // 已驳回，修改后可重新提交
+}
+
+impl ApprovalState {
+    /// 是否可参与上下文选择
+    pub fn is_selectable(&self) -> bool {
+        *self == ApprovalState::Approved
+    }
+
+    /// 序列化名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApprovalState::Draft => "draft",
+            ApprovalState::InReview => "in_review",
+            ApprovalState::Approved => "approved",
+            ApprovalState::Rejected => "rejected",
+        }
+    }
+
+    /// 由序列化名称解析，未知或空值返回None（调用方应按草稿处理，不能跳过审核）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "draft" => Some(ApprovalState::Draft),
+            "in_review" => Some(ApprovalState::InReview),
+            "approved" => Some(ApprovalState::Approved),
+            "rejected" => Some(ApprovalState::Rejected),
+            _ => None,
+        }
+    }
+}
+
+/// 审批策略
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ApprovalPolicy {
+    pub required_domains: HashSet<String>,  // 需要审批的领域
+    pub reviewers: HashSet<String>,         // 有审核权限的用户
+    pub reviewers_see_drafts: bool,         // 审核人选择时是否可见未批准的上下文
+    pub allow_self_approval: bool,          // 是否允许作者审核自己的上下文
+}
+
+impl ApprovalPolicy {
+    /// 为领域启用审批
+    pub fn require_domain(mut self, domain: &str) -> Self {
+        self.required_domains.insert(domain.to_string());
+        self
+    }
+
+    /// 添加审核人
+    pub fn with_reviewer(mut self, reviewer: &str) -> Self {
+        self.reviewers.insert(reviewer.to_string());
+        self
+    }
+}
+
+/// 审批流程 - 保存策略并记录审计日志
+pub struct ApprovalWorkflow {
+    policy: Arc<RwLock<ApprovalPolicy>>,
+    audit_log: Arc<AuditLog>,
+}
+
+impl ApprovalWorkflow {
+    /// 使用指定策略创建审批流程
+    pub fn new(policy: ApprovalPolicy) -> Self {
+        Self {
+            policy: Arc::new(RwLock::new(policy)),
+            audit_log: Arc::new(AuditLog::default()),
+        }
+    }
+
+    /// 使用指定的审计日志
+    pub fn with_audit_log(mut self, audit_log: Arc<AuditLog>) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    /// 领域是否需要审批
+    pub async fn requires_approval(&self, domain: &str) -> bool {
+        self.policy.read().await.required_domains.contains(domain)
+    }
+
+    /// 新建上下文的初始状态
+    pub async fn initial_state(&self, domain: &str) -> ApprovalState {
+        if self.requires_approval(domain).await {
+            ApprovalState::Draft
+        } else {
+            ApprovalState::Approved
+        }
+    }
+
+    /// 上下文对指定用户是否可见（用于选择）
+    pub async fn is_visible(&self, context: &LLMContext, viewer: Option<&str>) -> bool {
+        if context.approval_state.is_selectable() {
+            return true;
+        }
+        let policy = self.policy.read().await;
+        policy.reviewers_see_drafts && viewer.map(|v| policy.reviewers.contains(v)).unwrap_or(false)
+    }
+
+    /// 校验状态转换，返回目标状态
+    pub async fn check_transition(
+        &self,
+        context: &LLMContext,
+        actor: &str,
+        action: AuditAction,
+    ) -> Result<ApprovalState, Box<dyn std::error::Error + Send + Sync>> {
+        let policy = self.policy.read().await;
+        if !policy.required_domains.contains(&context.domain) {
+            return Err(format!("Approval is not enabled for domain '{}'", context.domain).into());
+        }
+        let from = context.approval_state;
+        match action {
+            AuditAction::Submitted => match from {
+                ApprovalState::Draft | ApprovalState::Rejected => Ok(ApprovalState::InReview),
+                _ => Err(format!("Cannot submit a context in state {:?}", from).into()),
+            },
+            AuditAction::Approved | AuditAction::Rejected => {
+                if !policy.reviewers.contains(actor) {
+                    return Err(format!("User '{}' is not a reviewer", actor).into());
+                }
+                if !policy.allow_self_approval && context.user_id == actor {
+                    return Err("Reviewers cannot review their own contexts".into());
+                }
+                if from != ApprovalState::InReview {
+                    return Err(format!("Cannot review a context in state {:?}", from).into());
+                }
+                Ok(if action == AuditAction::Approved { ApprovalState::Approved } else { ApprovalState::Rejected })
+            }
+            _ => Err(format!("{:?} is not a review action", action).into()),
+        }
+    }
+
+    /// 获取审计日志
+    pub fn get_audit_log(&self) -> Arc<AuditLog> {
+        self.audit_log.clone()
+    }
+
+    /// 更新策略
+    pub async fn update_policy(&self, new_policy: ApprovalPolicy) {
+        let mut policy = self.policy.write().await;
+        *policy = new_policy;
+    }
+
+    /// 获取当前策略
+    pub async fn get_policy(&self) -> ApprovalPolicy {
+        self.policy.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::selection::async_context_selector::ContextSelector;
+
+    #[tokio::test]
+    async fn test_approval_workflow() {
+        let workflow = Arc::new(ApprovalWorkflow::new(
+            ApprovalPolicy::default().require_domain("medical").with_reviewer("dr_lee"),
+        ));
+        let manager = Arc::new(ContextManager::new(10, 3600).with_approval_workflow(workflow.clone()));
+        let selector = ContextSelector::new(manager.clone());
+
+        let draft = manager
+            .create_context("s1".to_string(), "author".to_string(), "medical".to_string(), "Pneumonia dosage".to_string(), 5)
+            .await
+            .unwrap();
+        assert_eq!(draft.approval_state, ApprovalState::Draft);
+        let other = manager
+            .create_context("s1".to_string(), "author".to_string(), "technical".to_string(), "Rust".to_string(), 5)
+            .await
+            .unwrap();
+        assert_eq!(other.approval_state, ApprovalState::Approved);
+
+        let selected = selector.select_contexts("u2", "s2", "pneumonia", "medical").await.unwrap();
+        assert!(selected.is_empty());
+
+        // 未提交审核时不能批准，作者和非审核人也不能批准
+        assert!(manager.approve_context(draft.id, "dr_lee", None).await.is_err());
+        manager.submit_for_review(draft.id, "author").await.unwrap();
+        assert!(manager.approve_context(draft.id, "author", None).await.is_err());
+        manager.reject_context(draft.id, "dr_lee", Some("cite source".to_string())).await.unwrap();
+        manager.submit_for_review(draft.id, "author").await.unwrap();
+        let approved = manager.approve_context(draft.id, "dr_lee", Some("ok".to_string())).await.unwrap();
+        assert_eq!(approved.approval_state, ApprovalState::Approved);
+
+        let selected = selector.select_contexts("u2", "s3", "pneumonia", "medical").await.unwrap();
+        assert_eq!(selected.len(), 1);
+
+        // 修改已批准的内容后退回草稿
+        manager.update_context(draft.id, Some("Pneumonia dosage v2".to_string()), None, None).await.unwrap();
+        assert_eq!(manager.get_context(draft.id).await.unwrap().approval_state, ApprovalState::Draft);
+
+        let audit = workflow.get_audit_log().entries_for_context(draft.id).await;
+        let actions: Vec<AuditAction> = audit.iter().map(|e| e.action).collect();
+        assert_eq!(
+            actions,
+            vec![
+                AuditAction::Created,
+                AuditAction::Submitted,
+                AuditAction::Rejected,
+                AuditAction::Submitted,
+                AuditAction::Approved,
+                AuditAction::Revised
+            ]
+        );
+        assert_eq!(audit[4].actor, "dr_lee");
+        assert_eq!(audit[2].note.as_deref(), Some("cite source"));
+    }
+}
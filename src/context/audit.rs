@@ -0,0 +1,159 @@
+//! 审计日志 - 记录上下文审批相关操作及操作人，以及配置热加载的变更和用户数据的转交与删除
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use crate::context::approval::ApprovalState;
+
+/// 审计动作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditAction {
+    Created,            // 创建（需要审批的领域中以草稿创建）
+    Revised,            // 已审批内容被修改，退回草稿
+    Submitted,          // 提交审核
+    Approved,
+    Rejected,
+    ConfigChanged,      // 配置热加载（上下文ID为空UUID，备注中为变更内容）
+    ContextsTransferred, // 用户的上下文转交给其他用户（上下文ID为空UUID，涉及的用户为转出和转入用户，备注中为数量）
+    UserErased,         // 删除用户数据（上下文ID为空UUID，操作人为用户的假名）
+}
+
+/// 审计日志条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub context_id: Uuid,
+    pub actor: String,                      // 操作人（作者或审核人）
+    pub action: AuditAction,
+    pub from_state: Option<ApprovalState>,
+    pub to_state: Option<ApprovalState>,
+    pub note: Option<String>,               // 审核意见（不应包含用户ID，涉及的用户记录在subjects中）
+    #[serde(default)]
+    pub subjects: Vec<String>,              // 操作涉及的其他用户（如转交的双方），删除用户数据时与操作人一并处理
+}
+
+impl AuditEntry {
+    /// 条目是否关联到该用户（作为操作人或涉及的用户）
+    pub fn involves(&self, user_id: &str) -> bool {
+        self.actor == user_id || self.subjects.iter().any(|subject| subject == user_id)
+    }
+}
+
+/// 审计日志 - 只追加，超出容量时丢弃最早的条目
+pub struct AuditLog {
+    entries: Arc<RwLock<VecDeque<AuditEntry>>>,
+    max_entries: usize,
+}
+
+impl Default for AuditLog {
+    fn default() -> Self {
+        Self::new(100_000)
+    }
+}
+
+impl AuditLog {
+    /// 创建审计日志
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(VecDeque::new())),
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    /// 记录一条审计日志
+    pub async fn record(
+        &self,
+        context_id: Uuid,
+        actor: &str,
+        action: AuditAction,
+        from_state: Option<ApprovalState>,
+        to_state: Option<ApprovalState>,
+        note: Option<String>,
+    ) -> AuditEntry {
+        self.record_with_subjects(context_id, actor, action, from_state, to_state, note, Vec::new()).await
+    }
+
+    /// 记录一条涉及其他用户的审计日志
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record_with_subjects(
+        &self,
+        context_id: Uuid,
+        actor: &str,
+        action: AuditAction,
+        from_state: Option<ApprovalState>,
+        to_state: Option<ApprovalState>,
+        note: Option<String>,
+        subjects: Vec<String>,
+    ) -> AuditEntry {
+        let entry = AuditEntry {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            context_id,
+            actor: actor.to_string(),
+            action,
+            from_state,
+            to_state,
+            note,
+            subjects,
+        };
+        let mut entries = self.entries.write().await;
+        if entries.len() >= self.max_entries {
+            entries.pop_front();
+        }
+        entries.push_back(entry.clone());
+        entry
+    }
+
+    /// 获取上下文的审计记录（按时间顺序）
+    pub async fn entries_for_context(&self, context_id: Uuid) -> Vec<AuditEntry> {
+        self.entries.read().await.iter().filter(|e| e.context_id == context_id).cloned().collect()
+    }
+
+    /// 获取某个操作人的审计记录（按时间顺序）
+    pub async fn entries_by_actor(&self, actor: &str) -> Vec<AuditEntry> {
+        self.entries.read().await.iter().filter(|e| e.actor == actor).cloned().collect()
+    }
+
+    /// 获取关联到某个用户（作为操作人或涉及的用户）的审计记录（按时间顺序）
+    pub async fn entries_for_user(&self, user_id: &str) -> Vec<AuditEntry> {
+        self.entries.read().await.iter().filter(|e| e.involves(user_id)).cloned().collect()
+    }
+
+    /// 以假名替换某个用户（操作人和涉及的用户），保留审计轨迹但不再关联到该用户；返回替换的条目数，dry_run时只统计
+    pub async fn pseudonymize_user(&self, user_id: &str, pseudonym: &str, dry_run: bool) -> usize {
+        let mut entries = self.entries.write().await;
+        let mut count = 0;
+        for entry in entries.iter_mut().filter(|entry| entry.involves(user_id)) {
+            if !dry_run {
+                if entry.actor == user_id {
+                    entry.actor = pseudonym.to_string();
+                }
+                for subject in entry.subjects.iter_mut().filter(|subject| *subject == user_id) {
+                    *subject = pseudonym.to_string();
+                }
+            }
+            count += 1;
+        }
+        count
+    }
+
+    /// 删除关联到某个用户的全部条目，返回删除数；dry_run时只统计
+    pub async fn remove_user(&self, user_id: &str, dry_run: bool) -> usize {
+        let mut entries = self.entries.write().await;
+        let before = entries.len();
+        if dry_run {
+            return entries.iter().filter(|entry| entry.involves(user_id)).count();
+        }
+        entries.retain(|entry| !entry.involves(user_id));
+        before - entries.len()
+    }
+
+    /// 获取最近的审计记录（最新的在前）
+    pub async fn get_recent(&self, count: usize) -> Vec<AuditEntry> {
+        self.entries.read().await.iter().rev().take(count).cloned().collect()
+    }
+}
@@ -0,0 +1,365 @@
+//! 上下文序列化编解码层 - 为持久化、缓存层和传输提供可替换的序列化格式
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::context::access::Visibility;
+use crate::context::approval::ApprovalState;
+use crate::context::llm_context::LLMContext;
+use crate::context::schema_migration;
+
+/// 序列化格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SerializationFormat {
+    Json,          // 可读性好，体积最大
+    MessagePack,   // 与serde兼容的紧凑二进制格式
+    Protobuf,      // 固定字段编号的二进制格式，适合跨语言传输
+}
+
+impl SerializationFormat {
+    /// 对应的MIME类型
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SerializationFormat::Json => "application/json",
+            SerializationFormat::MessagePack => "application/msgpack",
+            SerializationFormat::Protobuf => "application/x-protobuf",
+        }
+    }
+
+    /// 按MIME类型解析格式
+    pub fn from_content_type(content_type: &str) -> Option<Self> {
+        match content_type.split(';').next().unwrap_or("").trim() {
+            "application/json" => Some(SerializationFormat::Json),
+            "application/msgpack" | "application/x-msgpack" => Some(SerializationFormat::MessagePack),
+            "application/x-protobuf" | "application/protobuf" => Some(SerializationFormat::Protobuf),
+            _ => None,
+        }
+    }
+}
+
+/// 上下文编解码器
+pub trait ContextCodec: Send + Sync {
+    /// 编解码器使用的格式
+    fn format(&self) -> SerializationFormat;
+
+    /// 编码上下文列表
+    fn encode(&self, contexts: &[LLMContext]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 解码上下文列表
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 编码单个上下文
+    fn encode_one(&self, context: &LLMContext) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        self.encode(std::slice::from_ref(context))
+    }
+
+    /// 解码单个上下文
+    fn decode_one(&self, bytes: &[u8]) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        self.decode(bytes)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| "Encoded payload contains no context".into())
+    }
+}
+
+/// JSON编解码器
+pub struct JsonCodec;
+
+impl ContextCodec for JsonCodec {
+    fn format(&self) -> SerializationFormat {
+        SerializationFormat::Json
+    }
+
+    fn encode(&self, contexts: &[LLMContext]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(serde_json::to_vec(contexts)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        schema_migration::decode_json(bytes)
+    }
+}
+
+/// MessagePack编解码器（按字段名编码，字段增减时保持兼容）
+pub struct MessagePackCodec;
+
+impl ContextCodec for MessagePackCodec {
+    fn format(&self) -> SerializationFormat {
+        SerializationFormat::MessagePack
+    }
+
+    fn encode(&self, contexts: &[LLMContext]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(rmp_serde::to_vec_named(contexts)?)
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut contexts: Vec<LLMContext> = rmp_serde::from_slice(bytes)?;
+        schema_migration::upgrade_decoded(&mut contexts)?;
+        Ok(contexts)
+    }
+}
+
+/// 上下文的Protobuf消息定义（字段编号一经发布不可修改）
+#[derive(Clone, PartialEq, prost::Message)]
+struct ContextMessage {
+    #[prost(bytes = "vec", tag = "1")]
+    id: Vec<u8>,
+    #[prost(string, tag = "2")]
+    session_id: String,
+    #[prost(string, tag = "3")]
+    user_id: String,
+    #[prost(string, tag = "4")]
+    domain: String,
+    #[prost(string, tag = "5")]
+    context_data: String,
+    #[prost(map = "string, string", tag = "6")]
+    metadata: HashMap<String, String>,
+    // 7-9为旧版的纳秒时间戳（只能表示1677-2262年），解码时在17-19缺失时使用；编码时仍在可表示范围内写出，供旧版本读取
+    #[prost(int64, tag = "7")]
+    created_at_nanos: i64,
+    #[prost(int64, tag = "8")]
+    updated_at_nanos: i64,
+    #[prost(int64, optional, tag = "9")]
+    expires_at_nanos: Option<i64>,
+    #[prost(uint32, tag = "10")]
+    priority: u32,
+    #[prost(uint32, tag = "11")]
+    version: u32,
+    #[prost(string, repeated, tag = "12")]
+    tags: Vec<String>,
+    #[prost(bool, tag = "13")]
+    active: bool,
+    #[prost(string, tag = "14")]
+    approval_state: String,
+    #[prost(string, tag = "15")]
+    visibility: String,
+    #[prost(uint32, tag = "16")]
+    schema_version: u32,
+    #[prost(message, optional, tag = "17")]
+    created_at: Option<TimestampMessage>,
+    #[prost(message, optional, tag = "18")]
+    updated_at: Option<TimestampMessage>,
+    #[prost(message, optional, tag = "19")]
+    expires_at: Option<TimestampMessage>,
+}
+
+/// 时间戳（秒和纳秒分开编码，与google.protobuf.Timestamp布局一致）
+#[derive(Clone, PartialEq, prost::Message)]
+struct TimestampMessage {
+    #[prost(int64, tag = "1")]
+    seconds: i64,
+    #[prost(int32, tag = "2")]
+    nanos: i32,
+}
+
+impl TimestampMessage {
+    fn from_time(time: &DateTime<Utc>) -> Self {
+        Self { seconds: time.timestamp(), nanos: time.timestamp_subsec_nanos() as i32 }
+    }
+
+    fn to_time(&self) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
+        let nanos = u32::try_from(self.nanos)?;
+        Ok(DateTime::from_timestamp(self.seconds, nanos).ok_or("Timestamp out of range")?)
+    }
+}
+
+/// 上下文列表的Protobuf消息
+#[derive(Clone, PartialEq, prost::Message)]
+struct ContextListMessage {
+    #[prost(message, repeated, tag = "1")]
+    contexts: Vec<ContextMessage>,
+}
+
+/// 旧版纳秒字段的取值，超出i64纳秒范围时写0
+fn legacy_nanos(time: &DateTime<Utc>) -> i64 {
+    time.timestamp_nanos_opt().unwrap_or(0)
+}
+
+/// 优先使用秒/纳秒字段，旧消息回退到纳秒字段
+fn decode_time(time: Option<TimestampMessage>, legacy_nanos: i64) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
+    match time {
+        Some(time) => time.to_time(),
+        None => Ok(DateTime::from_timestamp_nanos(legacy_nanos)),
+    }
+}
+
+impl ContextMessage {
+    fn from_context(context: &LLMContext) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(Self {
+            id: context.id.as_bytes().to_vec(),
+            session_id: context.session_id.clone(),
+            user_id: context.user_id.clone(),
+            domain: context.domain.clone(),
+            context_data: context.context_data.clone(),
+            metadata: context.metadata.clone(),
+            created_at_nanos: legacy_nanos(&context.created_at),
+            updated_at_nanos: legacy_nanos(&context.updated_at),
+            expires_at_nanos: context.expires_at.as_ref().map(legacy_nanos),
+            priority: context.priority as u32,
+            version: context.version,
+            tags: context.tags.clone(),
+            active: context.active,
+            approval_state: context.approval_state.as_str().to_string(),
+            visibility: context.visibility.as_string(),
+            schema_version: context.schema_version,
+            created_at: Some(TimestampMessage::from_time(&context.created_at)),
+            updated_at: Some(TimestampMessage::from_time(&context.updated_at)),
+            expires_at: context.expires_at.as_ref().map(TimestampMessage::from_time),
+        })
+    }
+
+    fn into_context(self) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(LLMContext {
+            id: Uuid::from_slice(&self.id)?,
+            session_id: self.session_id,
+            user_id: self.user_id,
+            domain: self.domain,
+            context_data: self.context_data,
+            metadata: self.metadata,
+            created_at: decode_time(self.created_at, self.created_at_nanos)?,
+            updated_at: decode_time(self.updated_at, self.updated_at_nanos)?,
+            expires_at: match (self.expires_at, self.expires_at_nanos) {
+                (Some(time), _) => Some(time.to_time()?),
+                (None, legacy) => legacy.map(DateTime::from_timestamp_nanos),
+            },
+            priority: u8::try_from(self.priority)?,
+            version: self.version,
+            tags: self.tags,
+            active: self.active,
+            // 无法识别的审批状态按草稿处理，需重新审核
+            approval_state: ApprovalState::parse(&self.approval_state).unwrap_or(ApprovalState::Draft),
+            // 无法识别的范围（损坏或来自更新版本的数据）按仅作者可见处理
+            visibility: Visibility::parse(&self.visibility).unwrap_or(Visibility::Private),
+            // 旧消息没有版本字段（解码为0）
+            schema_version: if self.schema_version == 0 { schema_migration::legacy_schema_version() } else { self.schema_version },
+        })
+    }
+}
+
+/// Protobuf编解码器
+pub struct ProtobufCodec;
+
+impl ContextCodec for ProtobufCodec {
+    fn format(&self) -> SerializationFormat {
+        SerializationFormat::Protobuf
+    }
+
+    fn encode(&self, contexts: &[LLMContext]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let message = ContextListMessage {
+            contexts: contexts
+                .iter()
+                .map(ContextMessage::from_context)
+                .collect::<Result<Vec<_>, _>>()?,
+        };
+        Ok(prost::Message::encode_to_vec(&message))
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let message: ContextListMessage = prost::Message::decode(bytes)?;
+        let mut contexts = message
+            .contexts
+            .into_iter()
+            .map(ContextMessage::into_context)
+            .collect::<Result<Vec<_>, _>>()?;
+        schema_migration::upgrade_decoded(&mut contexts)?;
+        Ok(contexts)
+    }
+}
+
+/// 获取指定格式的编解码器
+pub fn codec_for(format: SerializationFormat) -> Arc<dyn ContextCodec> {
+    match format {
+        SerializationFormat::Json => Arc::new(JsonCodec),
+        SerializationFormat::MessagePack => Arc::new(MessagePackCodec),
+        SerializationFormat::Protobuf => Arc::new(ProtobufCodec),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::schema_migration::CURRENT_SCHEMA_VERSION;
+
+    #[test]
+    fn test_codec_round_trip() {
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), "guidelines".to_string());
+        let context = LLMContext {
+            id: Uuid::new_v4(),
+            session_id: "s1".to_string(),
+            user_id: "u1".to_string(),
+            domain: "medical".to_string(),
+            context_data: "Pneumonia treatment involves antibiotics".to_string(),
+            metadata,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            expires_at: Some(Utc::now()),
+            priority: 7,
+            version: 3,
+            tags: vec!["respiratory".to_string()],
+            active: true,
+            approval_state: ApprovalState::InReview,
+            visibility: Visibility::Team("pulmonology".to_string()),
+            schema_version: CURRENT_SCHEMA_VERSION,
+        };
+
+        let mut sizes = Vec::new();
+        for format in [SerializationFormat::Json, SerializationFormat::MessagePack, SerializationFormat::Protobuf] {
+            let codec = codec_for(format);
+            let bytes = codec.encode_one(&context).unwrap();
+            let decoded = codec.decode_one(&bytes).unwrap();
+
+            assert_eq!(decoded.id, context.id);
+            assert_eq!(decoded.metadata, context.metadata);
+            assert_eq!(decoded.created_at, context.created_at);
+            assert_eq!(decoded.expires_at, context.expires_at);
+            assert_eq!(decoded.priority, 7);
+            assert_eq!(decoded.tags, context.tags);
+            assert_eq!(decoded.visibility, context.visibility);
+            assert_eq!(decoded.schema_version, CURRENT_SCHEMA_VERSION);
+            assert_eq!(SerializationFormat::from_content_type(format.content_type()), Some(format));
+            sizes.push(bytes.len());
+        }
+
+        // 二进制格式比JSON紧凑
+        assert!(sizes[1] < sizes[0]);
+        assert!(sizes[2] < sizes[0]);
+        assert!(ProtobufCodec.decode(b"\xff\xff").is_err());
+
+        // 无法识别的可见范围和审批状态不会放宽为全局可见或已批准
+        let mut message = ContextMessage::from_context(&context).unwrap();
+        message.visibility = "department:radiology".to_string();
+        message.approval_state = "published".to_string();
+        let decoded = message.into_context().unwrap();
+        assert_eq!(decoded.visibility, Visibility::Private);
+        assert_eq!(decoded.approval_state, ApprovalState::Draft);
+
+        // 超出纳秒时间戳范围（2262年以后）的时间照常往返；只有旧版纳秒字段的消息仍可解码
+        let mut far_future = context.clone();
+        far_future.expires_at = Some("3000-01-01T00:00:00.123456789Z".parse().unwrap());
+        let decoded = ProtobufCodec.decode_one(&ProtobufCodec.encode_one(&far_future).unwrap()).unwrap();
+        assert_eq!(decoded.expires_at, far_future.expires_at);
+        let mut legacy = ContextMessage::from_context(&context).unwrap();
+        (legacy.created_at, legacy.updated_at, legacy.expires_at) = (None, None, None);
+        let decoded = legacy.into_context().unwrap();
+        assert_eq!((decoded.created_at, decoded.expires_at), (context.created_at, context.expires_at));
+    }
+
+    #[tokio::test]
+    async fn test_manager_export_import() {
+        use crate::context::llm_context::ContextManager;
+
+        let source = ContextManager::new(10, 3600);
+        source
+            .create_context("s1".to_string(), "u1".to_string(), "legal".to_string(), "Contract law".to_string(), 6)
+            .await
+            .unwrap();
+        let bytes = source.export_contexts(&MessagePackCodec).await.unwrap();
+
+        let target = ContextManager::new(10, 3600);
+        assert_eq!(target.import_contexts(&MessagePackCodec, &bytes).await.unwrap(), 1);
+        assert_eq!(target.get_domain_contexts("legal").await.len(), 1);
+        assert_eq!(target.get_session_contexts("s1").await[0].context_data, "Contract law");
+    }
+}
@@ -0,0 +1,278 @@
+//! 存储整理 - 分批清理索引中的悬挂ID和空键、收缩存储容量、丢弃超过保留期的已删除上下文历史（墓碑）并重建布隆过滤器，
+//! 批次之间让出锁并限速，可周期运行。上下文存储是内存存储，没有WAL或磁盘文件，整理的对象是内存中的表和版本历史
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use crate::context::llm_context::ContextManager;
+use crate::maintenance::MaintenanceMode;
+
+/// 上下文管理器的二级索引
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexKind {
+    Session,
+    User,
+    Domain,
+}
+
+/// 整理配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionConfig {
+    pub batch_size: usize,              // 每批处理的索引键数量（每批持有一次写锁）
+    pub pause_between_batches_ms: u64,  // 批次间隔，限制整理对线上请求的影响
+    pub interval_seconds: u64,          // 周期运行的间隔
+    pub tombstone_retention_seconds: u64, // 已删除或过期的上下文在版本历史中保留的时长，超过后丢弃
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            pause_between_batches_ms: 10,
+            interval_seconds: 86_400, // 每天
+            tombstone_retention_seconds: 30 * 86_400,
+        }
+    }
+}
+
+/// 整理阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CompactionPhase {
+    Idle,
+    Indexes(IndexKind),     // 整理索引
+    Storage,                // 收缩存储容量
+    History,                // 丢弃超过保留期的墓碑
+    BloomFilter,            // 重建布隆过滤器
+}
+
+/// 整理进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompactionProgress {
+    pub phase: CompactionPhase,
+    pub processed_keys: usize,
+    pub total_keys: usize,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// 一次整理的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactionReport {
+    pub keys_scanned: usize,
+    pub dangling_ids_removed: usize,    // 指向已不存在上下文的索引项
+    pub empty_keys_removed: usize,      // 不再有上下文的会话/用户/领域键
+    pub storage_capacity_before: usize,
+    pub storage_capacity_after: usize,
+    pub tombstones_dropped: usize,      // 丢弃的已删除上下文历史
+    pub bloom_rebuilt: bool,
+    pub duration_ms: u64,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// 存储整理任务
+pub struct CompactionJob {
+    context_manager: Arc<ContextManager>,
+    config: Arc<RwLock<CompactionConfig>>,
+    progress: Arc<RwLock<CompactionProgress>>,
+    last_report: Arc<RwLock<Option<CompactionReport>>>,
+    running: AtomicBool,
+    maintenance: Option<Arc<MaintenanceMode>>,
+}
+
+impl CompactionJob {
+    /// 创建整理任务
+    pub fn new(context_manager: Arc<ContextManager>, config: CompactionConfig) -> Self {
+        Self {
+            context_manager,
+            config: Arc::new(RwLock::new(config)),
+            progress: Arc::new(RwLock::new(CompactionProgress {
+                phase: CompactionPhase::Idle,
+                processed_keys: 0,
+                total_keys: 0,
+                started_at: None,
+            })),
+            last_report: Arc::new(RwLock::new(None)),
+            running: AtomicBool::new(false),
+            maintenance: None,
+        }
+    }
+
+    /// 关联维护模式，维护期间暂停定期整理
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 执行一次整理；已有整理在运行时返回错误
+    pub async fn run(&self) -> Result<CompactionReport, Box<dyn std::error::Error + Send + Sync>> {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return Err("Compaction already running".into());
+        }
+        let report = self.run_phases().await;
+        *self.progress.write().await = CompactionProgress {
+            phase: CompactionPhase::Idle,
+            processed_keys: 0,
+            total_keys: 0,
+            started_at: None,
+        };
+        *self.last_report.write().await = Some(report.clone());
+        self.running.store(false, Ordering::SeqCst);
+        Ok(report)
+    }
+
+    async fn run_phases(&self) -> CompactionReport {
+        let config = self.config.read().await.clone();
+        let started = Instant::now();
+        let pause = std::time::Duration::from_millis(config.pause_between_batches_ms);
+        let mut report = CompactionReport::default();
+
+        let kinds = [IndexKind::Session, IndexKind::User, IndexKind::Domain];
+        let mut keys_by_kind = Vec::new();
+        for kind in kinds {
+            keys_by_kind.push((kind, self.context_manager.index_keys(kind).await));
+        }
+        {
+            let mut progress = self.progress.write().await;
+            progress.started_at = Some(Utc::now());
+            progress.processed_keys = 0;
+            progress.total_keys = keys_by_kind.iter().map(|(_, keys)| keys.len()).sum();
+        }
+
+        for (kind, keys) in keys_by_kind {
+            self.progress.write().await.phase = CompactionPhase::Indexes(kind);
+            for batch in keys.chunks(config.batch_size.max(1)) {
+                let (dangling, empty) = self.context_manager.compact_index_keys(kind, batch).await;
+                report.dangling_ids_removed += dangling;
+                report.empty_keys_removed += empty;
+                report.keys_scanned += batch.len();
+                self.progress.write().await.processed_keys += batch.len();
+                if !pause.is_zero() {
+                    tokio::time::sleep(pause).await;
+                }
+            }
+        }
+
+        self.progress.write().await.phase = CompactionPhase::Storage;
+        let (before, after) = self.context_manager.shrink_storage().await;
+        report.storage_capacity_before = before;
+        report.storage_capacity_after = after;
+
+        if let Some(history) = self.context_manager.get_version_history() {
+            self.progress.write().await.phase = CompactionPhase::History;
+            let cutoff = i64::try_from(config.tombstone_retention_seconds)
+                .ok()
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|retention| Utc::now().checked_sub_signed(retention));
+            if let Some(cutoff) = cutoff {
+                report.tombstones_dropped = history.drop_removed_before(cutoff).await;
+            }
+        }
+
+        self.progress.write().await.phase = CompactionPhase::BloomFilter;
+        if self.context_manager.get_bloom_stats().await.is_some() {
+            self.context_manager.rebuild_bloom_filter().await;
+            report.bloom_rebuilt = true;
+        }
+
+        report.duration_ms = started.elapsed().as_millis() as u64;
+        report.finished_at = Some(Utc::now());
+        log::info!(
+            "Compaction finished in {}ms: {} dangling ids, {} empty keys and {} tombstones removed",
+            report.duration_ms, report.dangling_ids_removed, report.empty_keys_removed, report.tombstones_dropped
+        );
+        report
+    }
+
+    /// 启动后台整理任务，按配置的间隔周期运行
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let interval = self.config.read().await.interval_seconds.max(1);
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+                if let Some(ref maintenance) = self.maintenance {
+                    maintenance.wait_until_inactive().await;
+                }
+                if let Err(e) = self.run().await {
+                    log::warn!("Scheduled compaction skipped: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 获取当前进度
+    pub async fn get_progress(&self) -> CompactionProgress {
+        self.progress.read().await.clone()
+    }
+
+    /// 获取最近一次整理的结果
+    pub async fn get_last_report(&self) -> Option<CompactionReport> {
+        self.last_report.read().await.clone()
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: CompactionConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> CompactionConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::bloom::BloomFilterConfig;
+    use crate::context::version_history::VersionHistory;
+
+    #[tokio::test]
+    async fn test_compaction_removes_empty_index_keys() {
+        let history = Arc::new(VersionHistory::new());
+        let manager = Arc::new(
+            ContextManager::new(10, 3600)
+                .with_bloom_filter(BloomFilterConfig::default())
+                .with_version_history(history.clone()),
+        );
+        let mut ids = Vec::new();
+        for i in 0..20 {
+            let context = manager
+                .create_context(format!("s{}", i), "u1".to_string(), "medical".to_string(), format!("note {}", i), 5)
+                .await
+                .unwrap();
+            ids.push(context.id);
+        }
+        for id in &ids[..15] {
+            manager.delete_context(*id).await.unwrap();
+        }
+        assert_eq!(manager.index_keys(IndexKind::Session).await.len(), 20);
+
+        let job = Arc::new(CompactionJob::new(manager.clone(), CompactionConfig {
+            batch_size: 4,
+            pause_between_batches_ms: 5,
+            interval_seconds: 3600,
+            tombstone_retention_seconds: 0,
+        }));
+        let runner = job.clone();
+        let handle = tokio::spawn(async move { runner.run().await });
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        assert!(matches!(job.get_progress().await.phase, CompactionPhase::Indexes(_)));
+        assert!(job.run().await.is_err());
+
+        let report = handle.await.unwrap().unwrap();
+        assert_eq!(report.keys_scanned, 22);
+        assert_eq!(report.empty_keys_removed, 15);
+        assert!(report.bloom_rebuilt);
+        assert_eq!(report.tombstones_dropped, 15);
+        assert!(history.get_revisions(ids[0]).await.is_empty());
+        assert_eq!(history.get_revisions(ids[19]).await.len(), 1);
+        assert_eq!(manager.index_keys(IndexKind::Session).await.len(), 5);
+        assert_eq!(manager.get_session_contexts("s19").await.len(), 1);
+        assert_eq!(job.get_progress().await.phase, CompactionPhase::Idle);
+        assert!(job.get_last_report().await.is_some());
+    }
+}
@@ -0,0 +1,82 @@
+//! 读一致性级别 - 副本部署中由调用方按请求选择：最终一致读直接使用本地快照（快，可能略旧），
+//! 强一致读先与主实例同步，保证看到请求到达前主实例上的全部写入；所用级别记录在请求来源信息中
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// 读一致性级别
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsistencyLevel {
+    #[default]
+    Eventual,   // 读本地快照，落后主实例最多一个同步间隔
+    Strong,     // 读主实例的最新状态（副本上先同步；主实例上本地读即为强一致）
+}
+
+impl ConsistencyLevel {
+    /// 按名称解析（不区分大小写）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "eventual" => Some(ConsistencyLevel::Eventual),
+            "strong" => Some(ConsistencyLevel::Strong),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::context::replica::{PrimarySnapshotSource, ReplicaConfig, ReplicaSync};
+    use crate::processing::concurrent_processor::{RequestOptions, RequestProcessor};
+    use crate::processing::feedback::FeedbackStore;
+    use crate::selection::async_context_selector::ContextSelector;
+
+    #[tokio::test]
+    async fn test_strong_reads_see_primary_writes_on_replica() {
+        assert_eq!(ConsistencyLevel::parse(" Strong "), Some(ConsistencyLevel::Strong));
+        assert_eq!(ConsistencyLevel::parse("linearizable"), None);
+
+        let primary = Arc::new(ContextManager::new(10, 3600));
+        let replica = Arc::new(ContextManager::new(10, 3600));
+        let sync = Arc::new(ReplicaSync::new(replica.clone(), Arc::new(PrimarySnapshotSource::new(primary.clone())), ReplicaConfig::default()));
+        sync.sync_once().await.unwrap();
+        let feedback = Arc::new(FeedbackStore::default());
+        let processor = RequestProcessor::new(replica.clone(), Arc::new(ContextSelector::new(replica.clone())))
+            .with_replica(sync.clone())
+            .with_feedback_store(feedback.clone());
+
+        // 同步之后主实例上的新写入
+        let written = primary
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia is treated with antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        let request = |consistency: ConsistencyLevel| {
+            let options = RequestOptions { consistency: Some(consistency), ..RequestOptions::default() };
+            processor.process_request_with_options("u1".to_string(), "s2".to_string(), "pneumonia".to_string(), "medical".to_string(), options)
+        };
+
+        // 最终一致读使用本地快照，看不到新写入
+        let eventual = request(ConsistencyLevel::Eventual).await.unwrap();
+        assert!(eventual.selected_contexts.is_empty());
+        let provenance = feedback.get_provenance(eventual.request_id).await.unwrap();
+        assert_eq!(provenance.consistency, ConsistencyLevel::Eventual);
+        let stale_snapshot = provenance.snapshot_at.unwrap();
+
+        // 强一致读先同步，看到新写入，并记录更新后的快照时间
+        let strong = request(ConsistencyLevel::Strong).await.unwrap();
+        assert_eq!(strong.selected_contexts[0].id, written.id);
+        let provenance = feedback.get_provenance(strong.request_id).await.unwrap();
+        assert_eq!(provenance.consistency, ConsistencyLevel::Strong);
+        assert!(provenance.snapshot_at.unwrap() > stale_snapshot);
+        assert_eq!(sync.get_status().await.syncs, 2);
+
+        // 其间已有更新的同步时复用，不再重复拉取
+        let before = chrono::Utc::now();
+        sync.sync_once().await.unwrap();
+        sync.sync_since(before).await.unwrap();
+        assert_eq!(sync.get_status().await.syncs, 3);
+    }
+}
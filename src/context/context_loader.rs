@@ -1,13 +1,34 @@
 use crate::context::llm_context::{LLMContext as Context, ContextManager};
-use crate::domain::domain_classifier::Domain;
+use crate::context::schema_migration::CURRENT_SCHEMA_VERSION;
+use crate::domain::domain_classifier::{Domain, DomainClassifier};
+use crate::selection::embedding::{Embedder, HashingEmbedder};
+use crate::selection::scoring_cache::ScoringCache;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use crate::utils::async_runtime::{fan_out, FanOutError};
+
+/// 预热时同时计算的上下文向量数
+const PRELOAD_EMBEDDING_CONCURRENCY: usize = 8;
+
+/// 预热结果
+#[derive(Debug, Clone)]
+pub struct PreloadResult {
+    pub domain: String,             // 预热的领域（请求指定的或查询被分类到的领域）
+    pub context_count: usize,       // 领域上下文数量
+    pub cache_hit: bool,            // 领域上下文是否已在缓存中
+    pub embedded_count: usize,      // 预计算向量的上下文数量
+}
 
 /// 上下文加载器 - 负责根据领域动态加载相应的上下文信息
 pub struct ContextLoader {
     context_manager: Arc<ContextManager>,
     domain_context_cache: Arc<RwLock<HashMap<String, Vec<Context>>>>,
+    /// 可选的评分缓存，预热时一并计算上下文向量
+    scoring_cache: Option<Arc<ScoringCache>>,
+    /// 预热向量使用的向量化器（应与上下文选择器一致）
+    embedder: Arc<dyn Embedder>,
 }
 
 impl ContextLoader {
@@ -16,7 +37,67 @@ impl ContextLoader {
         Self {
             context_manager,
             domain_context_cache: Arc::new(RwLock::new(HashMap::new())),
+            scoring_cache: None,
+            embedder: Arc::new(HashingEmbedder::default()),
+        }
+    }
+
+    /// 关联评分缓存和向量化器，预热时同时计算领域上下文的向量
+    pub fn with_scoring_cache(mut self, scoring_cache: Arc<ScoringCache>, embedder: Arc<dyn Embedder>) -> Self {
+        self.scoring_cache = Some(scoring_cache);
+        self.embedder = embedder;
+        self
+    }
+
+    /// 根据查询预热上下文：分类查询所属领域，加载并缓存该领域的上下文及其向量
+    pub async fn preload_for_query(&self, query: &str) -> Result<PreloadResult, Box<dyn std::error::Error + Send + Sync>> {
+        let domain = DomainClassifier::classify_domain_async(query).await;
+        self.preload_for_domain(&domain.to_string()).await
+    }
+
+    /// 预热指定领域：从上下文管理器加载该领域的上下文（同时回填管理器的读穿缓存），缓存并计算其向量
+    pub async fn preload_for_domain(&self, domain: &str) -> Result<PreloadResult, Box<dyn std::error::Error + Send + Sync>> {
+        let contexts = self.context_manager.get_domain_contexts(domain).await;
+        let cache_hit = self
+            .domain_context_cache
+            .write()
+            .await
+            .insert(domain.to_string(), contexts.clone())
+            .is_some();
+
+        // 并发计算各上下文的向量，任一失败时取消其余计算
+        let mut embedded_count = 0;
+        if let Some(ref scoring_cache) = self.scoring_cache {
+            let outcomes = fan_out(contexts.iter(), PRELOAD_EMBEDDING_CONCURRENCY, |_| true, |ctx| {
+                scoring_cache.get_embedding(ctx, self.embedder.as_ref())
+            })
+            .await;
+            embedded_count = FanOutError::collect(outcomes)?.len();
         }
+
+        Ok(PreloadResult {
+            domain: domain.to_string(),
+            context_count: contexts.len(),
+            cache_hit,
+            embedded_count,
+        })
+    }
+
+    /// 在后台任务中预热，便于与速率限制、校验等阶段并行执行；指定领域时直接预热该领域，否则按查询分类
+    pub fn spawn_preload(
+        self: &Arc<Self>,
+        query: &str,
+        domain: Option<&str>,
+    ) -> JoinHandle<Result<PreloadResult, Box<dyn std::error::Error + Send + Sync>>> {
+        let loader = self.clone();
+        let query = query.to_string();
+        let domain = domain.map(str::to_string);
+        tokio::spawn(async move {
+            match domain {
+                Some(domain) => loader.preload_for_domain(&domain).await,
+                None => loader.preload_for_query(&query).await,
+            }
+        })
     }
 
     /// 为特定领域加载上下文
@@ -41,6 +122,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["treatment".to_string(), "healthcare".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                     Context {
                         id: uuid::Uuid::new_v4(),
@@ -56,6 +140,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["diagnosis".to_string(), "symptoms".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                 ]
             },
@@ -75,6 +162,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["precedent".to_string(), "case".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                     Context {
                         id: uuid::Uuid::new_v4(),
@@ -90,6 +180,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["contract".to_string(), "agreement".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                 ]
             },
@@ -109,6 +202,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["development".to_string(), "best-practices".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                     Context {
                         id: uuid::Uuid::new_v4(),
@@ -124,6 +220,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["algorithm".to_string(), "design".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                 ]
             },
@@ -143,6 +242,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["pedagogy".to_string(), "teaching".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                     Context {
                         id: uuid::Uuid::new_v4(),
@@ -158,6 +260,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["curriculum".to_string(), "strategy".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                 ]
             },
@@ -177,6 +282,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["investment".to_string(), "analysis".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                     Context {
                         id: uuid::Uuid::new_v4(),
@@ -192,6 +300,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["risk".to_string(), "management".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                 ]
             },
@@ -211,6 +322,9 @@ impl ContextLoader {
                         version: 1,
                         tags: vec!["general".to_string(), "facts".to_string()],
                         active: true,
+                        approval_state: Default::default(),
+                        visibility: Default::default(),
+                        schema_version: CURRENT_SCHEMA_VERSION,
                     },
                 ]
             },
@@ -275,4 +389,42 @@ mod tests {
             assert_eq!(context.domain, "technical");
         }
     }
+
+    #[tokio::test]
+    async fn test_preload_for_query() {
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let stored = context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia guidelines".to_string(), 5)
+            .await
+            .unwrap();
+        context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "legal".to_string(), "Contract law".to_string(), 5)
+            .await
+            .unwrap();
+        let scoring_cache = Arc::new(ScoringCache::default());
+        let loader = Arc::new(
+            ContextLoader::new(context_manager)
+                .with_scoring_cache(scoring_cache.clone(), Arc::new(HashingEmbedder::default())),
+        );
+
+        // 预热存储中的真实上下文
+        let first = loader.spawn_preload("pneumonia treatment options", None).await.unwrap().unwrap();
+        assert_eq!(first.domain, "medical");
+        assert!(!first.cache_hit);
+        assert_eq!(first.context_count, 1);
+        assert_eq!(first.embedded_count, first.context_count);
+        let cached = loader.get_cached_context_for_domain("medical").await.unwrap();
+        assert_eq!(cached.iter().map(|ctx| ctx.id).collect::<Vec<_>>(), vec![stored.id]);
+
+        // 请求指定的领域优先于查询分类
+        let legal = loader.spawn_preload("pneumonia treatment options", Some("legal")).await.unwrap().unwrap();
+        assert_eq!(legal.domain, "legal");
+
+        // 再次预热命中领域缓存和向量缓存
+        let second = loader.preload_for_query("pneumonia treatment options").await.unwrap();
+        assert!(second.cache_hit);
+        let stats = scoring_cache.get_stats().await;
+        assert_eq!(stats.embedding_misses, (first.context_count + legal.context_count) as u64);
+        assert_eq!(stats.embedding_hits, second.context_count as u64);
+    }
 }
\ No newline at end of file
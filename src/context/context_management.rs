@@ -5,6 +5,7 @@ use uuid::Uuid;
 use anyhow;
 use crate::utils::web_search::{WebSearchClient, SearchResult};
 use crate::utils::intelligent_search::IntelligentSearchClient;
+use crate::utils::search_options::SearchOptions;
 
 /// 上下文结构体 - 用于存储特定领域的上下文信息
 #[derive(Debug, Clone)]
@@ -28,6 +29,12 @@ pub struct ContextManager {
     intelligent_search_client: Option<Arc<IntelligentSearchClient>>, // 可选的智能搜索客户端
 }
 
+impl Default for ContextManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ContextManager {
     /// 创建新的上下文管理器
     pub fn new() -> Self {
@@ -139,7 +146,7 @@ impl ContextManager {
     /// 使用网络搜索获取实时信息并创建上下文
     pub async fn create_context_from_web_search(&self, query: &str, domain: &str) -> Result<Context, Box<dyn std::error::Error>> {
         if let Some(ref search_client) = self.web_search_client {
-            let search_results = search_client.search_with_relevance_scoring(query, Some(5)).await
+            let search_results = search_client.search_with_relevance_scoring(query, Some(5), &SearchOptions::for_domain(domain)).await
                 .map_err(|e| anyhow::anyhow!("Web search failed: {:?}", e))?;
 
             // Format search results into context content
@@ -170,9 +177,9 @@ impl ContextManager {
     }
 
     /// 执行网络搜索并返回结果
-    pub async fn web_search(&self, query: &str) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+    pub async fn web_search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
         if let Some(ref search_client) = self.web_search_client {
-            let results = search_client.search_with_relevance_scoring(query, Some(5)).await
+            let results = search_client.search_with_relevance_scoring(query, Some(5), options).await
                 .map_err(|e| anyhow::anyhow!("Web search failed: {:?}", e))?;
             Ok(results)
         } else {
@@ -181,9 +188,9 @@ impl ContextManager {
     }
 
     /// 执行聚合搜索（多个查询）
-    pub async fn aggregate_web_search(&self, queries: &[&str]) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+    pub async fn aggregate_web_search(&self, queries: &[&str], options: &SearchOptions) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
         if let Some(ref search_client) = self.web_search_client {
-            let results = search_client.aggregate_search(queries, 10).await
+            let results = search_client.aggregate_search(queries, 10, options).await
                 .map_err(|e| anyhow::anyhow!("Aggregate search failed: {:?}", e))?;
             Ok(results)
         } else {
@@ -194,7 +201,7 @@ impl ContextManager {
     /// 使用智能搜索获取实时信息并创建上下文
     pub async fn create_context_from_intelligent_search(&self, query: &str, domain: &str) -> Result<Context, Box<dyn std::error::Error>> {
         if let Some(ref search_client) = self.intelligent_search_client {
-            let search_results = search_client.intelligent_search(query, Some(5)).await
+            let search_results = search_client.intelligent_search(query, Some(5), &SearchOptions::for_domain(domain)).await
                 .map_err(|e| anyhow::anyhow!("Intelligent search failed: {:?}", e))?;
 
             // Format search results into context content
@@ -225,9 +232,9 @@ impl ContextManager {
     }
 
     /// 执行智能搜索（自动路由到合适的搜索引擎）
-    pub async fn intelligent_search(&self, query: &str) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
+    pub async fn intelligent_search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>, Box<dyn std::error::Error>> {
         if let Some(ref search_client) = self.intelligent_search_client {
-            let results = search_client.intelligent_search(query, Some(5)).await
+            let results = search_client.intelligent_search(query, Some(5), options).await
                 .map_err(|e| anyhow::anyhow!("Intelligent search failed: {:?}", e))?;
             Ok(results)
         } else {
@@ -298,7 +305,7 @@ mod tests {
         // Test that web search client may not be available (due to missing API key)
         if manager.web_search_client.is_some() {
             // If web search is available, test the functionality
-            match manager.web_search("test query").await {
+            match manager.web_search("test query", &SearchOptions::default()).await {
                 Ok(results) => {
                     println!("Successfully got {} search results", results.len());
                     assert!(results.len() <= 5); // Should respect the default limit
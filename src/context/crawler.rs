@@ -0,0 +1,705 @@
+//! 网页爬取 - 遵守robots.txt、按主机限速、限制深度和页面大小，将文档站点抓取为领域上下文
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use url::Url;
+use uuid::Uuid;
+use crate::context::ingestion::{TITLE_METADATA_KEY, URL_METADATA_KEY};
+use crate::context::llm_context::ContextManager;
+use crate::selection::post_filters::SOURCE_METADATA_KEY;
+use crate::utils::outbound_scheduler::{CallPriority, OutboundScheduler, CRAWLER_PROVIDER};
+
+lazy_static! {
+    static ref LINK_RE: Regex = Regex::new(r#"(?i)<a\s[^>]*?href\s*=\s*["']([^"']+)["']"#).unwrap();
+    static ref TITLE_RE: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+    static ref SCRIPT_RE: Regex = Regex::new(r"(?is)<script\b.*?</script>").unwrap();
+    static ref STYLE_RE: Regex = Regex::new(r"(?is)<style\b.*?</style>").unwrap();
+    static ref TAG_RE: Regex = Regex::new(r"(?s)<[^>]*>").unwrap();
+    static ref SPACE_RE: Regex = Regex::new(r"\s+").unwrap();
+}
+
+/// 爬虫配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawlerConfig {
+    pub user_agent: String,
+    pub max_depth: usize,           // 从起始页开始的最大链接深度
+    pub max_pages: usize,           // 单次爬取的最大页面数
+    pub max_page_bytes: usize,      // 单个页面的最大字节数，超出则跳过
+    pub per_host_delay_ms: u64,     // 同一主机两次请求的最小间隔（robots.txt的Crawl-delay更大时以其为准）
+    #[serde(default = "default_max_crawl_delay_ms")]
+    pub max_crawl_delay_ms: u64,    // robots.txt的Crawl-delay超出该值时按该值等待
+    pub request_timeout_ms: u64,
+    pub respect_robots: bool,       // 是否遵守robots.txt
+    pub same_host_only: bool,       // 只跟随起始主机内的链接（包括重定向）
+    pub robots_cache_ttl_seconds: u64, // robots规则的缓存时间
+}
+
+fn default_max_crawl_delay_ms() -> u64 {
+    60_000
+}
+
+impl Default for CrawlerConfig {
+    fn default() -> Self {
+        Self {
+            user_agent: "penlai-crawler".to_string(),
+            max_depth: 2,
+            max_pages: 100,
+            max_page_bytes: 1024 * 1024,
+            per_host_delay_ms: 1000,
+            max_crawl_delay_ms: default_max_crawl_delay_ms(),
+            request_timeout_ms: 10000,
+            respect_robots: true,
+            same_host_only: true,
+            robots_cache_ttl_seconds: 86400,
+        }
+    }
+}
+
+/// robots.txt中适用于本爬虫的规则
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsRules {
+    pub allow: Vec<String>,
+    pub disallow: Vec<String>,
+    pub crawl_delay: Option<f64>,   // 秒，只接受有限的非负数
+}
+
+impl RobotsRules {
+    /// 禁止抓取全部路径（robots.txt暂时无法获取时使用）
+    pub fn disallow_all() -> Self {
+        Self {
+            disallow: vec!["/".to_string()],
+            ..Self::default()
+        }
+    }
+
+    /// 解析robots.txt，优先使用匹配User-agent的规则组，否则使用`*`规则组
+    pub fn parse(body: &str, user_agent: &str) -> Self {
+        let agent = user_agent.to_lowercase();
+        let mut groups: Vec<(Vec<String>, RobotsRules)> = Vec::new();
+        let mut in_rules = false;
+
+        for line in body.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_lowercase().as_str() {
+                "user-agent" => {
+                    // 规则之后出现的User-agent开始新的规则组
+                    if in_rules || groups.is_empty() {
+                        groups.push((Vec::new(), RobotsRules::default()));
+                        in_rules = false;
+                    }
+                    if let Some(group) = groups.last_mut() {
+                        group.0.push(value.to_lowercase());
+                    }
+                }
+                "allow" | "disallow" | "crawl-delay" => {
+                    in_rules = true;
+                    let Some((_, rules)) = groups.last_mut() else {
+                        continue;
+                    };
+                    match key.trim().to_lowercase().as_str() {
+                        "allow" if !value.is_empty() => rules.allow.push(value.to_string()),
+                        "disallow" if !value.is_empty() => rules.disallow.push(value.to_string()),
+                        "crawl-delay" => {
+                            rules.crawl_delay = value.parse::<f64>().ok().filter(|delay| delay.is_finite() && *delay >= 0.0)
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let specific = groups
+            .iter()
+            .find(|(agents, _)| agents.iter().any(|a| a != "*" && agent.contains(a.as_str())));
+        let wildcard = groups.iter().find(|(agents, _)| agents.iter().any(|a| a == "*"));
+        specific.or(wildcard).map(|(_, rules)| rules.clone()).unwrap_or_default()
+    }
+
+    /// 路径（含查询字符串）是否允许抓取：最长匹配的规则生效，长度相同时Allow优先
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let longest = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter(|p| robots_match(p, path))
+                .map(|p| p.len())
+                .max()
+        };
+        match (longest(&self.allow), longest(&self.disallow)) {
+            (_, None) => true,
+            (None, Some(_)) => false,
+            (Some(allow), Some(disallow)) => allow >= disallow,
+        }
+    }
+}
+
+/// URL中参与robots匹配的部分：路径加查询字符串
+fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// 前缀匹配，支持`*`通配符和`$`结尾锚定
+fn robots_match(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    if !pattern.contains('*') && !anchored {
+        return path.starts_with(pattern);
+    }
+    let body: Vec<String> = pattern.split('*').map(regex::escape).collect();
+    let expr = format!("^{}{}", body.join(".*"), if anchored { "$" } else { "" });
+    Regex::new(&expr).map(|re| re.is_match(path)).unwrap_or(false)
+}
+
+/// 爬取队列 - 广度优先，按规范化URL去重
+#[derive(Debug, Default)]
+pub struct CrawlQueue {
+    queue: VecDeque<(Url, usize)>,
+    seen: HashSet<String>,
+}
+
+impl CrawlQueue {
+    /// 创建空队列
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入队列，已见过的URL返回false
+    pub fn push(&mut self, mut url: Url, depth: usize) -> bool {
+        url.set_fragment(None);
+        if !self.seen.insert(url.to_string()) {
+            return false;
+        }
+        self.queue.push_back((url, depth));
+        true
+    }
+
+    /// 取出下一个待抓取的URL及其深度
+    pub fn pop(&mut self) -> Option<(Url, usize)> {
+        self.queue.pop_front()
+    }
+
+    /// 待抓取数量
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// 是否没有待抓取的URL
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+/// 抓取到的页面
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrawledPage {
+    pub url: String,
+    pub title: Option<String>,
+    pub text: String,       // 去除标签后的正文
+    pub depth: usize,
+    pub bytes: usize,       // 原始页面大小
+}
+
+/// 爬取统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CrawlReport {
+    pub pages_fetched: usize,
+    pub skipped_robots: usize,      // robots.txt禁止
+    pub skipped_too_large: usize,   // 超出页面大小限制
+    pub skipped_non_html: usize,    // 非HTML内容
+    pub duplicates: usize,          // 重复链接
+    pub errors: Vec<String>,        // 请求失败的URL及原因
+    pub context_ids: Vec<Uuid>,     // 写入的上下文（crawl_into时）
+}
+
+enum FetchOutcome {
+    Page { url: Url, html: String },    // 跟随重定向后的最终URL和页面内容
+    TooLarge,
+    NonHtml,
+    Disallowed,                         // 重定向目标被robots.txt禁止
+}
+
+/// 最多跟随的重定向次数
+const MAX_REDIRECTS: usize = 5;
+
+/// robots.txt暂时无法获取（5xx、超时）时禁止抓取的缓存时间，之后重新获取
+const ROBOTS_UNAVAILABLE_TTL: Duration = Duration::from_secs(300);
+
+/// 网页爬虫
+pub struct Crawler {
+    client: reqwest::Client,
+    config: Arc<RwLock<CrawlerConfig>>,
+    robots: Arc<RwLock<HashMap<String, (RobotsRules, Instant)>>>, // 按主机缓存的robots规则及过期时间
+    last_request: Arc<RwLock<HashMap<String, Instant>>>,    // 按主机记录的下次允许请求时间
+    scheduler: Option<Arc<OutboundScheduler>>,              // 可选的外呼调度器，抓取以后台优先级等待配额
+}
+
+impl Default for Crawler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crawler {
+    /// 使用默认配置创建爬虫
+    pub fn new() -> Self {
+        Self::with_config(CrawlerConfig::default())
+    }
+
+    /// 使用指定配置创建爬虫
+    pub fn with_config(config: CrawlerConfig) -> Self {
+        // 不自动跟随重定向，每一跳都重新检查robots规则和主机限制
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        Self {
+            client,
+            config: Arc::new(RwLock::new(config)),
+            robots: Arc::new(RwLock::new(HashMap::new())),
+            last_request: Arc::new(RwLock::new(HashMap::new())),
+            scheduler: None,
+        }
+    }
+
+    /// 关联外呼调度器，每个页面抓取前以后台优先级等待配额
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 从起始URL开始爬取，返回抓取到的页面和统计
+    pub async fn crawl(
+        &self,
+        start_url: &str,
+    ) -> Result<(Vec<CrawledPage>, CrawlReport), Box<dyn std::error::Error + Send + Sync>> {
+        let config = self.config.read().await.clone();
+        let start = Url::parse(start_url)?;
+        let start_host = start.host_str().ok_or("Start URL has no host")?.to_string();
+
+        let mut queue = CrawlQueue::new();
+        queue.push(start, 0);
+        let mut pages = Vec::new();
+        let mut report = CrawlReport::default();
+
+        while let Some((url, depth)) = queue.pop() {
+            if pages.len() >= config.max_pages {
+                break;
+            }
+            let Some(host) = url.host_str().map(|h| h.to_string()) else {
+                continue;
+            };
+
+            let rules = if config.respect_robots {
+                self.robots_for(&url, &config).await
+            } else {
+                RobotsRules::default()
+            };
+            if !rules.is_allowed(&robots_path(&url)) {
+                report.skipped_robots += 1;
+                continue;
+            }
+
+            self.throttle(&host, Self::host_delay(&rules, &config)).await;
+            if let Some(ref scheduler) = self.scheduler {
+                if let Err(e) = scheduler.acquire(CRAWLER_PROVIDER, CallPriority::Background).await {
+                    report.errors.push(format!("{}: {}", url, e));
+                    continue;
+                }
+            }
+
+            let (url, html) = match self.fetch(&url, &config, &start_host).await {
+                Ok(FetchOutcome::Page { url, html }) => (url, html),
+                Ok(FetchOutcome::Disallowed) => {
+                    report.skipped_robots += 1;
+                    continue;
+                }
+                Ok(FetchOutcome::TooLarge) => {
+                    report.skipped_too_large += 1;
+                    continue;
+                }
+                Ok(FetchOutcome::NonHtml) => {
+                    report.skipped_non_html += 1;
+                    continue;
+                }
+                Err(e) => {
+                    report.errors.push(format!("{}: {}", url, e));
+                    continue;
+                }
+            };
+
+            if depth < config.max_depth {
+                for link in extract_links(&url, &html) {
+                    if config.same_host_only && link.host_str() != Some(start_host.as_str()) {
+                        continue;
+                    }
+                    if !queue.push(link, depth + 1) {
+                        report.duplicates += 1;
+                    }
+                }
+            }
+
+            report.pages_fetched += 1;
+            pages.push(CrawledPage {
+                url: url.to_string(),
+                title: extract_title(&html),
+                text: extract_text(&html),
+                depth,
+                bytes: html.len(),
+            });
+        }
+
+        Ok((pages, report))
+    }
+
+    /// 爬取站点并将每个页面写入指定领域的上下文
+    pub async fn crawl_into(
+        &self,
+        manager: &ContextManager,
+        start_url: &str,
+        session_id: &str,
+        user_id: &str,
+        domain: &str,
+        priority: u8,
+    ) -> Result<CrawlReport, Box<dyn std::error::Error + Send + Sync>> {
+        let (pages, mut report) = self.crawl(start_url).await?;
+        for page in pages {
+            if page.text.is_empty() {
+                continue;
+            }
+            let mut metadata = HashMap::new();
+            metadata.insert(SOURCE_METADATA_KEY.to_string(), "crawler".to_string());
+            metadata.insert(URL_METADATA_KEY.to_string(), page.url.clone());
+            if let Some(ref title) = page.title {
+                metadata.insert(TITLE_METADATA_KEY.to_string(), title.clone());
+            }
+            match manager
+                .create_context_with_metadata(
+                    session_id.to_string(),
+                    user_id.to_string(),
+                    domain.to_string(),
+                    page.text,
+                    priority,
+                    metadata,
+                )
+                .await
+            {
+                Ok(context) => report.context_ids.push(context.id),
+                Err(e) => report.errors.push(format!("{}: {}", page.url, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    /// 同一主机两次请求的间隔（Crawl-delay不超过配置的上限）
+    fn host_delay(rules: &RobotsRules, config: &CrawlerConfig) -> Duration {
+        let crawl_delay = rules
+            .crawl_delay
+            .map(|seconds| Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX))
+            .unwrap_or_default()
+            .min(Duration::from_millis(config.max_crawl_delay_ms));
+        Duration::from_millis(config.per_host_delay_ms).max(crawl_delay)
+    }
+
+    /// 获取主机的robots规则（缓存到过期）：robots.txt不存在（4xx）时全部允许，
+    /// 服务端错误、超时等暂时无法获取时全部禁止，并在较短时间后重新获取
+    async fn robots_for(&self, url: &Url, config: &CrawlerConfig) -> RobotsRules {
+        let origin = url.origin().ascii_serialization();
+        if let Some((rules, expires_at)) = self.robots.read().await.get(&origin) {
+            if *expires_at > Instant::now() {
+                return rules.clone();
+            }
+        }
+
+        let ttl = Duration::from_secs(config.robots_cache_ttl_seconds);
+        let (rules, ttl) = match self.fetch_robots(&format!("{}/robots.txt", origin), config).await {
+            Some(rules) => (rules, ttl),
+            None => (RobotsRules::disallow_all(), ttl.min(ROBOTS_UNAVAILABLE_TTL)),
+        };
+        self.robots.write().await.insert(origin, (rules.clone(), Instant::now() + ttl));
+        rules
+    }
+
+    /// 请求robots.txt（跟随有限次重定向）；暂时无法获取时返回None
+    async fn fetch_robots(&self, robots_url: &str, config: &CrawlerConfig) -> Option<RobotsRules> {
+        let mut url = Url::parse(robots_url).ok()?;
+        for _ in 0..=MAX_REDIRECTS {
+            let response = self
+                .client
+                .get(url.clone())
+                .header(reqwest::header::USER_AGENT, &config.user_agent)
+                .timeout(Duration::from_millis(config.request_timeout_ms))
+                .send()
+                .await
+                .ok()?;
+            let status = response.status();
+            if status.is_redirection() {
+                url = redirect_target(&url, &response)?;
+                continue;
+            }
+            if status.is_success() {
+                return response.text().await.ok().map(|body| RobotsRules::parse(&body, &config.user_agent));
+            }
+            if status.is_client_error() {
+                return Some(RobotsRules::default());
+            }
+            return None;
+        }
+        // 重定向次数过多视为robots.txt不存在
+        Some(RobotsRules::default())
+    }
+
+    /// 等待到主机允许下一次请求
+    async fn throttle(&self, host: &str, delay: Duration) {
+        let wait = {
+            let mut last_request = self.last_request.write().await;
+            let now = Instant::now();
+            let next = last_request.get(host).map(|t| *t + delay).unwrap_or(now).max(now);
+            last_request.insert(host.to_string(), next);
+            next - now
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 抓取页面，逐跳跟随重定向并重新检查主机限制和robots规则，超出大小限制时中止读取
+    async fn fetch(
+        &self,
+        url: &Url,
+        config: &CrawlerConfig,
+        start_host: &str,
+    ) -> Result<FetchOutcome, Box<dyn std::error::Error + Send + Sync>> {
+        let mut url = url.clone();
+        let mut redirects = 0;
+        let mut response = loop {
+            let response = self
+                .client
+                .get(url.clone())
+                .header(reqwest::header::USER_AGENT, &config.user_agent)
+                .timeout(Duration::from_millis(config.request_timeout_ms))
+                .send()
+                .await?;
+            if !response.status().is_redirection() {
+                break response;
+            }
+
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err("too many redirects".into());
+            }
+            let next = redirect_target(&url, &response).ok_or("redirect without a valid location")?;
+            let host = next.host_str().ok_or("redirect target has no host")?.to_string();
+            if config.same_host_only && host != start_host {
+                return Err(format!("redirect to {} leaves the crawl host", next).into());
+            }
+            if config.respect_robots {
+                let rules = self.robots_for(&next, config).await;
+                if !rules.is_allowed(&robots_path(&next)) {
+                    return Ok(FetchOutcome::Disallowed);
+                }
+                self.throttle(&host, Self::host_delay(&rules, config)).await;
+            }
+            url = next;
+        };
+        if !response.status().is_success() {
+            return Err(format!("status {}", response.status()).into());
+        }
+
+        let is_html = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.contains("text/html"))
+            .unwrap_or(true);
+        if !is_html {
+            return Ok(FetchOutcome::NonHtml);
+        }
+        if response.content_length().is_some_and(|len| len as usize > config.max_page_bytes) {
+            return Ok(FetchOutcome::TooLarge);
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            body.extend_from_slice(&chunk);
+            if body.len() > config.max_page_bytes {
+                return Ok(FetchOutcome::TooLarge);
+            }
+        }
+        Ok(FetchOutcome::Page { url, html: String::from_utf8_lossy(&body).into_owned() })
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: CrawlerConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> CrawlerConfig {
+        self.config.read().await.clone()
+    }
+}
+
+/// 重定向响应的目标URL（Location相对于当前URL解析）
+fn redirect_target(url: &Url, response: &reqwest::Response) -> Option<Url> {
+    let location = response.headers().get(reqwest::header::LOCATION)?.to_str().ok()?;
+    url.join(location).ok().filter(|url| url.scheme() == "http" || url.scheme() == "https")
+}
+
+/// 提取页面中的http(s)链接
+fn extract_links(base: &Url, html: &str) -> Vec<Url> {
+    LINK_RE
+        .captures_iter(html)
+        .filter_map(|cap| base.join(cap[1].trim()).ok())
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+        .collect()
+}
+
+fn extract_title(html: &str) -> Option<String> {
+    TITLE_RE
+        .captures(html)
+        .map(|cap| decode_entities(SPACE_RE.replace_all(cap[1].trim(), " ").as_ref()))
+        .filter(|title| !title.is_empty())
+}
+
+/// 去除脚本、样式和标签，保留正文文本
+fn extract_text(html: &str) -> String {
+    let text = SCRIPT_RE.replace_all(html, " ");
+    let text = STYLE_RE.replace_all(&text, " ");
+    let text = TITLE_RE.replace_all(&text, " ");
+    let text = TAG_RE.replace_all(&text, " ");
+    decode_entities(SPACE_RE.replace_all(&text, " ").trim())
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::response::IntoResponse;
+    use axum::routing::get;
+    use axum::Router;
+
+    #[test]
+    fn test_robots_rules() {
+        let robots = "User-agent: *\nDisallow: /private\nAllow: /private/public\n\nUser-agent: penlai-crawler\nDisallow: /drafts/\nCrawl-delay: 2\n";
+        let rules = RobotsRules::parse(robots, "penlai-crawler/1.0");
+        assert!(!rules.is_allowed("/drafts/a"));
+        assert!(rules.is_allowed("/private"));
+        assert_eq!(rules.crawl_delay, Some(2.0));
+
+        // 无穷大、负数和非数字的Crawl-delay被忽略，过大的值按配置的上限等待
+        for delay in ["inf", "-1", "NaN", "soon"] {
+            let rules = RobotsRules::parse(&format!("User-agent: *\nCrawl-delay: {}\n", delay), "bot");
+            assert_eq!(rules.crawl_delay, None, "{}", delay);
+        }
+        let config = CrawlerConfig::default();
+        let huge = RobotsRules::parse("User-agent: *\nCrawl-delay: 1e30\n", "bot");
+        assert_eq!(Crawler::host_delay(&huge, &config), Duration::from_millis(config.max_crawl_delay_ms));
+        let infinite = RobotsRules { crawl_delay: Some(f64::INFINITY), ..RobotsRules::default() };
+        assert_eq!(Crawler::host_delay(&infinite, &config), Duration::from_millis(config.max_crawl_delay_ms));
+        assert_eq!(Crawler::host_delay(&rules, &config), Duration::from_secs(2));
+
+        let rules = RobotsRules::parse(robots, "other-bot");
+        assert!(!rules.is_allowed("/private/x"));
+        assert!(rules.is_allowed("/private/public/doc"));
+        assert!(RobotsRules::parse("User-agent: *\nDisallow: /*.pdf$\n", "bot").is_allowed("/a.pdf.html"));
+        assert!(!RobotsRules::parse("User-agent: *\nDisallow: /*.pdf$\n", "bot").is_allowed("/docs/a.pdf"));
+        let url = Url::parse("https://docs.example.com/search?session=1").unwrap();
+        assert!(!RobotsRules::parse("User-agent: *\nDisallow: /*?session=\n", "bot").is_allowed(&robots_path(&url)));
+        assert!(!RobotsRules::disallow_all().is_allowed("/"));
+
+        let mut queue = CrawlQueue::new();
+        assert!(queue.push(Url::parse("https://docs.example.com/a#intro").unwrap(), 0));
+        assert!(!queue.push(Url::parse("https://docs.example.com/a").unwrap(), 1));
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_crawl_site_into_contexts() {
+        let big_page = format!("<html><body>{}</body></html>", "x".repeat(4096));
+        let app = Router::new()
+            .route("/robots.txt", get(|| async { "User-agent: *\nDisallow: /private\nDisallow: /*?session=\n" }))
+            .route(
+                "/",
+                get(|| async {
+                    axum::response::Html(
+                        "<html><head><title>Docs Home</title><style>p{}</style></head><body><p>Welcome &amp; hello</p>\
+                         <a href=\"/guide#top\">Guide</a><a href='/guide'>Again</a><a href=\"/private/secret\">Secret</a>\
+                         <a href=\"/big\">Big</a><a href=\"https://elsewhere.example/\">External</a>\
+                         <a href=\"/go\">Go</a><a href=\"/guide?session=1\">Session</a></body></html>",
+                    )
+                }),
+            )
+            .route(
+                "/guide",
+                get(|| async { axum::response::Html("<html><body><h1>Guide</h1><a href=\"/guide/deep\">Deep</a></body></html>") }),
+            )
+            .route("/guide/deep", get(|| async { axum::response::Html("<p>too deep</p>") }))
+            .route("/go", get(|| async { axum::response::Redirect::temporary("/private/moved") }))
+            .route("/big", get(move || async move { axum::response::Html(big_page).into_response() }));
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        let crawler = Crawler::with_config(CrawlerConfig {
+            max_depth: 1,
+            max_page_bytes: 1024,
+            per_host_delay_ms: 10,
+            ..CrawlerConfig::default()
+        });
+        // 领域模式不允许未知键时，爬虫写入的来源、URL和标题仍可通过校验
+        let schemas = Arc::new(crate::context::metadata_schema::MetadataSchemaRegistry::new());
+        schemas.register(crate::context::metadata_schema::MetadataSchema::new("technical").allow_unknown_keys(false)).await;
+        let manager = ContextManager::new(10, 3600).with_metadata_schemas(schemas);
+        let report = crawler
+            .crawl_into(&manager, &format!("http://{}/", addr), "s1", "u1", "technical", 5)
+            .await
+            .unwrap();
+
+        assert_eq!(report.pages_fetched, 2);
+        // 直接禁止的路径、带查询字符串匹配的路径和重定向到禁止路径的链接
+        assert_eq!(report.skipped_robots, 3);
+        assert_eq!(report.skipped_too_large, 1);
+        assert_eq!(report.duplicates, 1);
+        assert_eq!(report.context_ids.len(), 2);
+
+        let contexts = manager.get_domain_contexts("technical").await;
+        let home = contexts.iter().find(|c| c.metadata.get("title").map(String::as_str) == Some("Docs Home")).unwrap();
+        assert_eq!(home.context_data, "Welcome & hello Guide Again Secret Big External Go Session");
+        assert_eq!(home.metadata["source"], "crawler");
+
+        // robots.txt返回服务端错误时不抓取任何页面
+        let app = Router::new()
+            .route("/robots.txt", get(|| async { axum::http::StatusCode::SERVICE_UNAVAILABLE }))
+            .route("/", get(|| async { axum::response::Html("<p>home</p>") }));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+        let (pages, report) = crawler.crawl(&format!("http://{}/", addr)).await.unwrap();
+        assert!(pages.is_empty());
+        assert_eq!(report.skipped_robots, 1);
+    }
+}
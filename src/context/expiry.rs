@@ -0,0 +1,252 @@
+//! 过期提醒 - 高优先级上下文过期前发出即将过期事件（钩子和可选的通知渠道），便于负责人及时续期
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::context::llm_context::{ContextManager, LLMContext};
+use crate::maintenance::MaintenanceMode;
+use crate::monitoring::notify::{AlertSink, Notification};
+use crate::monitoring::webhook::EVENT_CONTEXT_EXPIRING;
+
+/// 过期提醒配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiryNoticeConfig {
+    pub enabled: bool,
+    pub notice_window_hours: u64,   // 过期前多少小时提醒
+    pub min_priority: u8,           // 只提醒不低于该优先级的上下文
+    pub scan_interval_seconds: u64, // 扫描间隔
+}
+
+impl Default for ExpiryNoticeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            notice_window_hours: 24,
+            min_priority: 7,
+            scan_interval_seconds: 300,
+        }
+    }
+}
+
+/// 一条即将过期提醒
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpiryNotice {
+    pub context_id: Uuid,
+    pub user_id: String,            // 上下文负责人
+    pub domain: String,
+    pub priority: u8,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl ExpiryNotice {
+    fn from_context(context: &LLMContext) -> Option<Self> {
+        Some(Self {
+            context_id: context.id,
+            user_id: context.user_id.clone(),
+            domain: context.domain.clone(),
+            priority: context.priority,
+            expires_at: context.expires_at?,
+        })
+    }
+}
+
+/// 过期提醒任务
+pub struct ExpiryNotifier {
+    context_manager: Arc<ContextManager>,
+    sinks: std::sync::RwLock<Vec<Arc<dyn AlertSink>>>,
+    config: Arc<RwLock<ExpiryNoticeConfig>>,
+    notified: Arc<RwLock<HashMap<Uuid, DateTime<Utc>>>>,   // 已提醒的上下文及提醒时的过期时间
+    maintenance: Option<Arc<MaintenanceMode>>,
+}
+
+impl ExpiryNotifier {
+    /// 创建过期提醒任务
+    pub fn new(context_manager: Arc<ContextManager>, config: ExpiryNoticeConfig) -> Self {
+        Self {
+            context_manager,
+            sinks: std::sync::RwLock::new(Vec::new()),
+            config: Arc::new(RwLock::new(config)),
+            notified: Arc::new(RwLock::new(HashMap::new())),
+            maintenance: None,
+        }
+    }
+
+    /// 添加通知渠道（如Webhook），每次扫描的新提醒合并为一条通知发送
+    pub fn with_sink(mut self, sink: Arc<dyn AlertSink>) -> Self {
+        self.sinks.get_mut().unwrap_or_else(|e| e.into_inner()).push(sink);
+        self
+    }
+
+    /// 替换同名的通知渠道（如Webhook地址变更），不存在时添加
+    pub fn replace_sink(&self, sink: Arc<dyn AlertSink>) {
+        let mut sinks = self.sinks.write().unwrap_or_else(|e| e.into_inner());
+        sinks.retain(|existing| existing.name() != sink.name());
+        sinks.push(sink);
+    }
+
+    /// 关联维护模式，维护期间暂停扫描
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 扫描一次，返回本次新发出的提醒；同一过期时间只提醒一次，续期后重新计算
+    pub async fn scan_once(&self) -> Vec<ExpiryNotice> {
+        let config = self.config.read().await.clone();
+        if !config.enabled {
+            return Vec::new();
+        }
+        let window = chrono::Duration::hours(config.notice_window_hours as i64);
+        let expiring = self.context_manager.get_expiring_contexts(window, config.min_priority).await;
+
+        let mut fresh = Vec::new();
+        {
+            let mut notified = self.notified.write().await;
+            // 已续期到窗口之外或已被删除的上下文不再跟踪
+            notified.retain(|id, _| expiring.iter().any(|context| context.id == *id));
+            for context in expiring {
+                let Some(notice) = ExpiryNotice::from_context(&context) else {
+                    continue;
+                };
+                if notified.get(&context.id) == Some(&notice.expires_at) {
+                    continue;
+                }
+                notified.insert(context.id, notice.expires_at);
+                fresh.push((context, notice));
+            }
+        }
+
+        let mut notices = Vec::with_capacity(fresh.len());
+        for (context, notice) in fresh {
+            self.context_manager.notify_expiring(context).await;
+            notices.push(notice);
+        }
+        if !notices.is_empty() {
+            self.deliver(&notices).await;
+        }
+        notices
+    }
+
+    /// 发送到通知渠道（失败只记录日志）
+    async fn deliver(&self, notices: &[ExpiryNotice]) {
+        let sinks = self.sinks.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if sinks.is_empty() {
+            return;
+        }
+        let body = notices
+            .iter()
+            .map(|notice| format!("{} (owner {}, domain {}, priority {}) expires at {}", notice.context_id, notice.user_id, notice.domain, notice.priority, notice.expires_at))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let notification = Notification {
+            event_type: EVENT_CONTEXT_EXPIRING.to_string(),
+            subject: format!("{} context(s) expiring soon", notices.len()),
+            body,
+            payload: serde_json::json!({ "expiring": notices }),
+        };
+        for sink in &sinks {
+            if let Err(e) = sink.send(&notification).await {
+                log::warn!("Failed to deliver expiry notice via {}: {}", sink.name(), e);
+            }
+        }
+    }
+
+    /// 启动后台扫描任务
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Some(ref maintenance) = self.maintenance {
+                    maintenance.wait_until_inactive().await;
+                }
+                self.scan_once().await;
+                let interval = self.config.read().await.scan_interval_seconds.max(1);
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+            }
+        })
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: ExpiryNoticeConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> ExpiryNoticeConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::context::hooks::{ContextHook, HookRegistry};
+    use crate::test_support::RecordingSink;
+
+    #[derive(Default)]
+    struct ExpiringHook {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ContextHook for ExpiringHook {
+        fn name(&self) -> &str {
+            "expiring"
+        }
+
+        async fn on_expiring_soon(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expiry_notices_and_renewal() {
+        let hooks = Arc::new(HookRegistry::new());
+        let hook = Arc::new(ExpiringHook::default());
+        hooks.register(hook.clone()).await;
+        // TTL为1小时，提醒窗口为2小时
+        let manager = Arc::new(ContextManager::new(10, 3600).with_hooks(hooks.clone()));
+        let important = manager
+            .create_context("s1".to_string(), "alice".to_string(), "legal".to_string(), "contract template".to_string(), 9)
+            .await
+            .unwrap();
+        manager
+            .create_context("s1".to_string(), "alice".to_string(), "legal".to_string(), "scratch note".to_string(), 3)
+            .await
+            .unwrap();
+
+        let sink = Arc::new(RecordingSink::default());
+        let notifier = ExpiryNotifier::new(manager.clone(), ExpiryNoticeConfig { notice_window_hours: 2, ..ExpiryNoticeConfig::default() })
+            .with_sink(sink.clone());
+        let notices = notifier.scan_once().await;
+        assert_eq!(notices.iter().map(|notice| notice.context_id).collect::<Vec<_>>(), vec![important.id]);
+        assert_eq!(notices[0].user_id, "alice");
+        assert!(notifier.scan_once().await.is_empty());
+        hooks.flush().await;
+        assert_eq!(hook.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(sink.sent().len(), 1);
+
+        // 续期到窗口之外后不再提醒
+        let renewed = manager.renew_context(important.id, 7200).await.unwrap();
+        assert!(renewed.expires_at.unwrap() >= important.expires_at.unwrap() + chrono::Duration::seconds(7200));
+        assert_eq!(renewed.version, important.version + 1);
+        assert!(notifier.scan_once().await.is_empty());
+        assert!(manager.renew_context(Uuid::new_v4(), 60).await.is_err());
+
+        // 超出时间范围的续期被拒绝且不修改上下文
+        let error = manager.renew_context(important.id, u64::MAX).await.unwrap_err();
+        assert!(error.downcast_ref::<crate::context::llm_context::InvalidTtlError>().is_some());
+        let error = manager.renew_context(important.id, i64::MAX as u64).await.unwrap_err();
+        assert!(error.downcast_ref::<crate::context::llm_context::InvalidTtlError>().is_some());
+        assert_eq!(manager.get_context(important.id).await.unwrap().version, renewed.version);
+        // 续期后的过期时间计入基数统计
+        assert_eq!(manager.get_cardinality_stats().await.expired, 0);
+    }
+}
@@ -0,0 +1,339 @@
+//! 示例数据生成 - 按领域合成指定数量的仿真上下文（可配置长度分布、标签、时间分布），用于负载测试、基准测试和演示，
+//! 代替各示例中重复手写的字符串；相同种子生成相同的内容
+
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Builder;
+use crate::context::access::Visibility;
+use crate::context::approval::ApprovalState;
+use crate::context::llm_context::{ContextManager, LLMContext};
+use crate::context::schema_migration::CURRENT_SCHEMA_VERSION;
+
+/// 生成的上下文在元数据中的来源标记
+pub const FIXTURE_SOURCE: &str = "fixture";
+
+/// 各领域的语料：(领域, 句子, 标签)
+const CORPUS: &[(&str, &[&str], &[&str])] = &[
+    (
+        "medical",
+        &[
+            "Community-acquired pneumonia is usually treated with amoxicillin for five to seven days.",
+            "Patients with penicillin allergy can receive doxycycline or a macrolide instead.",
+            "Chest radiography confirms the diagnosis when clinical signs are ambiguous.",
+            "Elderly patients with low oxygen saturation should be admitted to hospital.",
+            "Hypertension is diagnosed after two elevated readings taken on separate visits.",
+            "First-line therapy for type 2 diabetes is metformin combined with lifestyle changes.",
+            "Influenza vaccination is recommended every autumn for adults over sixty-five.",
+            "Bronchitis is mostly viral and rarely requires antibiotics.",
+            "Symptoms of sepsis include fever, rapid heart rate and confusion.",
+            "Dosage should be reduced for patients with impaired kidney function.",
+        ],
+        &["cardiology", "infectious-disease", "pharmacology", "guideline", "pediatrics", "emergency"],
+    ),
+    (
+        "legal",
+        &[
+            "An employment contract may be terminated with the notice period stated in the agreement.",
+            "The statute of limitations for breach of contract claims is generally six years.",
+            "A tenant must receive written notice before the landlord enters the property.",
+            "The plaintiff bears the burden of proof in civil litigation.",
+            "Non-compete clauses are enforceable only when reasonable in scope and duration.",
+            "Personal data may be processed only with a lawful basis such as consent.",
+            "The court may award damages for losses that were foreseeable at the time of contracting.",
+            "Intellectual property created during employment usually belongs to the employer.",
+            "A valid will must be signed in the presence of two witnesses.",
+            "Appeals must be filed within thirty days of the judgment.",
+        ],
+        &["employment", "contract", "privacy", "litigation", "property", "compliance"],
+    ),
+    (
+        "technical",
+        &[
+            "Kubernetes restarts a container when its liveness probe fails repeatedly.",
+            "Database indexes speed up reads at the cost of slower writes.",
+            "The service retries failed requests with exponential backoff and jitter.",
+            "TLS certificates should be rotated before they expire to avoid outages.",
+            "A read replica lags behind the primary by a few hundred milliseconds under load.",
+            "Memory leaks in long-running processes show up as steadily growing heap usage.",
+            "Feature flags allow new code paths to be enabled gradually in production.",
+            "The API gateway rejects requests without a valid bearer token.",
+            "Connection pools should be sized according to the database's concurrency limit.",
+            "Structured logs make it easier to search for errors across services.",
+        ],
+        &["infrastructure", "database", "security", "networking", "observability", "runbook"],
+    ),
+    (
+        "education",
+        &[
+            "Spaced repetition improves long-term retention compared with cramming.",
+            "Formative assessment gives students feedback while they are still learning.",
+            "The curriculum introduces fractions before decimals and percentages.",
+            "Students who explain a concept to peers understand it more deeply.",
+            "Homework should reinforce material covered in class rather than introduce new topics.",
+            "Reading comprehension improves when students summarize each paragraph.",
+            "Project-based learning connects classroom topics to real-world problems.",
+            "Teachers can differentiate instruction by grouping students by skill level.",
+            "Clear rubrics help students understand how their work will be graded.",
+            "Attendance is strongly correlated with final exam performance.",
+        ],
+        &["curriculum", "assessment", "pedagogy", "mathematics", "literacy", "classroom"],
+    ),
+    (
+        "finance",
+        &[
+            "Diversifying across asset classes reduces portfolio volatility.",
+            "Interest on a savings account compounds monthly at the stated annual rate.",
+            "A mortgage payment consists of principal, interest, taxes and insurance.",
+            "Index funds track the market at a lower cost than actively managed funds.",
+            "Capital gains on assets held longer than a year are taxed at a reduced rate.",
+            "An emergency fund should cover three to six months of expenses.",
+            "Credit scores depend mainly on payment history and credit utilization.",
+            "Bond prices fall when interest rates rise.",
+            "Invoices are due thirty days after the billing date unless agreed otherwise.",
+            "Quarterly earnings reports disclose revenue, expenses and net income.",
+        ],
+        &["investing", "tax", "banking", "credit", "accounting", "retirement"],
+    ),
+];
+
+/// 上下文长度分布（按词数）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SizeDistribution {
+    Fixed { words: usize },
+    Uniform { min_words: usize, max_words: usize },
+    LogNormal { median_words: usize, sigma: f64 },    // 长尾分布，少数上下文远长于中位数
+}
+
+impl Default for SizeDistribution {
+    fn default() -> Self {
+        SizeDistribution::LogNormal { median_words: 60, sigma: 0.6 }
+    }
+}
+
+/// 生成配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureConfig {
+    pub domains: Vec<String>,           // 生成的领域，须在内置语料中
+    pub contexts_per_domain: usize,     // 每个领域的上下文数
+    pub size: SizeDistribution,         // 长度分布
+    pub max_tags: usize,                // 每个上下文最多的标签数
+    pub users: usize,                   // 用户数
+    pub sessions: usize,                // 会话数（按序分配给用户）
+    pub max_age_days: i64,              // 创建时间均匀分布在过去的天数内
+    pub ttl_seconds: i64,               // 自生成时起的有效期
+    pub seed: u64,                      // 随机种子
+}
+
+impl Default for FixtureConfig {
+    fn default() -> Self {
+        Self {
+            domains: CORPUS.iter().map(|(domain, _, _)| domain.to_string()).collect(),
+            contexts_per_domain: 100,
+            size: SizeDistribution::default(),
+            max_tags: 3,
+            users: 20,
+            sessions: 50,
+            max_age_days: 30,
+            ttl_seconds: 24 * 3600,
+            seed: 42,
+        }
+    }
+}
+
+impl FixtureConfig {
+    /// 每个领域生成count个上下文
+    pub fn with_contexts_per_domain(mut self, count: usize) -> Self {
+        self.contexts_per_domain = count;
+        self
+    }
+
+    /// 设置长度分布
+    pub fn with_size(mut self, size: SizeDistribution) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// 设置随机种子
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// 示例数据生成器
+pub struct FixtureGenerator {
+    config: FixtureConfig,
+    rng: u64,
+}
+
+impl FixtureGenerator {
+    /// 创建生成器
+    pub fn new(config: FixtureConfig) -> Self {
+        Self { rng: config.seed, config }
+    }
+
+    /// 内置语料支持的领域
+    pub fn supported_domains() -> Vec<&'static str> {
+        CORPUS.iter().map(|(domain, _, _)| *domain).collect()
+    }
+
+    /// 生成全部上下文（按领域依次排列）
+    pub fn generate(&mut self) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let now = Utc::now();
+        let mut contexts = Vec::with_capacity(self.config.domains.len() * self.config.contexts_per_domain);
+        for domain in self.config.domains.clone() {
+            let (_, sentences, tags) = CORPUS
+                .iter()
+                .find(|(name, _, _)| *name == domain)
+                .ok_or_else(|| format!("No fixture corpus for domain '{}'", domain))?;
+            for index in 0..self.config.contexts_per_domain {
+                contexts.push(self.context(&domain, index, sentences, tags, now));
+            }
+        }
+        Ok(contexts)
+    }
+
+    /// 生成并写入上下文管理器，返回写入的上下文
+    pub async fn load_into(&mut self, manager: &ContextManager) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let contexts = self.generate()?;
+        manager.insert_contexts(contexts.clone()).await?;
+        Ok(contexts)
+    }
+
+    fn context(&mut self, domain: &str, index: usize, sentences: &[&str], tags: &[&str], now: DateTime<Utc>) -> LLMContext {
+        let session = self.below(self.config.sessions.max(1));
+        let user = session % self.config.users.max(1);
+
+        let target_words = self.sample_words();
+        let mut words = 0;
+        let mut text = Vec::new();
+        while words < target_words {
+            let sentence = sentences[self.below(sentences.len())];
+            words += sentence.split_whitespace().count();
+            text.push(sentence);
+        }
+
+        let mut context_tags: Vec<String> = Vec::new();
+        for _ in 0..self.below(self.config.max_tags + 1) {
+            let tag = tags[self.below(tags.len())].to_string();
+            if !context_tags.contains(&tag) {
+                context_tags.push(tag);
+            }
+        }
+
+        let max_age_seconds = (self.config.max_age_days.max(0) * 86_400) as f64;
+        let created_at = now - Duration::seconds((self.unit() * max_age_seconds) as i64);
+        let updated_at = created_at + Duration::seconds((self.unit() * (now - created_at).num_seconds() as f64) as i64);
+
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&self.next_u64().to_le_bytes());
+        bytes[8..].copy_from_slice(&self.next_u64().to_le_bytes());
+
+        LLMContext {
+            id: Builder::from_random_bytes(bytes).into_uuid(),
+            session_id: format!("session_{}", session),
+            user_id: format!("user_{}", user),
+            domain: domain.to_string(),
+            context_data: text.join(" "),
+            metadata: HashMap::from([
+                ("source".to_string(), FIXTURE_SOURCE.to_string()),
+                ("fixture_index".to_string(), index.to_string()),
+            ]),
+            created_at,
+            updated_at,
+            expires_at: Some(now + Duration::seconds(self.config.ttl_seconds)),
+            priority: self.below(11) as u8,
+            version: 1,
+            tags: context_tags,
+            active: true,
+            approval_state: ApprovalState::default(),
+            visibility: Visibility::default(),
+            schema_version: CURRENT_SCHEMA_VERSION,
+        }
+    }
+
+    /// 按长度分布抽取目标词数（至少1）
+    fn sample_words(&mut self) -> usize {
+        let words = match self.config.size {
+            SizeDistribution::Fixed { words } => words,
+            SizeDistribution::Uniform { min_words, max_words } => {
+                min_words + self.below(max_words.saturating_sub(min_words) + 1)
+            }
+            SizeDistribution::LogNormal { median_words, sigma } => {
+                // Box-Muller变换得到标准正态分布
+                let normal = (-2.0 * self.unit().ln()).sqrt() * (2.0 * std::f64::consts::PI * self.unit()).cos();
+                (median_words as f64 * (sigma * normal).exp()).round() as usize
+            }
+        };
+        words.max(1)
+    }
+
+    /// splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// (0, 1]区间的均匀随机数
+    fn unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    /// [0, n)区间的随机整数
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+}
+
+/// 按默认配置为每个领域生成count个上下文并写入管理器
+pub async fn load_fixtures(manager: &ContextManager, contexts_per_domain: usize) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+    FixtureGenerator::new(FixtureConfig::default().with_contexts_per_domain(contexts_per_domain))
+        .load_into(manager)
+        .await
+}
+
+/// 上下文是否由生成器生成（按元数据判断）
+pub fn is_fixture(context: &LLMContext) -> bool {
+    context.metadata.get("source").map(String::as_str) == Some(FIXTURE_SOURCE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fixtures_are_reproducible_and_loadable() {
+        let config = FixtureConfig::default()
+            .with_contexts_per_domain(40)
+            .with_size(SizeDistribution::Uniform { min_words: 20, max_words: 80 });
+        let first = FixtureGenerator::new(config.clone()).generate().unwrap();
+        let second = FixtureGenerator::new(config.clone()).generate().unwrap();
+        assert_eq!(first.len(), 40 * FixtureGenerator::supported_domains().len());
+        assert_eq!(
+            first.iter().map(|context| (context.id, &context.context_data, &context.tags)).collect::<Vec<_>>(),
+            second.iter().map(|context| (context.id, &context.context_data, &context.tags)).collect::<Vec<_>>()
+        );
+        assert_ne!(FixtureGenerator::new(config.clone().with_seed(7)).generate().unwrap()[0].context_data, first[0].context_data);
+
+        // 长度落在分布范围内（按整句拼接，最多超出一句）；时间不晚于现在，标签不重复
+        for context in &first {
+            let words = context.context_data.split_whitespace().count();
+            assert!((20..=100).contains(&words), "{} words", words);
+            assert!(context.created_at <= context.updated_at && context.updated_at <= Utc::now());
+            assert!(context.tags.len() <= config.max_tags);
+            assert!(is_fixture(context));
+        }
+        let unknown = FixtureConfig { domains: vec!["astrology".to_string()], ..FixtureConfig::default() };
+        assert!(FixtureGenerator::new(unknown).generate().is_err());
+
+        let manager = ContextManager::new(1000, 3600);
+        let loaded = load_fixtures(&manager, 10).await.unwrap();
+        assert_eq!(manager.get_all_contexts().await.len(), loaded.len());
+        assert_eq!(manager.get_domain_contexts("legal").await.len(), 10);
+    }
+}
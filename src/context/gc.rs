@@ -0,0 +1,207 @@
+//! 回收事件与指标 - 每个被清理的上下文产生一条结构化事件，并按原因和领域汇总，用于调整TTL
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, RwLock};
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::context::llm_context::LLMContext;
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem};
+
+/// 事件流缓冲区大小，订阅者处理过慢时丢弃最早的事件
+const GC_EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 回收原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum GcReason {
+    Expired,    // 超过TTL被清理
+    Deleted,    // 被显式删除
+}
+
+/// 一条回收事件
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GcEvent {
+    pub context_id: Uuid,
+    pub domain: String,
+    pub user_id: String,
+    pub reason: GcReason,
+    pub age_seconds: i64,       // 创建至回收的时长
+    pub idle_seconds: i64,      // 最后一次更新至回收的时长
+    pub size_bytes: usize,      // 上下文数据大小
+    pub collected_at: DateTime<Utc>,
+}
+
+impl GcEvent {
+    /// 由被回收的上下文生成事件
+    pub fn new(context: &LLMContext, reason: GcReason) -> Self {
+        let now = Utc::now();
+        Self {
+            context_id: context.id,
+            domain: context.domain.clone(),
+            user_id: context.user_id.clone(),
+            reason,
+            age_seconds: (now - context.created_at).num_seconds().max(0),
+            idle_seconds: (now - context.updated_at).num_seconds().max(0),
+            size_bytes: context.context_data.len(),
+            collected_at: now,
+        }
+    }
+}
+
+/// 单个维度的回收汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct GcAggregate {
+    pub count: u64,
+    pub bytes: u64,             // 回收的数据总量
+    pub avg_age_seconds: f64,
+    pub max_age_seconds: i64,
+    pub avg_idle_seconds: f64,
+}
+
+impl GcAggregate {
+    fn record(&mut self, event: &GcEvent) {
+        let n = self.count as f64;
+        self.avg_age_seconds = (self.avg_age_seconds * n + event.age_seconds as f64) / (n + 1.0);
+        self.avg_idle_seconds = (self.avg_idle_seconds * n + event.idle_seconds as f64) / (n + 1.0);
+        self.max_age_seconds = self.max_age_seconds.max(event.age_seconds);
+        self.count += 1;
+        self.bytes += event.size_bytes as u64;
+    }
+}
+
+/// 回收指标
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct GcMetrics {
+    pub total: GcAggregate,
+    pub by_reason: HashMap<GcReason, GcAggregate>,
+    pub by_domain: HashMap<String, GcAggregate>,
+    pub last_collected_at: Option<DateTime<Utc>>,
+}
+
+/// 回收跟踪器 - 广播回收事件并汇总指标
+pub struct GcTracker {
+    sender: broadcast::Sender<GcEvent>,
+    metrics: Arc<RwLock<GcMetrics>>,
+}
+
+impl Default for GcTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GcTracker {
+    /// 创建回收跟踪器
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(GC_EVENT_CHANNEL_CAPACITY);
+        Self {
+            sender,
+            metrics: Arc::new(RwLock::new(GcMetrics::default())),
+        }
+    }
+
+    /// 记录一个被回收的上下文
+    pub async fn record(&self, context: &LLMContext, reason: GcReason) -> GcEvent {
+        let event = GcEvent::new(context, reason);
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.total.record(&event);
+            metrics.by_reason.entry(reason).or_default().record(&event);
+            metrics.by_domain.entry(event.domain.clone()).or_default().record(&event);
+            metrics.last_collected_at = Some(event.collected_at);
+        }
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.sender.send(event.clone());
+        event
+    }
+
+    /// 订阅回收事件流
+    pub fn subscribe(&self) -> broadcast::Receiver<GcEvent> {
+        self.sender.subscribe()
+    }
+
+    /// 获取回收指标
+    pub async fn get_metrics(&self) -> GcMetrics {
+        self.metrics.read().await.clone()
+    }
+
+    /// 将回收事件转发到监控系统（随监控事件一起导出）
+    pub fn forward_to(&self, monitoring: Arc<MonitoringSystem>) -> JoinHandle<()> {
+        let mut receiver = self.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => {
+                        monitoring
+                            .log_event(MonitoringEvent::ContextCollected {
+                                context_id: event.context_id,
+                                domain: event.domain,
+                                reason: format!("{:?}", event.reason),
+                                age_seconds: event.age_seconds,
+                                size_bytes: event.size_bytes,
+                            })
+                            .await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::warn!("GC event forwarder lagged, {} events dropped", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::monitoring::monitoring::EventKind;
+
+    #[tokio::test]
+    async fn test_gc_events_and_metrics() {
+        let manager = ContextManager::new(10, 0);
+        let monitoring = Arc::new(MonitoringSystem::new());
+        let forwarder = manager.get_gc_tracker().forward_to(monitoring.clone());
+        let mut events = manager.get_gc_tracker().subscribe();
+
+        let expiring = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "x".repeat(100), 5)
+            .await
+            .unwrap();
+        let deleted = manager
+            .create_context("s1".to_string(), "u1".to_string(), "legal".to_string(), "x".repeat(40), 5)
+            .await
+            .unwrap();
+        manager.delete_context(deleted.id).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        manager.cleanup_expired_contexts().await.unwrap();
+
+        let first = events.recv().await.unwrap();
+        assert_eq!(first.context_id, deleted.id);
+        assert_eq!(first.reason, GcReason::Deleted);
+        let second = events.recv().await.unwrap();
+        assert_eq!(second.context_id, expiring.id);
+        assert_eq!(second.reason, GcReason::Expired);
+        assert_eq!(second.size_bytes, 100);
+
+        let metrics = manager.get_gc_tracker().get_metrics().await;
+        assert_eq!(metrics.total.count, 2);
+        assert_eq!(metrics.total.bytes, 140);
+        assert_eq!(metrics.by_reason[&GcReason::Expired].count, 1);
+        assert_eq!(metrics.by_domain["legal"].bytes, 40);
+
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        let forwarded = monitoring
+            .get_recent_events(10)
+            .await
+            .into_iter()
+            .filter(|(_, event)| event.kind() == EventKind::ContextCollected)
+            .count();
+        assert_eq!(forwarded, 2);
+        forwarder.abort();
+    }
+}
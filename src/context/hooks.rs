@@ -0,0 +1,377 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify, RwLock};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use crate::context::llm_context::LLMContext;
+use crate::utils::async_runtime::{spawn_fan_out, TaskError};
+
+/// 上下文生命周期钩子 - 部署方可注册自定义异步逻辑（如通知搜索索引、同步外部系统）
+#[async_trait]
+pub trait ContextHook: Send + Sync {
+    /// 钩子名称（用于统计和注销）
+    fn name(&self) -> &str;
+
+    /// 上下文创建后调用
+    async fn on_create(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// 上下文更新后调用
+    async fn on_update(
+        &self,
+        _previous: &LLMContext,
+        _current: &LLMContext,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// 上下文被删除后调用
+    async fn on_delete(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// 上下文过期被清理后调用
+    async fn on_expire(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// 上下文即将过期时调用（每个过期时间只通知一次，续期后重新计算）
+    async fn on_expiring_soon(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+}
+
+/// 生命周期事件
+#[derive(Debug, Clone)]
+pub enum LifecycleEvent {
+    Created(LLMContext),
+    Updated { previous: Box<LLMContext>, current: LLMContext },
+    Deleted(LLMContext),
+    Expired(LLMContext),
+    ExpiringSoon(LLMContext),
+}
+
+/// 钩子执行配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookConfig {
+    pub timeout_ms: u64,  // 单个钩子单次执行的超时时间
+}
+
+impl Default for HookConfig {
+    fn default() -> Self {
+        Self { timeout_ms: 2000 }
+    }
+}
+
+/// 钩子执行失败
+enum HookFailure {
+    Error(Box<dyn std::error::Error + Send + Sync>),
+    TimedOut,
+}
+
+/// 单个钩子的执行统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookStats {
+    pub invocations: u64,
+    pub failures: u64,  // 返回错误或发生panic
+    pub timeouts: u64,
+}
+
+/// 默认的待分发事件队列容量
+pub const DEFAULT_HOOK_QUEUE_CAPACITY: usize = 1000;
+
+/// 钩子注册表 - 事件进入有界队列后由后台任务分发，每个钩子在独立任务中带超时执行，不阻塞也不影响主操作
+pub struct HookRegistry {
+    hooks: Arc<RwLock<Vec<Arc<dyn ContextHook>>>>,
+    config: Arc<RwLock<HookConfig>>,
+    stats: Arc<RwLock<HashMap<String, HookStats>>>,
+    queue: mpsc::Sender<LifecycleEvent>,
+    receiver: Mutex<Option<mpsc::Receiver<LifecycleEvent>>>,  // 首次分发时交给后台任务
+    pending: Arc<AtomicUsize>,      // 已入队但尚未分发完成的事件数
+    idle: Arc<Notify>,              // 待分发事件清空时通知
+    dropped: AtomicU64,             // 队列已满时丢弃的事件数
+}
+
+impl Default for HookRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HookRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::with_queue_capacity(DEFAULT_HOOK_QUEUE_CAPACITY)
+    }
+
+    /// 创建指定队列容量的空注册表
+    pub fn with_queue_capacity(capacity: usize) -> Self {
+        let (queue, receiver) = mpsc::channel(capacity.max(1));
+        Self {
+            hooks: Arc::new(RwLock::new(Vec::new())),
+            config: Arc::new(RwLock::new(HookConfig::default())),
+            stats: Arc::new(RwLock::new(HashMap::new())),
+            queue,
+            receiver: Mutex::new(Some(receiver)),
+            pending: Arc::new(AtomicUsize::new(0)),
+            idle: Arc::new(Notify::new()),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// 注册钩子
+    pub async fn register(&self, hook: Arc<dyn ContextHook>) {
+        self.hooks.write().await.push(hook);
+    }
+
+    /// 按名称注销钩子，返回是否存在
+    pub async fn unregister(&self, name: &str) -> bool {
+        let mut hooks = self.hooks.write().await;
+        let before = hooks.len();
+        hooks.retain(|hook| hook.name() != name);
+        hooks.len() != before
+    }
+
+    /// 已注册的钩子数量
+    pub async fn len(&self) -> usize {
+        self.hooks.read().await.len()
+    }
+
+    /// 是否没有注册钩子
+    pub async fn is_empty(&self) -> bool {
+        self.hooks.read().await.is_empty()
+    }
+
+    /// 将生命周期事件放入队列后立即返回；队列已满时丢弃事件并计数
+    pub async fn dispatch(&self, event: LifecycleEvent) {
+        if self.hooks.read().await.is_empty() {
+            return;
+        }
+        self.start_worker();
+        self.pending.fetch_add(1, Ordering::SeqCst);
+        if self.queue.try_send(event).is_err() {
+            self.finish_one();
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            log::warn!("Context hook queue is full, dropping lifecycle event");
+        }
+    }
+
+    /// 等待已入队的事件全部分发完成
+    pub async fn flush(&self) {
+        loop {
+            let idle = self.idle.notified();
+            tokio::pin!(idle);
+            idle.as_mut().enable();
+            if self.pending.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    /// 队列已满时丢弃的事件数
+    pub fn dropped_events(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn finish_one(&self) {
+        if self.pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    /// 启动后台分发任务（只启动一次），按入队顺序逐个分发事件
+    fn start_worker(&self) {
+        let Some(mut receiver) = self.receiver.lock().unwrap_or_else(PoisonError::into_inner).take() else {
+            return;
+        };
+        let (hooks, config, stats) = (self.hooks.clone(), self.config.clone(), self.stats.clone());
+        let (pending, idle) = (self.pending.clone(), self.idle.clone());
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                Self::run_hooks(&hooks, &config, &stats, event).await;
+                if pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                    idle.notify_waiters();
+                }
+            }
+        });
+    }
+
+    /// 分发一个事件到所有钩子，等待全部完成或超时
+    async fn run_hooks(
+        hooks: &RwLock<Vec<Arc<dyn ContextHook>>>,
+        config: &RwLock<HookConfig>,
+        stats: &RwLock<HashMap<String, HookStats>>,
+        event: LifecycleEvent,
+    ) {
+        let hooks = hooks.read().await.clone();
+        if hooks.is_empty() {
+            return;
+        }
+        let timeout = Duration::from_millis(config.read().await.timeout_ms);
+        let event = Arc::new(event);
+
+        let outcomes = spawn_fan_out(hooks.iter().cloned(), hooks.len(), |_| false, |hook| {
+            let event = event.clone();
+            async move {
+                tokio::time::timeout(timeout, async {
+                    match event.as_ref() {
+                        LifecycleEvent::Created(context) => hook.on_create(context).await,
+                        LifecycleEvent::Updated { previous, current } => hook.on_update(previous, current).await,
+                        LifecycleEvent::Deleted(context) => hook.on_delete(context).await,
+                        LifecycleEvent::Expired(context) => hook.on_expire(context).await,
+                        LifecycleEvent::ExpiringSoon(context) => hook.on_expiring_soon(context).await,
+                    }
+                })
+                .await
+                .map_err(|_| HookFailure::TimedOut)?
+                .map_err(HookFailure::Error)
+            }
+        })
+        .await;
+
+        let mut stats = stats.write().await;
+        for (hook, outcome) in hooks.iter().zip(outcomes) {
+            let name = hook.name();
+            let entry = stats.entry(name.to_string()).or_default();
+            entry.invocations += 1;
+            match outcome {
+                Ok(()) => {}
+                Err(TaskError::Failed(HookFailure::Error(e))) => {
+                    entry.failures += 1;
+                    log::warn!("Context hook {} failed: {}", name, e);
+                }
+                Err(TaskError::Failed(HookFailure::TimedOut)) => {
+                    entry.timeouts += 1;
+                    log::warn!("Context hook {} timed out after {:?}", name, timeout);
+                }
+                Err(TaskError::Panicked(message)) => {
+                    entry.failures += 1;
+                    log::error!("Context hook {} panicked: {}", name, message);
+                }
+                Err(TaskError::Cancelled) => {}
+            }
+        }
+    }
+
+    /// 获取各钩子的执行统计
+    pub async fn get_stats(&self) -> HashMap<String, HookStats> {
+        self.stats.read().await.clone()
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: HookConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> HookConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingHook {
+        created: AtomicUsize,
+        updated: AtomicUsize,
+        deleted: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ContextHook for CountingHook {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        async fn on_create(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.created.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn on_update(&self, previous: &LLMContext, current: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            assert_eq!(previous.version + 1, current.version);
+            self.updated.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn on_delete(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.deleted.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    struct FaultyHook;
+
+    #[async_trait]
+    impl ContextHook for FaultyHook {
+        fn name(&self) -> &str {
+            "faulty"
+        }
+
+        async fn on_create(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Err("indexer unavailable".into())
+        }
+
+        async fn on_update(&self, _previous: &LLMContext, _current: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(())
+        }
+
+        async fn on_delete(&self, _context: &LLMContext) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            panic!("hook bug");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_isolated_from_operations() {
+        let registry = Arc::new(HookRegistry::new());
+        registry.update_config(HookConfig { timeout_ms: 50 }).await;
+        let counting = Arc::new(CountingHook {
+            created: AtomicUsize::new(0),
+            updated: AtomicUsize::new(0),
+            deleted: AtomicUsize::new(0),
+        });
+        registry.register(counting.clone()).await;
+        registry.register(Arc::new(FaultyHook)).await;
+
+        let manager = ContextManager::new(10, 3600).with_hooks(registry.clone());
+        let context = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "data".to_string(), 5)
+            .await
+            .unwrap();
+        manager.update_context(context.id, Some("v2".to_string()), None, None).await.unwrap();
+        manager.delete_context(context.id).await.unwrap();
+        // 钩子在后台执行，等待队列清空后再检查统计
+        registry.flush().await;
+
+        assert_eq!(counting.created.load(Ordering::SeqCst), 1);
+        assert_eq!(counting.updated.load(Ordering::SeqCst), 1);
+        assert_eq!(counting.deleted.load(Ordering::SeqCst), 1);
+
+        let stats = registry.get_stats().await;
+        assert_eq!(stats["counting"].invocations, 3);
+        assert_eq!(stats["counting"].failures, 0);
+        assert_eq!(stats["faulty"].failures, 2);
+        assert_eq!(stats["faulty"].timeouts, 1);
+
+        assert!(registry.unregister("faulty").await);
+        assert_eq!(registry.len().await, 1);
+
+        // 队列已满时丢弃事件而不是阻塞调用方
+        let bounded = HookRegistry::with_queue_capacity(1);
+        bounded.register(Arc::new(FaultyHook)).await;
+        for _ in 0..3 {
+            bounded.dispatch(LifecycleEvent::Updated { previous: Box::new(context.clone()), current: context.clone() }).await;
+        }
+        assert!(bounded.dropped_events() >= 1);
+    }
+}
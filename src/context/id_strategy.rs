@@ -0,0 +1,193 @@
+//! 上下文ID生成策略 - 支持按时间排序的UUIDv7和雪花ID，兼容已有的随机UUIDv4
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::{Uuid, Version};
+use crate::context::llm_context::LLMContext;
+
+/// 雪花ID的起始时间（2024-01-01T00:00:00Z，毫秒）
+const SNOWFLAKE_EPOCH_MS: u64 = 1_704_067_200_000;
+/// 节点ID位数
+const SNOWFLAKE_NODE_BITS: u64 = 10;
+/// 序列号位数
+const SNOWFLAKE_SEQUENCE_BITS: u64 = 12;
+/// 最大节点ID
+pub const SNOWFLAKE_MAX_NODE_ID: u16 = (1 << SNOWFLAKE_NODE_BITS) - 1;
+
+/// ID生成策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdStrategy {
+    #[default]
+    UuidV4,                         // 随机UUID（不可按时间排序）
+    UuidV7,                         // 毫秒时间戳前缀的UUID
+    Snowflake { node_id: u16 },     // 时间戳 + 节点 + 序列号，编码为自定义版本(v8)UUID
+}
+
+impl IdStrategy {
+    /// 从配置字符串解析（"v4"、"v7"、"snowflake:<节点ID>"），无法识别时返回None
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "v4" | "uuidv4" => Some(IdStrategy::UuidV4),
+            "v7" | "uuidv7" => Some(IdStrategy::UuidV7),
+            "snowflake" => Some(IdStrategy::Snowflake { node_id: 0 }),
+            other => {
+                let node_id: u16 = other.strip_prefix("snowflake:")?.parse().ok()?;
+                (node_id <= SNOWFLAKE_MAX_NODE_ID).then_some(IdStrategy::Snowflake { node_id })
+            }
+        }
+    }
+}
+
+/// 上下文ID生成器
+#[derive(Debug, Default)]
+pub struct ContextIdGenerator {
+    strategy: IdStrategy,
+    last_snowflake: AtomicU64, // 上一个雪花ID，保证单调递增
+}
+
+impl ContextIdGenerator {
+    /// 创建指定策略的生成器
+    pub fn new(strategy: IdStrategy) -> Self {
+        Self {
+            strategy,
+            last_snowflake: AtomicU64::new(0),
+        }
+    }
+
+    /// 当前策略
+    pub fn strategy(&self) -> IdStrategy {
+        self.strategy
+    }
+
+    /// 生成新ID
+    pub fn generate(&self) -> Uuid {
+        match self.strategy {
+            IdStrategy::UuidV4 => Uuid::new_v4(),
+            IdStrategy::UuidV7 => Uuid::now_v7(),
+            IdStrategy::Snowflake { node_id } => snowflake_to_uuid(self.next_snowflake(node_id)),
+        }
+    }
+
+    /// 生成下一个雪花ID；同一毫秒内序列号用尽或时钟回拨时借用下一毫秒
+    fn next_snowflake(&self, node_id: u16) -> u64 {
+        let node = (node_id.min(SNOWFLAKE_MAX_NODE_ID) as u64) << SNOWFLAKE_SEQUENCE_BITS;
+        let elapsed_ms = (Utc::now().timestamp_millis() as u64).saturating_sub(SNOWFLAKE_EPOCH_MS);
+        let candidate = (elapsed_ms << (SNOWFLAKE_NODE_BITS + SNOWFLAKE_SEQUENCE_BITS)) | node;
+        let sequence_mask = (1 << SNOWFLAKE_SEQUENCE_BITS) - 1;
+
+        let previous = self
+            .last_snowflake
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |last| {
+                Some(next_after(last, candidate, node, sequence_mask))
+            })
+            .unwrap_or_else(|last| last);
+        next_after(previous, candidate, node, sequence_mask)
+    }
+}
+
+/// 在上一个ID之后选取下一个ID
+fn next_after(last: u64, candidate: u64, node: u64, sequence_mask: u64) -> u64 {
+    if candidate > last {
+        candidate
+    } else if last & sequence_mask == sequence_mask {
+        let timestamp = (last >> (SNOWFLAKE_NODE_BITS + SNOWFLAKE_SEQUENCE_BITS)) + 1;
+        (timestamp << (SNOWFLAKE_NODE_BITS + SNOWFLAKE_SEQUENCE_BITS)) | node
+    } else {
+        last + 1
+    }
+}
+
+/// 将雪花ID编码为v8 UUID，高位在前，保持字节序与时间顺序一致
+fn snowflake_to_uuid(snowflake: u64) -> Uuid {
+    let mut bytes = [0u8; 16];
+    bytes[..6].copy_from_slice(&(snowflake >> 16).to_be_bytes()[2..]);
+    let middle = ((snowflake >> 4) & 0xFFF) as u16;
+    bytes[6] = 0x80 | (middle >> 8) as u8;  // 版本号8
+    bytes[7] = middle as u8;
+    bytes[8] = 0x80 | (((snowflake & 0xF) as u8) << 2); // RFC 4122变体
+    Uuid::from_bytes(bytes)
+}
+
+/// 从v8 UUID中还原雪花ID
+fn uuid_to_snowflake(id: &Uuid) -> u64 {
+    let bytes = id.as_bytes();
+    let mut high = [0u8; 8];
+    high[2..].copy_from_slice(&bytes[..6]);
+    let middle = (((bytes[6] & 0x0F) as u64) << 8) | bytes[7] as u64;
+    (u64::from_be_bytes(high) << 16) | (middle << 4) | ((bytes[8] >> 2) & 0xF) as u64
+}
+
+/// 解析ID中嵌入的生成时间；随机UUIDv4等不含时间的ID返回None
+pub fn id_timestamp(id: &Uuid) -> Option<DateTime<Utc>> {
+    match id.get_version() {
+        Some(Version::SortRand) => {
+            let (seconds, nanos) = id.get_timestamp()?.to_unix();
+            Utc.timestamp_opt(seconds as i64, nanos).single()
+        }
+        Some(Version::Custom) => {
+            let elapsed_ms = uuid_to_snowflake(id) >> (SNOWFLAKE_NODE_BITS + SNOWFLAKE_SEQUENCE_BITS);
+            Utc.timestamp_millis_opt((SNOWFLAKE_EPOCH_MS + elapsed_ms) as i64).single()
+        }
+        _ => None,
+    }
+}
+
+/// 上下文的时间排序键：优先使用ID中的时间，旧的v4 ID回退到创建时间
+pub fn time_sort_key(context: &LLMContext) -> (DateTime<Utc>, Uuid) {
+    (id_timestamp(&context.id).unwrap_or(context.created_at), context.id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_ordered_ids() {
+        assert_eq!(IdStrategy::parse("snowflake:7"), Some(IdStrategy::Snowflake { node_id: 7 }));
+        assert_eq!(IdStrategy::parse("snowflake:4096"), None);
+        assert_eq!(IdStrategy::parse("V7"), Some(IdStrategy::UuidV7));
+
+        let before = Utc::now() - chrono::Duration::milliseconds(1);
+        for strategy in [IdStrategy::UuidV7, IdStrategy::Snowflake { node_id: 3 }] {
+            let generator = ContextIdGenerator::new(strategy);
+            let ids: Vec<Uuid> = (0..5000).map(|_| generator.generate()).collect();
+            // 字节序即时间序，且不重复
+            assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+            let timestamp = id_timestamp(&ids[0]).unwrap();
+            assert!(timestamp >= before && timestamp <= Utc::now() + chrono::Duration::seconds(1));
+        }
+
+        let snowflake = ContextIdGenerator::new(IdStrategy::Snowflake { node_id: 3 }).next_snowflake(3);
+        assert_eq!(uuid_to_snowflake(&snowflake_to_uuid(snowflake)), snowflake);
+        assert_eq!(snowflake_to_uuid(snowflake).get_version(), Some(Version::Custom));
+        assert!(id_timestamp(&Uuid::new_v4()).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_range_scan_mixes_v4_and_v7() {
+        use crate::context::codec::JsonCodec;
+        use crate::context::llm_context::ContextManager;
+
+        let legacy = ContextManager::new(10, 3600);
+        let old = legacy
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "legacy".to_string(), 5)
+            .await
+            .unwrap();
+        let manager = ContextManager::new(10, 3600).with_id_strategy(IdStrategy::UuidV7);
+        let exported = legacy.export_contexts(&JsonCodec).await.unwrap();
+        manager.import_contexts(&JsonCodec, &exported).await.unwrap();
+        // v7 ID只精确到毫秒，避免与旧上下文落在同一毫秒内
+        tokio::time::sleep(std::time::Duration::from_millis(2)).await;
+        let new = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "fresh".to_string(), 5)
+            .await
+            .unwrap();
+
+        let start = Utc::now() - chrono::Duration::minutes(1);
+        let range = manager.get_contexts_created_between(start, Utc::now() + chrono::Duration::minutes(1)).await;
+        let ids: Vec<Uuid> = range.iter().map(|ctx| ctx.id).collect();
+        assert_eq!(ids, vec![old.id, new.id]);
+        assert!(manager.get_contexts_created_between(start, old.created_at).await.is_empty());
+    }
+}
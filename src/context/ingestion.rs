@@ -0,0 +1,516 @@
+//! Webhook入库 - 外部系统（CMS、Wiki、工单系统）推送文档，校验带时间戳的共享密钥签名（拒绝重放）后异步写入上下文
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::context::llm_context::ContextManager;
+use crate::maintenance::MaintenanceMode;
+use crate::processing::context_packing::chunk_at_sentences;
+use crate::selection::post_filters::SOURCE_METADATA_KEY;
+use crate::utils::outbound_scheduler::{CallPriority, OutboundScheduler, INGESTION_PROVIDER};
+use crate::utils::tokenizer::{Tokenizer, WhitespaceTokenizer};
+
+/// 签名请求头，格式为`sha256=<十六进制HMAC>`
+pub const SIGNATURE_HEADER: &str = "x-penlai-signature";
+/// 签名时间戳请求头（Unix秒），带时间戳的签名覆盖`<时间戳>.<请求体>`
+pub const TIMESTAMP_HEADER: &str = "x-penlai-timestamp";
+/// 分块写入的文档在元数据中的分块序号（从0开始）
+pub const CHUNK_INDEX_METADATA_KEY: &str = "chunk_index";
+/// 分块写入的文档在元数据中的分块总数
+pub const CHUNK_COUNT_METADATA_KEY: &str = "chunk_count";
+/// 推送的文档在元数据中的来源系统文档ID
+pub const EXTERNAL_ID_METADATA_KEY: &str = "external_id";
+/// 推送的文档和爬取的页面在元数据中的标题
+pub const TITLE_METADATA_KEY: &str = "title";
+/// 爬取的页面在元数据中的URL
+pub const URL_METADATA_KEY: &str = "url";
+
+/// 推送的单个文档；带external_id的文档再次推送时更新已有上下文
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IngestDocument {
+    pub external_id: Option<String>,    // 来源系统中的文档ID
+    pub title: Option<String>,
+    pub content: String,
+    pub domain: String,
+    pub session_id: Option<String>,     // 缺省为`ingest:<source>`
+    pub user_id: Option<String>,        // 缺省为来源名称
+    pub priority: Option<u8>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+/// Webhook请求体
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IngestRequest {
+    pub source: String,                 // 来源系统名称
+    pub documents: Vec<IngestDocument>,
+}
+
+/// 入库任务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum IngestionJobStatus {
+    Queued,
+    Running,
+    Completed,  // 全部文档成功
+    Partial,    // 部分文档失败
+    Failed,     // 全部文档失败
+}
+
+/// 入库任务
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct IngestionJob {
+    pub id: Uuid,
+    pub source: String,
+    pub status: IngestionJobStatus,
+    pub total: usize,
+    pub created: usize,                 // 新建的上下文数
+    pub updated: usize,                 // 按external_id更新的上下文数
+    pub failed: usize,
+    pub context_ids: Vec<Uuid>,
+    pub errors: Vec<String>,
+    pub submitted_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// Webhook入库配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngestionConfig {
+    pub shared_secret: Option<String>,      // 未配置时拒绝所有请求
+    pub max_documents_per_request: usize,
+    pub default_priority: u8,
+    pub max_jobs_retained: usize,           // 保留的任务记录数
+    #[serde(default)]
+    pub max_chunk_tokens: usize,            // 超过该令牌数的文档在句子边界切分为多个上下文，0表示不切分
+    #[serde(default = "default_max_signature_age_seconds")]
+    pub max_signature_age_seconds: i64,     // 签名时间戳与当前时间允许的最大偏差，超出的请求视为重放
+}
+
+fn default_max_signature_age_seconds() -> i64 {
+    300
+}
+
+impl Default for IngestionConfig {
+    fn default() -> Self {
+        Self {
+            shared_secret: std::env::var("PENLAI_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty()),
+            max_documents_per_request: 100,
+            default_priority: 5,
+            max_jobs_retained: 1000,
+            max_chunk_tokens: 0,
+            max_signature_age_seconds: default_max_signature_age_seconds(),
+        }
+    }
+}
+
+/// Webhook请求被拒绝的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebhookRejection {
+    NotConfigured,              // 未配置共享密钥
+    MissingSignature,
+    MissingTimestamp,           // 缺少或无法解析签名时间戳
+    StaleTimestamp,             // 签名时间戳超出允许的偏差（可能是重放）
+    InvalidSignature,
+    InvalidPayload(String),     // 请求体无法解析或不符合限制
+}
+
+impl std::fmt::Display for WebhookRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WebhookRejection::NotConfigured => write!(f, "Webhook shared secret not configured"),
+            WebhookRejection::MissingSignature => write!(f, "Missing {} header", SIGNATURE_HEADER),
+            WebhookRejection::MissingTimestamp => write!(f, "Missing or malformed {} header", TIMESTAMP_HEADER),
+            WebhookRejection::StaleTimestamp => write!(f, "Signature timestamp outside the allowed window"),
+            WebhookRejection::InvalidSignature => write!(f, "Invalid signature"),
+            WebhookRejection::InvalidPayload(reason) => write!(f, "Invalid payload: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for WebhookRejection {}
+
+/// 计算请求体签名（`sha256=<hex>`）
+pub fn sign_payload(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 计算带时间戳的签名：对`<timestamp>.<body>`签名，接收方据此拒绝过期的重放请求
+pub fn sign_timestamped(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    sign_payload(secret, &timestamped_payload(timestamp, body))
+}
+
+/// 以常量时间校验带时间戳的签名
+pub fn verify_timestamped(secret: &str, timestamp: i64, body: &[u8], signature: &str) -> bool {
+    verify_signature(secret, &timestamped_payload(timestamp, body), signature)
+}
+
+fn timestamped_payload(timestamp: i64, body: &[u8]) -> Vec<u8> {
+    let mut payload = format!("{}.", timestamp).into_bytes();
+    payload.extend_from_slice(body);
+    payload
+}
+
+/// 以常量时间校验请求体签名
+pub fn verify_signature(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(expected) = signature.trim().strip_prefix("sha256=").and_then(|hex_sig| hex::decode(hex_sig).ok()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Webhook入库服务
+pub struct IngestionService {
+    context_manager: Arc<ContextManager>,
+    jobs: Arc<RwLock<HashMap<Uuid, IngestionJob>>>,
+    config: Arc<RwLock<IngestionConfig>>,
+    /// 分块使用的分词器，与选择器和提示词装填共用
+    tokenizer: Arc<dyn Tokenizer>,
+    /// 可选的维护模式开关，维护期间任务在文档之间暂停
+    maintenance: Option<Arc<MaintenanceMode>>,
+    /// 可选的外呼调度器，每个文档以后台优先级等待配额
+    scheduler: Option<Arc<OutboundScheduler>>,
+}
+
+impl IngestionService {
+    /// 创建入库服务
+    pub fn new(context_manager: Arc<ContextManager>) -> Self {
+        Self {
+            context_manager,
+            jobs: Arc::new(RwLock::new(HashMap::new())),
+            config: Arc::new(RwLock::new(IngestionConfig::default())),
+            tokenizer: Arc::new(WhitespaceTokenizer),
+            maintenance: None,
+            scheduler: None,
+        }
+    }
+
+    /// 关联外呼调度器，入库任务不挤占交互请求的配额
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 使用指定的分词器切分文档
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// 维护期间暂停写入，维护结束后继续
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 校验带时间戳的签名并解析请求体；时间戳超出允许偏差的请求被拒绝，截获的请求无法在窗口外重放
+    pub async fn authenticate(
+        &self,
+        body: &[u8],
+        timestamp: Option<&str>,
+        signature: Option<&str>,
+    ) -> Result<IngestRequest, WebhookRejection> {
+        let config = self.config.read().await;
+        let secret = config.shared_secret.as_deref().ok_or(WebhookRejection::NotConfigured)?;
+        let signature = signature.ok_or(WebhookRejection::MissingSignature)?;
+        let timestamp: i64 = timestamp
+            .and_then(|value| value.trim().parse().ok())
+            .ok_or(WebhookRejection::MissingTimestamp)?;
+        if !verify_timestamped(secret, timestamp, body, signature) {
+            return Err(WebhookRejection::InvalidSignature);
+        }
+        if Utc::now().timestamp().abs_diff(timestamp) > config.max_signature_age_seconds.max(0) as u64 {
+            return Err(WebhookRejection::StaleTimestamp);
+        }
+        let request: IngestRequest =
+            serde_json::from_slice(body).map_err(|e| WebhookRejection::InvalidPayload(e.to_string()))?;
+        if request.documents.is_empty() {
+            return Err(WebhookRejection::InvalidPayload("no documents".to_string()));
+        }
+        if request.documents.len() > config.max_documents_per_request {
+            return Err(WebhookRejection::InvalidPayload(format!(
+                "too many documents: {} (max {})",
+                request.documents.len(),
+                config.max_documents_per_request
+            )));
+        }
+        Ok(request)
+    }
+
+    /// 提交入库任务，立即返回任务ID，文档在后台写入
+    pub async fn submit(self: &Arc<Self>, request: IngestRequest) -> Uuid {
+        let job = IngestionJob {
+            id: Uuid::new_v4(),
+            source: request.source.clone(),
+            status: IngestionJobStatus::Queued,
+            total: request.documents.len(),
+            created: 0,
+            updated: 0,
+            failed: 0,
+            context_ids: Vec::new(),
+            errors: Vec::new(),
+            submitted_at: Utc::now(),
+            finished_at: None,
+        };
+        let job_id = job.id;
+        self.insert_job(job).await;
+
+        let service = self.clone();
+        tokio::spawn(async move {
+            service.run_job(job_id, request).await;
+        });
+        job_id
+    }
+
+    /// 查询任务状态
+    pub async fn get_job(&self, job_id: Uuid) -> Option<IngestionJob> {
+        self.jobs.read().await.get(&job_id).cloned()
+    }
+
+    async fn insert_job(&self, job: IngestionJob) {
+        let max_jobs = self.config.read().await.max_jobs_retained.max(1);
+        let mut jobs = self.jobs.write().await;
+        // 超出保留上限时移除最早完成的任务
+        while jobs.len() >= max_jobs {
+            let oldest = jobs
+                .values()
+                .filter(|j| j.finished_at.is_some())
+                .min_by_key(|j| j.submitted_at)
+                .map(|j| j.id);
+            match oldest {
+                Some(id) => jobs.remove(&id),
+                None => break,
+            };
+        }
+        jobs.insert(job.id, job);
+    }
+
+    async fn run_job(&self, job_id: Uuid, request: IngestRequest) {
+        self.update_job(job_id, |job| job.status = IngestionJobStatus::Running).await;
+        let default_priority = self.config.read().await.default_priority;
+
+        for document in request.documents {
+            if let Some(ref maintenance) = self.maintenance {
+                maintenance.wait_until_inactive().await;
+            }
+            let outcome = match self.scheduler {
+                Some(ref scheduler) => match scheduler.acquire(INGESTION_PROVIDER, CallPriority::Background).await {
+                    Ok(()) => self.ingest_document(&request.source, document, default_priority).await,
+                    Err(e) => Err(e.to_string()),
+                },
+                None => self.ingest_document(&request.source, document, default_priority).await,
+            };
+            self.update_job(job_id, |job| match outcome {
+                Ok(written) => {
+                    for (context_id, created) in written {
+                        if created {
+                            job.created += 1;
+                        } else {
+                            job.updated += 1;
+                        }
+                        job.context_ids.push(context_id);
+                    }
+                }
+                Err(e) => {
+                    job.failed += 1;
+                    job.errors.push(e);
+                }
+            })
+            .await;
+        }
+
+        self.update_job(job_id, |job| {
+            job.status = if job.failed == 0 {
+                IngestionJobStatus::Completed
+            } else if job.failed == job.total {
+                IngestionJobStatus::Failed
+            } else {
+                IngestionJobStatus::Partial
+            };
+            job.finished_at = Some(Utc::now());
+        })
+        .await;
+    }
+
+    /// 写入单个文档，返回写入的上下文ID及是否为新建；配置了分块上限时超长文档切分为多个上下文，
+    /// 同一external_id再次推送时按分块序号复用已有上下文，多出的旧分块被删除
+    pub(crate) async fn ingest_document(&self, source: &str, document: IngestDocument, default_priority: u8) -> Result<Vec<(Uuid, bool)>, String> {
+        let label = document.external_id.clone().unwrap_or_else(|| "<no id>".to_string());
+        let content = match document.title {
+            Some(ref title) => format!("{}\n\n{}", title, document.content),
+            None => document.content.clone(),
+        };
+        let mut metadata = document.metadata.clone();
+        metadata.insert(SOURCE_METADATA_KEY.to_string(), source.to_string());
+        if let Some(ref external_id) = document.external_id {
+            metadata.insert(EXTERNAL_ID_METADATA_KEY.to_string(), external_id.clone());
+        }
+        if let Some(ref title) = document.title {
+            metadata.insert(TITLE_METADATA_KEY.to_string(), title.clone());
+        }
+
+        let max_chunk_tokens = self.config.read().await.max_chunk_tokens;
+        let mut chunks = if max_chunk_tokens == 0 {
+            Vec::new()
+        } else {
+            chunk_at_sentences(&content, max_chunk_tokens, self.tokenizer.as_ref())
+        };
+        if chunks.is_empty() {
+            chunks.push(content);
+        }
+
+        let mut existing = Vec::new();
+        if let Some(ref external_id) = document.external_id {
+            existing = self
+                .context_manager
+                .get_domain_contexts(&document.domain)
+                .await
+                .into_iter()
+                .filter(|ctx| {
+                    ctx.metadata.get(SOURCE_METADATA_KEY).map(String::as_str) == Some(source)
+                        && ctx.metadata.get(EXTERNAL_ID_METADATA_KEY) == Some(external_id)
+                })
+                .collect();
+            existing.sort_by_key(|ctx| {
+                ctx.metadata.get(CHUNK_INDEX_METADATA_KEY).and_then(|index| index.parse::<usize>().ok()).unwrap_or(0)
+            });
+        }
+
+        let chunk_count = chunks.len();
+        let mut written = Vec::with_capacity(chunk_count);
+        for (index, chunk) in chunks.into_iter().enumerate() {
+            let mut metadata = metadata.clone();
+            if chunk_count > 1 {
+                metadata.insert(CHUNK_INDEX_METADATA_KEY.to_string(), index.to_string());
+                metadata.insert(CHUNK_COUNT_METADATA_KEY.to_string(), chunk_count.to_string());
+            }
+            if let Some(previous) = existing.get(index) {
+                self.context_manager
+                    .update_context(previous.id, Some(chunk), Some(metadata), document.priority)
+                    .await
+                    .map_err(|e| format!("{}: {}", label, e))?;
+                written.push((previous.id, false));
+                continue;
+            }
+            let context = self
+                .context_manager
+                .create_context_with_metadata(
+                    document.session_id.clone().unwrap_or_else(|| format!("ingest:{}", source)),
+                    document.user_id.clone().unwrap_or_else(|| source.to_string()),
+                    document.domain.clone(),
+                    chunk,
+                    document.priority.unwrap_or(default_priority),
+                    metadata,
+                )
+                .await
+                .map_err(|e| format!("{}: {}", label, e))?;
+            written.push((context.id, true));
+        }
+
+        // 文档变短后多出的旧分块
+        for stale in existing.iter().skip(chunk_count) {
+            self.context_manager
+                .delete_context(stale.id)
+                .await
+                .map_err(|e| format!("{}: {}", label, e))?;
+        }
+        Ok(written)
+    }
+
+    async fn update_job<F: FnOnce(&mut IngestionJob)>(&self, job_id: Uuid, update: F) {
+        if let Some(job) = self.jobs.write().await.get_mut(&job_id) {
+            update(job);
+        }
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: IngestionConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> IngestionConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signed_ingestion_job() {
+        use crate::context::metadata_schema::{MetadataSchema, MetadataSchemaRegistry};
+
+        // 领域模式不允许未知键时，入库写入的来源、文档ID和标题仍可通过校验
+        let schemas = Arc::new(MetadataSchemaRegistry::new());
+        schemas.register(MetadataSchema::new("education").allow_unknown_keys(false)).await;
+        let manager = Arc::new(ContextManager::new(10, 3600).with_metadata_schemas(schemas));
+        let service = Arc::new(IngestionService::new(manager.clone()));
+        let mut config = service.get_config().await;
+        config.shared_secret = Some("s3cret".to_string());
+        service.update_config(config).await;
+
+        let body = serde_json::to_vec(&serde_json::json!({
+            "source": "wiki",
+            "documents": [{ "external_id": "page-1", "title": "Onboarding", "content": "Read the handbook", "domain": "education" }]
+        }))
+        .unwrap();
+        let now = Utc::now().timestamp();
+        let timestamp = now.to_string();
+        assert_eq!(service.authenticate(&body, Some(&timestamp), None).await.unwrap_err(), WebhookRejection::MissingSignature);
+        assert_eq!(
+            service.authenticate(&body, Some(&timestamp), Some("sha256=00")).await.unwrap_err(),
+            WebhookRejection::InvalidSignature
+        );
+        // 未带时间戳的签名、过期的时间戳均被拒绝
+        let untimed = sign_payload("s3cret", &body);
+        assert_eq!(service.authenticate(&body, None, Some(&untimed)).await.unwrap_err(), WebhookRejection::MissingTimestamp);
+        assert_eq!(
+            service.authenticate(&body, Some(&timestamp), Some(&untimed)).await.unwrap_err(),
+            WebhookRejection::InvalidSignature
+        );
+        let stale = (now - 600).to_string();
+        assert_eq!(
+            service.authenticate(&body, Some(&stale), Some(&sign_timestamped("s3cret", now - 600, &body))).await.unwrap_err(),
+            WebhookRejection::StaleTimestamp
+        );
+
+        let signature = sign_timestamped("s3cret", now, &body);
+        let request = service.authenticate(&body, Some(&timestamp), Some(&signature)).await.unwrap();
+        let job_id = service.submit(request.clone()).await;
+        let job = wait_for(&service, job_id).await;
+        assert_eq!(job.status, IngestionJobStatus::Completed);
+        assert_eq!(job.created, 1);
+
+        // 同一external_id再次推送时更新原上下文
+        let mut update = request;
+        update.documents[0].content = "Read the updated handbook".to_string();
+        let job = wait_for(&service, service.submit(update).await).await;
+        assert_eq!(job.updated, 1);
+        let contexts = manager.get_domain_contexts("education").await;
+        assert_eq!(contexts.len(), 1);
+        assert_eq!(contexts[0].context_data, "Onboarding\n\nRead the updated handbook");
+        assert_eq!(contexts[0].metadata["external_id"], "page-1");
+    }
+
+    async fn wait_for(service: &IngestionService, job_id: Uuid) -> IngestionJob {
+        for _ in 0..100 {
+            let job = service.get_job(job_id).await.unwrap();
+            if job.finished_at.is_some() {
+                return job;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        panic!("ingestion job did not finish");
+    }
+}
@@ -1,9 +1,29 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{RwLock, Semaphore};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard, Semaphore};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use crate::cache::bloom::{BloomFilterConfig, BloomFilterGuard, BloomGuardStats};
+use crate::cache::cache::{CacheKey, CacheManager};
+use crate::domain::language::{retag_language, tag_language};
+use crate::context::access::{AccessDirectory, Viewer, Visibility};
+use crate::context::approval::{ApprovalState, ApprovalWorkflow};
+use crate::context::audit::AuditAction;
+use crate::context::codec::ContextCodec;
+use crate::context::hooks::{HookRegistry, LifecycleEvent};
+use crate::context::metadata_schema::{MetadataSchemaRegistry, MetadataViolation};
+use crate::context::stats::{ContextCardinalityStats, ContextStatsTracker};
+use crate::context::gc::{GcReason, GcTracker};
+use crate::context::compaction::IndexKind;
+use crate::context::replica::ReadOnlyError;
+use crate::context::schema_migration::{self, CURRENT_SCHEMA_VERSION};
+use crate::context::version_history::VersionHistory;
+use crate::context::lock_metrics::{ContextManagerMetrics, LockMetricsReport};
+use crate::maintenance::MaintenanceMode;
+use crate::monitoring::metrics::MetricsRegistry;
+use crate::context::id_strategy::{time_sort_key, ContextIdGenerator, IdStrategy};
 
 /// 大模型上下文结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +41,12 @@ pub struct LLMContext {
     pub version: u32,                 // 版本号
     pub tags: Vec<String>,            // 标签
     pub active: bool,                 // 是否活跃
+    #[serde(default)]
+    pub approval_state: ApprovalState, // 审批状态
+    #[serde(default)]
+    pub visibility: Visibility,       // 可见范围
+    #[serde(default = "schema_migration::legacy_schema_version")]
+    pub schema_version: u32,          // 记录的结构版本，加载时升级到当前版本
 }
 
 /// 上下文管理器 - 企业级大模型上下文管理
@@ -39,6 +65,84 @@ pub struct ContextManager {
     max_concurrent: usize,
     /// 上下文过期时间（秒）
     context_ttl: u64,
+    /// 可选的读穿缓存，用于会话、用户和领域上下文查询
+    cache: Option<Arc<CacheManager>>,
+    /// 缓存失效代数，查询期间发生失效时不回填缓存
+    cache_generation: AtomicU64,
+    /// 可选的布隆过滤器，按上下文ID跳过一定不存在的查询
+    bloom_guard: Option<Arc<BloomFilterGuard>>,
+    /// 可选的领域元数据模式，写入时校验
+    metadata_schemas: Option<Arc<MetadataSchemaRegistry>>,
+    /// 可选的生命周期钩子
+    hooks: Option<Arc<HookRegistry>>,
+    /// 可选的审批流程
+    approval: Option<Arc<ApprovalWorkflow>>,
+    /// 团队和租户成员目录（用于可见范围检查）
+    access: Arc<AccessDirectory>,
+    /// 增量维护的基数统计
+    stats: Arc<ContextStatsTracker>,
+    /// 回收事件流与指标
+    gc: Arc<GcTracker>,
+    /// 新上下文的ID生成器
+    id_generator: Arc<ContextIdGenerator>,
+    /// 只读模式（副本），拒绝所有写操作
+    read_only: AtomicBool,
+    /// 可选的维护模式开关，维护期间拒绝写操作
+    maintenance: Option<Arc<MaintenanceMode>>,
+    /// 可选的版本历史，用于按时间点检索
+    history: Option<Arc<VersionHistory>>,
+    /// 锁等待和操作耗时指标
+    metrics: Arc<ContextManagerMetrics>,
+}
+
+/// 续期时长超出可表示的时间范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTtlError {
+    pub extra_ttl_seconds: u64,
+}
+
+impl std::fmt::Display for InvalidTtlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "extra_ttl_seconds {} is out of range", self.extra_ttl_seconds)
+    }
+}
+
+impl std::error::Error for InvalidTtlError {}
+
+/// 元数据迁移报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataMigrationReport {
+    pub domain: String,
+    pub scanned: usize,                                   // 检查的上下文数量
+    pub migrated: Vec<Uuid>,                              // 元数据被更新的上下文
+    pub invalid: Vec<(Uuid, Vec<MetadataViolation>)>,     // 迁移后仍不符合模式的上下文
+}
+
+/// 索引表：键 -> 上下文ID列表
+type ContextIndex = RwLock<HashMap<String, Vec<Uuid>>>;
+
+/// 把ID加入索引键（已存在时不重复加入）
+fn index(index: &mut HashMap<String, Vec<Uuid>>, key: &str, id: Uuid) {
+    let ids = index.entry(key.to_string()).or_default();
+    if !ids.contains(&id) {
+        ids.push(id);
+    }
+}
+
+/// 从索引键中移除ID
+fn unindex(index: &mut HashMap<String, Vec<Uuid>>, key: &str, id: Uuid) {
+    if let Some(ids) = index.get_mut(key) {
+        ids.retain(|existing| *existing != id);
+    }
+}
+
+/// 索引表在锁指标中的名称
+fn index_lock_name(kind: IndexKind) -> &'static str {
+    match kind {
+        IndexKind::Session => "session_index",
+        IndexKind::User => "user_index",
+        IndexKind::Domain => "domain_index",
+    }
 }
 
 impl ContextManager {
@@ -52,6 +156,180 @@ impl ContextManager {
             concurrency_limiter: Arc::new(Semaphore::new(max_concurrent)),
             max_concurrent,
             context_ttl: context_ttl_seconds,
+            cache: None,
+            cache_generation: AtomicU64::new(0),
+            bloom_guard: None,
+            metadata_schemas: None,
+            hooks: None,
+            approval: None,
+            access: Arc::new(AccessDirectory::new()),
+            stats: Arc::new(ContextStatsTracker::new()),
+            gc: Arc::new(GcTracker::new()),
+            id_generator: Arc::new(ContextIdGenerator::default()),
+            read_only: AtomicBool::new(false),
+            maintenance: None,
+            history: None,
+            metrics: Arc::new(ContextManagerMetrics::new(Arc::new(MetricsRegistry::new()))),
+        }
+    }
+
+    /// 把锁等待和操作耗时指标写入指定的注册表（如监控系统的注册表）
+    pub fn with_metrics_registry(mut self, registry: Arc<MetricsRegistry>) -> Self {
+        self.metrics = Arc::new(ContextManagerMetrics::new(registry));
+        self
+    }
+
+    /// 锁等待和操作耗时汇总
+    pub fn get_lock_metrics(&self) -> LockMetricsReport {
+        self.metrics.report()
+    }
+
+    /// 启用读穿缓存：会话、用户和领域查询优先读取缓存，未命中时加载并回填，上下文变更时自动失效
+    pub fn with_cache(mut self, cache: Arc<CacheManager>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// 启用布隆过滤器：查询一定不存在的上下文ID时不访问存储
+    pub fn with_bloom_filter(mut self, config: BloomFilterConfig) -> Self {
+        self.bloom_guard = Some(Arc::new(BloomFilterGuard::new(config)));
+        self
+    }
+
+    /// 启用领域元数据模式校验
+    pub fn with_metadata_schemas(mut self, registry: Arc<MetadataSchemaRegistry>) -> Self {
+        self.metadata_schemas = Some(registry);
+        self
+    }
+
+    /// 获取元数据模式注册表
+    pub fn get_metadata_schemas(&self) -> Option<Arc<MetadataSchemaRegistry>> {
+        self.metadata_schemas.clone()
+    }
+
+    /// 注册生命周期钩子，钩子在操作完成后执行，失败和超时不影响操作结果
+    pub fn with_hooks(mut self, hooks: Arc<HookRegistry>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// 启用版本历史：记录每个版本的生效区间，支持按时间点检索
+    pub fn with_version_history(mut self, history: Arc<VersionHistory>) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// 获取版本历史
+    pub fn get_version_history(&self) -> Option<Arc<VersionHistory>> {
+        self.history.clone()
+    }
+
+    /// 获取指定时间点有效的上下文版本（需要启用版本历史）
+    pub async fn get_contexts_as_of(&self, at: DateTime<Utc>) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let history = self.history.as_ref().ok_or("Version history not enabled")?;
+        Ok(history.get_contexts_as_of(at).await)
+    }
+
+    /// 启用审批流程：需要审批的领域中新建的上下文为草稿，批准后才参与选择
+    pub fn with_approval_workflow(mut self, approval: Arc<ApprovalWorkflow>) -> Self {
+        self.approval = Some(approval);
+        self
+    }
+
+    /// 获取审批流程
+    pub fn get_approval_workflow(&self) -> Option<Arc<ApprovalWorkflow>> {
+        self.approval.clone()
+    }
+
+    /// 使用指定的成员目录
+    pub fn with_access_directory(mut self, access: Arc<AccessDirectory>) -> Self {
+        self.access = access;
+        self
+    }
+
+    /// 设置新上下文的ID生成策略（已有上下文的ID保持不变）
+    pub fn with_id_strategy(mut self, strategy: IdStrategy) -> Self {
+        self.id_generator = Arc::new(ContextIdGenerator::new(strategy));
+        self
+    }
+
+    /// 维护期间拒绝写操作，保证维护快照和迁移看到一致的存储
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 开启或关闭只读模式
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::SeqCst);
+    }
+
+    /// 是否处于只读模式
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
+
+    /// 写操作前检查只读模式和维护模式
+    fn ensure_writable(&self, operation: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.is_read_only() {
+            return Err(Box::new(ReadOnlyError { operation: operation.to_string() }));
+        }
+        if let Some(ref maintenance) = self.maintenance {
+            maintenance.ensure_inactive()?;
+        }
+        Ok(())
+    }
+
+    /// 获取成员目录
+    pub fn get_access_directory(&self) -> Arc<AccessDirectory> {
+        self.access.clone()
+    }
+
+    /// 过滤出对指定身份可见的上下文（所有检索路径在评分前调用），同时检查可见范围和审批状态
+    pub async fn filter_visible(&self, contexts: Vec<LLMContext>, viewer: &Viewer) -> Vec<LLMContext> {
+        let mut visible = Vec::with_capacity(contexts.len());
+        for context in contexts {
+            if !self.access.can_view(&context, viewer).await {
+                continue;
+            }
+            if let Some(ref approval) = self.approval {
+                if !approval.is_visible(&context, viewer.user_id.as_deref()).await {
+                    continue;
+                }
+            }
+            visible.push(context);
+        }
+        visible
+    }
+
+    /// 分发生命周期事件（先记录版本历史）
+    async fn dispatch_hooks(&self, event: LifecycleEvent) {
+        if let Some(ref history) = self.history {
+            match event {
+                LifecycleEvent::Created(ref context) | LifecycleEvent::Updated { current: ref context, .. } => {
+                    history.record_version(context).await
+                }
+                LifecycleEvent::Deleted(ref context) => history.record_removal(context.id, Utc::now()).await,
+                LifecycleEvent::Expired(ref context) => {
+                    history.record_removal(context.id, context.expires_at.unwrap_or_else(Utc::now)).await
+                }
+                LifecycleEvent::ExpiringSoon(_) => {}
+            }
+        }
+        if let Some(ref hooks) = self.hooks {
+            hooks.dispatch(event).await;
+        }
+    }
+
+    /// 按领域模式填充默认值并校验元数据
+    async fn prepare_metadata(
+        &self,
+        domain: &str,
+        metadata: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, Box<dyn std::error::Error + Send + Sync>> {
+        match self.metadata_schemas {
+            Some(ref registry) => registry.prepare(domain, metadata).await,
+            None => Ok(metadata),
         }
     }
 
@@ -64,13 +342,51 @@ impl ContextManager {
         context_data: String,
         priority: u8,
     ) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        self.create_context_with_metadata(session_id, user_id, domain, context_data, priority, HashMap::new())
+            .await
+    }
+
+    /// 创建带元数据的上下文，启用模式时按领域校验
+    pub async fn create_context_with_metadata(
+        &self,
+        session_id: String,
+        user_id: String,
+        domain: String,
+        context_data: String,
+        priority: u8,
+        metadata: HashMap<String, String>,
+    ) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        self.create_context_with_visibility(session_id, user_id, domain, context_data, priority, metadata, Visibility::default())
+            .await
+    }
+
+    /// 创建指定可见范围的上下文
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_context_with_visibility(
+        &self,
+        session_id: String,
+        user_id: String,
+        domain: String,
+        context_data: String,
+        priority: u8,
+        metadata: HashMap<String, String>,
+        visibility: Visibility,
+    ) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        let _timer = self.metrics.time_operation("create_context");
+        self.ensure_writable("create_context")?;
+        let mut metadata = self.prepare_metadata(&domain, metadata).await?;
+        tag_language(&mut metadata, &context_data);
+        let approval_state = match self.approval {
+            Some(ref approval) => approval.initial_state(&domain).await,
+            None => ApprovalState::Approved,
+        };
         let context = LLMContext {
-            id: Uuid::new_v4(),
+            id: self.id_generator.generate(),
             session_id: session_id.clone(),
             user_id: user_id.clone(),
             domain: domain.clone(),
             context_data,
-            metadata: HashMap::new(),
+            metadata,
             created_at: Utc::now(),
             updated_at: Utc::now(),
             expires_at: Some(Utc::now() + chrono::Duration::seconds(self.context_ttl as i64)),
@@ -78,23 +394,45 @@ impl ContextManager {
             version: 1,
             tags: Vec::new(),
             active: true,
+            approval_state,
+            visibility,
+            schema_version: CURRENT_SCHEMA_VERSION,
         };
 
-        // 存储上下文
+        // 存储上下文（持有存储写锁时先记录布隆过滤器，避免并发查询或重建造成漏判）
         {
-            let mut contexts = self.contexts.write().await;
+            let mut contexts = self.write_contexts().await;
+            if let Some(ref guard) = self.bloom_guard {
+                guard.insert(&context.id).await;
+            }
             contexts.insert(context.id, context.clone());
         }
+        self.stats.record_added(&context).await;
 
         // 更新索引
         self.update_indexes(context.clone()).await;
+        self.invalidate_cached(&context).await;
+        if let (Some(ref approval), ApprovalState::Draft) = (&self.approval, approval_state) {
+            approval
+                .get_audit_log()
+                .record(context.id, &context.user_id, AuditAction::Created, None, Some(approval_state), None)
+                .await;
+        }
+        self.dispatch_hooks(LifecycleEvent::Created(context.clone())).await;
 
         Ok(context)
     }
 
     /// 获取上下文
     pub async fn get_context(&self, context_id: Uuid) -> Option<LLMContext> {
-        let contexts = self.contexts.read().await;
+        let _timer = self.metrics.time_operation("get_context");
+        if let Some(ref guard) = self.bloom_guard {
+            if !guard.might_contain(&context_id).await {
+                return None;
+            }
+        }
+
+        let contexts = self.read_contexts().await;
         if let Some(context) = contexts.get(&context_id) {
             // 检查是否过期
             if let Some(expires_at) = context.expires_at {
@@ -105,63 +443,96 @@ impl ContextManager {
             }
             Some(context.clone())
         } else {
+            if let Some(ref guard) = self.bloom_guard {
+                guard.record_false_positive();
+            }
             None
         }
     }
 
+    /// 用当前存储的上下文ID重建布隆过滤器（清除已删除ID的残留位），重建期间新建的上下文会重放到新过滤器
+    pub async fn rebuild_bloom_filter(&self) {
+        if let Some(ref guard) = self.bloom_guard {
+            guard
+                .rebuild_from(async { self.read_contexts().await.keys().copied().collect::<Vec<Uuid>>() })
+                .await;
+        }
+    }
+
+    /// 按配置的间隔定期重建布隆过滤器；未启用布隆过滤器时返回None
+    pub async fn start_bloom_rebuild(self: Arc<Self>) -> Option<tokio::task::JoinHandle<()>> {
+        let interval_seconds = self.bloom_guard.as_ref()?.get_config().await.rebuild_interval_seconds;
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(interval_seconds.max(1)));
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.rebuild_bloom_filter().await;
+            }
+        }))
+    }
+
+    /// 获取布隆过滤器统计；未启用时返回None
+    pub async fn get_bloom_stats(&self) -> Option<BloomGuardStats> {
+        match self.bloom_guard {
+            Some(ref guard) => Some(guard.get_stats().await),
+            None => None,
+        }
+    }
+
     /// 获取会话的所有上下文
     pub async fn get_session_contexts(&self, session_id: &str) -> Vec<LLMContext> {
-        let session_contexts = self.session_contexts.read().await;
-        if let Some(context_ids) = session_contexts.get(session_id) {
-            let contexts = self.contexts.read().await;
-            context_ids
-                .iter()
-                .filter_map(|id| {
-                    contexts.get(id).cloned().and_then(|ctx| {
-                        // 检查是否过期
-                        if let Some(expires_at) = ctx.expires_at {
-                            if Utc::now() > expires_at {
-                                return None;
-                            }
-                        }
-                        Some(ctx)
-                    })
-                })
-                .collect()
-        } else {
-            Vec::new()
-        }
+        let _timer = self.metrics.time_operation("get_session_contexts");
+        self.read_through(CacheKey::SessionId(session_id.to_string()), IndexKind::Session, session_id)
+            .await
     }
 
     /// 获取用户的所有上下文
     pub async fn get_user_contexts(&self, user_id: &str) -> Vec<LLMContext> {
-        let user_contexts = self.user_contexts.read().await;
-        if let Some(context_ids) = user_contexts.get(user_id) {
-            let contexts = self.contexts.read().await;
-            context_ids
-                .iter()
-                .filter_map(|id| {
-                    contexts.get(id).cloned().and_then(|ctx| {
-                        // 检查是否过期
-                        if let Some(expires_at) = ctx.expires_at {
-                            if Utc::now() > expires_at {
-                                return None;
-                            }
-                        }
-                        Some(ctx)
-                    })
-                })
-                .collect()
-        } else {
-            Vec::new()
-        }
+        let _timer = self.metrics.time_operation("get_user_contexts");
+        self.read_through(CacheKey::UserId(user_id.to_string()), IndexKind::User, user_id)
+            .await
     }
 
     /// 获取特定领域的上下文
     pub async fn get_domain_contexts(&self, domain: &str) -> Vec<LLMContext> {
-        let domain_contexts = self.domain_contexts.read().await;
-        if let Some(context_ids) = domain_contexts.get(domain) {
-            let contexts = self.contexts.read().await;
+        let _timer = self.metrics.time_operation("get_domain_contexts");
+        self.read_through(CacheKey::Domain(domain.to_string()), IndexKind::Domain, domain)
+            .await
+    }
+
+    /// 读穿查询：优先返回缓存（过滤已过期的上下文），未命中时从索引加载并回填
+    async fn read_through(&self, cache_key: CacheKey, index: IndexKind, key: &str) -> Vec<LLMContext> {
+        let cache = match self.cache {
+            Some(ref cache) => cache,
+            None => return self.load_indexed_contexts(index, key).await,
+        };
+
+        if let Some(cached) = cache.get_context(&cache_key).await {
+            let now = Utc::now();
+            return cached
+                .into_iter()
+                .filter(|ctx| ctx.expires_at.map(|expires_at| now <= expires_at).unwrap_or(true))
+                .collect();
+        }
+
+        let generation = self.cache_generation.load(Ordering::Acquire);
+        let contexts = self.load_indexed_contexts(index, key).await;
+        if self.cache_generation.load(Ordering::Acquire) == generation {
+            cache.put_context(cache_key.clone(), contexts.clone()).await;
+            // 检查与回填之间发生的失效可能先于回填执行，回填后代数变化时移除可能过期的缓存项
+            if self.cache_generation.load(Ordering::Acquire) != generation {
+                cache.remove_context(&cache_key).await;
+            }
+        }
+        contexts
+    }
+
+    /// 从索引加载未过期的上下文
+    async fn load_indexed_contexts(&self, index: IndexKind, key: &str) -> Vec<LLMContext> {
+        let index = self.read_index(index).await;
+        if let Some(context_ids) = index.get(key) {
+            let contexts = self.read_contexts().await;
             context_ids
                 .iter()
                 .filter_map(|id| {
@@ -181,6 +552,43 @@ impl ContextManager {
         }
     }
 
+    /// 使与上下文相关的缓存项失效
+    async fn invalidate_cached(&self, context: &LLMContext) {
+        if let Some(ref cache) = self.cache {
+            self.cache_generation.fetch_add(1, Ordering::AcqRel);
+            cache.remove_context(&CacheKey::SessionId(context.session_id.clone())).await;
+            cache.remove_context(&CacheKey::UserId(context.user_id.clone())).await;
+            cache.remove_context(&CacheKey::Domain(context.domain.clone())).await;
+        }
+    }
+
+    /// 获取所有未过期的上下文
+    pub async fn get_all_contexts(&self) -> Vec<LLMContext> {
+        let _timer = self.metrics.time_operation("get_all_contexts");
+        let now = Utc::now();
+        let contexts = self.read_contexts().await;
+        contexts
+            .values()
+            .filter(|ctx| ctx.expires_at.map(|expires_at| now <= expires_at).unwrap_or(true))
+            .cloned()
+            .collect()
+    }
+
+    /// 获取创建时间在[start, end)内的上下文，按时间排序；时间优先取自ID，旧的v4 ID回退到创建时间
+    pub async fn get_contexts_created_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Vec<LLMContext> {
+        let mut contexts: Vec<LLMContext> = self
+            .get_all_contexts()
+            .await
+            .into_iter()
+            .filter(|ctx| {
+                let (created, _) = time_sort_key(ctx);
+                created >= start && created < end
+            })
+            .collect();
+        contexts.sort_by_key(time_sort_key);
+        contexts
+    }
+
     /// 更新上下文
     pub async fn update_context(
         &self,
@@ -189,9 +597,33 @@ impl ContextManager {
         metadata: Option<HashMap<String, String>>,
         priority: Option<u8>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let mut contexts = self.contexts.write().await;
-        if let Some(context) = contexts.get_mut(&context_id) {
+        let _timer = self.metrics.time_operation("update_context");
+        self.ensure_writable("update_context")?;
+        let metadata = match metadata {
+            Some(meta) if self.metadata_schemas.is_some() => {
+                let domain = self.read_contexts().await.get(&context_id).ok_or("Context not found")?.domain.clone();
+                Some(self.prepare_metadata(&domain, meta).await?)
+            }
+            other => other,
+        };
+
+        let requires_approval = match self.approval {
+            Some(ref approval) if context_data.is_some() => {
+                let domain = self.read_contexts().await.get(&context_id).ok_or("Context not found")?.domain.clone();
+                approval.requires_approval(&domain).await
+            }
+            _ => false,
+        };
+
+        let (previous, updated) = {
+            let mut contexts = self.write_contexts().await;
+            let context = contexts.get_mut(&context_id).ok_or("Context not found")?;
+            let previous = context.clone();
             if let Some(data) = context_data {
+                // 受审批的内容被修改后需要重新审核
+                if requires_approval && data != context.context_data {
+                    context.approval_state = ApprovalState::Draft;
+                }
                 context.context_data = data;
             }
             if let Some(meta) = metadata {
@@ -200,33 +632,295 @@ impl ContextManager {
             if let Some(pri) = priority {
                 context.priority = pri;
             }
+            retag_language(&previous, context);
             context.updated_at = Utc::now();
             context.version += 1;
+            (previous, context.clone())
+        };
 
-            // 更新索引
-            self.update_indexes(context.clone()).await;
-            Ok(())
-        } else {
-            Err("Context not found".into())
+        // 更新索引（在释放存储锁之后进行，避免与读路径的锁顺序相反）
+        self.stats.record_replaced(&previous, &updated).await;
+        self.update_indexes(updated.clone()).await;
+        self.invalidate_cached(&updated).await;
+        if let Some(ref approval) = self.approval {
+            if previous.approval_state != updated.approval_state {
+                approval
+                    .get_audit_log()
+                    .record(
+                        updated.id,
+                        &updated.user_id,
+                        AuditAction::Revised,
+                        Some(previous.approval_state),
+                        Some(updated.approval_state),
+                        None,
+                    )
+                    .await;
+            }
         }
+        self.dispatch_hooks(LifecycleEvent::Updated { previous: Box::new(previous), current: updated }).await;
+        Ok(())
+    }
+
+    /// 修改上下文的可见范围
+    pub async fn set_visibility(&self, context_id: Uuid, visibility: Visibility) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _timer = self.metrics.time_operation("set_visibility");
+        self.ensure_writable("set_visibility")?;
+        let (previous, updated) = {
+            let mut contexts = self.write_contexts().await;
+            let context = contexts.get_mut(&context_id).ok_or("Context not found")?;
+            let previous = context.clone();
+            context.visibility = visibility;
+            context.updated_at = Utc::now();
+            context.version += 1;
+            (previous, context.clone())
+        };
+
+        self.update_indexes(updated.clone()).await;
+        self.invalidate_cached(&updated).await;
+        self.dispatch_hooks(LifecycleEvent::Updated { previous: Box::new(previous), current: updated }).await;
+        Ok(())
+    }
+
+    /// 设置上下文优先级（供优先级自动调整使用）：内容未变化，因此不更新修改时间和版本号，也不触发钩子
+    pub async fn set_priority(&self, context_id: Uuid, priority: u8) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _timer = self.metrics.time_operation("set_priority");
+        self.ensure_writable("set_priority")?;
+        let (previous, updated) = {
+            let mut contexts = self.write_contexts().await;
+            let context = contexts.get_mut(&context_id).ok_or("Context not found")?;
+            let previous = context.clone();
+            context.priority = priority;
+            (previous, context.clone())
+        };
+
+        self.stats.record_replaced(&previous, &updated).await;
+        self.invalidate_cached(&updated).await;
+        Ok(())
+    }
+
+    /// 续期上下文：在当前过期时间（已过期时为现在）基础上延长extra_ttl_seconds，不过期的上下文保持不变；
+    /// 延长后超出可表示的时间范围时返回`InvalidTtlError`
+    pub async fn renew_context(&self, context_id: Uuid, extra_ttl_seconds: u64) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        let _timer = self.metrics.time_operation("renew_context");
+        self.ensure_writable("renew_context")?;
+        let (previous, updated) = {
+            let mut contexts = self.write_contexts().await;
+            let context = contexts.get_mut(&context_id).ok_or("Context not found")?;
+            let Some(expires_at) = context.expires_at else {
+                return Ok(context.clone());
+            };
+            let previous = context.clone();
+            let now = Utc::now();
+            let renewed_until = i64::try_from(extra_ttl_seconds)
+                .ok()
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|extra| expires_at.max(now).checked_add_signed(extra))
+                .ok_or(InvalidTtlError { extra_ttl_seconds })?;
+            context.expires_at = Some(renewed_until);
+            context.updated_at = now;
+            context.version += 1;
+            (previous, context.clone())
+        };
+
+        self.stats.record_replaced(&previous, &updated).await;
+        self.update_indexes(updated.clone()).await;
+        self.invalidate_cached(&updated).await;
+        self.dispatch_hooks(LifecycleEvent::Updated { previous: Box::new(previous), current: updated.clone() }).await;
+        Ok(updated)
+    }
+
+    /// 获取在指定时间内过期、优先级不低于min_priority的上下文（按过期时间排序）
+    pub async fn get_expiring_contexts(&self, within: chrono::Duration, min_priority: u8) -> Vec<LLMContext> {
+        let now = Utc::now();
+        let deadline = now + within;
+        let mut expiring: Vec<LLMContext> = self
+            .contexts
+            .read()
+            .await
+            .values()
+            .filter(|context| context.priority >= min_priority)
+            .filter(|context| matches!(context.expires_at, Some(expires_at) if expires_at > now && expires_at <= deadline))
+            .cloned()
+            .collect();
+        expiring.sort_by_key(|context| context.expires_at);
+        expiring
+    }
+
+    /// 向钩子发送即将过期事件
+    pub(crate) async fn notify_expiring(&self, context: LLMContext) {
+        self.dispatch_hooks(LifecycleEvent::ExpiringSoon(context)).await;
+    }
+
+    /// 提交上下文审核
+    pub async fn submit_for_review(&self, context_id: Uuid, actor: &str) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        self.transition_approval(context_id, actor, AuditAction::Submitted, None).await
+    }
+
+    /// 批准上下文，审核人记录在审计日志中
+    pub async fn approve_context(
+        &self,
+        context_id: Uuid,
+        reviewer: &str,
+        note: Option<String>,
+    ) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        self.transition_approval(context_id, reviewer, AuditAction::Approved, note).await
+    }
+
+    /// 驳回上下文，审核人记录在审计日志中
+    pub async fn reject_context(
+        &self,
+        context_id: Uuid,
+        reviewer: &str,
+        note: Option<String>,
+    ) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        self.transition_approval(context_id, reviewer, AuditAction::Rejected, note).await
+    }
+
+    /// 执行审批状态转换
+    async fn transition_approval(
+        &self,
+        context_id: Uuid,
+        actor: &str,
+        action: AuditAction,
+        note: Option<String>,
+    ) -> Result<LLMContext, Box<dyn std::error::Error + Send + Sync>> {
+        self.ensure_writable("transition_approval")?;
+        let approval = self.approval.as_ref().ok_or("Approval workflow not enabled")?;
+        let (previous, updated) = {
+            let mut contexts = self.write_contexts().await;
+            let context = contexts.get_mut(&context_id).ok_or("Context not found")?;
+            let target = approval.check_transition(context, actor, action).await?;
+            let previous = context.clone();
+            context.approval_state = target;
+            context.updated_at = Utc::now();
+            context.version += 1;
+            (previous, context.clone())
+        };
+
+        self.update_indexes(updated.clone()).await;
+        self.invalidate_cached(&updated).await;
+        approval
+            .get_audit_log()
+            .record(context_id, actor, action, Some(previous.approval_state), Some(updated.approval_state), note)
+            .await;
+        self.dispatch_hooks(LifecycleEvent::Updated { previous: Box::new(previous), current: updated.clone() }).await;
+        Ok(updated)
     }
 
     /// 删除上下文
     pub async fn delete_context(&self, context_id: Uuid) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let mut contexts = self.contexts.write().await;
-        if let Some(context) = contexts.remove(&context_id) {
+        let _timer = self.metrics.time_operation("delete_context");
+        self.ensure_writable("delete_context")?;
+        let removed = self.write_contexts().await.remove(&context_id);
+        if let Some(context) = removed {
             // 从索引中移除
-            self.remove_from_indexes(context).await;
+            self.stats.record_removed(&context).await;
+            self.invalidate_cached(&context).await;
+            self.remove_from_indexes(context.clone()).await;
+            self.gc.record(&context, GcReason::Deleted).await;
+            self.dispatch_hooks(LifecycleEvent::Deleted(context)).await;
             Ok(())
         } else {
             Err("Context not found".into())
         }
     }
 
+    /// 将from_user的全部上下文（含已过期未清理的）转交给to_user，返回涉及的上下文ID；dry_run时只统计
+    pub async fn transfer_contexts(
+        &self,
+        from_user: &str,
+        to_user: &str,
+        dry_run: bool,
+    ) -> Result<Vec<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+        let _timer = self.metrics.time_operation("transfer_contexts");
+        if to_user.is_empty() || from_user == to_user {
+            return Err("Target user must be a different, non-empty user ID".into());
+        }
+        if dry_run {
+            return Ok(self.owned_context_ids(from_user).await);
+        }
+        self.ensure_writable("transfer_contexts")?;
+        let transferred: Vec<(LLMContext, LLMContext)> = {
+            let mut contexts = self.write_contexts().await;
+            let now = Utc::now();
+            contexts
+                .values_mut()
+                .filter(|context| context.user_id == from_user)
+                .map(|context| {
+                    let previous = context.clone();
+                    context.user_id = to_user.to_string();
+                    context.updated_at = now;
+                    context.version += 1;
+                    (previous, context.clone())
+                })
+                .collect()
+        };
+
+        let mut ids = Vec::with_capacity(transferred.len());
+        for (previous, updated) in transferred {
+            ids.push(updated.id);
+            self.stats.record_replaced(&previous, &updated).await;
+            self.invalidate_cached(&previous).await;
+            self.remove_from_indexes(previous.clone()).await;
+            self.update_indexes(updated.clone()).await;
+            self.invalidate_cached(&updated).await;
+            self.dispatch_hooks(LifecycleEvent::Updated { previous: Box::new(previous), current: updated }).await;
+        }
+        {
+            let mut user_contexts = self.write_index(IndexKind::User).await;
+            if user_contexts.get(from_user).is_some_and(Vec::is_empty) {
+                user_contexts.remove(from_user);
+            }
+        }
+        Ok(ids)
+    }
+
+    /// 删除用户的全部上下文及其版本历史（用户数据删除），返回删除的上下文ID；dry_run时只统计
+    pub async fn purge_user_contexts(&self, user_id: &str, dry_run: bool) -> Result<Vec<Uuid>, Box<dyn std::error::Error + Send + Sync>> {
+        let _timer = self.metrics.time_operation("purge_user_contexts");
+        let ids = self.owned_context_ids(user_id).await;
+        if dry_run {
+            return Ok(ids);
+        }
+        self.ensure_writable("purge_user_contexts")?;
+        for context_id in &ids {
+            // 并发删除的上下文不算错误
+            let _ = self.delete_context(*context_id).await;
+        }
+        {
+            let mut user_contexts = self.write_index(IndexKind::User).await;
+            if user_contexts.get(user_id).is_some_and(Vec::is_empty) {
+                user_contexts.remove(user_id);
+            }
+        }
+        if let Some(ref history) = self.history {
+            history.purge_user(user_id, false).await;
+        }
+        Ok(ids)
+    }
+
+    /// 用户拥有的全部上下文ID（含已过期未清理的）
+    async fn owned_context_ids(&self, user_id: &str) -> Vec<Uuid> {
+        let mut ids: Vec<Uuid> = self
+            .read_contexts()
+            .await
+            .values()
+            .filter(|context| context.user_id == user_id)
+            .map(|context| context.id)
+            .collect();
+        ids.sort();
+        ids
+    }
+
     /// 清理过期的上下文
     pub async fn cleanup_expired_contexts(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let _timer = self.metrics.time_operation("cleanup_expired_contexts");
+        // 副本的内容以主实例快照为准，过期由主实例处理；维护期间推迟到维护结束
+        if self.is_read_only() || self.maintenance.as_ref().is_some_and(|maintenance| maintenance.is_active()) {
+            return Ok(());
+        }
         let now = Utc::now();
-        let mut contexts = self.contexts.write().await;
+        let mut contexts = self.write_contexts().await;
         let expired_ids: Vec<Uuid> = contexts
             .iter()
             .filter_map(|(id, ctx)| {
@@ -242,10 +936,18 @@ impl ContextManager {
             })
             .collect();
 
-        for id in expired_ids {
-            if let Some(context) = contexts.remove(&id) {
-                self.remove_from_indexes(context).await;
-            }
+        let expired: Vec<LLMContext> = expired_ids.iter().filter_map(|id| contexts.remove(id)).collect();
+        drop(contexts);
+
+        // 释放存储锁后再更新索引（与读路径的锁顺序一致）并执行钩子，钩子内部可以安全地访问管理器
+        for context in &expired {
+            self.stats.record_removed(context).await;
+            self.invalidate_cached(context).await;
+            self.remove_from_indexes(context.clone()).await;
+        }
+        for context in expired {
+            self.gc.record(&context, GcReason::Expired).await;
+            self.dispatch_hooks(LifecycleEvent::Expired(context)).await;
         }
 
         Ok(())
@@ -255,29 +957,35 @@ impl ContextManager {
     async fn update_indexes(&self, context: LLMContext) {
         // 更新会话索引
         {
-            let mut session_contexts = self.session_contexts.write().await;
-            session_contexts
+            let mut session_contexts = self.write_index(IndexKind::Session).await;
+            let ids = session_contexts
                 .entry(context.session_id.clone())
-                .or_insert_with(Vec::new)
-                .push(context.id);
+                .or_insert_with(Vec::new);
+            if !ids.contains(&context.id) {
+                ids.push(context.id);
+            }
         }
 
         // 更新用户索引
         {
-            let mut user_contexts = self.user_contexts.write().await;
-            user_contexts
+            let mut user_contexts = self.write_index(IndexKind::User).await;
+            let ids = user_contexts
                 .entry(context.user_id.clone())
-                .or_insert_with(Vec::new)
-                .push(context.id);
+                .or_insert_with(Vec::new);
+            if !ids.contains(&context.id) {
+                ids.push(context.id);
+            }
         }
 
         // 更新领域索引
         {
-            let mut domain_contexts = self.domain_contexts.write().await;
-            domain_contexts
+            let mut domain_contexts = self.write_index(IndexKind::Domain).await;
+            let ids = domain_contexts
                 .entry(context.domain.clone())
-                .or_insert_with(Vec::new)
-                .push(context.id);
+                .or_insert_with(Vec::new);
+            if !ids.contains(&context.id) {
+                ids.push(context.id);
+            }
         }
     }
 
@@ -285,7 +993,7 @@ impl ContextManager {
     async fn remove_from_indexes(&self, context: LLMContext) {
         // 从会话索引中移除
         {
-            let mut session_contexts = self.session_contexts.write().await;
+            let mut session_contexts = self.write_index(IndexKind::Session).await;
             if let Some(ids) = session_contexts.get_mut(&context.session_id) {
                 ids.retain(|id| *id != context.id);
             }
@@ -293,7 +1001,7 @@ impl ContextManager {
 
         // 从用户索引中移除
         {
-            let mut user_contexts = self.user_contexts.write().await;
+            let mut user_contexts = self.write_index(IndexKind::User).await;
             if let Some(ids) = user_contexts.get_mut(&context.user_id) {
                 ids.retain(|id| *id != context.id);
             }
@@ -301,21 +1009,260 @@ impl ContextManager {
 
         // 从领域索引中移除
         {
-            let mut domain_contexts = self.domain_contexts.write().await;
+            let mut domain_contexts = self.write_index(IndexKind::Domain).await;
             if let Some(ids) = domain_contexts.get_mut(&context.domain) {
                 ids.retain(|id| *id != context.id);
             }
         }
     }
 
+    /// 按类型获取索引表
+    /// 获取上下文表的读锁（记录等待时间）
+    async fn read_contexts(&self) -> RwLockReadGuard<'_, HashMap<Uuid, LLMContext>> {
+        self.metrics.read("contexts", &self.contexts).await
+    }
+
+    /// 获取上下文表的写锁（记录等待时间）
+    async fn write_contexts(&self) -> RwLockWriteGuard<'_, HashMap<Uuid, LLMContext>> {
+        self.metrics.write("contexts", &self.contexts).await
+    }
+
+    /// 获取索引表的读锁（记录等待时间）
+    async fn read_index(&self, kind: IndexKind) -> RwLockReadGuard<'_, HashMap<String, Vec<Uuid>>> {
+        self.metrics.read(index_lock_name(kind), self.index(kind)).await
+    }
+
+    /// 获取索引表的写锁（记录等待时间）
+    async fn write_index(&self, kind: IndexKind) -> RwLockWriteGuard<'_, HashMap<String, Vec<Uuid>>> {
+        self.metrics.write(index_lock_name(kind), self.index(kind)).await
+    }
+
+    fn index(&self, kind: IndexKind) -> &ContextIndex {
+        match kind {
+            IndexKind::Session => &self.session_contexts,
+            IndexKind::User => &self.user_contexts,
+            IndexKind::Domain => &self.domain_contexts,
+        }
+    }
+
+    /// 列出索引表的所有键（供整理任务分批处理）
+    pub(crate) async fn index_keys(&self, kind: IndexKind) -> Vec<String> {
+        self.read_index(kind).await.keys().cloned().collect()
+    }
+
+    /// 整理索引表中的一批键：移除指向已不存在上下文的ID和重复ID，删除空键并收缩容量，
+    /// 返回(移除的悬挂ID数, 删除的空键数)
+    pub(crate) async fn compact_index_keys(&self, kind: IndexKind, keys: &[String]) -> (usize, usize) {
+        // 先复制存活的ID并释放存储锁，再锁索引：同时持有两把锁时顺序必须与读路径（先索引后存储）一致
+        let live: std::collections::HashSet<Uuid> = self.read_contexts().await.keys().copied().collect();
+        let mut index = self.write_index(kind).await;
+        let mut dangling = 0;
+        let mut empty = 0;
+        for key in keys {
+            let Some(ids) = index.get_mut(key) else {
+                continue;
+            };
+            let before = ids.len();
+            let mut seen = std::collections::HashSet::new();
+            ids.retain(|id| live.contains(id) && seen.insert(*id));
+            dangling += before - ids.len();
+            if ids.is_empty() {
+                index.remove(key);
+                empty += 1;
+            } else {
+                ids.shrink_to_fit();
+            }
+        }
+        (dangling, empty)
+    }
+
+    /// 收缩存储和索引表的容量，返回收缩前后的存储容量
+    pub(crate) async fn shrink_storage(&self) -> (usize, usize) {
+        let mut contexts = self.write_contexts().await;
+        let before = contexts.capacity();
+        contexts.shrink_to_fit();
+        let after = contexts.capacity();
+        drop(contexts);
+        for kind in [IndexKind::Session, IndexKind::User, IndexKind::Domain] {
+            self.write_index(kind).await.shrink_to_fit();
+        }
+        (before, after)
+    }
+
     /// 获取并发许可
     pub async fn acquire_concurrent_permit(&self) -> tokio::sync::SemaphorePermit<'_> {
         self.concurrency_limiter.acquire().await.unwrap()
     }
 
+    /// 按领域模式迁移已有的无类型元数据；可自动修复的上下文会被更新，其余列入报告
+    pub async fn migrate_metadata(&self, domain: &str) -> Result<MetadataMigrationReport, Box<dyn std::error::Error + Send + Sync>> {
+        self.ensure_writable("migrate_metadata")?;
+        let schema = match self.metadata_schemas {
+            Some(ref registry) => registry.get(domain).await,
+            None => None,
+        }
+        .ok_or("No metadata schema registered for domain")?;
+
+        let candidates: Vec<LLMContext> = self
+            .contexts
+            .read()
+            .await
+            .values()
+            .filter(|ctx| ctx.domain == domain)
+            .cloned()
+            .collect();
+
+        let mut report = MetadataMigrationReport {
+            domain: domain.to_string(),
+            scanned: candidates.len(),
+            migrated: Vec::new(),
+            invalid: Vec::new(),
+        };
+        for context in candidates {
+            let migration = schema.migrate(&context.metadata);
+            if !migration.violations.is_empty() {
+                report.invalid.push((context.id, migration.violations));
+            } else if migration.changed() {
+                self.update_context(context.id, None, Some(migration.metadata), None).await?;
+                report.migrated.push(context.id);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// 按指定编解码器导出所有未过期的上下文，用于持久化快照或传输
+    pub async fn export_contexts(&self, codec: &dyn ContextCodec) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        codec.encode(&self.get_all_contexts().await)
+    }
+
+    /// 导入编码后的上下文，ID相同的上下文会被覆盖；返回导入数量
+    pub async fn import_contexts(&self, codec: &dyn ContextCodec, bytes: &[u8]) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        self.ensure_writable("import_contexts")?;
+        self.insert_contexts(codec.decode(bytes)?).await
+    }
+
+    /// 写入已构造的上下文（保留其ID和时间戳），ID相同的上下文会被覆盖；返回写入数量
+    pub async fn insert_contexts(&self, contexts: Vec<LLMContext>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        self.ensure_writable("insert_contexts")?;
+        let count = contexts.len();
+        self.apply_imported(contexts).await;
+        Ok(count)
+    }
+
+    /// 用快照替换全部上下文（副本同步使用，只读模式下也允许）：快照中没有的上下文被移除，
+    /// 版本号和优先级未变的上下文保持不动；差异在持有全部写锁时一次性应用，读者不会看到部分同步的存储
+    pub async fn load_snapshot(&self, codec: &dyn ContextCodec, bytes: &[u8]) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let snapshot = codec.decode(bytes)?;
+        let count = snapshot.len();
+        let (removed, changed) = self.apply_contexts(snapshot, true).await;
+        for context in &removed {
+            self.stats.record_removed(context).await;
+            self.invalidate_cached(context).await;
+        }
+        self.finish_applied(changed).await;
+        if !removed.is_empty() {
+            self.rebuild_bloom_filter().await;
+        }
+        Ok(count)
+    }
+
+    /// 写入解码后的上下文，ID相同的覆盖
+    async fn apply_imported(&self, imported: Vec<LLMContext>) {
+        let (_, changed) = self.apply_contexts(imported, false).await;
+        self.finish_applied(changed).await;
+    }
+
+    /// 在持有全部索引和存储写锁时应用上下文（锁顺序与读路径一致：先索引后存储）。
+    /// replace为true时移除不在contexts中的上下文，并跳过版本号和优先级未变的上下文；
+    /// 返回(移除的上下文, (被替换的旧版本, 写入的上下文))
+    async fn apply_contexts(
+        &self,
+        incoming: Vec<LLMContext>,
+        replace: bool,
+    ) -> (Vec<LLMContext>, Vec<(Option<LLMContext>, LLMContext)>) {
+        let mut sessions = self.write_index(IndexKind::Session).await;
+        let mut users = self.write_index(IndexKind::User).await;
+        let mut domains = self.write_index(IndexKind::Domain).await;
+        let mut contexts = self.write_contexts().await;
+
+        let mut removed = Vec::new();
+        if replace {
+            let keep: std::collections::HashSet<Uuid> = incoming.iter().map(|context| context.id).collect();
+            let stale: Vec<Uuid> = contexts.keys().filter(|id| !keep.contains(id)).copied().collect();
+            for id in stale {
+                if let Some(context) = contexts.remove(&id) {
+                    unindex(&mut sessions, &context.session_id, id);
+                    unindex(&mut users, &context.user_id, id);
+                    unindex(&mut domains, &context.domain, id);
+                    removed.push(context);
+                }
+            }
+        }
+
+        let mut changed = Vec::new();
+        for context in incoming {
+            if replace
+                && contexts
+                    .get(&context.id)
+                    .is_some_and(|existing| existing.version == context.version && existing.priority == context.priority)
+            {
+                continue;
+            }
+            if let Some(ref guard) = self.bloom_guard {
+                guard.insert(&context.id).await;
+            }
+            let previous = contexts.insert(context.id, context.clone());
+            if let Some(ref previous) = previous {
+                if previous.session_id != context.session_id {
+                    unindex(&mut sessions, &previous.session_id, context.id);
+                }
+                if previous.user_id != context.user_id {
+                    unindex(&mut users, &previous.user_id, context.id);
+                }
+                if previous.domain != context.domain {
+                    unindex(&mut domains, &previous.domain, context.id);
+                }
+            }
+            index(&mut sessions, &context.session_id, context.id);
+            index(&mut users, &context.user_id, context.id);
+            index(&mut domains, &context.domain, context.id);
+            changed.push((previous, context));
+        }
+        (removed, changed)
+    }
+
+    /// 应用上下文后更新统计、缓存和版本历史，并分发钩子
+    async fn finish_applied(&self, changed: Vec<(Option<LLMContext>, LLMContext)>) {
+        for (previous, context) in changed {
+            match previous {
+                Some(ref previous) => {
+                    self.stats.record_replaced(previous, &context).await;
+                    self.invalidate_cached(previous).await;
+                }
+                None => self.stats.record_added(&context).await,
+            }
+            self.invalidate_cached(&context).await;
+            match previous {
+                Some(previous) => self.dispatch_hooks(LifecycleEvent::Updated { previous: Box::new(previous), current: context }).await,
+                None => self.dispatch_hooks(LifecycleEvent::Created(context)).await,
+            }
+        }
+    }
+
+    /// 获取按领域、用户、标签、状态和大小的基数统计（增量维护，不扫描存储）
+    pub async fn get_cardinality_stats(&self) -> ContextCardinalityStats {
+        self.stats.snapshot().await
+    }
+
+    /// 获取回收跟踪器（订阅回收事件流、查询回收指标）
+    pub fn get_gc_tracker(&self) -> Arc<GcTracker> {
+        self.gc.clone()
+    }
+
     /// 获取统计信息
     pub async fn get_stats(&self) -> ContextManagerStats {
-        let contexts = self.contexts.read().await;
+        let contexts = self.read_contexts().await;
         let available_permits = self.concurrency_limiter.available_permits();
 
         ContextManagerStats {
@@ -393,4 +1340,93 @@ mod tests {
         assert_eq!(stats.total_contexts, 1);
         assert_eq!(stats.max_concurrent, 10);
     }
+
+    #[tokio::test]
+    async fn test_read_through_cache() {
+        let cache = Arc::new(CacheManager::new());
+        let manager = ContextManager::new(10, 3600).with_cache(cache.clone());
+
+        let context = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "v1".to_string(), 5)
+            .await
+            .unwrap();
+
+        // 首次查询回填缓存，第二次命中
+        assert_eq!(manager.get_domain_contexts("medical").await.len(), 1);
+        assert_eq!(manager.get_domain_contexts("medical").await.len(), 1);
+        assert_eq!(manager.get_session_contexts("s1").await.len(), 1);
+        let stats = cache.get_stats().await;
+        assert_eq!(stats.hit_count, 1);
+        assert_eq!(stats.miss_count, 2);
+
+        // 更新后缓存失效，读取到最新内容
+        manager
+            .update_context(context.id, Some("v2".to_string()), None, None)
+            .await
+            .unwrap();
+        assert_eq!(manager.get_domain_contexts("medical").await[0].context_data, "v2");
+
+        // 删除后会话查询不再返回该上下文
+        manager.get_session_contexts("s1").await;
+        manager.delete_context(context.id).await.unwrap();
+        assert!(manager.get_session_contexts("s1").await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_metadata_schema_on_write() {
+        use crate::context::metadata_schema::{MetadataField, MetadataSchema, MetadataValueType};
+
+        let registry = Arc::new(MetadataSchemaRegistry::new());
+        let manager = ContextManager::new(10, 3600);
+        let legacy = manager
+            .create_context_with_metadata(
+                "s1".to_string(),
+                "u1".to_string(),
+                "legal".to_string(),
+                "Contract law".to_string(),
+                5,
+                HashMap::from([("Jurisdiction".to_string(), "CN".to_string())]),
+            )
+            .await
+            .unwrap();
+
+        let manager = manager.with_metadata_schemas(registry.clone());
+        registry
+            .register(MetadataSchema::new("legal").field("jurisdiction", MetadataField::required(MetadataValueType::String)))
+            .await;
+
+        // 写入时校验
+        let missing = manager
+            .create_context("s1".to_string(), "u1".to_string(), "legal".to_string(), "Tort law".to_string(), 5)
+            .await;
+        assert!(missing.is_err());
+        assert!(manager.update_context(legacy.id, None, Some(HashMap::new()), None).await.is_err());
+
+        // 迁移旧数据
+        let report = manager.migrate_metadata("legal").await.unwrap();
+        assert_eq!(report.migrated, vec![legacy.id]);
+        assert!(report.invalid.is_empty());
+        assert_eq!(manager.get_context(legacy.id).await.unwrap().metadata["jurisdiction"], "CN");
+    }
+
+    #[tokio::test]
+    async fn test_bloom_filter_lookups() {
+        let manager = ContextManager::new(10, 3600).with_bloom_filter(BloomFilterConfig::default());
+        let context = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "data".to_string(), 5)
+            .await
+            .unwrap();
+
+        assert!(manager.get_context(context.id).await.is_some());
+        assert!(manager.get_context(Uuid::new_v4()).await.is_none());
+
+        manager.delete_context(context.id).await.unwrap();
+        manager.rebuild_bloom_filter().await;
+        assert!(manager.get_context(context.id).await.is_none());
+
+        let stats = manager.get_bloom_stats().await.unwrap();
+        assert_eq!(stats.lookups, 3);
+        assert_eq!(stats.skipped, 2);
+        assert_eq!(stats.item_count, 0);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,170 @@
+//! 锁竞争与操作耗时指标 - 记录上下文管理器各读写锁的等待时间和各操作的总耗时，写入监控指标注册表，用于判断分片前后读写锁是否为瓶颈
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use utoipa::ToSchema;
+use crate::monitoring::metrics::{HistogramSnapshot, MetricsRegistry};
+
+/// 锁等待时间的直方图桶（毫秒），无竞争时的获取耗时在微秒级
+pub const LOCK_WAIT_BUCKETS_MS: [f64; 12] = [
+    0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0, 10.0, 50.0, 100.0, 500.0, 1000.0,
+];
+
+/// 锁等待指标名前缀，完整名称为`前缀.<锁>.<read|write>`
+pub const LOCK_WAIT_METRIC_PREFIX: &str = "context_manager.lock_wait_ms";
+/// 操作耗时指标名前缀，完整名称为`前缀.<操作>`
+pub const OPERATION_METRIC_PREFIX: &str = "context_manager.operation_ms";
+
+/// 一个直方图的摘要
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,    // 所在桶的上边界
+    pub p99_ms: f64,    // 所在桶的上边界，超出最大桶时为最大桶边界
+    pub total_ms: f64,
+}
+
+impl LatencySummary {
+    fn from_snapshot(snapshot: &HistogramSnapshot) -> Self {
+        let largest_bound = snapshot
+            .buckets
+            .iter()
+            .map(|(bound, _)| *bound)
+            .filter(|bound| bound.is_finite())
+            .fold(0.0, f64::max);
+        let quantile = |q: f64| {
+            let value = snapshot.quantile(q);
+            if value.is_finite() { value } else { largest_bound }
+        };
+        Self {
+            count: snapshot.count,
+            mean_ms: snapshot.mean,
+            p50_ms: quantile(0.5),
+            p99_ms: quantile(0.99),
+            total_ms: snapshot.sum,
+        }
+    }
+}
+
+/// 锁竞争报告
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct LockMetricsReport {
+    pub lock_waits: BTreeMap<String, LatencySummary>,   // "<锁>.<read|write>" -> 等待时间
+    pub operations: BTreeMap<String, LatencySummary>,   // 操作名 -> 总耗时（含等待锁的时间）
+}
+
+/// 上下文管理器的计时器
+pub struct ContextManagerMetrics {
+    registry: Arc<MetricsRegistry>,
+}
+
+impl ContextManagerMetrics {
+    /// 写入指定的指标注册表（通常为监控系统的注册表）
+    pub fn new(registry: Arc<MetricsRegistry>) -> Self {
+        Self { registry }
+    }
+
+    /// 指标注册表
+    pub fn registry(&self) -> Arc<MetricsRegistry> {
+        self.registry.clone()
+    }
+
+    /// 获取读锁并记录等待时间
+    pub async fn read<'a, T>(&self, lock: &str, rwlock: &'a RwLock<T>) -> RwLockReadGuard<'a, T> {
+        let started = Instant::now();
+        let guard = rwlock.read().await;
+        self.observe_wait(lock, "read", started);
+        guard
+    }
+
+    /// 获取写锁并记录等待时间
+    pub async fn write<'a, T>(&self, lock: &str, rwlock: &'a RwLock<T>) -> RwLockWriteGuard<'a, T> {
+        let started = Instant::now();
+        let guard = rwlock.write().await;
+        self.observe_wait(lock, "write", started);
+        guard
+    }
+
+    fn observe_wait(&self, lock: &str, mode: &str, started: Instant) {
+        self.registry
+            .histogram_with_buckets(&format!("{}.{}.{}", LOCK_WAIT_METRIC_PREFIX, lock, mode), &LOCK_WAIT_BUCKETS_MS)
+            .observe(started.elapsed().as_secs_f64() * 1000.0);
+    }
+
+    /// 开始为一个操作计时，返回的计时器被丢弃时记录耗时（提前返回和出错的路径同样计入）
+    pub fn time_operation(&self, operation: &str) -> OperationTimer {
+        OperationTimer {
+            histogram_name: format!("{}.{}", OPERATION_METRIC_PREFIX, operation),
+            registry: self.registry.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    /// 按锁和操作汇总当前指标
+    pub fn report(&self) -> LockMetricsReport {
+        let snapshot = self.registry.snapshot();
+        let mut report = LockMetricsReport::default();
+        for (name, histogram) in &snapshot.histograms {
+            if let Some(lock) = name.strip_prefix(LOCK_WAIT_METRIC_PREFIX).and_then(|rest| rest.strip_prefix('.')) {
+                report.lock_waits.insert(lock.to_string(), LatencySummary::from_snapshot(histogram));
+            } else if let Some(operation) = name.strip_prefix(OPERATION_METRIC_PREFIX).and_then(|rest| rest.strip_prefix('.')) {
+                report.operations.insert(operation.to_string(), LatencySummary::from_snapshot(histogram));
+            }
+        }
+        report
+    }
+}
+
+/// 操作计时器
+pub struct OperationTimer {
+    histogram_name: String,
+    registry: Arc<MetricsRegistry>,
+    started: Instant,
+}
+
+impl Drop for OperationTimer {
+    fn drop(&mut self) {
+        self.registry
+            .histogram(&self.histogram_name)
+            .observe(self.started.elapsed().as_secs_f64() * 1000.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::monitoring::monitoring::MonitoringSystem;
+
+    #[tokio::test]
+    async fn test_lock_wait_and_operation_metrics() {
+        let monitoring = MonitoringSystem::new();
+        let manager = Arc::new(ContextManager::new(10, 3600).with_metrics_registry(monitoring.registry()));
+        let context = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "pneumonia".to_string(), 5)
+            .await
+            .unwrap();
+        for _ in 0..3 {
+            manager.get_context(context.id).await.unwrap();
+        }
+        manager.get_session_contexts("s1").await;
+        manager.delete_context(context.id).await.unwrap();
+
+        let report = manager.get_lock_metrics();
+        assert_eq!(report.operations["get_context"].count, 3);
+        assert_eq!(report.operations["create_context"].count, 1);
+        assert_eq!(report.operations["get_session_contexts"].count, 1);
+        assert!(report.lock_waits["contexts.read"].count >= 4);
+        assert!(report.lock_waits["contexts.write"].count >= 2);
+        assert!(report.lock_waits["session_index.read"].count >= 1);
+        assert!(report.lock_waits.values().all(|summary| summary.p99_ms.is_finite()));
+
+        // 指标写入监控系统的注册表
+        let snapshot = monitoring.registry().snapshot();
+        assert_eq!(snapshot.histograms[&format!("{}.get_context", OPERATION_METRIC_PREFIX)].count, 3);
+    }
+}
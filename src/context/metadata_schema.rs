@@ -0,0 +1,390 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use crate::context::ingestion::{
+    CHUNK_COUNT_METADATA_KEY, CHUNK_INDEX_METADATA_KEY, EXTERNAL_ID_METADATA_KEY, TITLE_METADATA_KEY, URL_METADATA_KEY,
+};
+use crate::domain::language::LANGUAGE_METADATA_KEY;
+use crate::selection::post_filters::SOURCE_METADATA_KEY;
+use crate::strategy::priority_tuner::PRIORITY_TUNING_METADATA_KEY;
+
+/// 系统写入的保留键（语言标签、分块序号、自动调优开关，以及入库和爬虫写入的来源、文档ID、标题和URL），
+/// 不受领域模式的未知键限制
+pub const RESERVED_METADATA_KEYS: [&str; 8] = [
+    LANGUAGE_METADATA_KEY,
+    CHUNK_INDEX_METADATA_KEY,
+    CHUNK_COUNT_METADATA_KEY,
+    PRIORITY_TUNING_METADATA_KEY,
+    SOURCE_METADATA_KEY,
+    EXTERNAL_ID_METADATA_KEY,
+    TITLE_METADATA_KEY,
+    URL_METADATA_KEY,
+];
+
+/// 元数据值类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MetadataValueType {
+    String,             // 任意字符串
+    Integer,            // 整数
+    Number,             // 浮点数
+    Boolean,            // true/false
+    Date,               // YYYY-MM-DD
+    DateTime,           // RFC 3339
+    Enum(Vec<String>),  // 枚举值之一
+}
+
+impl MetadataValueType {
+    /// 检查值是否符合类型
+    pub fn is_valid(&self, value: &str) -> bool {
+        match self {
+            MetadataValueType::String => true,
+            MetadataValueType::Integer => value.parse::<i64>().is_ok(),
+            MetadataValueType::Number => value.parse::<f64>().map(|v| v.is_finite()).unwrap_or(false),
+            MetadataValueType::Boolean => value == "true" || value == "false",
+            MetadataValueType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+            MetadataValueType::DateTime => DateTime::parse_from_rfc3339(value).is_ok(),
+            MetadataValueType::Enum(values) => values.iter().any(|v| v == value),
+        }
+    }
+
+    /// 尝试将旧的非规范值转换为规范形式（用于迁移），无法转换时返回None
+    pub fn normalize(&self, value: &str) -> Option<String> {
+        let trimmed = value.trim();
+        if self.is_valid(trimmed) {
+            return Some(trimmed.to_string());
+        }
+
+        match self {
+            MetadataValueType::Integer => trimmed.replace([',', '_'], "").parse::<i64>().ok().map(|v| v.to_string()),
+            MetadataValueType::Number => trimmed.replace([',', '_'], "").parse::<f64>().ok().filter(|v| v.is_finite()).map(|v| v.to_string()),
+            MetadataValueType::Boolean => match trimmed.to_lowercase().as_str() {
+                "true" | "yes" | "y" | "1" => Some("true".to_string()),
+                "false" | "no" | "n" | "0" => Some("false".to_string()),
+                _ => None,
+            },
+            MetadataValueType::Date => ["%Y/%m/%d", "%Y.%m.%d", "%Y%m%d"]
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(trimmed, format).ok())
+                .or_else(|| DateTime::parse_from_rfc3339(trimmed).ok().map(|dt| dt.date_naive()))
+                .map(|date| date.format("%Y-%m-%d").to_string()),
+            MetadataValueType::Enum(values) => values.iter().find(|v| v.eq_ignore_ascii_case(trimmed)).cloned(),
+            MetadataValueType::String | MetadataValueType::DateTime => None,
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            MetadataValueType::Enum(values) => format!("one of [{}]", values.join(", ")),
+            other => format!("{:?}", other),
+        }
+    }
+}
+
+/// 元数据字段定义
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataField {
+    pub value_type: MetadataValueType,
+    pub required: bool,           // 是否必填
+    pub default: Option<String>,  // 缺失时填充的默认值
+    pub aliases: Vec<String>,     // 旧键名或常见拼写，迁移时重命名为正式键名
+}
+
+impl MetadataField {
+    /// 创建可选字段
+    pub fn optional(value_type: MetadataValueType) -> Self {
+        Self {
+            value_type,
+            required: false,
+            default: None,
+            aliases: Vec::new(),
+        }
+    }
+
+    /// 创建必填字段
+    pub fn required(value_type: MetadataValueType) -> Self {
+        Self {
+            required: true,
+            ..Self::optional(value_type)
+        }
+    }
+
+    /// 设置默认值
+    pub fn with_default(mut self, default: &str) -> Self {
+        self.default = Some(default.to_string());
+        self
+    }
+
+    /// 设置别名
+    pub fn with_aliases(mut self, aliases: &[&str]) -> Self {
+        self.aliases = aliases.iter().map(|a| a.to_string()).collect();
+        self
+    }
+}
+
+/// 领域元数据模式
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataSchema {
+    pub domain: String,
+    pub fields: HashMap<String, MetadataField>,
+    pub allow_unknown_keys: bool,  // 是否允许模式之外的键
+}
+
+/// 元数据校验错误
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum MetadataViolation {
+    MissingKey(String),
+    InvalidValue { key: String, value: String, expected: String },
+    UnknownKey(String),
+}
+
+impl fmt::Display for MetadataViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetadataViolation::MissingKey(key) => write!(f, "missing required key '{}'", key),
+            MetadataViolation::InvalidValue { key, value, expected } => {
+                write!(f, "invalid value '{}' for key '{}', expected {}", value, key, expected)
+            }
+            MetadataViolation::UnknownKey(key) => write!(f, "unknown key '{}'", key),
+        }
+    }
+}
+
+/// 元数据迁移结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetadataMigration {
+    pub metadata: HashMap<String, String>,
+    pub renamed: Vec<(String, String)>,       // (旧键, 新键)
+    pub normalized: Vec<String>,              // 值被规范化的键
+    pub defaulted: Vec<String>,               // 填充了默认值的键
+    pub violations: Vec<MetadataViolation>,   // 迁移后仍存在的问题
+}
+
+impl MetadataMigration {
+    /// 迁移是否修改了元数据
+    pub fn changed(&self) -> bool {
+        !self.renamed.is_empty() || !self.normalized.is_empty() || !self.defaulted.is_empty()
+    }
+}
+
+impl MetadataSchema {
+    /// 创建空模式（默认允许未知键）
+    pub fn new(domain: &str) -> Self {
+        Self {
+            domain: domain.to_string(),
+            fields: HashMap::new(),
+            allow_unknown_keys: true,
+        }
+    }
+
+    /// 添加字段
+    pub fn field(mut self, key: &str, field: MetadataField) -> Self {
+        self.fields.insert(key.to_string(), field);
+        self
+    }
+
+    /// 设置是否允许未知键
+    pub fn allow_unknown_keys(mut self, allow: bool) -> Self {
+        self.allow_unknown_keys = allow;
+        self
+    }
+
+    /// 校验元数据，返回所有问题（按键名排序）
+    pub fn validate(&self, metadata: &HashMap<String, String>) -> Vec<MetadataViolation> {
+        let mut violations = Vec::new();
+
+        let mut keys: Vec<&String> = self.fields.keys().collect();
+        keys.sort();
+        for key in keys {
+            let field = &self.fields[key];
+            match metadata.get(key) {
+                Some(value) if !field.value_type.is_valid(value) => violations.push(MetadataViolation::InvalidValue {
+                    key: key.clone(),
+                    value: value.clone(),
+                    expected: field.value_type.describe(),
+                }),
+                None if field.required => violations.push(MetadataViolation::MissingKey(key.clone())),
+                _ => {}
+            }
+        }
+
+        if !self.allow_unknown_keys {
+            let mut unknown: Vec<&String> = metadata
+                .keys()
+                .filter(|k| !self.fields.contains_key(*k) && !RESERVED_METADATA_KEYS.contains(&k.as_str()))
+                .collect();
+            unknown.sort();
+            violations.extend(unknown.into_iter().map(|k| MetadataViolation::UnknownKey(k.clone())));
+        }
+
+        violations
+    }
+
+    /// 填充默认值后校验，用于写入路径
+    pub fn prepare(&self, mut metadata: HashMap<String, String>) -> Result<HashMap<String, String>, Vec<MetadataViolation>> {
+        for (key, field) in &self.fields {
+            if let Some(ref default) = field.default {
+                metadata.entry(key.clone()).or_insert_with(|| default.clone());
+            }
+        }
+        let violations = self.validate(&metadata);
+        if violations.is_empty() {
+            Ok(metadata)
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// 迁移旧的无类型元数据：别名重命名、值规范化、填充默认值
+    pub fn migrate(&self, metadata: &HashMap<String, String>) -> MetadataMigration {
+        let mut result = MetadataMigration {
+            metadata: metadata.clone(),
+            renamed: Vec::new(),
+            normalized: Vec::new(),
+            defaulted: Vec::new(),
+            violations: Vec::new(),
+        };
+
+        let mut keys: Vec<&String> = self.fields.keys().collect();
+        keys.sort();
+        for key in keys {
+            let field = &self.fields[key];
+
+            // 别名（忽略大小写）重命名为正式键名
+            if !result.metadata.contains_key(key) {
+                let alias = result
+                    .metadata
+                    .keys()
+                    .find(|existing| {
+                        existing.eq_ignore_ascii_case(key) || field.aliases.iter().any(|a| a.eq_ignore_ascii_case(existing))
+                    })
+                    .cloned();
+                if let Some(alias) = alias {
+                    if let Some(value) = result.metadata.remove(&alias) {
+                        result.metadata.insert(key.clone(), value);
+                        result.renamed.push((alias, key.clone()));
+                    }
+                }
+            }
+
+            match result.metadata.get(key).cloned() {
+                Some(value) => {
+                    if let Some(normalized) = field.value_type.normalize(&value) {
+                        if normalized != value {
+                            result.metadata.insert(key.clone(), normalized);
+                            result.normalized.push(key.clone());
+                        }
+                    }
+                }
+                None => {
+                    if let Some(ref default) = field.default {
+                        result.metadata.insert(key.clone(), default.clone());
+                        result.defaulted.push(key.clone());
+                    }
+                }
+            }
+        }
+
+        result.violations = self.validate(&result.metadata);
+        result
+    }
+}
+
+/// 元数据模式注册表 - 按领域管理模式，未注册模式的领域不做校验
+pub struct MetadataSchemaRegistry {
+    schemas: Arc<RwLock<HashMap<String, MetadataSchema>>>,
+}
+
+impl Default for MetadataSchemaRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MetadataSchemaRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self {
+            schemas: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 注册或替换领域模式
+    pub async fn register(&self, schema: MetadataSchema) {
+        self.schemas.write().await.insert(schema.domain.clone(), schema);
+    }
+
+    /// 移除领域模式
+    pub async fn remove(&self, domain: &str) -> Option<MetadataSchema> {
+        self.schemas.write().await.remove(domain)
+    }
+
+    /// 获取领域模式
+    pub async fn get(&self, domain: &str) -> Option<MetadataSchema> {
+        self.schemas.read().await.get(domain).cloned()
+    }
+
+    /// 写入前处理元数据：填充默认值并校验
+    pub async fn prepare(
+        &self,
+        domain: &str,
+        metadata: HashMap<String, String>,
+    ) -> Result<HashMap<String, String>, Box<dyn std::error::Error + Send + Sync>> {
+        let schemas = self.schemas.read().await;
+        let schema = match schemas.get(domain) {
+            Some(schema) => schema,
+            None => return Ok(metadata),
+        };
+        schema.prepare(metadata).map_err(|violations| {
+            let messages: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            format!("Metadata validation failed for domain '{}': {}", domain, messages.join("; ")).into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn medical_schema() -> MetadataSchema {
+        MetadataSchema::new("medical")
+            .field("source", MetadataField::required(MetadataValueType::Enum(vec!["guideline".to_string(), "journal".to_string()])))
+            .field("published", MetadataField::optional(MetadataValueType::Date).with_aliases(&["publish_date"]))
+            .field("reviewed", MetadataField::required(MetadataValueType::Boolean).with_default("false"))
+            .allow_unknown_keys(false)
+    }
+
+    #[test]
+    fn test_schema_validation_and_migration() {
+        let schema = medical_schema();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), "blog".to_string());
+        metadata.insert("publsher".to_string(), "x".to_string());
+        let violations = schema.prepare(metadata).unwrap_err();
+        assert_eq!(violations.len(), 2);
+        assert!(matches!(violations[0], MetadataViolation::InvalidValue { ref key, .. } if key == "source"));
+        assert_eq!(violations[1], MetadataViolation::UnknownKey("publsher".to_string()));
+
+        // 系统写入的保留键不算未知键
+        let mut tagged = HashMap::new();
+        tagged.insert("source".to_string(), "guideline".to_string());
+        tagged.insert(LANGUAGE_METADATA_KEY.to_string(), "zh".to_string());
+        tagged.insert(CHUNK_INDEX_METADATA_KEY.to_string(), "0".to_string());
+        assert!(schema.prepare(tagged).is_ok());
+
+        // 旧数据：键名大小写不一致、别名、非规范日期，缺少默认字段
+        let mut legacy = HashMap::new();
+        legacy.insert("Source".to_string(), "Guideline".to_string());
+        legacy.insert("publish_date".to_string(), "2024/03/05".to_string());
+        let migration = schema.migrate(&legacy);
+        assert!(migration.changed());
+        assert!(migration.violations.is_empty());
+        assert_eq!(migration.metadata["source"], "guideline");
+        assert_eq!(migration.metadata["published"], "2024-03-05");
+        assert_eq!(migration.metadata["reviewed"], "false");
+        assert_eq!(migration.renamed.len(), 2);
+    }
+}
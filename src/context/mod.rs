@@ -1,3 +1,22 @@
 pub mod llm_context;
 pub mod context_management;
-pub mod context_loader;
\ No newline at end of file
+pub mod context_loader;
+pub mod codec;
+pub mod metadata_schema;
+pub mod hooks;
+pub mod crawler;
+pub mod ingestion;
+pub mod audit;
+pub mod approval;
+pub mod access;
+pub mod stats;
+pub mod gc;
+pub mod id_strategy;
+pub mod compaction;
+pub mod replica;
+pub mod schema_migration;
+pub mod expiry;
+pub mod version_history;
+pub mod lock_metrics;
+pub mod fixtures;
+pub mod consistency;
\ No newline at end of file
@@ -0,0 +1,290 @@
+//! 只读副本 - 实例以只读模式运行时拒绝所有写操作，并定期从主实例拉取快照替换本地存储，只承担选择类读流量
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use crate::context::codec::{ContextCodec, JsonCodec};
+use crate::context::llm_context::ContextManager;
+use crate::maintenance::MaintenanceMode;
+
+/// 主实例发布快照的HTTP路径
+pub const SNAPSHOT_PATH: &str = "/api/contexts/snapshot";
+
+/// 只读模式下拒绝写操作的错误（可通过`downcast_ref`识别）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReadOnlyError {
+    pub operation: String,
+}
+
+impl std::fmt::Display for ReadOnlyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Context store is read-only, '{}' refused", self.operation)
+    }
+}
+
+impl std::error::Error for ReadOnlyError {}
+
+/// 判断错误是否由只读模式引起
+pub fn is_read_only_error(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    error.downcast_ref::<ReadOnlyError>().is_some()
+}
+
+/// 快照来源
+#[async_trait]
+pub trait SnapshotSource: Send + Sync {
+    /// 拉取编码后的快照
+    async fn fetch_snapshot(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>>;
+}
+
+/// 同进程内的主实例
+pub struct PrimarySnapshotSource {
+    primary: Arc<ContextManager>,
+}
+
+impl PrimarySnapshotSource {
+    /// 以主实例的上下文管理器作为快照来源（JSON编码）
+    pub fn new(primary: Arc<ContextManager>) -> Self {
+        Self { primary }
+    }
+}
+
+#[async_trait]
+impl SnapshotSource for PrimarySnapshotSource {
+    async fn fetch_snapshot(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        self.primary.export_contexts(&JsonCodec).await
+    }
+}
+
+/// 通过HTTP从主实例拉取快照
+pub struct HttpSnapshotSource {
+    url: String,
+    token: Option<String>,  // 主实例配置的复制令牌
+    client: reqwest::Client,
+}
+
+impl HttpSnapshotSource {
+    /// 主实例的基础地址，如`http://primary:8080`
+    pub fn new(primary_base_url: &str) -> Self {
+        Self {
+            url: format!("{}{}", primary_base_url.trim_end_matches('/'), SNAPSHOT_PATH),
+            token: None,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// 设置复制令牌（以Bearer方式发送）
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl SnapshotSource for HttpSnapshotSource {
+    async fn fetch_snapshot(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut request = self.client.get(&self.url);
+        if let Some(ref token) = self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?.error_for_status()?;
+        Ok(response.bytes().await?.to_vec())
+    }
+}
+
+/// 副本同步配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplicaConfig {
+    pub refresh_interval_seconds: u64,  // 拉取快照的间隔
+}
+
+impl Default for ReplicaConfig {
+    fn default() -> Self {
+        Self { refresh_interval_seconds: 30 }
+    }
+}
+
+/// 副本同步状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplicaStatus {
+    pub last_synced_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub snapshot_requested_at: Option<DateTime<Utc>>, // 最近一次成功同步开始拉取快照的时间，本地数据不早于此时主实例的状态
+    pub contexts: usize,                // 最近一次快照中的上下文数量
+    pub syncs: u64,
+    pub failures: u64,
+    pub last_error: Option<String>,
+}
+
+/// 副本同步任务
+pub struct ReplicaSync {
+    context_manager: Arc<ContextManager>,
+    source: Arc<dyn SnapshotSource>,
+    codec: Arc<dyn ContextCodec>,
+    config: Arc<RwLock<ReplicaConfig>>,
+    status: Arc<RwLock<ReplicaStatus>>,
+    sync_lock: Mutex<()>,   // 同一时间只进行一次同步，强一致读可复用其间完成的同步
+    maintenance: Option<Arc<MaintenanceMode>>,
+}
+
+impl ReplicaSync {
+    /// 创建同步任务，并将上下文管理器切换为只读模式
+    pub fn new(context_manager: Arc<ContextManager>, source: Arc<dyn SnapshotSource>, config: ReplicaConfig) -> Self {
+        context_manager.set_read_only(true);
+        Self {
+            context_manager,
+            source,
+            codec: Arc::new(JsonCodec),
+            config: Arc::new(RwLock::new(config)),
+            status: Arc::new(RwLock::new(ReplicaStatus::default())),
+            sync_lock: Mutex::new(()),
+            maintenance: None,
+        }
+    }
+
+    /// 使用指定编解码器解析快照（需与来源一致）
+    pub fn with_codec(mut self, codec: Arc<dyn ContextCodec>) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    /// 关联维护模式，维护期间暂停定期同步（强一致读仍会按需同步）
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 拉取一次快照并替换本地存储；失败时保留原有数据继续提供读服务
+    pub async fn sync_once(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.sync_lock.lock().await;
+        self.sync_locked().await
+    }
+
+    /// 确保本地数据不早于主实例在requested_at时的状态（强一致读）：等待中的同步若在requested_at之后开始则直接复用，
+    /// 否则立即同步一次；返回本地快照的拉取时间
+    pub async fn sync_since(&self, requested_at: DateTime<Utc>) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>> {
+        let _guard = self.sync_lock.lock().await;
+        if let Some(snapshot_at) = self.status.read().await.snapshot_requested_at.filter(|at| *at >= requested_at) {
+            return Ok(snapshot_at);
+        }
+        self.sync_locked().await?;
+        self.status.read().await.snapshot_requested_at.ok_or_else(|| "Replica has not synced".into())
+    }
+
+    /// 在独立任务中执行`sync_since`：调用方超时或被取消时同步仍会完整执行，不会留下部分加载的存储
+    pub fn spawn_sync_since(self: &Arc<Self>, requested_at: DateTime<Utc>) -> JoinHandle<Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync>>> {
+        let sync = self.clone();
+        tokio::spawn(async move { sync.sync_since(requested_at).await })
+    }
+
+    async fn sync_locked(&self) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let requested_at = Utc::now();
+        let result = match self.source.fetch_snapshot().await {
+            Ok(bytes) => self.context_manager.load_snapshot(self.codec.as_ref(), &bytes).await,
+            Err(e) => Err(e),
+        };
+        let mut status = self.status.write().await;
+        match result {
+            Ok(count) => {
+                status.last_synced_at = Some(Utc::now());
+                status.snapshot_requested_at = Some(requested_at);
+                status.contexts = count;
+                status.syncs += 1;
+                status.last_error = None;
+                Ok(count)
+            }
+            Err(e) => {
+                status.failures += 1;
+                status.last_error = Some(e.to_string());
+                log::warn!("Replica snapshot sync failed: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// 启动后台同步任务：立即同步一次，之后按配置的间隔同步
+    pub fn start(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Some(ref maintenance) = self.maintenance {
+                    maintenance.wait_until_inactive().await;
+                }
+                let _ = self.sync_once().await;
+                let interval = self.config.read().await.refresh_interval_seconds.max(1);
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+            }
+        })
+    }
+
+    /// 获取同步状态
+    pub async fn get_status(&self) -> ReplicaStatus {
+        self.status.read().await.clone()
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: ReplicaConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> ReplicaConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::version_history::VersionHistory;
+    use crate::selection::async_context_selector::ContextSelector;
+
+    #[tokio::test]
+    async fn test_read_only_replica() {
+        let primary = Arc::new(ContextManager::new(10, 3600));
+        let removed = primary
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "pneumonia antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+
+        let history = Arc::new(VersionHistory::new());
+        let replica = Arc::new(ContextManager::new(10, 3600).with_version_history(history.clone()));
+        let sync = ReplicaSync::new(replica.clone(), Arc::new(PrimarySnapshotSource::new(primary.clone())), ReplicaConfig::default());
+        assert!(replica.is_read_only());
+        assert_eq!(sync.sync_once().await.unwrap(), 1);
+
+        // 写操作返回类型化错误
+        let err = replica
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "x".to_string(), 5)
+            .await
+            .unwrap_err();
+        assert!(is_read_only_error(err.as_ref()));
+        assert!(is_read_only_error(replica.delete_context(removed.id).await.unwrap_err().as_ref()));
+
+        // 快照替换：主实例删除的上下文在副本中也被移除
+        let kept = primary
+            .create_context("s2".to_string(), "u1".to_string(), "medical".to_string(), "pneumonia vaccine".to_string(), 5)
+            .await
+            .unwrap();
+        primary.delete_context(removed.id).await.unwrap();
+        sync.sync_once().await.unwrap();
+        let selector = ContextSelector::new(replica.clone());
+        let selected = selector.select_contexts("u1", "s9", "pneumonia", "medical").await.unwrap();
+        assert_eq!(selected.iter().map(|ctx| ctx.id).collect::<Vec<_>>(), vec![kept.id]);
+        assert!(replica.get_session_contexts("s1").await.is_empty());
+
+        let status = sync.get_status().await;
+        assert_eq!((status.syncs, status.contexts), (2, 1));
+
+        // 未变化的上下文不会重复记录版本；变化的上下文在新的索引键下可见
+        sync.sync_once().await.unwrap();
+        assert_eq!(history.get_revisions(kept.id).await.len(), 1);
+        primary.transfer_contexts("u1", "u2", false).await.unwrap();
+        sync.sync_once().await.unwrap();
+        assert_eq!(history.get_revisions(kept.id).await.len(), 2);
+        assert!(replica.get_user_contexts("u1").await.is_empty());
+        assert_eq!(replica.get_user_contexts("u2").await.len(), 1);
+    }
+}
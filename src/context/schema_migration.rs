@@ -0,0 +1,252 @@
+//! 结构版本迁移 - 上下文记录携带结构版本号，加载快照、导出文件等存量数据时逐级升级到当前版本
+//!
+//! 版本历史：
+//! - v1：初始结构
+//! - v2：新增`approval_state`（审批流程上线前的数据视为已发布）
+//! - v3：新增`visibility`（此前的数据全局可见）
+//! - v4：记录携带`schema_version`
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use crate::context::access::Visibility;
+use crate::context::approval::ApprovalState;
+use crate::context::llm_context::LLMContext;
+
+/// 当前结构版本
+pub const CURRENT_SCHEMA_VERSION: u32 = 4;
+
+/// 记录中结构版本字段的名称
+const VERSION_FIELD: &str = "schema_version";
+
+/// 未携带版本号的记录按最早版本处理（serde默认值）
+pub fn legacy_schema_version() -> u32 {
+    1
+}
+
+/// 单步迁移：把`from_version`的记录升级到下一个版本
+pub struct SchemaMigration {
+    pub from_version: u32,
+    pub description: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+/// 按版本排列的迁移步骤，新增字段时在末尾追加一步并提升`CURRENT_SCHEMA_VERSION`
+pub fn migrations() -> &'static [SchemaMigration] {
+    const MIGRATIONS: &[SchemaMigration] = &[
+        SchemaMigration {
+            from_version: 1,
+            description: "add approval_state (existing contexts are approved)",
+            apply: |record| insert_default(record, "approval_state", ApprovalState::Approved),
+        },
+        SchemaMigration {
+            from_version: 2,
+            description: "add visibility (existing contexts are global)",
+            apply: |record| insert_default(record, "visibility", Visibility::Global),
+        },
+        SchemaMigration {
+            from_version: 3,
+            description: "add schema_version",
+            apply: |_| {},
+        },
+    ];
+    MIGRATIONS
+}
+
+/// 字段缺失时写入默认值，已有的值保持不变
+fn insert_default<T: Serialize>(record: &mut Map<String, Value>, field: &str, value: T) {
+    if !record.contains_key(field) {
+        record.insert(field.to_string(), serde_json::to_value(value).unwrap_or(Value::Null));
+    }
+}
+
+/// 迁移错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaMigrationError {
+    UnsupportedVersion { found: u32, current: u32 },   // 由更新版本写入的数据
+    InvalidRecord(String),
+}
+
+impl std::fmt::Display for SchemaMigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchemaMigrationError::UnsupportedVersion { found, current } => {
+                write!(f, "Context schema version {} is newer than supported version {}", found, current)
+            }
+            SchemaMigrationError::InvalidRecord(reason) => write!(f, "Invalid context record: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SchemaMigrationError {}
+
+/// 一次加载的迁移统计
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    pub records: usize,
+    pub upgraded_from: BTreeMap<u32, usize>,    // 原始版本 -> 被升级的记录数
+}
+
+impl MigrationReport {
+    fn record(&mut self, original_version: u32) {
+        self.records += 1;
+        if original_version < CURRENT_SCHEMA_VERSION {
+            *self.upgraded_from.entry(original_version).or_insert(0) += 1;
+        }
+    }
+
+    /// 被升级的记录总数
+    pub fn upgraded(&self) -> usize {
+        self.upgraded_from.values().sum()
+    }
+
+    fn log(&self) {
+        if self.upgraded() > 0 {
+            log::info!("Migrated {} of {} context records to schema v{}: {:?}", self.upgraded(), self.records, CURRENT_SCHEMA_VERSION, self.upgraded_from);
+        }
+    }
+}
+
+/// 推断记录的版本：优先读取版本字段，缺失时按出现的字段判断
+pub fn detect_version(record: &Map<String, Value>) -> Result<u32, SchemaMigrationError> {
+    match record.get(VERSION_FIELD) {
+        Some(value) => value
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| SchemaMigrationError::InvalidRecord(format!("{} is not a version number", VERSION_FIELD))),
+        None if record.contains_key("visibility") => Ok(3),
+        None if record.contains_key("approval_state") => Ok(2),
+        None => Ok(legacy_schema_version()),
+    }
+}
+
+/// 把单条记录升级到当前版本，返回原始版本
+pub fn migrate_record(record: &mut Map<String, Value>) -> Result<u32, SchemaMigrationError> {
+    let original = detect_version(record)?;
+    if original > CURRENT_SCHEMA_VERSION {
+        return Err(SchemaMigrationError::UnsupportedVersion { found: original, current: CURRENT_SCHEMA_VERSION });
+    }
+    let mut version = original;
+    for migration in migrations().iter().filter(|migration| migration.from_version >= original) {
+        (migration.apply)(record);
+        version = migration.from_version + 1;
+    }
+    record.insert(VERSION_FIELD.to_string(), Value::from(version));
+    Ok(original)
+}
+
+/// 升级JSON记录（单个对象或对象数组）
+pub fn migrate_value(value: &mut Value) -> Result<MigrationReport, SchemaMigrationError> {
+    let mut report = MigrationReport::default();
+    let records: Vec<&mut Value> = match value {
+        Value::Array(items) => items.iter_mut().collect(),
+        other => vec![other],
+    };
+    for record in records {
+        let record = record
+            .as_object_mut()
+            .ok_or_else(|| SchemaMigrationError::InvalidRecord("expected a JSON object".to_string()))?;
+        report.record(migrate_record(record)?);
+    }
+    Ok(report)
+}
+
+/// 解码JSON上下文列表，先升级旧版本记录
+pub fn decode_json(bytes: &[u8]) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut value: Value = serde_json::from_slice(bytes)?;
+    let report = migrate_value(&mut value)?;
+    report.log();
+    Ok(serde_json::from_value(value)?)
+}
+
+/// 升级已解码的上下文（二进制格式无法在解码前改写结构，新增字段依赖字段默认值，与迁移步骤写入的值一致）
+pub fn upgrade_decoded(contexts: &mut [LLMContext]) -> Result<MigrationReport, SchemaMigrationError> {
+    let mut report = MigrationReport::default();
+    for context in contexts.iter_mut() {
+        if context.schema_version > CURRENT_SCHEMA_VERSION {
+            return Err(SchemaMigrationError::UnsupportedVersion { found: context.schema_version, current: CURRENT_SCHEMA_VERSION });
+        }
+        report.record(context.schema_version);
+        context.schema_version = CURRENT_SCHEMA_VERSION;
+    }
+    report.log();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use crate::context::codec::{ContextCodec, JsonCodec};
+
+    fn v1_record() -> Value {
+        json!({
+            "id": "6f1c2a9e-4d3b-4c8e-9a71-2b5d8e0f1a34",
+            "session_id": "s1",
+            "user_id": "u1",
+            "domain": "medical",
+            "context_data": "Pneumonia treatment involves antibiotics",
+            "metadata": {},
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "expires_at": null,
+            "priority": 5,
+            "version": 1,
+            "tags": [],
+            "active": true
+        })
+    }
+
+    #[test]
+    fn test_each_migration_step() {
+        // v1 -> v2
+        let mut record = v1_record().as_object().unwrap().clone();
+        assert_eq!(detect_version(&record).unwrap(), 1);
+        (migrations()[0].apply)(&mut record);
+        assert_eq!(record["approval_state"], json!("Approved"));
+        assert_eq!(detect_version(&record).unwrap(), 2);
+
+        // v2 -> v3，已有字段不被覆盖
+        record.insert("approval_state".to_string(), json!("Draft"));
+        (migrations()[1].apply)(&mut record);
+        assert_eq!(record["approval_state"], json!("Draft"));
+        assert_eq!(record["visibility"], serde_json::to_value(Visibility::Global).unwrap());
+        assert_eq!(detect_version(&record).unwrap(), 3);
+
+        // v3 -> v4
+        assert_eq!(migrate_record(&mut record).unwrap(), 3);
+        assert_eq!(record[VERSION_FIELD], json!(CURRENT_SCHEMA_VERSION));
+        assert_eq!(migrations().len() as u32, CURRENT_SCHEMA_VERSION - 1);
+    }
+
+    #[test]
+    fn test_decode_upgrades_legacy_payloads() {
+        let mut draft_v2 = v1_record();
+        draft_v2["id"] = json!("0b7d6c1e-2f4a-4e5b-8c9d-1a2b3c4d5e6f");
+        draft_v2["approval_state"] = json!("Draft");
+        let bytes = serde_json::to_vec(&json!([v1_record(), draft_v2])).unwrap();
+
+        let mut value: Value = serde_json::from_slice(&bytes).unwrap();
+        let report = migrate_value(&mut value).unwrap();
+        assert_eq!(report.upgraded_from, BTreeMap::from([(1, 1), (2, 1)]));
+
+        let contexts = JsonCodec.decode(&bytes).unwrap();
+        assert!(contexts.iter().all(|context| context.schema_version == CURRENT_SCHEMA_VERSION));
+        assert_eq!(contexts[0].approval_state, ApprovalState::Approved);
+        assert_eq!(contexts[1].approval_state, ApprovalState::Draft);
+        assert_eq!(contexts[1].visibility, Visibility::Global);
+
+        // 当前版本的导出可以原样读回
+        let round_trip = JsonCodec.decode(&JsonCodec.encode(&contexts).unwrap()).unwrap();
+        assert_eq!(round_trip[1].approval_state, ApprovalState::Draft);
+
+        // 更新版本写入的数据被拒绝
+        let mut future = v1_record();
+        future[VERSION_FIELD] = json!(CURRENT_SCHEMA_VERSION + 1);
+        let err = JsonCodec.decode(&serde_json::to_vec(&json!([future])).unwrap()).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<SchemaMigrationError>(),
+            Some(&SchemaMigrationError::UnsupportedVersion { found: CURRENT_SCHEMA_VERSION + 1, current: CURRENT_SCHEMA_VERSION })
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! 上下文基数统计 - 在写入路径上增量维护按领域、用户、标签、状态和大小的计数，查询时无需全量扫描
+
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use crate::context::llm_context::LLMContext;
+
+/// 上下文大小分布的桶上界（字节），最后一个桶不设上界
+const SIZE_BUCKET_BOUNDS: [usize; 5] = [256, 1024, 4096, 16384, 65536];
+
+/// 大小分布中的一个桶
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SizeBucket {
+    pub le_bytes: Option<usize>,    // 桶上界（含），None表示无上界
+    pub count: usize,
+}
+
+/// 上下文基数统计快照
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct ContextCardinalityStats {
+    pub total: usize,
+    pub active: usize,                      // 活跃且未过期
+    pub inactive: usize,                    // 被标记为非活跃
+    pub expired: usize,                     // 已过期但尚未清理
+    pub by_domain: HashMap<String, usize>,
+    pub by_user: HashMap<String, usize>,
+    pub by_tag: HashMap<String, usize>,
+    pub size_distribution: Vec<SizeBucket>,
+    pub total_bytes: usize,                 // 上下文数据总大小
+}
+
+/// 增量维护的计数
+#[derive(Default)]
+struct Counts {
+    total: usize,
+    inactive: usize,
+    by_domain: HashMap<String, usize>,
+    by_user: HashMap<String, usize>,
+    by_tag: HashMap<String, usize>,
+    size_buckets: [usize; SIZE_BUCKET_BOUNDS.len() + 1],
+    total_bytes: usize,
+    expiries: BTreeMap<DateTime<Utc>, usize>, // 活跃上下文的过期时间 -> 数量
+}
+
+/// 计数加一
+fn increment(map: &mut HashMap<String, usize>, key: &str) {
+    *map.entry(key.to_string()).or_default() += 1;
+}
+
+/// 计数减一，减到0时移除键，避免维度无限增长
+fn decrement(map: &mut HashMap<String, usize>, key: &str) {
+    if let Some(count) = map.get_mut(key) {
+        *count = count.saturating_sub(1);
+        if *count == 0 {
+            map.remove(key);
+        }
+    }
+}
+
+/// 大小所在的桶
+fn size_bucket(bytes: usize) -> usize {
+    SIZE_BUCKET_BOUNDS.iter().position(|bound| bytes <= *bound).unwrap_or(SIZE_BUCKET_BOUNDS.len())
+}
+
+impl Counts {
+    fn apply(&mut self, context: &LLMContext, added: bool) {
+        let adjust = |map: &mut HashMap<String, usize>, key: &str| {
+            if added {
+                increment(map, key)
+            } else {
+                decrement(map, key)
+            }
+        };
+        adjust(&mut self.by_domain, &context.domain);
+        adjust(&mut self.by_user, &context.user_id);
+        for tag in &context.tags {
+            adjust(&mut self.by_tag, tag);
+        }
+
+        let bytes = context.context_data.len();
+        let bucket = &mut self.size_buckets[size_bucket(bytes)];
+        if added {
+            self.total += 1;
+            self.total_bytes += bytes;
+            *bucket += 1;
+        } else {
+            self.total = self.total.saturating_sub(1);
+            self.total_bytes = self.total_bytes.saturating_sub(bytes);
+            *bucket = bucket.saturating_sub(1);
+        }
+
+        if !context.active {
+            self.inactive = if added { self.inactive + 1 } else { self.inactive.saturating_sub(1) };
+        } else if let Some(expires_at) = context.expires_at {
+            if added {
+                *self.expiries.entry(expires_at).or_default() += 1;
+            } else if let Some(count) = self.expiries.get_mut(&expires_at) {
+                *count = count.saturating_sub(1);
+                if *count == 0 {
+                    self.expiries.remove(&expires_at);
+                }
+            }
+        }
+    }
+}
+
+/// 上下文基数统计器
+#[derive(Default)]
+pub struct ContextStatsTracker {
+    counts: RwLock<Counts>,
+}
+
+impl ContextStatsTracker {
+    /// 创建统计器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录新增的上下文
+    pub async fn record_added(&self, context: &LLMContext) {
+        self.counts.write().await.apply(context, true);
+    }
+
+    /// 记录移除的上下文
+    pub async fn record_removed(&self, context: &LLMContext) {
+        self.counts.write().await.apply(context, false);
+    }
+
+    /// 记录被替换的上下文（更新或覆盖导入）
+    pub async fn record_replaced(&self, previous: &LLMContext, current: &LLMContext) {
+        let mut counts = self.counts.write().await;
+        counts.apply(previous, false);
+        counts.apply(current, true);
+    }
+
+    /// 获取统计快照；过期数量按当前时间从过期时间索引中计算
+    pub async fn snapshot(&self) -> ContextCardinalityStats {
+        let counts = self.counts.read().await;
+        let expired: usize = counts.expiries.range(..Utc::now()).map(|(_, count)| *count).sum();
+        let size_distribution = counts
+            .size_buckets
+            .iter()
+            .enumerate()
+            .map(|(i, count)| SizeBucket {
+                le_bytes: SIZE_BUCKET_BOUNDS.get(i).copied(),
+                count: *count,
+            })
+            .collect();
+
+        ContextCardinalityStats {
+            total: counts.total,
+            active: counts.total.saturating_sub(counts.inactive + expired),
+            inactive: counts.inactive,
+            expired,
+            by_domain: counts.by_domain.clone(),
+            by_user: counts.by_user.clone(),
+            by_tag: counts.by_tag.clone(),
+            size_distribution,
+            total_bytes: counts.total_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::context::llm_context::ContextManager;
+
+    #[tokio::test]
+    async fn test_cardinality_stats() {
+        let manager = ContextManager::new(10, 3600);
+        let a = manager
+            .create_context("s1".to_string(), "alice".to_string(), "medical".to_string(), "x".repeat(100), 5)
+            .await
+            .unwrap();
+        manager
+            .create_context("s1".to_string(), "bob".to_string(), "medical".to_string(), "x".repeat(2000), 5)
+            .await
+            .unwrap();
+        let c = manager
+            .create_context("s2".to_string(), "alice".to_string(), "legal".to_string(), "x".repeat(100_000), 5)
+            .await
+            .unwrap();
+
+        let stats = manager.get_cardinality_stats().await;
+        assert_eq!(stats.total, 3);
+        assert_eq!(stats.active, 3);
+        assert_eq!(stats.by_domain["medical"], 2);
+        assert_eq!(stats.by_user["alice"], 2);
+        assert_eq!(stats.size_distribution[0].count, 1);
+        assert_eq!(stats.size_distribution[2].count, 1);
+        assert_eq!(stats.size_distribution[5].count, 1);
+        assert_eq!(stats.total_bytes, 102_100);
+
+        manager.update_context(a.id, Some("x".repeat(500)), None, None).await.unwrap();
+        manager.delete_context(c.id).await.unwrap();
+        let stats = manager.get_cardinality_stats().await;
+        assert_eq!(stats.total, 2);
+        assert!(!stats.by_domain.contains_key("legal"));
+        assert_eq!(stats.by_user["alice"], 1);
+        assert_eq!(stats.size_distribution[0].count, 0);
+        assert_eq!(stats.size_distribution[1].count, 1);
+        assert_eq!(stats.total_bytes, 2500);
+    }
+}
@@ -0,0 +1,184 @@
+//! 版本历史 - 记录上下文每个版本的生效区间，支持按时间点重现当时可用的上下文（法务、审计场景的"as of"检索）
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use crate::context::llm_context::LLMContext;
+
+/// 上下文的一个历史版本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextRevision {
+    pub context: LLMContext,
+    pub valid_from: DateTime<Utc>,
+    pub valid_to: Option<DateTime<Utc>>,    // 被新版本替换、删除或过期的时间，为空表示仍是当前版本
+}
+
+impl ContextRevision {
+    /// 该版本在指定时间点是否有效（同时考虑过期时间）
+    fn is_valid_at(&self, at: DateTime<Utc>) -> bool {
+        self.valid_from <= at
+            && self.valid_to.map(|valid_to| at < valid_to).unwrap_or(true)
+            && self.context.expires_at.map(|expires_at| at <= expires_at).unwrap_or(true)
+    }
+}
+
+/// 版本历史存储
+pub struct VersionHistory {
+    revisions: Arc<RwLock<HashMap<Uuid, Vec<ContextRevision>>>>,
+}
+
+impl Default for VersionHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl VersionHistory {
+    /// 创建空的版本历史
+    pub fn new() -> Self {
+        Self {
+            revisions: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 记录上下文的新版本（以更新时间为生效时间），并结束上一个版本
+    pub async fn record_version(&self, context: &LLMContext) {
+        let mut revisions = self.revisions.write().await;
+        let history = revisions.entry(context.id).or_default();
+        let valid_from = context.updated_at;
+        if let Some(last) = history.last_mut() {
+            if last.context.version == context.version && last.valid_to.is_none() {
+                return;
+            }
+            if last.valid_to.is_none() {
+                last.valid_to = Some(valid_from);
+            }
+        }
+        history.push(ContextRevision { context: context.clone(), valid_from, valid_to: None });
+    }
+
+    /// 记录上下文被删除或过期清理的时间
+    pub async fn record_removal(&self, context_id: Uuid, at: DateTime<Utc>) {
+        if let Some(last) = self.revisions.write().await.get_mut(&context_id).and_then(|history| history.last_mut()) {
+            if last.valid_to.is_none() {
+                last.valid_to = Some(at);
+            }
+        }
+    }
+
+    /// 上下文的全部历史版本（按时间排序）
+    pub async fn get_revisions(&self, context_id: Uuid) -> Vec<ContextRevision> {
+        self.revisions.read().await.get(&context_id).cloned().unwrap_or_default()
+    }
+
+    /// 指定时间点有效的上下文版本
+    pub async fn get_context_as_of(&self, context_id: Uuid, at: DateTime<Utc>) -> Option<LLMContext> {
+        self.revisions
+            .read()
+            .await
+            .get(&context_id)?
+            .iter()
+            .find(|revision| revision.is_valid_at(at))
+            .map(|revision| revision.context.clone())
+    }
+
+    /// 删除任一版本属于该用户的上下文的全部历史（用户数据删除），返回涉及的上下文数；dry_run时只统计
+    pub async fn purge_user(&self, user_id: &str, dry_run: bool) -> usize {
+        let mut revisions = self.revisions.write().await;
+        let owned: Vec<Uuid> = revisions
+            .iter()
+            .filter(|(_, history)| history.iter().any(|revision| revision.context.user_id == user_id))
+            .map(|(context_id, _)| *context_id)
+            .collect();
+        if !dry_run {
+            for context_id in &owned {
+                revisions.remove(context_id);
+            }
+        }
+        owned.len()
+    }
+
+    /// 丢弃在cutoff之前被删除或过期清理的上下文的全部历史（墓碑），返回丢弃的上下文数
+    pub async fn drop_removed_before(&self, cutoff: DateTime<Utc>) -> usize {
+        let mut revisions = self.revisions.write().await;
+        let before = revisions.len();
+        revisions.retain(|_, history| {
+            !matches!(history.last(), Some(ContextRevision { valid_to: Some(removed_at), .. }) if *removed_at < cutoff)
+        });
+        before - revisions.len()
+    }
+
+    /// 指定时间点有效的全部上下文
+    pub async fn get_contexts_as_of(&self, at: DateTime<Utc>) -> Vec<LLMContext> {
+        self.revisions
+            .read()
+            .await
+            .values()
+            .filter_map(|history| history.iter().find(|revision| revision.is_valid_at(at)))
+            .map(|revision| revision.context.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::{RequestOptions, RequestProcessor};
+    use crate::selection::async_context_selector::ContextSelector;
+
+    #[tokio::test]
+    async fn test_as_of_selection_reproduces_past_state() {
+        let history = Arc::new(VersionHistory::new());
+        let manager = Arc::new(ContextManager::new(10, 3600).with_version_history(history.clone()));
+        let statute = manager
+            .create_context("s1".to_string(), "u1".to_string(), "legal".to_string(), "Notice period is 30 days".to_string(), 5)
+            .await
+            .unwrap();
+        let repealed = manager
+            .create_context("s1".to_string(), "u1".to_string(), "legal".to_string(), "Notice period exemption for startups".to_string(), 5)
+            .await
+            .unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let before_change = Utc::now();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+
+        manager.update_context(statute.id, Some("Notice period is 60 days".to_string()), None, None).await.unwrap();
+        manager.delete_context(repealed.id).await.unwrap();
+        assert_eq!(history.get_revisions(statute.id).await.len(), 2);
+
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let current = selector.select_contexts("u1", "s1", "notice period", "legal").await.unwrap();
+        assert_eq!(current.len(), 1);
+        assert!(current[0].context_data.contains("60 days"));
+
+        let past = selector.select_contexts_as_of("u1", "s1", "notice period", "legal", before_change).await.unwrap();
+        assert_eq!(past.len(), 2);
+        let past_statute = past.iter().find(|context| context.id == statute.id).unwrap();
+        assert!(past_statute.context_data.contains("30 days"));
+        assert!(past.iter().any(|context| context.id == repealed.id));
+
+        // 请求选项中的时间点传递到选择阶段
+        let processor = RequestProcessor::new(manager.clone(), selector.clone());
+        let options = RequestOptions { as_of: Some(before_change), ..RequestOptions::default() };
+        let result = processor
+            .process_request_with_options("u1".to_string(), "s1".to_string(), "notice period".to_string(), "legal".to_string(), options)
+            .await
+            .unwrap();
+        assert_eq!(result.selected_contexts.len(), 2);
+
+        // 创建之前没有任何上下文
+        let empty = selector
+            .select_contexts_as_of("u1", "s1", "notice period", "legal", statute.created_at - chrono::Duration::seconds(1))
+            .await
+            .unwrap();
+        assert!(empty.is_empty());
+
+        // 未启用版本历史时拒绝
+        let plain = ContextSelector::new(Arc::new(ContextManager::new(10, 3600)));
+        assert!(plain.select_contexts_as_of("u1", "s1", "notice", "legal", Utc::now()).await.is_err());
+    }
+}
@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use serde::{Deserialize, Serialize};
-use std::fs;
+use crate::domain::keyword_store::{keywords_path, read_keyword_file, WeightedKeyword};
 
 /// 领域枚举 - 定义系统支持的知识领域
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -26,14 +27,19 @@ impl std::fmt::Display for Domain {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct Keywords {
-    medical: Vec<String>,
-    legal: Vec<String>,
-    technical: Vec<String>,
-    education: Vec<String>,
-    finance: Vec<String>,
-    general: Vec<String>,
+impl Domain {
+    /// 按名称解析领域（不区分大小写）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "medical" => Some(Domain::Medical),
+            "legal" => Some(Domain::Legal),
+            "technical" => Some(Domain::Technical),
+            "education" => Some(Domain::Education),
+            "finance" => Some(Domain::Finance),
+            "general" => Some(Domain::General),
+            _ => None,
+        }
+    }
 }
 
 /// 领域分类器 - 根据输入文本识别其所属的知识领域
@@ -44,23 +50,50 @@ pub struct DomainClassifier {
     pub education_keywords: Vec<String>,
     pub finance_keywords: Vec<String>,
     pub general_keywords: Vec<String>,
+    pub keyword_weights: HashMap<String, f64>,  // 权重不为1的关键词
 }
 
 impl DomainClassifier {
     /// 创建新的领域分类器
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
-        // 从JSON文件加载关键词
-        let keywords_json = fs::read_to_string("src/domain/keywords.json")?;
-        let keywords: Keywords = serde_json::from_str(&keywords_json)?;
-
-        Ok(Self {
-            medical_keywords: keywords.medical,
-            legal_keywords: keywords.legal,
-            technical_keywords: keywords.technical,
-            education_keywords: keywords.education,
-            finance_keywords: keywords.finance,
-            general_keywords: keywords.general,
-        })
+        // 从配置的JSON文件加载关键词
+        Self::from_path(&keywords_path())
+    }
+
+    /// 从指定的关键词文件创建分类器
+    pub fn from_path(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let keywords = read_keyword_file(path).map_err(|e| e.to_string())?;
+        Ok(Self::from_keywords(&keywords))
+    }
+
+    /// 从带权重的关键词创建分类器
+    pub fn from_keywords(keywords: &HashMap<Domain, Vec<WeightedKeyword>>) -> Self {
+        let names = |domain: Domain| -> Vec<String> {
+            keywords
+                .get(&domain)
+                .map(|list| list.iter().map(|keyword| keyword.keyword.clone()).collect())
+                .unwrap_or_default()
+        };
+        let keyword_weights = keywords
+            .values()
+            .flatten()
+            .filter(|keyword| keyword.weight != 1.0)
+            .map(|keyword| (keyword.keyword.clone(), keyword.weight))
+            .collect();
+        Self {
+            medical_keywords: names(Domain::Medical),
+            legal_keywords: names(Domain::Legal),
+            technical_keywords: names(Domain::Technical),
+            education_keywords: names(Domain::Education),
+            finance_keywords: names(Domain::Finance),
+            general_keywords: names(Domain::General),
+            keyword_weights,
+        }
+    }
+
+    /// 关键词权重（未设置时为1）
+    fn weight(&self, keyword: &str) -> f64 {
+        self.keyword_weights.get(keyword).copied().unwrap_or(1.0)
     }
 
     /// 创建新的领域分类器实例
@@ -70,83 +103,69 @@ impl DomainClassifier {
 
     /// 分类领域 - 根据输入文本识别其所属的知识领域
     pub fn classify_domain(&self, text: &str) -> Domain {
-        // 将输入文本转换为小写以便匹配
-        let lower_text = text.to_lowercase();
-        let words: Vec<&str> = lower_text.split_whitespace().collect();
-
-        // 统计每个领域的关键词匹配数量
-        let mut scores = HashMap::new();
-        scores.insert(Domain::Medical, 0);
-        scores.insert(Domain::Legal, 0);
-        scores.insert(Domain::Technical, 0);
-        scores.insert(Domain::Education, 0);
-        scores.insert(Domain::Finance, 0);
-        scores.insert(Domain::General, 0);
-
-        for word in words {
-            // 检查医疗关键词
-            for keyword in &self.medical_keywords {
-                if word == keyword.as_str() || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == keyword.as_str() { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Medical).unwrap() += score_increment;
-                }
-            }
-
-            // 检查法律关键词
-            for keyword in &self.legal_keywords {
-                if word == keyword.as_str() || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == keyword.as_str() { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Legal).unwrap() += score_increment;
-                }
-            }
-
-            // 检查技术关键词
-            for keyword in &self.technical_keywords {
-                if word == keyword.as_str() || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == keyword.as_str() { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Technical).unwrap() += score_increment;
-                }
-            }
-
-            // 检查教育关键词
-            for keyword in &self.education_keywords {
-                if word == keyword.as_str() || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == keyword.as_str() { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Education).unwrap() += score_increment;
-                }
-            }
+        self.score_domains(text).domain
+    }
 
-            // 检查金融关键词
-            for keyword in &self.finance_keywords {
-                if word == keyword.as_str() || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == keyword.as_str() { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Finance).unwrap() += score_increment;
-                }
-            }
+    /// 各领域得分：每个关键词在一段文本中只计一次
+    pub fn score_domains(&self, text: &str) -> DomainScores {
+        self.compile().score(text)
+    }
 
-            // 检查通用关键词
-            for keyword in &self.general_keywords {
-                if word == keyword.as_str() || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 通用关键词给较低分，避免覆盖专业领域
-                    let score_increment = if word == keyword.as_str() { 1 } else { 0 }; // 避免过度匹配
-                    *scores.get_mut(&Domain::General).unwrap() += score_increment;
-                }
+    /// 把当前关键词编译为匹配器，批量分类时所有输入共用一个
+    pub fn compile(&self) -> KeywordMatcher {
+        let lists = [
+            &self.medical_keywords,
+            &self.legal_keywords,
+            &self.technical_keywords,
+            &self.education_keywords,
+            &self.finance_keywords,
+            &self.general_keywords,
+        ];
+        let mut keywords = Vec::new();
+        for (domain, list) in lists.into_iter().enumerate() {
+            for keyword in list {
+                let weight = self.weight(keyword);
+                let keyword = keyword.to_lowercase();
+                // 通用关键词只计完整匹配且得分较低，避免覆盖专业领域；短关键词和短语不做部分匹配
+                let (exact_score, partial_score) = if DOMAIN_ORDER[domain] == Domain::General {
+                    (weight, 0.0)
+                } else if keyword.contains(' ') || keyword.chars().count() < MIN_PARTIAL_MATCH_LEN {
+                    (2.0 * weight, 0.0)
+                } else {
+                    (2.0 * weight, weight)
+                };
+                keywords.push(CompiledKeyword { keyword, domain, exact_score, partial_score });
             }
         }
+        KeywordMatcher { keywords }
+    }
 
-        // 找到得分最高的领域
-        let highest_score_domain = scores
-            .into_iter()
-            .max_by_key(|&(_, score)| score)
-            .map(|(domain, _)| domain)
-            .unwrap_or(Domain::General); // 默认为通用领域
+    /// 批量分类（离线标注历史日志、构建训练集），按可用CPU数并行
+    pub fn classify_batch<T: AsRef<str> + Sync>(&self, texts: &[T]) -> Vec<DomainScores> {
+        let parallelism = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        self.classify_batch_with_parallelism(texts, parallelism)
+    }
 
-        highest_score_domain
+    /// 批量分类，最多使用max_parallelism个线程；结果顺序与输入一致
+    pub fn classify_batch_with_parallelism<T: AsRef<str> + Sync>(&self, texts: &[T], max_parallelism: usize) -> Vec<DomainScores> {
+        if texts.is_empty() {
+            return Vec::new();
+        }
+        let matcher = self.compile();
+        let chunk_size = texts.len().div_ceil(max_parallelism.max(1));
+        std::thread::scope(|scope| {
+            let workers: Vec<_> = texts
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let matcher = &matcher;
+                    scope.spawn(move || chunk.iter().map(|text| matcher.score(text.as_ref())).collect::<Vec<_>>())
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+                .collect()
+        })
     }
 
     /// 异步分类领域 - 根据输入文本识别其所属的知识领域
@@ -164,7 +183,11 @@ impl DomainClassifier {
 
     /// 默认分类方法，当无法加载JSON时使用
     pub fn default_classify_domain(text: &str) -> Domain {
-        // 使用默认关键词
+        Self::with_default_keywords().classify_domain(text)
+    }
+
+    /// 使用内置默认关键词的分类器（关键词文件无法加载时使用）
+    pub fn with_default_keywords() -> Self {
         let medical_keywords = vec![
             "disease", "treatment", "symptom", "doctor", "patient", "medicine", "hospital",
             "diagnosis", "therapy", "pharmacy", "health", "medical", "surgery", "therapy",
@@ -197,83 +220,155 @@ impl DomainClassifier {
             "week", "month", "year", "season", "weather", "temperature", "hot", "cold", "rain", "snow", "sunny"
         ];
 
-        // 将输入文本转换为小写以便匹配
-        let lower_text = text.to_lowercase();
-        let words: Vec<&str> = lower_text.split_whitespace().collect();
-
-        // 统计每个领域的关键词匹配数量
-        let mut scores = HashMap::new();
-        scores.insert(Domain::Medical, 0);
-        scores.insert(Domain::Legal, 0);
-        scores.insert(Domain::Technical, 0);
-        scores.insert(Domain::Education, 0);
-        scores.insert(Domain::Finance, 0);
-        scores.insert(Domain::General, 0);
-
-        for word in words {
-            // 检查医疗关键词
-            for keyword in &medical_keywords {
-                if word == *keyword || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == *keyword { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Medical).unwrap() += score_increment;
-                }
-            }
+        let owned = |keywords: Vec<&str>| keywords.into_iter().map(|keyword| keyword.to_lowercase()).collect();
+        Self {
+            medical_keywords: owned(medical_keywords),
+            legal_keywords: owned(legal_keywords),
+            technical_keywords: owned(technical_keywords),
+            education_keywords: owned(education_keywords),
+            finance_keywords: owned(finance_keywords),
+            general_keywords: owned(general_keywords),
+            keyword_weights: HashMap::new(),
+        }
+    }
+}
 
-            // 检查法律关键词
-            for keyword in &legal_keywords {
-                if word == *keyword || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == *keyword { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Legal).unwrap() += score_increment;
-                }
-            }
+/// 领域的固定顺序，同分时排在前面的领域优先
+const DOMAIN_ORDER: [Domain; 6] = [
+    Domain::Medical,
+    Domain::Legal,
+    Domain::Technical,
+    Domain::Education,
+    Domain::Finance,
+    Domain::General,
+];
+
+/// 一段文本的分类结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainScores {
+    pub domain: Domain,             // 得分最高的领域，没有任何匹配时为通用领域
+    pub scores: Vec<(Domain, f64)>, // 各领域得分（按固定领域顺序）
+}
 
-            // 检查技术关键词
-            for keyword in &technical_keywords {
-                if word == *keyword || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == *keyword { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Technical).unwrap() += score_increment;
-                }
-            }
+impl DomainScores {
+    /// 置信度：最高分在各领域总分中的占比，没有任何匹配时为0
+    pub fn confidence(&self) -> f64 {
+        self.share(&self.domain)
+    }
 
-            // 检查教育关键词
-            for keyword in &education_keywords {
-                if word == *keyword || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == *keyword { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Education).unwrap() += score_increment;
-                }
-            }
+    fn share(&self, domain: &Domain) -> f64 {
+        let total: f64 = self.scores.iter().map(|(_, score)| score).sum();
+        if total <= 0.0 {
+            return 0.0;
+        }
+        self.scores.iter().find(|(candidate, _)| candidate == domain).map(|(_, score)| score / total).unwrap_or(0.0)
+    }
 
-            // 检查金融关键词
-            for keyword in &finance_keywords {
-                if word == *keyword || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 精确匹配给更高分
-                    let score_increment = if word == *keyword { 2 } else { 1 };
-                    *scores.get_mut(&Domain::Finance).unwrap() += score_increment;
-                }
-            }
+    /// 路由结果：选中的领域、置信度和至多max_runner_ups个有得分的备选领域（按置信度降序）
+    pub fn routing(&self, max_runner_ups: usize) -> DomainRouting {
+        let mut runner_ups: Vec<DomainCandidate> = self
+            .scores
+            .iter()
+            .filter(|(domain, score)| *domain != self.domain && *score > 0.0)
+            .map(|(domain, _)| DomainCandidate { domain: domain.clone(), confidence: self.share(domain) })
+            .collect();
+        runner_ups.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap_or(std::cmp::Ordering::Equal));
+        runner_ups.truncate(max_runner_ups);
+        DomainRouting {
+            domain: self.domain.clone(),
+            confidence: self.confidence(),
+            runner_ups,
+        }
+    }
+}
 
-            // 检查通用关键词
-            for keyword in &general_keywords {
-                if word == *keyword || word.contains(keyword) || text.to_lowercase().contains(keyword) {
-                    // 通用关键词给较低分，避免覆盖专业领域
-                    let score_increment = if word == *keyword { 1 } else { 0 }; // 避免过度匹配
-                    *scores.get_mut(&Domain::General).unwrap() += score_increment;
-                }
-            }
+/// 备选领域
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainCandidate {
+    pub domain: Domain,
+    pub confidence: f64,    // 0-1
+}
+
+/// 自动领域路由的结果，便于客户端展示所依据的知识领域，并把低置信度的请求转人工
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainRouting {
+    pub domain: Domain,                     // 选中的领域
+    pub confidence: f64,                    // 0-1
+    pub runner_ups: Vec<DomainCandidate>,   // 备选领域
+}
+
+/// 编译后的关键词
+struct CompiledKeyword {
+    keyword: String,
+    domain: usize,          // 在DOMAIN_ORDER中的下标
+    exact_score: f64,       // 完整匹配得分（已乘权重）
+    partial_score: f64,     // 部分匹配得分，不允许部分匹配时为0
+}
+
+/// 关键词匹配器 - 小写化并预先计算权重后的关键词表，可在多个线程间共享
+pub struct KeywordMatcher {
+    keywords: Vec<CompiledKeyword>,
+}
+
+impl KeywordMatcher {
+    /// 计算一段文本的各领域得分
+    pub fn score(&self, text: &str) -> DomainScores {
+        let text = TextMatcher::new(text);
+        let mut totals = [0.0; DOMAIN_ORDER.len()];
+        for keyword in &self.keywords {
+            totals[keyword.domain] += match text.match_keyword(&keyword.keyword, keyword.partial_score > 0.0) {
+                KeywordMatch::Exact => keyword.exact_score,
+                KeywordMatch::Partial => keyword.partial_score,
+                KeywordMatch::None => 0.0,
+            };
         }
+        // 同分时按领域顺序取第一个
+        let best = (1..totals.len()).fold(0, |best, i| if totals[i] > totals[best] { i } else { best });
+        let domain = if totals[best] > 0.0 { DOMAIN_ORDER[best].clone() } else { Domain::General };
+        DomainScores {
+            domain,
+            scores: DOMAIN_ORDER.iter().cloned().zip(totals).collect(),
+        }
+    }
+}
 
-        // 找到得分最高的领域
-        let highest_score_domain = scores
-            .into_iter()
-            .max_by_key(|&(_, score)| score)
-            .map(|(domain, _)| domain)
-            .unwrap_or(Domain::General); // 默认为通用领域
+/// 短于该长度的关键词只按完整单词匹配（避免"go"匹配"good"、"art"匹配"start"）
+const MIN_PARTIAL_MATCH_LEN: usize = 4;
+
+/// 关键词匹配程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeywordMatch {
+    Exact,      // 完整单词或完整短语
+    Partial,    // 作为单词的一部分出现（如"antibiotic"匹配"antibiotics"）
+    None,
+}
 
-        highest_score_domain
+/// 预处理后的输入文本：按非字母数字字符切分的小写单词
+struct TextMatcher {
+    words: HashSet<String>,
+    joined: String,     // 以空格连接并在首尾补空格，用于短语的单词边界匹配
+}
+
+impl TextMatcher {
+    fn new(text: &str) -> Self {
+        let lower = text.to_lowercase();
+        let ordered: Vec<&str> = lower.split(|c: char| !c.is_alphanumeric()).filter(|word| !word.is_empty()).collect();
+        let joined = format!(" {} ", ordered.join(" "));
+        let words = ordered.into_iter().map(str::to_string).collect();
+        Self { words, joined }
+    }
+
+    fn match_keyword(&self, keyword: &str, allow_partial: bool) -> KeywordMatch {
+        if keyword.contains(' ') {
+            return if self.joined.contains(&format!(" {} ", keyword)) { KeywordMatch::Exact } else { KeywordMatch::None };
+        }
+        if self.words.contains(keyword) {
+            KeywordMatch::Exact
+        } else if allow_partial && self.words.iter().any(|word| word.contains(keyword)) {
+            KeywordMatch::Partial
+        } else {
+            KeywordMatch::None
+        }
     }
 }
 
@@ -316,4 +411,59 @@ mod tests {
         // 通用查询可能被分类为任意领域，这里我们接受任何结果
         println!("General query classified as: {:?}", domain);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_scoring_regressions() {
+        let classifier = DomainClassifier::new().unwrap();
+
+        // 以前短关键词作为子串匹配（"go"匹配"good"，"art"匹配"start"，"ai"匹配"explain"）导致误分类
+        assert_eq!(classifier.classify_domain("Good morning, how are you today?"), Domain::General);
+        assert_eq!(classifier.classify_domain("Good evening, thank you for the help"), Domain::General);
+        assert_ne!(classifier.classify_domain("I need to start a party for my department"), Domain::Education);
+        assert_eq!(classifier.classify_domain("Can you explain the pain in my knee?"), Domain::Medical);
+        // 短关键词作为完整单词仍然匹配
+        assert_eq!(classifier.classify_domain("Is Go faster than PHP for a web API?"), Domain::Technical);
+
+        // 每个关键词只计一次，得分不随文本长度成倍增长
+        let short = classifier.score_domains("pneumonia");
+        let long = classifier.score_domains("pneumonia and other things we might talk about at length today");
+        assert_eq!(short.scores[0].1, long.scores[0].1);
+
+        // 短语需要完整出现
+        let matcher = TextMatcher::new("Is a case, lawful?");
+        assert_eq!(matcher.match_keyword("case law", false), KeywordMatch::None);
+        assert_eq!(TextMatcher::new("Precedent in case law.").match_keyword("case law", false), KeywordMatch::Exact);
+
+        assert_eq!(DomainClassifier::default_classify_domain("Where should I go tomorrow?"), Domain::General);
+        assert_eq!(DomainClassifier::default_classify_domain(""), Domain::General);
+    }
+
+    #[test]
+    fn test_classify_batch() {
+        let classifier = DomainClassifier::new().unwrap();
+        let texts: Vec<String> = [
+            "What is the treatment for pneumonia?",
+            "What is contract law and its legal requirements?",
+            "How do I implement a binary search algorithm?",
+            "How do I calculate the return on investment?",
+            "Good morning, how are you today?",
+        ]
+        .iter()
+        .cycle()
+        .take(23)
+        .map(|text| text.to_string())
+        .collect();
+
+        // 结果顺序与输入一致，且与逐条分类相同，不受并行度影响
+        let batch = classifier.classify_batch_with_parallelism(&texts, 4);
+        assert_eq!(batch.len(), texts.len());
+        for (text, scores) in texts.iter().zip(&batch) {
+            assert_eq!(scores, &classifier.score_domains(text));
+        }
+        assert_eq!(batch, classifier.classify_batch_with_parallelism(&texts, 1));
+        assert_eq!(batch, classifier.classify_batch(&texts));
+        assert_eq!(batch[0].domain, Domain::Medical);
+        assert_eq!(batch[4].domain, Domain::General);
+        assert!(classifier.classify_batch::<&str>(&[]).is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+//! 关键词管理 - 运行时增删带权重的领域关键词（支持多词短语），并写回配置的关键词文件，便于运维在不重新部署的情况下纠正误分类
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use crate::domain::domain_classifier::{Domain, DomainClassifier};
+
+/// 默认关键词文件
+pub const DEFAULT_KEYWORDS_PATH: &str = "src/domain/keywords.json";
+
+/// 当前配置的关键词文件（PENLAI_KEYWORDS_PATH，未设置时使用默认路径）
+pub fn keywords_path() -> PathBuf {
+    std::env::var("PENLAI_KEYWORDS_PATH")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_KEYWORDS_PATH))
+}
+
+/// 带权重的关键词
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct WeightedKeyword {
+    pub keyword: String,    // 小写，多个词之间以单个空格分隔
+    pub weight: f64,        // 匹配得分的倍数，默认1.0
+}
+
+impl WeightedKeyword {
+    /// 是否为多词短语
+    pub fn is_phrase(&self) -> bool {
+        self.keyword.contains(' ')
+    }
+}
+
+/// 关键词文件中的条目：权重为1时写成纯字符串，与原有格式兼容
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum KeywordEntry {
+    Plain(String),
+    Weighted { keyword: String, weight: f64 },
+}
+
+impl From<KeywordEntry> for WeightedKeyword {
+    fn from(entry: KeywordEntry) -> Self {
+        match entry {
+            KeywordEntry::Plain(keyword) => Self { keyword: normalize_keyword(&keyword), weight: 1.0 },
+            KeywordEntry::Weighted { keyword, weight } => Self { keyword: normalize_keyword(&keyword), weight },
+        }
+    }
+}
+
+impl From<&WeightedKeyword> for KeywordEntry {
+    fn from(keyword: &WeightedKeyword) -> Self {
+        if keyword.weight == 1.0 {
+            KeywordEntry::Plain(keyword.keyword.clone())
+        } else {
+            KeywordEntry::Weighted { keyword: keyword.keyword.clone(), weight: keyword.weight }
+        }
+    }
+}
+
+/// 统一关键词形式：小写并合并空白
+pub fn normalize_keyword(keyword: &str) -> String {
+    keyword.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+/// 规范化关键词并校验：关键词不能为空，权重必须为正数
+pub fn validate_keyword(keyword: &str, weight: f64) -> Result<WeightedKeyword, String> {
+    let keyword = normalize_keyword(keyword);
+    if keyword.is_empty() {
+        return Err("Keyword must not be empty".to_string());
+    }
+    if !weight.is_finite() || weight <= 0.0 {
+        return Err(format!("Keyword weight must be positive, got {}", weight));
+    }
+    Ok(WeightedKeyword { keyword, weight })
+}
+
+/// 读取关键词文件
+pub fn read_keyword_file(path: &Path) -> Result<HashMap<Domain, Vec<WeightedKeyword>>, Box<dyn std::error::Error + Send + Sync>> {
+    let file: BTreeMap<String, Vec<KeywordEntry>> = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let mut keywords = HashMap::new();
+    for (name, entries) in file {
+        let domain = Domain::parse(&name).ok_or_else(|| format!("Unknown domain '{}' in {}", name, path.display()))?;
+        keywords.insert(domain, entries.into_iter().map(WeightedKeyword::from).collect());
+    }
+    Ok(keywords)
+}
+
+/// 关键词存储
+pub struct KeywordStore {
+    path: PathBuf,
+    keywords: Arc<RwLock<HashMap<Domain, Vec<WeightedKeyword>>>>,
+}
+
+impl KeywordStore {
+    /// 从关键词文件加载
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.into();
+        let keywords = read_keyword_file(&path)?;
+        Ok(Self {
+            path,
+            keywords: Arc::new(RwLock::new(keywords)),
+        })
+    }
+
+    /// 关键词文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 添加关键词或更新已有关键词的权重，并写回文件
+    pub async fn add_keyword(&self, domain: Domain, keyword: &str, weight: f64) -> Result<WeightedKeyword, Box<dyn std::error::Error + Send + Sync>> {
+        let entry = validate_keyword(keyword, weight)?;
+        self.add_keywords(domain, vec![entry.clone()]).await?;
+        Ok(entry)
+    }
+
+    /// 批量添加关键词：全部校验通过后才修改，并只写回一次文件
+    pub async fn add_keywords(&self, domain: Domain, entries: Vec<WeightedKeyword>) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        let entries = entries
+            .iter()
+            .map(|entry| validate_keyword(&entry.keyword, entry.weight))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut keywords = self.keywords.write().await;
+        let list = keywords.entry(domain).or_default();
+        for entry in &entries {
+            match list.iter_mut().find(|existing| existing.keyword == entry.keyword) {
+                Some(existing) => existing.weight = entry.weight,
+                None => list.push(entry.clone()),
+            }
+        }
+        self.persist(&keywords)?;
+        Ok(entries.len())
+    }
+
+    /// 删除关键词并写回文件；关键词不存在时返回false
+    pub async fn remove_keyword(&self, domain: Domain, keyword: &str) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let keyword = normalize_keyword(keyword);
+        let mut keywords = self.keywords.write().await;
+        let Some(list) = keywords.get_mut(&domain) else {
+            return Ok(false);
+        };
+        let before = list.len();
+        list.retain(|existing| existing.keyword != keyword);
+        if list.len() == before {
+            return Ok(false);
+        }
+        self.persist(&keywords)?;
+        Ok(true)
+    }
+
+    /// 各领域的关键词（按领域名称索引）
+    pub async fn list_keywords(&self) -> BTreeMap<String, Vec<WeightedKeyword>> {
+        self.keywords
+            .read()
+            .await
+            .iter()
+            .map(|(domain, keywords)| (domain.to_string(), keywords.clone()))
+            .collect()
+    }
+
+    /// 用当前关键词构建分类器
+    pub async fn classifier(&self) -> DomainClassifier {
+        DomainClassifier::from_keywords(&*self.keywords.read().await)
+    }
+
+    /// 先写临时文件再重命名，避免写到一半时被读取
+    fn persist(&self, keywords: &HashMap<Domain, Vec<WeightedKeyword>>) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let file: BTreeMap<String, Vec<KeywordEntry>> = keywords
+            .iter()
+            .map(|(domain, list)| (domain.to_string(), list.iter().map(KeywordEntry::from).collect()))
+            .collect();
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&file)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_weighted_keywords_persist() {
+        let path = std::env::temp_dir().join(format!("penlai-keywords-{}.json", Uuid::new_v4()));
+        fs::copy(DEFAULT_KEYWORDS_PATH, &path).unwrap();
+        let store = KeywordStore::load(&path).unwrap();
+
+        // 添加关键词前不会被判为法律领域
+        let query = "my broker issued a margin call";
+        assert_ne!(store.classifier().await.classify_domain(query), Domain::Legal);
+
+        let added = store.add_keyword(Domain::Legal, "  Margin   Call ", 5.0).await.unwrap();
+        assert_eq!(added.keyword, "margin call");
+        assert!(added.is_phrase());
+        assert_eq!(store.classifier().await.classify_domain(query), Domain::Legal);
+        assert!(store.add_keyword(Domain::Legal, "broker", 0.0).await.is_err());
+
+        // 写回文件后重新加载仍然生效，原有纯字符串条目保持不变
+        let reloaded = KeywordStore::load(&path).unwrap();
+        let legal = reloaded.list_keywords().await.remove("legal").unwrap();
+        assert!(legal.contains(&WeightedKeyword { keyword: "margin call".to_string(), weight: 5.0 }));
+        assert!(legal.iter().any(|keyword| keyword.keyword == "contract" && keyword.weight == 1.0));
+        assert_eq!(DomainClassifier::from_path(&path).unwrap().classify_domain(query), Domain::Legal);
+
+        assert!(reloaded.remove_keyword(Domain::Legal, "MARGIN CALL").await.unwrap());
+        assert!(!reloaded.remove_keyword(Domain::Legal, "margin call").await.unwrap());
+        assert_ne!(KeywordStore::load(&path).unwrap().classifier().await.classify_domain(query), Domain::Legal);
+        fs::remove_file(&path).unwrap();
+    }
+}
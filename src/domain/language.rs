@@ -0,0 +1,86 @@
+//! 内容语言识别 - 按字符分布判断上下文和查询是中文还是英文，创建上下文时写入元数据供检索按语言路由
+
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use crate::context::llm_context::LLMContext;
+
+/// 上下文元数据中的语言标签（取值为语言代码，如"zh"、"en"）
+pub const LANGUAGE_METADATA_KEY: &str = "language";
+
+/// 内容语言
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentLanguage {
+    Chinese,
+    English,
+}
+
+impl ContentLanguage {
+    /// 语言代码
+    pub fn code(&self) -> &'static str {
+        match self {
+            ContentLanguage::Chinese => "zh",
+            ContentLanguage::English => "en",
+        }
+    }
+
+    /// 按语言代码或语言区域解析（如"zh"、"zh-CN"、"en_US"），不区分大小写
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary = code.trim().split(['-', '_']).next().unwrap_or_default().to_ascii_lowercase();
+        match primary.as_str() {
+            "zh" => Some(ContentLanguage::Chinese),
+            "en" => Some(ContentLanguage::English),
+            _ => None,
+        }
+    }
+}
+
+/// 是否为CJK统一表意文字
+fn is_han(c: char) -> bool {
+    matches!(c, '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}')
+}
+
+/// 一个汉字约相当于五个拉丁字母的信息量，夹杂少量英文术语的中文仍判为中文
+const LATIN_LETTERS_PER_HAN: usize = 5;
+
+/// 识别文本语言；没有汉字也没有拉丁字母时返回None
+pub fn detect_language(text: &str) -> Option<ContentLanguage> {
+    let han = text.chars().filter(|c| is_han(*c)).count();
+    let latin = text.chars().filter(|c| c.is_ascii_alphabetic()).count();
+    if han == 0 && latin == 0 {
+        return None;
+    }
+    if han * LATIN_LETTERS_PER_HAN >= latin {
+        Some(ContentLanguage::Chinese)
+    } else {
+        Some(ContentLanguage::English)
+    }
+}
+
+/// 元数据中没有语言标签时按内容识别并写入
+pub fn tag_language(metadata: &mut HashMap<String, String>, content: &str) {
+    if metadata.contains_key(LANGUAGE_METADATA_KEY) {
+        return;
+    }
+    if let Some(language) = detect_language(content) {
+        metadata.insert(LANGUAGE_METADATA_KEY.to_string(), language.code().to_string());
+    }
+}
+
+/// 上下文更新后刷新语言标签：原标签是自动识别的且内容变化时重新识别，显式指定的标签保留
+pub fn retag_language(previous: &LLMContext, current: &mut LLMContext) {
+    let previous_tag = previous.metadata.get(LANGUAGE_METADATA_KEY);
+    let was_detected = previous_tag.map(String::as_str) == detect_language(&previous.context_data).map(|language| language.code());
+    if was_detected && current.context_data != previous.context_data && current.metadata.get(LANGUAGE_METADATA_KEY) == previous_tag {
+        current.metadata.remove(LANGUAGE_METADATA_KEY);
+    }
+    tag_language(&mut current.metadata, &current.context_data);
+}
+
+/// 上下文的语言：优先使用语言标签，没有标签（如早期创建的上下文）时按内容识别
+pub fn context_language(context: &LLMContext) -> Option<ContentLanguage> {
+    match context.metadata.get(LANGUAGE_METADATA_KEY) {
+        Some(code) => ContentLanguage::from_code(code),
+        None => detect_language(&context.context_data),
+    }
+}
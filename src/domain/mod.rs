@@ -1 +1,3 @@
-pub mod domain_classifier;
\ No newline at end of file
+pub mod domain_classifier;
+pub mod keyword_store;
+pub mod language;
\ No newline at end of file
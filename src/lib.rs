@@ -7,4 +7,12 @@ pub mod monitoring;
 pub mod utils;
 pub mod cache;
 pub mod strategy;
-pub mod domain;
\ No newline at end of file
+pub mod domain;
+pub mod app;
+pub mod server;
+pub mod config_reload;
+pub mod offboarding;
+pub mod maintenance;
+pub mod bundle;
+#[cfg(test)]
+pub(crate) mod test_support;
\ No newline at end of file
@@ -1,35 +1,341 @@
 use std::sync::Arc;
-use tokio;
-use penlai::context::llm_context;
-use penlai::selection::async_context_selector;
-use penlai::processing::concurrent_processor;
-use penlai::monitoring::monitoring;
+use clap::{Parser, Subcommand};
+use penlai::bundle::{bundle_verifying_key, generate_signing_key, parse_verifying_key, BundleInstallReport, BundleSpec, SignedBundle};
+use penlai::maintenance::{MaintenanceStatus, SnapshotReport};
+use penlai::selection::embedding_migration::{MigrationProgress, MigrationStatus};
+
+/// Penlai命令行
+#[derive(Parser)]
+#[command(name = "penlai", about = "Enterprise-level asynchronous context management for LLMs")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 启动服务（默认）
+    Serve,
+    /// 让运行中的服务迁移到新的嵌入模型，并等待迁移完成
+    MigrateEmbeddings {
+        /// 新嵌入模型名称（如hashing-512）
+        #[arg(long)]
+        model: String,
+        /// 每批重新计算的上下文数
+        #[arg(long, default_value_t = 100)]
+        batch_size: usize,
+        /// 服务的HTTP地址
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        server: String,
+    },
+    /// 查看或切换运行中服务的维护模式
+    Maintenance {
+        #[command(subcommand)]
+        action: MaintenanceAction,
+        /// 服务的HTTP地址
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        server: String,
+    },
+    /// 打包、校验或安装供隔离环境使用的上下文包
+    Bundle {
+        #[command(subcommand)]
+        action: BundleAction,
+        /// 服务的HTTP地址
+        #[arg(long, default_value = "http://127.0.0.1:8080")]
+        server: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum BundleAction {
+    /// 由运行中的服务打包指定领域，写入本地包文件
+    Create {
+        /// 打包的领域（可重复）
+        #[arg(long = "domain", required = true)]
+        domains: Vec<String>,
+        /// 额外打包的上下文ID（可重复）
+        #[arg(long = "context")]
+        context_ids: Vec<uuid::Uuid>,
+        /// 附带上下文向量
+        #[arg(long)]
+        embeddings: bool,
+        /// 包说明
+        #[arg(long)]
+        description: Option<String>,
+        /// 输出的包文件
+        #[arg(long)]
+        out: String,
+    },
+    /// 离线校验包文件签名并显示清单（公钥默认取PENLAI_BUNDLE_PUBLIC_KEY）
+    Verify {
+        file: String,
+        /// 十六进制的验签公钥
+        #[arg(long)]
+        key: Option<String>,
+    },
+    /// 将本地包文件安装到运行中的服务（服务端再次校验签名）
+    Install {
+        file: String,
+        /// 用包中的安全策略替换服务已有的不同策略
+        #[arg(long)]
+        replace_safety_policies: bool,
+    },
+    /// 生成新的签名密钥对：私钥配置在打包实例，公钥分发到安装实例
+    Keygen,
+}
+
+#[derive(Subcommand)]
+enum MaintenanceAction {
+    /// 查看维护状态
+    Status,
+    /// 开启维护模式，等待进行中的请求处理完毕
+    On {
+        /// 拒绝新请求（默认排队等待维护结束）
+        #[arg(long)]
+        reject: bool,
+        /// 拒绝时建议客户端的重试间隔（秒）
+        #[arg(long, default_value_t = 30)]
+        retry_after: u64,
+        /// 维护原因
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// 关闭维护模式
+    Off,
+    /// 在维护期间将全部上下文导出为服务端快照目录下的JSON快照文件
+    Snapshot {
+        /// 快照目录（服务端的PENLAI_SNAPSHOT_DIR）下的文件名
+        #[arg(long)]
+        name: String,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match Cli::parse().command {
+        Some(Command::MigrateEmbeddings { model, batch_size, server }) => return migrate_embeddings(&server, &model, batch_size).await,
+        Some(Command::Maintenance { action, server }) => return maintenance(&server, action).await,
+        Some(Command::Bundle { action, server }) => return bundle(&server, action).await,
+        Some(Command::Serve) | None => {}
+    }
+
     println!("Penlai: Enterprise-Level Asynchronous Context Management Control for Large Language Models");
 
-    // 初始化上下文管理器
-    let context_manager = Arc::new(llm_context::ContextManager::new(100, 3600)); // 100并发，1小时TTL
+    // 初始化所有组件（100并发，1小时TTL）
+    let app = Arc::new(penlai::app::Penlai::new(100, 3600));
+
+    // 启动前自检，关键检查失败时拒绝启动
+    let options = penlai::app::SelfTestOptions {
+        require_ai_endpoint: std::env::var("SELF_TEST_REQUIRE_AI").map(|v| v != "false").unwrap_or(true),
+        ..Default::default()
+    };
+    let report = app.self_test(&options).await;
+    println!("{}", report);
+    if !report.ready {
+        return Err("Self-test failed, refusing to start".into());
+    }
+
+    // 定期检查搜索服务密钥，失效或被限流时触发监控警报
+    app.provider_health.clone().start_periodic_checks(app.monitoring.clone());
 
-    // 初始化上下文选择器
-    let context_selector = Arc::new(async_context_selector::ContextSelector::new(context_manager.clone()));
+    // 每日生成使用报告并推送到通知渠道
+    app.reports.clone().start_daily_reports();
 
-    // 初始化请求处理器
-    let request_processor = Arc::new(concurrent_processor::RequestProcessor::new(
-        context_manager.clone(),
-        context_selector.clone(),
-    ));
+    // 配置了分析数据库时导出监控事件，并每分钟快照一次原子指标一并导出
+    if app.start_analytics_export().await.is_some() {
+        app.monitoring.clone().start_snapshotting(std::time::Duration::from_secs(60));
+    }
+
+    // 上下文回收事件随监控事件一起导出
+    app.context_manager.get_gc_tracker().forward_to(app.monitoring.clone());
+
+    // 只读副本定期从主实例同步快照；过期提醒由主实例负责
+    if let Some(ref replica) = app.replica {
+        replica.clone().start();
+    } else {
+        app.expiry_notifier.clone().start();
+    }
 
-    // 创建监控系统
-    let monitoring_system = Arc::new(monitoring::MonitoringSystem::new());
+    // penlai.toml变化或收到SIGHUP时热加载配置，变更写入审计日志
+    let audit_log = app.context_manager.get_approval_workflow().map(|approval| approval.get_audit_log()).unwrap_or_default();
+    Arc::new(penlai::config_reload::ConfigWatcher::new(app.clone(), penlai::config_reload::config_path()).with_audit_log(audit_log)).start();
+
+    let context_manager = app.context_manager.clone();
+    let context_selector = app.context_selector.clone();
+    let request_processor = app.request_processor.clone();
+    let monitoring_system = app.monitoring.clone();
 
     // 启动服务
     start_service(context_manager, context_selector, request_processor, monitoring_system).await?;
 
+    // 配置了监听地址时启动HTTP接口
+    if let Ok(addr) = std::env::var("PENLAI_HTTP_ADDR") {
+        println!("Serving HTTP API on {} (OpenAPI spec at /openapi.json)", addr);
+        let state = penlai::server::http::HttpState::new(app, Some(report));
+        penlai::server::http::serve(state, addr.parse()?).await?;
+    }
+
+    Ok(())
+}
+
+/// 调用管理接口的HTTP客户端，携带PENLAI_ADMIN_TOKEN
+fn admin_client() -> Result<reqwest::Client, Box<dyn std::error::Error + Send + Sync>> {
+    let token = std::env::var("PENLAI_ADMIN_TOKEN").map_err(|_| "PENLAI_ADMIN_TOKEN must be set to call admin endpoints")?;
+    let mut authorization = reqwest::header::HeaderValue::from_str(&format!("Bearer {}", token))?;
+    authorization.set_sensitive(true);
+    let headers = reqwest::header::HeaderMap::from_iter([(reqwest::header::AUTHORIZATION, authorization)]);
+    Ok(reqwest::Client::builder().default_headers(headers).build()?)
+}
+
+/// 通过HTTP接口查看或切换维护模式
+async fn maintenance(server: &str, action: MaintenanceAction) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = admin_client()?;
+    let url = format!("{}/api/admin/maintenance", server.trim_end_matches('/'));
+    let request = match action {
+        MaintenanceAction::Status => client.get(&url),
+        MaintenanceAction::On { reject, retry_after, reason } => client.post(&url).json(&serde_json::json!({
+            "reason": reason,
+            "requests": if reject { "reject" } else { "queue" },
+            "retry_after_seconds": retry_after,
+        })),
+        MaintenanceAction::Off => client.delete(&url),
+        MaintenanceAction::Snapshot { name } => {
+            let response = client.post(format!("{}/snapshot", url)).json(&serde_json::json!({ "name": name })).send().await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                return Err(format!("Snapshot failed ({}): {}", status, response.text().await.unwrap_or_default()).into());
+            }
+            let report: SnapshotReport = response.json().await?;
+            println!("Wrote {} contexts ({} bytes) to {}", report.contexts, report.bytes, report.path);
+            return Ok(());
+        }
+    };
+    let status: MaintenanceStatus = request.send().await?.error_for_status()?.json().await?;
+    match (status.active, status.settings) {
+        (true, Some(settings)) => println!(
+            "Maintenance mode ON since {} ({:?} new requests, retry after {}s){}",
+            status.since.map(|since| since.to_rfc3339()).unwrap_or_default(),
+            settings.requests,
+            settings.retry_after_seconds,
+            settings.reason.map(|reason| format!(": {}", reason)).unwrap_or_default()
+        ),
+        _ => println!("Maintenance mode OFF"),
+    }
+    println!("  {} request(s) in flight, {} queued", status.in_flight_requests, status.queued_requests);
     Ok(())
 }
 
+/// 打包、离线校验或通过HTTP接口安装上下文包
+async fn bundle(server: &str, action: BundleAction) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let url = format!("{}/api/admin/bundles", server.trim_end_matches('/'));
+    match action {
+        BundleAction::Create { domains, context_ids, embeddings, description, out } => {
+            let spec = BundleSpec { domains, context_ids, include_embeddings: embeddings, description };
+            let response = admin_client()?.post(&url).json(&spec).send().await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                return Err(format!("Bundle creation failed ({}): {}", status, response.text().await.unwrap_or_default()).into());
+            }
+            let signed: SignedBundle = response.json().await?;
+            signed.write(&out)?;
+            println!("Wrote bundle to {}", out);
+        }
+        BundleAction::Verify { file, key } => {
+            let key = match key {
+                Some(key) => parse_verifying_key(&key)?,
+                None => bundle_verifying_key()?.ok_or("No public key: pass --key or set PENLAI_BUNDLE_PUBLIC_KEY")?,
+            };
+            let manifest = SignedBundle::read(&file)?.verify(&key)?.manifest();
+            println!("Bundle OK: format v{}, created {}", manifest.format_version, manifest.created_at.to_rfc3339());
+            if let Some(description) = manifest.description {
+                println!("  {}", description);
+            }
+            println!("  domains: {}", manifest.domains.join(", "));
+            println!(
+                "  {} contexts, {} keywords, {} safety policies, {} embeddings{}",
+                manifest.contexts,
+                manifest.keywords,
+                manifest.safety_policies,
+                manifest.embeddings,
+                manifest.embedding_model.map(|model| format!(" ({})", model)).unwrap_or_default()
+            );
+        }
+        BundleAction::Install { file, replace_safety_policies } => {
+            let signed = SignedBundle::read(&file)?;
+            let response = admin_client()?
+                .post(format!("{}/install", url))
+                .query(&[("replace_safety_policies", replace_safety_policies)])
+                .json(&signed)
+                .send()
+                .await?;
+            if !response.status().is_success() {
+                let status = response.status();
+                return Err(format!("Bundle installation failed ({}): {}", status, response.text().await.unwrap_or_default()).into());
+            }
+            let report: BundleInstallReport = response.json().await?;
+            println!(
+                "Installed {} contexts, {} keywords, {} safety policies, {} embeddings for {}",
+                report.contexts_installed,
+                report.keywords_installed,
+                report.safety_policies_installed,
+                report.embeddings_installed,
+                report.manifest.domains.join(", ")
+            );
+            if report.keywords_skipped > 0 || report.embeddings_skipped > 0 {
+                println!("  skipped {} keywords and {} embeddings", report.keywords_skipped, report.embeddings_skipped);
+            }
+            if !report.safety_policies_replaced.is_empty() {
+                println!("  replaced local safety policies for {}", report.safety_policies_replaced.join(", "));
+            }
+            if !report.safety_policies_kept.is_empty() {
+                println!(
+                    "  kept differing local safety policies for {} (pass --replace-safety-policies to overwrite)",
+                    report.safety_policies_kept.join(", ")
+                );
+            }
+        }
+        BundleAction::Keygen => {
+            let key = generate_signing_key();
+            println!("PENLAI_BUNDLE_SIGNING_KEY={}", hex::encode(key.to_bytes()));
+            println!("PENLAI_BUNDLE_PUBLIC_KEY={}", hex::encode(key.verifying_key().to_bytes()));
+        }
+    }
+    Ok(())
+}
+
+/// 通过HTTP接口发起嵌入模型迁移并轮询进度
+async fn migrate_embeddings(server: &str, model: &str, batch_size: usize) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let client = admin_client()?;
+    let url = format!("{}/api/embeddings/migration", server.trim_end_matches('/'));
+    let response = client
+        .post(&url)
+        .json(&serde_json::json!({ "model": model, "batch_size": batch_size }))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        let status = response.status();
+        return Err(format!("Failed to start migration ({}): {}", status, response.text().await.unwrap_or_default()).into());
+    }
+    let started: MigrationProgress = response.json().await?;
+    println!("Migrating embeddings {} -> {}", started.source_model, started.target_model);
+
+    loop {
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        let progress: MigrationProgress = client.get(&url).send().await?.error_for_status()?.json().await?;
+        println!("  {}/{} contexts ({:.0}%)", progress.migrated, progress.total, progress.fraction() * 100.0);
+        match progress.status {
+            MigrationStatus::Running => continue,
+            MigrationStatus::Completed => {
+                println!("Switched to {}", progress.target_model);
+                return Ok(());
+            }
+            MigrationStatus::Failed => {
+                return Err(format!("Migration failed: {}", progress.error.unwrap_or_default()).into());
+            }
+        }
+    }
+}
+
 async fn start_service(
     context_manager: Arc<penlai::context::llm_context::ContextManager>,
     context_selector: Arc<penlai::selection::async_context_selector::ContextSelector>,
@@ -132,7 +438,12 @@ async fn demo_functionality(
         }
         Err(e) => {
             eprintln!("Request processing failed: {:?}", e);
-            monitoring_system.record_metric("error_rate", penlai::monitoring::monitoring::PerformanceMetric::ErrorRate(1.0)).await;
+            monitoring_system.log_event(penlai::monitoring::monitoring::MonitoringEvent::RequestFailed {
+                user_id: "user_medical_001".to_string(),
+                session_id: "session_medical_001".to_string(),
+                error: e.to_string(),
+                duration_ms: request_duration,
+            }).await;
         }
     }
 
@@ -165,13 +476,13 @@ async fn test_concurrent_requests(
 ) {
     println!("Testing concurrent request handling...");
 
-    let mut handles = Vec::new();
-
-    // 发起多个并发请求
-    for i in 0..5 {
+    // 发起多个并发请求（任务panic或失败都会汇总报告，不会被静默忽略）
+    let requests = 5;
+    monitoring_system.record_metric("concurrent_requests", penlai::monitoring::monitoring::PerformanceMetric::ConcurrentRequests(requests)).await;
+    let outcomes = penlai::utils::async_runtime::spawn_fan_out(0..requests, requests, |_| false, |i| {
         let processor_clone = request_processor.clone();
         let monitoring_clone = monitoring_system.clone();
-        let handle = tokio::spawn(async move {
+        async move {
             let start_time = std::time::Instant::now();
             let result = processor_clone
                 .process_request(
@@ -192,24 +503,24 @@ async fn test_concurrent_requests(
                         session_id: process_result.session_id,
                         duration_ms: duration,
                     }).await;
+                    Ok(())
                 },
                 Err(e) => {
-                    eprintln!("Concurrent request {} failed: {:?}", i, e);
-                    monitoring_clone.record_metric("error_rate", penlai::monitoring::monitoring::PerformanceMetric::ErrorRate(1.0)).await;
+                    monitoring_clone.log_event(penlai::monitoring::monitoring::MonitoringEvent::RequestFailed {
+                        user_id: format!("concurrent_user_{}", i),
+                        session_id: format!("session_concurrent_{}", i),
+                        error: e.to_string(),
+                        duration_ms: duration,
+                    }).await;
+                    Err(e)
                 }
             }
-        });
-
-        handles.push(handle);
-    }
-
-    // 记录并发请求数量
-    monitoring_system.record_metric("concurrent_requests", penlai::monitoring::monitoring::PerformanceMetric::ConcurrentRequests(handles.len())).await;
+        }
+    })
+    .await;
 
-    // 等待所有并发请求完成
-    for handle in handles {
-        let _ = handle.await;
+    if let Err(e) = penlai::utils::async_runtime::FanOutError::collect(outcomes) {
+        eprintln!("Concurrent requests failed: {}", e);
     }
-
     println!("Concurrent request testing completed");
 }
\ No newline at end of file
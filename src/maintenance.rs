@@ -0,0 +1,367 @@
+//! 维护模式 - 开启后暂停后台任务（副本同步、过期提醒、日报、整理和优先级调整），新请求按设置排队等待或带重试提示拒绝；
+//! 进行中的请求处理完毕后可安全地导出快照或执行迁移，健康检查在维护期间返回503；
+//! 维护期间上下文存储拒绝写入，入库任务暂停到维护结束
+
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{watch, RwLock};
+use tokio::time::Duration;
+use utoipa::ToSchema;
+use crate::app::Penlai;
+use crate::context::codec::JsonCodec;
+
+/// 默认快照目录
+pub const DEFAULT_SNAPSHOT_DIR: &str = "snapshots";
+
+/// 维护快照的存放目录，可通过`PENLAI_SNAPSHOT_DIR`覆盖
+pub fn snapshot_dir() -> PathBuf {
+    std::env::var("PENLAI_SNAPSHOT_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from(DEFAULT_SNAPSHOT_DIR))
+}
+
+/// 将快照文件名解析为快照目录下的路径；拒绝空名、绝对路径和包含`..`等非普通成分的名称
+pub fn resolve_snapshot_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(name);
+    if name.is_empty() || !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        return Err(format!("Invalid snapshot name '{}': must be a relative path inside the snapshot directory", name));
+    }
+    Ok(dir.join(relative))
+}
+
+/// 维护期间新请求的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceRequestPolicy {
+    #[default]
+    Queue,      // 排队等待维护结束（受请求截止时间限制）
+    Reject,     // 立即拒绝，附带重试提示
+}
+
+/// 维护设置
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceSettings {
+    #[serde(default)]
+    pub reason: Option<String>,
+    #[serde(default)]
+    pub requests: MaintenanceRequestPolicy,
+    #[serde(default = "default_retry_after_seconds")]
+    pub retry_after_seconds: u64,   // 拒绝时建议客户端的重试间隔
+}
+
+fn default_retry_after_seconds() -> u64 {
+    30
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            reason: None,
+            requests: MaintenanceRequestPolicy::default(),
+            retry_after_seconds: default_retry_after_seconds(),
+        }
+    }
+}
+
+/// 维护状态
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceStatus {
+    pub active: bool,
+    pub since: Option<DateTime<Utc>>,
+    pub settings: Option<MaintenanceSettings>,
+    pub queued_requests: usize,     // 正在排队等待维护结束的请求
+    pub in_flight_requests: usize,  // 仍在处理的请求，为0时可安全导出快照或迁移
+}
+
+/// 维护期间被拒绝（或排队超时）的请求
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceRejection {
+    pub retry_after_seconds: u64,
+}
+
+impl std::fmt::Display for MaintenanceRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Service is in maintenance mode, retry after {}s", self.retry_after_seconds)
+    }
+}
+
+impl std::error::Error for MaintenanceRejection {}
+
+/// 判断错误是否由维护模式引起
+pub fn is_maintenance_error(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    error.downcast_ref::<MaintenanceRejection>().is_some()
+}
+
+/// 快照导出结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SnapshotReport {
+    pub path: String,
+    pub contexts: usize,
+    pub bytes: usize,
+}
+
+/// 维护模式开关，由请求处理器和后台任务共享
+pub struct MaintenanceMode {
+    active: watch::Sender<bool>,
+    state: RwLock<Option<(DateTime<Utc>, MaintenanceSettings)>>,
+    queued: AtomicUsize,
+    retry_after_seconds: AtomicU64,   // 当前设置的重试间隔，供同步的写入检查使用
+}
+
+impl Default for MaintenanceMode {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MaintenanceMode {
+    /// 创建（未开启的）维护模式开关
+    pub fn new() -> Self {
+        Self {
+            active: watch::Sender::new(false),
+            state: RwLock::new(None),
+            queued: AtomicUsize::new(0),
+            retry_after_seconds: AtomicU64::new(default_retry_after_seconds()),
+        }
+    }
+
+    /// 开启维护模式；已开启时更新设置，保留开始时间
+    pub async fn enable(&self, settings: MaintenanceSettings) {
+        let mut state = self.state.write().await;
+        let since = state.as_ref().map(|(since, _)| *since).unwrap_or_else(Utc::now);
+        log::info!("Maintenance mode enabled ({:?}): {}", settings.requests, settings.reason.as_deref().unwrap_or("no reason given"));
+        self.retry_after_seconds.store(settings.retry_after_seconds, Ordering::SeqCst);
+        *state = Some((since, settings));
+        self.active.send_replace(true);
+    }
+
+    /// 关闭维护模式，排队的请求和暂停的后台任务继续执行；返回之前是否开启
+    pub async fn disable(&self) -> bool {
+        let was_active = self.state.write().await.take().is_some();
+        self.active.send_replace(false);
+        if was_active {
+            log::info!("Maintenance mode disabled");
+        }
+        was_active
+    }
+
+    /// 是否处于维护模式
+    pub fn is_active(&self) -> bool {
+        *self.active.borrow()
+    }
+
+    /// 写操作检查：维护期间拒绝，附带重试提示
+    pub fn ensure_inactive(&self) -> Result<(), MaintenanceRejection> {
+        if self.is_active() {
+            return Err(MaintenanceRejection { retry_after_seconds: self.retry_after_seconds.load(Ordering::SeqCst) });
+        }
+        Ok(())
+    }
+
+    /// 当前状态（不含进行中的请求数，由`Penlai::maintenance_status`补充）
+    pub async fn status(&self) -> MaintenanceStatus {
+        let state = self.state.read().await.clone();
+        MaintenanceStatus {
+            active: state.is_some(),
+            since: state.as_ref().map(|(since, _)| *since),
+            settings: state.map(|(_, settings)| settings),
+            queued_requests: self.queued.load(Ordering::SeqCst),
+            in_flight_requests: 0,
+        }
+    }
+
+    /// 等待维护结束；后台任务在每轮工作前调用，维护期间暂停于此
+    pub async fn wait_until_inactive(&self) {
+        let mut receiver = self.active.subscribe();
+        let _ = receiver.wait_for(|active| !*active).await;
+    }
+
+    /// 请求准入：未在维护时直接通过；维护期间按设置排队（最多等待max_wait）或拒绝
+    pub async fn admit(&self, max_wait: Option<Duration>) -> Result<(), MaintenanceRejection> {
+        if !self.is_active() {
+            return Ok(());
+        }
+        let Some((_, settings)) = self.state.read().await.clone() else {
+            return Ok(());
+        };
+        let rejection = MaintenanceRejection { retry_after_seconds: settings.retry_after_seconds };
+        if settings.requests == MaintenanceRequestPolicy::Reject {
+            return Err(rejection);
+        }
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        let waited = match max_wait {
+            Some(max_wait) => tokio::time::timeout(max_wait, self.wait_until_inactive()).await.is_ok(),
+            None => {
+                self.wait_until_inactive().await;
+                true
+            }
+        };
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        if waited {
+            Ok(())
+        } else {
+            Err(rejection)
+        }
+    }
+}
+
+impl Penlai {
+    /// 开启维护模式，并最多等待drain_timeout让进行中的请求处理完毕；返回开启后的状态
+    pub async fn enter_maintenance(&self, settings: MaintenanceSettings, drain_timeout: Duration) -> MaintenanceStatus {
+        self.maintenance.enable(settings).await;
+        let drained = tokio::time::timeout(drain_timeout, async {
+            while self.request_processor.get_stats().await.active_requests > 0 {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await;
+        if drained.is_err() {
+            log::warn!("Requests still in flight {:?} after entering maintenance mode", drain_timeout);
+        }
+        self.maintenance_status().await
+    }
+
+    /// 关闭维护模式
+    pub async fn exit_maintenance(&self) -> MaintenanceStatus {
+        self.maintenance.disable().await;
+        self.maintenance_status().await
+    }
+
+    /// 维护状态，含进行中的请求数
+    pub async fn maintenance_status(&self) -> MaintenanceStatus {
+        let mut status = self.maintenance.status().await;
+        status.in_flight_requests = self.request_processor.get_stats().await.active_requests;
+        status
+    }
+
+    /// 在维护期间将全部上下文以JSON快照导出到快照目录下的name文件；名称不合法、未开启维护或仍有进行中的请求时拒绝
+    pub async fn maintenance_snapshot(&self, name: &str) -> Result<SnapshotReport, Box<dyn std::error::Error + Send + Sync>> {
+        let path = resolve_snapshot_path(&self.snapshot_dir, name)?;
+        let status = self.maintenance_status().await;
+        if !status.active {
+            return Err("Snapshots require maintenance mode".into());
+        }
+        if status.in_flight_requests > 0 {
+            return Err(format!("{} request(s) still in flight", status.in_flight_requests).into());
+        }
+        let contexts = self.context_manager.get_all_contexts().await.len();
+        let bytes = self.context_manager.export_contexts(&JsonCodec).await?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, &bytes).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        Ok(SnapshotReport {
+            path: path.display().to_string(),
+            contexts,
+            bytes: bytes.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use crate::context::ingestion::{IngestDocument, IngestRequest};
+    use crate::context::llm_context::ContextManager;
+    use crate::context::replica::{PrimarySnapshotSource, ReplicaConfig, ReplicaSync};
+    use crate::processing::concurrent_processor::{RequestError, RequestOptions};
+
+    #[tokio::test]
+    async fn test_maintenance_queues_rejects_and_pauses_jobs() {
+        let mut app = Penlai::new(10, 3600);
+        app.snapshot_dir = std::env::temp_dir().join(format!("penlai-maintenance-{}", Uuid::new_v4()));
+        let app = Arc::new(app);
+        app.context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia is treated with antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        assert!(app.maintenance_snapshot("snapshot.json").await.is_err());
+
+        // 拒绝模式：带重试提示
+        let settings = MaintenanceSettings { requests: MaintenanceRequestPolicy::Reject, retry_after_seconds: 120, ..MaintenanceSettings::default() };
+        let status = app.enter_maintenance(settings, Duration::from_secs(1)).await;
+        assert!(status.active && status.in_flight_requests == 0);
+        let request = |deadline_ms: Option<u64>| {
+            let app = app.clone();
+            let options = RequestOptions { deadline_ms, ..RequestOptions::default() };
+            async move {
+                app.request_processor
+                    .process_request_with_options("u1".to_string(), "s1".to_string(), "pneumonia".to_string(), "medical".to_string(), options)
+                    .await
+            }
+        };
+        assert!(matches!(request(None).await, Err(RequestError::Maintenance { retry_after_seconds: 120 })));
+
+        // 维护期间拒绝写入上下文
+        let write = app
+            .context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Written during maintenance".to_string(), 5)
+            .await;
+        assert!(write.is_err_and(|e| is_maintenance_error(e.as_ref())));
+        // 入库任务暂停到维护结束
+        let ingest_job = app
+            .ingestion
+            .submit(IngestRequest {
+                source: "wiki".to_string(),
+                documents: vec![IngestDocument {
+                    external_id: None,
+                    title: None,
+                    content: "Ingested after maintenance".to_string(),
+                    domain: "education".to_string(),
+                    session_id: None,
+                    user_id: None,
+                    priority: None,
+                    metadata: Default::default(),
+                }],
+            })
+            .await;
+
+        // 维护期间可安全导出快照，只能写入快照目录
+        for name in ["/etc/passwd", "../escape.json", "nested/../../escape.json", ""] {
+            assert!(app.maintenance_snapshot(name).await.is_err());
+        }
+        let snapshot = app.maintenance_snapshot("nested/snapshot.json").await.unwrap();
+        assert_eq!(snapshot.contexts, 1);
+        assert_eq!(std::fs::metadata(app.snapshot_dir.join("nested/snapshot.json")).unwrap().len() as usize, snapshot.bytes);
+        std::fs::remove_dir_all(&app.snapshot_dir).unwrap();
+
+        // 排队模式：截止时间内未结束维护则拒绝，结束后排队的请求继续处理
+        app.enter_maintenance(MaintenanceSettings::default(), Duration::from_secs(1)).await;
+        assert!(matches!(request(Some(50)).await, Err(RequestError::Maintenance { .. })));
+        let queued = tokio::spawn(request(None));
+        while app.maintenance_status().await.queued_requests == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // 后台任务在维护期间暂停
+        let sync = Arc::new(
+            ReplicaSync::new(
+                Arc::new(ContextManager::new(10, 3600)),
+                Arc::new(PrimarySnapshotSource::new(app.context_manager.clone())),
+                ReplicaConfig::default(),
+            )
+            .with_maintenance(app.maintenance.clone()),
+        );
+        let job = sync.clone().start();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(sync.get_status().await.syncs, 0);
+
+        assert_eq!(app.ingestion.get_job(ingest_job).await.unwrap().created, 0);
+
+        app.exit_maintenance().await;
+        assert_eq!(queued.await.unwrap().unwrap().selected_contexts.len(), 1);
+        while app.ingestion.get_job(ingest_job).await.unwrap().finished_at.is_none() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(app.ingestion.get_job(ingest_job).await.unwrap().created, 1);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(sync.get_status().await.syncs, 1);
+        job.abort();
+    }
+}
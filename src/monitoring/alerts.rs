@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 警报状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AlertState {
+    Firing,         // 触发中，尚未处理
+    Acknowledged,   // 已确认，处理中
+    Resolved,       // 已解决
+}
+
+/// 警报 - 同一指标在解决前的重复越限合并为一个警报
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: Uuid,
+    pub metric: String,                         // 越限的指标名
+    pub value: f64,                             // 最近一次越限的值
+    pub threshold: f64,                         // 阈值
+    pub state: AlertState,
+    pub first_fired_at: DateTime<Utc>,          // 首次触发时间
+    pub last_fired_at: DateTime<Utc>,           // 最近一次越限时间
+    pub occurrence_count: u32,                  // 越限次数
+    pub assignee: Option<String>,               // 负责人
+    pub acknowledged_by: Option<String>,        // 确认人
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub resolution_note: Option<String>,        // 解决说明
+}
+
+impl Alert {
+    /// 警报是否仍未解决
+    pub fn is_open(&self) -> bool {
+        self.state != AlertState::Resolved
+    }
+}
+
+/// 警报管理器 - 跟踪警报从触发、确认到解决的生命周期
+pub struct AlertManager {
+    alerts: Arc<RwLock<HashMap<Uuid, Alert>>>,
+}
+
+impl Default for AlertManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AlertManager {
+    /// 创建新的警报管理器
+    pub fn new() -> Self {
+        Self {
+            alerts: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 记录一次指标越限；指标已有未解决的警报时合并到该警报。返回警报及是否为新触发
+    pub async fn fire(&self, metric: &str, value: f64, threshold: f64) -> (Alert, bool) {
+        let now = Utc::now();
+        let mut alerts = self.alerts.write().await;
+
+        if let Some(alert) = alerts.values_mut().find(|a| a.metric == metric && a.is_open()) {
+            alert.value = value;
+            alert.threshold = threshold;
+            alert.last_fired_at = now;
+            alert.occurrence_count += 1;
+            return (alert.clone(), false);
+        }
+
+        let alert = Alert {
+            id: Uuid::new_v4(),
+            metric: metric.to_string(),
+            value,
+            threshold,
+            state: AlertState::Firing,
+            first_fired_at: now,
+            last_fired_at: now,
+            occurrence_count: 1,
+            assignee: None,
+            acknowledged_by: None,
+            acknowledged_at: None,
+            resolved_at: None,
+            resolution_note: None,
+        };
+        alerts.insert(alert.id, alert.clone());
+        (alert, true)
+    }
+
+    /// 确认警报
+    pub async fn acknowledge(&self, alert_id: Uuid, acknowledged_by: &str) -> Result<Alert, Box<dyn std::error::Error + Send + Sync>> {
+        let mut alerts = self.alerts.write().await;
+        let alert = alerts.get_mut(&alert_id).ok_or("Alert not found")?;
+        if alert.state == AlertState::Resolved {
+            return Err("Alert already resolved".into());
+        }
+        alert.state = AlertState::Acknowledged;
+        alert.acknowledged_by = Some(acknowledged_by.to_string());
+        alert.acknowledged_at = Some(Utc::now());
+        if alert.assignee.is_none() {
+            alert.assignee = Some(acknowledged_by.to_string());
+        }
+        Ok(alert.clone())
+    }
+
+    /// 指派警报负责人
+    pub async fn assign(&self, alert_id: Uuid, assignee: &str) -> Result<Alert, Box<dyn std::error::Error + Send + Sync>> {
+        let mut alerts = self.alerts.write().await;
+        let alert = alerts.get_mut(&alert_id).ok_or("Alert not found")?;
+        alert.assignee = Some(assignee.to_string());
+        Ok(alert.clone())
+    }
+
+    /// 解决警报
+    pub async fn resolve(&self, alert_id: Uuid, note: Option<String>) -> Result<Alert, Box<dyn std::error::Error + Send + Sync>> {
+        let mut alerts = self.alerts.write().await;
+        let alert = alerts.get_mut(&alert_id).ok_or("Alert not found")?;
+        if alert.state != AlertState::Resolved {
+            alert.state = AlertState::Resolved;
+            alert.resolved_at = Some(Utc::now());
+            alert.resolution_note = note;
+        }
+        Ok(alert.clone())
+    }
+
+    /// 指标恢复正常时自动解决其未解决的警报，返回被解决的警报
+    pub async fn resolve_metric(&self, metric: &str) -> Option<Alert> {
+        let mut alerts = self.alerts.write().await;
+        let alert = alerts.values_mut().find(|a| a.metric == metric && a.is_open())?;
+        alert.state = AlertState::Resolved;
+        alert.resolved_at = Some(Utc::now());
+        alert.resolution_note = Some("Metric recovered below threshold".to_string());
+        Some(alert.clone())
+    }
+
+    /// 获取警报
+    pub async fn get_alert(&self, alert_id: Uuid) -> Option<Alert> {
+        self.alerts.read().await.get(&alert_id).cloned()
+    }
+
+    /// 获取所有未解决的警报（按首次触发时间排序）
+    pub async fn get_open_alerts(&self) -> Vec<Alert> {
+        let mut open: Vec<Alert> = self.alerts.read().await.values().filter(|a| a.is_open()).cloned().collect();
+        open.sort_by_key(|a| a.first_fired_at);
+        open
+    }
+
+    /// 获取所有警报（按首次触发时间排序）
+    pub async fn get_all_alerts(&self) -> Vec<Alert> {
+        let mut all: Vec<Alert> = self.alerts.read().await.values().cloned().collect();
+        all.sort_by_key(|a| a.first_fired_at);
+        all
+    }
+
+    /// 清除已解决的警报
+    pub async fn purge_resolved(&self) {
+        self.alerts.write().await.retain(|_, alert| alert.is_open());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_alert_lifecycle() {
+        let manager = AlertManager::new();
+
+        let (alert, is_new) = manager.fire("request_latency_ms", 800.0, 500.0).await;
+        assert!(is_new);
+        assert_eq!(alert.state, AlertState::Firing);
+
+        // 重复越限合并到同一警报
+        let (repeat, is_new) = manager.fire("request_latency_ms", 900.0, 500.0).await;
+        assert!(!is_new);
+        assert_eq!(repeat.id, alert.id);
+        assert_eq!(repeat.occurrence_count, 2);
+
+        let acked = manager.acknowledge(alert.id, "oncall").await.unwrap();
+        assert_eq!(acked.state, AlertState::Acknowledged);
+        assert_eq!(acked.assignee.as_deref(), Some("oncall"));
+        manager.assign(alert.id, "dba").await.unwrap();
+
+        let resolved = manager.resolve(alert.id, Some("Index added".to_string())).await.unwrap();
+        assert_eq!(resolved.state, AlertState::Resolved);
+        assert_eq!(resolved.assignee.as_deref(), Some("dba"));
+        assert!(manager.get_open_alerts().await.is_empty());
+        assert!(manager.acknowledge(alert.id, "oncall").await.is_err());
+
+        // 解决后再次越限产生新警报
+        let (next, is_new) = manager.fire("request_latency_ms", 700.0, 500.0).await;
+        assert!(is_new);
+        assert_ne!(next.id, alert.id);
+        assert!(manager.acknowledge(Uuid::new_v4(), "oncall").await.is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! 监控仪表盘API - 将原始监控事件聚合为可直接绘图的JSON数据
+
+use std::collections::{BTreeMap, HashMap};
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem};
+
+/// 延迟时间桶
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct LatencyBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub count: usize,       // 桶内请求数
+    pub avg_ms: f64,        // 平均延迟
+    pub p95_ms: f64,        // P95延迟
+    pub max_ms: f64,        // 最大延迟
+}
+
+/// 错误率时间桶
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ErrorRateBucket {
+    pub bucket_start: DateTime<Utc>,
+    pub requests: usize,        // 完成的请求数（成功 + 失败）
+    pub errors: usize,          // 失败的请求数
+    pub rate_limited: usize,    // 被限流的请求数
+    pub alerts: usize,          // 性能警报数
+    pub error_rate: f64,        // 失败请求数 / 完成的请求数
+}
+
+/// 领域统计
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DomainStat {
+    pub domain: String,
+    pub load_count: usize,      // 上下文加载次数
+    pub avg_load_ms: f64,       // 平均加载时间
+}
+
+/// 缓存访问统计（按键类型）
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CacheAccessStat {
+    pub key_type: String,
+    pub hits: usize,
+    pub misses: usize,
+    pub hit_rate: f64,
+}
+
+/// 查询窗口的最大小时数
+pub const MAX_WINDOW_HOURS: i64 = 24 * 30;
+/// 单次查询最多生成的时间桶数
+pub const MAX_BUCKETS: i64 = 10_000;
+
+/// 在MAX_BUCKETS限制下，指定窗口允许的最小桶大小（秒）
+pub fn min_bucket_seconds(hours: i64) -> i64 {
+    ((hours.clamp(1, MAX_WINDOW_HOURS) * 3600 + MAX_BUCKETS - 1) / MAX_BUCKETS).max(1)
+}
+
+/// 校验查询窗口：小时数在1..=MAX_WINDOW_HOURS内，且桶大小使桶数不超过MAX_BUCKETS
+pub fn validate_window(hours: i64, bucket_seconds: i64) -> Result<(), String> {
+    if !(1..=MAX_WINDOW_HOURS).contains(&hours) {
+        return Err(format!("hours must be between 1 and {}", MAX_WINDOW_HOURS));
+    }
+    let min_bucket_seconds = min_bucket_seconds(hours);
+    if bucket_seconds < min_bucket_seconds {
+        return Err(format!("bucket_seconds must be at least {} for a {}-hour window", min_bucket_seconds, hours));
+    }
+    Ok(())
+}
+
+/// 将时间对齐到桶的起始时间
+fn bucket_start(timestamp: DateTime<Utc>, bucket_seconds: i64) -> i64 {
+    timestamp.timestamp().div_euclid(bucket_seconds) * bucket_seconds
+}
+
+/// 生成覆盖时间范围的所有桶（包含空桶，便于绘图）
+fn empty_buckets<T>(hours: i64, bucket_seconds: i64, init: impl Fn() -> T) -> BTreeMap<i64, T> {
+    let now = Utc::now();
+    let first = bucket_start(now - Duration::hours(hours), bucket_seconds);
+    let last = bucket_start(now, bucket_seconds);
+    (0..=(last - first) / bucket_seconds)
+        .map(|i| (first + i * bucket_seconds, init()))
+        .collect()
+}
+
+fn bucket_time(start: i64) -> DateTime<Utc> {
+    Utc.timestamp_opt(start, 0).single().unwrap_or_else(Utc::now)
+}
+
+/// 按时间桶统计请求延迟
+pub async fn latency_series(monitor: &MonitoringSystem, hours: i64, bucket_seconds: i64) -> Value {
+    let bucket_seconds = bucket_seconds.max(1);
+    let mut buckets = empty_buckets(hours, bucket_seconds, Vec::<f64>::new);
+    for (timestamp, event) in monitor.get_events_since(Utc::now() - Duration::hours(hours)).await {
+        if let MonitoringEvent::RequestProcessed { duration_ms, .. } = event {
+            buckets.entry(bucket_start(timestamp, bucket_seconds)).or_default().push(duration_ms);
+        }
+    }
+
+    let series: Vec<LatencyBucket> = buckets
+        .into_iter()
+        .map(|(start, mut durations)| {
+            durations.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let count = durations.len();
+            let (avg_ms, p95_ms, max_ms) = if count == 0 {
+                (0.0, 0.0, 0.0)
+            } else {
+                let p95_index = ((count as f64 * 0.95).ceil() as usize).clamp(1, count) - 1;
+                (durations.iter().sum::<f64>() / count as f64, durations[p95_index], durations[count - 1])
+            };
+            LatencyBucket { bucket_start: bucket_time(start), count, avg_ms, p95_ms, max_ms }
+        })
+        .collect();
+
+    serde_json::to_value(series).unwrap_or(Value::Null)
+}
+
+/// 按时间桶统计错误率
+pub async fn error_rates(monitor: &MonitoringSystem, hours: i64, bucket_seconds: i64) -> Value {
+    let bucket_seconds = bucket_seconds.max(1);
+    let mut buckets = empty_buckets(hours, bucket_seconds, || (0usize, 0usize, 0usize, 0usize));
+    for (timestamp, event) in monitor.get_events_since(Utc::now() - Duration::hours(hours)).await {
+        let bucket = buckets.entry(bucket_start(timestamp, bucket_seconds)).or_default();
+        match event {
+            MonitoringEvent::RequestProcessed { .. } => bucket.0 += 1,
+            MonitoringEvent::RequestFailed { .. } => {
+                bucket.0 += 1;
+                bucket.1 += 1;
+            }
+            MonitoringEvent::RateLimitTriggered { .. } => bucket.2 += 1,
+            MonitoringEvent::PerformanceAlert { .. } => bucket.3 += 1,
+            _ => {}
+        }
+    }
+
+    let series: Vec<ErrorRateBucket> = buckets
+        .into_iter()
+        .map(|(start, (requests, errors, rate_limited, alerts))| ErrorRateBucket {
+            bucket_start: bucket_time(start),
+            requests,
+            errors,
+            rate_limited,
+            alerts,
+            error_rate: if requests == 0 { 0.0 } else { errors as f64 / requests as f64 },
+        })
+        .collect();
+
+    serde_json::to_value(series).unwrap_or(Value::Null)
+}
+
+/// 按上下文加载次数排序的领域
+pub async fn top_domains(monitor: &MonitoringSystem, hours: i64, limit: usize) -> Value {
+    let mut domains: HashMap<String, (usize, f64)> = HashMap::new();
+    for (_, event) in monitor.get_events_since(Utc::now() - Duration::hours(hours)).await {
+        if let MonitoringEvent::ContextLoaded { domain, duration_ms } = event {
+            let entry = domains.entry(domain).or_default();
+            entry.0 += 1;
+            entry.1 += duration_ms;
+        }
+    }
+
+    let mut stats: Vec<DomainStat> = domains
+        .into_iter()
+        .map(|(domain, (load_count, total_ms))| DomainStat {
+            domain,
+            load_count,
+            avg_load_ms: total_ms / load_count as f64,
+        })
+        .collect();
+    stats.sort_by(|a, b| b.load_count.cmp(&a.load_count).then_with(|| a.domain.cmp(&b.domain)));
+    stats.truncate(limit);
+
+    serde_json::to_value(stats).unwrap_or(Value::Null)
+}
+
+/// 按键类型统计缓存命中情况
+pub async fn cache_stats(monitor: &MonitoringSystem, hours: i64) -> Value {
+    let mut key_types: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for (_, event) in monitor.get_events_since(Utc::now() - Duration::hours(hours)).await {
+        if let MonitoringEvent::CacheAccess { hit, key_type } = event {
+            let entry = key_types.entry(key_type).or_default();
+            if hit {
+                entry.0 += 1;
+            } else {
+                entry.1 += 1;
+            }
+        }
+    }
+
+    let stats: Vec<CacheAccessStat> = key_types
+        .into_iter()
+        .map(|(key_type, (hits, misses))| CacheAccessStat {
+            key_type,
+            hits,
+            misses,
+            hit_rate: hits as f64 / (hits + misses) as f64,
+        })
+        .collect();
+
+    serde_json::to_value(stats).unwrap_or(Value::Null)
+}
+
+/// 仪表盘总览 - 组合所有聚合视图
+pub async fn dashboard(monitor: &MonitoringSystem, hours: i64, bucket_seconds: i64) -> Value {
+    serde_json::json!({
+        "generated_at": Utc::now(),
+        "window_hours": hours,
+        "bucket_seconds": bucket_seconds,
+        "latency": latency_series(monitor, hours, bucket_seconds).await,
+        "error_rates": error_rates(monitor, hours, bucket_seconds).await,
+        "top_domains": top_domains(monitor, hours, 10).await,
+        "cache": cache_stats(monitor, hours).await,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dashboard_views() {
+        let monitor = MonitoringSystem::new();
+        for duration_ms in [100.0, 200.0, 300.0] {
+            monitor.log_event(MonitoringEvent::RequestProcessed {
+                user_id: "user1".to_string(),
+                session_id: "session1".to_string(),
+                duration_ms,
+            }).await;
+        }
+        monitor.log_event(MonitoringEvent::RateLimitTriggered { user_id: "user1".to_string(), limit: 3 }).await;
+        monitor.log_event(MonitoringEvent::ContextLoaded { domain: "medical".to_string(), duration_ms: 10.0 }).await;
+        monitor.log_event(MonitoringEvent::ContextLoaded { domain: "medical".to_string(), duration_ms: 30.0 }).await;
+        monitor.log_event(MonitoringEvent::ContextLoaded { domain: "legal".to_string(), duration_ms: 5.0 }).await;
+        monitor.log_event(MonitoringEvent::CacheAccess { hit: true, key_type: "query".to_string() }).await;
+        monitor.log_event(MonitoringEvent::CacheAccess { hit: false, key_type: "query".to_string() }).await;
+
+        let dashboard = dashboard(&monitor, 1, 3600).await;
+
+        let latency = dashboard["latency"].as_array().unwrap();
+        let current = latency.last().unwrap();
+        assert_eq!(current["count"], 3);
+        assert_eq!(current["avg_ms"], 200.0);
+        assert_eq!(current["max_ms"], 300.0);
+
+        let errors = dashboard["error_rates"].as_array().unwrap().last().unwrap().clone();
+        assert_eq!(errors["requests"], 3);
+        assert_eq!(errors["rate_limited"], 1);
+
+        let domains = dashboard["top_domains"].as_array().unwrap();
+        assert_eq!(domains[0]["domain"], "medical");
+        assert_eq!(domains[0]["avg_load_ms"], 20.0);
+
+        assert_eq!(dashboard["cache"][0]["hit_rate"], 0.5);
+    }
+}
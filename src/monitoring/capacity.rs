@@ -0,0 +1,238 @@
+//! 容量规划 - 基于已记录的监控数据模拟目标负载下的并发、内存、缓存和费用需求
+
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use crate::context::llm_context::LLMContext;
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem};
+
+/// 每个上下文在内存中的固定开销估算（结构体、索引和哈希表项）
+const CONTEXT_OVERHEAD_BYTES: f64 = 512.0;
+
+/// 平均每个token对应的字节数估算
+const BYTES_PER_TOKEN: f64 = 4.0;
+
+/// 按30天计算月度用量
+const SECONDS_PER_MONTH: f64 = 30.0 * 24.0 * 3600.0;
+
+/// 模型计价（每千token）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub model: String,
+    pub input_per_1k_tokens: f64,   // 输入token单价
+    pub output_per_1k_tokens: f64,  // 输出token单价
+}
+
+/// 容量规划输入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityPlanInput {
+    pub target_qps: f64,                    // 目标每秒请求数
+    pub avg_contexts_per_request: f64,      // 每个请求平均选择的上下文数
+    pub avg_context_bytes: f64,             // 上下文平均大小（字节）
+    pub stored_contexts: u64,               // 预期常驻的上下文总数
+    pub prompt_tokens: u64,                 // 每个请求除上下文外的提示词token数
+    pub output_tokens: u64,                 // 每个请求的平均输出token数
+    pub pricing: ModelPricing,
+    pub cache_ttl_seconds: u64,             // 选择结果缓存的TTL
+    pub headroom: f64,                      // 并发余量系数（如1.3表示预留30%）
+    pub default_latency_ms: f64,            // 没有监控数据时使用的请求延迟
+    pub observation_hours: i64,             // 读取监控数据的时间窗口
+}
+
+impl CapacityPlanInput {
+    /// 使用默认假设创建规划输入
+    pub fn new(target_qps: f64, avg_contexts_per_request: f64, pricing: ModelPricing) -> Self {
+        Self {
+            target_qps,
+            avg_contexts_per_request,
+            avg_context_bytes: 2048.0,
+            stored_contexts: 10_000,
+            prompt_tokens: 200,
+            output_tokens: 300,
+            pricing,
+            cache_ttl_seconds: 300,
+            headroom: 1.3,
+            default_latency_ms: 500.0,
+            observation_hours: 24,
+        }
+    }
+
+    /// 按现有上下文样本估算平均大小
+    pub fn with_context_sample(mut self, contexts: &[LLMContext]) -> Self {
+        if !contexts.is_empty() {
+            let total: usize = contexts.iter().map(|ctx| ctx.context_data.len()).sum();
+            self.avg_context_bytes = total as f64 / contexts.len() as f64;
+        }
+        self
+    }
+}
+
+/// 从监控数据中观测到的负载特征
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ObservedProfile {
+    pub request_samples: usize,     // 观测到的完成请求数
+    pub avg_latency_ms: f64,
+    pub p95_latency_ms: f64,
+    pub avg_selection_ms: f64,      // 平均上下文选择耗时
+    pub avg_selected_contexts: f64, // 平均选中的上下文数
+    pub cache_hit_rate: f64,        // 缓存命中率（无访问时为0）
+    pub error_rate: f64,
+}
+
+/// 容量规划报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CapacityReport {
+    pub generated_at: chrono::DateTime<Utc>,
+    pub input: CapacityPlanInput,
+    pub observed: ObservedProfile,
+    pub latency_basis_ms: f64,              // 计算并发时使用的延迟（P95，无数据时取默认值）
+    pub required_concurrency: u64,          // 建议的最大并发请求数
+    pub in_flight_context_bytes: u64,       // 并发请求同时持有的上下文内存
+    pub stored_context_bytes: u64,          // 常驻上下文内存
+    pub cache_entries: u64,                 // 缓存TTL内预计写入的缓存项数
+    pub cache_bytes: u64,                   // 缓存预计占用内存
+    pub monthly_requests: u64,
+    pub monthly_input_tokens: u64,
+    pub monthly_output_tokens: u64,
+    pub monthly_cost: f64,                  // 月度token费用
+}
+
+/// 从监控事件中提取负载特征
+pub async fn observe_profile(monitor: &MonitoringSystem, hours: i64) -> ObservedProfile {
+    let mut latencies = Vec::new();
+    let mut failures = 0usize;
+    let mut selections = (0usize, 0.0f64, 0usize);
+    let mut cache = (0usize, 0usize);
+
+    for (_, event) in monitor.get_events_since(Utc::now() - Duration::hours(hours)).await {
+        match event {
+            MonitoringEvent::RequestProcessed { duration_ms, .. } => latencies.push(duration_ms),
+            MonitoringEvent::RequestFailed { duration_ms, .. } => {
+                latencies.push(duration_ms);
+                failures += 1;
+            }
+            MonitoringEvent::ContextSelected { selected_count, duration_ms, .. } => {
+                selections.0 += 1;
+                selections.1 += duration_ms;
+                selections.2 += selected_count;
+            }
+            MonitoringEvent::CacheAccess { hit, .. } => {
+                if hit {
+                    cache.0 += 1;
+                } else {
+                    cache.1 += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut profile = ObservedProfile {
+        request_samples: latencies.len(),
+        ..ObservedProfile::default()
+    };
+    if !latencies.is_empty() {
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let count = latencies.len();
+        let p95_index = ((count as f64 * 0.95).ceil() as usize).clamp(1, count) - 1;
+        profile.avg_latency_ms = latencies.iter().sum::<f64>() / count as f64;
+        profile.p95_latency_ms = latencies[p95_index];
+        profile.error_rate = failures as f64 / count as f64;
+    }
+    if selections.0 > 0 {
+        profile.avg_selection_ms = selections.1 / selections.0 as f64;
+        profile.avg_selected_contexts = selections.2 as f64 / selections.0 as f64;
+    }
+    if cache.0 + cache.1 > 0 {
+        profile.cache_hit_rate = cache.0 as f64 / (cache.0 + cache.1) as f64;
+    }
+    profile
+}
+
+/// 根据目标负载和观测数据生成容量规划报告
+pub fn project(input: &CapacityPlanInput, observed: &ObservedProfile) -> CapacityReport {
+    let latency_basis_ms = if observed.request_samples > 0 {
+        observed.p95_latency_ms
+    } else {
+        input.default_latency_ms
+    };
+
+    // 利特尔法则：并发数 = 到达率 × 停留时间
+    let required_concurrency = (input.target_qps * latency_basis_ms / 1000.0 * input.headroom).ceil().max(1.0) as u64;
+
+    let context_bytes = input.avg_context_bytes + CONTEXT_OVERHEAD_BYTES;
+    let in_flight_context_bytes = (required_concurrency as f64 * input.avg_contexts_per_request * context_bytes) as u64;
+    let stored_context_bytes = (input.stored_contexts as f64 * context_bytes) as u64;
+
+    // 每次缓存未命中写入一项，TTL内累积的项数即缓存规模
+    let cache_entries = (input.target_qps * (1.0 - observed.cache_hit_rate) * input.cache_ttl_seconds as f64).ceil() as u64;
+    let cache_bytes = (cache_entries as f64 * input.avg_contexts_per_request * context_bytes) as u64;
+
+    let monthly_requests = (input.target_qps * SECONDS_PER_MONTH) as u64;
+    let context_tokens = input.avg_contexts_per_request * input.avg_context_bytes / BYTES_PER_TOKEN;
+    let monthly_input_tokens = (monthly_requests as f64 * (context_tokens + input.prompt_tokens as f64)) as u64;
+    let monthly_output_tokens = monthly_requests * input.output_tokens;
+    let monthly_cost = monthly_input_tokens as f64 / 1000.0 * input.pricing.input_per_1k_tokens
+        + monthly_output_tokens as f64 / 1000.0 * input.pricing.output_per_1k_tokens;
+
+    CapacityReport {
+        generated_at: Utc::now(),
+        input: input.clone(),
+        observed: observed.clone(),
+        latency_basis_ms,
+        required_concurrency,
+        in_flight_context_bytes,
+        stored_context_bytes,
+        cache_entries,
+        cache_bytes,
+        monthly_requests,
+        monthly_input_tokens,
+        monthly_output_tokens,
+        monthly_cost,
+    }
+}
+
+/// 读取监控数据并生成容量规划报告
+pub async fn plan_capacity(monitor: &MonitoringSystem, input: &CapacityPlanInput) -> CapacityReport {
+    let observed = observe_profile(monitor, input.observation_hours).await;
+    project(input, &observed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_capacity_plan() {
+        let monitor = MonitoringSystem::new();
+        for duration_ms in [100.0, 200.0, 300.0, 400.0] {
+            monitor
+                .log_event(MonitoringEvent::RequestProcessed {
+                    user_id: "u1".to_string(),
+                    session_id: "s1".to_string(),
+                    duration_ms,
+                })
+                .await;
+        }
+        monitor.log_event(MonitoringEvent::CacheAccess { hit: true, key_type: "selection".to_string() }).await;
+        monitor.log_event(MonitoringEvent::CacheAccess { hit: false, key_type: "selection".to_string() }).await;
+
+        let pricing = ModelPricing {
+            model: "test-model".to_string(),
+            input_per_1k_tokens: 0.001,
+            output_per_1k_tokens: 0.002,
+        };
+        let mut input = CapacityPlanInput::new(100.0, 3.0, pricing);
+        input.avg_context_bytes = 400.0;
+        input.headroom = 1.0;
+        let report = plan_capacity(&monitor, &input).await;
+
+        assert_eq!(report.observed.request_samples, 4);
+        assert_eq!(report.latency_basis_ms, 400.0);
+        assert_eq!(report.required_concurrency, 40);
+        assert_eq!(report.cache_entries, 15_000);
+        assert_eq!(report.monthly_requests, 259_200_000);
+        // 每请求输入token：3 × 100 + 200 = 500
+        assert_eq!(report.monthly_input_tokens, 259_200_000 * 500);
+        assert!((report.monthly_cost - (259_200_000.0 * 0.5 * 0.001 + 259_200_000.0 * 0.3 * 0.002)).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,462 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, timeout_at, Duration, Instant};
+use crate::monitoring::metrics::{MetricsRegistry, MetricsSnapshot};
+use crate::monitoring::monitoring::MonitoringEvent;
+
+/// 重试等待时间上限
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(30);
+
+/// 默认导出表
+pub const DEFAULT_EXPORT_TABLE: &str = "penlai_events";
+
+/// 校验表名：字母或下划线开头，只含字母、数字和下划线，可带一级库名/模式名前缀（如analytics.events）
+fn validate_table_name(table: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let valid_part = |part: &str| {
+        let mut chars = part.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+    let parts: Vec<&str> = table.split('.').collect();
+    if parts.len() > 2 || !parts.iter().all(|part| valid_part(part)) {
+        return Err(format!("Invalid table name '{}'", table).into());
+    }
+    Ok(())
+}
+
+/// 导出到分析数据库的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportRecord {
+    pub timestamp: DateTime<Utc>,
+    pub record_type: String,        // "event" 或 "metric"
+    pub name: String,               // 事件种类或指标名
+    pub value: Option<f64>,         // 指标值（事件为None）
+    pub payload: serde_json::Value, // 完整的事件或指标内容
+}
+
+impl ExportRecord {
+    /// 由监控事件生成记录
+    pub fn from_event(timestamp: DateTime<Utc>, event: &MonitoringEvent) -> Self {
+        Self {
+            timestamp,
+            record_type: "event".to_string(),
+            name: format!("{:?}", event.kind()),
+            value: None,
+            payload: serde_json::to_value(event).unwrap_or(serde_json::Value::Null),
+        }
+    }
+
+    /// 由指标快照生成记录（计数器、仪表各一条，直方图记录均值）
+    pub fn from_snapshot(snapshot: &MetricsSnapshot) -> Vec<Self> {
+        let counters = snapshot.counters.iter().map(|(name, value)| (name, *value as f64, serde_json::json!(value)));
+        let gauges = snapshot.gauges.iter().map(|(name, value)| (name, *value, serde_json::json!(value)));
+        let histograms = snapshot
+            .histograms
+            .iter()
+            .map(|(name, histogram)| (name, histogram.mean, serde_json::to_value(histogram).unwrap_or_default()));
+
+        counters
+            .chain(gauges)
+            .chain(histograms)
+            .map(|(name, value, payload)| Self {
+                timestamp: snapshot.taken_at,
+                record_type: "metric".to_string(),
+                name: name.clone(),
+                value: Some(value),
+                payload,
+            })
+            .collect()
+    }
+}
+
+/// 分析数据库写入接口 - ClickHouse、Postgres等后端实现该接口
+#[async_trait]
+pub trait AnalyticsSink: Send + Sync {
+    /// 写入一批记录，失败时由导出器重试
+    async fn write_batch(&self, records: &[ExportRecord]) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 后端名称
+    fn name(&self) -> &str;
+}
+
+/// 通过HTTP接口写入ClickHouse（JSONEachRow格式）
+pub struct ClickHouseSink {
+    client: reqwest::Client,
+    endpoint: String,                   // 如 http://localhost:8123
+    table: String,                      // 目标表
+    credentials: Option<(String, String)>,
+}
+
+impl ClickHouseSink {
+    /// 创建ClickHouse写入器；表名会拼接进INSERT语句，不是合法标识符时返回错误
+    pub fn new(endpoint: &str, table: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        validate_table_name(table)?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            table: table.to_string(),
+            credentials: None,
+        })
+    }
+
+    /// 设置认证信息
+    pub fn with_credentials(mut self, user: &str, password: &str) -> Self {
+        self.credentials = Some((user.to_string(), password.to_string()));
+        self
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for ClickHouseSink {
+    async fn write_batch(&self, records: &[ExportRecord]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut body = String::new();
+        for record in records {
+            body.push_str(&serde_json::to_string(record)?);
+            body.push('\n');
+        }
+
+        let query = format!("INSERT INTO {} FORMAT JSONEachRow", self.table);
+        let mut request = self.client.post(&self.endpoint).query(&[("query", query)]).body(body);
+        if let Some((ref user, ref password)) = self.credentials {
+            request = request.header("X-ClickHouse-User", user).header("X-ClickHouse-Key", password);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("ClickHouse insert failed ({}): {}", status, text).into());
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "clickhouse"
+    }
+}
+
+/// 通过PostgREST接口写入Postgres表（列：timestamp、record_type、name、value、payload，payload为jsonb）
+pub struct PostgresSink {
+    client: reqwest::Client,
+    endpoint: String,                   // PostgREST地址，如 http://localhost:3000
+    table: String,                      // 目标表
+    token: Option<String>,              // PostgREST的JWT
+}
+
+impl PostgresSink {
+    /// 创建Postgres写入器；表名会拼接进请求路径，不是合法标识符时返回错误
+    pub fn new(endpoint: &str, table: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        validate_table_name(table)?;
+        Ok(Self {
+            client: reqwest::Client::new(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            table: table.to_string(),
+            token: None,
+        })
+    }
+
+    /// 设置认证令牌
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl AnalyticsSink for PostgresSink {
+    async fn write_batch(&self, records: &[ExportRecord]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let mut request = self
+            .client
+            .post(format!("{}/{}", self.endpoint, self.table))
+            .header("Prefer", "return=minimal")
+            .json(records);
+        if let Some(ref token) = self.token {
+            request = request.bearer_auth(token);
+        }
+
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(format!("Postgres insert failed ({}): {}", status, text).into());
+        }
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "postgres"
+    }
+}
+
+/// 按环境变量创建写入器：PENLAI_EXPORT_SINK为clickhouse或postgres，PENLAI_EXPORT_URL为服务地址，
+/// PENLAI_EXPORT_TABLE为目标表（默认penlai_events）；ClickHouse使用PENLAI_EXPORT_USER/PENLAI_EXPORT_PASSWORD认证，
+/// Postgres使用PENLAI_EXPORT_TOKEN。未配置时返回None
+pub fn analytics_sink_from_env() -> Option<Result<Arc<dyn AnalyticsSink>, Box<dyn std::error::Error + Send + Sync>>> {
+    let kind = std::env::var("PENLAI_EXPORT_SINK").ok()?;
+    Some(analytics_sink(&kind))
+}
+
+fn analytics_sink(kind: &str) -> Result<Arc<dyn AnalyticsSink>, Box<dyn std::error::Error + Send + Sync>> {
+    let endpoint = std::env::var("PENLAI_EXPORT_URL").map_err(|_| "PENLAI_EXPORT_URL is not set")?;
+    let table = std::env::var("PENLAI_EXPORT_TABLE").unwrap_or_else(|_| DEFAULT_EXPORT_TABLE.to_string());
+    let sink: Arc<dyn AnalyticsSink> = match kind {
+        "clickhouse" => {
+            let mut sink = ClickHouseSink::new(&endpoint, &table)?;
+            if let (Ok(user), Ok(password)) = (std::env::var("PENLAI_EXPORT_USER"), std::env::var("PENLAI_EXPORT_PASSWORD")) {
+                sink = sink.with_credentials(&user, &password);
+            }
+            Arc::new(sink)
+        }
+        "postgres" => {
+            let mut sink = PostgresSink::new(&endpoint, &table)?;
+            if let Ok(token) = std::env::var("PENLAI_EXPORT_TOKEN") {
+                sink = sink.with_token(&token);
+            }
+            Arc::new(sink)
+        }
+        other => return Err(format!("Unknown PENLAI_EXPORT_SINK '{}'", other).into()),
+    };
+    Ok(sink)
+}
+
+/// 导出配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportConfig {
+    pub batch_size: usize,              // 每批最大记录数
+    pub flush_interval_ms: u64,         // 未满批时的最长等待时间（毫秒）
+    pub queue_capacity: usize,          // 待导出队列容量，满时产生背压
+    pub max_retries: u32,               // 单批最大重试次数
+    pub retry_backoff_ms: u64,          // 首次重试等待时间，之后指数增长
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            flush_interval_ms: 10_000,
+            queue_capacity: 10_000,
+            max_retries: 3,
+            retry_backoff_ms: 200,
+        }
+    }
+}
+
+/// 导出统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportStats {
+    pub exported: u64,          // 成功写入的记录数
+    pub dropped: u64,           // 因队列已满被丢弃的记录数
+    pub failed: u64,            // 重试耗尽后放弃的记录数
+    pub retries: u64,           // 重试次数
+    pub batches: u64,           // 成功写入的批次数
+}
+
+#[derive(Default)]
+struct ExportCounters {
+    exported: AtomicU64,
+    dropped: AtomicU64,
+    failed: AtomicU64,
+    retries: AtomicU64,
+    batches: AtomicU64,
+    registry: Option<Arc<MetricsRegistry>>,     // 丢弃和放弃的记录数同时计入该注册表
+}
+
+impl ExportCounters {
+    fn record_dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+        if let Some(ref registry) = self.registry {
+            registry.counter("export_records_dropped").add(count);
+        }
+    }
+
+    fn record_failed(&self, count: u64) {
+        self.failed.fetch_add(count, Ordering::Relaxed);
+        if let Some(ref registry) = self.registry {
+            registry.counter("export_records_failed").add(count);
+        }
+    }
+}
+
+/// 监控数据导出器 - 按批次和时间间隔将记录写入分析数据库，带重试和有界队列
+pub struct EventExporter {
+    config: ExportConfig,
+    sender: mpsc::Sender<ExportRecord>,
+    receiver: Mutex<Option<mpsc::Receiver<ExportRecord>>>,
+    counters: Arc<ExportCounters>,
+}
+
+impl EventExporter {
+    /// 创建导出器（需调用`start`启动后台写入任务）
+    pub fn new(config: ExportConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_capacity.max(1));
+        Self {
+            config,
+            sender,
+            receiver: Mutex::new(Some(receiver)),
+            counters: Arc::new(ExportCounters::default()),
+        }
+    }
+
+    /// 关联指标注册表，丢弃和放弃的记录数记为export_records_dropped/export_records_failed计数器
+    pub fn with_registry(mut self, registry: Arc<MetricsRegistry>) -> Self {
+        self.counters = Arc::new(ExportCounters { registry: Some(registry), ..Default::default() });
+        self
+    }
+
+    /// 提交记录，队列已满时等待（背压）
+    pub async fn export(&self, record: ExportRecord) {
+        if self.sender.send(record).await.is_err() {
+            self.counters.record_dropped(1);
+        }
+    }
+
+    /// 尝试提交记录，适用于不能阻塞的热路径；队列已满时丢弃，丢弃数计入统计和指标注册表
+    pub fn try_export(&self, record: ExportRecord) -> bool {
+        match self.sender.try_send(record) {
+            Ok(()) => true,
+            Err(_) => {
+                self.counters.record_dropped(1);
+                false
+            }
+        }
+    }
+
+    /// 启动后台写入任务；重复调用返回None
+    pub async fn start(&self, sink: Arc<dyn AnalyticsSink>) -> Option<JoinHandle<()>> {
+        let mut receiver = self.receiver.lock().await.take()?;
+        let config = self.config.clone();
+        let counters = self.counters.clone();
+
+        Some(tokio::spawn(async move {
+            let flush_interval = Duration::from_millis(config.flush_interval_ms);
+            let mut batch = Vec::with_capacity(config.batch_size);
+            loop {
+                // 收集一批记录：达到批大小或超过刷新间隔即写入
+                let deadline = Instant::now() + flush_interval;
+                let mut closed = false;
+                while batch.len() < config.batch_size {
+                    match timeout_at(deadline, receiver.recv()).await {
+                        Ok(Some(record)) => batch.push(record),
+                        Ok(None) => {
+                            closed = true;
+                            break;
+                        }
+                        Err(_) => break,
+                    }
+                }
+
+                if !batch.is_empty() {
+                    Self::write_with_retry(sink.as_ref(), &batch, &config, &counters).await;
+                    batch.clear();
+                }
+                if closed {
+                    break;
+                }
+            }
+        }))
+    }
+
+    /// 带指数退避重试的批量写入
+    async fn write_with_retry(sink: &dyn AnalyticsSink, batch: &[ExportRecord], config: &ExportConfig, counters: &ExportCounters) {
+        let mut backoff = Duration::from_millis(config.retry_backoff_ms).min(MAX_RETRY_BACKOFF);
+        for attempt in 0..=config.max_retries {
+            match sink.write_batch(batch).await {
+                Ok(()) => {
+                    counters.exported.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                    counters.batches.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Err(e) if attempt < config.max_retries => {
+                    log::warn!("Export to {} failed (attempt {}): {}", sink.name(), attempt + 1, e);
+                    counters.retries.fetch_add(1, Ordering::Relaxed);
+                    sleep(backoff).await;
+                    backoff = backoff.saturating_mul(2).min(MAX_RETRY_BACKOFF);
+                }
+                Err(e) => {
+                    log::error!("Export to {} failed, dropping {} records: {}", sink.name(), batch.len(), e);
+                    counters.record_failed(batch.len() as u64);
+                }
+            }
+        }
+    }
+
+    /// 获取导出统计
+    pub fn get_stats(&self) -> ExportStats {
+        ExportStats {
+            exported: self.counters.exported.load(Ordering::Relaxed),
+            dropped: self.counters.dropped.load(Ordering::Relaxed),
+            failed: self.counters.failed.load(Ordering::Relaxed),
+            retries: self.counters.retries.load(Ordering::Relaxed),
+            batches: self.counters.batches.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 前若干次写入失败的内存写入器
+    struct FlakySink {
+        failures_left: AtomicU64,
+        written: Mutex<Vec<usize>>,
+    }
+
+    #[async_trait]
+    impl AnalyticsSink for FlakySink {
+        async fn write_batch(&self, records: &[ExportRecord]) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            if self.failures_left.load(Ordering::Relaxed) > 0 {
+                self.failures_left.fetch_sub(1, Ordering::Relaxed);
+                return Err("connection refused".into());
+            }
+            self.written.lock().await.push(records.len());
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "memory"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_batching_and_retry() {
+        assert!(ClickHouseSink::new("http://localhost:8123", "analytics.penlai_events").is_ok());
+        assert!(ClickHouseSink::new("http://localhost:8123", "events; DROP TABLE users").is_err());
+        assert!(PostgresSink::new("http://localhost:3000", "../rpc/admin").is_err());
+
+        let registry = Arc::new(MetricsRegistry::new());
+        let exporter = EventExporter::new(ExportConfig {
+            batch_size: 3,
+            flush_interval_ms: 20,
+            queue_capacity: 5,
+            max_retries: 2,
+            retry_backoff_ms: 1,
+        })
+        .with_registry(registry.clone());
+        let sink = Arc::new(FlakySink { failures_left: AtomicU64::new(1), written: Mutex::new(Vec::new()) });
+
+        // 未启动时队列满后丢弃
+        let event = MonitoringEvent::CacheAccess { hit: true, key_type: "query".to_string() };
+        for _ in 0..6 {
+            exporter.try_export(ExportRecord::from_event(Utc::now(), &event));
+        }
+        assert_eq!(exporter.get_stats().dropped, 1);
+        assert_eq!(registry.counter("export_records_dropped").get(), 1);
+
+        let handle = exporter.start(sink.clone()).await.unwrap();
+        assert!(exporter.start(sink.clone()).await.is_none());
+        sleep(Duration::from_millis(200)).await;
+
+        let stats = exporter.get_stats();
+        assert_eq!(stats.exported, 5);
+        assert_eq!(stats.retries, 1);
+        assert_eq!(stats.failed, 0);
+        assert_eq!(*sink.written.lock().await, vec![3, 2]);
+        handle.abort();
+    }
+}
@@ -0,0 +1,274 @@
+//! 原子指标类型 - 计数器、仪表和直方图的更新只使用原子操作，由监控系统定期快照
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 单调递增计数器
+#[derive(Debug, Default)]
+pub struct Counter {
+    value: AtomicU64,
+}
+
+impl Counter {
+    /// 计数加一
+    pub fn inc(&self) {
+        self.add(1);
+    }
+
+    /// 计数增加指定值
+    pub fn add(&self, n: u64) {
+        self.value.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// 当前计数
+    pub fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+/// 可增减的仪表（以f64位模式原子存储）
+#[derive(Debug)]
+pub struct Gauge {
+    bits: AtomicU64,
+}
+
+impl Default for Gauge {
+    fn default() -> Self {
+        Self { bits: AtomicU64::new(0f64.to_bits()) }
+    }
+}
+
+impl Gauge {
+    /// 设置当前值
+    pub fn set(&self, value: f64) {
+        self.bits.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// 增加指定值（可为负数）
+    pub fn add(&self, delta: f64) {
+        let _ = self.bits.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + delta).to_bits())
+        });
+    }
+
+    /// 当前值
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.bits.load(Ordering::Relaxed))
+    }
+}
+
+/// 默认直方图桶边界（毫秒），适用于延迟类指标
+pub const DEFAULT_LATENCY_BUCKETS: [f64; 12] = [
+    1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0, 2500.0, 5000.0, 10000.0,
+];
+
+/// 固定桶直方图
+#[derive(Debug)]
+pub struct Histogram {
+    bounds: Vec<f64>,           // 各桶上边界（升序）
+    buckets: Vec<AtomicU64>,    // 各桶计数，最后一个为溢出桶
+    count: AtomicU64,
+    sum_bits: AtomicU64,
+}
+
+/// 直方图快照
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramSnapshot {
+    pub count: u64,
+    pub sum: f64,
+    pub mean: f64,
+    pub buckets: Vec<(f64, u64)>,   // (上边界, 该桶计数)，溢出桶上边界为无穷大
+}
+
+impl HistogramSnapshot {
+    /// 估算分位数（返回所在桶的上边界）
+    pub fn quantile(&self, q: f64) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let target = (self.count as f64 * q.clamp(0.0, 1.0)).ceil().max(1.0) as u64;
+        let mut cumulative = 0;
+        for (bound, count) in &self.buckets {
+            cumulative += count;
+            if cumulative >= target {
+                return *bound;
+            }
+        }
+        f64::INFINITY
+    }
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self::new(&DEFAULT_LATENCY_BUCKETS)
+    }
+}
+
+impl Histogram {
+    /// 使用指定桶边界创建直方图
+    pub fn new(bounds: &[f64]) -> Self {
+        let mut bounds = bounds.to_vec();
+        bounds.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        bounds.dedup();
+        Self {
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            bounds,
+            count: AtomicU64::new(0),
+            sum_bits: AtomicU64::new(0f64.to_bits()),
+        }
+    }
+
+    /// 记录一个观测值
+    pub fn observe(&self, value: f64) {
+        let index = self.bounds.partition_point(|bound| *bound < value);
+        self.buckets[index].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        let _ = self.sum_bits.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + value).to_bits())
+        });
+    }
+
+    /// 获取快照
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let count = self.count.load(Ordering::Relaxed);
+        let sum = f64::from_bits(self.sum_bits.load(Ordering::Relaxed));
+        let buckets = self
+            .bounds
+            .iter()
+            .copied()
+            .chain(std::iter::once(f64::INFINITY))
+            .zip(self.buckets.iter().map(|b| b.load(Ordering::Relaxed)))
+            .collect();
+        HistogramSnapshot {
+            count,
+            sum,
+            mean: if count == 0 { 0.0 } else { sum / count as f64 },
+            buckets,
+        }
+    }
+}
+
+/// 指标快照 - 某一时刻所有注册指标的值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub counters: HashMap<String, u64>,
+    pub gauges: HashMap<String, f64>,
+    pub histograms: HashMap<String, HistogramSnapshot>,
+}
+
+/// 指标注册表 - 按名称获取或创建指标，调用方可持有返回的Arc以跳过查找
+#[derive(Debug, Default)]
+pub struct MetricsRegistry {
+    counters: RwLock<HashMap<String, Arc<Counter>>>,
+    gauges: RwLock<HashMap<String, Arc<Gauge>>>,
+    histograms: RwLock<HashMap<String, Arc<Histogram>>>,
+}
+
+/// 从注册表中获取指标，不存在时创建
+fn get_or_create<T>(map: &RwLock<HashMap<String, Arc<T>>>, name: &str, create: impl FnOnce() -> T) -> Arc<T> {
+    if let Some(metric) = map.read().unwrap_or_else(PoisonError::into_inner).get(name) {
+        return metric.clone();
+    }
+    map.write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .entry(name.to_string())
+        .or_insert_with(|| Arc::new(create()))
+        .clone()
+}
+
+impl MetricsRegistry {
+    /// 创建空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 获取计数器
+    pub fn counter(&self, name: &str) -> Arc<Counter> {
+        get_or_create(&self.counters, name, Counter::default)
+    }
+
+    /// 获取仪表
+    pub fn gauge(&self, name: &str) -> Arc<Gauge> {
+        get_or_create(&self.gauges, name, Gauge::default)
+    }
+
+    /// 获取直方图（首次创建时使用默认延迟桶）
+    pub fn histogram(&self, name: &str) -> Arc<Histogram> {
+        get_or_create(&self.histograms, name, Histogram::default)
+    }
+
+    /// 获取指定桶边界的直方图（已存在时沿用原有边界）
+    pub fn histogram_with_buckets(&self, name: &str, bounds: &[f64]) -> Arc<Histogram> {
+        get_or_create(&self.histograms, name, || Histogram::new(bounds))
+    }
+
+    /// 获取所有指标的快照
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            taken_at: Utc::now(),
+            counters: self
+                .counters
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(name, counter)| (name.clone(), counter.get()))
+                .collect(),
+            gauges: self
+                .gauges
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(name, gauge)| (name.clone(), gauge.get()))
+                .collect(),
+            histograms: self
+                .histograms
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(name, histogram)| (name.clone(), histogram.snapshot()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_metric_updates() {
+        let registry = Arc::new(MetricsRegistry::new());
+
+        let mut handles = Vec::new();
+        for _ in 0..8 {
+            let registry = registry.clone();
+            handles.push(tokio::spawn(async move {
+                let requests = registry.counter("requests");
+                let latency = registry.histogram("latency_ms");
+                for i in 0..1000 {
+                    requests.inc();
+                    latency.observe((i % 100) as f64);
+                    registry.gauge("in_flight").add(1.0);
+                    registry.gauge("in_flight").add(-1.0);
+                }
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+
+        let snapshot = registry.snapshot();
+        assert_eq!(snapshot.counters["requests"], 8000);
+        assert_eq!(snapshot.gauges["in_flight"], 0.0);
+
+        let latency = &snapshot.histograms["latency_ms"];
+        assert_eq!(latency.count, 8000);
+        assert!((latency.mean - 49.5).abs() < 1e-9);
+        assert_eq!(latency.quantile(0.5), 50.0);
+        assert_eq!(latency.quantile(1.0), 100.0);
+    }
+}
@@ -1 +1,12 @@
-pub mod monitoring;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod monitoring;
+pub mod api;
+pub mod metrics;
+pub mod alerts;
+pub mod sampling;
+pub mod export;
+pub mod capacity;
+pub mod notify;
+pub mod reports;
+pub mod webhook;
+pub mod quota;
\ No newline at end of file
@@ -1,8 +1,16 @@
 use std::sync::Arc;
 use tokio::sync::RwLock;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use utoipa::ToSchema;
+use crate::monitoring::metrics::{MetricsRegistry, MetricsSnapshot};
+use crate::monitoring::alerts::AlertManager;
+use crate::monitoring::sampling::{EventSampler, EventSamplingConfig, SamplingStats};
+use crate::monitoring::export::{EventExporter, ExportRecord};
+use crate::monitoring::quota::QuotaKind;
+use crate::utils::search_budget::{BudgetScope, SearchBudget, SearchUsageSummary};
 
 /// 性能指标枚举
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,7 +33,234 @@ pub enum MonitoringEvent {
     CacheAccess { hit: bool, key_type: String },
     PerformanceAlert { metric: String, value: f64, threshold: f64 },
     RequestProcessed { user_id: String, session_id: String, duration_ms: f64 },
+    RequestFailed { user_id: String, session_id: String, error: String, duration_ms: f64 },
     RateLimitTriggered { user_id: String, limit: u32 },
+    RequestStageCompleted { request_id: Uuid, stage: RequestStage, duration_ms: f64, success: bool, detail: Option<String> },
+    TokensUsed { domain: String, tokens: u64 },
+    ContextCollected { context_id: Uuid, domain: String, reason: String, age_seconds: i64, size_bytes: usize },
+    QuotaWarning { kind: QuotaKind, scope: BudgetScope, key: String, used: u64, limit: u64, threshold_percent: u8 },
+}
+
+/// 请求处理阶段（按处理顺序排列）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ToSchema)]
+pub enum RequestStage {
+    Classification, // 领域分类
+    Validation,     // 速率限制、预算等前置检查
+    Selection,      // 上下文选择
+    Generation,     // 模型生成
+    Response,       // 响应返回（耗时为端到端总耗时）
+}
+
+/// 请求追踪时间线中的一项
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TraceEntry {
+    pub timestamp: DateTime<Utc>,   // 阶段完成时间
+    pub stage: RequestStage,
+    pub duration_ms: f64,
+    pub success: bool,
+    pub detail: Option<String>,     // 附加信息（如错误原因）
+}
+
+/// 单个请求的追踪时间线
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RequestTrace {
+    pub request_id: Uuid,
+    pub entries: Vec<TraceEntry>,           // 按阶段顺序排列
+    pub total_duration_ms: Option<f64>,     // 端到端耗时（记录了Response阶段时）
+    pub slowest_stage: Option<RequestStage>, // 耗时最长的阶段（不含Response）
+    pub failed_stage: Option<RequestStage>, // 第一个失败的阶段
+}
+
+/// 监控事件种类（用于事件查询过滤）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum EventKind {
+    ContextLoaded,
+    ContextSelected,
+    CacheAccess,
+    PerformanceAlert,
+    RequestProcessed,
+    RequestFailed,
+    RateLimitTriggered,
+    RequestStageCompleted,
+    TokensUsed,
+    ContextCollected,
+    QuotaWarning,
+}
+
+impl EventKind {
+    /// 全部事件种类（顺序与声明顺序一致，可按`kind as usize`索引）
+    pub const ALL: [EventKind; 11] = [
+        EventKind::ContextLoaded,
+        EventKind::ContextSelected,
+        EventKind::CacheAccess,
+        EventKind::PerformanceAlert,
+        EventKind::RequestProcessed,
+        EventKind::RequestFailed,
+        EventKind::RateLimitTriggered,
+        EventKind::RequestStageCompleted,
+        EventKind::TokensUsed,
+        EventKind::ContextCollected,
+        EventKind::QuotaWarning,
+    ];
+}
+
+impl MonitoringEvent {
+    /// 事件种类
+    pub fn kind(&self) -> EventKind {
+        match self {
+            MonitoringEvent::ContextLoaded { .. } => EventKind::ContextLoaded,
+            MonitoringEvent::ContextSelected { .. } => EventKind::ContextSelected,
+            MonitoringEvent::CacheAccess { .. } => EventKind::CacheAccess,
+            MonitoringEvent::PerformanceAlert { .. } => EventKind::PerformanceAlert,
+            MonitoringEvent::RequestProcessed { .. } => EventKind::RequestProcessed,
+            MonitoringEvent::RequestFailed { .. } => EventKind::RequestFailed,
+            MonitoringEvent::RateLimitTriggered { .. } => EventKind::RateLimitTriggered,
+            MonitoringEvent::RequestStageCompleted { .. } => EventKind::RequestStageCompleted,
+            MonitoringEvent::TokensUsed { .. } => EventKind::TokensUsed,
+            MonitoringEvent::ContextCollected { .. } => EventKind::ContextCollected,
+            MonitoringEvent::QuotaWarning { .. } => EventKind::QuotaWarning,
+        }
+    }
+
+    /// 事件关联的用户ID
+    pub fn user_id(&self) -> Option<&str> {
+        match self {
+            MonitoringEvent::RequestProcessed { user_id, .. } => Some(user_id),
+            MonitoringEvent::RequestFailed { user_id, .. } => Some(user_id),
+            MonitoringEvent::RateLimitTriggered { user_id, .. } => Some(user_id),
+            MonitoringEvent::QuotaWarning { scope: BudgetScope::User, key, .. } => Some(key),
+            _ => None,
+        }
+    }
+
+    /// 事件关联的会话ID
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            MonitoringEvent::RequestProcessed { session_id, .. } => Some(session_id),
+            MonitoringEvent::RequestFailed { session_id, .. } => Some(session_id),
+            _ => None,
+        }
+    }
+
+    /// 事件关联的领域
+    pub fn domain(&self) -> Option<&str> {
+        match self {
+            MonitoringEvent::ContextLoaded { domain, .. } => Some(domain),
+            MonitoringEvent::TokensUsed { domain, .. } => Some(domain),
+            MonitoringEvent::ContextCollected { domain, .. } => Some(domain),
+            MonitoringEvent::QuotaWarning { scope: BudgetScope::Domain, key, .. } => Some(key),
+            _ => None,
+        }
+    }
+}
+
+/// 事件查询过滤条件（所有条件同时满足）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EventFilter {
+    pub kinds: Vec<EventKind>,              // 事件种类（为空表示不限）
+    pub user_id: Option<String>,            // 用户ID
+    pub session_id: Option<String>,         // 会话ID
+    pub domain: Option<String>,             // 领域
+    pub start_time: Option<DateTime<Utc>>,  // 起始时间（包含）
+    pub end_time: Option<DateTime<Utc>>,    // 结束时间（不包含）
+    pub offset: usize,                      // 分页偏移
+    pub limit: Option<usize>,               // 每页数量（None表示不限）
+}
+
+impl EventFilter {
+    /// 检查事件是否满足过滤条件
+    pub fn matches(&self, timestamp: &DateTime<Utc>, event: &MonitoringEvent) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&event.kind()))
+            && self.user_id.as_deref().map(|u| event.user_id() == Some(u)).unwrap_or(true)
+            && self.session_id.as_deref().map(|s| event.session_id() == Some(s)).unwrap_or(true)
+            && self.domain.as_deref().map(|d| event.domain() == Some(d)).unwrap_or(true)
+            && self.start_time.map(|start| *timestamp >= start).unwrap_or(true)
+            && self.end_time.map(|end| *timestamp < end).unwrap_or(true)
+    }
+}
+
+/// 事件查询结果页
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPage {
+    pub events: Vec<(DateTime<Utc>, MonitoringEvent)>,  // 本页事件（按时间先后）
+    pub total: usize,                                   // 满足条件的事件总数
+    pub offset: usize,
+    pub has_more: bool,                                 // 是否还有下一页
+}
+
+/// 滚动窗口内的请求速率
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollingRates {
+    pub window_seconds: i64,        // 窗口长度（秒）
+    pub requests: usize,            // 窗口内完成的请求数（成功 + 失败）
+    pub errors: usize,              // 窗口内失败的请求数
+    pub error_rate: f64,            // 错误率
+    pub throughput: f64,            // 吞吐量（每秒请求数）
+}
+
+/// 派生指标使用的滚动窗口（1分钟、5分钟、15分钟）
+pub const ROLLING_WINDOWS_SECONDS: [i64; 3] = [60, 300, 900];
+
+/// 根据请求事件计算滚动窗口内的错误率和吞吐量
+fn compute_rolling_rates(events: &EventLog, now: DateTime<Utc>, window_seconds: i64) -> RollingRates {
+    let window_start = now - chrono::Duration::seconds(window_seconds);
+    let mut requests = 0;
+    let mut errors = 0;
+    // 事件按时间追加，从尾部向前扫描到窗口起点即可
+    for (timestamp, event) in events.iter().rev() {
+        if *timestamp < window_start {
+            break;
+        }
+        match event {
+            MonitoringEvent::RequestProcessed { .. } => requests += 1,
+            MonitoringEvent::RequestFailed { .. } => {
+                requests += 1;
+                errors += 1;
+            }
+            _ => {}
+        }
+    }
+
+    RollingRates {
+        window_seconds,
+        requests,
+        errors,
+        error_rate: if requests == 0 { 0.0 } else { errors as f64 / requests as f64 },
+        throughput: requests as f64 / window_seconds as f64,
+    }
+}
+
+/// 带时间戳的事件日志
+type EventLog = VecDeque<(DateTime<Utc>, MonitoringEvent)>;
+
+/// 事件日志保留的事件数上限（超出时淘汰最早的事件）
+const MAX_EVENT_LOG_ENTRIES: usize = 100_000;
+
+/// 请求追踪索引中保留的请求数上限（超出时淘汰最早的请求）
+const MAX_TRACED_REQUESTS: usize = 10_000;
+
+/// 按请求ID索引的阶段记录，避免查询追踪时扫描整个事件日志
+#[derive(Debug, Default)]
+struct TraceIndex {
+    entries: HashMap<Uuid, Vec<TraceEntry>>,
+    order: VecDeque<Uuid>,
+}
+
+impl TraceIndex {
+    fn insert(&mut self, request_id: Uuid, entry: TraceEntry) {
+        match self.entries.get_mut(&request_id) {
+            Some(entries) => entries.push(entry),
+            None => {
+                self.entries.insert(request_id, vec![entry]);
+                self.order.push_back(request_id);
+                while self.order.len() > MAX_TRACED_REQUESTS {
+                    if let Some(oldest) = self.order.pop_front() {
+                        self.entries.remove(&oldest);
+                    }
+                }
+            }
+        }
+    }
 }
 
 /// 企业级监控系统 - 实时监控大模型异步上下文管理系统的性能
@@ -34,10 +269,40 @@ pub struct MonitoringSystem {
     metrics: Arc<RwLock<HashMap<String, Vec<PerformanceMetric>>>>,
     
     /// 监控事件日志
-    event_log: Arc<RwLock<Vec<(DateTime<Utc>, MonitoringEvent)>>>,
+    event_log: Arc<RwLock<EventLog>>,
+
+    /// 按请求ID索引的阶段记录
+    traces: Arc<RwLock<TraceIndex>>,
     
     /// 配置阈值
     thresholds: Arc<RwLock<HashMap<String, f64>>>,
+
+    /// 原子指标注册表（计数器、仪表、直方图）
+    registry: Arc<MetricsRegistry>,
+
+    /// 原子指标的定期快照
+    snapshots: Arc<RwLock<VecDeque<MetricsSnapshot>>>,
+
+    /// 警报生命周期管理
+    alert_manager: Arc<AlertManager>,
+
+    /// 事件采样与标签基数控制
+    sampler: Arc<EventSampler>,
+
+    /// 可选的分析数据库导出器
+    exporter: Option<Arc<EventExporter>>,
+
+    /// 可选的搜索预算（用于在摘要中展示搜索用量）
+    search_budget: Option<Arc<SearchBudget>>,
+}
+
+/// 保留的指标快照数量上限
+const MAX_METRIC_SNAPSHOTS: usize = 1440;
+
+impl Default for MonitoringSystem {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MonitoringSystem {
@@ -52,9 +317,108 @@ impl MonitoringSystem {
         
         Self {
             metrics: Arc::new(RwLock::new(HashMap::new())),
-            event_log: Arc::new(RwLock::new(Vec::new())),
+            event_log: Arc::new(RwLock::new(VecDeque::new())),
+            traces: Arc::new(RwLock::new(TraceIndex::default())),
             thresholds: Arc::new(RwLock::new(thresholds)),
+            registry: Arc::new(MetricsRegistry::new()),
+            snapshots: Arc::new(RwLock::new(VecDeque::new())),
+            alert_manager: Arc::new(AlertManager::new()),
+            sampler: Arc::new(EventSampler::default()),
+            exporter: None,
+            search_budget: None,
+        }
+    }
+
+    /// 删除事件日志中关联到该用户的事件，返回删除数；dry_run时只统计
+    pub async fn purge_user_events(&self, user_id: &str, dry_run: bool) -> usize {
+        let mut event_log = self.event_log.write().await;
+        let before = event_log.len();
+        if dry_run {
+            return event_log.iter().filter(|(_, event)| event.user_id() == Some(user_id)).count();
+        }
+        event_log.retain(|(_, event)| event.user_id() != Some(user_id));
+        before - event_log.len()
+    }
+
+    /// 关联导出器，记录的事件和指标快照将批量导出到分析数据库
+    pub fn with_exporter(mut self, exporter: Arc<EventExporter>) -> Self {
+        self.exporter = Some(exporter);
+        self
+    }
+
+    /// 关联搜索预算，系统摘要将包含当日搜索用量
+    pub fn with_search_budget(mut self, search_budget: Arc<SearchBudget>) -> Self {
+        self.search_budget = Some(search_budget);
+        self
+    }
+
+    /// 更新事件采样配置
+    pub async fn update_sampling_config(&self, config: EventSamplingConfig) {
+        self.sampler.update_config(config);
+    }
+
+    /// 获取事件采样配置
+    pub async fn get_sampling_config(&self) -> EventSamplingConfig {
+        self.sampler.get_config()
+    }
+
+    /// 获取事件采样统计（丢弃数、标签溢出数等）
+    pub async fn get_sampling_stats(&self) -> SamplingStats {
+        self.sampler.get_stats()
+    }
+
+    /// 获取警报管理器，用于确认、指派和解决警报
+    pub fn get_alert_manager(&self) -> Arc<AlertManager> {
+        self.alert_manager.clone()
+    }
+
+    /// 获取原子指标注册表，热路径上应优先使用其中的计数器、仪表和直方图
+    pub fn registry(&self) -> Arc<MetricsRegistry> {
+        self.registry.clone()
+    }
+
+    /// 将原子指标的当前值快照写入存储
+    pub async fn take_snapshot(&self) -> MetricsSnapshot {
+        let snapshot = self.registry.snapshot();
+        if let Some(ref exporter) = self.exporter {
+            for record in ExportRecord::from_snapshot(&snapshot) {
+                exporter.try_export(record);
+            }
+        }
+        let mut snapshots = self.snapshots.write().await;
+        snapshots.push_back(snapshot.clone());
+        while snapshots.len() > MAX_METRIC_SNAPSHOTS {
+            snapshots.pop_front();
         }
+        snapshot
+    }
+
+    /// 启动后台任务，按固定间隔快照原子指标
+    pub fn start_snapshotting(self: Arc<Self>, interval: std::time::Duration) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                self.take_snapshot().await;
+            }
+        })
+    }
+
+    /// 获取最近一次指标快照
+    pub async fn get_latest_snapshot(&self) -> Option<MetricsSnapshot> {
+        self.snapshots.read().await.back().cloned()
+    }
+
+    /// 获取指定时间之后的指标快照
+    pub async fn get_snapshots_since(&self, since: DateTime<Utc>) -> Vec<MetricsSnapshot> {
+        self.snapshots
+            .read()
+            .await
+            .iter()
+            .filter(|snapshot| snapshot.taken_at >= since)
+            .cloned()
+            .collect()
     }
 
     /// 记录性能指标
@@ -66,10 +430,32 @@ impl MonitoringSystem {
             .push(metric);
     }
 
-    /// 记录监控事件
-    pub async fn log_event(&self, event: MonitoringEvent) {
+    /// 记录监控事件（按采样配置可能被丢弃，高基数标签值可能被替换）
+    pub async fn log_event(&self, mut event: MonitoringEvent) {
+        if !self.sampler.admit(&mut event) {
+            return;
+        }
+
+        let timestamp = Utc::now();
+        if let Some(ref exporter) = self.exporter {
+            exporter.try_export(ExportRecord::from_event(timestamp, &event));
+        }
+
+        if let MonitoringEvent::RequestStageCompleted { request_id, stage, duration_ms, success, ref detail } = event {
+            self.traces.write().await.insert(request_id, TraceEntry {
+                timestamp,
+                stage,
+                duration_ms,
+                success,
+                detail: detail.clone(),
+            });
+        }
+
         let mut events = self.event_log.write().await;
-        events.push((Utc::now(), event));
+        events.push_back((timestamp, event));
+        while events.len() > MAX_EVENT_LOG_ENTRIES {
+            events.pop_front();
+        }
     }
 
     /// 获取特定指标的最新值
@@ -86,8 +472,31 @@ impl MonitoringSystem {
         metrics.get(name).cloned().unwrap_or_default()
     }
 
-    /// 检查是否超过阈值并记录警报
+    /// 获取1分钟、5分钟、15分钟窗口的错误率和吞吐量
+    pub async fn get_rolling_rates(&self) -> Vec<RollingRates> {
+        let events = self.event_log.read().await;
+        let now = Utc::now();
+        ROLLING_WINDOWS_SECONDS
+            .iter()
+            .map(|window| compute_rolling_rates(&events, now, *window))
+            .collect()
+    }
+
+    /// 根据请求事件刷新派生指标（1分钟窗口的错误率和吞吐量）
+    pub async fn refresh_derived_metrics(&self) {
+        let rates = {
+            let events = self.event_log.read().await;
+            compute_rolling_rates(&events, Utc::now(), ROLLING_WINDOWS_SECONDS[0])
+        };
+        self.record_metric("error_rate", PerformanceMetric::ErrorRate(rates.error_rate)).await;
+        self.record_metric("throughput", PerformanceMetric::Throughput(rates.throughput.round() as u64)).await;
+    }
+
+    /// 检查是否超过阈值并记录警报。同一指标的重复越限合并到未解决的警报中，只返回新触发的警报；
+    /// 恢复正常的指标会自动解决其警报
     pub async fn check_thresholds(&self) -> Vec<String> {
+        self.refresh_derived_metrics().await;
+
         let mut alerts = Vec::new();
         let metrics = self.metrics.read().await;
         let thresholds = self.thresholds.read().await;
@@ -106,6 +515,11 @@ impl MonitoringSystem {
                     };
 
                     if metric_value > *threshold_value {
+                        let (_, is_new) = self.alert_manager.fire(metric_name, metric_value, *threshold_value).await;
+                        if !is_new {
+                            continue;
+                        }
+
                         let alert_msg = format!(
                             "Performance alert: {} ({}) exceeds threshold ({})",
                             metric_name, metric_value, threshold_value
@@ -118,6 +532,8 @@ impl MonitoringSystem {
                             value: metric_value,
                             threshold: *threshold_value,
                         }).await;
+                    } else {
+                        self.alert_manager.resolve_metric(metric_name).await;
                     }
                 }
             }
@@ -130,10 +546,87 @@ impl MonitoringSystem {
     pub async fn get_recent_events(&self, count: usize) -> Vec<(DateTime<Utc>, MonitoringEvent)> {
         let events = self.event_log.read().await;
         let total_events = events.len();
-        let start_idx = if count > total_events { 0 } else { total_events - count };
+        let start_idx = total_events.saturating_sub(count);
         
-        events[start_idx..]
-            .to_vec()
+        events.range(start_idx..).cloned().collect()
+    }
+
+    /// 按过滤条件分页查询监控事件
+    pub async fn query_events(&self, filter: &EventFilter) -> EventPage {
+        let events = self.event_log.read().await;
+        let matched: Vec<&(DateTime<Utc>, MonitoringEvent)> = events
+            .iter()
+            .filter(|(timestamp, event)| filter.matches(timestamp, event))
+            .collect();
+
+        let total = matched.len();
+        let limit = filter.limit.unwrap_or(total);
+        let page: Vec<(DateTime<Utc>, MonitoringEvent)> = matched
+            .into_iter()
+            .skip(filter.offset)
+            .take(limit)
+            .cloned()
+            .collect();
+
+        EventPage {
+            has_more: filter.offset.saturating_add(page.len()) < total,
+            events: page,
+            total,
+            offset: filter.offset,
+        }
+    }
+
+    /// 记录请求某一阶段的完成情况，用于请求追踪
+    pub async fn record_stage(
+        &self,
+        request_id: Uuid,
+        stage: RequestStage,
+        duration_ms: f64,
+        success: bool,
+        detail: Option<String>,
+    ) {
+        self.log_event(MonitoringEvent::RequestStageCompleted {
+            request_id,
+            stage,
+            duration_ms,
+            success,
+            detail,
+        }).await;
+    }
+
+    /// 组装单个请求的阶段时间线（分类 → 校验 → 选择 → 生成 → 响应）；没有任何记录时返回None
+    pub async fn get_request_trace(&self, request_id: Uuid) -> Option<RequestTrace> {
+        let mut entries = self.traces.read().await.entries.get(&request_id)?.clone();
+        entries.sort_by_key(|entry| (entry.stage, entry.timestamp));
+
+        let total_duration_ms = entries
+            .iter()
+            .find(|entry| entry.stage == RequestStage::Response)
+            .map(|entry| entry.duration_ms);
+        let slowest_stage = entries
+            .iter()
+            .filter(|entry| entry.stage != RequestStage::Response)
+            .max_by(|a, b| a.duration_ms.partial_cmp(&b.duration_ms).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|entry| entry.stage);
+        let failed_stage = entries.iter().find(|entry| !entry.success).map(|entry| entry.stage);
+
+        Some(RequestTrace {
+            request_id,
+            entries,
+            total_duration_ms,
+            slowest_stage,
+            failed_stage,
+        })
+    }
+
+    /// 获取指定时间之后的监控事件
+    pub async fn get_events_since(&self, since: DateTime<Utc>) -> Vec<(DateTime<Utc>, MonitoringEvent)> {
+        let events = self.event_log.read().await;
+        events
+            .iter()
+            .filter(|(timestamp, _)| *timestamp >= since)
+            .cloned()
+            .collect()
     }
 
     /// 获取系统摘要
@@ -228,6 +721,17 @@ impl MonitoringSystem {
             }
         }
 
+        let now = Utc::now();
+        let rolling_rates = ROLLING_WINDOWS_SECONDS
+            .iter()
+            .map(|window| compute_rolling_rates(&events, now, *window))
+            .collect();
+
+        let search_usage = match self.search_budget {
+            Some(ref budget) => Some(budget.get_usage().await),
+            None => None,
+        };
+
         SystemSummary {
             total_metrics: metrics.len(),
             total_events: events.len(),
@@ -238,12 +742,13 @@ impl MonitoringSystem {
             error_count,
             total_requests,
             total_processed_requests,
+            rolling_rates,
+            search_usage,
         }
     }
 
     /// 获取性能趋势
     pub async fn get_performance_trends(&self, metric_name: &str, hours: i64) -> Vec<(DateTime<Utc>, f64)> {
-        let metrics = self.metrics.read().await;
         let events = self.event_log.read().await;
         
         let cutoff_time = Utc::now() - chrono::Duration::hours(hours);
@@ -280,13 +785,15 @@ pub struct SystemSummary {
     pub error_count: usize,                // 错误数量
     pub total_requests: usize,             // 总请求数量
     pub total_processed_requests: usize,   // 总处理请求数量
+    pub rolling_rates: Vec<RollingRates>,  // 1分钟/5分钟/15分钟窗口的错误率和吞吐量
+    pub search_usage: Option<SearchUsageSummary>, // 当日搜索用量（关联了搜索预算时）
 }
 
 impl std::fmt::Display for SystemSummary {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "SystemSummary {{ metrics: {}, events: {}, avg_switch_time: {:.2}ms, avg_hit_rate: {:.2}%, avg_latency: {:.2}ms, avg_selection_time: {:.2}ms, errors: {}, total_requests: {}, processed_requests: {} }}",
+            "SystemSummary {{ metrics: {}, events: {}, avg_switch_time: {:.2}ms, avg_hit_rate: {:.2}%, avg_latency: {:.2}ms, avg_selection_time: {:.2}ms, errors: {}, total_requests: {}, processed_requests: {}, error_rate_1m: {:.2}%, throughput_1m: {:.2}/s }}",
             self.total_metrics,
             self.total_events,
             self.avg_context_switch_time,
@@ -295,8 +802,18 @@ impl std::fmt::Display for SystemSummary {
             self.avg_context_selection_time,
             self.error_count,
             self.total_requests,
-            self.total_processed_requests
-        )
+            self.total_processed_requests,
+            self.rolling_rates.first().map(|r| r.error_rate * 100.0).unwrap_or(0.0),
+            self.rolling_rates.first().map(|r| r.throughput).unwrap_or(0.0)
+        )?;
+        if let Some(ref usage) = self.search_usage {
+            write!(
+                f,
+                " SearchUsage {{ queries: {}, rejected: {}, cost: {:.4} }}",
+                usage.total_queries, usage.rejected_queries, usage.total_cost
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -356,5 +873,136 @@ mod tests {
         // 测试性能趋势
         let trends = monitor.get_performance_trends("request_latency", 1).await;
         assert!(!trends.is_empty());
+
+        // 测试原子指标快照
+        monitor.registry().counter("requests").add(3);
+        monitor.registry().histogram("request_latency_ms").observe(42.0);
+        assert!(monitor.get_latest_snapshot().await.is_none());
+        monitor.take_snapshot().await;
+        let snapshot = monitor.get_latest_snapshot().await.unwrap();
+        assert_eq!(snapshot.counters["requests"], 3);
+        assert_eq!(snapshot.histograms["request_latency_ms"].count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_query_events() {
+        let monitor = MonitoringSystem::new();
+        for i in 0..5 {
+            monitor.log_event(MonitoringEvent::RequestProcessed {
+                user_id: if i % 2 == 0 { "alice" } else { "bob" }.to_string(),
+                session_id: format!("session{}", i),
+                duration_ms: i as f64,
+            }).await;
+        }
+        monitor.log_event(MonitoringEvent::RateLimitTriggered { user_id: "alice".to_string(), limit: 10 }).await;
+        monitor.log_event(MonitoringEvent::ContextLoaded { domain: "medical".to_string(), duration_ms: 5.0 }).await;
+
+        // 按用户查询并分页
+        let filter = EventFilter {
+            user_id: Some("alice".to_string()),
+            limit: Some(2),
+            ..EventFilter::default()
+        };
+        let page = monitor.query_events(&filter).await;
+        assert_eq!(page.total, 4);
+        assert_eq!(page.events.len(), 2);
+        assert!(page.has_more);
+
+        let next = monitor.query_events(&EventFilter { offset: 2, ..filter.clone() }).await;
+        assert_eq!(next.events.len(), 2);
+        assert!(!next.has_more);
+        assert_eq!(next.events[1].1.kind(), EventKind::RateLimitTriggered);
+
+        // 按种类、领域和时间范围查询
+        let page = monitor.query_events(&EventFilter {
+            kinds: vec![EventKind::ContextLoaded],
+            domain: Some("medical".to_string()),
+            start_time: Some(Utc::now() - chrono::Duration::minutes(1)),
+            ..EventFilter::default()
+        }).await;
+        assert_eq!(page.total, 1);
+
+        let future = monitor.query_events(&EventFilter {
+            start_time: Some(Utc::now() + chrono::Duration::minutes(1)),
+            ..EventFilter::default()
+        }).await;
+        assert_eq!(future.total, 0);
+    }
+
+    #[tokio::test]
+    async fn test_rolling_rates() {
+        let monitor = MonitoringSystem::new();
+        for i in 0..6 {
+            monitor.log_event(MonitoringEvent::RequestProcessed {
+                user_id: "user1".to_string(),
+                session_id: format!("session{}", i),
+                duration_ms: 10.0,
+            }).await;
+        }
+        for _ in 0..2 {
+            monitor.log_event(MonitoringEvent::RequestFailed {
+                user_id: "user1".to_string(),
+                session_id: "session1".to_string(),
+                error: "Timeout".to_string(),
+                duration_ms: 30.0,
+            }).await;
+        }
+
+        // 调用方手动推送的错误率会被派生值覆盖
+        monitor.record_metric("error_rate", PerformanceMetric::ErrorRate(1.0)).await;
+
+        let summary = monitor.get_system_summary().await;
+        assert_eq!(summary.rolling_rates.len(), 3);
+        let one_minute = &summary.rolling_rates[0];
+        assert_eq!(one_minute.window_seconds, 60);
+        assert_eq!(one_minute.requests, 8);
+        assert_eq!(one_minute.errors, 2);
+        assert!((one_minute.error_rate - 0.25).abs() < 1e-9);
+        assert!((summary.rolling_rates[2].throughput - 8.0 / 900.0).abs() < 1e-9);
+
+        let alerts = monitor.check_thresholds().await;
+        assert!(alerts.iter().any(|a| a.contains("error_rate (0.25)")));
+        assert!(matches!(monitor.get_latest_metric("error_rate").await, Some(PerformanceMetric::ErrorRate(r)) if r == 0.25));
+
+        // 持续越限不再重复报警，恢复后自动解决
+        assert!(monitor.check_thresholds().await.is_empty());
+        let open = monitor.get_alert_manager().get_open_alerts().await;
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].occurrence_count, 2);
+        for _ in 0..40 {
+            monitor.log_event(MonitoringEvent::RequestProcessed {
+                user_id: "user1".to_string(),
+                session_id: "session1".to_string(),
+                duration_ms: 10.0,
+            }).await;
+        }
+        monitor.check_thresholds().await;
+        assert!(monitor.get_alert_manager().get_open_alerts().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_trace() {
+        let monitor = MonitoringSystem::new();
+        let request_id = Uuid::new_v4();
+
+        monitor.record_stage(request_id, RequestStage::Classification, 2.0, true, None).await;
+        monitor.record_stage(Uuid::new_v4(), RequestStage::Selection, 1.0, true, None).await;
+        monitor.record_stage(request_id, RequestStage::Selection, 40.0, true, None).await;
+        monitor.record_stage(request_id, RequestStage::Generation, 5.0, false, Some("Timeout".to_string())).await;
+        monitor.record_stage(request_id, RequestStage::Response, 50.0, false, Some("Timeout".to_string())).await;
+
+        let trace = monitor.get_request_trace(request_id).await.unwrap();
+        let stages: Vec<RequestStage> = trace.entries.iter().map(|e| e.stage).collect();
+        assert_eq!(stages, vec![
+            RequestStage::Classification,
+            RequestStage::Selection,
+            RequestStage::Generation,
+            RequestStage::Response,
+        ]);
+        assert_eq!(trace.total_duration_ms, Some(50.0));
+        assert_eq!(trace.slowest_stage, Some(RequestStage::Selection));
+        assert_eq!(trace.failed_stage, Some(RequestStage::Generation));
+
+        assert!(monitor.get_request_trace(Uuid::new_v4()).await.is_none());
     }
 }
\ No newline at end of file
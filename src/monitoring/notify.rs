@@ -0,0 +1,169 @@
+//! 通知渠道 - 将警报和报告推送到Webhook或邮件
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use crate::monitoring::webhook::{WebhookDelivery, WebhookEvent};
+
+/// 一条待发送的通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    #[serde(default)]
+    pub event_type: String,         // 事件类型，Webhook按该类型的模式校验payload
+    pub subject: String,            // 标题（邮件主题）
+    pub body: String,               // 纯文本正文
+    pub payload: serde_json::Value, // 结构化内容（Webhook请求体中携带）
+}
+
+/// 通知渠道接口 - Webhook、邮件等实现该接口
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// 发送通知
+    async fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 渠道名称
+    fn name(&self) -> &str;
+}
+
+/// 将通知作为版本化事件以JSON POST推送的Webhook渠道
+pub struct WebhookSink {
+    url: String,
+    delivery: Arc<WebhookDelivery>,
+}
+
+impl WebhookSink {
+    /// 创建Webhook渠道（不签名，默认重试策略）
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            delivery: Arc::new(WebhookDelivery::new()),
+        }
+    }
+
+    /// 使用共享的投递器（签名密钥、重试策略和死信存储）
+    pub fn with_delivery(mut self, delivery: Arc<WebhookDelivery>) -> Self {
+        self.delivery = delivery;
+        self
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    async fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let event = WebhookEvent::from_notification(notification)?;
+        self.delivery.deliver(&self.url, &event).await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "webhook"
+    }
+}
+
+/// 邮件渠道配置（通过内网SMTP中继发送，不做认证和TLS）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub from: String,           // 发件人地址
+    pub to: Vec<String>,        // 收件人地址
+    pub helo_name: String,      // EHLO中使用的主机名
+}
+
+impl Default for EmailConfig {
+    fn default() -> Self {
+        Self {
+            smtp_host: "localhost".to_string(),
+            smtp_port: 25,
+            from: "penlai@localhost".to_string(),
+            to: Vec::new(),
+            helo_name: "penlai".to_string(),
+        }
+    }
+}
+
+/// 通过SMTP中继发送通知邮件的渠道
+pub struct EmailSink {
+    config: EmailConfig,
+}
+
+impl EmailSink {
+    /// 创建邮件渠道
+    pub fn new(config: EmailConfig) -> Self {
+        Self { config }
+    }
+
+    /// 读取一条SMTP应答（可能为多行），校验状态码
+    async fn expect_reply<R>(reader: &mut R, expected: u16) -> Result<(), Box<dyn std::error::Error + Send + Sync>>
+    where
+        R: AsyncBufReadExt + Unpin,
+    {
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 {
+                return Err("SMTP connection closed".into());
+            }
+            let code: u16 = line.get(..3).and_then(|c| c.parse().ok()).ok_or("Malformed SMTP reply")?;
+            // "250-"表示多行应答尚未结束
+            if line.as_bytes().get(3) == Some(&b'-') {
+                continue;
+            }
+            if code != expected {
+                return Err(format!("Unexpected SMTP reply: {}", line.trim_end()).into());
+            }
+            return Ok(());
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for EmailSink {
+    async fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.config.to.is_empty() {
+            return Err("No email recipients configured".into());
+        }
+
+        let stream = TcpStream::connect((self.config.smtp_host.as_str(), self.config.smtp_port)).await?;
+        let (read_half, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(read_half);
+
+        Self::expect_reply(&mut reader, 220).await?;
+        writer.write_all(format!("EHLO {}\r\n", self.config.helo_name).as_bytes()).await?;
+        Self::expect_reply(&mut reader, 250).await?;
+        writer.write_all(format!("MAIL FROM:<{}>\r\n", self.config.from).as_bytes()).await?;
+        Self::expect_reply(&mut reader, 250).await?;
+        for recipient in &self.config.to {
+            writer.write_all(format!("RCPT TO:<{}>\r\n", recipient).as_bytes()).await?;
+            Self::expect_reply(&mut reader, 250).await?;
+        }
+        writer.write_all(b"DATA\r\n").await?;
+        Self::expect_reply(&mut reader, 354).await?;
+
+        let mut message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n",
+            self.config.from,
+            self.config.to.join(", "),
+            notification.subject
+        );
+        for line in notification.body.lines() {
+            // 行首的"."需要转义，避免被当作正文结束
+            if line.starts_with('.') {
+                message.push('.');
+            }
+            message.push_str(line);
+            message.push_str("\r\n");
+        }
+        message.push_str(".\r\n");
+        writer.write_all(message.as_bytes()).await?;
+        Self::expect_reply(&mut reader, 250).await?;
+
+        writer.write_all(b"QUIT\r\n").await?;
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "email"
+    }
+}
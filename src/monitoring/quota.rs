@@ -0,0 +1,295 @@
+//! 配额预警 - 用户速率限制、领域令牌预算和搜索预算在硬性拒绝之前，用量达到配置的百分比（如80%、95%）时
+//! 记录监控事件并推送预警，便于集成方提前提醒其用户；同一阈值在用量回落或进入新周期前只通知一次
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use crate::app::Penlai;
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem};
+use crate::monitoring::notify::{AlertSink, Notification};
+use crate::monitoring::webhook::EVENT_QUOTA_WARNING;
+use crate::utils::search_budget::BudgetScope;
+
+/// 配额种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuotaKind {
+    RateLimit,      // 每用户每分钟请求数
+    TokenBudget,    // 每领域每月生成令牌数
+    SearchBudget,   // 每服务/用户/领域每日搜索次数
+}
+
+/// 单项配额的当前用量
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QuotaUsage {
+    pub kind: QuotaKind,
+    pub scope: BudgetScope,
+    pub key: String,        // 用户ID、领域或搜索服务名
+    pub used: u64,
+    pub limit: u64,
+    pub percent: f64,       // used / limit * 100
+    pub period: String,     // 统计周期：令牌预算为YYYY-MM，搜索预算为日期，速率限制为滚动窗口"minute"
+}
+
+impl QuotaUsage {
+    /// 创建用量记录并计算百分比（上限为0时记为100%）
+    pub fn new(kind: QuotaKind, scope: BudgetScope, key: &str, used: u64, limit: u64, period: &str) -> Self {
+        let percent = if limit == 0 { 100.0 } else { used as f64 / limit as f64 * 100.0 };
+        Self {
+            kind,
+            scope,
+            key: key.to_string(),
+            used,
+            limit,
+            percent,
+            period: period.to_string(),
+        }
+    }
+}
+
+/// 配额预警配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaWarningConfig {
+    pub enabled: bool,
+    pub thresholds_percent: Vec<u8>,    // 预警阈值（百分比），用量达到时各通知一次
+}
+
+impl Default for QuotaWarningConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            thresholds_percent: vec![80, 95],
+        }
+    }
+}
+
+/// 一次配额预警
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QuotaWarning {
+    #[serde(flatten)]
+    pub usage: QuotaUsage,
+    pub threshold_percent: u8,  // 本次越过的阈值
+}
+
+/// 已通知的阈值：(种类, 范围, 键) -> (周期, 已通知的最高阈值)
+type NotifiedThresholds = HashMap<(QuotaKind, BudgetScope, String), (String, u8)>;
+
+/// 配额预警器 - 速率限制、令牌预算和搜索预算在记录用量后调用`observe`
+pub struct QuotaWarner {
+    config: RwLock<QuotaWarningConfig>,
+    notified: RwLock<NotifiedThresholds>,
+    monitoring: Option<Arc<MonitoringSystem>>,
+    sinks: std::sync::RwLock<Vec<Arc<dyn AlertSink>>>,
+}
+
+impl Default for QuotaWarner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QuotaWarner {
+    /// 使用默认阈值创建预警器
+    pub fn new() -> Self {
+        Self {
+            config: RwLock::new(QuotaWarningConfig::default()),
+            notified: RwLock::new(HashMap::new()),
+            monitoring: None,
+            sinks: std::sync::RwLock::new(Vec::new()),
+        }
+    }
+
+    /// 关联监控系统，预警记录为监控事件
+    pub fn with_monitoring(mut self, monitoring: Arc<MonitoringSystem>) -> Self {
+        self.monitoring = Some(monitoring);
+        self
+    }
+
+    /// 添加预警的通知渠道
+    pub fn with_sink(mut self, sink: Arc<dyn AlertSink>) -> Self {
+        self.sinks.get_mut().unwrap_or_else(|e| e.into_inner()).push(sink);
+        self
+    }
+
+    /// 替换同名的通知渠道（如Webhook地址变更），不存在时添加
+    pub fn replace_sink(&self, sink: Arc<dyn AlertSink>) {
+        let mut sinks = self.sinks.write().unwrap_or_else(|e| e.into_inner());
+        sinks.retain(|existing| existing.name() != sink.name());
+        sinks.push(sink);
+    }
+
+    /// 检查用量是否越过新的阈值，越过时记录监控事件并在后台推送通知；返回产生的预警
+    pub async fn observe(&self, usage: QuotaUsage) -> Option<QuotaWarning> {
+        let config = self.config.read().await.clone();
+        if !config.enabled || usage.limit == 0 {
+            return None;
+        }
+        let crossed = config
+            .thresholds_percent
+            .iter()
+            .copied()
+            .filter(|threshold| usage.percent >= *threshold as f64)
+            .max()
+            .unwrap_or(0);
+
+        {
+            let mut notified = self.notified.write().await;
+            let key = (usage.kind, usage.scope, usage.key.clone());
+            let previous = notified.get(&key).filter(|(period, _)| *period == usage.period).map(|(_, threshold)| *threshold).unwrap_or(0);
+            // 用量回落（如限流窗口滑过）后降低记录，再次上升时重新预警
+            if crossed <= previous {
+                if crossed < previous {
+                    notified.insert(key, (usage.period.clone(), crossed));
+                }
+                return None;
+            }
+            notified.insert(key, (usage.period.clone(), crossed));
+        }
+
+        let warning = QuotaWarning { usage, threshold_percent: crossed };
+        log::warn!(
+            "{:?} quota for {:?} '{}' at {:.0}% ({}/{})",
+            warning.usage.kind, warning.usage.scope, warning.usage.key, warning.usage.percent, warning.usage.used, warning.usage.limit
+        );
+        if let Some(ref monitoring) = self.monitoring {
+            monitoring.log_event(MonitoringEvent::QuotaWarning {
+                kind: warning.usage.kind,
+                scope: warning.usage.scope,
+                key: warning.usage.key.clone(),
+                used: warning.usage.used,
+                limit: warning.usage.limit,
+                threshold_percent: crossed,
+            }).await;
+        }
+        self.deliver(&warning);
+        Some(warning)
+    }
+
+    /// 在后台发送到通知渠道，不阻塞请求处理（失败只记录日志）
+    fn deliver(&self, warning: &QuotaWarning) {
+        let sinks = self.sinks.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if sinks.is_empty() {
+            return;
+        }
+        let notification = Notification {
+            event_type: EVENT_QUOTA_WARNING.to_string(),
+            subject: format!("{:?} quota for '{}' reached {}%", warning.usage.kind, warning.usage.key, warning.threshold_percent),
+            body: format!(
+                "{} of {} used ({:.1}%) in period {}",
+                warning.usage.used, warning.usage.limit, warning.usage.percent, warning.usage.period
+            ),
+            payload: serde_json::to_value(warning).unwrap_or_default(),
+        };
+        tokio::spawn(async move {
+            for sink in &sinks {
+                if let Err(e) = sink.send(&notification).await {
+                    log::warn!("Failed to deliver quota warning via {}: {}", sink.name(), e);
+                }
+            }
+        });
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: QuotaWarningConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> QuotaWarningConfig {
+        self.config.read().await.clone()
+    }
+}
+
+impl Penlai {
+    /// 全部配额的当前用量，可按用户ID或领域筛选（搜索服务配额只在不筛选时返回）
+    pub async fn quota_usage(&self, user_id: Option<&str>, domain: Option<&str>) -> Vec<QuotaUsage> {
+        let mut usages = self.request_processor.rate_limit_usage().await;
+        usages.extend(self.request_processor.get_token_budget().get_all_statuses().await.into_iter().map(|status| {
+            QuotaUsage::new(QuotaKind::TokenBudget, BudgetScope::Domain, &status.domain, status.used_tokens, status.monthly_limit, &status.period)
+        }));
+        usages.extend(self.search_budget.quota_usage().await);
+        if user_id.is_some() || domain.is_some() {
+            usages.retain(|usage| match usage.scope {
+                BudgetScope::User => user_id == Some(usage.key.as_str()),
+                BudgetScope::Domain => domain == Some(usage.key.as_str()),
+                BudgetScope::Provider => false,
+            });
+        }
+        usages.sort_by(|a, b| b.percent.partial_cmp(&a.percent).unwrap_or(std::cmp::Ordering::Equal));
+        usages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitoring::monitoring::EventKind;
+    use crate::processing::concurrent_processor::{RequestError, RequestProcessor};
+    use crate::test_support::RecordingSink;
+
+    #[tokio::test]
+    async fn test_warnings_fire_once_per_threshold_before_hard_limit() {
+        let app = Penlai::new(10, 3600);
+        let sink = Arc::new(RecordingSink::default());
+        let warner = Arc::new(QuotaWarner::new().with_monitoring(app.monitoring.clone()).with_sink(sink.clone()));
+        let processor = RequestProcessor::new(app.context_manager.clone(), app.context_selector.clone())
+            .with_monitoring(app.monitoring.clone())
+            .with_quota_warner(warner.clone());
+        let mut config = processor.get_config().await;
+        config.max_requests_per_minute = 10;
+        processor.update_config(config).await;
+
+        // 第8次请求达到80%，第10次达到95%以上；其间的请求不重复预警
+        let request = |query: &str| processor.process_request("alice".to_string(), "s1".to_string(), query.to_string(), "medical".to_string());
+        for _ in 0..10 {
+            request("pneumonia").await.unwrap();
+        }
+        assert!(matches!(request("flu").await, Err(RequestError::RateLimitExceeded(_))));
+        let warnings = app.monitoring.get_events_since(chrono::Utc::now() - chrono::Duration::minutes(1)).await;
+        let thresholds: Vec<u8> = warnings
+            .iter()
+            .filter_map(|(_, event)| match event {
+                MonitoringEvent::QuotaWarning { threshold_percent, .. } => Some(*threshold_percent),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(thresholds, vec![80, 95]);
+        assert!(warnings.iter().any(|(_, event)| event.kind() == EventKind::QuotaWarning && event.user_id() == Some("alice")));
+        // 通知在后台送达，等待送达而不是固定时长
+        assert_eq!(sink.wait_for(2).await.len(), 2);
+
+        // 令牌预算：直接越过两个阈值时只按最高阈值预警一次
+        processor.get_token_budget().set_budget("medical", 1000).await;
+        processor.record_generation_tokens("medical", 960).await;
+        processor.record_generation_tokens("medical", 10).await;
+        let sent = sink.wait_for(3).await;
+        assert_eq!(sent.len(), 3);
+        assert!(sent.iter().all(|notification| notification.event_type == EVENT_QUOTA_WARNING));
+
+        // 统计API按用户或领域返回当前用量
+        app.request_processor.get_token_budget().set_budget("medical", 1000).await;
+        app.request_processor.record_generation_tokens("medical", 970).await;
+        for _ in 0..3 {
+            app.request_processor
+                .process_request("alice".to_string(), "s1".to_string(), "pneumonia".to_string(), "medical".to_string())
+                .await
+                .unwrap();
+        }
+        let usage = app.quota_usage(Some("alice"), Some("medical")).await;
+        assert_eq!(usage.iter().map(|u| (u.kind, u.used, u.limit)).collect::<Vec<_>>(), vec![
+            (QuotaKind::TokenBudget, 970, 1000),
+            (QuotaKind::RateLimit, 3, 1000),
+        ]);
+        assert!(app.quota_usage(None, None).await.iter().any(|u| u.kind == QuotaKind::SearchBudget && u.scope == BudgetScope::Provider));
+
+        // 用量回落后重新越过阈值时再次预警
+        let usage = |used| QuotaUsage::new(QuotaKind::SearchBudget, BudgetScope::User, "bob", used, 100, "2026-10-16");
+        assert_eq!(warner.observe(usage(85)).await.unwrap().threshold_percent, 80);
+        assert!(warner.observe(usage(90)).await.is_none());
+        assert!(warner.observe(usage(10)).await.is_none());
+        assert!(warner.observe(usage(81)).await.is_some());
+    }
+}
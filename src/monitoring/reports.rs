@@ -0,0 +1,471 @@
+//! 定时报告 - 汇总请求量、延迟分位数、热门领域、令牌消耗、陈旧上下文和警报，按日通过通知渠道推送
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use utoipa::ToSchema;
+use crate::context::llm_context::ContextManager;
+use crate::monitoring::alerts::AlertState;
+use crate::monitoring::api::DomainStat;
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem};
+use crate::monitoring::notify::{AlertSink, Notification};
+use crate::monitoring::webhook::EVENT_USAGE_REPORT;
+use crate::maintenance::MaintenanceMode;
+
+/// 报告时间范围 [start, end)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ReportRange {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl ReportRange {
+    /// 创建时间范围
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    /// 截至当前的最近若干小时
+    pub fn last_hours(hours: i64) -> Self {
+        let end = Utc::now();
+        Self { start: end - Duration::hours(hours), end }
+    }
+
+    /// 时间点是否在范围内
+    pub fn contains(&self, time: &DateTime<Utc>) -> bool {
+        *time >= self.start && *time < self.end
+    }
+}
+
+/// 请求量统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct RequestVolume {
+    pub processed: usize,       // 成功处理的请求数
+    pub failed: usize,          // 失败的请求数
+    pub rate_limited: usize,    // 被限流的请求数
+    pub error_rate: f64,        // 失败请求数 / 完成的请求数
+}
+
+/// 请求延迟分位数（毫秒）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct LatencyPercentiles {
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// 令牌消耗统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TokenSpend {
+    pub total_tokens: u64,
+    pub by_domain: BTreeMap<String, u64>,
+    pub estimated_cost: f64,    // 按配置单价估算的费用
+}
+
+/// 警报摘要
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct AlertSummary {
+    pub fired: usize,               // 范围内新触发的警报数
+    pub resolved: usize,            // 范围内解决的警报数
+    pub open: usize,                // 当前未解决的警报数
+    pub open_metrics: Vec<String>,  // 未解决警报的指标名
+}
+
+/// 日报
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UsageReport {
+    pub generated_at: DateTime<Utc>,
+    pub range: ReportRange,
+    pub requests: RequestVolume,
+    pub latency: LatencyPercentiles,
+    pub top_domains: Vec<DomainStat>,
+    pub token_spend: TokenSpend,
+    pub stale_contexts: usize,      // 超过配置时长未更新的活跃上下文数
+    pub alerts: AlertSummary,
+}
+
+impl UsageReport {
+    /// 转换为通知
+    pub fn to_notification(&self) -> Notification {
+        Notification {
+            event_type: EVENT_USAGE_REPORT.to_string(),
+            subject: format!("Penlai usage report {} - {}", self.range.start.format("%Y-%m-%d %H:%M"), self.range.end.format("%Y-%m-%d %H:%M")),
+            body: self.to_string(),
+            payload: serde_json::to_value(self).unwrap_or(serde_json::Value::Null),
+        }
+    }
+}
+
+impl fmt::Display for UsageReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Usage report {} - {} (UTC)", self.range.start.format("%Y-%m-%d %H:%M"), self.range.end.format("%Y-%m-%d %H:%M"))?;
+        writeln!(
+            f,
+            "  Requests: {} processed, {} failed ({:.2}%), {} rate limited",
+            self.requests.processed,
+            self.requests.failed,
+            self.requests.error_rate * 100.0,
+            self.requests.rate_limited
+        )?;
+        writeln!(
+            f,
+            "  Latency: p50 {:.0}ms, p95 {:.0}ms, p99 {:.0}ms, max {:.0}ms",
+            self.latency.p50_ms, self.latency.p95_ms, self.latency.p99_ms, self.latency.max_ms
+        )?;
+        let domains: Vec<String> = self.top_domains.iter().map(|d| format!("{} ({})", d.domain, d.load_count)).collect();
+        writeln!(f, "  Top domains: {}", if domains.is_empty() { "-".to_string() } else { domains.join(", ") })?;
+        writeln!(f, "  Tokens: {} (est. cost {:.2})", self.token_spend.total_tokens, self.token_spend.estimated_cost)?;
+        writeln!(f, "  Stale contexts: {}", self.stale_contexts)?;
+        write!(
+            f,
+            "  Alerts: {} fired, {} resolved, {} open",
+            self.alerts.fired, self.alerts.resolved, self.alerts.open
+        )?;
+        if !self.alerts.open_metrics.is_empty() {
+            write!(f, " ({})", self.alerts.open_metrics.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// 报告配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportConfig {
+    pub delivery_hour_utc: u32,         // 每日推送时间（UTC小时）
+    pub top_domains_limit: usize,       // 报告中列出的领域数
+    pub stale_after_hours: i64,         // 超过该时长未更新的上下文视为陈旧
+    pub cost_per_1k_tokens: f64,        // 估算费用使用的令牌单价
+    pub max_history: usize,             // 保留的历史报告数
+}
+
+impl Default for ReportConfig {
+    fn default() -> Self {
+        Self {
+            delivery_hour_utc: 0,
+            top_domains_limit: 5,
+            stale_after_hours: 24 * 30,
+            cost_per_1k_tokens: 0.0,
+            max_history: 30,
+        }
+    }
+}
+
+/// 单个渠道的推送结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryOutcome {
+    pub sink: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// 报告生成器 - 从监控数据和上下文存储生成报告，并推送到通知渠道
+pub struct ReportGenerator {
+    monitoring: Arc<MonitoringSystem>,
+    context_manager: Option<Arc<ContextManager>>,
+    sinks: std::sync::RwLock<Vec<Arc<dyn AlertSink>>>,
+    config: Arc<RwLock<ReportConfig>>,
+    history: Arc<RwLock<VecDeque<UsageReport>>>,
+    maintenance: Option<Arc<MaintenanceMode>>,
+}
+
+impl ReportGenerator {
+    /// 创建报告生成器
+    pub fn new(monitoring: Arc<MonitoringSystem>) -> Self {
+        Self {
+            monitoring,
+            context_manager: None,
+            sinks: std::sync::RwLock::new(Vec::new()),
+            config: Arc::new(RwLock::new(ReportConfig::default())),
+            history: Arc::new(RwLock::new(VecDeque::new())),
+            maintenance: None,
+        }
+    }
+
+    /// 关联上下文管理器，用于统计陈旧上下文
+    pub fn with_context_manager(mut self, context_manager: Arc<ContextManager>) -> Self {
+        self.context_manager = Some(context_manager);
+        self
+    }
+
+    /// 添加通知渠道
+    pub fn with_sink(mut self, sink: Arc<dyn AlertSink>) -> Self {
+        self.sinks.get_mut().unwrap_or_else(|e| e.into_inner()).push(sink);
+        self
+    }
+
+    /// 替换同名的通知渠道（如Webhook地址变更），不存在时添加
+    pub fn replace_sink(&self, sink: Arc<dyn AlertSink>) {
+        let mut sinks = self.sinks.write().unwrap_or_else(|e| e.into_inner());
+        sinks.retain(|existing| existing.name() != sink.name());
+        sinks.push(sink);
+    }
+
+    /// 关联维护模式，维护期间暂停每日报告，维护结束后补发
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 生成指定时间范围的报告
+    pub async fn generate_report(&self, range: ReportRange) -> UsageReport {
+        let config = self.config.read().await.clone();
+
+        let mut requests = RequestVolume::default();
+        let mut latencies = Vec::new();
+        let mut domains: HashMap<String, (usize, f64)> = HashMap::new();
+        let mut token_spend = TokenSpend::default();
+        for (timestamp, event) in self.monitoring.get_events_since(range.start).await {
+            if !range.contains(&timestamp) {
+                continue;
+            }
+            match event {
+                MonitoringEvent::RequestProcessed { duration_ms, .. } => {
+                    requests.processed += 1;
+                    latencies.push(duration_ms);
+                }
+                MonitoringEvent::RequestFailed { duration_ms, .. } => {
+                    requests.failed += 1;
+                    latencies.push(duration_ms);
+                }
+                MonitoringEvent::RateLimitTriggered { .. } => requests.rate_limited += 1,
+                MonitoringEvent::ContextLoaded { domain, duration_ms } => {
+                    let entry = domains.entry(domain).or_default();
+                    entry.0 += 1;
+                    entry.1 += duration_ms;
+                }
+                MonitoringEvent::TokensUsed { domain, tokens } => {
+                    token_spend.total_tokens += tokens;
+                    *token_spend.by_domain.entry(domain).or_default() += tokens;
+                }
+                _ => {}
+            }
+        }
+        let completed = requests.processed + requests.failed;
+        if completed > 0 {
+            requests.error_rate = requests.failed as f64 / completed as f64;
+        }
+        token_spend.estimated_cost = token_spend.total_tokens as f64 / 1000.0 * config.cost_per_1k_tokens;
+
+        let mut top_domains: Vec<DomainStat> = domains
+            .into_iter()
+            .map(|(domain, (load_count, total_ms))| DomainStat {
+                domain,
+                load_count,
+                avg_load_ms: total_ms / load_count as f64,
+            })
+            .collect();
+        top_domains.sort_by(|a, b| b.load_count.cmp(&a.load_count).then_with(|| a.domain.cmp(&b.domain)));
+        top_domains.truncate(config.top_domains_limit);
+
+        let stale_contexts = match self.context_manager {
+            Some(ref manager) => {
+                let cutoff = range.end - Duration::hours(config.stale_after_hours);
+                manager
+                    .get_all_contexts()
+                    .await
+                    .iter()
+                    .filter(|ctx| ctx.active && ctx.updated_at < cutoff)
+                    .count()
+            }
+            None => 0,
+        };
+
+        let all_alerts = self.monitoring.get_alert_manager().get_all_alerts().await;
+        let open_alerts: Vec<_> = all_alerts.iter().filter(|a| a.is_open()).collect();
+        let alerts = AlertSummary {
+            fired: all_alerts.iter().filter(|a| range.contains(&a.first_fired_at)).count(),
+            resolved: all_alerts
+                .iter()
+                .filter(|a| a.state == AlertState::Resolved && a.resolved_at.map(|t| range.contains(&t)).unwrap_or(false))
+                .count(),
+            open: open_alerts.len(),
+            open_metrics: open_alerts.iter().map(|a| a.metric.clone()).collect(),
+        };
+
+        UsageReport {
+            generated_at: Utc::now(),
+            range,
+            requests,
+            latency: percentiles(latencies),
+            top_domains,
+            token_spend,
+            stale_contexts,
+            alerts,
+        }
+    }
+
+    /// 将报告推送到所有通知渠道
+    pub async fn deliver(&self, report: &UsageReport) -> Vec<DeliveryOutcome> {
+        let notification = report.to_notification();
+        let sinks = self.sinks.read().unwrap_or_else(|e| e.into_inner()).clone();
+        let mut outcomes = Vec::with_capacity(sinks.len());
+        for sink in &sinks {
+            let result = sink.send(&notification).await;
+            if let Err(ref e) = result {
+                log::warn!("Failed to deliver report via {}: {}", sink.name(), e);
+            }
+            outcomes.push(DeliveryOutcome {
+                sink: sink.name().to_string(),
+                success: result.is_ok(),
+                error: result.err().map(|e| e.to_string()),
+            });
+        }
+        outcomes
+    }
+
+    /// 生成、保存并推送报告
+    pub async fn run_report(&self, range: ReportRange) -> (UsageReport, Vec<DeliveryOutcome>) {
+        let report = self.generate_report(range).await;
+        let max_history = self.config.read().await.max_history;
+        {
+            let mut history = self.history.write().await;
+            history.push_back(report.clone());
+            while history.len() > max_history {
+                history.pop_front();
+            }
+        }
+        let outcomes = self.deliver(&report).await;
+        (report, outcomes)
+    }
+
+    /// 启动每日报告任务：每天在配置的UTC小时生成前24小时的报告
+    pub fn start_daily_reports(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let delivery_hour = self.config.read().await.delivery_hour_utc;
+                let now = Utc::now();
+                let next_run = next_delivery_time(now, delivery_hour);
+                let wait = (next_run - now).to_std().unwrap_or_default();
+                tokio::time::sleep(wait).await;
+                if let Some(ref maintenance) = self.maintenance {
+                    maintenance.wait_until_inactive().await;
+                }
+                self.run_report(ReportRange::new(next_run - Duration::days(1), next_run)).await;
+            }
+        })
+    }
+
+    /// 获取最近的报告（最新的在前）
+    pub async fn get_recent_reports(&self, count: usize) -> Vec<UsageReport> {
+        self.history.read().await.iter().rev().take(count).cloned().collect()
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: ReportConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> ReportConfig {
+        self.config.read().await.clone()
+    }
+}
+
+/// 计算延迟分位数
+fn percentiles(mut latencies: Vec<f64>) -> LatencyPercentiles {
+    if latencies.is_empty() {
+        return LatencyPercentiles::default();
+    }
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let count = latencies.len();
+    let at = |q: f64| latencies[((count as f64 * q).ceil() as usize).clamp(1, count) - 1];
+    LatencyPercentiles {
+        p50_ms: at(0.5),
+        p95_ms: at(0.95),
+        p99_ms: at(0.99),
+        max_ms: latencies[count - 1],
+    }
+}
+
+/// 下一次推送时间（今天的推送时间已过则为明天）
+fn next_delivery_time(now: DateTime<Utc>, hour: u32) -> DateTime<Utc> {
+    let today = now.date_naive().and_hms_opt(hour.min(23), 0, 0).unwrap_or_default().and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::test_support::RecordingSink;
+
+    #[tokio::test]
+    async fn test_generate_and_deliver_report() {
+        let monitor = Arc::new(MonitoringSystem::new());
+        for duration_ms in [100.0, 200.0, 300.0, 400.0] {
+            monitor
+                .log_event(MonitoringEvent::RequestProcessed {
+                    user_id: "u1".to_string(),
+                    session_id: "s1".to_string(),
+                    duration_ms,
+                })
+                .await;
+        }
+        monitor
+            .log_event(MonitoringEvent::RequestFailed {
+                user_id: "u1".to_string(),
+                session_id: "s1".to_string(),
+                error: "timeout".to_string(),
+                duration_ms: 1000.0,
+            })
+            .await;
+        monitor.log_event(MonitoringEvent::ContextLoaded { domain: "medical".to_string(), duration_ms: 5.0 }).await;
+        monitor.log_event(MonitoringEvent::TokensUsed { domain: "medical".to_string(), tokens: 1500 }).await;
+        monitor.log_event(MonitoringEvent::TokensUsed { domain: "legal".to_string(), tokens: 500 }).await;
+        monitor.get_alert_manager().fire("request_latency_ms", 1000.0, 500.0).await;
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Fresh".to_string(), 5)
+            .await
+            .unwrap();
+
+        let sink = Arc::new(RecordingSink::default());
+        let generator = ReportGenerator::new(monitor.clone())
+            .with_context_manager(manager.clone())
+            .with_sink(sink.clone());
+        generator
+            .update_config(ReportConfig { cost_per_1k_tokens: 0.5, stale_after_hours: 0, ..ReportConfig::default() })
+            .await;
+
+        let (report, outcomes) = generator.run_report(ReportRange::last_hours(24)).await;
+        assert_eq!(report.requests.processed, 4);
+        assert_eq!(report.requests.failed, 1);
+        assert!((report.requests.error_rate - 0.2).abs() < 1e-9);
+        assert_eq!(report.latency.p50_ms, 300.0);
+        assert_eq!(report.latency.max_ms, 1000.0);
+        assert_eq!(report.top_domains[0].domain, "medical");
+        assert_eq!(report.token_spend.total_tokens, 2000);
+        assert!((report.token_spend.estimated_cost - 1.0).abs() < 1e-9);
+        assert_eq!(report.stale_contexts, 1);
+        assert_eq!(report.alerts.fired, 1);
+        assert_eq!(report.alerts.open_metrics, vec!["request_latency_ms".to_string()]);
+
+        assert!(outcomes.iter().all(|o| o.success));
+        let sent = sink.sent();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].body.contains("4 processed, 1 failed"));
+        assert_eq!(generator.get_recent_reports(5).await.len(), 1);
+
+        // 范围之外的事件不计入
+        let past = ReportRange::new(Utc::now() - Duration::days(2), Utc::now() - Duration::days(1));
+        assert_eq!(generator.generate_report(past).await.requests.processed, 0);
+    }
+
+    #[test]
+    fn test_next_delivery_time() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 10, 30, 0).unwrap();
+        assert_eq!(next_delivery_time(now, 12), Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap());
+        assert_eq!(next_delivery_time(now, 6), Utc.with_ymd_and_hms(2024, 3, 2, 6, 0, 0).unwrap());
+    }
+}
@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+use serde::{Deserialize, Serialize};
+use crate::monitoring::monitoring::{EventKind, MonitoringEvent};
+
+/// 超出基数上限的标签值统一替换为该值
+pub const OVERFLOW_LABEL_VALUE: &str = "__overflow__";
+
+/// 事件采样与基数控制配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventSamplingConfig {
+    pub sample_rates: HashMap<EventKind, f64>,  // 按事件种类的采样率（0.0-1.0，未配置时为1.0）
+    pub max_values_per_label: usize,            // 每个标签允许的不同取值数上限
+    pub label_limits: HashMap<String, usize>,   // 按标签名覆盖的取值数上限
+}
+
+impl Default for EventSamplingConfig {
+    fn default() -> Self {
+        Self {
+            sample_rates: HashMap::new(),
+            max_values_per_label: 10_000,
+            label_limits: HashMap::new(),
+        }
+    }
+}
+
+/// 采样统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SamplingStats {
+    pub received: HashMap<EventKind, u64>,          // 收到的事件数
+    pub sampled_out: HashMap<EventKind, u64>,       // 被采样丢弃的事件数
+    pub label_overflows: HashMap<String, u64>,      // 因基数上限被替换的标签值次数
+    pub distinct_label_values: HashMap<String, usize>, // 已记录的不同标签值数量
+}
+
+impl MonitoringEvent {
+    /// 事件中可能产生高基数的标签（标签名, 值）
+    fn labels_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+        match self {
+            MonitoringEvent::ContextLoaded { domain, .. } => vec![("domain", domain)],
+            MonitoringEvent::ContextSelected { .. } => Vec::new(),
+            MonitoringEvent::CacheAccess { key_type, .. } => vec![("key_type", key_type)],
+            MonitoringEvent::PerformanceAlert { metric, .. } => vec![("metric", metric)],
+            MonitoringEvent::RequestProcessed { user_id, session_id, .. } => {
+                vec![("user_id", user_id), ("session_id", session_id)]
+            }
+            MonitoringEvent::RequestFailed { user_id, session_id, error, .. } => {
+                vec![("user_id", user_id), ("session_id", session_id), ("error", error)]
+            }
+            MonitoringEvent::RateLimitTriggered { user_id, .. } => vec![("user_id", user_id)],
+            MonitoringEvent::RequestStageCompleted { .. } => Vec::new(),
+            MonitoringEvent::TokensUsed { domain, .. } => vec![("domain", domain)],
+            MonitoringEvent::ContextCollected { domain, reason, .. } => vec![("domain", domain), ("reason", reason)],
+            MonitoringEvent::QuotaWarning { key, .. } => vec![("key", key)],
+        }
+    }
+}
+
+/// 事件采样器 - 按种类确定性采样，并限制标签取值的基数。
+/// 计数使用按种类划分的原子计数器，记录事件时不需要独占锁；只有首次出现的标签值需要写锁
+#[derive(Debug)]
+pub struct EventSampler {
+    config: RwLock<EventSamplingConfig>,
+    label_values: RwLock<HashMap<&'static str, HashSet<String>>>,
+    received: [AtomicU64; EventKind::ALL.len()],
+    sampled_out: [AtomicU64; EventKind::ALL.len()],
+    label_overflows: Mutex<HashMap<String, u64>>,
+}
+
+impl Default for EventSampler {
+    fn default() -> Self {
+        Self::new(EventSamplingConfig::default())
+    }
+}
+
+impl EventSampler {
+    /// 使用指定配置创建采样器
+    pub fn new(config: EventSamplingConfig) -> Self {
+        Self {
+            config: RwLock::new(config),
+            label_values: RwLock::new(HashMap::new()),
+            received: Default::default(),
+            sampled_out: Default::default(),
+            label_overflows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 不参与采样的事件种类：请求阶段事件用于组装请求追踪，请求完成/失败事件用于计算错误率和吞吐量
+    fn never_sampled(kind: EventKind) -> bool {
+        matches!(kind, EventKind::RequestStageCompleted | EventKind::RequestProcessed | EventKind::RequestFailed)
+    }
+
+    /// 判断事件是否应被记录；记录时将超出基数上限的标签值替换为溢出值
+    pub fn admit(&self, event: &mut MonitoringEvent) -> bool {
+        let kind = event.kind();
+        let index = self.received[kind as usize].fetch_add(1, Ordering::Relaxed);
+
+        if !Self::never_sampled(kind) {
+            // 按采样率均匀保留：第n个事件在 floor((n+1)*rate) 增长时保留
+            let rate = self
+                .config
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .sample_rates
+                .get(&kind)
+                .copied()
+                .unwrap_or(1.0)
+                .clamp(0.0, 1.0);
+            if ((index + 1) as f64 * rate).floor() <= (index as f64 * rate).floor() {
+                self.sampled_out[kind as usize].fetch_add(1, Ordering::Relaxed);
+                return false;
+            }
+        }
+
+        for (label, value) in event.labels_mut() {
+            let known = self
+                .label_values
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(label)
+                .is_some_and(|values| values.contains(value.as_str()));
+            if known {
+                continue;
+            }
+
+            let limit = {
+                let config = self.config.read().unwrap_or_else(PoisonError::into_inner);
+                config.label_limits.get(label).copied().unwrap_or(config.max_values_per_label)
+            };
+            let mut label_values = self.label_values.write().unwrap_or_else(PoisonError::into_inner);
+            let values = label_values.entry(label).or_default();
+            if values.contains(value.as_str()) {
+                continue;
+            }
+            if values.len() < limit {
+                values.insert(value.clone());
+            } else {
+                *value = OVERFLOW_LABEL_VALUE.to_string();
+                *self
+                    .label_overflows
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .entry(label.to_string())
+                    .or_insert(0) += 1;
+            }
+        }
+
+        true
+    }
+
+    /// 更新配置（已记录的标签取值保留）
+    pub fn update_config(&self, config: EventSamplingConfig) {
+        *self.config.write().unwrap_or_else(PoisonError::into_inner) = config;
+    }
+
+    /// 获取当前配置
+    pub fn get_config(&self) -> EventSamplingConfig {
+        self.config.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// 获取采样统计
+    pub fn get_stats(&self) -> SamplingStats {
+        let counts = |counters: &[AtomicU64]| -> HashMap<EventKind, u64> {
+            EventKind::ALL
+                .iter()
+                .zip(counters)
+                .map(|(kind, counter)| (*kind, counter.load(Ordering::Relaxed)))
+                .filter(|(_, count)| *count > 0)
+                .collect()
+        };
+        SamplingStats {
+            received: counts(&self.received),
+            sampled_out: counts(&self.sampled_out),
+            label_overflows: self.label_overflows.lock().unwrap_or_else(PoisonError::into_inner).clone(),
+            distinct_label_values: self
+                .label_values
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(label, values)| (label.to_string(), values.len()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate_limited(user_id: &str) -> MonitoringEvent {
+        MonitoringEvent::RateLimitTriggered { user_id: user_id.to_string(), limit: 1 }
+    }
+
+    #[test]
+    fn test_sampling_and_cardinality() {
+        let mut config = EventSamplingConfig::default();
+        config.sample_rates.insert(EventKind::RateLimitTriggered, 0.25);
+        config.label_limits.insert("user_id".to_string(), 2);
+        let sampler = EventSampler::new(config);
+
+        let mut admitted = Vec::new();
+        for i in 0..100 {
+            let mut event = rate_limited(&format!("user{}", i % 5));
+            if sampler.admit(&mut event) {
+                admitted.push(event);
+            }
+        }
+        assert_eq!(admitted.len(), 25);
+
+        // 超出基数上限的用户被归入溢出值
+        let overflowed = admitted.iter().filter(|e| e.user_id() == Some(OVERFLOW_LABEL_VALUE)).count();
+        assert!(overflowed > 0);
+
+        let stats = sampler.get_stats();
+        assert_eq!(stats.received[&EventKind::RateLimitTriggered], 100);
+        assert_eq!(stats.sampled_out[&EventKind::RateLimitTriggered], 75);
+        assert_eq!(stats.label_overflows["user_id"], overflowed as u64);
+        assert_eq!(stats.distinct_label_values["user_id"], 2);
+
+        // 未配置采样率的事件全部保留
+        let mut alert = MonitoringEvent::PerformanceAlert { metric: "error_rate".to_string(), value: 1.0, threshold: 0.5 };
+        assert!(sampler.admit(&mut alert));
+
+        // 请求完成/失败和请求阶段事件不受采样率影响
+        let mut config = EventSamplingConfig::default();
+        config.sample_rates.insert(EventKind::RequestStageCompleted, 0.0);
+        config.sample_rates.insert(EventKind::RequestProcessed, 0.0);
+        config.sample_rates.insert(EventKind::RequestFailed, 0.0);
+        sampler.update_config(config);
+        let mut processed = MonitoringEvent::RequestProcessed {
+            user_id: "user0".to_string(),
+            session_id: "session1".to_string(),
+            duration_ms: 1.0,
+        };
+        assert!(sampler.admit(&mut processed));
+        let mut failed = MonitoringEvent::RequestFailed {
+            user_id: "user0".to_string(),
+            session_id: "session1".to_string(),
+            error: "Timeout".to_string(),
+            duration_ms: 1.0,
+        };
+        assert!(sampler.admit(&mut failed));
+        let mut stage = MonitoringEvent::RequestStageCompleted {
+            request_id: uuid::Uuid::new_v4(),
+            stage: crate::monitoring::monitoring::RequestStage::Response,
+            duration_ms: 1.0,
+            success: true,
+            detail: None,
+        };
+        assert!(sampler.admit(&mut stage));
+    }
+}
@@ -0,0 +1,645 @@
+//! 出站Webhook事件 - 为推送给外部系统的事件提供版本化的JSON模式、HMAC签名、失败重试和死信存储，
+//! 消费方可按模式版本解析事件，并用共享密钥和签名时间戳校验来源、拒绝重放
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::context::ingestion::{sign_timestamped, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use crate::monitoring::notify::Notification;
+
+/// 事件类型：使用报告
+pub const EVENT_USAGE_REPORT: &str = "usage_report";
+/// 事件类型：上下文即将过期
+pub const EVENT_CONTEXT_EXPIRING: &str = "context_expiring";
+/// 事件类型：配额用量达到预警阈值
+pub const EVENT_QUOTA_WARNING: &str = "quota_warning";
+
+/// 事件类型请求头
+pub const EVENT_HEADER: &str = "x-penlai-event";
+/// 模式版本请求头
+pub const SCHEMA_VERSION_HEADER: &str = "x-penlai-schema-version";
+/// 投递ID请求头，重试时不变，消费方据此去重
+pub const DELIVERY_HEADER: &str = "x-penlai-delivery";
+
+/// 一个事件类型某一版本的JSON模式
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EventSchema {
+    pub event_type: String,
+    pub version: u32,
+    pub schema: Value,      // 整个事件（含信封字段）的JSON Schema
+}
+
+/// 事件信封字段的模式，data为具体事件的模式
+fn envelope_schema(event_type: &str, version: u32, data: Value) -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": format!("https://penlai.dev/schemas/events/{}/v{}.json", event_type, version),
+        "type": "object",
+        "required": ["id", "event_type", "schema_version", "created_at", "subject", "data"],
+        "properties": {
+            "id": { "type": "string" },
+            "event_type": { "const": event_type },
+            "schema_version": { "const": version },
+            "created_at": { "type": "string" },
+            "subject": { "type": "string" },
+            "data": data
+        }
+    })
+}
+
+/// 全部已发布的事件模式；同一事件类型的新版本追加在后，旧版本保留供消费方迁移
+pub fn event_schemas() -> Vec<EventSchema> {
+    let usage_report_v1 = json!({
+        "type": "object",
+        "required": ["generated_at", "range", "requests", "latency", "top_domains", "token_spend", "stale_contexts", "alerts"],
+        "properties": {
+            "generated_at": { "type": "string" },
+            "range": {
+                "type": "object",
+                "required": ["start", "end"],
+                "properties": { "start": { "type": "string" }, "end": { "type": "string" } }
+            },
+            "requests": {
+                "type": "object",
+                "required": ["processed", "failed", "rate_limited", "error_rate"],
+                "properties": {
+                    "processed": { "type": "integer" },
+                    "failed": { "type": "integer" },
+                    "rate_limited": { "type": "integer" },
+                    "error_rate": { "type": "number" }
+                }
+            },
+            "latency": {
+                "type": "object",
+                "required": ["p50_ms", "p95_ms", "p99_ms", "max_ms"],
+                "properties": {
+                    "p50_ms": { "type": "number" },
+                    "p95_ms": { "type": "number" },
+                    "p99_ms": { "type": "number" },
+                    "max_ms": { "type": "number" }
+                }
+            },
+            "top_domains": { "type": "array", "items": { "type": "object" } },
+            "token_spend": {
+                "type": "object",
+                "required": ["total_tokens", "by_domain", "estimated_cost"],
+                "properties": {
+                    "total_tokens": { "type": "integer" },
+                    "by_domain": { "type": "object" },
+                    "estimated_cost": { "type": "number" }
+                }
+            },
+            "stale_contexts": { "type": "integer" },
+            "alerts": {
+                "type": "object",
+                "required": ["fired", "resolved", "open", "open_metrics"],
+                "properties": {
+                    "fired": { "type": "integer" },
+                    "resolved": { "type": "integer" },
+                    "open": { "type": "integer" },
+                    "open_metrics": { "type": "array", "items": { "type": "string" } }
+                }
+            }
+        }
+    });
+    let context_expiring_v1 = json!({
+        "type": "object",
+        "required": ["expiring"],
+        "properties": {
+            "expiring": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["context_id", "user_id", "domain", "priority", "expires_at"],
+                    "properties": {
+                        "context_id": { "type": "string" },
+                        "user_id": { "type": "string" },
+                        "domain": { "type": "string" },
+                        "priority": { "type": "integer" },
+                        "expires_at": { "type": "string" }
+                    }
+                }
+            }
+        }
+    });
+    let quota_warning_v1 = json!({
+        "type": "object",
+        "required": ["kind", "scope", "key", "used", "limit", "percent", "period", "threshold_percent"],
+        "properties": {
+            "kind": { "enum": ["rate_limit", "token_budget", "search_budget"] },
+            "scope": { "enum": ["Provider", "User", "Domain"] },
+            "key": { "type": "string" },
+            "used": { "type": "integer" },
+            "limit": { "type": "integer" },
+            "percent": { "type": "number" },
+            "period": { "type": "string" },
+            "threshold_percent": { "type": "integer" }
+        }
+    });
+    [
+        (EVENT_USAGE_REPORT, 1, usage_report_v1),
+        (EVENT_CONTEXT_EXPIRING, 1, context_expiring_v1),
+        (EVENT_QUOTA_WARNING, 1, quota_warning_v1),
+    ]
+        .into_iter()
+        .map(|(event_type, version, data)| EventSchema {
+            event_type: event_type.to_string(),
+            version,
+            schema: envelope_schema(event_type, version, data),
+        })
+        .collect()
+}
+
+/// 事件类型的当前（最新）模式
+pub fn current_schema(event_type: &str) -> Option<EventSchema> {
+    event_schemas()
+        .into_iter()
+        .filter(|schema| schema.event_type == event_type)
+        .max_by_key(|schema| schema.version)
+}
+
+/// 按JSON Schema的子集（type、const、enum、required、properties、items）校验，返回第一个不符合处的路径和原因
+pub fn validate_against(schema: &Value, value: &Value) -> Result<(), String> {
+    validate_at(schema, value, "$")
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        let matches = match expected {
+            "object" => value.is_object(),
+            "array" => value.is_array(),
+            "string" => value.is_string(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "null" => value.is_null(),
+            _ => true,
+        };
+        if !matches {
+            return Err(format!("{}: expected {}", path, expected));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            return Err(format!("{}: expected {}", path, expected));
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(format!("{}: value not allowed", path));
+        }
+    }
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for field in required.iter().filter_map(Value::as_str) {
+            if value.get(field).is_none() {
+                return Err(format!("{}: missing required field '{}'", path, field));
+            }
+        }
+    }
+    if let (Some(properties), Some(object)) = (schema.get("properties").and_then(Value::as_object), value.as_object()) {
+        for (field, field_schema) in properties {
+            if let Some(field_value) = object.get(field) {
+                validate_at(field_schema, field_value, &format!("{}.{}", path, field))?;
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (index, item) in array.iter().enumerate() {
+            validate_at(items, item, &format!("{}[{}]", path, index))?;
+        }
+    }
+    Ok(())
+}
+
+/// 推送给Webhook的事件
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct WebhookEvent {
+    pub id: Uuid,                   // 事件ID，重试和重新投递时不变
+    pub event_type: String,
+    pub schema_version: u32,        // 事件符合的模式版本
+    pub created_at: DateTime<Utc>,
+    pub subject: String,
+    pub data: Value,                // 事件内容
+}
+
+impl WebhookEvent {
+    /// 由通知生成事件，并按事件类型的当前模式校验；未登记模式或不符合模式的事件不会发出
+    pub fn from_notification(notification: &Notification) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let schema = current_schema(&notification.event_type)
+            .ok_or_else(|| format!("No schema registered for event type '{}'", notification.event_type))?;
+        let event = Self {
+            id: Uuid::new_v4(),
+            event_type: notification.event_type.clone(),
+            schema_version: schema.version,
+            created_at: Utc::now(),
+            subject: notification.subject.clone(),
+            data: notification.payload.clone(),
+        };
+        validate_against(&schema.schema, &serde_json::to_value(&event)?)
+            .map_err(|reason| format!("Event '{}' does not match schema v{}: {}", event.event_type, schema.version, reason))?;
+        Ok(event)
+    }
+}
+
+/// 投递重试策略（指数退避）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,          // 含首次投递
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第attempt次失败后的等待时间
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u64 << attempt.saturating_sub(1).min(16);
+        Duration::from_millis(self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms))
+    }
+}
+
+/// 重试耗尽或被消费方拒绝的投递
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DeadLetter {
+    pub event: WebhookEvent,
+    pub url: String,
+    pub attempts: u32,
+    pub last_error: String,
+    pub failed_at: DateTime<Utc>,
+}
+
+/// 死信文件中的一行：新增的死信，或移除某条死信的记录
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum DeadLetterLogEntry {
+    Added(Box<DeadLetter>),
+    Removed { removed: Uuid },
+}
+
+/// 死信存储，超过容量时淘汰最早的记录；指定文件时变更以追加方式写入，记录行数超过容量两倍时压缩重写
+pub struct DeadLetterStore {
+    entries: Arc<RwLock<VecDeque<DeadLetter>>>,
+    max_entries: usize,
+    path: Option<PathBuf>,
+    write_lock: Mutex<()>,          // 串行化文件写入，内存中的读取不受文件写入阻塞
+    log_lines: AtomicUsize,         // 文件中的记录行数
+}
+
+impl DeadLetterStore {
+    /// 创建内存中的死信存储
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            entries: Arc::new(RwLock::new(VecDeque::new())),
+            max_entries: max_entries.max(1),
+            path: None,
+            write_lock: Mutex::new(()),
+            log_lines: AtomicUsize::new(0),
+        }
+    }
+
+    /// 打开以JSONL文件持久化的死信存储，重放已有记录
+    pub fn open(path: impl AsRef<Path>, max_entries: usize) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.as_ref().to_path_buf();
+        let mut store = Self::new(max_entries);
+        if path.exists() {
+            let mut entries = VecDeque::new();
+            let mut lines = 0;
+            for (line_no, line) in BufReader::new(std::fs::File::open(&path)?).lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                lines += 1;
+                match serde_json::from_str::<DeadLetterLogEntry>(&line) {
+                    Ok(DeadLetterLogEntry::Added(letter)) => {
+                        entries.retain(|existing: &DeadLetter| existing.event.id != letter.event.id);
+                        entries.push_back(*letter);
+                    }
+                    Ok(DeadLetterLogEntry::Removed { removed }) => entries.retain(|existing| existing.event.id != removed),
+                    Err(e) => log::warn!("Skipping malformed dead letter line {}: {}", line_no + 1, e),
+                }
+            }
+            while entries.len() > store.max_entries {
+                entries.pop_front();
+            }
+            store.entries = Arc::new(RwLock::new(entries));
+            store.log_lines = AtomicUsize::new(lines);
+        }
+        store.path = Some(path);
+        Ok(store)
+    }
+
+    /// 追加一行记录，需持有写文件锁；记录过多时改为按当前内容压缩重写（写入失败只记录日志）
+    async fn persist(&self, entry: DeadLetterLogEntry) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        let result = async {
+            if self.log_lines.load(Ordering::SeqCst) >= self.max_entries * 2 {
+                let mut contents = String::new();
+                let entries: Vec<DeadLetter> = self.entries.read().await.iter().cloned().collect();
+                for letter in entries {
+                    contents.push_str(&serde_json::to_string(&DeadLetterLogEntry::Added(Box::new(letter)))?);
+                    contents.push('\n');
+                }
+                let tmp = path.with_extension("tmp");
+                tokio::fs::write(&tmp, contents).await?;
+                tokio::fs::rename(&tmp, path).await?;
+                self.log_lines.store(self.entries.read().await.len(), Ordering::SeqCst);
+                return Ok(());
+            }
+            let mut line = serde_json::to_string(&entry)?;
+            line.push('\n');
+            let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+            file.write_all(line.as_bytes()).await?;
+            file.flush().await?;
+            self.log_lines.fetch_add(1, Ordering::SeqCst);
+            Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+        }
+        .await;
+        if let Err(e) = result {
+            log::warn!("Failed to persist webhook dead letters to {}: {}", path.display(), e);
+        }
+    }
+
+    /// 记录一条死信
+    pub async fn push(&self, letter: DeadLetter) {
+        let _guard = self.write_lock.lock().await;
+        {
+            let mut entries = self.entries.write().await;
+            entries.retain(|existing| existing.event.id != letter.event.id);
+            entries.push_back(letter.clone());
+            while entries.len() > self.max_entries {
+                entries.pop_front();
+            }
+        }
+        self.persist(DeadLetterLogEntry::Added(Box::new(letter))).await;
+    }
+
+    /// 全部死信（从旧到新）
+    pub async fn list(&self) -> Vec<DeadLetter> {
+        self.entries.read().await.iter().cloned().collect()
+    }
+
+    /// 按事件ID查找死信
+    pub async fn get(&self, event_id: Uuid) -> Option<DeadLetter> {
+        self.entries.read().await.iter().find(|letter| letter.event.id == event_id).cloned()
+    }
+
+    /// 取出（移除）一条死信
+    pub async fn take(&self, event_id: Uuid) -> Option<DeadLetter> {
+        let _guard = self.write_lock.lock().await;
+        let letter = {
+            let mut entries = self.entries.write().await;
+            let index = entries.iter().position(|letter| letter.event.id == event_id)?;
+            entries.remove(index)
+        };
+        self.persist(DeadLetterLogEntry::Removed { removed: event_id }).await;
+        letter
+    }
+}
+
+/// 单次投递的失败
+struct AttemptFailure {
+    message: String,
+    retryable: bool,    // 网络错误、5xx和429可重试，其他4xx表示消费方拒绝该事件
+}
+
+/// 事件投递器：签名、重试，失败后写入死信
+pub struct WebhookDelivery {
+    client: reqwest::Client,
+    secret: Option<String>,
+    retry_policy: RetryPolicy,
+    dead_letters: Arc<DeadLetterStore>,
+}
+
+impl Default for WebhookDelivery {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookDelivery {
+    /// 创建投递器（不签名，默认重试策略，内存中的死信存储）
+    pub fn new() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            secret: None,
+            retry_policy: RetryPolicy::default(),
+            dead_letters: Arc::new(DeadLetterStore::new(1000)),
+        }
+    }
+
+    /// 设置签名密钥：对`<时间戳>.<请求体>`计算HMAC-SHA256放在签名请求头中，时间戳放在时间戳请求头中
+    pub fn with_secret(mut self, secret: &str) -> Self {
+        self.secret = Some(secret.to_string());
+        self
+    }
+
+    /// 设置重试策略
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// 设置死信存储
+    pub fn with_dead_letters(mut self, dead_letters: Arc<DeadLetterStore>) -> Self {
+        self.dead_letters = dead_letters;
+        self
+    }
+
+    /// 获取死信存储
+    pub fn get_dead_letters(&self) -> Arc<DeadLetterStore> {
+        self.dead_letters.clone()
+    }
+
+    async fn attempt(&self, url: &str, event: &WebhookEvent, body: &[u8]) -> Result<(), AttemptFailure> {
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &event.event_type)
+            .header(SCHEMA_VERSION_HEADER, event.schema_version.to_string())
+            .header(DELIVERY_HEADER, event.id.to_string());
+        if let Some(ref secret) = self.secret {
+            let timestamp = Utc::now().timestamp();
+            request = request
+                .header(TIMESTAMP_HEADER, timestamp.to_string())
+                .header(SIGNATURE_HEADER, sign_timestamped(secret, timestamp, body));
+        }
+        let response = request
+            .body(body.to_vec())
+            .send()
+            .await
+            .map_err(|e| AttemptFailure { message: e.to_string(), retryable: true })?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(());
+        }
+        let text = response.text().await.unwrap_or_default();
+        Err(AttemptFailure {
+            message: format!("Webhook delivery failed ({}): {}", status, text),
+            retryable: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+        })
+    }
+
+    /// 投递事件，返回成功时的尝试次数；重试耗尽或被拒绝时写入死信并返回错误
+    pub async fn deliver(&self, url: &str, event: &WebhookEvent) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        let body = serde_json::to_vec(event)?;
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempts = 0;
+        let last_error = loop {
+            attempts += 1;
+            match self.attempt(url, event, &body).await {
+                Ok(()) => return Ok(attempts),
+                Err(failure) if failure.retryable && attempts < max_attempts => {
+                    log::debug!("Webhook attempt {} for event {} failed, retrying: {}", attempts, event.id, failure.message);
+                    tokio::time::sleep(self.retry_policy.backoff(attempts)).await;
+                }
+                Err(failure) => break failure.message,
+            }
+        };
+        self.dead_letters
+            .push(DeadLetter {
+                event: event.clone(),
+                url: url.to_string(),
+                attempts,
+                last_error: last_error.clone(),
+                failed_at: Utc::now(),
+            })
+            .await;
+        Err(format!("Event {} moved to dead letters after {} attempt(s): {}", event.id, attempts, last_error).into())
+    }
+
+    /// 重新投递一条死信；再次失败时重新写入死信
+    pub async fn redeliver(&self, event_id: Uuid) -> Result<u32, Box<dyn std::error::Error + Send + Sync>> {
+        let letter = self
+            .dead_letters
+            .take(event_id)
+            .await
+            .ok_or_else(|| format!("Dead letter {} not found", event_id))?;
+        self.deliver(&letter.url, &letter.event).await
+    }
+
+    /// 取出一条死信并在后台重新投递（按重试策略退避），死信不存在时返回None；再次失败时重新写入死信
+    pub async fn enqueue_redelivery(self: &Arc<Self>, event_id: Uuid) -> Option<JoinHandle<Result<u32, Box<dyn std::error::Error + Send + Sync>>>> {
+        let letter = self.dead_letters.take(event_id).await?;
+        let delivery = self.clone();
+        Some(tokio::spawn(async move {
+            let result = delivery.deliver(&letter.url, &letter.event).await;
+            if let Err(ref e) = result {
+                log::warn!("Redelivery of event {} failed: {}", event_id, e);
+            }
+            result
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::Router;
+    use crate::context::ingestion::verify_timestamped;
+    use crate::monitoring::notify::{AlertSink, WebhookSink};
+
+    #[tokio::test]
+    async fn test_signed_delivery_retries_and_dead_letters() {
+        // 消费方：前两次返回503，之后校验签名和模式版本
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(move |headers: HeaderMap, body: axum::body::Bytes| {
+                    let counter = counter.clone();
+                    async move {
+                        if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                            return StatusCode::SERVICE_UNAVAILABLE;
+                        }
+                        let signature = headers[SIGNATURE_HEADER].to_str().unwrap();
+                        let timestamp: i64 = headers[TIMESTAMP_HEADER].to_str().unwrap().parse().unwrap();
+                        assert!(verify_timestamped("s3cret", timestamp, &body, signature));
+                        assert!(!verify_timestamped("s3cret", timestamp + 1, &body, signature));
+                        assert_eq!(headers[SCHEMA_VERSION_HEADER], "1");
+                        let event: WebhookEvent = serde_json::from_slice(&body).unwrap();
+                        assert_eq!(headers[DELIVERY_HEADER].to_str().unwrap(), event.id.to_string());
+                        StatusCode::NO_CONTENT
+                    }
+                }),
+            )
+            .route("/reject", post(|| async { StatusCode::BAD_REQUEST }));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        let path = std::env::temp_dir().join(format!("penlai-dead-letters-{}.jsonl", Uuid::new_v4()));
+        let dead_letters = Arc::new(DeadLetterStore::open(&path, 10).unwrap());
+        let delivery = Arc::new(
+            WebhookDelivery::new()
+                .with_secret("s3cret")
+                .with_retry_policy(RetryPolicy { max_attempts: 3, initial_backoff_ms: 10, max_backoff_ms: 50 })
+                .with_dead_letters(dead_letters.clone()),
+        );
+        let notification = Notification {
+            event_type: EVENT_CONTEXT_EXPIRING.to_string(),
+            subject: "1 context(s) expiring soon".to_string(),
+            body: String::new(),
+            payload: json!({ "expiring": [{
+                "context_id": Uuid::new_v4(), "user_id": "u1", "domain": "medical", "priority": 8, "expires_at": Utc::now()
+            }] }),
+        };
+
+        // 两次5xx后第三次成功
+        let sink = WebhookSink::new(&format!("http://{}/hook", addr)).with_delivery(delivery.clone());
+        sink.send(&notification).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // 不符合模式的事件不会发出
+        let malformed = Notification { payload: json!({ "expiring": [{ "context_id": 1 }] }), ..notification.clone() };
+        let error = sink.send(&malformed).await.unwrap_err().to_string();
+        assert!(error.contains("$.data.expiring[0]"), "{}", error);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(sink.send(&Notification { event_type: "unknown".to_string(), ..notification.clone() }).await.is_err());
+
+        // 4xx不重试，直接进入死信，并在重启后仍可查到
+        let rejecting = WebhookSink::new(&format!("http://{}/reject", addr)).with_delivery(delivery.clone());
+        assert!(rejecting.send(&notification).await.is_err());
+        let letters = DeadLetterStore::open(&path, 10).unwrap().list().await;
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].attempts, 1);
+
+        // 重新投递到可用地址后移出死信
+        let mut letter = dead_letters.take(letters[0].event.id).await.unwrap();
+        letter.url = format!("http://{}/hook", addr);
+        dead_letters.push(letter.clone()).await;
+        assert_eq!(delivery.enqueue_redelivery(letter.event.id).await.unwrap().await.unwrap().unwrap(), 1);
+        assert!(dead_letters.list().await.is_empty());
+        assert!(DeadLetterStore::open(&path, 10).unwrap().list().await.is_empty());
+        assert!(delivery.enqueue_redelivery(letter.event.id).await.is_none());
+        assert!(delivery.redeliver(letter.event.id).await.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
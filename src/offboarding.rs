@@ -0,0 +1,242 @@
+//! 用户交接与数据删除 - 将离职用户的上下文转交给其他用户，或按删除请求清除用户在上下文、索引、缓存、限流状态、
+//! 搜索预算计数、反馈记录、监控事件和审计日志中的数据；两者均支持只报告影响范围、不做修改的演练模式
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::app::Penlai;
+use crate::context::audit::AuditAction;
+
+/// 审计记录的操作人
+const OFFBOARDING_ACTOR: &str = "offboarding";
+
+/// 删除用户数据时审计日志的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditRetention {
+    Retain,         // 合规要求保留原始记录
+    #[default]
+    Pseudonymize,   // 保留审计轨迹，操作人和涉及的用户替换为假名
+    Delete,         // 删除关联到该用户的记录
+}
+
+/// 删除选项
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PurgeOptions {
+    pub dry_run: bool,              // 只统计影响范围
+    pub audit: AuditRetention,
+}
+
+/// 转交结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransferReport {
+    pub from_user: String,
+    pub to_user: String,
+    pub dry_run: bool,
+    pub context_ids: Vec<Uuid>,     // 转交（演练时为将被转交）的上下文
+}
+
+/// 删除结果（演练时为将被删除的数量）
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PurgeReport {
+    pub user_id: String,
+    pub dry_run: bool,
+    pub context_ids: Vec<Uuid>,             // 删除的上下文（连同其版本历史）
+    pub selection_cache_entries: usize,     // 选择结果缓存项
+    pub ranking_profile: bool,              // 是否有个性化排序偏好
+    pub rate_limit_state: bool,             // 是否有速率限制计数
+    pub search_budget_state: bool,          // 是否有当日的搜索预算计数
+    pub access_memberships: bool,           // 是否有团队或租户成员关系
+    pub feedback_requests: usize,           // 来源信息和评分记录（按请求）
+    pub monitoring_events: usize,           // 监控事件日志中的事件
+    pub audit_entries: usize,               // 关联到该用户（作为操作人或涉及的用户）的审计记录
+    pub audit_retention: AuditRetention,
+    pub pseudonym: Option<String>,          // 审计记录中替换使用的假名
+}
+
+impl Penlai {
+    /// 将from_user的全部上下文转交给to_user
+    pub async fn transfer_contexts(
+        &self,
+        from_user: &str,
+        to_user: &str,
+        dry_run: bool,
+    ) -> Result<TransferReport, Box<dyn std::error::Error + Send + Sync>> {
+        let context_ids = self.context_manager.transfer_contexts(from_user, to_user, dry_run).await?;
+        if !dry_run {
+            // 只清除两人的缓存选择结果，离职用户的排序偏好由数据删除流程处理
+            self.context_selector.clear_user_cache(from_user).await;
+            self.context_selector.clear_user_cache(to_user).await;
+            if let Some(approval) = self.context_manager.get_approval_workflow() {
+                // 用户ID记录在结构化字段中，删除用户数据时可一并假名化或删除
+                approval
+                    .get_audit_log()
+                    .record_with_subjects(
+                        Uuid::nil(),
+                        OFFBOARDING_ACTOR,
+                        AuditAction::ContextsTransferred,
+                        None,
+                        None,
+                        Some(format!("{} context(s) transferred", context_ids.len())),
+                        vec![from_user.to_string(), to_user.to_string()],
+                    )
+                    .await;
+            }
+        }
+        Ok(TransferReport {
+            from_user: from_user.to_string(),
+            to_user: to_user.to_string(),
+            dry_run,
+            context_ids,
+        })
+    }
+
+    /// 删除用户的全部数据；上下文先于其他状态删除，只读副本上直接失败而不留下部分删除的状态
+    pub async fn purge_user(&self, user_id: &str, options: PurgeOptions) -> Result<PurgeReport, Box<dyn std::error::Error + Send + Sync>> {
+        if user_id.is_empty() {
+            return Err("User ID must not be empty".into());
+        }
+        let dry_run = options.dry_run;
+        let context_ids = self.context_manager.purge_user_contexts(user_id, dry_run).await?;
+        let (selection_cache_entries, ranking_profile) = self.context_selector.purge_user_data(user_id, dry_run).await;
+        let rate_limit_state = self.request_processor.purge_rate_limit_state(user_id, dry_run).await;
+        let search_budget_state = self.search_budget.purge_user(user_id, dry_run).await;
+        let access_memberships = self.context_manager.get_access_directory().remove_user(user_id, dry_run).await;
+        let feedback_requests = self.request_processor.get_feedback_store().purge_user(user_id, dry_run).await?;
+        let monitoring_events = self.monitoring.purge_user_events(user_id, dry_run).await;
+
+        let audit_log = self.context_manager.get_approval_workflow().map(|approval| approval.get_audit_log());
+        let pseudonym = format!("erased-{}", Uuid::new_v4().simple());
+        let audit_entries = match (&audit_log, options.audit) {
+            (None, _) => 0,
+            (Some(log), AuditRetention::Retain) => log.entries_for_user(user_id).await.len(),
+            (Some(log), AuditRetention::Pseudonymize) => log.pseudonymize_user(user_id, &pseudonym, dry_run).await,
+            (Some(log), AuditRetention::Delete) => log.remove_user(user_id, dry_run).await,
+        };
+        if let (Some(log), false) = (&audit_log, dry_run) {
+            log.record(
+                Uuid::nil(),
+                OFFBOARDING_ACTOR,
+                AuditAction::UserErased,
+                None,
+                None,
+                Some(format!("Erased data of {} ({} context(s))", pseudonym, context_ids.len())),
+            )
+            .await;
+        }
+
+        Ok(PurgeReport {
+            user_id: user_id.to_string(),
+            dry_run,
+            context_ids,
+            selection_cache_entries,
+            ranking_profile,
+            rate_limit_state,
+            search_budget_state,
+            access_memberships,
+            feedback_requests,
+            monitoring_events,
+            audit_entries,
+            audit_retention: options.audit,
+            pseudonym: (options.audit == AuditRetention::Pseudonymize && audit_log.is_some()).then_some(pseudonym),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::approval::{ApprovalPolicy, ApprovalWorkflow};
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::feedback::FeedbackStore;
+    use crate::processing::concurrent_processor::RequestProcessor;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::selection::personalization::RankingWeights;
+    use crate::utils::provider_health::BING_PROVIDER;
+
+    #[tokio::test]
+    async fn test_transfer_and_purge_user() {
+        let mut app = Penlai::new(10, 3600);
+        let approval = Arc::new(ApprovalWorkflow::new(ApprovalPolicy::default().require_domain("legal")));
+        let manager = Arc::new(ContextManager::new(10, 3600).with_approval_workflow(approval.clone()));
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let log_path = std::env::temp_dir().join(format!("penlai-offboarding-{}.jsonl", Uuid::new_v4()));
+        let processor = RequestProcessor::new(manager.clone(), selector.clone())
+            .with_feedback_store(Arc::new(FeedbackStore::open(&log_path, 100).unwrap()));
+        app.context_manager = manager.clone();
+        app.context_selector = selector.clone();
+        app.request_processor = Arc::new(processor);
+
+        // 离职用户的上下文转交给继任者
+        let handover = manager
+            .create_context("s1".to_string(), "leaver".to_string(), "medical".to_string(), "Ward rota for pneumonia patients".to_string(), 5)
+            .await
+            .unwrap();
+        selector.get_profile_store().set_profile("leaver", RankingWeights::default()).await.unwrap();
+        let dry = app.transfer_contexts("leaver", "successor", true).await.unwrap();
+        assert_eq!(dry.context_ids, vec![handover.id]);
+        assert_eq!(manager.get_user_contexts("leaver").await.len(), 1);
+        app.transfer_contexts("leaver", "successor", false).await.unwrap();
+        assert!(manager.get_user_contexts("leaver").await.is_empty());
+        assert_eq!(manager.get_user_contexts("successor").await[0].id, handover.id);
+        // 转交不影响离职用户的排序偏好
+        assert!(selector.get_profile_store().get_profile("leaver").await.is_some());
+        assert!(app.transfer_contexts("successor", "successor", false).await.is_err());
+
+        // 要删除的用户在各组件中留下的数据
+        let draft = manager
+            .create_context("s2".to_string(), "alice".to_string(), "legal".to_string(), "Alice's tenancy dispute notes".to_string(), 5)
+            .await
+            .unwrap();
+        manager.submit_for_review(draft.id, "alice").await.unwrap();
+        selector.get_profile_store().set_profile("alice", RankingWeights::default()).await.unwrap();
+        manager.get_access_directory().add_team_member("litigation", "alice").await;
+        let result = app
+            .request_processor
+            .process_request("alice".to_string(), "s2".to_string(), "tenancy dispute".to_string(), "legal".to_string())
+            .await
+            .unwrap();
+        app.request_processor.record_answer_feedback(result.request_id, 4, Some("helpful".to_string())).await.unwrap();
+        app.search_budget.try_consume(BING_PROVIDER, Some("alice"), Some("legal"), None).await.unwrap();
+
+        // ID以该用户ID加分隔符开头的其他用户不受影响
+        selector.select_contexts("alice:ops", "s9", "tenancy dispute", "legal").await.unwrap();
+
+        // 演练只报告影响范围
+        let preview = app.purge_user("alice", PurgeOptions { dry_run: true, ..PurgeOptions::default() }).await.unwrap();
+        assert_eq!(preview.context_ids, vec![draft.id]);
+        assert!(preview.ranking_profile && preview.rate_limit_state && preview.search_budget_state && preview.access_memberships);
+        assert_eq!(preview.feedback_requests, 1);
+        assert_eq!(preview.audit_entries, 2);
+        assert!(manager.get_context(draft.id).await.is_some());
+
+        let report = app.purge_user("alice", PurgeOptions::default()).await.unwrap();
+        assert_eq!(report.context_ids, vec![draft.id]);
+        assert!(manager.get_context(draft.id).await.is_none());
+        assert!(selector.get_profile_store().get_profile("alice").await.is_none());
+        assert_eq!(selector.purge_user_data("alice:ops", true).await.0, 1);
+        assert!(!app.request_processor.purge_rate_limit_state("alice", true).await);
+        assert!(!app.search_budget.get_usage().await.by_user.contains_key("alice"));
+        assert!(manager.get_access_directory().teams_of("alice").await.is_empty());
+        assert!(app.request_processor.get_feedback_store().get_provenance(result.request_id).await.is_none());
+        assert!(!std::fs::read_to_string(&log_path).unwrap().contains("alice"));
+
+        // 审计轨迹保留，但不再关联到该用户
+        let audit = approval.get_audit_log();
+        assert!(audit.entries_by_actor("alice").await.is_empty());
+        assert_eq!(audit.entries_by_actor(report.pseudonym.as_deref().unwrap()).await.len(), 2);
+        assert_eq!(audit.get_recent(1).await[0].action, AuditAction::UserErased);
+
+        // 转交记录中的用户ID在删除时一并假名化，备注中不含用户ID
+        let report = app.purge_user("leaver", PurgeOptions::default()).await.unwrap();
+        assert_eq!(report.audit_entries, 1);
+        assert!(audit.entries_for_user("leaver").await.is_empty());
+        let pseudonym = report.pseudonym.unwrap();
+        let transfer = audit.entries_for_user(&pseudonym).await.remove(0);
+        assert_eq!(transfer.action, AuditAction::ContextsTransferred);
+        assert_eq!(transfer.subjects, vec![pseudonym.clone(), "successor".to_string()]);
+        assert!(audit.get_recent(usize::MAX).await.iter().all(|entry| !entry.note.as_deref().unwrap_or_default().contains("leaver")));
+        std::fs::remove_file(&log_path).unwrap();
+    }
+}
@@ -4,7 +4,28 @@ use tokio::time::{timeout, Duration};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
 use crate::context::llm_context::{ContextManager, LLMContext};
+use crate::context::context_loader::ContextLoader;
+use crate::context::consistency::ConsistencyLevel;
+use crate::context::replica::ReplicaSync;
+use crate::maintenance::MaintenanceMode;
+use crate::domain::domain_classifier::{DomainClassifier, DomainRouting};
+use crate::domain::keyword_store::KeywordStore;
 use crate::selection::async_context_selector::ContextSelector;
+use crate::processing::token_budget::{BudgetDecision, TokenBudgetManager};
+use crate::processing::feedback::{AnswerFeedback, FeedbackError, FeedbackStore, RequestProvenance};
+use crate::processing::context_packing::pack_contexts;
+use crate::processing::cost_estimate::{CostEstimate, CostPricing};
+use crate::processing::response_constraints::ResponseConstraints;
+use crate::processing::shadow::{PrimaryOutcome, ShadowPipeline};
+use crate::processing::safety_policy::{PolicyVerdict, SafetyPolicyEngine};
+use crate::processing::stage_budget::{StageBudget, StageBudgetWeights};
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem, RequestStage};
+use crate::utils::ai_client::TextGenerator;
+use crate::utils::outbound_scheduler::CallPriority;
+use crate::utils::request_context::{RequestContext, FLAG_ANSWER_GENERATION};
+use crate::utils::tokenizer::{Tokenizer, WhitespaceTokenizer};
+use crate::utils::search_budget::{BudgetScope, SearchBudget};
+use crate::monitoring::quota::{QuotaKind, QuotaUsage, QuotaWarner};
 
 /// 请求处理配置
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -13,8 +34,21 @@ pub struct RequestProcessorConfig {
     pub request_timeout_seconds: u64,        // 请求超时时间（秒）
     pub context_load_timeout_seconds: u64,   // 上下文加载超时时间（秒）
     pub context_selection_timeout_seconds: u64, // 上下文选择超时时间（秒）
+    pub generation_timeout_seconds: u64,     // 回答生成超时时间（秒）
     pub enable_rate_limiting: bool,          // 是否启用速率限制
     pub max_requests_per_minute: u32,        // 每分钟最大请求数
+    #[serde(default = "default_max_context_tokens")]
+    pub max_context_tokens: usize,           // 装入提示词的上下文令牌上限（超出时在句子边界截断，0为不限制）
+    #[serde(default)]
+    pub stage_budget_weights: StageBudgetWeights, // 各阶段分得请求剩余时间的权重，阶段超时取该份额与阶段默认超时中的较小者
+    #[serde(default)]
+    pub default_consistency: ConsistencyLevel, // 请求未指定时的读一致性级别（只在以副本运行时有区别）
+    #[serde(default)]
+    pub pricing: CostPricing,                // 估算请求费用的模型计价表
+}
+
+fn default_max_context_tokens() -> usize {
+    3000
 }
 
 impl Default for RequestProcessorConfig {
@@ -24,21 +58,104 @@ impl Default for RequestProcessorConfig {
             request_timeout_seconds: 30,
             context_load_timeout_seconds: 10,
             context_selection_timeout_seconds: 5,
+            generation_timeout_seconds: 20,
             enable_rate_limiting: true,
             max_requests_per_minute: 1000,
+            max_context_tokens: default_max_context_tokens(),
+            stage_budget_weights: StageBudgetWeights::default(),
+            default_consistency: ConsistencyLevel::default(),
+            pricing: CostPricing::default(),
         }
     }
 }
 
+/// 请求中表示由处理器自动判断领域的取值（空字符串同样表示自动判断）
+pub const AUTO_DOMAIN: &str = "auto";
+
+/// 路由结果中最多给出的备选领域数
+const MAX_RUNNER_UP_DOMAINS: usize = 2;
+
+/// 速率限制的统计周期（滚动的一分钟窗口）
+const RATE_LIMIT_PERIOD: &str = "minute";
+
+/// 用户请求计数表：用户ID -> (窗口内请求数, 最近请求时间)
+type UserRequestCounts = std::collections::HashMap<String, (u32, chrono::DateTime<chrono::Utc>)>;
+
 /// 请求处理器 - 企业级大模型并发请求处理
 pub struct RequestProcessor {
     config: Arc<RwLock<RequestProcessorConfig>>,
+    #[allow(dead_code)]
     context_manager: Arc<ContextManager>,
     context_selector: Arc<ContextSelector>,
     /// 并发控制信号量
     request_semaphore: Arc<Semaphore>,
     /// 用户请求计数器（用于速率限制）
-    user_request_counts: Arc<RwLock<std::collections::HashMap<String, (u32, chrono::DateTime<chrono::Utc>)>>>,
+    user_request_counts: Arc<RwLock<UserRequestCounts>>,
+    /// 按领域的令牌预算
+    token_budget: Arc<TokenBudgetManager>,
+    /// 可选的上下文加载器，用于在前置检查期间预热领域上下文
+    context_loader: Option<Arc<ContextLoader>>,
+    /// 可选的监控系统，用于记录请求各阶段耗时
+    monitoring: Option<Arc<MonitoringSystem>>,
+    /// 可选的文本生成器，设置后在选择上下文之后生成回答
+    generator: Option<Arc<dyn TextGenerator>>,
+    /// 领域安全策略（免责声明和拒答规则）
+    safety_policy: Arc<SafetyPolicyEngine>,
+    /// 请求来源信息和回答反馈
+    feedback_store: Arc<FeedbackStore>,
+    /// 可选的影子管道，抽样镜像请求以验证备选配置
+    shadow: Option<Arc<ShadowPipeline>>,
+    /// 可选的关键词存储，自动判断领域时使用其中的最新关键词
+    keyword_store: Option<Arc<KeywordStore>>,
+    /// 未关联关键词存储时使用的分类器（创建时加载一次关键词文件）
+    classifier: Arc<DomainClassifier>,
+    /// 以只读副本运行时的快照同步任务，强一致读通过它与主实例同步
+    replica: Option<Arc<ReplicaSync>>,
+    /// 可选的维护模式，维护期间新请求排队或被拒绝
+    maintenance: Option<Arc<MaintenanceMode>>,
+    /// 提示词装填、回答截断和令牌计量使用的分词器
+    tokenizer: Arc<dyn Tokenizer>,
+    /// 可选的配额预警器，速率限制和令牌预算接近上限时预警
+    quota_warner: Option<Arc<QuotaWarner>>,
+    /// 可选的搜索预算，请求发起的付费搜索按其记录的次数计入费用
+    search_budget: Option<Arc<SearchBudget>>,
+}
+
+/// 请求选项
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestOptions {
+    pub constraints: ResponseConstraints, // 回答约束（长度、格式、语言、引用）
+    #[serde(default)]
+    pub as_of: Option<chrono::DateTime<chrono::Utc>>, // 按该时间点有效的上下文版本选择（需要启用版本历史）
+    #[serde(default)]
+    pub deadline_ms: Option<u64>,         // 调用方的时间预算（毫秒），与请求超时取较早者
+    #[serde(default)]
+    pub locale: Option<String>,           // 语言区域，回答约束未指定语言时按此语言回答
+    #[serde(default)]
+    pub tenant: Option<String>,           // 租户
+    #[serde(default)]
+    pub feature_flags: std::collections::BTreeMap<String, bool>, // 按请求覆盖的功能开关
+    #[serde(default)]
+    pub consistency: Option<ConsistencyLevel>, // 读一致性级别，缺省使用处理器配置
+    #[serde(default)]
+    pub dry_run: bool,                    // 试运行：选择上下文并估算费用，不生成回答也不记录令牌用量
+    #[serde(default)]
+    pub max_cost: Option<f64>,            // 预计费用上限，生成前的估算超出时拒绝请求
+}
+
+impl RequestOptions {
+    /// 构建请求上下文，截止时间为请求超时与调用方时间预算中的较早者
+    fn request_context(&self, request_id: Uuid, request_timeout: Duration, default_consistency: ConsistencyLevel) -> RequestContext {
+        let mut request = RequestContext::new(request_id).with_timeout(request_timeout);
+        if let Some(deadline_ms) = self.deadline_ms {
+            request = request.with_timeout(Duration::from_millis(deadline_ms));
+        }
+        request.locale = self.locale.clone();
+        request.tenant = self.tenant.clone();
+        request.feature_flags = self.feature_flags.clone();
+        request.consistency = self.consistency.unwrap_or(default_consistency);
+        request
+    }
 }
 
 impl RequestProcessor {
@@ -55,9 +172,144 @@ impl RequestProcessor {
             context_selector,
             request_semaphore: Arc::new(Semaphore::new(config.max_concurrent_requests)),
             user_request_counts: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            token_budget: Arc::new(TokenBudgetManager::new()),
+            context_loader: None,
+            monitoring: None,
+            generator: None,
+            safety_policy: Arc::new(SafetyPolicyEngine::new()),
+            feedback_store: Arc::new(FeedbackStore::default()),
+            shadow: None,
+            keyword_store: None,
+            classifier: Arc::new(DomainClassifier::new().unwrap_or_else(|_| DomainClassifier::with_default_keywords())),
+            replica: None,
+            maintenance: None,
+            tokenizer: Arc::new(WhitespaceTokenizer),
+            quota_warner: None,
+            search_budget: None,
+        }
+    }
+
+    /// 使用指定配置（替换默认配置，并发许可数按配置的并发上限设置）
+    pub fn with_config(mut self, config: RequestProcessorConfig) -> Self {
+        self.request_semaphore = Arc::new(Semaphore::new(config.max_concurrent_requests));
+        self.config = Arc::new(RwLock::new(config));
+        self
+    }
+
+    /// 关联关键词存储，自动判断领域时使用运行时调整后的关键词
+    pub fn with_keyword_store(mut self, keyword_store: Arc<KeywordStore>) -> Self {
+        self.keyword_store = Some(keyword_store);
+        self
+    }
+
+    /// 请求未指定领域时按查询文本判断领域
+    async fn route_domain(&self, query: &str) -> DomainRouting {
+        let scores = match self.keyword_store {
+            Some(ref store) => store.classifier().await.score_domains(query),
+            None => self.classifier.score_domains(query),
+        };
+        scores.routing(MAX_RUNNER_UP_DOMAINS)
+    }
+
+    /// 关联副本同步任务（实例以只读副本运行），强一致读在选择前与主实例同步
+    pub fn with_replica(mut self, replica: Arc<ReplicaSync>) -> Self {
+        self.replica = Some(replica);
+        self
+    }
+
+    /// 关联维护模式，维护期间新请求按设置排队等待或带重试提示拒绝
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 使用指定的分词器装填提示词和计量令牌（应与选择器和入库使用的分词器一致）
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// 关联配额预警器，速率限制和领域令牌预算的用量达到预警阈值时通知
+    pub fn with_quota_warner(mut self, quota_warner: Arc<QuotaWarner>) -> Self {
+        self.quota_warner = Some(quota_warner);
+        self
+    }
+
+    /// 关联搜索预算，请求选择上下文期间发起的付费搜索计入预计费用和费用上限检查
+    pub fn with_search_budget(mut self, search_budget: Arc<SearchBudget>) -> Self {
+        self.search_budget = Some(search_budget);
+        self
+    }
+
+    /// 取出请求已发起的付费搜索，返回各服务的(查询次数, 单次费用)
+    async fn take_request_searches(&self, request_id: Uuid) -> Vec<(u64, f64)> {
+        let Some(ref budget) = self.search_budget else {
+            return Vec::new();
+        };
+        let cost_per_query = budget.get_config().await.cost_per_query;
+        budget
+            .take_request_queries(request_id)
+            .await
+            .into_iter()
+            .map(|(provider, queries)| (queries, cost_per_query.get(&provider).copied().unwrap_or(0.0)))
+            .collect()
+    }
+
+    /// 按请求的一致性级别准备读取，返回副本所用快照的拉取时间（主实例上为None）
+    async fn prepare_read(&self, request: &RequestContext, stage_default: Duration) -> Result<Option<chrono::DateTime<chrono::Utc>>, RequestError> {
+        let Some(ref replica) = self.replica else {
+            return Ok(None);
+        };
+        match request.consistency {
+            ConsistencyLevel::Eventual => Ok(replica.get_status().await.snapshot_requested_at),
+            // 同步在独立任务中进行，超时只放弃等待，不会中断正在加载的快照
+            ConsistencyLevel::Strong => timeout(request.stage_timeout(stage_default), replica.spawn_sync_since(chrono::Utc::now()))
+                .await
+                .map_err(|_| RequestError::ConsistencyUnavailable("Timed out syncing with the primary".to_string()))?
+                .map_err(|e| RequestError::ConsistencyUnavailable(format!("Sync task failed: {}", e)))?
+                .map(Some)
+                .map_err(|e| RequestError::ConsistencyUnavailable(format!("Failed to sync with the primary: {}", e))),
         }
     }
 
+    /// 关联影子管道，抽中的请求在响应后于后台用备选配置重放
+    pub fn with_shadow_pipeline(mut self, shadow: Arc<ShadowPipeline>) -> Self {
+        self.shadow = Some(shadow);
+        self
+    }
+
+    /// 使用指定的反馈存储（如持久化到文件的存储）
+    pub fn with_feedback_store(mut self, feedback_store: Arc<FeedbackStore>) -> Self {
+        self.feedback_store = feedback_store;
+        self
+    }
+
+    /// 关联文本生成器，启用回答生成阶段
+    pub fn with_text_generator(mut self, generator: Arc<dyn TextGenerator>) -> Self {
+        self.generator = Some(generator);
+        self
+    }
+
+    /// 关联监控系统，按请求记录各处理阶段的耗时（可通过`get_request_trace`查询）
+    pub fn with_monitoring(mut self, monitoring: Arc<MonitoringSystem>) -> Self {
+        self.monitoring = Some(monitoring);
+        self
+    }
+
+    /// 记录请求阶段
+    async fn record_stage(&self, request_id: Uuid, stage: RequestStage, started: std::time::Instant, error: Option<String>) {
+        if let Some(ref monitoring) = self.monitoring {
+            let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+            monitoring.record_stage(request_id, stage, duration_ms, error.is_none(), error).await;
+        }
+    }
+
+    /// 关联上下文加载器，请求进入后立即在后台预热查询领域的上下文
+    pub fn with_context_loader(mut self, context_loader: Arc<ContextLoader>) -> Self {
+        self.context_loader = Some(context_loader);
+        self
+    }
+
     /// 处理大模型请求
     pub async fn process_request(
         &self,
@@ -66,51 +318,336 @@ impl RequestProcessor {
         query: String,
         domain: String,
     ) -> Result<RequestResult, RequestError> {
-        // 检查速率限制
-        if self.config.read().await.enable_rate_limiting {
-            self.check_rate_limit(&user_id).await?;
+        self.process_request_with_options(user_id, session_id, query, domain, RequestOptions::default()).await
+    }
+
+    /// 按请求选项处理大模型请求
+    pub async fn process_request_with_options(
+        &self,
+        user_id: String,
+        session_id: String,
+        query: String,
+        domain: String,
+        options: RequestOptions,
+    ) -> Result<RequestResult, RequestError> {
+        let request_id = Uuid::new_v4();
+        let started = std::time::Instant::now();
+        let result = self.handle_request(request_id, user_id, session_id, query, domain, options).await;
+        // 请求失败或超时时丢弃未计入费用的搜索次数
+        self.take_request_searches(request_id).await;
+        self.record_stage(request_id, RequestStage::Response, started, result.as_ref().err().map(|e| e.to_string())).await;
+        result
+    }
+
+    /// 执行前置检查并处理请求
+    async fn handle_request(
+        &self,
+        request_id: Uuid,
+        user_id: String,
+        session_id: String,
+        query: String,
+        domain: String,
+        options: RequestOptions,
+    ) -> Result<RequestResult, RequestError> {
+        // 未指定领域时由处理器判断，并在结果中给出置信度
+        let routing = if domain.is_empty() || domain.eq_ignore_ascii_case(AUTO_DOMAIN) {
+            let classification_started = std::time::Instant::now();
+            let routing = self.route_domain(&query).await;
+            self.record_stage(request_id, RequestStage::Classification, classification_started, None).await;
+            Some(routing)
+        } else {
+            None
+        };
+        let domain = routing.as_ref().map(|routing| routing.domain.to_string()).unwrap_or(domain);
+        let request = {
+            let config = self.config.read().await;
+            options.request_context(request_id, Duration::from_secs(config.request_timeout_seconds), config.default_consistency)
+        };
+
+        // 维护期间排队（等待时间计入请求的剩余时间）或拒绝
+        if let Some(ref maintenance) = self.maintenance {
+            maintenance
+                .admit(request.remaining())
+                .await
+                .map_err(|rejection| RequestError::Maintenance { retry_after_seconds: rejection.retry_after_seconds })?;
         }
 
-        // 获取并发许可
-        let _permit = self.request_semaphore
-            .acquire()
+        // 后台预热上下文，与速率限制和预算检查并行
+        let preload = self.context_loader.as_ref().map(|loader| loader.spawn_preload(&query, Some(&domain)));
+
+        let validation_started = std::time::Instant::now();
+        let validation = self.validate_request(&user_id, &domain).await;
+        self.record_stage(
+            request_id,
+            RequestStage::Validation,
+            validation_started,
+            validation.as_ref().err().map(|e| e.to_string()),
+        ).await;
+        let budget_decision = validation?;
+
+        // 获取并发许可（排队时间计入请求的剩余时间）
+        let _permit = timeout(request.remaining().unwrap_or_default(), self.request_semaphore.acquire())
             .await
+            .map_err(|_| RequestError::Timeout("Request timed out waiting for a permit".to_string()))?
             .map_err(|_| RequestError::ResourceUnavailable("Failed to acquire request permit".to_string()))?;
 
-        // 更新请求计数
-        self.increment_request_count(&user_id).await;
+        // 更新请求计数，接近速率上限时预警
+        let request_count = self.increment_request_count(&user_id).await;
+        if let Some(ref warner) = self.quota_warner {
+            let max_requests = self.config.read().await.max_requests_per_minute;
+            warner.observe(QuotaUsage::new(QuotaKind::RateLimit, BudgetScope::User, &user_id, request_count as u64, max_requests as u64, RATE_LIMIT_PERIOD)).await;
+        }
 
-        // 设置总请求超时
+        // 等待预热完成（预热失败或超时不影响请求处理）
+        if let Some(preload) = preload {
+            let load_timeout = Duration::from_secs(self.config.read().await.context_load_timeout_seconds);
+            let _ = timeout(request.stage_timeout(load_timeout), preload).await;
+        }
+
+        // 按请求的剩余时间设置总超时
         let result = timeout(
-            Duration::from_secs(self.config.read().await.request_timeout_seconds),
-            self.process_request_internal(user_id, session_id, query, domain)
+            request.remaining().unwrap_or_default(),
+            self.process_request_internal(&request, user_id, session_id, query, domain, budget_decision, options)
         ).await;
 
         match result {
-            Ok(process_result) => process_result,
+            Ok(process_result) => process_result.map(|mut request_result| {
+                request_result.routing = routing;
+                request_result
+            }),
             Err(_) => Err(RequestError::Timeout("Request timed out".to_string())),
         }
     }
 
+    /// 清除用户的速率限制计数（用户数据删除），返回是否存在计数；dry_run时只检查
+    pub async fn purge_rate_limit_state(&self, user_id: &str, dry_run: bool) -> bool {
+        let mut counts = self.user_request_counts.write().await;
+        if dry_run {
+            counts.contains_key(user_id)
+        } else {
+            counts.remove(user_id).is_some()
+        }
+    }
+
+    /// 前置检查：速率限制和领域令牌预算，返回预算决策
+    async fn validate_request(&self, user_id: &str, domain: &str) -> Result<BudgetDecision, RequestError> {
+        // 检查速率限制
+        if self.config.read().await.enable_rate_limiting {
+            self.check_rate_limit(user_id).await?;
+        }
+
+        // 检查领域令牌预算
+        let budget_decision = self.token_budget.evaluate(domain).await;
+        if budget_decision == BudgetDecision::Rejected {
+            return Err(RequestError::BudgetExceeded(format!(
+                "Monthly token budget exhausted for domain '{}'", domain
+            )));
+        }
+
+        Ok(budget_decision)
+    }
+
     /// 内部请求处理逻辑
+    #[allow(clippy::too_many_arguments)]
     async fn process_request_internal(
         &self,
+        request: &RequestContext,
         user_id: String,
         session_id: String,
         query: String,
         domain: String,
+        budget_decision: BudgetDecision,
+        options: RequestOptions,
     ) -> Result<RequestResult, RequestError> {
-        // 1. 选择相关上下文
-        let selected_contexts = timeout(
-            Duration::from_secs(self.config.read().await.context_selection_timeout_seconds),
-            self.context_selector.select_contexts(&user_id, &session_id, &query, &domain)
+        let request_id = request.request_id;
+        let mut pricing = self.config.read().await.pricing.clone();
+        if pricing.default_model.is_none() {
+            pricing.default_model = self.generator.as_ref().and_then(|generator| generator.model_name());
+        }
+        // 超出领域范围的查询直接按模板拒答，不选择上下文也不调用大模型
+        if let PolicyVerdict::Refuse { rule, message } = self.safety_policy.evaluate_query(&domain, &query).await {
+            log::info!("Request {} refused by safety rule '{}' in domain '{}'", request_id, rule, domain);
+            let model = budget_decision.model().unwrap_or(pricing.default_model()).to_string();
+            return Ok(RequestResult {
+                request_id,
+                user_id,
+                session_id,
+                query,
+                domain,
+                selected_contexts: Vec::new(),
+                timestamp: chrono::Utc::now(),
+                processing_time_ms: 0,
+                budget_decision,
+                answer: Some(message),
+                answer_truncated: false,
+                refused_by: Some(rule),
+                routing: None,
+                estimated_cost: Some(CostEstimate::free(&model)),
+            });
+        }
+
+        // 1. 选择相关上下文（强一致读先与主实例同步）
+        let selection_timeout = Duration::from_secs(self.config.read().await.context_selection_timeout_seconds);
+        let snapshot_at = self.prepare_read(request, selection_timeout).await?;
+        let generation_pending = !options.dry_run && self.generator.is_some() && request.feature_enabled(FLAG_ANSWER_GENERATION, true);
+        let selection_budget = {
+            let config = self.config.read().await;
+            let later_stages: &[RequestStage] = if generation_pending { &[RequestStage::Generation] } else { &[] };
+            StageBudget::allocate(
+                request,
+                RequestStage::Selection,
+                selection_timeout,
+                &config.stage_budget_weights,
+                later_stages,
+            )
+        };
+        let selection_started = std::time::Instant::now();
+        let selection = timeout(
+            selection_budget.budget,
+            async {
+                match options.as_of {
+                    Some(as_of) => {
+                        self.context_selector
+                            .select_contexts_as_of_for_request(request, &user_id, &session_id, &query, &domain, as_of)
+                            .await
+                    }
+                    None => self.context_selector.select_contexts_for_request(request, &user_id, &session_id, &query, &domain).await,
+                }
+            }
         ).await
-        .map_err(|_| RequestError::Timeout("Context selection timed out".to_string()))?
-        .map_err(|e| RequestError::ContextSelectionFailed(e.to_string()))?;
+        .map_err(|_| RequestError::stage_timeout(&selection_budget))
+        .and_then(|selected| selected.map_err(|e| RequestError::ContextSelectionFailed(e.to_string())));
+        self.record_stage(
+            request_id,
+            RequestStage::Selection,
+            selection_started,
+            selection.as_ref().err().map(|e| e.to_string()),
+        ).await;
+        let selection_latency_ms = selection_started.elapsed().as_secs_f64() * 1000.0;
+        let mut selected_contexts = selection?;
+
+        // 预算紧张时减少上下文数量
+        if let BudgetDecision::Degraded { max_contexts, .. } = &budget_decision {
+            selected_contexts.truncate(*max_contexts);
+        }
+
+        // 按令牌上限装填，超出部分在句子边界截断
+        let packed = pack_contexts(selected_contexts, self.config.read().await.max_context_tokens, self.tokenizer.as_ref());
+        if !packed.truncated.is_empty() || !packed.dropped.is_empty() {
+            log::debug!(
+                "Request {}: {} context(s) truncated and {} dropped to fit the context token limit",
+                request_id, packed.truncated.len(), packed.dropped.len()
+            );
+        }
+        let selected_contexts = packed.contexts;
+
+        // 2. 生成回答（未配置生成器时只返回选中的上下文）
+        let mut answer = None;
+        let mut answer_truncated = false;
+        let mut refused_by = None;
+        let mut constraints = options.constraints.clone();
+        if constraints.language.is_none() {
+            constraints.language = request.locale.clone();
+        }
+        let model = budget_decision.model();
+        let prompt = build_answer_prompt(&query, &selected_contexts, &constraints);
+
+        // 生成前按提示词令牌数、假定的回答长度和已发起的付费搜索估算费用，试运行时即为结果中的费用
+        let searches = self.take_request_searches(request_id).await;
+        let estimate = |input_tokens: u64, output_tokens: u64| {
+            let mut estimate = pricing.estimate(model, input_tokens, output_tokens);
+            for &(queries, cost_per_query) in &searches {
+                estimate.add_search_queries(queries, cost_per_query);
+            }
+            estimate
+        };
+        let assumed_output_tokens = constraints.max_tokens.map_or(pricing.dry_run_output_tokens, u64::from);
+        let prompt_tokens = self.tokenizer.count_tokens(&prompt) as u64;
+        let planned_cost = estimate(prompt_tokens, assumed_output_tokens);
+        if let Some(max_cost) = options.max_cost.filter(|_| generation_pending) {
+            if planned_cost.total_cost > max_cost {
+                return Err(RequestError::CostLimitExceeded { estimated_cost: planned_cost.total_cost, max_cost });
+            }
+        }
+        let mut estimated_cost = if options.dry_run { planned_cost } else { estimate(0, 0) };
+
+        let generator = self.generator.as_ref().filter(|_| generation_pending);
+        if let Some(generator) = generator {
+            let generation_budget = {
+                let config = self.config.read().await;
+                StageBudget::allocate(
+                    request,
+                    RequestStage::Generation,
+                    Duration::from_secs(config.generation_timeout_seconds),
+                    &config.stage_budget_weights,
+                    &[],
+                )
+            };
+            let generation_started = std::time::Instant::now();
+            let generation = timeout(
+                generation_budget.budget,
+                generator.generate(&prompt, model, CallPriority::Interactive)
+            ).await
+            .map_err(|_| RequestError::stage_timeout(&generation_budget))
+            .and_then(|generated| generated.map_err(|e| RequestError::GenerationFailed(e.to_string())));
+            self.record_stage(
+                request_id,
+                RequestStage::Generation,
+                generation_started,
+                generation.as_ref().err().map(|e| e.to_string()),
+            ).await;
+            let generated = generation?;
+            estimated_cost = estimate(prompt_tokens, self.tokenizer.count_tokens(&generated) as u64);
+            self.record_generation_tokens(&domain, estimated_cost.input_tokens + estimated_cost.output_tokens).await;
+
+            // 回答命中拒答规则时替换为拒答，否则截断后附加免责声明（声明不计入长度限制）
+            match self.safety_policy.evaluate_answer(&domain, &generated).await {
+                PolicyVerdict::Refuse { rule, message } => {
+                    log::info!("Answer for request {} replaced by safety rule '{}'", request_id, rule);
+                    answer = Some(message);
+                    refused_by = Some(rule);
+                }
+                PolicyVerdict::Allow => {
+                    let constrained = constraints.enforce(&generated, self.tokenizer.as_ref());
+                    answer = Some(self.safety_policy.apply_disclaimer(&domain, &constrained.text).await);
+                    answer_truncated = constrained.truncated;
+                }
+            }
+        }
 
-        // 2. 准备响应数据
+        // 3. 记录来源信息，供之后的回答反馈关联到上下文和选择策略（试运行不记录，也不镜像到影子管道）
+        if !options.dry_run {
+            self.feedback_store.record_provenance(RequestProvenance {
+                request_id,
+                user_id: user_id.clone(),
+                session_id: session_id.clone(),
+                domain: domain.clone(),
+                query: query.clone(),
+                context_ids: selected_contexts.iter().map(|context| context.id).collect(),
+                strategy: self.context_selector.strategy_label(&domain).await,
+                timestamp: chrono::Utc::now(),
+                consistency: request.consistency,
+                snapshot_at,
+            }).await;
+
+            // 影子管道在后台运行，不等待其结果
+            if let Some(ref shadow) = self.shadow {
+                shadow.mirror(PrimaryOutcome {
+                    request_id,
+                    user_id: user_id.clone(),
+                    session_id: session_id.clone(),
+                    query: query.clone(),
+                    domain: domain.clone(),
+                    context_ids: selected_contexts.iter().map(|context| context.id).collect(),
+                    selection_latency_ms,
+                    answer: answer.clone(),
+                }).await;
+            }
+        }
+
+        // 4. 准备响应数据
         let response_data = RequestResult {
-            request_id: Uuid::new_v4(),
+            request_id,
             user_id,
             session_id,
             query,
@@ -118,6 +655,12 @@ impl RequestProcessor {
             selected_contexts,
             timestamp: chrono::Utc::now(),
             processing_time_ms: 0, // 实际处理时间会在外部计算
+            budget_decision,
+            answer,
+            answer_truncated,
+            refused_by,
+            routing: None,
+            estimated_cost: Some(estimated_cost),
         };
 
         Ok(response_data)
@@ -147,14 +690,31 @@ impl RequestProcessor {
         Ok(())
     }
 
-    /// 增加请求计数
-    async fn increment_request_count(&self, user_id: &str) {
+    /// 增加请求计数，返回当前窗口内的请求数（上一次请求已超出窗口时重新计数）
+    async fn increment_request_count(&self, user_id: &str) -> u32 {
         let mut request_counts = self.user_request_counts.write().await;
         let now = chrono::Utc::now();
         
         let entry = request_counts.entry(user_id.to_string()).or_insert((0, now));
+        if entry.1 < now - chrono::Duration::minutes(1) {
+            entry.0 = 0;
+        }
         entry.0 += 1;
         entry.1 = now;
+        entry.0
+    }
+
+    /// 各用户当前窗口内的请求数及速率上限
+    pub async fn rate_limit_usage(&self) -> Vec<QuotaUsage> {
+        let max_requests = self.config.read().await.max_requests_per_minute;
+        let window_start = chrono::Utc::now() - chrono::Duration::minutes(1);
+        self.user_request_counts
+            .read()
+            .await
+            .iter()
+            .filter(|(_, (_, last_request_time))| *last_request_time >= window_start)
+            .map(|(user_id, (count, _))| QuotaUsage::new(QuotaKind::RateLimit, BudgetScope::User, user_id, *count as u64, max_requests as u64, RATE_LIMIT_PERIOD))
+            .collect()
     }
 
     /// 清除过期的请求计数（用于速率限制）
@@ -168,6 +728,44 @@ impl RequestProcessor {
         });
     }
 
+    /// 记录领域生成所消耗的令牌数（生成完成后由调用方上报）
+    pub async fn record_generation_tokens(&self, domain: &str, tokens: u64) {
+        self.token_budget.record_usage(domain, tokens).await;
+        if let Some(ref monitoring) = self.monitoring {
+            monitoring.log_event(MonitoringEvent::TokensUsed { domain: domain.to_string(), tokens }).await;
+        }
+        if let Some(ref warner) = self.quota_warner {
+            if let Some(status) = self.token_budget.get_status(domain).await {
+                warner.observe(QuotaUsage::new(QuotaKind::TokenBudget, BudgetScope::Domain, domain, status.used_tokens, status.monthly_limit, &status.period)).await;
+            }
+        }
+    }
+
+    /// 记录用户对回答的评分（1-5分）和评论
+    pub async fn record_answer_feedback(
+        &self,
+        request_id: Uuid,
+        rating: u8,
+        comment: Option<String>,
+    ) -> Result<AnswerFeedback, FeedbackError> {
+        self.feedback_store.record_answer_feedback(request_id, rating, comment).await
+    }
+
+    /// 获取反馈存储
+    pub fn get_feedback_store(&self) -> Arc<FeedbackStore> {
+        self.feedback_store.clone()
+    }
+
+    /// 获取安全策略引擎
+    pub fn get_safety_policy(&self) -> Arc<SafetyPolicyEngine> {
+        self.safety_policy.clone()
+    }
+
+    /// 获取令牌预算管理器
+    pub fn get_token_budget(&self) -> Arc<TokenBudgetManager> {
+        self.token_budget.clone()
+    }
+
     /// 更新配置
     pub async fn update_config(&self, new_config: RequestProcessorConfig) {
         let mut config = self.config.write().await;
@@ -213,15 +811,48 @@ pub struct RequestResult {
     pub selected_contexts: Vec<LLMContext>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
     pub processing_time_ms: u64,
+    pub budget_decision: BudgetDecision, // 预算决策（降级时给出上下文上限和替代模型）
+    pub answer: Option<String>,          // 生成的回答（未配置生成器时为空）
+    pub answer_truncated: bool,          // 回答是否因超过max_tokens被截断
+    pub refused_by: Option<String>,      // 触发拒答的安全规则
+    #[serde(default)]
+    pub routing: Option<DomainRouting>,  // 由处理器判断领域时的领域、置信度和备选领域
+    #[serde(default)]
+    pub estimated_cost: Option<CostEstimate>, // 按令牌数、模型计价和搜索次数估算的费用（试运行时为生成前的估算）
+}
+
+/// 构造回答提示：编号的上下文段落、约束指令和问题
+fn build_answer_prompt(query: &str, contexts: &[LLMContext], constraints: &ResponseConstraints) -> String {
+    let passages: Vec<String> = contexts
+        .iter()
+        .enumerate()
+        .map(|(i, context)| format!("[{}] {}", i + 1, context.context_data))
+        .collect();
+    let mut prompt = format!(
+        "Answer the question using the numbered passages below.\n\n{}\n",
+        passages.join("\n")
+    );
+    let instructions = constraints.to_prompt_instructions();
+    if !instructions.is_empty() {
+        prompt.push_str(&format!("\nInstructions:\n{}\n", instructions));
+    }
+    prompt.push_str(&format!("\nQuestion: {}", query));
+    prompt
 }
 
 /// 请求错误类型
 #[derive(Debug)]
 pub enum RequestError {
     Timeout(String),
+    StageTimeout { stage: RequestStage, budget_ms: u64, limited_by_deadline: bool }, // 阶段耗尽了分得的时间预算
     RateLimitExceeded(String),
     ContextSelectionFailed(String),
     ResourceUnavailable(String),
+    ConsistencyUnavailable(String), // 无法满足请求的一致性级别（如强一致读时主实例不可达）
+    Maintenance { retry_after_seconds: u64 }, // 服务处于维护模式
+    BudgetExceeded(String),
+    CostLimitExceeded { estimated_cost: f64, max_cost: f64 }, // 预计费用超出请求的费用上限
+    GenerationFailed(String),
     Other(String),
 }
 
@@ -229,9 +860,25 @@ impl std::fmt::Display for RequestError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             RequestError::Timeout(msg) => write!(f, "Timeout: {}", msg),
+            RequestError::StageTimeout { stage, budget_ms, limited_by_deadline } => write!(
+                f,
+                "Timeout: {:?} stage exhausted its {}ms budget{}",
+                stage,
+                budget_ms,
+                if *limited_by_deadline { " (limited by the request deadline)" } else { "" }
+            ),
             RequestError::RateLimitExceeded(msg) => write!(f, "RateLimitExceeded: {}", msg),
             RequestError::ContextSelectionFailed(msg) => write!(f, "ContextSelectionFailed: {}", msg),
             RequestError::ResourceUnavailable(msg) => write!(f, "ResourceUnavailable: {}", msg),
+            RequestError::Maintenance { retry_after_seconds } => {
+                write!(f, "Maintenance: service is in maintenance mode, retry after {}s", retry_after_seconds)
+            }
+            RequestError::ConsistencyUnavailable(msg) => write!(f, "ConsistencyUnavailable: {}", msg),
+            RequestError::BudgetExceeded(msg) => write!(f, "BudgetExceeded: {}", msg),
+            RequestError::CostLimitExceeded { estimated_cost, max_cost } => {
+                write!(f, "CostLimitExceeded: estimated cost {:.4} exceeds the limit of {:.4}", estimated_cost, max_cost)
+            }
+            RequestError::GenerationFailed(msg) => write!(f, "GenerationFailed: {}", msg),
             RequestError::Other(msg) => write!(f, "Other: {}", msg),
         }
     }
@@ -239,6 +886,17 @@ impl std::fmt::Display for RequestError {
 
 impl std::error::Error for RequestError {}
 
+impl RequestError {
+    /// 阶段超出预算时的超时错误
+    fn stage_timeout(budget: &StageBudget) -> Self {
+        RequestError::StageTimeout {
+            stage: budget.stage,
+            budget_ms: budget.budget_ms(),
+            limited_by_deadline: budget.limited_by_deadline,
+        }
+    }
+}
+
 /// 请求处理器统计信息
 #[derive(Debug)]
 pub struct RequestProcessorStats {
@@ -371,4 +1029,195 @@ mod tests {
         // 第三个请求可能因为速率限制而失败
         println!("Result 3: {:?}", result3);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_token_budget_enforcement() {
+        use async_trait::async_trait;
+
+        /// 记录每次生成请求的模型
+        #[derive(Default)]
+        struct ModelRecordingGenerator {
+            models: std::sync::Mutex<Vec<Option<String>>>,
+        }
+
+        #[async_trait]
+        impl TextGenerator for ModelRecordingGenerator {
+            async fn generate(&self, _prompt: &str, model: Option<&str>, _priority: CallPriority) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+                self.models.lock().unwrap_or_else(|e| e.into_inner()).push(model.map(str::to_string));
+                Ok("Diversify holdings".to_string())
+            }
+        }
+
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let context_selector = Arc::new(ContextSelector::new(context_manager.clone()));
+        let generator = Arc::new(ModelRecordingGenerator::default());
+        let processor = RequestProcessor::new(context_manager.clone(), context_selector.clone()).with_text_generator(generator.clone());
+
+        processor.get_token_budget().set_budget("finance", 1000).await;
+        let mut budget_config = processor.get_token_budget().get_config().await;
+        budget_config.fallback_model = Some("small-model".to_string());
+        processor.get_token_budget().update_config(budget_config).await;
+
+        let result = processor
+            .process_request("user1".to_string(), "session1".to_string(), "stock risk".to_string(), "finance".to_string())
+            .await
+            .unwrap();
+        assert_eq!(result.budget_decision, BudgetDecision::Normal);
+
+        processor.record_generation_tokens("finance", 950).await;
+        let result = processor
+            .process_request("user1".to_string(), "session1".to_string(), "bond yield".to_string(), "finance".to_string())
+            .await
+            .unwrap();
+        assert!(matches!(result.budget_decision, BudgetDecision::Degraded { .. }));
+
+        // 降级的请求实际调用替代模型，并按该模型计价
+        let models = generator.models.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        assert_eq!(models, vec![None, Some("small-model".to_string())]);
+        assert_eq!(result.estimated_cost.unwrap().model, "small-model");
+
+        processor.record_generation_tokens("finance", 50).await;
+        let result = processor
+            .process_request("user1".to_string(), "session1".to_string(), "loan rate".to_string(), "finance".to_string())
+            .await;
+        assert!(matches!(result, Err(RequestError::BudgetExceeded(_))));
+    }
+
+    #[tokio::test]
+    async fn test_request_stage_trace() {
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let context_selector = Arc::new(ContextSelector::new(context_manager.clone()));
+        let monitoring = Arc::new(MonitoringSystem::new());
+        let processor = RequestProcessor::new(context_manager.clone(), context_selector.clone())
+            .with_monitoring(monitoring.clone());
+
+        let result = processor
+            .process_request("user1".to_string(), "session1".to_string(), "flu".to_string(), "medical".to_string())
+            .await
+            .unwrap();
+
+        let trace = monitoring.get_request_trace(result.request_id).await.unwrap();
+        let stages: Vec<RequestStage> = trace.entries.iter().map(|e| e.stage).collect();
+        assert_eq!(stages, vec![RequestStage::Validation, RequestStage::Selection, RequestStage::Response]);
+        assert!(trace.failed_stage.is_none());
+        assert!(trace.total_duration_ms.is_some());
+
+        // 自动判断领域的请求记录分类阶段
+        let result = processor
+            .process_request("user1".to_string(), "session1".to_string(), "flu".to_string(), AUTO_DOMAIN.to_string())
+            .await
+            .unwrap();
+        let trace = monitoring.get_request_trace(result.request_id).await.unwrap();
+        assert_eq!(trace.entries[0].stage, RequestStage::Classification);
+    }
+
+    #[tokio::test]
+    async fn test_domain_routing_confidence() {
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let context_selector = Arc::new(ContextSelector::new(context_manager.clone()));
+        let processor = RequestProcessor::new(context_manager, context_selector);
+
+        let result = processor
+            .process_request("user1".to_string(), "session1".to_string(), "What is the treatment for pneumonia?".to_string(), AUTO_DOMAIN.to_string())
+            .await
+            .unwrap();
+        assert_eq!(result.domain, "medical");
+        let routing = result.routing.unwrap();
+        assert_eq!(routing.domain, crate::domain::domain_classifier::Domain::Medical);
+        assert!(routing.confidence > 0.5 && routing.confidence <= 1.0);
+        assert!(routing.runner_ups.iter().all(|candidate| candidate.confidence <= routing.confidence));
+
+        // 调用方指定领域时不做路由
+        let result = processor
+            .process_request("user1".to_string(), "session1".to_string(), "pneumonia".to_string(), "medical".to_string())
+            .await
+            .unwrap();
+        assert!(result.routing.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cost_estimate_and_dry_run() {
+        use async_trait::async_trait;
+        use crate::monitoring::capacity::ModelPricing;
+        use crate::selection::rag_fusion::RetrievalMode;
+        use crate::test_support::FixedGenerator;
+        use crate::utils::provider_health::BING_PROVIDER;
+        use crate::utils::request_context::RequestContext;
+
+        /// 检索阶段的生成器，每次调用前为请求发起一次付费搜索
+        struct SearchingGenerator {
+            budget: Arc<SearchBudget>,
+        }
+
+        #[async_trait]
+        impl TextGenerator for SearchingGenerator {
+            async fn generate(&self, _prompt: &str, _model: Option<&str>, _priority: CallPriority) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+                Ok("Pneumonia is treated with antibiotics".to_string())
+            }
+
+            async fn generate_for_request(
+                &self,
+                prompt: &str,
+                priority: CallPriority,
+                request: &RequestContext,
+            ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+                self.budget.try_consume(BING_PROVIDER, None, None, Some(request.request_id)).await?;
+                self.generate(prompt, None, priority).await
+            }
+        }
+
+        let search_budget = Arc::new(SearchBudget::new());
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let context_selector = Arc::new(
+            ContextSelector::new(context_manager.clone()).with_text_generator(Arc::new(SearchingGenerator { budget: search_budget.clone() })),
+        );
+        let mut selector_config = context_selector.get_config().await;
+        selector_config.domain_retrieval_modes.insert("medical".to_string(), RetrievalMode::Hyde);
+        context_selector.update_config(selector_config).await;
+        let processor = RequestProcessor::new(context_manager.clone(), context_selector)
+            .with_text_generator(Arc::new(FixedGenerator::new("Antibiotics such as amoxicillin").with_model("large")))
+            .with_search_budget(search_budget.clone());
+        processor.get_token_budget().set_budget("medical", 100_000).await;
+        let mut config = processor.get_config().await;
+        // 未配置默认模型时按生成器的模型计价
+        config.pricing = CostPricing {
+            default_model: None,
+            models: vec![ModelPricing { model: "large".to_string(), input_per_1k_tokens: 1.0, output_per_1k_tokens: 2.0 }],
+            dry_run_output_tokens: 100,
+        };
+        processor.update_config(config).await;
+        context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia is treated with antibiotics".to_string(), 8)
+            .await
+            .unwrap();
+        // 每个请求使用新会话，避免命中选择缓存而跳过检索阶段的搜索
+        let request = |session: &str, options: RequestOptions| {
+            processor.process_request_with_options("u1".to_string(), session.to_string(), "How to treat pneumonia?".to_string(), "medical".to_string(), options)
+        };
+
+        // 试运行：不生成回答也不记录令牌，按假定的回答长度估算
+        let dry_run = request("s1", RequestOptions { dry_run: true, ..RequestOptions::default() }).await.unwrap();
+        assert!(dry_run.answer.is_none());
+        let planned = dry_run.estimated_cost.unwrap();
+        assert_eq!((planned.model.as_str(), planned.output_tokens), ("large", 100));
+        assert!(planned.input_tokens > 0);
+        // 检索阶段发起的一次Bing搜索计入费用
+        assert_eq!(planned.search_queries, 1);
+        assert!((planned.total_cost - (planned.input_tokens as f64 / 1000.0 + 0.2 + 0.007)).abs() < 1e-9);
+        assert_eq!(processor.get_token_budget().get_status("medical").await.unwrap().used_tokens, 0);
+
+        // 实际生成按回答的令牌数计费
+        let result = request("s2", RequestOptions::default()).await.unwrap();
+        let actual = result.estimated_cost.unwrap();
+        let answer_tokens = WhitespaceTokenizer.count_tokens("Antibiotics such as amoxicillin") as u64;
+        assert_eq!((actual.input_tokens, actual.output_tokens), (planned.input_tokens, answer_tokens));
+        assert!((actual.search_cost - 0.007).abs() < 1e-9);
+        assert_eq!(processor.get_token_budget().get_status("medical").await.unwrap().used_tokens, actual.input_tokens + answer_tokens);
+
+        // 生成前的估算（含搜索费用）超出费用上限时拒绝
+        let token_only = planned.total_cost - planned.search_cost;
+        let capped = request("s3", RequestOptions { max_cost: Some(token_only + 0.001), ..RequestOptions::default() }).await;
+        assert!(matches!(capped, Err(RequestError::CostLimitExceeded { .. })));
+        assert_eq!(search_budget.get_usage().await.total_queries, 3);
+    }
+}
@@ -0,0 +1,166 @@
+//! 上下文装填 - 按排名把选中的上下文装入提示词的令牌预算，超出剩余预算的上下文在句子边界截断并标注，而不是整条丢弃或超出提示词长度
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::context::llm_context::LLMContext;
+use crate::utils::tokenizer::Tokenizer;
+
+/// 截断后追加的省略标记
+pub const TRUNCATION_MARKER: &str = "…";
+/// 截断的上下文在元数据中的标记键（值为"true"）
+pub const TRUNCATED_METADATA_KEY: &str = "truncated";
+/// 截断前的估算令牌数
+pub const ORIGINAL_TOKENS_METADATA_KEY: &str = "original_tokens";
+
+/// 装填结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackedContexts {
+    pub contexts: Vec<LLMContext>,
+    pub truncated: Vec<Uuid>,   // 被截断的上下文
+    pub dropped: Vec<Uuid>,     // 预算用尽后未能装入的上下文
+    pub tokens_used: usize,
+}
+
+/// 按句子切分：英文句末标点后跟空白或文本结束，中文句末标点和换行直接断句
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let end = i + c.len_utf8();
+        let boundary = match c {
+            '。' | '！' | '？' | '\n' => true,
+            '.' | '!' | '?' => chars.peek().map(|(_, next)| next.is_whitespace()).unwrap_or(true),
+            _ => false,
+        };
+        if boundary {
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+    let rest = text[start..].trim();
+    if !rest.is_empty() {
+        sentences.push(rest);
+    }
+    sentences
+}
+
+/// 截断到max_tokens以内（含省略标记）：尽量保留完整句子，第一句就放不下时按词截断；什么都放不下时返回None
+pub fn truncate_at_sentence(text: &str, max_tokens: usize, tokenizer: &dyn Tokenizer) -> Option<String> {
+    if tokenizer.count_tokens(text) <= max_tokens {
+        return Some(text.to_string());
+    }
+    let budget = max_tokens.checked_sub(tokenizer.count_tokens(TRUNCATION_MARKER))?;
+    let mut kept: Vec<&str> = Vec::new();
+    let mut used = 0;
+    for sentence in split_sentences(text) {
+        let tokens = tokenizer.count_tokens(sentence);
+        if used + tokens > budget {
+            break;
+        }
+        used += tokens;
+        kept.push(sentence);
+    }
+    let kept = if kept.is_empty() { tokenizer.truncate(text, budget) } else { kept.join(" ") };
+    if kept.is_empty() {
+        return None;
+    }
+    Some(format!("{} {}", kept, TRUNCATION_MARKER))
+}
+
+/// 切分为每块不超过max_tokens的文本块：按句子贪心合并，单句超长时交由分词器按词切分
+pub fn chunk_at_sentences(text: &str, max_tokens: usize, tokenizer: &dyn Tokenizer) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for sentence in split_sentences(text) {
+        let candidate = if current.is_empty() { sentence.to_string() } else { format!("{} {}", current, sentence) };
+        if tokenizer.count_tokens(&candidate) <= max_tokens {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if tokenizer.count_tokens(sentence) <= max_tokens {
+            current = sentence.to_string();
+        } else {
+            chunks.extend(tokenizer.chunk(sentence, max_tokens));
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// 按顺序装填上下文；max_tokens为0时不限制
+pub fn pack_contexts(contexts: Vec<LLMContext>, max_tokens: usize, tokenizer: &dyn Tokenizer) -> PackedContexts {
+    let mut packed = PackedContexts { contexts: Vec::new(), truncated: Vec::new(), dropped: Vec::new(), tokens_used: 0 };
+    for mut context in contexts {
+        let tokens = tokenizer.count_tokens(&context.context_data);
+        if max_tokens == 0 || packed.tokens_used + tokens <= max_tokens {
+            packed.tokens_used += tokens;
+            packed.contexts.push(context);
+            continue;
+        }
+        let remaining = max_tokens - packed.tokens_used;
+        match truncate_at_sentence(&context.context_data, remaining, tokenizer) {
+            Some(text) => {
+                packed.tokens_used += tokenizer.count_tokens(&text);
+                context.context_data = text;
+                context.metadata.insert(TRUNCATED_METADATA_KEY.to_string(), "true".to_string());
+                context.metadata.insert(ORIGINAL_TOKENS_METADATA_KEY.to_string(), tokens.to_string());
+                packed.truncated.push(context.id);
+                packed.contexts.push(context);
+            }
+            None => packed.dropped.push(context.id),
+        }
+    }
+    packed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::RequestProcessor;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::utils::tokenizer::WhitespaceTokenizer;
+
+    #[tokio::test]
+    async fn test_truncates_at_sentence_boundaries() {
+        assert_eq!(split_sentences("Dr. Smith arrived. Then left!"), vec!["Dr.", "Smith arrived.", "Then left!"]);
+        assert_eq!(split_sentences("肺炎需要治疗。抗生素有效"), vec!["肺炎需要治疗。", "抗生素有效"]);
+
+        let text = "Pneumonia is a lung infection. Antibiotics treat bacterial cases. Viral cases need rest.";
+        let truncated = truncate_at_sentence(text, 20, &WhitespaceTokenizer).unwrap();
+        assert_eq!(truncated, "Pneumonia is a lung infection. Antibiotics treat bacterial cases. …");
+        assert!(WhitespaceTokenizer.count_tokens(&truncated) <= 20);
+        // 第一句就放不下时按词截断
+        assert_eq!(truncate_at_sentence(text, 4, &WhitespaceTokenizer).unwrap(), "Pneumonia …");
+        assert!(truncate_at_sentence(text, 1, &WhitespaceTokenizer).is_none());
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), text.to_string(), 5)
+            .await
+            .unwrap();
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let processor = RequestProcessor::new(manager, selector);
+        let mut config = processor.get_config().await;
+        config.max_context_tokens = 10;
+        processor.update_config(config).await;
+        let result = processor
+            .process_request("u1".to_string(), "s1".to_string(), "pneumonia".to_string(), "medical".to_string())
+            .await
+            .unwrap();
+        let context = &result.selected_contexts[0];
+        assert_eq!(context.context_data, "Pneumonia is a lung infection. …");
+        assert_eq!(context.metadata.get(TRUNCATED_METADATA_KEY).map(String::as_str), Some("true"));
+        assert_eq!(context.metadata.get(ORIGINAL_TOKENS_METADATA_KEY), Some(&WhitespaceTokenizer.count_tokens(text).to_string()));
+    }
+}
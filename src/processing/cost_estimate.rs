@@ -0,0 +1,163 @@
+//! 请求费用估算 - 按模型计价表将提示词和回答的令牌数折算为费用，并计入请求发起的付费搜索，
+//! 供产品侧展示和限制每次对话的花费
+
+use serde::{Deserialize, Serialize};
+use crate::monitoring::capacity::ModelPricing;
+
+/// 未设置默认模型且生成器未给出模型名时，估算结果中的模型名（按0计价）
+pub const UNKNOWN_MODEL: &str = "unknown";
+
+/// 请求费用计价配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CostPricing {
+    #[serde(default)]
+    pub default_model: Option<String>,  // 未降级时生成回答使用的模型，未设置时取生成器配置的模型
+    #[serde(default)]
+    pub models: Vec<ModelPricing>,      // 各模型的令牌单价，未列出的模型按0计价
+    #[serde(default = "default_dry_run_output_tokens")]
+    pub dry_run_output_tokens: u64,     // 试运行和费用上限检查时假定的回答令牌数（回答约束的max_tokens优先）
+}
+
+fn default_dry_run_output_tokens() -> u64 {
+    300
+}
+
+impl Default for CostPricing {
+    fn default() -> Self {
+        Self {
+            default_model: None,
+            models: Vec::new(),
+            dry_run_output_tokens: default_dry_run_output_tokens(),
+        }
+    }
+}
+
+impl CostPricing {
+    /// 校验单价为非负数且模型不重复
+    pub fn validate(&self) -> Result<(), String> {
+        for (i, pricing) in self.models.iter().enumerate() {
+            for (name, price) in [("input_per_1k_tokens", pricing.input_per_1k_tokens), ("output_per_1k_tokens", pricing.output_per_1k_tokens)] {
+                if !price.is_finite() || price < 0.0 {
+                    return Err(format!("pricing.models '{}' {} must be a non-negative number", pricing.model, name));
+                }
+            }
+            if self.models[..i].iter().any(|other| other.model == pricing.model) {
+                return Err(format!("pricing.models lists '{}' more than once", pricing.model));
+            }
+        }
+        Ok(())
+    }
+
+    /// 模型的计价
+    pub fn model_pricing(&self, model: &str) -> Option<&ModelPricing> {
+        self.models.iter().find(|pricing| pricing.model == model)
+    }
+
+    /// 未降级时使用的模型
+    pub fn default_model(&self) -> &str {
+        self.default_model.as_deref().unwrap_or(UNKNOWN_MODEL)
+    }
+
+    /// 估算一次模型调用的费用；model为None时使用默认模型
+    pub fn estimate(&self, model: Option<&str>, input_tokens: u64, output_tokens: u64) -> CostEstimate {
+        let model = model.unwrap_or(self.default_model());
+        let pricing = self.model_pricing(model);
+        let token_cost = pricing.map_or(0.0, |pricing| {
+            input_tokens as f64 / 1000.0 * pricing.input_per_1k_tokens + output_tokens as f64 / 1000.0 * pricing.output_per_1k_tokens
+        });
+        CostEstimate {
+            model: model.to_string(),
+            priced: pricing.is_some(),
+            input_tokens,
+            output_tokens,
+            token_cost,
+            search_queries: 0,
+            search_cost: 0.0,
+            total_cost: token_cost,
+        }
+    }
+}
+
+/// 请求的预计费用
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub model: String,
+    pub priced: bool,           // 计价表中是否有该模型（否则令牌费用为0）
+    pub input_tokens: u64,      // 提示词令牌数
+    pub output_tokens: u64,     // 回答令牌数（试运行时为假定值）
+    pub token_cost: f64,
+    pub search_queries: u64,    // 请求发起的付费搜索次数
+    pub search_cost: f64,
+    pub total_cost: f64,
+}
+
+impl CostEstimate {
+    /// 不调用模型的请求（如按安全规则拒答）
+    pub fn free(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            priced: true,
+            input_tokens: 0,
+            output_tokens: 0,
+            token_cost: 0.0,
+            search_queries: 0,
+            search_cost: 0.0,
+            total_cost: 0.0,
+        }
+    }
+
+    /// 计入付费搜索（单价取搜索预算配置中服务的单次查询费用）
+    pub fn add_search_queries(&mut self, queries: u64, cost_per_query: f64) {
+        let cost = queries as f64 * cost_per_query;
+        self.search_queries += queries;
+        self.search_cost += cost;
+        self.total_cost += cost;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pricing() -> CostPricing {
+        CostPricing {
+            default_model: Some("large".to_string()),
+            models: vec![
+                ModelPricing { model: "large".to_string(), input_per_1k_tokens: 0.01, output_per_1k_tokens: 0.03 },
+                ModelPricing { model: "small".to_string(), input_per_1k_tokens: 0.001, output_per_1k_tokens: 0.002 },
+            ],
+            dry_run_output_tokens: 300,
+        }
+    }
+
+    #[test]
+    fn test_estimate_combines_tokens_and_searches() {
+        let mut estimate = pricing().estimate(None, 2000, 500);
+        assert_eq!(estimate.model, "large");
+        assert!((estimate.token_cost - (0.02 + 0.015)).abs() < 1e-9);
+        estimate.add_search_queries(2, 0.007);
+        assert_eq!(estimate.search_queries, 2);
+        assert!((estimate.total_cost - (0.035 + 0.014)).abs() < 1e-9);
+
+        let degraded = pricing().estimate(Some("small"), 2000, 500);
+        assert!((degraded.total_cost - 0.003).abs() < 1e-9);
+
+        // 未列出的模型不计令牌费用，并标记为未计价
+        let unknown = pricing().estimate(Some("mystery"), 2000, 500);
+        assert!(!unknown.priced);
+        assert_eq!(unknown.total_cost, 0.0);
+        let unset = CostPricing::default().estimate(None, 2000, 500);
+        assert_eq!((unset.model.as_str(), unset.priced), (UNKNOWN_MODEL, false));
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_prices() {
+        assert!(pricing().validate().is_ok());
+        let mut negative = pricing();
+        negative.models[1].output_per_1k_tokens = -1.0;
+        assert!(negative.validate().is_err());
+        let mut duplicate = pricing();
+        duplicate.models.push(duplicate.models[0].clone());
+        assert!(duplicate.validate().is_err());
+    }
+}
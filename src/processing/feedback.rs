@@ -0,0 +1,369 @@
+//! 回答反馈 - 记录每个请求的来源信息（选中的上下文和选择策略）及用户评分，持久化为JSON Lines并按上下文和策略汇总质量指标
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+use crate::context::consistency::ConsistencyLevel;
+use crate::strategy::priority_tuner::PriorityTuner;
+
+/// 评分范围
+pub const MIN_RATING: u8 = 1;
+pub const MAX_RATING: u8 = 5;
+
+/// 请求来源信息：回答依据的上下文和选择策略
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestProvenance {
+    pub request_id: Uuid,
+    pub user_id: String,
+    pub session_id: String,
+    pub domain: String,
+    pub query: String,
+    pub context_ids: Vec<Uuid>,     // 选中的上下文（按排名）
+    pub strategy: String,           // 选择策略标签，如"Hybrid/Standard"
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub consistency: ConsistencyLevel, // 选择上下文时使用的读一致性级别
+    #[serde(default)]
+    pub snapshot_at: Option<DateTime<Utc>>, // 在副本上读取时，所用快照拉取自主实例的时间
+}
+
+/// 一条回答反馈
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnswerFeedback {
+    pub request_id: Uuid,
+    pub rating: u8,                 // 1-5分
+    pub comment: Option<String>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 持久化日志中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum FeedbackLogEntry {
+    Provenance(RequestProvenance),
+    Feedback(AnswerFeedback),
+}
+
+/// 质量汇总
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityMetrics {
+    pub ratings: u64,
+    pub average_rating: f64,
+    pub negative_ratings: u64,      // 1-2分的次数
+}
+
+impl QualityMetrics {
+    fn record(&mut self, rating: u8) {
+        let n = self.ratings as f64;
+        self.average_rating = (self.average_rating * n + rating as f64) / (n + 1.0);
+        self.ratings += 1;
+        if rating <= 2 {
+            self.negative_ratings += 1;
+        }
+    }
+}
+
+/// 反馈错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedbackError {
+    UnknownRequest(Uuid),
+    InvalidRating(u8),
+}
+
+impl std::fmt::Display for FeedbackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeedbackError::UnknownRequest(id) => write!(f, "No provenance recorded for request {}", id),
+            FeedbackError::InvalidRating(rating) => {
+                write!(f, "Rating {} out of range {}-{}", rating, MIN_RATING, MAX_RATING)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FeedbackError {}
+
+/// 内存中的反馈状态
+#[derive(Default)]
+struct FeedbackState {
+    provenance: HashMap<Uuid, RequestProvenance>,
+    order: VecDeque<Uuid>,                          // 来源信息的写入顺序，超出容量时淘汰最早的
+    feedback: HashMap<Uuid, Vec<AnswerFeedback>>,
+    by_context: HashMap<Uuid, QualityMetrics>,
+    by_strategy: HashMap<String, QualityMetrics>,
+}
+
+impl FeedbackState {
+    fn insert_provenance(&mut self, provenance: RequestProvenance, max_requests: usize) {
+        self.order.push_back(provenance.request_id);
+        self.provenance.insert(provenance.request_id, provenance);
+        while self.order.len() > max_requests {
+            if let Some(evicted) = self.order.pop_front() {
+                self.provenance.remove(&evicted);
+                self.feedback.remove(&evicted);
+            }
+        }
+    }
+
+    /// 记录反馈并更新汇总，返回对应请求的来源信息
+    fn apply_feedback(&mut self, feedback: AnswerFeedback) -> Option<RequestProvenance> {
+        let provenance = self.provenance.get(&feedback.request_id)?.clone();
+        for context_id in &provenance.context_ids {
+            self.by_context.entry(*context_id).or_default().record(feedback.rating);
+        }
+        self.by_strategy.entry(provenance.strategy.clone()).or_default().record(feedback.rating);
+        self.feedback.entry(feedback.request_id).or_default().push(feedback);
+        Some(provenance)
+    }
+}
+
+/// 反馈存储
+pub struct FeedbackStore {
+    state: Arc<RwLock<FeedbackState>>,
+    max_requests: usize,
+    log_path: Option<PathBuf>,      // 持久化文件，为空时只保存在内存中
+    write_lock: Mutex<()>,
+    priority_tuner: Option<Arc<PriorityTuner>>,
+}
+
+impl Default for FeedbackStore {
+    fn default() -> Self {
+        Self::new(100_000)
+    }
+}
+
+impl FeedbackStore {
+    /// 创建内存中的反馈存储，最多保留max_requests个请求的来源信息
+    pub fn new(max_requests: usize) -> Self {
+        Self {
+            state: Arc::new(RwLock::new(FeedbackState::default())),
+            max_requests: max_requests.max(1),
+            log_path: None,
+            write_lock: Mutex::new(()),
+            priority_tuner: None,
+        }
+    }
+
+    /// 打开持久化的反馈存储：重放已有日志，之后的记录追加写入该文件
+    pub fn open(path: impl AsRef<Path>, max_requests: usize) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let path = path.as_ref().to_path_buf();
+        let mut store = Self::new(max_requests);
+        if path.exists() {
+            let mut state = FeedbackState::default();
+            let reader = BufReader::new(std::fs::File::open(&path)?);
+            for (line_no, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                match serde_json::from_str::<FeedbackLogEntry>(&line) {
+                    Ok(FeedbackLogEntry::Provenance(provenance)) => state.insert_provenance(provenance, store.max_requests),
+                    Ok(FeedbackLogEntry::Feedback(feedback)) => {
+                        state.apply_feedback(feedback);
+                    }
+                    Err(e) => log::warn!("Skipping malformed feedback log line {}: {}", line_no + 1, e),
+                }
+            }
+            store.state = Arc::new(RwLock::new(state));
+        }
+        store.log_path = Some(path);
+        Ok(store)
+    }
+
+    /// 关联优先级自动调整器，评分按上下文转换为0-1的反馈分数
+    pub fn with_priority_tuner(mut self, priority_tuner: Arc<PriorityTuner>) -> Self {
+        self.priority_tuner = Some(priority_tuner);
+        self
+    }
+
+    /// 追加一行到持久化文件（写入失败只记录日志，不影响请求）
+    async fn append(&self, entry: &FeedbackLogEntry) {
+        let Some(ref path) = self.log_path else {
+            return;
+        };
+        let _guard = self.write_lock.lock().await;
+        let result = async {
+            let mut line = serde_json::to_string(entry)?;
+            line.push('\n');
+            let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+            file.write_all(line.as_bytes()).await?;
+            // tokio的文件写入在后台完成，刷新后才能保证追加顺序
+            file.flush().await?;
+            Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+        }
+        .await;
+        if let Err(e) = result {
+            log::warn!("Failed to persist feedback log entry to {}: {}", path.display(), e);
+        }
+    }
+
+    /// 记录请求来源信息
+    pub async fn record_provenance(&self, provenance: RequestProvenance) {
+        self.append(&FeedbackLogEntry::Provenance(provenance.clone())).await;
+        self.state.write().await.insert_provenance(provenance, self.max_requests);
+    }
+
+    /// 记录对回答的评分
+    pub async fn record_answer_feedback(
+        &self,
+        request_id: Uuid,
+        rating: u8,
+        comment: Option<String>,
+    ) -> Result<AnswerFeedback, FeedbackError> {
+        if !(MIN_RATING..=MAX_RATING).contains(&rating) {
+            return Err(FeedbackError::InvalidRating(rating));
+        }
+        let feedback = AnswerFeedback { request_id, rating, comment, recorded_at: Utc::now() };
+        let provenance = self
+            .state
+            .write()
+            .await
+            .apply_feedback(feedback.clone())
+            .ok_or(FeedbackError::UnknownRequest(request_id))?;
+        self.append(&FeedbackLogEntry::Feedback(feedback.clone())).await;
+
+        if let Some(ref tuner) = self.priority_tuner {
+            let score = (rating - MIN_RATING) as f64 / (MAX_RATING - MIN_RATING) as f64;
+            for context_id in provenance.context_ids {
+                tuner.record_feedback(context_id, score).await;
+            }
+        }
+        Ok(feedback)
+    }
+
+    /// 删除用户请求的来源信息和评分（用户数据删除），持久化文件同步重写；按上下文和策略的汇总不含个人信息，予以保留。
+    /// 返回涉及的请求数，dry_run时只统计
+    pub async fn purge_user(&self, user_id: &str, dry_run: bool) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        // 持有写文件锁，重写期间新的记录不会追加到旧文件；内存状态的写锁只在最后短暂持有
+        let _guard = self.write_lock.lock().await;
+        let mut requests: std::collections::HashSet<Uuid> = self
+            .state
+            .read()
+            .await
+            .provenance
+            .values()
+            .filter(|provenance| provenance.user_id == user_id)
+            .map(|provenance| provenance.request_id)
+            .collect();
+
+        // 持久化文件中还可能有已从内存淘汰的请求
+        let mut kept_lines = Vec::new();
+        let log_exists = match self.log_path {
+            Some(ref path) => tokio::fs::try_exists(path).await?,
+            None => false,
+        };
+        if let (Some(path), true) = (&self.log_path, log_exists) {
+            let contents = tokio::fs::read_to_string(path).await?;
+            let lines: Vec<&str> = contents.lines().collect();
+            let entries: Vec<Option<FeedbackLogEntry>> = lines.iter().map(|line| serde_json::from_str(line).ok()).collect();
+            for entry in entries.iter().flatten() {
+                if let FeedbackLogEntry::Provenance(provenance) = entry {
+                    if provenance.user_id == user_id {
+                        requests.insert(provenance.request_id);
+                    }
+                }
+            }
+            for (line, entry) in lines.into_iter().zip(entries) {
+                let erased = match entry {
+                    Some(FeedbackLogEntry::Provenance(provenance)) => requests.contains(&provenance.request_id),
+                    Some(FeedbackLogEntry::Feedback(feedback)) => requests.contains(&feedback.request_id),
+                    None => false,
+                };
+                if !erased {
+                    kept_lines.push(line.to_string());
+                }
+            }
+        }
+        if dry_run {
+            return Ok(requests.len());
+        }
+
+        if let (Some(path), true) = (&self.log_path, log_exists) {
+            let mut contents = kept_lines.join("\n");
+            if !contents.is_empty() {
+                contents.push('\n');
+            }
+            let tmp = path.with_extension("tmp");
+            tokio::fs::write(&tmp, contents).await?;
+            tokio::fs::rename(&tmp, path).await?;
+        }
+        let mut state = self.state.write().await;
+        for request_id in &requests {
+            state.provenance.remove(request_id);
+            state.feedback.remove(request_id);
+        }
+        state.order.retain(|request_id| !requests.contains(request_id));
+        Ok(requests.len())
+    }
+
+    /// 获取请求的来源信息
+    pub async fn get_provenance(&self, request_id: Uuid) -> Option<RequestProvenance> {
+        self.state.read().await.provenance.get(&request_id).cloned()
+    }
+
+    /// 获取请求收到的反馈
+    pub async fn get_feedback(&self, request_id: Uuid) -> Vec<AnswerFeedback> {
+        self.state.read().await.feedback.get(&request_id).cloned().unwrap_or_default()
+    }
+
+    /// 获取上下文的质量指标
+    pub async fn get_context_quality(&self, context_id: Uuid) -> Option<QualityMetrics> {
+        self.state.read().await.by_context.get(&context_id).cloned()
+    }
+
+    /// 获取各选择策略的质量指标（用于策略对比）
+    pub async fn get_strategy_quality(&self) -> HashMap<String, QualityMetrics> {
+        self.state.read().await.by_strategy.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::RequestProcessor;
+    use crate::selection::async_context_selector::ContextSelector;
+
+    #[tokio::test]
+    async fn test_feedback_persisted_and_aggregated() {
+        let path = std::env::temp_dir().join(format!("penlai-feedback-{}.jsonl", Uuid::new_v4()));
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let context = manager
+            .create_context("s0".to_string(), "u0".to_string(), "medical".to_string(), "Pneumonia antibiotics treatment".to_string(), 5)
+            .await
+            .unwrap();
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let tuner = Arc::new(PriorityTuner::new(manager.clone()));
+        let store = Arc::new(FeedbackStore::open(&path, 100).unwrap().with_priority_tuner(tuner.clone()));
+        let processor = RequestProcessor::new(manager.clone(), selector).with_feedback_store(store.clone());
+
+        let result = processor
+            .process_request("u1".to_string(), "s1".to_string(), "pneumonia treatment".to_string(), "medical".to_string())
+            .await
+            .unwrap();
+        let provenance = store.get_provenance(result.request_id).await.unwrap();
+        assert_eq!(provenance.context_ids, vec![context.id]);
+        assert_eq!(provenance.strategy, "Hybrid/Standard");
+
+        assert_eq!(processor.record_answer_feedback(result.request_id, 6, None).await.unwrap_err(), FeedbackError::InvalidRating(6));
+        assert!(processor.record_answer_feedback(Uuid::new_v4(), 3, None).await.is_err());
+        processor.record_answer_feedback(result.request_id, 5, Some("clear".to_string())).await.unwrap();
+        processor.record_answer_feedback(result.request_id, 2, None).await.unwrap();
+        assert_eq!(tuner.get_usage_stats(context.id).await.unwrap().feedback_count, 2);
+
+        // 重新打开后从日志恢复
+        let reopened = FeedbackStore::open(&path, 100).unwrap();
+        let quality = reopened.get_context_quality(context.id).await.unwrap();
+        assert_eq!(quality.ratings, 2);
+        assert_eq!(quality.average_rating, 3.5);
+        assert_eq!(quality.negative_ratings, 1);
+        assert_eq!(reopened.get_strategy_quality().await["Hybrid/Standard"].ratings, 2);
+        assert_eq!(reopened.get_feedback(result.request_id).await[0].comment.as_deref(), Some("clear"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -1 +1,9 @@
-pub mod concurrent_processor;
\ No newline at end of file
+pub mod concurrent_processor;
+pub mod token_budget;
+pub mod response_constraints;
+pub mod safety_policy;
+pub mod feedback;
+pub mod shadow;
+pub mod context_packing;
+pub mod stage_budget;
+pub mod cost_estimate;
\ No newline at end of file
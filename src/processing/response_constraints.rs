@@ -0,0 +1,168 @@
+//! 回答约束 - 请求级的长度、格式、语言和引用要求，生成前转换为提示指令，生成后按长度截断
+
+use serde::{Deserialize, Serialize};
+use crate::utils::tokenizer::Tokenizer;
+
+/// 回答格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AnswerFormat {
+    #[default]
+    Prose,      // 连贯段落
+    Bullets,    // 要点列表
+}
+
+/// 回答约束，未设置的项不限制
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResponseConstraints {
+    pub max_tokens: Option<u32>,        // 回答最大令牌数（超出部分截断）
+    pub format: Option<AnswerFormat>,   // 回答格式
+    pub language: Option<String>,       // 回答语言（如"zh"、"English"）
+    pub citations: Option<bool>,        // 是否以[n]标注引用的上下文
+}
+
+/// 按约束处理后的回答
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConstrainedAnswer {
+    pub text: String,
+    pub truncated: bool,    // 是否因超过max_tokens被截断
+}
+
+impl ResponseConstraints {
+    /// 设置最大令牌数
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// 设置回答格式
+    pub fn with_format(mut self, format: AnswerFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// 设置回答语言
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// 设置是否标注引用
+    pub fn with_citations(mut self, citations: bool) -> Self {
+        self.citations = Some(citations);
+        self
+    }
+
+    /// 转换为提示指令，每条约束一行
+    pub fn to_prompt_instructions(&self) -> String {
+        let mut instructions = Vec::new();
+        if let Some(max_tokens) = self.max_tokens {
+            instructions.push(format!("- Keep the answer under {} tokens.", max_tokens));
+        }
+        match self.format {
+            Some(AnswerFormat::Bullets) => instructions.push("- Format the answer as a bulleted list, one point per line starting with \"- \".".to_string()),
+            Some(AnswerFormat::Prose) => instructions.push("- Write the answer as prose paragraphs without lists.".to_string()),
+            None => {}
+        }
+        if let Some(ref language) = self.language {
+            instructions.push(format!("- Answer in {}.", language));
+        }
+        match self.citations {
+            Some(true) => instructions.push("- Cite the supporting passages inline as [n] using their numbers.".to_string()),
+            Some(false) => instructions.push("- Do not include citations or passage numbers.".to_string()),
+            None => {}
+        }
+        instructions.join("\n")
+    }
+
+    /// 在生成结果上执行约束：超过max_tokens时截断（要点格式按整行截断）
+    pub fn enforce(&self, answer: &str, tokenizer: &dyn Tokenizer) -> ConstrainedAnswer {
+        let answer = answer.trim();
+        let Some(max_tokens) = self.max_tokens else {
+            return ConstrainedAnswer { text: answer.to_string(), truncated: false };
+        };
+        let max_tokens = max_tokens as usize;
+        if tokenizer.count_tokens(answer) <= max_tokens {
+            return ConstrainedAnswer { text: answer.to_string(), truncated: false };
+        }
+
+        if self.format == Some(AnswerFormat::Bullets) {
+            let mut used = 0;
+            let mut lines = Vec::new();
+            for line in answer.lines().filter(|line| !line.trim().is_empty()) {
+                used += tokenizer.count_tokens(line);
+                if used > max_tokens {
+                    break;
+                }
+                lines.push(line);
+            }
+            // 第一条要点就超长时退回到按词截断
+            if !lines.is_empty() {
+                return ConstrainedAnswer { text: lines.join("\n"), truncated: true };
+            }
+        }
+
+        ConstrainedAnswer { text: tokenizer.truncate(answer, max_tokens), truncated: true }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use async_trait::async_trait;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::{RequestOptions, RequestProcessor};
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::utils::ai_client::TextGenerator;
+    use crate::utils::outbound_scheduler::CallPriority;
+    use crate::utils::tokenizer::WhitespaceTokenizer;
+
+    struct EchoGenerator;
+
+    #[async_trait]
+    impl TextGenerator for EchoGenerator {
+        async fn generate(&self, prompt: &str, _model: Option<&str>, _priority: CallPriority) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+            assert!(prompt.contains("- Answer in Chinese."));
+            assert!(prompt.contains("bulleted list"));
+            assert!(prompt.contains("[1] Amoxicillin"));
+            Ok("- Amoxicillin is first line [1]\n- Doxycycline for penicillin allergy [1]\n- Macrolides in regions with low resistance".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_constraints_in_generation_stage() {
+        let constraints = ResponseConstraints::default().with_max_tokens(5);
+        let answer = constraints.enforce("a b c d e f g", &WhitespaceTokenizer);
+        assert_eq!(answer.text, "a b c d e");
+        assert!(answer.truncated);
+        assert_eq!(constraints.enforce("肺炎的治疗方法包括抗生素", &WhitespaceTokenizer).text, "肺炎的治疗");
+        assert!(ResponseConstraints::default().to_prompt_instructions().is_empty());
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        manager
+            .create_context("s0".to_string(), "u0".to_string(), "medical".to_string(),
+                "Amoxicillin treats pneumonia".to_string(), 5)
+            .await
+            .unwrap();
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let processor = RequestProcessor::new(manager.clone(), selector).with_text_generator(Arc::new(EchoGenerator));
+
+        let options = RequestOptions {
+            constraints: ResponseConstraints::default()
+                .with_max_tokens(22)
+                .with_format(AnswerFormat::Bullets)
+                .with_language("Chinese")
+                .with_citations(true),
+            ..RequestOptions::default()
+        };
+        let result = processor
+            .process_request_with_options("u1".to_string(), "s1".to_string(), "pneumonia treatment".to_string(), "medical".to_string(), options)
+            .await
+            .unwrap();
+        // 要点格式按整行截断，领域免责声明附加在截断后的回答之后
+        let answer = result.answer.unwrap();
+        assert!(answer.starts_with("- Amoxicillin is first line [1]\n- Doxycycline for penicillin allergy [1]\n\n"));
+        assert!(!answer.contains("Macrolides"));
+        assert!(result.answer_truncated);
+    }
+}
@@ -0,0 +1,260 @@
+//! 领域安全策略 - 医疗、法律、金融等领域的自动免责声明，以及对查询和生成回答的拒答规则
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+/// 规则作用对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PolicyTarget {
+    Query,  // 用户查询
+    Answer, // 生成的回答
+    Both,
+}
+
+/// 拒答规则：每组至少命中一个词（或短语）时规则触发
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SafetyRule {
+    pub name: String,
+    pub description: String,            // 拒答原因，填入模板的{reason}
+    pub target: PolicyTarget,
+    pub keyword_groups: Vec<Vec<String>>,
+}
+
+impl SafetyRule {
+    /// 创建规则
+    pub fn new(name: &str, description: &str, target: PolicyTarget, keyword_groups: &[&[&str]]) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            target,
+            keyword_groups: keyword_groups
+                .iter()
+                .map(|group| group.iter().map(|word| word.to_string()).collect())
+                .collect(),
+        }
+    }
+
+    /// 规则是否命中规范化后的文本
+    fn matches(&self, normalized: &str) -> bool {
+        !self.keyword_groups.is_empty()
+            && self.keyword_groups.iter().all(|group| {
+                group.iter().any(|term| {
+                    let term = normalize(term);
+                    !term.trim().is_empty() && normalized.contains(&term)
+                })
+            })
+    }
+}
+
+/// 单个领域的安全策略
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DomainSafetyPolicy {
+    pub disclaimer: Option<String>,     // 附加在回答末尾的免责声明
+    pub refusal_template: String,       // 拒答模板，{reason}替换为规则描述
+    pub rules: Vec<SafetyRule>,
+}
+
+/// 安全策略配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SafetyPolicyConfig {
+    pub enabled: bool,
+    pub domains: HashMap<String, DomainSafetyPolicy>,
+}
+
+impl Default for SafetyPolicyConfig {
+    fn default() -> Self {
+        let mut domains = HashMap::new();
+        domains.insert("medical".to_string(), DomainSafetyPolicy {
+            disclaimer: Some("This information is for general reference only and is not a substitute for professional medical advice.".to_string()),
+            refusal_template: "I can't help with {reason}. Please consult a licensed clinician who can assess the individual situation.".to_string(),
+            rules: vec![
+                SafetyRule::new(
+                    "personal-dosage",
+                    "dosage for a specific patient",
+                    PolicyTarget::Query,
+                    &[&["dosage", "dose", "how many mg", "how much should"], &["my", "i", "me", "patient", "child", "son", "daughter", "husband", "wife"]],
+                ),
+                SafetyRule::new(
+                    "personal-prescription",
+                    "individual prescriptions",
+                    PolicyTarget::Answer,
+                    &[&["you should take", "i prescribe", "your dose is"]],
+                ),
+            ],
+        });
+        domains.insert("legal".to_string(), DomainSafetyPolicy {
+            disclaimer: Some("This is general legal information, not legal advice; consult a qualified lawyer for your situation.".to_string()),
+            refusal_template: "I can't help with {reason}. Please contact a licensed attorney.".to_string(),
+            rules: vec![SafetyRule::new(
+                "personal-representation",
+                "acting as your legal representative",
+                PolicyTarget::Query,
+                &[&["represent me", "be my lawyer", "file my lawsuit"]],
+            )],
+        });
+        domains.insert("finance".to_string(), DomainSafetyPolicy {
+            disclaimer: Some("This is not investment advice; past performance does not guarantee future results.".to_string()),
+            refusal_template: "I can't help with {reason}. Please speak with a licensed financial advisor.".to_string(),
+            rules: vec![
+                SafetyRule::new(
+                    "personal-trade",
+                    "personalized buy or sell recommendations",
+                    PolicyTarget::Query,
+                    &[&["should i buy", "should i sell", "should i invest"]],
+                ),
+                SafetyRule::new(
+                    "guaranteed-returns",
+                    "promises of guaranteed returns",
+                    PolicyTarget::Answer,
+                    &[&["guaranteed return", "guaranteed profit", "risk free", "risk-free"]],
+                ),
+            ],
+        });
+
+        Self { enabled: true, domains }
+    }
+}
+
+/// 策略判定结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PolicyVerdict {
+    Allow,
+    Refuse { rule: String, message: String },   // 命中的规则和按模板生成的拒答
+}
+
+/// 规范化文本：小写，非字母数字字符替换为空格，首尾补空格以便按整词匹配
+fn normalize(text: &str) -> String {
+    let cleaned: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    format!(" {} ", cleaned.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// 安全策略引擎
+pub struct SafetyPolicyEngine {
+    config: Arc<RwLock<SafetyPolicyConfig>>,
+}
+
+impl Default for SafetyPolicyEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SafetyPolicyEngine {
+    /// 使用默认策略创建
+    pub fn new() -> Self {
+        Self::with_config(SafetyPolicyConfig::default())
+    }
+
+    /// 使用指定策略创建
+    pub fn with_config(config: SafetyPolicyConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+        }
+    }
+
+    /// 检查用户查询
+    pub async fn evaluate_query(&self, domain: &str, query: &str) -> PolicyVerdict {
+        self.evaluate(domain, query, PolicyTarget::Query).await
+    }
+
+    /// 检查生成的回答
+    pub async fn evaluate_answer(&self, domain: &str, answer: &str) -> PolicyVerdict {
+        self.evaluate(domain, answer, PolicyTarget::Answer).await
+    }
+
+    async fn evaluate(&self, domain: &str, text: &str, target: PolicyTarget) -> PolicyVerdict {
+        let config = self.config.read().await;
+        if !config.enabled {
+            return PolicyVerdict::Allow;
+        }
+        let Some(policy) = config.domains.get(domain) else {
+            return PolicyVerdict::Allow;
+        };
+        let normalized = normalize(text);
+        policy
+            .rules
+            .iter()
+            .filter(|rule| rule.target == target || rule.target == PolicyTarget::Both)
+            .find(|rule| rule.matches(&normalized))
+            .map(|rule| PolicyVerdict::Refuse {
+                rule: rule.name.clone(),
+                message: policy.refusal_template.replace("{reason}", &rule.description),
+            })
+            .unwrap_or(PolicyVerdict::Allow)
+    }
+
+    /// 在回答末尾附加领域免责声明
+    pub async fn apply_disclaimer(&self, domain: &str, answer: &str) -> String {
+        let config = self.config.read().await;
+        match config.domains.get(domain).and_then(|policy| policy.disclaimer.as_ref()) {
+            Some(disclaimer) if config.enabled => format!("{}\n\n{}", answer, disclaimer),
+            _ => answer.to_string(),
+        }
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: SafetyPolicyConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> SafetyPolicyConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::RequestProcessor;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::test_support::FixedGenerator;
+
+    #[tokio::test]
+    async fn test_domain_safety_policies() {
+        let engine = SafetyPolicyEngine::new();
+        assert!(matches!(
+            engine.evaluate_query("medical", "What dose of amoxicillin should my son take?").await,
+            PolicyVerdict::Refuse { ref rule, .. } if rule == "personal-dosage"
+        ));
+        // 一般性问题不触发；"i"需要整词匹配
+        assert_eq!(engine.evaluate_query("medical", "What is the usual adult dose of amoxicillin?").await, PolicyVerdict::Allow);
+        assert_eq!(engine.evaluate_query("technical", "should i buy more RAM").await, PolicyVerdict::Allow);
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let processor = RequestProcessor::new(manager.clone(), selector.clone())
+            .with_text_generator(Arc::new(FixedGenerator::new("Index funds spread risk across many holdings.")));
+
+        let refused = processor
+            .process_request("u1".to_string(), "s1".to_string(), "Should I buy Tesla stock?".to_string(), "finance".to_string())
+            .await
+            .unwrap();
+        assert_eq!(refused.refused_by.as_deref(), Some("personal-trade"));
+        assert!(refused.answer.unwrap().contains("licensed financial advisor"));
+
+        let allowed = processor
+            .process_request("u1".to_string(), "s1".to_string(), "How do index funds work?".to_string(), "finance".to_string())
+            .await
+            .unwrap();
+        assert!(allowed.refused_by.is_none());
+        assert!(allowed.answer.unwrap().ends_with("past performance does not guarantee future results."));
+
+        // 回答命中规则时替换为拒答
+        let processor = RequestProcessor::new(manager, selector)
+            .with_text_generator(Arc::new(FixedGenerator::new("This fund offers a guaranteed return of 12%.")));
+        let result = processor
+            .process_request("u1".to_string(), "s1".to_string(), "Which fund is best?".to_string(), "finance".to_string())
+            .await
+            .unwrap();
+        assert_eq!(result.refused_by.as_deref(), Some("guaranteed-returns"));
+    }
+}
@@ -0,0 +1,271 @@
+//! 影子模式 - 将部分线上请求镜像到备选的选择器/策略/模型配置，在后台运行并记录对比指标，不影响实际响应
+
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Instant;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{RwLock, Semaphore};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use crate::selection::async_context_selector::ContextSelector;
+use crate::utils::ai_client::TextGenerator;
+use crate::utils::outbound_scheduler::CallPriority;
+
+/// 保留的最近对比记录数
+const RECENT_COMPARISONS: usize = 100;
+
+/// 影子模式配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowConfig {
+    pub enabled: bool,
+    pub sample_rate: f64,       // 镜像的请求比例（0-1），按请求ID确定性抽样
+    pub max_in_flight: usize,   // 同时运行的影子请求上限，超出时跳过
+}
+
+impl Default for ShadowConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_rate: 0.05,
+            max_in_flight: 10,
+        }
+    }
+}
+
+/// 主路径的处理结果，作为影子对比的基准
+#[derive(Debug, Clone)]
+pub struct PrimaryOutcome {
+    pub request_id: Uuid,
+    pub user_id: String,
+    pub session_id: String,
+    pub query: String,
+    pub domain: String,
+    pub context_ids: Vec<Uuid>,
+    pub selection_latency_ms: f64,
+    pub answer: Option<String>,
+}
+
+/// 单个请求的对比结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowComparison {
+    pub request_id: Uuid,
+    pub domain: String,
+    pub primary_context_ids: Vec<Uuid>,
+    pub shadow_context_ids: Vec<Uuid>,
+    pub overlap: f64,                       // 两次选择结果的Jaccard相似度
+    pub top1_match: bool,                   // 排名第一的上下文是否一致
+    pub primary_latency_ms: f64,
+    pub shadow_latency_ms: f64,
+    pub primary_answer_chars: Option<usize>,
+    pub shadow_answer_chars: Option<usize>,
+    pub error: Option<String>,              // 影子请求失败原因
+    pub timestamp: DateTime<Utc>,
+}
+
+/// 影子模式汇总指标
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShadowMetrics {
+    pub mirrored: u64,                  // 完成对比的请求数
+    pub skipped: u64,                   // 因并发上限跳过的请求数
+    pub shadow_errors: u64,
+    pub avg_overlap: f64,
+    pub top1_agreement_rate: f64,
+    pub avg_primary_latency_ms: f64,
+    pub avg_shadow_latency_ms: f64,
+    pub recent: VecDeque<ShadowComparison>,
+}
+
+impl ShadowMetrics {
+    fn record(&mut self, comparison: ShadowComparison) {
+        if comparison.error.is_some() {
+            self.shadow_errors += 1;
+        } else {
+            let n = self.mirrored as f64;
+            let average = |current: f64, value: f64| (current * n + value) / (n + 1.0);
+            self.avg_overlap = average(self.avg_overlap, comparison.overlap);
+            self.top1_agreement_rate = average(self.top1_agreement_rate, if comparison.top1_match { 1.0 } else { 0.0 });
+            self.avg_primary_latency_ms = average(self.avg_primary_latency_ms, comparison.primary_latency_ms);
+            self.avg_shadow_latency_ms = average(self.avg_shadow_latency_ms, comparison.shadow_latency_ms);
+            self.mirrored += 1;
+        }
+        self.recent.push_back(comparison);
+        while self.recent.len() > RECENT_COMPARISONS {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// 两组上下文ID的Jaccard相似度，均为空时视为一致
+fn jaccard(a: &[Uuid], b: &[Uuid]) -> f64 {
+    let a: HashSet<&Uuid> = a.iter().collect();
+    let b: HashSet<&Uuid> = b.iter().collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        1.0
+    } else {
+        a.intersection(&b).count() as f64 / union as f64
+    }
+}
+
+/// 影子管道 - 使用备选选择器（及可选的备选生成模型）重放抽样请求
+pub struct ShadowPipeline {
+    config: Arc<RwLock<ShadowConfig>>,
+    selector: Arc<ContextSelector>,
+    generator: Option<Arc<dyn TextGenerator>>,
+    in_flight: Arc<Semaphore>,
+    metrics: Arc<RwLock<ShadowMetrics>>,
+}
+
+impl ShadowPipeline {
+    /// 使用备选选择器创建影子管道（选择器应与主路径共享上下文管理器）
+    pub fn new(selector: Arc<ContextSelector>, config: ShadowConfig) -> Self {
+        Self {
+            in_flight: Arc::new(Semaphore::new(config.max_in_flight.max(1))),
+            config: Arc::new(RwLock::new(config)),
+            selector,
+            generator: None,
+            metrics: Arc::new(RwLock::new(ShadowMetrics::default())),
+        }
+    }
+
+    /// 设置备选生成模型，主路径生成了回答时影子管道也生成回答（以后台优先级调用）
+    pub fn with_text_generator(mut self, generator: Arc<dyn TextGenerator>) -> Self {
+        self.generator = Some(generator);
+        self
+    }
+
+    /// 请求是否被抽中
+    pub async fn should_mirror(&self, request_id: Uuid) -> bool {
+        let config = self.config.read().await;
+        let bucket = (request_id.as_u128() % 10_000) as f64 / 10_000.0;
+        config.enabled && bucket < config.sample_rate.clamp(0.0, 1.0)
+    }
+
+    /// 在后台镜像一个已完成的请求；未抽中或达到并发上限时返回None
+    pub async fn mirror(self: &Arc<Self>, primary: PrimaryOutcome) -> Option<JoinHandle<()>> {
+        if !self.should_mirror(primary.request_id).await {
+            return None;
+        }
+        let Ok(permit) = self.in_flight.clone().try_acquire_owned() else {
+            self.metrics.write().await.skipped += 1;
+            return None;
+        };
+        let pipeline = self.clone();
+        Some(tokio::spawn(async move {
+            let comparison = pipeline.run_shadow(&primary).await;
+            pipeline.metrics.write().await.record(comparison);
+            drop(permit);
+        }))
+    }
+
+    /// 执行影子选择（和生成）并与主路径对比
+    async fn run_shadow(&self, primary: &PrimaryOutcome) -> ShadowComparison {
+        let started = Instant::now();
+        let selection = self
+            .selector
+            .select_contexts(&primary.user_id, &primary.session_id, &primary.query, &primary.domain)
+            .await;
+        let shadow_latency_ms = started.elapsed().as_secs_f64() * 1000.0;
+
+        let mut comparison = ShadowComparison {
+            request_id: primary.request_id,
+            domain: primary.domain.clone(),
+            primary_context_ids: primary.context_ids.clone(),
+            shadow_context_ids: Vec::new(),
+            overlap: 0.0,
+            top1_match: false,
+            primary_latency_ms: primary.selection_latency_ms,
+            shadow_latency_ms,
+            primary_answer_chars: primary.answer.as_ref().map(|answer| answer.chars().count()),
+            shadow_answer_chars: None,
+            error: None,
+            timestamp: Utc::now(),
+        };
+        let shadow_contexts = match selection {
+            Ok(contexts) => contexts,
+            Err(e) => {
+                comparison.error = Some(e.to_string());
+                return comparison;
+            }
+        };
+        comparison.shadow_context_ids = shadow_contexts.iter().map(|context| context.id).collect();
+        comparison.overlap = jaccard(&comparison.primary_context_ids, &comparison.shadow_context_ids);
+        comparison.top1_match = comparison.primary_context_ids.first() == comparison.shadow_context_ids.first();
+
+        if let (Some(generator), Some(_)) = (&self.generator, &primary.answer) {
+            let passages: Vec<&str> = shadow_contexts.iter().map(|context| context.context_data.as_str()).collect();
+            let prompt = format!("{}\n\nQuestion: {}", passages.join("\n"), primary.query);
+            match generator.generate(&prompt, None, CallPriority::Background).await {
+                Ok(answer) => comparison.shadow_answer_chars = Some(answer.chars().count()),
+                Err(e) => comparison.error = Some(e.to_string()),
+            }
+        }
+        comparison
+    }
+
+    /// 获取汇总指标
+    pub async fn get_metrics(&self) -> ShadowMetrics {
+        self.metrics.read().await.clone()
+    }
+
+    /// 更新配置（并发上限在创建时确定）
+    pub async fn update_config(&self, new_config: ShadowConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> ShadowConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::RequestProcessor;
+
+    #[tokio::test]
+    async fn test_shadow_mirrors_without_affecting_response() {
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        for text in ["pneumonia antibiotics", "pneumonia vaccine schedule", "pneumonia chest imaging"] {
+            manager
+                .create_context("s0".to_string(), "u0".to_string(), "medical".to_string(), text.to_string(), 5)
+                .await
+                .unwrap();
+        }
+        let primary = Arc::new(ContextSelector::new(manager.clone()));
+        let candidate = Arc::new(ContextSelector::new(manager.clone()));
+        let mut config = candidate.get_config().await;
+        config.max_contexts_to_return = 1;
+        candidate.update_config(config).await;
+
+        let shadow = Arc::new(ShadowPipeline::new(candidate, ShadowConfig { enabled: true, sample_rate: 1.0, max_in_flight: 4 }));
+        let processor = RequestProcessor::new(manager.clone(), primary).with_shadow_pipeline(shadow.clone());
+        let result = processor
+            .process_request("u1".to_string(), "s1".to_string(), "pneumonia".to_string(), "medical".to_string())
+            .await
+            .unwrap();
+        assert_eq!(result.selected_contexts.len(), 3);
+
+        let mut metrics = shadow.get_metrics().await;
+        for _ in 0..100 {
+            if metrics.mirrored > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            metrics = shadow.get_metrics().await;
+        }
+        assert_eq!(metrics.mirrored, 1);
+        let comparison = &metrics.recent[0];
+        assert_eq!(comparison.request_id, result.request_id);
+        assert_eq!(comparison.shadow_context_ids.len(), 1);
+        assert!((comparison.overlap - 1.0 / 3.0).abs() < 1e-9);
+        assert!(comparison.top1_match);
+
+        shadow.update_config(ShadowConfig { sample_rate: 0.0, ..shadow.get_config().await }).await;
+        assert!(!shadow.should_mirror(Uuid::new_v4()).await);
+    }
+}
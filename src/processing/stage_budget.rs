@@ -0,0 +1,134 @@
+//! 阶段时间预算 - 按请求的剩余时间和各阶段权重动态分配阶段超时，为后续阶段预留时间，
+//! 避免上下文选择耗尽整个请求预算后回答生成才开始；前一阶段未用完的时间自动留给后续阶段
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Duration;
+use crate::monitoring::monitoring::RequestStage;
+use crate::utils::request_context::RequestContext;
+
+/// 各阶段分得剩余时间的权重
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageBudgetWeights {
+    pub selection: f64,     // 上下文选择
+    pub generation: f64,    // 回答生成
+}
+
+impl Default for StageBudgetWeights {
+    fn default() -> Self {
+        Self {
+            selection: 1.0,
+            generation: 3.0,
+        }
+    }
+}
+
+impl StageBudgetWeights {
+    /// 校验权重为正数
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, weight) in [("selection", self.selection), ("generation", self.generation)] {
+            if !weight.is_finite() || weight <= 0.0 {
+                return Err(format!("stage_budget_weights.{} must be a positive number", name));
+            }
+        }
+        Ok(())
+    }
+
+    fn weight(&self, stage: RequestStage) -> f64 {
+        match stage {
+            RequestStage::Selection => self.selection,
+            RequestStage::Generation => self.generation,
+            _ => 0.0,
+        }
+    }
+}
+
+/// 一个阶段分得的时间预算
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StageBudget {
+    pub stage: RequestStage,
+    pub budget: Duration,
+    pub limited_by_deadline: bool,  // 预算是否因请求剩余时间不足而小于阶段的默认超时
+}
+
+impl StageBudget {
+    /// 计算阶段预算：剩余时间按本阶段与后续阶段的权重比例分配，且不超过阶段的默认超时
+    pub fn allocate(
+        request: &RequestContext,
+        stage: RequestStage,
+        stage_default: Duration,
+        weights: &StageBudgetWeights,
+        later_stages: &[RequestStage],
+    ) -> Self {
+        let Some(remaining) = request.remaining() else {
+            return Self { stage, budget: stage_default, limited_by_deadline: false };
+        };
+        let weight = weights.weight(stage);
+        let reserved: f64 = later_stages.iter().map(|later| weights.weight(*later)).sum();
+        let share = if weight + reserved > 0.0 {
+            remaining.mul_f64(weight / (weight + reserved))
+        } else {
+            remaining
+        };
+        Self {
+            stage,
+            budget: share.min(stage_default),
+            limited_by_deadline: share < stage_default,
+        }
+    }
+
+    /// 预算的毫秒数
+    pub fn budget_ms(&self) -> u64 {
+        self.budget.as_millis() as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use uuid::Uuid;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::{RequestError, RequestOptions, RequestProcessor};
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::test_support::FixedGenerator;
+
+    #[tokio::test]
+    async fn test_stage_budgets_follow_remaining_deadline() {
+        let weights = StageBudgetWeights::default();
+        let request = RequestContext::new(Uuid::new_v4()).with_timeout(Duration::from_secs(8));
+
+        // 选择阶段为生成阶段预留3/4的剩余时间
+        let selection = StageBudget::allocate(&request, RequestStage::Selection, Duration::from_secs(5), &weights, &[RequestStage::Generation]);
+        assert!(selection.limited_by_deadline);
+        assert!(selection.budget <= Duration::from_secs(2) && selection.budget > Duration::from_millis(1900));
+        // 没有后续阶段时可使用全部剩余时间，但不超过阶段默认超时
+        let alone = StageBudget::allocate(&request, RequestStage::Selection, Duration::from_secs(5), &weights, &[]);
+        assert_eq!(alone.budget, Duration::from_secs(5));
+        assert!(!alone.limited_by_deadline);
+        let unlimited = StageBudget::allocate(&RequestContext::default(), RequestStage::Generation, Duration::from_secs(20), &weights, &[]);
+        assert_eq!(unlimited.budget, Duration::from_secs(20));
+        assert!(StageBudgetWeights { selection: 0.0, generation: 1.0 }.validate().is_err());
+
+        // 超时错误指明耗尽预算的阶段
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia is treated with antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let processor = RequestProcessor::new(manager, selector).with_text_generator(Arc::new(FixedGenerator::new("late").with_delay(Duration::from_secs(5))));
+        let options = RequestOptions { deadline_ms: Some(200), ..RequestOptions::default() };
+        let error = processor
+            .process_request_with_options("u1".to_string(), "s1".to_string(), "pneumonia".to_string(), "medical".to_string(), options)
+            .await
+            .unwrap_err();
+        match error {
+            RequestError::StageTimeout { stage, budget_ms, limited_by_deadline } => {
+                assert_eq!(stage, RequestStage::Generation);
+                assert!(budget_ms <= 200);
+                assert!(limited_by_deadline);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 预算耗尽后的处理方式
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BudgetExhaustedAction {
+    Degrade,    // 降级处理（更少的上下文、更便宜的模型）
+    Reject,     // 拒绝请求
+}
+
+/// 令牌预算配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenBudgetConfig {
+    pub soft_limit_ratio: f64,              // 使用量达到预算该比例后开始降级
+    pub degraded_max_contexts: usize,       // 接近预算时的最大上下文数
+    pub exhausted_max_contexts: usize,      // 预算耗尽（降级模式）时的最大上下文数
+    pub fallback_model: Option<String>,     // 降级时使用的低成本模型
+    pub exhausted_action: BudgetExhaustedAction, // 预算耗尽后的处理方式
+}
+
+impl Default for TokenBudgetConfig {
+    fn default() -> Self {
+        Self {
+            soft_limit_ratio: 0.9,
+            degraded_max_contexts: 3,
+            exhausted_max_contexts: 1,
+            fallback_model: None,
+            exhausted_action: BudgetExhaustedAction::Reject,
+        }
+    }
+}
+
+/// 单个领域的月度令牌预算
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainBudget {
+    pub monthly_limit: u64,     // 每月令牌上限
+    pub used_tokens: u64,       // 本月已使用令牌数
+    pub period: String,         // 统计周期（YYYY-MM）
+}
+
+/// 预算决策 - 决定请求按正常、降级还是拒绝处理
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum BudgetDecision {
+    Normal,
+    Degraded { max_contexts: usize, model: Option<String> },
+    Rejected,
+}
+
+impl BudgetDecision {
+    /// 降级时改用的模型，None表示使用默认模型
+    pub fn model(&self) -> Option<&str> {
+        match self {
+            BudgetDecision::Degraded { model, .. } => model.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// 领域预算状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetStatus {
+    pub domain: String,
+    pub period: String,
+    pub monthly_limit: u64,
+    pub used_tokens: u64,
+    pub remaining_tokens: u64,
+}
+
+/// 令牌预算管理器 - 按领域跟踪生成令牌用量并执行月度预算
+pub struct TokenBudgetManager {
+    config: Arc<RwLock<TokenBudgetConfig>>,
+    budgets: Arc<RwLock<HashMap<String, DomainBudget>>>,
+}
+
+impl Default for TokenBudgetManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenBudgetManager {
+    /// 创建新的令牌预算管理器
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(RwLock::new(TokenBudgetConfig::default())),
+            budgets: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 计算时间点所属的统计周期
+    fn period_of(time: DateTime<Utc>) -> String {
+        format!("{:04}-{:02}", time.year(), time.month())
+    }
+
+    /// 进入新的月份时重置用量
+    fn roll_period(budget: &mut DomainBudget, period: &str) {
+        if budget.period != period {
+            budget.period = period.to_string();
+            budget.used_tokens = 0;
+        }
+    }
+
+    /// 设置领域的月度令牌预算
+    pub async fn set_budget(&self, domain: &str, monthly_limit: u64) {
+        let period = Self::period_of(Utc::now());
+        let mut budgets = self.budgets.write().await;
+        let budget = budgets.entry(domain.to_string()).or_insert_with(|| DomainBudget {
+            monthly_limit,
+            used_tokens: 0,
+            period: period.clone(),
+        });
+        Self::roll_period(budget, &period);
+        budget.monthly_limit = monthly_limit;
+    }
+
+    /// 移除领域预算（不再限制）
+    pub async fn remove_budget(&self, domain: &str) {
+        self.budgets.write().await.remove(domain);
+    }
+
+    /// 记录领域的生成令牌用量
+    pub async fn record_usage(&self, domain: &str, tokens: u64) {
+        let period = Self::period_of(Utc::now());
+        let mut budgets = self.budgets.write().await;
+        if let Some(budget) = budgets.get_mut(domain) {
+            Self::roll_period(budget, &period);
+            budget.used_tokens = budget.used_tokens.saturating_add(tokens);
+        }
+    }
+
+    /// 获取领域剩余令牌数（未设置预算时返回None）
+    pub async fn remaining(&self, domain: &str) -> Option<u64> {
+        self.get_status(domain).await.map(|status| status.remaining_tokens)
+    }
+
+    /// 获取领域预算状态
+    pub async fn get_status(&self, domain: &str) -> Option<BudgetStatus> {
+        let period = Self::period_of(Utc::now());
+        let budgets = self.budgets.read().await;
+        budgets.get(domain).map(|budget| {
+            let used_tokens = if budget.period == period { budget.used_tokens } else { 0 };
+            BudgetStatus {
+                domain: domain.to_string(),
+                period: period.clone(),
+                monthly_limit: budget.monthly_limit,
+                used_tokens,
+                remaining_tokens: budget.monthly_limit.saturating_sub(used_tokens),
+            }
+        })
+    }
+
+    /// 获取所有领域的预算状态
+    pub async fn get_all_statuses(&self) -> Vec<BudgetStatus> {
+        let domains: Vec<String> = self.budgets.read().await.keys().cloned().collect();
+        let mut statuses = Vec::new();
+        for domain in domains {
+            if let Some(status) = self.get_status(&domain).await {
+                statuses.push(status);
+            }
+        }
+        statuses
+    }
+
+    /// 根据领域当前用量做出预算决策
+    pub async fn evaluate(&self, domain: &str) -> BudgetDecision {
+        let status = match self.get_status(domain).await {
+            Some(status) => status,
+            None => return BudgetDecision::Normal,
+        };
+        let config = self.config.read().await;
+
+        if status.remaining_tokens == 0 {
+            match config.exhausted_action {
+                BudgetExhaustedAction::Reject => BudgetDecision::Rejected,
+                BudgetExhaustedAction::Degrade => BudgetDecision::Degraded {
+                    max_contexts: config.exhausted_max_contexts,
+                    model: config.fallback_model.clone(),
+                },
+            }
+        } else if status.used_tokens as f64 >= status.monthly_limit as f64 * config.soft_limit_ratio {
+            BudgetDecision::Degraded {
+                max_contexts: config.degraded_max_contexts,
+                model: config.fallback_model.clone(),
+            }
+        } else {
+            BudgetDecision::Normal
+        }
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: TokenBudgetConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> TokenBudgetConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_token_budget() {
+        let manager = TokenBudgetManager::new();
+
+        // 未设置预算的领域不受限制
+        assert_eq!(manager.evaluate("medical").await, BudgetDecision::Normal);
+        assert!(manager.remaining("medical").await.is_none());
+
+        manager.set_budget("medical", 1000).await;
+        manager.record_usage("medical", 500).await;
+        assert_eq!(manager.remaining("medical").await, Some(500));
+        assert_eq!(manager.evaluate("medical").await, BudgetDecision::Normal);
+
+        // 超过软限制后降级
+        manager.record_usage("medical", 450).await;
+        assert!(matches!(
+            manager.evaluate("medical").await,
+            BudgetDecision::Degraded { max_contexts: 3, .. }
+        ));
+
+        // 预算耗尽后拒绝
+        manager.record_usage("medical", 100).await;
+        assert_eq!(manager.remaining("medical").await, Some(0));
+        assert_eq!(manager.evaluate("medical").await, BudgetDecision::Rejected);
+
+        // 配置为降级模式时使用低成本模型
+        let mut config = manager.get_config().await;
+        config.exhausted_action = BudgetExhaustedAction::Degrade;
+        config.fallback_model = Some("small-model".to_string());
+        manager.update_config(config).await;
+        assert_eq!(
+            manager.evaluate("medical").await,
+            BudgetDecision::Degraded { max_contexts: 1, model: Some("small-model".to_string()) }
+        );
+    }
+}
@@ -3,7 +3,33 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
+use crate::context::consistency::ConsistencyLevel;
+use crate::context::access::Viewer;
 use crate::context::llm_context::{LLMContext, ContextManager};
+use crate::strategy::priority_tuner::PriorityTuner;
+use crate::selection::embedding::{cosine_similarity, Embedder, HashingEmbedder};
+use crate::selection::embedding_migration::{EmbeddingMigration, MigrationProgress};
+use crate::selection::hybrid_search::{bm25_scores, fuse_scores, HybridQuery, ScoredContext};
+use crate::selection::threshold_calibration::{CalibrationReport, RelevanceCalibrator};
+use crate::selection::scoring_cache::{QueryEmbeddingCache, ScoringCache};
+use crate::selection::rag_fusion::{
+    parse_variations, reciprocal_rank_fusion, variation_prompt, GenerationBudget, GenerationUsage, MultiQueryConfig, RetrievalMode,
+};
+use crate::selection::hyde::{hyde_prompt, truncate_words, HydeConfig};
+use crate::selection::language_routing::{query_language, route_by_language, LanguageRoutingConfig};
+use crate::selection::post_filters::{apply_post_filters, PostFilterConfig};
+use crate::selection::personalization::{RankingWeights, UserProfileStore, UserRankingProfile};
+use crate::selection::ranking::{
+    compare_scores_desc, compare_ties, default_tie_breakers, sort_scored, TieBreaker, TieRotationConfig, TieRotationMode, TieRotator,
+};
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem, PerformanceMetric};
+use crate::utils::ai_client::TextGenerator;
+use crate::utils::outbound_scheduler::{CallPriority, OutboundScheduler};
+use crate::utils::request_context::{RequestContext, FLAG_LLM_RETRIEVAL};
+use crate::utils::tokenizer::{Tokenizer, WhitespaceTokenizer};
+
+/// 请求剩余时间少于该值时跳过检索阶段的大模型调用，直接使用标准检索
+const MIN_LLM_RETRIEVAL_TIME: std::time::Duration = std::time::Duration::from_millis(500);
 
 /// 上下文选择策略
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,6 +48,25 @@ pub struct ContextSelectorConfig {
     pub selection_strategy: ContextSelectionStrategy, // 选择策略
     pub enable_cache: bool,             // 是否启用缓存
     pub cache_ttl_seconds: u64,         // 缓存TTL（秒）
+    pub domain_relevance_thresholds: HashMap<String, f64>, // 按领域覆盖的最小相关性分数
+    #[serde(default)]
+    pub retrieval_mode: RetrievalMode,  // 检索模式
+    #[serde(default)]
+    pub multi_query: MultiQueryConfig,  // 多查询检索配置
+    #[serde(default)]
+    pub domain_retrieval_modes: HashMap<String, RetrievalMode>, // 按领域覆盖的检索模式
+    #[serde(default)]
+    pub hyde: HydeConfig,               // HyDE检索配置
+    #[serde(default = "default_tie_breakers")]
+    pub tie_breakers: Vec<TieBreaker>,  // 同分时的决胜顺序（最终总是按ID）
+    #[serde(default)]
+    pub tie_rotation: TieRotationConfig, // 同分轮换（开启后不使用查询缓存）
+    #[serde(default)]
+    pub language_routing: LanguageRoutingConfig, // 按查询语言优先返回同语言的上下文
+    #[serde(default)]
+    pub post_filters: PostFilterConfig, // 排序后的过滤（更新时间窗口、来源/标签白名单、每个来源的数量上限）
+    #[serde(default)]
+    pub domain_post_filters: HashMap<String, PostFilterConfig>, // 按领域覆盖的后置过滤
 }
 
 impl Default for ContextSelectorConfig {
@@ -32,16 +77,76 @@ impl Default for ContextSelectorConfig {
             selection_strategy: ContextSelectionStrategy::Hybrid,
             enable_cache: true,
             cache_ttl_seconds: 300, // 5分钟
+            domain_relevance_thresholds: HashMap::new(),
+            retrieval_mode: RetrievalMode::Standard,
+            multi_query: MultiQueryConfig::default(),
+            domain_retrieval_modes: HashMap::new(),
+            hyde: HydeConfig::default(),
+            tie_breakers: default_tie_breakers(),
+            tie_rotation: TieRotationConfig::default(),
+            language_routing: LanguageRoutingConfig::default(),
+            post_filters: PostFilterConfig::default(),
+            domain_post_filters: HashMap::new(),
         }
     }
 }
 
+/// 缓存的选择结果 - 记录每个上下文被缓存时的版本，读取时与管理器中的当前版本比对
+#[derive(Debug, Clone)]
+struct CachedSelection {
+    context_versions: Vec<(Uuid, u32)>,         // 按选择顺序排列的(上下文ID, 版本)
+    cached_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// 查询缓存键；按字段比较，用户ID中含有分隔符时也不会与其他用户的键混淆
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct QueryCacheKey {
+    user_id: String,
+    session_id: String,
+    query: String,
+    domain: String,
+    locale: Option<String>,     // 查询语言无法识别时按语言区域路由，结果随之不同
+}
+
+/// 查询缓存表：查询键 -> 缓存的选择结果
+type QueryContextCache = HashMap<QueryCacheKey, CachedSelection>;
+
 /// 上下文选择器 - 企业级大模型上下文选择
 pub struct ContextSelector {
     config: Arc<RwLock<ContextSelectorConfig>>,
     context_manager: Arc<ContextManager>,
     /// 查询-上下文ID缓存
-    query_context_cache: Arc<RwLock<HashMap<String, (Vec<Uuid>, chrono::DateTime<chrono::Utc>)>>>,
+    query_context_cache: Arc<RwLock<QueryContextCache>>,
+    /// 可选的优先级自动调整器，用于记录选择频率
+    priority_tuner: Option<Arc<PriorityTuner>>,
+    /// 向量化器，用于混合检索中的向量相似度（迁移完成时原子替换）
+    embedder: Arc<RwLock<Arc<dyn Embedder>>>,
+    /// 进行中的嵌入模型迁移
+    migration: Arc<RwLock<Option<Arc<EmbeddingMigration>>>>,
+    /// 最近一次迁移的进度
+    last_migration: Arc<RwLock<Option<MigrationProgress>>>,
+    /// 相关性阈值校准器
+    calibrator: Arc<RelevanceCalibrator>,
+    /// 按上下文版本缓存的分词结果和向量
+    scoring_cache: Arc<ScoringCache>,
+    /// 按规范化查询文本缓存的查询向量
+    query_embedding_cache: Arc<QueryEmbeddingCache>,
+    /// 可选的监控系统，用于记录查询向量缓存命中率
+    monitoring: Option<Arc<MonitoringSystem>>,
+    /// 用户排序偏好
+    profiles: Arc<UserProfileStore>,
+    /// 可选的文本生成器，用于多查询检索生成查询变体（AI端点地址热加载时整体替换）
+    generator: Arc<RwLock<Option<Arc<dyn TextGenerator>>>>,
+    /// 多查询检索调用大模型的每小时预算
+    generation_budget: Arc<GenerationBudget>,
+    /// HyDE检索调用大模型的每小时预算
+    hyde_budget: Arc<GenerationBudget>,
+    /// 同分轮换状态
+    tie_rotator: Arc<TieRotator>,
+    /// 检索词项使用的分词器
+    tokenizer: Arc<dyn Tokenizer>,
+    /// 可选的外呼调度器，嵌入模型迁移的回填以后台优先级等待配额
+    scheduler: Option<Arc<OutboundScheduler>>,
 }
 
 impl ContextSelector {
@@ -51,9 +156,83 @@ impl ContextSelector {
             config: Arc::new(RwLock::new(ContextSelectorConfig::default())),
             context_manager,
             query_context_cache: Arc::new(RwLock::new(HashMap::new())),
+            priority_tuner: None,
+            embedder: Arc::new(RwLock::new(Arc::new(HashingEmbedder::default()))),
+            migration: Arc::new(RwLock::new(None)),
+            last_migration: Arc::new(RwLock::new(None)),
+            calibrator: Arc::new(RelevanceCalibrator::new()),
+            scoring_cache: Arc::new(ScoringCache::default()),
+            query_embedding_cache: Arc::new(QueryEmbeddingCache::default()),
+            monitoring: None,
+            profiles: Arc::new(UserProfileStore::new()),
+            generator: Arc::new(RwLock::new(None)),
+            generation_budget: Arc::new(GenerationBudget::new()),
+            hyde_budget: Arc::new(GenerationBudget::new()),
+            tie_rotator: Arc::new(TieRotator::new()),
+            tokenizer: Arc::new(WhitespaceTokenizer),
+            scheduler: None,
         }
     }
 
+    /// 使用指定的用户偏好存储（例如在多个选择器间共享）
+    pub fn with_profile_store(mut self, profiles: Arc<UserProfileStore>) -> Self {
+        self.profiles = profiles;
+        self
+    }
+
+    /// 关联监控系统，记录查询向量缓存的访问和命中率
+    pub fn with_monitoring(mut self, monitoring: Arc<MonitoringSystem>) -> Self {
+        self.monitoring = Some(monitoring);
+        self
+    }
+
+    /// 使用指定的查询向量缓存（例如调整容量或在多个选择器间共享）
+    pub fn with_query_embedding_cache(mut self, query_embedding_cache: Arc<QueryEmbeddingCache>) -> Self {
+        self.query_embedding_cache = query_embedding_cache;
+        self
+    }
+
+    /// 使用指定的评分缓存（例如调整内存上限或在多个选择器间共享）
+    pub fn with_scoring_cache(mut self, scoring_cache: Arc<ScoringCache>) -> Self {
+        self.scoring_cache = scoring_cache;
+        self
+    }
+
+    /// 使用指定的分词器切分查询和上下文（应与请求处理器和入库使用的分词器一致）
+    pub fn with_tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = tokenizer;
+        self
+    }
+
+    /// 获取分词器
+    pub fn get_tokenizer(&self) -> Arc<dyn Tokenizer> {
+        self.tokenizer.clone()
+    }
+
+    /// 替换向量化器（例如接入外部嵌入模型）
+    pub fn with_embedder(mut self, embedder: Arc<dyn Embedder>) -> Self {
+        self.embedder = Arc::new(RwLock::new(embedder));
+        self
+    }
+
+    /// 关联文本生成器（通常为AI客户端），启用多查询检索和HyDE检索
+    pub fn with_text_generator(mut self, generator: Arc<dyn TextGenerator>) -> Self {
+        self.generator = Arc::new(RwLock::new(Some(generator)));
+        self
+    }
+
+    /// 关联外呼调度器，嵌入模型迁移不挤占交互请求的配额
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 关联优先级自动调整器，选择结果将计入其使用统计
+    pub fn with_priority_tuner(mut self, priority_tuner: Arc<PriorityTuner>) -> Self {
+        self.priority_tuner = Some(priority_tuner);
+        self
+    }
+
     /// 选择与查询最相关的上下文
     pub async fn select_contexts(
         &self,
@@ -62,9 +241,32 @@ impl ContextSelector {
         query: &str,
         domain: &str,
     ) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
-        // 检查缓存
-        if self.config.read().await.enable_cache {
-            if let Some(cached_result) = self.get_cached_contexts(query, domain).await {
+        self.select_contexts_for_request(&RequestContext::default(), user_id, session_id, query, domain).await
+    }
+
+    /// 在请求上下文中选择上下文：检索阶段的大模型调用受截止时间和功能开关约束
+    pub async fn select_contexts_for_request(
+        &self,
+        request: &RequestContext,
+        user_id: &str,
+        session_id: &str,
+        query: &str,
+        domain: &str,
+    ) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        // 检查缓存（同分轮换、请求覆盖了功能开关或要求强一致读时每次重新排序，不使用缓存）
+        let use_cache = {
+            let config = self.config.read().await;
+            config.enable_cache
+                && config.tie_rotation.mode == TieRotationMode::Off
+                && !request.has_feature_overrides()
+                && request.consistency == ConsistencyLevel::Eventual
+        };
+        if use_cache {
+            if let Some(cached_result) = self.get_cached_contexts(user_id, session_id, query, domain, request.locale.as_deref()).await {
+                // 缓存命中同样计入选择频率
+                if let Some(ref tuner) = self.priority_tuner {
+                    tuner.record_selection(&cached_result).await;
+                }
                 return Ok(cached_result);
             }
         }
@@ -81,15 +283,14 @@ impl ContextSelector {
         // 从领域获取上下文
         candidate_contexts.extend(self.context_manager.get_domain_contexts(domain).await);
 
-        // 移除重复项
+        // 移除重复项和对该用户不可见的上下文
         candidate_contexts = self.deduplicate_contexts(candidate_contexts).await;
+        candidate_contexts = self.context_manager.filter_visible(candidate_contexts, &Viewer::new(Some(user_id), Some(session_id))).await;
 
-        // 根据策略选择上下文
-        let selected_contexts = self.apply_selection_strategy(
-            candidate_contexts,
-            query,
-            &self.config.read().await.selection_strategy,
-        ).await;
+        // 根据检索模式和策略选择上下文，按查询语言调整顺序后应用后置过滤
+        let selected_contexts = self.rank_candidates(request, candidate_contexts, user_id, query, domain).await;
+        let selected_contexts = self.route_by_query_language(request, selected_contexts, query).await;
+        let selected_contexts = self.post_filter(selected_contexts, domain, chrono::Utc::now()).await;
 
         // 应用最大数量限制
         let final_contexts: Vec<LLMContext> = selected_contexts
@@ -97,71 +298,594 @@ impl ContextSelector {
             .take(self.config.read().await.max_contexts_to_return)
             .collect();
 
+        // 记录选择频率
+        if let Some(ref tuner) = self.priority_tuner {
+            tuner.record_selection(&final_contexts).await;
+        }
+
         // 缓存结果
-        if self.config.read().await.enable_cache {
-            self.cache_contexts(query, domain, &final_contexts).await;
+        if use_cache {
+            self.cache_contexts(user_id, session_id, query, domain, request.locale.as_deref(), &final_contexts).await;
         }
 
         Ok(final_contexts)
     }
 
+    /// 按时间点选择上下文：只使用该时间有效的上下文版本（需要上下文管理器启用版本历史），用于重现当时的检索结果，不读写缓存
+    pub async fn select_contexts_as_of(
+        &self,
+        user_id: &str,
+        session_id: &str,
+        query: &str,
+        domain: &str,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        self.select_contexts_as_of_for_request(&RequestContext::default(), user_id, session_id, query, domain, as_of).await
+    }
+
+    /// 在请求上下文中按时间点选择上下文
+    pub async fn select_contexts_as_of_for_request(
+        &self,
+        request: &RequestContext,
+        user_id: &str,
+        session_id: &str,
+        query: &str,
+        domain: &str,
+        as_of: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let candidate_contexts: Vec<LLMContext> = self
+            .context_manager
+            .get_contexts_as_of(as_of)
+            .await?
+            .into_iter()
+            .filter(|ctx| ctx.session_id == session_id || ctx.user_id == user_id || ctx.domain == domain)
+            .collect();
+        let candidate_contexts = self.context_manager.filter_visible(candidate_contexts, &Viewer::new(Some(user_id), Some(session_id))).await;
+
+        let selected_contexts = self.rank_candidates(request, candidate_contexts, user_id, query, domain).await;
+        let selected_contexts = self.route_by_query_language(request, selected_contexts, query).await;
+        let selected_contexts = self.post_filter(selected_contexts, domain, as_of).await;
+        Ok(selected_contexts
+            .into_iter()
+            .take(self.config.read().await.max_contexts_to_return)
+            .collect())
+    }
+
+    /// 混合检索 - 结合BM25词法得分、向量相似度和元数据过滤，按融合权重返回带得分的上下文
+    pub async fn hybrid_search(
+        &self,
+        query: HybridQuery,
+    ) -> Result<Vec<ScoredContext>, Box<dyn std::error::Error + Send + Sync>> {
+        // 收集候选上下文：从最窄的限定范围（会话、用户、领域）取候选，其余限定条件取交集
+        let mut candidates = if let Some(ref session_id) = query.session_id {
+            self.context_manager.get_session_contexts(session_id).await
+        } else if let Some(ref user_id) = query.user_id {
+            self.context_manager.get_user_contexts(user_id).await
+        } else if let Some(ref domain) = query.domain {
+            self.context_manager.get_domain_contexts(domain).await
+        } else {
+            self.context_manager.get_all_contexts().await
+        };
+        candidates = self.deduplicate_contexts(candidates).await;
+        let viewer = Viewer::new(query.user_id.as_deref(), query.session_id.as_deref());
+        candidates = self.context_manager.filter_visible(candidates, &viewer).await;
+
+        // 应用会话、用户、领域限定和元数据过滤
+        candidates.retain(|ctx| {
+            query.session_id.as_ref().is_none_or(|session_id| &ctx.session_id == session_id)
+                && query.user_id.as_ref().is_none_or(|user_id| &ctx.user_id == user_id)
+                && query.domain.as_ref().is_none_or(|domain| &ctx.domain == domain)
+                && query.metadata_filters.iter().all(|filter| filter.matches(ctx))
+        });
+
+        if candidates.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // 词法得分
+        let query_terms = self.tokenizer.terms(&query.query);
+        let mut documents: Vec<Vec<String>> = Vec::with_capacity(candidates.len());
+        for ctx in &candidates {
+            documents.push(self.scoring_cache.get_features(ctx, self.tokenizer.as_ref()).await.terms.clone());
+        }
+        let lexical_scores = bm25_scores(&query_terms, &documents);
+
+        // 向量得分；嵌入模型迁移期间双读：已迁移的上下文使用新模型，其余使用原模型
+        let embedder = self.get_embedder().await;
+        let query_vector = self.embed_query(&query.query, embedder.as_ref()).await?;
+        let migration = self.migration.read().await.clone();
+        let target_query_vector = match migration {
+            Some(ref migration) => Some(self.embed_query(&query.query, migration.target().as_ref()).await?),
+            None => None,
+        };
+
+        // 设置了排序偏好的用户：以词法和向量融合得分作为相关性，再按偏好结合优先级和时效性
+        let profile = match query.user_id {
+            Some(ref user_id) => self.profiles.get_profile(user_id).await,
+            None => None,
+        };
+
+        let mut scored = Vec::with_capacity(candidates.len());
+        for (context, lexical_score) in candidates.into_iter().zip(lexical_scores) {
+            let vector_score = match (&migration, &target_query_vector) {
+                (Some(migration), Some(target_query_vector)) if migration.is_migrated(&context).await => {
+                    let context_vector = self.scoring_cache.get_embedding(&context, migration.target().as_ref()).await?;
+                    cosine_similarity(target_query_vector, &context_vector).max(0.0)
+                }
+                _ => {
+                    let context_vector = self.scoring_cache.get_embedding(&context, embedder.as_ref()).await?;
+                    cosine_similarity(&query_vector, &context_vector).max(0.0)
+                }
+            };
+            let score = match profile {
+                Some(ref profile) => {
+                    let match_weight = query.weights.lexical + query.weights.vector;
+                    let relevance = if match_weight > 0.0 {
+                        (query.weights.lexical * lexical_score + query.weights.vector * vector_score) / match_weight
+                    } else {
+                        0.0
+                    };
+                    let recency = self.time_decay_score(&context.updated_at).await;
+                    profile.weights.combine(relevance, context.priority, recency)
+                }
+                None => fuse_scores(&query.weights, lexical_score, vector_score, context.priority),
+            };
+            if score >= query.min_score {
+                scored.push(ScoredContext {
+                    context,
+                    score,
+                    lexical_score,
+                    vector_score,
+                });
+            }
+        }
+
+        let tie_breakers = self.config.read().await.tie_breakers.clone();
+        scored.sort_by(|a, b| compare_scores_desc(a.score, b.score).then_with(|| compare_ties(&a.context, &b.context, &tie_breakers)));
+        scored.truncate(query.limit);
+        Ok(scored)
+    }
+
+    /// 查找与指定上下文最相似的k个上下文（词法和向量相似度等权融合），用于相关知识推荐以及合并、矛盾检测任务
+    pub async fn find_similar_contexts(
+        &self,
+        context_id: Uuid,
+        k: usize,
+    ) -> Result<Vec<ScoredContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let source = self.context_manager.get_context(context_id).await.ok_or("Context not found")?;
+        let candidates = self.context_manager.get_all_contexts().await;
+        self.rank_similar(source, candidates, k).await
+    }
+
+    /// 按查看者的可见范围查找相似上下文；查看者不可见的源上下文视为不存在
+    pub async fn find_similar_contexts_for(
+        &self,
+        context_id: Uuid,
+        k: usize,
+        viewer: &Viewer,
+    ) -> Result<Vec<ScoredContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let source = self.context_manager.get_context(context_id).await.ok_or("Context not found")?;
+        let source = self
+            .context_manager
+            .filter_visible(vec![source], viewer)
+            .await
+            .pop()
+            .ok_or("Context not found")?;
+        let candidates = self.context_manager.get_all_contexts().await;
+        let candidates = self.context_manager.filter_visible(candidates, viewer).await;
+        self.rank_similar(source, candidates, k).await
+    }
+
+    /// 以源上下文的词项为查询计算BM25，并与向量余弦相似度融合排序
+    async fn rank_similar(
+        &self,
+        source: LLMContext,
+        mut candidates: Vec<LLMContext>,
+        k: usize,
+    ) -> Result<Vec<ScoredContext>, Box<dyn std::error::Error + Send + Sync>> {
+        candidates.retain(|ctx| ctx.id != source.id);
+        if candidates.is_empty() || k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let source_terms = self.scoring_cache.get_features(&source, self.tokenizer.as_ref()).await.terms.clone();
+        let mut documents: Vec<Vec<String>> = Vec::with_capacity(candidates.len());
+        for ctx in &candidates {
+            documents.push(self.scoring_cache.get_features(ctx, self.tokenizer.as_ref()).await.terms.clone());
+        }
+        let lexical_scores = bm25_scores(&source_terms, &documents);
+
+        // 迁移期间两侧都已有新向量时使用新模型，否则使用原模型
+        let embedder = self.get_embedder().await;
+        let migration = self.migration.read().await.clone();
+        let source_migrated = match migration {
+            Some(ref migration) => migration.is_migrated(&source).await,
+            None => false,
+        };
+        let source_vector = self.scoring_cache.get_embedding(&source, embedder.as_ref()).await?;
+        let target_source_vector = match migration {
+            Some(ref migration) if source_migrated => {
+                Some(self.scoring_cache.get_embedding(&source, migration.target().as_ref()).await?)
+            }
+            _ => None,
+        };
+
+        let mut scored = Vec::with_capacity(candidates.len());
+        for (context, lexical_score) in candidates.into_iter().zip(lexical_scores) {
+            let vector_score = match (&migration, &target_source_vector) {
+                (Some(migration), Some(target_source_vector)) if migration.is_migrated(&context).await => {
+                    let context_vector = self.scoring_cache.get_embedding(&context, migration.target().as_ref()).await?;
+                    cosine_similarity(target_source_vector, &context_vector).max(0.0)
+                }
+                _ => {
+                    let context_vector = self.scoring_cache.get_embedding(&context, embedder.as_ref()).await?;
+                    cosine_similarity(&source_vector, &context_vector).max(0.0)
+                }
+            };
+            scored.push(ScoredContext {
+                context,
+                score: (lexical_score + vector_score) / 2.0,
+                lexical_score,
+                vector_score,
+            });
+        }
+
+        let tie_breakers = self.config.read().await.tie_breakers.clone();
+        scored.sort_by(|a, b| compare_scores_desc(a.score, b.score).then_with(|| compare_ties(&a.context, &b.context, &tie_breakers)));
+        scored.truncate(k);
+        Ok(scored)
+    }
+
+    /// 获取查询向量，优先使用查询向量缓存并记录命中情况
+    async fn embed_query(&self, query: &str, embedder: &dyn Embedder) -> Result<Arc<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+        let (embedding, hit) = self
+            .query_embedding_cache
+            .get_or_embed(query, embedder)
+            .await?;
+
+        if let Some(ref monitoring) = self.monitoring {
+            monitoring.log_event(MonitoringEvent::CacheAccess {
+                hit,
+                key_type: "query_embedding".to_string(),
+            }).await;
+            monitoring.record_metric(
+                "query_embedding_cache_hit_rate",
+                PerformanceMetric::CacheHitRate(self.query_embedding_cache.hit_rate()),
+            ).await;
+        }
+
+        Ok(embedding)
+    }
+
+    /// 按检索模式（领域覆盖优先）对候选上下文排序；调用大模型失败或预算用尽时回退到标准检索
+    async fn rank_candidates(
+        &self,
+        request: &RequestContext,
+        candidates: Vec<LLMContext>,
+        user_id: &str,
+        query: &str,
+        domain: &str,
+    ) -> Vec<LLMContext> {
+        let config = self.config.read().await.clone();
+        let mut mode = config.domain_retrieval_modes.get(domain).copied().unwrap_or(config.retrieval_mode);
+        if matches!(mode, RetrievalMode::Hyde | RetrievalMode::MultiQuery) {
+            if !request.feature_enabled(FLAG_LLM_RETRIEVAL, true) {
+                mode = RetrievalMode::Standard;
+            } else if request.remaining().is_some_and(|remaining| remaining < MIN_LLM_RETRIEVAL_TIME) {
+                log::debug!("Request {} is close to its deadline, skipping {:?} retrieval", request.request_id, mode);
+                mode = RetrievalMode::Standard;
+            }
+        }
+        if mode == RetrievalMode::Hyde {
+            match self.rank_by_hypothetical_answer(request, &candidates, query, domain, &config.hyde).await {
+                Ok(ranked) => return ranked,
+                Err(e) => log::warn!("HyDE retrieval fell back to standard selection: {}", e),
+            }
+        }
+        if mode == RetrievalMode::MultiQuery {
+            match self.query_variations(request, query, &config.multi_query).await {
+                Ok(variations) if !variations.is_empty() => {
+                    let mut lists = Vec::with_capacity(variations.len() + 1);
+                    for variant in std::iter::once(query).chain(variations.iter().map(String::as_str)) {
+                        lists.push(
+                            self.apply_selection_strategy(candidates.clone(), user_id, variant, domain, &config.selection_strategy)
+                                .await,
+                        );
+                    }
+                    return reciprocal_rank_fusion(lists, config.multi_query.rrf_k)
+                        .into_iter()
+                        .map(|(context, _)| context)
+                        .collect();
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Multi-query retrieval fell back to single query: {}", e),
+            }
+        }
+        self.apply_selection_strategy(candidates, user_id, query, domain, &config.selection_strategy).await
+    }
+
+    /// 同语言的上下文排在前面；查询语言无法识别时使用请求的语言区域
+    async fn route_by_query_language(&self, request: &RequestContext, ranked: Vec<LLMContext>, query: &str) -> Vec<LLMContext> {
+        let config = self.config.read().await.language_routing.clone();
+        route_by_language(ranked, query_language(query, request.locale.as_deref()), &config)
+    }
+
+    /// 应用领域覆盖（没有时使用全局）的后置过滤
+    async fn post_filter(&self, ranked: Vec<LLMContext>, domain: &str, now: chrono::DateTime<chrono::Utc>) -> Vec<LLMContext> {
+        let config = self.config.read().await;
+        let filters = config.domain_post_filters.get(domain).unwrap_or(&config.post_filters);
+        apply_post_filters(ranked, filters, now)
+    }
+
+    /// 调用文本生成器获取查询变体
+    async fn query_variations(
+        &self,
+        request: &RequestContext,
+        query: &str,
+        config: &MultiQueryConfig,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
+        let generator = self.generator.read().await.clone().ok_or("No text generator configured")?;
+        if !self.generation_budget.try_consume(config.hourly_generation_budget).await {
+            return Err("Hourly generation budget exhausted".into());
+        }
+        let text = generator
+            .generate_for_request(&variation_prompt(query, config.variations), CallPriority::Interactive, request)
+            .await?;
+        Ok(parse_variations(&text, query, config.variations))
+    }
+
+    /// 生成假设答案并按其向量与候选上下文向量的相似度排序
+    async fn rank_by_hypothetical_answer(
+        &self,
+        request: &RequestContext,
+        candidates: &[LLMContext],
+        query: &str,
+        domain: &str,
+        config: &HydeConfig,
+    ) -> Result<Vec<LLMContext>, Box<dyn std::error::Error + Send + Sync>> {
+        let generator = self.generator.read().await.clone().ok_or("No text generator configured")?;
+        if !self.hyde_budget.try_consume(config.hourly_generation_budget).await {
+            return Err("Hourly generation budget exhausted".into());
+        }
+        let answer = generator
+            .generate_for_request(&hyde_prompt(query, domain, config.max_answer_words), CallPriority::Interactive, request)
+            .await?;
+        let embedder = self.get_embedder().await;
+        let answer_vector = embedder.embed(&truncate_words(&answer, config.max_answer_words)).await?;
+
+        let mut scored = Vec::with_capacity(candidates.len());
+        for context in candidates {
+            let context_vector = self.scoring_cache.get_embedding(context, embedder.as_ref()).await?;
+            let similarity = cosine_similarity(&answer_vector, &context_vector);
+            if similarity >= config.min_similarity {
+                scored.push((context.clone(), similarity));
+            }
+        }
+        sort_scored(&mut scored, &self.config.read().await.tie_breakers);
+        Ok(scored.into_iter().map(|(context, _)| context).collect())
+    }
+
+    /// 领域当前生效的选择策略标签（"选择策略/检索模式"），用于按策略汇总回答质量
+    pub async fn strategy_label(&self, domain: &str) -> String {
+        let config = self.config.read().await;
+        let mode = config.domain_retrieval_modes.get(domain).copied().unwrap_or(config.retrieval_mode);
+        format!("{:?}/{:?}", config.selection_strategy, mode)
+    }
+
+    /// 获取多查询检索的大模型调用用量
+    pub async fn get_generation_usage(&self) -> GenerationUsage {
+        self.generation_budget.get_usage().await
+    }
+
+    /// 获取HyDE检索的大模型调用用量
+    pub async fn get_hyde_usage(&self) -> GenerationUsage {
+        self.hyde_budget.get_usage().await
+    }
+
     /// 应用选择策略
     async fn apply_selection_strategy(
         &self,
         mut contexts: Vec<LLMContext>,
+        user_id: &str,
         query: &str,
+        domain: &str,
         strategy: &ContextSelectionStrategy,
     ) -> Vec<LLMContext> {
+        let min_relevance_score = self.relevance_threshold(domain).await;
+        let (tie_breakers, tie_rotation) = {
+            let config = self.config.read().await;
+            (config.tie_breakers.clone(), config.tie_rotation.clone())
+        };
         match strategy {
             ContextSelectionStrategy::PriorityBased => {
-                contexts.sort_by(|a, b| b.priority.cmp(&a.priority));
+                contexts.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| compare_ties(a, b, &tie_breakers)));
                 contexts
             }
             ContextSelectionStrategy::RecencyBased => {
-                contexts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+                contexts.sort_by(|a, b| b.updated_at.cmp(&a.updated_at).then_with(|| compare_ties(a, b, &tie_breakers)));
                 contexts
             }
             ContextSelectionStrategy::RelevanceBased => {
                 let mut scored_contexts = Vec::new();
                 for context in contexts {
-                    let score = self.calculate_relevance_score(&context.context_data, query).await;
-                    if score >= self.config.read().await.min_relevance_score {
+                    let score = self.calculate_relevance_score(&context, query).await;
+                    self.calibrator.record_score(domain, context.id, score).await;
+                    if score >= min_relevance_score {
                         scored_contexts.push((context, score));
                     }
                 }
-                scored_contexts.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+                sort_scored(&mut scored_contexts, &tie_breakers);
+                self.tie_rotator.rotate(&mut scored_contexts, &tie_rotation);
                 scored_contexts.into_iter().map(|(ctx, _)| ctx).collect()
             }
             ContextSelectionStrategy::Hybrid => {
+                let weights = self.profiles.effective_weights(user_id).await;
                 let mut scored_contexts = Vec::new();
                 for context in contexts {
-                    let relevance_score = self.calculate_relevance_score(&context.context_data, query).await;
-                    if relevance_score >= self.config.read().await.min_relevance_score {
-                        // 综合考虑相关性、优先级和时间
-                        let hybrid_score = relevance_score * 0.5 + 
-                                         (context.priority as f64 / 10.0) * 0.3 + 
-                                         self.time_decay_score(&context.updated_at).await * 0.2;
+                    let relevance_score = self.calculate_relevance_score(&context, query).await;
+                    self.calibrator.record_score(domain, context.id, relevance_score).await;
+                    if relevance_score >= min_relevance_score {
+                        // 按用户偏好综合考虑相关性、优先级和时间
+                        let recency_score = self.time_decay_score(&context.updated_at).await;
+                        let hybrid_score = weights.combine(relevance_score, context.priority, recency_score);
                         scored_contexts.push((context, hybrid_score));
                     }
                 }
-                scored_contexts.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+                sort_scored(&mut scored_contexts, &tie_breakers);
+                self.tie_rotator.rotate(&mut scored_contexts, &tie_rotation);
                 scored_contexts.into_iter().map(|(ctx, _)| ctx).collect()
             }
         }
     }
 
+    /// 获取领域生效的最小相关性分数
+    async fn relevance_threshold(&self, domain: &str) -> f64 {
+        let config = self.config.read().await;
+        *config
+            .domain_relevance_thresholds
+            .get(domain)
+            .unwrap_or(&config.min_relevance_score)
+    }
+
+    /// 记录对已选上下文的反馈（0.0-1.0），用于阈值校准和优先级调整
+    pub async fn record_feedback(&self, context_id: Uuid, score: f64) {
+        self.calibrator.record_feedback(context_id, score).await;
+        if let Some(ref tuner) = self.priority_tuner {
+            tuner.record_feedback(context_id, score).await;
+        }
+    }
+
+    /// 根据历史选择和反馈校准各领域的相关性阈值；`apply`为true时直接写入配置
+    pub async fn calibrate_thresholds(&self, apply: bool) -> CalibrationReport {
+        let (current, default_threshold) = {
+            let config = self.config.read().await;
+            (config.domain_relevance_thresholds.clone(), config.min_relevance_score)
+        };
+        let domains = self.calibrator.calibrate(&current, default_threshold).await;
+
+        if apply {
+            let mut config = self.config.write().await;
+            for calibration in &domains {
+                if calibration.recommended_threshold != calibration.current_threshold {
+                    config
+                        .domain_relevance_thresholds
+                        .insert(calibration.domain.clone(), calibration.recommended_threshold);
+                }
+            }
+        }
+
+        CalibrationReport {
+            generated_at: chrono::Utc::now(),
+            applied: apply,
+            domains,
+        }
+    }
+
+    /// 获取评分缓存
+    pub fn get_scoring_cache(&self) -> Arc<ScoringCache> {
+        self.scoring_cache.clone()
+    }
+
+    /// 获取当前使用的向量化器
+    pub async fn get_embedder(&self) -> Arc<dyn Embedder> {
+        self.embedder.read().await.clone()
+    }
+
+    /// 替换文本生成器（如AI端点地址变更后使用新的客户端）
+    pub async fn set_text_generator(&self, generator: Arc<dyn TextGenerator>) {
+        *self.generator.write().await = Some(generator);
+    }
+
+    /// 登记嵌入模型迁移；已有迁移进行中或目标模型与当前相同时返回错误
+    pub async fn begin_embedding_migration(
+        &self,
+        target: Arc<dyn Embedder>,
+        batch_size: usize,
+    ) -> Result<Arc<EmbeddingMigration>, Box<dyn std::error::Error + Send + Sync>> {
+        let mut current = self.migration.write().await;
+        if current.is_some() {
+            return Err("An embedding migration is already running".into());
+        }
+        let source = self.get_embedder().await;
+        if source.model_name() == target.model_name() {
+            return Err(format!("Embedding model '{}' is already active", target.model_name()).into());
+        }
+        let mut migration = EmbeddingMigration::new(source.model_name(), target, batch_size);
+        if let Some(ref scheduler) = self.scheduler {
+            migration = migration.with_scheduler(scheduler.clone());
+        }
+        let migration = Arc::new(migration);
+        *current = Some(migration.clone());
+        *self.last_migration.write().await = Some(migration.get_progress().await);
+        Ok(migration)
+    }
+
+    /// 执行已登记的迁移：回填所有上下文的新向量，完成后原子切换到新模型；失败时继续使用原模型
+    pub async fn run_embedding_migration(&self, migration: Arc<EmbeddingMigration>) -> MigrationProgress {
+        let result = migration.backfill(&self.context_manager, &self.scoring_cache).await;
+        let progress = {
+            // 同时持有两把写锁，检索要么看到迁移中的双读状态，要么看到切换后的新模型
+            let mut current = self.migration.write().await;
+            let mut embedder = self.embedder.write().await;
+            *current = None;
+            match result {
+                Ok(()) => {
+                    *embedder = migration.target();
+                    migration.finish(None).await
+                }
+                Err(e) => migration.finish(Some(e.to_string())).await,
+            }
+        };
+        *self.last_migration.write().await = Some(progress.clone());
+        progress
+    }
+
+    /// 在后台执行嵌入模型迁移，返回初始进度
+    pub async fn start_embedding_migration(
+        self: Arc<Self>,
+        target: Arc<dyn Embedder>,
+        batch_size: usize,
+    ) -> Result<MigrationProgress, Box<dyn std::error::Error + Send + Sync>> {
+        let migration = self.begin_embedding_migration(target, batch_size).await?;
+        let progress = migration.get_progress().await;
+        tokio::spawn(async move {
+            let progress = self.run_embedding_migration(migration).await;
+            if let Some(ref error) = progress.error {
+                log::warn!("Embedding migration to {} failed: {}", progress.target_model, error);
+            }
+        });
+        Ok(progress)
+    }
+
+    /// 获取进行中或最近一次迁移的进度
+    pub async fn get_migration_progress(&self) -> Option<MigrationProgress> {
+        if let Some(ref migration) = *self.migration.read().await {
+            return Some(migration.get_progress().await);
+        }
+        self.last_migration.read().await.clone()
+    }
+
+    /// 获取查询向量缓存
+    pub fn get_query_embedding_cache(&self) -> Arc<QueryEmbeddingCache> {
+        self.query_embedding_cache.clone()
+    }
+
+    /// 获取阈值校准器
+    pub fn get_calibrator(&self) -> Arc<RelevanceCalibrator> {
+        self.calibrator.clone()
+    }
+
     /// 计算上下文相关性分数
-    async fn calculate_relevance_score(&self, context_data: &str, query: &str) -> f64 {
+    async fn calculate_relevance_score(&self, context: &LLMContext, query: &str) -> f64 {
         // 简化的相关性计算 - 在实际实现中，这可能使用向量嵌入或更复杂的算法
-        let context_lower = context_data.to_lowercase();
+        let features = self.scoring_cache.get_features(context, self.tokenizer.as_ref()).await;
         let query_lower = query.to_lowercase();
         
         let query_words: Vec<&str> = query_lower.split_whitespace().collect();
-        let context_words: std::collections::HashSet<&str> = context_lower.split_whitespace().collect();
 
         let mut matches = 0;
         for word in &query_words {
-            if context_words.contains(word) {
+            if features.words.contains(*word) {
                 matches += 1;
             }
         }
@@ -181,7 +905,7 @@ impl ContextSelector {
         // 基于时间的衰减函数，最近的上下文得分更高
         let hours_since_update = duration.num_seconds() as f64 / 3600.0;
         // 衰减函数：分数随时间指数衰减
-        (1.0 / (1.0 + hours_since_update * 0.1)).max(0.0).min(1.0)
+        (1.0 / (1.0 + hours_since_update * 0.1)).clamp(0.0, 1.0)
     }
 
     /// 去除重复上下文
@@ -190,40 +914,113 @@ impl ContextSelector {
         contexts.into_iter().filter(|ctx| seen_ids.insert(ctx.id)).collect()
     }
 
-    /// 获取缓存的上下文
-    async fn get_cached_contexts(&self, query: &str, domain: &str) -> Option<Vec<LLMContext>> {
-        let cache_key = format!("{}:{}", query, domain);
-        let cache = self.query_context_cache.read().await;
-        
-        if let Some((context_ids, cache_time)) = cache.get(&cache_key) {
-            // 检查缓存是否过期
-            let now = chrono::Utc::now();
-            let ttl = chrono::Duration::seconds(self.config.read().await.cache_ttl_seconds as i64);
-            
-            if now - *cache_time < ttl {
-                // 在实际实现中，这里会从缓存中获取上下文
-                // 为演示目的，我们返回空向量
-                Some(Vec::new()) // 实际实现会从缓存中获取上下文对象
-            } else {
-                None
+    /// 查询缓存键（选择结果依赖用户、会话和语言区域，需纳入键中）
+    fn cache_key(user_id: &str, session_id: &str, query: &str, domain: &str, locale: Option<&str>) -> QueryCacheKey {
+        QueryCacheKey {
+            user_id: user_id.to_string(),
+            session_id: session_id.to_string(),
+            query: query.to_string(),
+            domain: domain.to_string(),
+            locale: locale.map(str::to_string),
+        }
+    }
+
+    /// 获取缓存的上下文；任一上下文已更新或删除时视为未命中并移除缓存项，由调用方重新选择
+    async fn get_cached_contexts(&self, user_id: &str, session_id: &str, query: &str, domain: &str, locale: Option<&str>) -> Option<Vec<LLMContext>> {
+        let cache_key = Self::cache_key(user_id, session_id, query, domain, locale);
+        let cached = self.query_context_cache.read().await.get(&cache_key).cloned()?;
+
+        // 检查缓存是否过期
+        let ttl = chrono::Duration::seconds(self.config.read().await.cache_ttl_seconds as i64);
+        if chrono::Utc::now() - cached.cached_at >= ttl {
+            return None;
+        }
+
+        // 校验版本，返回当前的上下文内容
+        let mut contexts = Vec::with_capacity(cached.context_versions.len());
+        for (context_id, version) in &cached.context_versions {
+            match self.context_manager.get_context(*context_id).await {
+                Some(context) if context.version == *version => contexts.push(context),
+                _ => {
+                    self.query_context_cache.write().await.remove(&cache_key);
+                    return None;
+                }
             }
-        } else {
-            None
         }
+
+        // 成员或审批策略可能已变化，缓存结果中出现不可见的上下文时重新选择
+        let count = contexts.len();
+        let contexts = self.context_manager.filter_visible(contexts, &Viewer::new(Some(user_id), Some(session_id))).await;
+        if contexts.len() != count {
+            self.query_context_cache.write().await.remove(&cache_key);
+            return None;
+        }
+        Some(contexts)
     }
 
     /// 缓存上下文
-    async fn cache_contexts(&self, query: &str, domain: &str, contexts: &[LLMContext]) {
+    async fn cache_contexts(&self, user_id: &str, session_id: &str, query: &str, domain: &str, locale: Option<&str>, contexts: &[LLMContext]) {
         if !self.config.read().await.enable_cache {
             return;
         }
         
-        let cache_key = format!("{}:{}", query, domain);
-        let context_ids: Vec<Uuid> = contexts.iter().map(|ctx| ctx.id).collect();
-        let now = chrono::Utc::now();
+        let cache_key = Self::cache_key(user_id, session_id, query, domain, locale);
+        let cached = CachedSelection {
+            context_versions: contexts.iter().map(|ctx| (ctx.id, ctx.version)).collect(),
+            cached_at: chrono::Utc::now(),
+        };
         
         let mut cache = self.query_context_cache.write().await;
-        cache.insert(cache_key, (context_ids, now));
+        cache.insert(cache_key, cached);
+    }
+
+    /// 设置用户排序偏好，并清除该用户已缓存的选择结果
+    pub async fn set_user_profile(
+        &self,
+        user_id: &str,
+        weights: RankingWeights,
+    ) -> Result<UserRankingProfile, Box<dyn std::error::Error + Send + Sync>> {
+        let profile = self.profiles.set_profile(user_id, weights).await?;
+        self.clear_user_cache(user_id).await;
+        Ok(profile)
+    }
+
+    /// 重置用户排序偏好为默认权重，并清除该用户已缓存的选择结果
+    pub async fn reset_user_profile(&self, user_id: &str) -> bool {
+        let existed = self.profiles.reset_profile(user_id).await;
+        self.clear_user_cache(user_id).await;
+        existed
+    }
+
+    /// 获取用户排序偏好存储
+    pub fn get_profile_store(&self) -> Arc<UserProfileStore> {
+        self.profiles.clone()
+    }
+
+    /// 清除用户的缓存选择结果
+    pub async fn clear_user_cache(&self, user_id: &str) {
+        self.purge_user_cache(user_id, false).await;
+    }
+
+    /// 清除用户的缓存选择结果和个性化排序偏好（用户数据删除），返回(缓存项数, 是否有自定义偏好)；dry_run时只统计
+    pub async fn purge_user_data(&self, user_id: &str, dry_run: bool) -> (usize, bool) {
+        let cached = self.purge_user_cache(user_id, dry_run).await;
+        let profile = if dry_run {
+            self.profiles.get_profile(user_id).await.is_some()
+        } else {
+            self.profiles.reset_profile(user_id).await
+        };
+        (cached, profile)
+    }
+
+    async fn purge_user_cache(&self, user_id: &str, dry_run: bool) -> usize {
+        let mut cache = self.query_context_cache.write().await;
+        let before = cache.len();
+        if dry_run {
+            return cache.keys().filter(|key| key.user_id == user_id).count();
+        }
+        cache.retain(|key, _| key.user_id != user_id);
+        before - cache.len()
     }
 
     /// 更新配置
@@ -250,7 +1047,7 @@ impl ContextSelector {
         let now = chrono::Utc::now();
         
         let mut cache = self.query_context_cache.write().await;
-        cache.retain(|_, (_, cache_time)| now - *cache_time < ttl);
+        cache.retain(|_, cached| now - cached.cached_at < ttl);
     }
 }
 
@@ -266,7 +1063,7 @@ mod tests {
         let selector = ContextSelector::new(context_manager.clone());
 
         // 创建测试上下文
-        let ctx1 = context_manager
+        let _ctx1 = context_manager
             .create_context(
                 "session1".to_string(),
                 "user1".to_string(),
@@ -277,7 +1074,7 @@ mod tests {
             .await
             .unwrap();
 
-        let ctx2 = context_manager
+        let _ctx2 = context_manager
             .create_context(
                 "session1".to_string(),
                 "user1".to_string(),
@@ -304,6 +1101,16 @@ mod tests {
             selection_strategy: ContextSelectionStrategy::RelevanceBased,
             enable_cache: true,
             cache_ttl_seconds: 300,
+            domain_relevance_thresholds: HashMap::new(),
+            retrieval_mode: RetrievalMode::Standard,
+            multi_query: MultiQueryConfig::default(),
+            domain_retrieval_modes: HashMap::new(),
+            hyde: HydeConfig::default(),
+            tie_breakers: default_tie_breakers(),
+            tie_rotation: TieRotationConfig::default(),
+            language_routing: LanguageRoutingConfig::default(),
+            post_filters: PostFilterConfig::default(),
+            domain_post_filters: HashMap::new(),
         };
         
         selector.update_config(new_config).await;
@@ -313,4 +1120,220 @@ mod tests {
         // 测试清除缓存
         selector.clear_cache().await;
     }
+
+    #[tokio::test]
+    async fn test_cached_selection_versioning() {
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let selector = ContextSelector::new(context_manager.clone());
+
+        let ctx = context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(),
+                "pneumonia treatment antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+
+        let first = selector.select_contexts("u1", "s1", "pneumonia treatment", "medical").await.unwrap();
+        assert_eq!(first.len(), 1);
+
+        // 缓存命中返回完整的上下文
+        let cached = selector.select_contexts("u1", "s1", "pneumonia treatment", "medical").await.unwrap();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].context_data, "pneumonia treatment antibiotics");
+
+        // 上下文更新后缓存失效，返回新内容
+        context_manager
+            .update_context(ctx.id, Some("pneumonia treatment with amoxicillin".to_string()), None, None)
+            .await
+            .unwrap();
+        let refreshed = selector.select_contexts("u1", "s1", "pneumonia treatment", "medical").await.unwrap();
+        assert_eq!(refreshed.len(), 1);
+        assert_eq!(refreshed[0].context_data, "pneumonia treatment with amoxicillin");
+        assert_eq!(refreshed[0].version, ctx.version + 1);
+    }
+
+    #[tokio::test]
+    async fn test_user_ranking_profile() {
+        use crate::context::codec::{ContextCodec, JsonCodec};
+
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let selector = ContextSelector::new(context_manager.clone());
+
+        // 高优先级但很久未更新的上下文，和低优先级的新上下文
+        let fresh = context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "pneumonia update".to_string(), 1)
+            .await
+            .unwrap();
+        let mut authoritative = fresh.clone();
+        authoritative.id = Uuid::new_v4();
+        authoritative.context_data = "pneumonia guideline".to_string();
+        authoritative.priority = 9;
+        authoritative.updated_at = chrono::Utc::now() - chrono::Duration::hours(100);
+        let bytes = JsonCodec.encode_one(&authoritative).unwrap();
+        context_manager.import_contexts(&JsonCodec, &bytes).await.unwrap();
+
+        let default_order = selector.select_contexts("u1", "s1", "pneumonia", "medical").await.unwrap();
+        assert_eq!(default_order[0].id, authoritative.id);
+
+        // 偏好时效性的用户，设置后缓存结果随之失效
+        selector
+            .set_user_profile("u1", RankingWeights { relevance: 1.0, authority: 0.0, recency: 1.0 })
+            .await
+            .unwrap();
+        let personalized = selector.select_contexts("u1", "s1", "pneumonia", "medical").await.unwrap();
+        assert_eq!(personalized[0].id, fresh.id);
+
+        assert!(selector.reset_user_profile("u1").await);
+        let reset = selector.select_contexts("u1", "s1", "pneumonia", "medical").await.unwrap();
+        assert_eq!(reset[0].id, authoritative.id);
+    }
+
+    #[tokio::test]
+    async fn test_threshold_calibration() {
+        use crate::selection::threshold_calibration::{CalibrationConfig, CalibrationMethod};
+
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let selector = ContextSelector::new(context_manager.clone());
+        let mut config = selector.get_config().await;
+        config.enable_cache = false;
+        selector.update_config(config).await;
+        selector.get_calibrator().update_config(CalibrationConfig {
+            min_labeled_samples: 2,
+            ..CalibrationConfig::default()
+        }).await;
+
+        let relevant = context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(),
+                "pneumonia treatment antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        let noise = context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(),
+                "pneumonia history".to_string(), 5)
+            .await
+            .unwrap();
+
+        selector.select_contexts("u1", "s1", "pneumonia treatment", "medical").await.unwrap();
+        selector.record_feedback(relevant.id, 1.0).await;
+        selector.record_feedback(noise.id, 0.0).await;
+
+        let report = selector.calibrate_thresholds(true).await;
+        assert!(report.applied);
+        assert_eq!(report.domains[0].method, CalibrationMethod::Feedback);
+        assert_eq!(report.domains[0].recommended_threshold, 1.0);
+
+        let config = selector.get_config().await;
+        assert_eq!(config.domain_relevance_thresholds.get("medical"), Some(&1.0));
+
+        let selected = selector.select_contexts("u1", "s1", "pneumonia treatment", "medical").await.unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, relevant.id);
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search() {
+        use crate::selection::hybrid_search::MetadataFilter;
+
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let monitoring = Arc::new(MonitoringSystem::new());
+        let selector = ContextSelector::new(context_manager.clone()).with_monitoring(monitoring.clone());
+
+        let pneumonia = context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(),
+                "Treatment for pneumonia involves antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        let flu = context_manager
+            .create_context("s2".to_string(), "u2".to_string(), "medical".to_string(),
+                "Symptoms of flu include fever and fatigue".to_string(), 9)
+            .await
+            .unwrap();
+        context_manager
+            .create_context("s3".to_string(), "u3".to_string(), "technical".to_string(),
+                "Pneumonia detection model written in Rust".to_string(), 5)
+            .await
+            .unwrap();
+
+        let mut metadata = HashMap::new();
+        metadata.insert("source".to_string(), "guideline".to_string());
+        context_manager.update_context(pneumonia.id, None, Some(metadata), None).await.unwrap();
+
+        let mut query = HybridQuery::new("pneumonia treatment");
+        query.domain = Some("medical".to_string());
+        let results = selector.hybrid_search(query.clone()).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].context.id, pneumonia.id);
+        assert!(results[0].lexical_score > results[1].lexical_score);
+
+        // 元数据过滤
+        query.metadata_filters = vec![MetadataFilter::NotExists("source".to_string())];
+        let results = selector.hybrid_search(query.clone()).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].context.id, flu.id);
+
+        // 未限定范围时检索所有上下文
+        let results = selector.hybrid_search(HybridQuery::new("pneumonia")).await.unwrap();
+        assert_eq!(results.len(), 3);
+
+        // 重复检索稳定语料时命中评分缓存
+        let stats = selector.get_scoring_cache().get_stats().await;
+        assert_eq!(stats.feature_misses, 3);
+        assert!(stats.feature_hits >= 3);
+
+        // 规范化后相同的查询复用查询向量
+        let query_stats = selector.get_query_embedding_cache().get_stats().await;
+        assert_eq!(query_stats.misses, 2);
+        assert_eq!(query_stats.hits, 1);
+        assert!(matches!(
+            monitoring.get_latest_metric("query_embedding_cache_hit_rate").await,
+            Some(PerformanceMetric::CacheHitRate(rate)) if (rate - 1.0 / 3.0).abs() < 1e-9
+        ));
+
+        // 多个限定条件取交集：其他用户的会话不会混入
+        let mut scoped = HybridQuery::new("pneumonia");
+        scoped.domain = Some("medical".to_string());
+        scoped.user_id = Some("u2".to_string());
+        let results = selector.hybrid_search(scoped.clone()).await.unwrap();
+        assert_eq!(results.iter().map(|result| result.context.id).collect::<Vec<_>>(), vec![flu.id]);
+        scoped.session_id = Some("s1".to_string());
+        assert!(selector.hybrid_search(scoped).await.unwrap().is_empty());
+    }
+    #[tokio::test]
+    async fn test_find_similar_contexts() {
+        use crate::context::access::Visibility;
+
+        let context_manager = Arc::new(ContextManager::new(10, 3600));
+        let selector = ContextSelector::new(context_manager.clone());
+        let source = context_manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(),
+                "Pneumonia treatment with antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        let related = context_manager
+            .create_context("s2".to_string(), "u2".to_string(), "medical".to_string(),
+                "Antibiotics for bacterial pneumonia treatment".to_string(), 5)
+            .await
+            .unwrap();
+        let unrelated = context_manager
+            .create_context("s3".to_string(), "u3".to_string(), "legal".to_string(),
+                "Contract termination clauses".to_string(), 5)
+            .await
+            .unwrap();
+        let private = context_manager
+            .create_context_with_visibility("s4".to_string(), "u4".to_string(), "medical".to_string(),
+                "Pneumonia treatment notes".to_string(), 5, HashMap::new(), Visibility::Private)
+            .await
+            .unwrap();
+
+        let results = selector.find_similar_contexts(source.id, 2).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.context.id != source.id && r.context.id != unrelated.id));
+        assert!(results[0].score >= results[1].score);
+
+        // 按查看者过滤时不返回他人的私有上下文
+        let viewer = Viewer::new(Some("u1"), Some("s1"));
+        let results = selector.find_similar_contexts_for(source.id, 5, &viewer).await.unwrap();
+        assert_eq!(results[0].context.id, related.id);
+        assert!(results.iter().all(|r| r.context.id != private.id));
+        assert!(selector.find_similar_contexts_for(private.id, 5, &viewer).await.is_err());
+    }
 }
\ No newline at end of file
@@ -1,4 +1,5 @@
 use crate::context::llm_context::LLMContext as Context;
+use crate::selection::ranking::{sort_scored, TieBreaker};
 
 /// 上下文选择器 - 根据用户查询选择最相关的上下文
 pub struct ContextSelector {
@@ -25,6 +26,12 @@ impl Default for ContextSelectionConfig {
     }
 }
 
+impl Default for ContextSelector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ContextSelector {
     /// 创建新的上下文选择器
     pub fn new() -> Self {
@@ -56,19 +63,13 @@ impl ContextSelector {
             }
         }
 
-        // 按相似度排序（降序）
-        scored_contexts.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-
-        // 如果配置了按优先级排序，则在相似度相近的情况下考虑优先级
-        if self.strategy_config.prioritize_by_priority {
-            scored_contexts.sort_by(|a, b| {
-                // 首先按相似度排序，相似度相近时按优先级排序
-                match b.1.partial_cmp(&a.1).unwrap() {
-                    std::cmp::Ordering::Equal => b.0.priority.cmp(&a.0.priority),
-                    other => other,
-                }
-            });
-        }
+        // 按相似度排序（降序）；如果配置了按优先级排序，则在相似度相同时先比较优先级
+        let tie_breakers: &[TieBreaker] = if self.strategy_config.prioritize_by_priority {
+            &[TieBreaker::Priority, TieBreaker::Id]
+        } else {
+            &[TieBreaker::Id]
+        };
+        sort_scored(&mut scored_contexts, tie_breakers);
 
         // 返回指定数量的上下文
         scored_contexts
@@ -83,7 +84,7 @@ impl ContextSelector {
         let mut contexts_with_priority = contexts.to_vec();
         
         // 按优先级排序（降序）
-        contexts_with_priority.sort_by(|a, b| b.priority.cmp(&a.priority));
+        contexts_with_priority.sort_by_key(|b| std::cmp::Reverse(b.priority));
 
         // 返回指定数量的上下文
         contexts_with_priority
@@ -121,6 +122,8 @@ impl ContextSelector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::schema_migration::CURRENT_SCHEMA_VERSION;
+    use std::collections::HashMap;
 
     #[tokio::test]
     async fn test_context_selection() {
@@ -142,6 +145,9 @@ mod tests {
                 version: 1,
                 tags: vec!["treatment".to_string(), "pneumonia".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             },
             Context {
                 id: uuid::Uuid::new_v4(),
@@ -157,6 +163,9 @@ mod tests {
                 version: 1,
                 tags: vec!["symptoms".to_string(), "flu".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             },
             Context {
                 id: uuid::Uuid::new_v4(),
@@ -172,6 +181,9 @@ mod tests {
                 version: 1,
                 tags: vec!["algorithm".to_string(), "rust".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             },
         ];
 
@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// 文本向量化接口 - 可替换为调用外部嵌入模型的实现
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// 将文本转换为向量
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 嵌入模型名称（不同模型的向量不可比较）
+    fn model_name(&self) -> &str;
+}
+
+/// 基于特征哈希的本地向量化实现 - 无需外部服务，适合作为默认实现
+pub struct HashingEmbedder {
+    dimensions: usize,
+    model_name: String,
+}
+
+impl HashingEmbedder {
+    /// 创建指定维度的哈希向量化器
+    pub fn new(dimensions: usize) -> Self {
+        let dimensions = dimensions.max(1);
+        Self {
+            dimensions,
+            model_name: format!("hashing-{}", dimensions),
+        }
+    }
+
+    /// 同步计算向量
+    pub fn embed_sync(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0f32; self.dimensions];
+        for token in text
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+        {
+            let mut hasher = DefaultHasher::new();
+            token.hash(&mut hasher);
+            let hash = hasher.finish();
+            let index = (hash % self.dimensions as u64) as usize;
+            // 使用哈希的最高位决定符号，减少碰撞带来的偏差
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[index] += sign;
+        }
+        vector
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+#[async_trait]
+impl Embedder for HashingEmbedder {
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, Box<dyn std::error::Error + Send + Sync>> {
+        Ok(self.embed_sync(text))
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
+}
+
+/// 按模型名称创建向量化器（如hashing-512），未知模型返回None
+pub fn embedder_for_model(model_name: &str) -> Option<Arc<dyn Embedder>> {
+    let dimensions: usize = model_name.strip_prefix("hashing-")?.parse().ok()?;
+    if dimensions == 0 {
+        return None;
+    }
+    Some(Arc::new(HashingEmbedder::new(dimensions)))
+}
+
+/// 计算两个向量的余弦相似度，维度不一致或零向量时返回0
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let mut dot = 0.0f64;
+    let mut norm_a = 0.0f64;
+    let mut norm_b = 0.0f64;
+    for (x, y) in a.iter().zip(b.iter()) {
+        dot += (*x as f64) * (*y as f64);
+        norm_a += (*x as f64) * (*x as f64);
+        norm_b += (*y as f64) * (*y as f64);
+    }
+
+    let magnitude = (norm_a * norm_b).sqrt();
+    if magnitude == 0.0 {
+        0.0
+    } else {
+        dot / magnitude
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hashing_embedder() {
+        let embedder = HashingEmbedder::new(64);
+        let a = embedder.embed("pneumonia treatment antibiotics").await.unwrap();
+        let b = embedder.embed("Treatment of pneumonia").await.unwrap();
+        let c = embedder.embed("stock market portfolio").await.unwrap();
+
+        assert_eq!(a.len(), 64);
+        assert!(cosine_similarity(&a, &b) > cosine_similarity(&a, &c));
+        assert!((cosine_similarity(&a, &a) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&a, &[]), 0.0);
+        assert_eq!(embedder_for_model("hashing-64").unwrap().model_name(), "hashing-64");
+        assert!(embedder_for_model("unknown-model").is_none());
+    }
+}
@@ -0,0 +1,189 @@
+//! 嵌入模型迁移 - 切换嵌入模型时用新模型重新计算所有上下文向量，迁移期间双读，完成后原子切换
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::context::llm_context::{ContextManager, LLMContext};
+use crate::selection::embedding::Embedder;
+use crate::utils::outbound_scheduler::{CallPriority, OutboundScheduler, EMBEDDING_PROVIDER};
+use crate::selection::scoring_cache::ScoringCache;
+
+/// 迁移状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum MigrationStatus {
+    Running,    // 正在重新计算向量，检索时双读
+    Completed,  // 已切换到新模型
+    Failed,     // 失败，继续使用原模型
+}
+
+/// 迁移进度
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MigrationProgress {
+    pub id: Uuid,
+    pub source_model: String,               // 原嵌入模型
+    pub target_model: String,               // 新嵌入模型
+    pub status: MigrationStatus,
+    pub total: usize,                       // 需要迁移的上下文数（迁移期间新增的上下文会追加）
+    pub migrated: usize,                    // 已计算新向量的上下文数
+    pub batch_size: usize,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+}
+
+impl MigrationProgress {
+    /// 完成比例（0-1）
+    pub fn fraction(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.migrated as f64 / self.total as f64
+        }
+    }
+}
+
+/// 一次嵌入模型迁移
+pub struct EmbeddingMigration {
+    target: Arc<dyn Embedder>,
+    scheduler: Option<Arc<OutboundScheduler>>,     // 可选的外呼调度器，回填以后台优先级等待配额
+    progress: Arc<RwLock<MigrationProgress>>,
+    migrated: Arc<RwLock<HashSet<(Uuid, u32)>>>, // 已有新向量的(上下文ID, 版本)
+}
+
+impl EmbeddingMigration {
+    /// 创建迁移
+    pub fn new(source_model: &str, target: Arc<dyn Embedder>, batch_size: usize) -> Self {
+        let progress = MigrationProgress {
+            id: Uuid::new_v4(),
+            source_model: source_model.to_string(),
+            target_model: target.model_name().to_string(),
+            status: MigrationStatus::Running,
+            total: 0,
+            migrated: 0,
+            batch_size: batch_size.max(1),
+            started_at: Utc::now(),
+            finished_at: None,
+            error: None,
+        };
+        Self {
+            target,
+            scheduler: None,
+            progress: Arc::new(RwLock::new(progress)),
+            migrated: Arc::new(RwLock::new(HashSet::new())),
+        }
+    }
+
+    /// 关联外呼调度器，每个上下文计算新向量前以后台优先级等待配额
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 新嵌入模型
+    pub fn target(&self) -> Arc<dyn Embedder> {
+        self.target.clone()
+    }
+
+    /// 上下文当前版本是否已有新向量
+    pub async fn is_migrated(&self, context: &LLMContext) -> bool {
+        self.migrated.read().await.contains(&(context.id, context.version))
+    }
+
+    /// 获取进度
+    pub async fn get_progress(&self) -> MigrationProgress {
+        self.progress.read().await.clone()
+    }
+
+    /// 分批为所有尚未迁移的上下文计算新向量，直到没有遗漏（覆盖迁移期间新增或修改的上下文）
+    pub async fn backfill(
+        &self,
+        context_manager: &ContextManager,
+        scoring_cache: &ScoringCache,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let batch_size = self.progress.read().await.batch_size;
+        loop {
+            let pending: Vec<LLMContext> = {
+                let migrated = self.migrated.read().await;
+                context_manager
+                    .get_all_contexts()
+                    .await
+                    .into_iter()
+                    .filter(|ctx| !migrated.contains(&(ctx.id, ctx.version)))
+                    .collect()
+            };
+            if pending.is_empty() {
+                return Ok(());
+            }
+            {
+                let mut progress = self.progress.write().await;
+                progress.total = progress.migrated + pending.len();
+            }
+
+            for batch in pending.chunks(batch_size) {
+                for context in batch {
+                    if let Some(ref scheduler) = self.scheduler {
+                        scheduler.acquire(EMBEDDING_PROVIDER, CallPriority::Background).await?;
+                    }
+                    scoring_cache.get_embedding(context, self.target.as_ref()).await?;
+                    self.migrated.write().await.insert((context.id, context.version));
+                }
+                self.progress.write().await.migrated += batch.len();
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+
+    /// 标记迁移结束
+    pub async fn finish(&self, error: Option<String>) -> MigrationProgress {
+        let mut progress = self.progress.write().await;
+        progress.status = if error.is_none() { MigrationStatus::Completed } else { MigrationStatus::Failed };
+        progress.finished_at = Some(Utc::now());
+        progress.error = error;
+        progress.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::selection::embedding::HashingEmbedder;
+    use crate::selection::hybrid_search::HybridQuery;
+
+    #[tokio::test]
+    async fn test_embedding_migration() {
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        for i in 0..5 {
+            manager
+                .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), format!("pneumonia case {}", i), 5)
+                .await
+                .unwrap();
+        }
+        let selector = Arc::new(ContextSelector::new(manager.clone()).with_embedder(Arc::new(HashingEmbedder::new(32))));
+        selector.hybrid_search(HybridQuery::new("pneumonia")).await.unwrap();
+
+        // 同一模型或迁移进行中不能再次发起
+        assert!(selector.begin_embedding_migration(Arc::new(HashingEmbedder::new(32)), 2).await.is_err());
+        let migration = selector.begin_embedding_migration(Arc::new(HashingEmbedder::new(64)), 2).await.unwrap();
+        assert!(selector.begin_embedding_migration(Arc::new(HashingEmbedder::new(128)), 2).await.is_err());
+
+        // 双读：迁移期间的检索不受影响
+        assert_eq!(selector.hybrid_search(HybridQuery::new("pneumonia")).await.unwrap().len(), 5);
+        assert_eq!(selector.get_embedder().await.model_name(), "hashing-32");
+
+        let progress = selector.run_embedding_migration(migration).await;
+        assert_eq!(progress.status, MigrationStatus::Completed);
+        assert_eq!(progress.migrated, 5);
+        assert_eq!(progress.fraction(), 1.0);
+        assert_eq!(selector.get_embedder().await.model_name(), "hashing-64");
+        assert_eq!(selector.get_migration_progress().await.unwrap().target_model, "hashing-64");
+
+        let results = selector.hybrid_search(HybridQuery::new("pneumonia")).await.unwrap();
+        assert_eq!(results.len(), 5);
+        assert!(results.iter().all(|r| r.vector_score > 0.0));
+    }
+}
@@ -0,0 +1,180 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::context::llm_context::LLMContext;
+
+/// 融合权重 - 控制词法、向量和优先级得分在最终得分中的占比
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FusionWeights {
+    pub lexical: f64,   // BM25词法得分权重
+    pub vector: f64,    // 向量相似度权重
+    pub priority: f64,  // 上下文优先级权重
+}
+
+impl Default for FusionWeights {
+    fn default() -> Self {
+        Self {
+            lexical: 0.5,
+            vector: 0.3,
+            priority: 0.2,
+        }
+    }
+}
+
+/// 元数据过滤条件
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MetadataFilter {
+    Equals { key: String, value: String },      // 元数据键等于指定值
+    OneOf { key: String, values: Vec<String> }, // 元数据键为候选值之一
+    Exists(String),                             // 存在元数据键
+    NotExists(String),                          // 不存在元数据键
+    HasTag(String),                             // 包含标签
+}
+
+impl MetadataFilter {
+    /// 检查上下文是否满足过滤条件
+    pub fn matches(&self, context: &LLMContext) -> bool {
+        match self {
+            MetadataFilter::Equals { key, value } => context.metadata.get(key) == Some(value),
+            MetadataFilter::OneOf { key, values } => context
+                .metadata
+                .get(key)
+                .map(|v| values.contains(v))
+                .unwrap_or(false),
+            MetadataFilter::Exists(key) => context.metadata.contains_key(key),
+            MetadataFilter::NotExists(key) => !context.metadata.contains_key(key),
+            MetadataFilter::HasTag(tag) => context.tags.iter().any(|t| t == tag),
+        }
+    }
+}
+
+/// 混合检索查询
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HybridQuery {
+    pub query: String,                          // 查询文本
+    pub domain: Option<String>,                 // 限定领域
+    pub user_id: Option<String>,                // 限定用户
+    pub session_id: Option<String>,             // 限定会话
+    pub metadata_filters: Vec<MetadataFilter>,  // 元数据过滤条件（全部满足）
+    pub weights: FusionWeights,                 // 融合权重
+    pub min_score: f64,                         // 最低融合得分
+    pub limit: usize,                           // 最大返回数量
+}
+
+impl HybridQuery {
+    /// 创建使用默认权重的查询
+    pub fn new(query: &str) -> Self {
+        Self {
+            query: query.to_string(),
+            domain: None,
+            user_id: None,
+            session_id: None,
+            metadata_filters: Vec::new(),
+            weights: FusionWeights::default(),
+            min_score: 0.0,
+            limit: 10,
+        }
+    }
+}
+
+/// 带得分的上下文
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoredContext {
+    pub context: LLMContext,
+    pub score: f64,           // 融合得分
+    pub lexical_score: f64,   // 归一化BM25得分（0-1）
+    pub vector_score: f64,    // 向量相似度（0-1）
+}
+
+/// BM25参数
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+/// 将文本切分为小写词项
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(|t| t.to_string())
+        .collect()
+}
+
+/// 计算查询对每个文档的BM25得分，并按最高分归一化到0-1
+pub fn bm25_scores(query_terms: &[String], documents: &[Vec<String>]) -> Vec<f64> {
+    if documents.is_empty() {
+        return Vec::new();
+    }
+
+    let doc_count = documents.len() as f64;
+    let avg_len = documents.iter().map(|d| d.len()).sum::<usize>() as f64 / doc_count;
+    let unique_terms: HashSet<&String> = query_terms.iter().collect();
+
+    // 文档频率
+    let mut document_frequency: HashMap<&String, usize> = HashMap::new();
+    for doc in documents {
+        let doc_terms: HashSet<&String> = doc.iter().collect();
+        for term in &unique_terms {
+            if doc_terms.contains(*term) {
+                *document_frequency.entry(*term).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let raw: Vec<f64> = documents
+        .iter()
+        .map(|doc| {
+            let mut term_frequency: HashMap<&String, usize> = HashMap::new();
+            for token in doc {
+                if unique_terms.contains(token) {
+                    *term_frequency.entry(token).or_insert(0) += 1;
+                }
+            }
+
+            let doc_len = doc.len() as f64;
+            unique_terms
+                .iter()
+                .map(|term| {
+                    let tf = *term_frequency.get(*term).unwrap_or(&0) as f64;
+                    if tf == 0.0 {
+                        return 0.0;
+                    }
+                    let df = *document_frequency.get(*term).unwrap_or(&0) as f64;
+                    let idf = ((doc_count - df + 0.5) / (df + 0.5) + 1.0).ln();
+                    let norm = if avg_len > 0.0 { doc_len / avg_len } else { 0.0 };
+                    idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * (1.0 - BM25_B + BM25_B * norm))
+                })
+                .sum()
+        })
+        .collect();
+
+    let max = raw.iter().cloned().fold(0.0f64, f64::max);
+    if max > 0.0 {
+        raw.into_iter().map(|s| s / max).collect()
+    } else {
+        raw
+    }
+}
+
+/// 计算融合得分
+pub fn fuse_scores(weights: &FusionWeights, lexical: f64, vector: f64, priority: u8) -> f64 {
+    weights.lexical * lexical + weights.vector * vector.max(0.0) + weights.priority * (priority as f64 / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bm25_scoring() {
+        let documents = vec![
+            tokenize("Treatment for pneumonia involves antibiotics"),
+            tokenize("Symptoms of flu include fever and fatigue"),
+            tokenize("Pneumonia pneumonia pneumonia"),
+        ];
+        let scores = bm25_scores(&tokenize("pneumonia treatment"), &documents);
+
+        assert_eq!(scores.len(), 3);
+        assert_eq!(scores[1], 0.0);
+        assert!(scores[0] > scores[2]);
+        assert!((scores[0] - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,87 @@
+//! 假设文档嵌入（HyDE）- 先由大模型生成一段假设答案，再以该答案的向量检索相似上下文，缓解短查询与文档的表述差异
+
+use serde::{Deserialize, Serialize};
+
+/// HyDE检索配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HydeConfig {
+    pub max_answer_words: usize,        // 假设答案的最大词数
+    pub min_similarity: f64,            // 最低向量相似度
+    pub hourly_generation_budget: u32,  // 每小时最多调用大模型的次数，用尽后回退到标准检索
+}
+
+impl Default for HydeConfig {
+    fn default() -> Self {
+        Self {
+            max_answer_words: 80,
+            min_similarity: 0.1,
+            hourly_generation_budget: 500,
+        }
+    }
+}
+
+/// 构造生成假设答案的提示
+pub fn hyde_prompt(query: &str, domain: &str, max_words: usize) -> String {
+    format!(
+        "Write a short passage (at most {} words) from a {} knowledge base that directly answers the question below. \
+         Answer with the passage only.\n\nQuestion: {}",
+        max_words, domain, query
+    )
+}
+
+/// 截断假设答案，避免过长的生成结果稀释向量
+pub fn truncate_words(text: &str, max_words: usize) -> String {
+    text.split_whitespace().take(max_words).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::selection::rag_fusion::RetrievalMode;
+    use crate::test_support::FixedGenerator;
+
+    #[tokio::test]
+    async fn test_hyde_per_domain() {
+        assert_eq!(truncate_words("a b  c d", 3), "a b c");
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let pneumonia = manager
+            .create_context("s0".to_string(), "u0".to_string(), "medical".to_string(),
+                "Amoxicillin antibiotics remain first line treatment for community acquired pneumonia".to_string(), 5)
+            .await
+            .unwrap();
+        manager
+            .create_context("s0".to_string(), "u0".to_string(), "medical".to_string(),
+                "Hospital visiting hours and parking information".to_string(), 5)
+            .await
+            .unwrap();
+        manager
+            .create_context("s0".to_string(), "u0".to_string(), "legal".to_string(),
+                "Contract law basics".to_string(), 5)
+            .await
+            .unwrap();
+        let selector = ContextSelector::new(manager.clone()).with_text_generator(Arc::new(
+            FixedGenerator::new("Community acquired pneumonia is usually treated with a course of oral antibiotics such as amoxicillin")
+                .expecting_prompt("medical"),
+        ));
+
+        // 口语化查询与文档几乎没有共同词
+        assert!(selector.select_contexts("u1", "s1", "what fixes a lung infection", "medical").await.unwrap().is_empty());
+
+        let mut config = selector.get_config().await;
+        config.domain_retrieval_modes = HashMap::from([("medical".to_string(), RetrievalMode::Hyde)]);
+        config.hyde.min_similarity = 0.3;
+        selector.update_config(config).await;
+        let selected = selector.select_contexts("u1", "s2", "what fixes a lung infection", "medical").await.unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].id, pneumonia.id);
+
+        // 其他领域仍使用标准检索，不调用大模型
+        selector.select_contexts("u1", "s3", "what fixes a lung infection", "legal").await.unwrap();
+        assert_eq!(selector.get_hyde_usage().await.used, 1);
+    }
+}
@@ -0,0 +1,112 @@
+//! 查询语言路由 - 同一领域同时有中英文上下文时，优先返回与查询语言一致的上下文，
+//! 其他语言的上下文排在其后作为跨语言回退（可关闭），避免中文用户只拿到英文的指南原文
+
+use serde::{Deserialize, Serialize};
+use crate::context::llm_context::LLMContext;
+use crate::domain::language::{context_language, detect_language, ContentLanguage};
+
+/// 语言路由配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageRoutingConfig {
+    pub enabled: bool,
+    pub cross_language_fallback: bool,  // 保留其他语言的上下文（排在同语言上下文之后）
+}
+
+impl Default for LanguageRoutingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cross_language_fallback: true,
+        }
+    }
+}
+
+/// 查询语言：按查询文本识别，无法识别时使用语言区域（如"zh-CN"）
+pub fn query_language(query: &str, locale: Option<&str>) -> Option<ContentLanguage> {
+    detect_language(query).or_else(|| locale.and_then(ContentLanguage::from_code))
+}
+
+/// 按查询语言调整已排序的上下文：同语言和语言未知的上下文保持原有顺序排在前面，其他语言的上下文随后（关闭回退时移除）
+pub fn route_by_language(ranked: Vec<LLMContext>, language: Option<ContentLanguage>, config: &LanguageRoutingConfig) -> Vec<LLMContext> {
+    let Some(language) = language.filter(|_| config.enabled) else {
+        return ranked;
+    };
+    let (mut matching, other): (Vec<_>, Vec<_>) = ranked
+        .into_iter()
+        .partition(|context| context_language(context).is_none_or(|context_language| context_language == language));
+    if config.cross_language_fallback {
+        matching.extend(other);
+    }
+    matching
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::domain::language::LANGUAGE_METADATA_KEY;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::utils::request_context::RequestContext;
+
+    #[tokio::test]
+    async fn test_contexts_in_query_language_come_first() {
+        assert_eq!(detect_language("肺炎的抗生素治疗方案（参考WHO指南）"), Some(ContentLanguage::Chinese));
+        assert_eq!(detect_language("Pneumonia (肺炎) is treated with antibiotics"), Some(ContentLanguage::English));
+        assert_eq!(detect_language("2024-01-01"), None);
+        assert_eq!(query_language("42", Some("zh-CN")), Some(ContentLanguage::Chinese));
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let english = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia 肺炎 guideline: antibiotics for 5 days".to_string(), 9)
+            .await
+            .unwrap();
+        let chinese = manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "肺炎 pneumonia 治疗指南：抗生素疗程五天".to_string(), 5)
+            .await
+            .unwrap();
+        assert_eq!(english.metadata[LANGUAGE_METADATA_KEY], "en");
+        assert_eq!(chinese.metadata[LANGUAGE_METADATA_KEY], "zh");
+
+        // 内容改为中文后自动识别的标签随之更新，显式指定的标签保留
+        manager.update_context(english.id, Some("肺炎指南".to_string()), None, None).await.unwrap();
+        assert_eq!(manager.get_context(english.id).await.unwrap().metadata[LANGUAGE_METADATA_KEY], "zh");
+        let explicit = HashMap::from([(LANGUAGE_METADATA_KEY.to_string(), "en".to_string())]);
+        manager.update_context(english.id, Some("Pneumonia 肺炎 guideline: antibiotics for 5 days".to_string()), Some(explicit), None).await.unwrap();
+        manager.update_context(english.id, Some("Pneumonia 肺炎 guideline: antibiotics for 7 days".to_string()), None, None).await.unwrap();
+        assert_eq!(manager.get_context(english.id).await.unwrap().metadata[LANGUAGE_METADATA_KEY], "en");
+
+        // 中文查询优先得到中文上下文，英文上下文作为回退排在后面
+        let selector = ContextSelector::new(manager.clone());
+        let selected = selector.select_contexts("u1", "s1", "肺炎 pneumonia", "medical").await.unwrap();
+        assert_eq!(selected.iter().map(|ctx| ctx.id).collect::<Vec<_>>(), vec![chinese.id, english.id]);
+        let selected = selector.select_contexts("u1", "s1", "pneumonia 肺炎 antibiotics", "medical").await.unwrap();
+        assert_eq!(selected[0].id, english.id);
+
+
+        // 关闭跨语言回退时只返回同语言的上下文
+        let routed = route_by_language(
+            selected,
+            Some(ContentLanguage::Chinese),
+            &LanguageRoutingConfig { cross_language_fallback: false, ..LanguageRoutingConfig::default() },
+        );
+        assert_eq!(routed.iter().map(|ctx| ctx.id).collect::<Vec<_>>(), vec![chinese.id]);
+
+        // 查询语言无法识别时按语言区域路由，不同语言区域不共用缓存结果
+        let english = manager
+            .create_context("s2".to_string(), "u2".to_string(), "legal".to_string(), "2024 contract law revision".to_string(), 9)
+            .await
+            .unwrap();
+        let chinese = manager
+            .create_context("s2".to_string(), "u2".to_string(), "legal".to_string(), "2024 合同法修订".to_string(), 5)
+            .await
+            .unwrap();
+        let zh = RequestContext::default().with_locale("zh-CN");
+        let selected = selector.select_contexts_for_request(&zh, "u2", "s2", "2024", "legal").await.unwrap();
+        assert_eq!(selected[0].id, chinese.id);
+        let en = RequestContext::default().with_locale("en-US");
+        let selected = selector.select_contexts_for_request(&en, "u2", "s2", "2024", "legal").await.unwrap();
+        assert_eq!(selected[0].id, english.id);
+    }
+}
@@ -1,2 +1,13 @@
 pub mod context_selector;
-pub mod async_context_selector;
\ No newline at end of file
+pub mod async_context_selector;
+pub mod embedding;
+pub mod hybrid_search;
+pub mod threshold_calibration;
+pub mod scoring_cache;
+pub mod personalization;
+pub mod embedding_migration;
+pub mod rag_fusion;
+pub mod hyde;
+pub mod ranking;
+pub mod language_routing;
+pub mod post_filters;
\ No newline at end of file
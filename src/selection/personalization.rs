@@ -0,0 +1,153 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 排序权重 - 控制相关性、权威性（优先级）和时效性在混合得分中的占比
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankingWeights {
+    pub relevance: f64,  // 与查询的匹配程度
+    pub authority: f64,  // 上下文优先级
+    pub recency: f64,    // 更新时间衰减
+}
+
+impl Default for RankingWeights {
+    fn default() -> Self {
+        Self {
+            relevance: 0.5,
+            authority: 0.3,
+            recency: 0.2,
+        }
+    }
+}
+
+impl RankingWeights {
+    /// 校验并归一化权重（各项非负且总和为1）
+    pub fn normalized(&self) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let weights = [self.relevance, self.authority, self.recency];
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err("Ranking weights must be finite and non-negative".into());
+        }
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return Err("Ranking weights must not all be zero".into());
+        }
+        Ok(Self {
+            relevance: self.relevance / total,
+            authority: self.authority / total,
+            recency: self.recency / total,
+        })
+    }
+
+    /// 计算综合得分（各分项均为0-1）
+    pub fn combine(&self, relevance: f64, priority: u8, recency: f64) -> f64 {
+        self.relevance * relevance + self.authority * (priority as f64 / 10.0) + self.recency * recency
+    }
+}
+
+/// 用户排序偏好
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserRankingProfile {
+    pub user_id: String,
+    pub weights: RankingWeights,     // 已归一化的权重
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 用户排序偏好存储 - 未设置偏好的用户使用默认权重
+pub struct UserProfileStore {
+    default_weights: Arc<RwLock<RankingWeights>>,
+    profiles: Arc<RwLock<HashMap<String, UserRankingProfile>>>,
+}
+
+impl Default for UserProfileStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UserProfileStore {
+    /// 创建空的偏好存储
+    pub fn new() -> Self {
+        Self {
+            default_weights: Arc::new(RwLock::new(RankingWeights::default())),
+            profiles: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 设置用户偏好，返回归一化后的偏好
+    pub async fn set_profile(
+        &self,
+        user_id: &str,
+        weights: RankingWeights,
+    ) -> Result<UserRankingProfile, Box<dyn std::error::Error + Send + Sync>> {
+        let profile = UserRankingProfile {
+            user_id: user_id.to_string(),
+            weights: weights.normalized()?,
+            updated_at: Utc::now(),
+        };
+        self.profiles.write().await.insert(user_id.to_string(), profile.clone());
+        Ok(profile)
+    }
+
+    /// 重置用户偏好为默认权重，返回是否存在自定义偏好
+    pub async fn reset_profile(&self, user_id: &str) -> bool {
+        self.profiles.write().await.remove(user_id).is_some()
+    }
+
+    /// 获取用户的自定义偏好
+    pub async fn get_profile(&self, user_id: &str) -> Option<UserRankingProfile> {
+        self.profiles.read().await.get(user_id).cloned()
+    }
+
+    /// 获取用户生效的权重
+    pub async fn effective_weights(&self, user_id: &str) -> RankingWeights {
+        match self.profiles.read().await.get(user_id) {
+            Some(profile) => profile.weights.clone(),
+            None => self.default_weights.read().await.clone(),
+        }
+    }
+
+    /// 获取所有自定义偏好
+    pub async fn list_profiles(&self) -> Vec<UserRankingProfile> {
+        let mut profiles: Vec<UserRankingProfile> = self.profiles.read().await.values().cloned().collect();
+        profiles.sort_by(|a, b| a.user_id.cmp(&b.user_id));
+        profiles
+    }
+
+    /// 设置默认权重
+    pub async fn set_default_weights(&self, weights: RankingWeights) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        *self.default_weights.write().await = weights.normalized()?;
+        Ok(())
+    }
+
+    /// 获取默认权重
+    pub async fn get_default_weights(&self) -> RankingWeights {
+        self.default_weights.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_profile_store() {
+        let store = UserProfileStore::new();
+        assert_eq!(store.effective_weights("u1").await, RankingWeights::default());
+
+        let profile = store
+            .set_profile("u1", RankingWeights { relevance: 2.0, authority: 0.0, recency: 2.0 })
+            .await
+            .unwrap();
+        assert_eq!(profile.weights, RankingWeights { relevance: 0.5, authority: 0.0, recency: 0.5 });
+        assert!(store
+            .set_profile("u2", RankingWeights { relevance: -1.0, authority: 1.0, recency: 1.0 })
+            .await
+            .is_err());
+
+        assert!(store.reset_profile("u1").await);
+        assert!(!store.reset_profile("u1").await);
+        assert_eq!(store.effective_weights("u1").await, RankingWeights::default());
+    }
+}
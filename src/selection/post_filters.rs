@@ -0,0 +1,134 @@
+//! 选择结果后置过滤 - 排序之后按更新时间窗口、来源/标签白名单和每个来源的数量上限筛选，
+//! 用于新闻类领域，避免返回过时的内容或全部来自同一来源的回答
+
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use crate::context::llm_context::LLMContext;
+
+/// 记录上下文来源的元数据键（入库、爬虫和搜索导入时写入）
+pub const SOURCE_METADATA_KEY: &str = "source";
+
+/// 后置过滤配置，各项为空时不限制
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PostFilterConfig {
+    #[serde(default)]
+    pub max_age_days: Option<u32>,          // 只保留最近N天内更新的上下文
+    #[serde(default)]
+    pub allowed_sources: Vec<String>,       // 来源白名单，没有来源的上下文不通过
+    #[serde(default)]
+    pub allowed_tags: Vec<String>,          // 标签白名单，至少带有其中一个标签
+    #[serde(default)]
+    pub max_per_source: Option<usize>,      // 每个来源最多保留的上下文数（没有来源的上下文视为同一来源）
+}
+
+impl PostFilterConfig {
+    /// 是否配置了任何过滤
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// 校验数量上限
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_per_source == Some(0) {
+            return Err("max_per_source must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 上下文的来源
+pub fn context_source(context: &LLMContext) -> Option<&str> {
+    context.metadata.get(SOURCE_METADATA_KEY).map(String::as_str)
+}
+
+/// 按配置过滤已排序的上下文，保持原有顺序；now为判断更新时间窗口的时间点（按时间点选择时为该时间）
+pub fn apply_post_filters(ranked: Vec<LLMContext>, config: &PostFilterConfig, now: DateTime<Utc>) -> Vec<LLMContext> {
+    if config.is_empty() {
+        return ranked;
+    }
+    let updated_after = config.max_age_days.map(|days| now - Duration::days(days as i64));
+    let mut per_source: HashMap<Option<String>, usize> = HashMap::new();
+    ranked
+        .into_iter()
+        .filter(|context| updated_after.is_none_or(|updated_after| context.updated_at >= updated_after))
+        .filter(|context| {
+            config.allowed_sources.is_empty()
+                || context_source(context).is_some_and(|source| config.allowed_sources.iter().any(|allowed| allowed == source))
+        })
+        .filter(|context| config.allowed_tags.is_empty() || context.tags.iter().any(|tag| config.allowed_tags.contains(tag)))
+        .filter(|context| {
+            let Some(max_per_source) = config.max_per_source else {
+                return true;
+            };
+            let count = per_source.entry(context_source(context).map(str::to_string)).or_insert(0);
+            *count += 1;
+            *count <= max_per_source
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::selection::async_context_selector::ContextSelector;
+
+    #[tokio::test]
+    async fn test_news_domain_post_filters() {
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let mut contexts = Vec::new();
+        for (source, tags, age_days) in [
+            ("wire", vec!["markets"], 1),
+            ("wire", vec!["markets"], 2),
+            ("wire", vec!["markets"], 3),
+            ("blog", vec!["markets"], 1),
+            ("herald", vec!["markets", "opinion"], 20),
+            ("herald", vec!["sports"], 1),
+        ] {
+            let mut context = manager
+                .create_context(
+                    "s1".to_string(),
+                    "u1".to_string(),
+                    "finance".to_string(),
+                    format!("Stock markets rallied today ({} report)", source),
+                    5,
+                )
+                .await
+                .unwrap();
+            context.metadata.insert(SOURCE_METADATA_KEY.to_string(), source.to_string());
+            context.tags = tags.into_iter().map(str::to_string).collect();
+            context.updated_at = Utc::now() - Duration::days(age_days);
+            contexts.push(context);
+        }
+
+        let config = PostFilterConfig {
+            max_age_days: Some(7),
+            allowed_sources: vec!["wire".to_string(), "herald".to_string()],
+            allowed_tags: vec!["markets".to_string()],
+            max_per_source: Some(2),
+        };
+        let ids = |filtered: Vec<LLMContext>| filtered.iter().map(|ctx| ctx.id).collect::<Vec<_>>();
+        // 第三条通讯稿超出来源上限，博客不在白名单，旧评论超出时间窗口，体育稿没有白名单标签
+        assert_eq!(ids(apply_post_filters(contexts.clone(), &config, Utc::now())), vec![contexts[0].id, contexts[1].id]);
+        // 按时间点选择时以该时间判断时间窗口
+        let earlier = Utc::now() - Duration::days(15);
+        assert_eq!(ids(apply_post_filters(contexts.clone(), &config, earlier)), ids(contexts[..2].iter().chain(&contexts[4..5]).cloned().collect()));
+        assert_eq!(apply_post_filters(contexts.clone(), &PostFilterConfig::default(), Utc::now()).len(), contexts.len());
+
+        // 领域覆盖的过滤在选择器中生效
+        let selector = ContextSelector::new(manager);
+        let mut selector_config = selector.get_config().await;
+        selector_config.max_contexts_to_return = 10;
+        selector_config.domain_post_filters.insert("finance".to_string(), PostFilterConfig { max_per_source: Some(1), ..PostFilterConfig::default() });
+        selector.update_config(selector_config).await;
+        let selected = selector.select_contexts("u2", "s2", "stock markets rallied", "finance").await.unwrap();
+        assert_eq!(selected.len(), 1);
+        let mut selector_config = selector.get_config().await;
+        selector_config.domain_post_filters.clear();
+        selector.update_config(selector_config).await;
+        selector.clear_cache().await;
+        assert!(selector.select_contexts("u2", "s2", "stock markets rallied", "finance").await.unwrap().len() > 1);
+    }
+}
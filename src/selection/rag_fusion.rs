@@ -0,0 +1,190 @@
+//! 多查询检索（RAG-fusion）- 由大模型生成若干查询变体，分别选择后以倒数排名融合（RRF）合并结果，提升模糊查询的召回
+
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use crate::context::llm_context::LLMContext;
+use crate::selection::ranking::compare_scores_desc;
+
+/// 检索模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RetrievalMode {
+    #[default]
+    Standard,   // 直接按原始查询选择
+    MultiQuery, // 原始查询加大模型生成的变体，RRF融合
+    Hyde,       // 以大模型生成的假设答案的向量检索
+}
+
+/// 多查询检索配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiQueryConfig {
+    pub variations: usize,              // 生成的查询变体数量（建议3-4）
+    pub rrf_k: f64,                     // RRF平滑常数
+    pub hourly_generation_budget: u32,  // 每小时最多调用大模型的次数，用尽后回退到单查询
+}
+
+impl Default for MultiQueryConfig {
+    fn default() -> Self {
+        Self {
+            variations: 3,
+            rrf_k: 60.0,
+            hourly_generation_budget: 500,
+        }
+    }
+}
+
+/// 生成调用用量
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationUsage {
+    pub window_start: DateTime<Utc>,    // 当前小时窗口起点
+    pub used: u32,                      // 本窗口已用次数
+    pub rejected: u64,                  // 因预算用尽被跳过的次数（累计）
+}
+
+/// 按小时窗口计数的大模型调用预算
+#[derive(Debug)]
+pub struct GenerationBudget {
+    usage: RwLock<GenerationUsage>,
+}
+
+impl Default for GenerationBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 当前所在小时的起点
+fn current_hour() -> DateTime<Utc> {
+    let now = Utc::now();
+    now.duration_trunc(Duration::hours(1)).unwrap_or(now)
+}
+
+impl GenerationBudget {
+    /// 创建预算计数器
+    pub fn new() -> Self {
+        Self {
+            usage: RwLock::new(GenerationUsage { window_start: current_hour(), used: 0, rejected: 0 }),
+        }
+    }
+
+    /// 尝试消耗一次调用额度，超出每小时上限时返回false
+    pub async fn try_consume(&self, hourly_limit: u32) -> bool {
+        let mut usage = self.usage.write().await;
+        let hour = current_hour();
+        if usage.window_start != hour {
+            usage.window_start = hour;
+            usage.used = 0;
+        }
+        if usage.used >= hourly_limit {
+            usage.rejected += 1;
+            return false;
+        }
+        usage.used += 1;
+        true
+    }
+
+    /// 获取用量
+    pub async fn get_usage(&self) -> GenerationUsage {
+        self.usage.read().await.clone()
+    }
+}
+
+/// 构造生成查询变体的提示
+pub fn variation_prompt(query: &str, count: usize) -> String {
+    format!(
+        "Rewrite the following search query into {} alternative queries that capture different phrasings and related terms. \
+         Return one query per line without numbering or explanations.\n\nQuery: {}",
+        count, query
+    )
+}
+
+/// 去掉行首的编号（"1."、"2)"）或列表符号
+fn strip_list_marker(line: &str) -> &str {
+    let line = line.trim();
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let line = match line[digits..].chars().next() {
+        Some('.') | Some(')') if digits > 0 => &line[digits + 1..],
+        _ => line,
+    };
+    line.trim_start_matches(['-', '*', '•']).trim()
+}
+
+/// 解析大模型返回的查询变体：去掉编号和列表符号，剔除空行、重复项和与原查询相同的变体
+pub fn parse_variations(text: &str, original: &str, max: usize) -> Vec<String> {
+    let mut seen: HashSet<String> = HashSet::new();
+    seen.insert(original.trim().to_lowercase());
+    text.lines()
+        .map(|line| strip_list_marker(line).trim_matches('"').trim().to_string())
+        .filter(|line| !line.is_empty() && seen.insert(line.to_lowercase()))
+        .take(max)
+        .collect()
+}
+
+/// 倒数排名融合：每个列表中排名r（从1开始）的上下文得分1/(k+r)，多个列表累加
+pub fn reciprocal_rank_fusion(lists: Vec<Vec<LLMContext>>, k: f64) -> Vec<(LLMContext, f64)> {
+    let mut fused: Vec<(LLMContext, f64)> = Vec::new();
+    let mut positions: HashMap<Uuid, usize> = HashMap::new();
+    for list in lists {
+        for (rank, context) in list.into_iter().enumerate() {
+            let score = 1.0 / (k + rank as f64 + 1.0);
+            match positions.get(&context.id) {
+                Some(&index) => fused[index].1 += score,
+                None => {
+                    positions.insert(context.id, fused.len());
+                    fused.push((context, score));
+                }
+            }
+        }
+    }
+    // 稳定排序，同分时保持首次出现的顺序
+    fused.sort_by(|a, b| compare_scores_desc(a.1, b.1));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::test_support::FixedGenerator;
+
+    #[tokio::test]
+    async fn test_multi_query_selection() {
+        assert_eq!(
+            parse_variations("1. pneumonia antibiotics\n- \"bronchitis inhaler\"\n\nchest problem\n2) Pneumonia Antibiotics\n2024 flu guidance", "chest problem", 4),
+            vec!["pneumonia antibiotics".to_string(), "bronchitis inhaler".to_string(), "2024 flu guidance".to_string()]
+        );
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let pneumonia = manager
+            .create_context("s0".to_string(), "u0".to_string(), "medical".to_string(), "pneumonia antibiotics course".to_string(), 5)
+            .await
+            .unwrap();
+        let bronchitis = manager
+            .create_context("s0".to_string(), "u0".to_string(), "medical".to_string(), "bronchitis inhaler guidance".to_string(), 5)
+            .await
+            .unwrap();
+        let generator = Arc::new(FixedGenerator::new("pneumonia antibiotics\nbronchitis inhaler"));
+        let selector = ContextSelector::new(manager.clone()).with_text_generator(generator);
+
+        // 标准模式下模糊查询没有命中
+        assert!(selector.select_contexts("u1", "s1", "chest problem", "medical").await.unwrap().is_empty());
+
+        let mut config = selector.get_config().await;
+        config.retrieval_mode = RetrievalMode::MultiQuery;
+        config.multi_query.hourly_generation_budget = 1;
+        selector.update_config(config).await;
+        let selected = selector.select_contexts("u1", "s2", "chest problem", "medical").await.unwrap();
+        let ids: HashSet<Uuid> = selected.iter().map(|ctx| ctx.id).collect();
+        assert_eq!(ids, [pneumonia.id, bronchitis.id].into_iter().collect());
+
+        // 预算用尽后回退到单查询
+        assert!(selector.select_contexts("u1", "s3", "chest problem", "medical").await.unwrap().is_empty());
+        let usage = selector.get_generation_usage().await;
+        assert_eq!(usage.used, 1);
+        assert_eq!(usage.rejected, 1);
+    }
+}
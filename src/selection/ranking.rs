@@ -0,0 +1,295 @@
+//! 排序规则 - 不会因NaN而panic的得分比较，以及同分时按优先级、时效性和ID依次决胜，保证相同输入总是得到相同的选择结果；
+//! 可选的同分轮换让得分相同的上下文轮流获得曝光
+
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::context::llm_context::LLMContext;
+
+/// 轮换状态最多跟踪的同分组数量，超出时重置
+const MAX_TRACKED_GROUPS: usize = 10_000;
+
+/// 同分决胜规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TieBreaker {
+    Priority,   // 优先级高的在前
+    Recency,    // 更新时间近的在前
+    Id,         // ID小的在前
+}
+
+/// 默认决胜顺序
+pub fn default_tie_breakers() -> Vec<TieBreaker> {
+    vec![TieBreaker::Priority, TieBreaker::Recency, TieBreaker::Id]
+}
+
+/// 按得分降序比较；NaN排在所有数值之后
+pub fn compare_scores_desc(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+    }
+}
+
+/// 按决胜规则比较两个上下文；规则未区分时最终按ID比较，保证全序
+pub fn compare_ties(a: &LLMContext, b: &LLMContext, tie_breakers: &[TieBreaker]) -> Ordering {
+    tie_breakers
+        .iter()
+        .map(|tie_breaker| match tie_breaker {
+            TieBreaker::Priority => b.priority.cmp(&a.priority),
+            TieBreaker::Recency => b.updated_at.cmp(&a.updated_at),
+            TieBreaker::Id => a.id.cmp(&b.id),
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or_else(|| a.id.cmp(&b.id))
+}
+
+/// 按得分降序排序，同分时按决胜规则排序
+pub fn sort_scored(scored: &mut [(LLMContext, f64)], tie_breakers: &[TieBreaker]) {
+    scored.sort_by(|a, b| compare_scores_desc(a.1, b.1).then_with(|| compare_ties(&a.0, &b.0, tie_breakers)));
+}
+
+/// 同分轮换模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TieRotationMode {
+    #[default]
+    Off,            // 按决胜规则固定排序
+    RoundRobin,     // 平滑加权轮询，权重为优先级+1
+    WeightedSample, // 按优先级+1加权随机打乱
+}
+
+/// 同分轮换配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TieRotationConfig {
+    pub mode: TieRotationMode,
+    pub seed: Option<u64>,          // 加权随机的种子，设置后结果可复现
+    pub score_tolerance: f64,       // 得分差不超过该值视为同分
+}
+
+impl Default for TieRotationConfig {
+    fn default() -> Self {
+        Self {
+            mode: TieRotationMode::Off,
+            seed: None,
+            score_tolerance: 1e-9,
+        }
+    }
+}
+
+/// 轮换状态
+struct RotationState {
+    seed: Option<u64>,
+    rng: u64,
+    current_weights: HashMap<u64, HashMap<Uuid, i64>>,  // 同分组 -> 各上下文的当前权重
+}
+
+/// 同分轮换器 - 在已排序的结果中打乱得分相同的相邻上下文
+pub struct TieRotator {
+    state: Mutex<RotationState>,
+}
+
+impl Default for TieRotator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TieRotator {
+    /// 创建轮换器
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(RotationState {
+                seed: None,
+                rng: Uuid::new_v4().as_u128() as u64,
+                current_weights: HashMap::new(),
+            }),
+        }
+    }
+
+    /// 对已按得分降序排列的结果应用轮换
+    pub fn rotate(&self, scored: &mut [(LLMContext, f64)], config: &TieRotationConfig) {
+        if config.mode == TieRotationMode::Off || scored.len() < 2 {
+            return;
+        }
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if state.seed != config.seed {
+            state.seed = config.seed;
+            state.rng = config.seed.unwrap_or_else(|| Uuid::new_v4().as_u128() as u64);
+            state.current_weights.clear();
+        }
+
+        let mut start = 0;
+        while start < scored.len() {
+            let mut end = start + 1;
+            while end < scored.len() && (scored[start].1 - scored[end].1).abs() <= config.score_tolerance {
+                end += 1;
+            }
+            if end - start > 1 {
+                let group = &mut scored[start..end];
+                match config.mode {
+                    TieRotationMode::RoundRobin => state.round_robin(group),
+                    TieRotationMode::WeightedSample => state.weighted_shuffle(group),
+                    TieRotationMode::Off => {}
+                }
+            }
+            start = end;
+        }
+    }
+}
+
+fn weight(context: &LLMContext) -> i64 {
+    context.priority as i64 + 1
+}
+
+impl RotationState {
+    /// splitmix64
+    fn next_u64(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// (0, 1]区间的均匀随机数
+    fn next_unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) as f64 + 1.0) / (1u64 << 53) as f64
+    }
+
+    /// 平滑加权轮询：各上下文当前权重加上自身权重后按当前权重排序，排在首位的减去总权重
+    fn round_robin(&mut self, group: &mut [(LLMContext, f64)]) {
+        let mut ids: Vec<Uuid> = group.iter().map(|(context, _)| context.id).collect();
+        ids.sort();
+        let mut hasher = DefaultHasher::new();
+        ids.hash(&mut hasher);
+        let key = hasher.finish();
+        if self.current_weights.len() >= MAX_TRACKED_GROUPS && !self.current_weights.contains_key(&key) {
+            self.current_weights.clear();
+        }
+
+        let current = self.current_weights.entry(key).or_default();
+        let total: i64 = group.iter().map(|(context, _)| weight(context)).sum();
+        for (context, _) in group.iter() {
+            *current.entry(context.id).or_insert(0) += weight(context);
+        }
+        group.sort_by(|a, b| current[&b.0.id].cmp(&current[&a.0.id]));
+        if let Some(entry) = current.get_mut(&group[0].0.id) {
+            *entry -= total;
+        }
+    }
+
+    /// 加权随机打乱（Efraimidis-Spirakis：键为u^(1/w)，按键降序）
+    fn weighted_shuffle(&mut self, group: &mut [(LLMContext, f64)]) {
+        let mut keyed: Vec<(f64, (LLMContext, f64))> = group
+            .iter()
+            .map(|item| (self.next_unit().powf(1.0 / weight(&item.0) as f64), item.clone()))
+            .collect();
+        keyed.sort_by(|a, b| compare_scores_desc(a.0, b.0));
+        for (slot, (_, item)) in group.iter_mut().zip(keyed) {
+            *slot = item;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::selection::async_context_selector::ContextSelector;
+
+    #[tokio::test]
+    async fn test_stable_nan_safe_ranking() {
+        assert_eq!(compare_scores_desc(f64::NAN, 0.1), Ordering::Greater);
+        assert_eq!(compare_scores_desc(0.9, 0.1), Ordering::Less);
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let mut contexts = Vec::new();
+        for priority in [5, 9, 5, 5] {
+            contexts.push(
+                manager
+                    .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "pneumonia".to_string(), priority)
+                    .await
+                    .unwrap(),
+            );
+        }
+
+        // NaN得分不会panic并排在最后；同分按优先级、时效性、ID决胜，与输入顺序无关
+        let mut scored: Vec<(LLMContext, f64)> = contexts.iter().map(|context| (context.clone(), 0.5)).collect();
+        scored[3].1 = f64::NAN;
+        let mut reversed = scored.clone();
+        reversed.reverse();
+        sort_scored(&mut scored, &default_tie_breakers());
+        sort_scored(&mut reversed, &default_tie_breakers());
+        let order: Vec<_> = scored.iter().map(|(context, _)| context.id).collect();
+        assert_eq!(order, reversed.iter().map(|(context, _)| context.id).collect::<Vec<_>>());
+        assert_eq!(order[0], contexts[1].id);
+        assert_eq!(order[3], contexts[3].id);
+
+        let mut by_id: Vec<(LLMContext, f64)> = contexts[..3].iter().map(|context| (context.clone(), 0.5)).collect();
+        sort_scored(&mut by_id, &[TieBreaker::Id]);
+        assert!(by_id.windows(2).all(|pair| pair[0].0.id < pair[1].0.id));
+
+        // 选择器对相同输入给出相同结果
+        let selector = ContextSelector::new(manager.clone());
+        let mut config = selector.get_config().await;
+        config.enable_cache = false;
+        selector.update_config(config).await;
+        let first = selector.select_contexts("u1", "s1", "pneumonia", "medical").await.unwrap();
+        for _ in 0..5 {
+            let again = selector.select_contexts("u1", "s1", "pneumonia", "medical").await.unwrap();
+            assert_eq!(first.iter().map(|context| context.id).collect::<Vec<_>>(), again.iter().map(|context| context.id).collect::<Vec<_>>());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tie_rotation_spreads_exposure() {
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let context = manager
+                .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "pneumonia".to_string(), 5)
+                .await
+                .unwrap();
+            ids.push(context.id);
+        }
+
+        // 轮询：同分的三个上下文轮流排在首位
+        let selector = ContextSelector::new(manager.clone());
+        let mut config = selector.get_config().await;
+        config.max_contexts_to_return = 1;
+        config.tie_rotation = TieRotationConfig { mode: TieRotationMode::RoundRobin, ..TieRotationConfig::default() };
+        selector.update_config(config.clone()).await;
+        let mut top = Vec::new();
+        for _ in 0..6 {
+            top.push(selector.select_contexts("u1", "s1", "pneumonia", "medical").await.unwrap()[0].id);
+        }
+        for id in &ids {
+            assert_eq!(top.iter().filter(|selected| *selected == id).count(), 2);
+        }
+
+        // 加权随机：相同种子得到相同序列
+        let sequence = |seed: u64| {
+            let manager = manager.clone();
+            let mut config = config.clone();
+            async move {
+                let selector = ContextSelector::new(manager);
+                config.tie_rotation = TieRotationConfig { mode: TieRotationMode::WeightedSample, seed: Some(seed), ..TieRotationConfig::default() };
+                selector.update_config(config).await;
+                let mut top = Vec::new();
+                for _ in 0..30 {
+                    top.push(selector.select_contexts("u1", "s1", "pneumonia", "medical").await.unwrap()[0].id);
+                }
+                top
+            }
+        };
+        let first = sequence(42).await;
+        assert_eq!(first, sequence(42).await);
+        assert!(ids.iter().all(|id| first.contains(id)));
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use moka::future::Cache;
+use moka::policy::EvictionPolicy;
+use uuid::Uuid;
+use crate::context::llm_context::LLMContext;
+use crate::selection::embedding::Embedder;
+use crate::utils::tokenizer::Tokenizer;
+
+/// 上下文文本特征（按上下文版本缓存）
+#[derive(Debug)]
+pub struct ContextFeatures {
+    pub words: HashSet<String>,   // 小写空白切分的词集合（相关性评分使用）
+    pub terms: Vec<String>,       // 词项序列（BM25使用）
+}
+
+impl ContextFeatures {
+    /// 从上下文内容计算特征
+    pub fn from_text(text: &str, tokenizer: &dyn Tokenizer) -> Self {
+        let lower = text.to_lowercase();
+        Self {
+            words: lower.split_whitespace().map(|w| w.to_string()).collect(),
+            terms: tokenizer.terms(text),
+        }
+    }
+
+    /// 估算占用的字节数
+    fn approximate_size(&self) -> u32 {
+        let words: usize = self.words.iter().map(|w| w.len() + 24).sum();
+        let terms: usize = self.terms.iter().map(|t| t.len() + 24).sum();
+        (words + terms + 64).min(u32::MAX as usize) as u32
+    }
+}
+
+/// 评分缓存统计
+#[derive(Debug, Clone, Default)]
+pub struct ScoringCacheStats {
+    pub feature_hits: u64,
+    pub feature_misses: u64,
+    pub embedding_hits: u64,
+    pub embedding_misses: u64,
+    pub entry_count: u64,
+    pub weighted_size_bytes: u64,
+}
+
+/// 评分缓存 - 按(上下文ID, 版本, 分词器或向量模型)缓存分词结果和向量，上下文更新后版本变化自然失效
+pub struct ScoringCache {
+    features: Cache<(Uuid, u32, String), Arc<ContextFeatures>>,
+    embeddings: Cache<(Uuid, u32, String), Arc<Vec<f32>>>,
+    feature_hits: AtomicU64,
+    feature_misses: AtomicU64,
+    embedding_hits: AtomicU64,
+    embedding_misses: AtomicU64,
+}
+
+impl Default for ScoringCache {
+    fn default() -> Self {
+        Self::new(32 * 1024 * 1024) // 32MB
+    }
+}
+
+impl ScoringCache {
+    /// 创建评分缓存，`max_bytes`为分词和向量缓存各自的内存上限（估算值）
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            features: Cache::builder()
+                .max_capacity(max_bytes)
+                .weigher(|_key, value: &Arc<ContextFeatures>| value.approximate_size())
+                .support_invalidation_closures()
+                .build(),
+            embeddings: Cache::builder()
+                .max_capacity(max_bytes)
+                .weigher(|_key, value: &Arc<Vec<f32>>| (value.len() * 4 + 64).min(u32::MAX as usize) as u32)
+                .support_invalidation_closures()
+                .build(),
+            feature_hits: AtomicU64::new(0),
+            feature_misses: AtomicU64::new(0),
+            embedding_hits: AtomicU64::new(0),
+            embedding_misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 获取上下文特征，未命中时计算并缓存
+    pub async fn get_features(&self, context: &LLMContext, tokenizer: &dyn Tokenizer) -> Arc<ContextFeatures> {
+        let key = (context.id, context.version, tokenizer.name().to_string());
+        if let Some(features) = self.features.get(&key).await {
+            self.feature_hits.fetch_add(1, Ordering::Relaxed);
+            return features;
+        }
+
+        self.feature_misses.fetch_add(1, Ordering::Relaxed);
+        let features = Arc::new(ContextFeatures::from_text(&context.context_data, tokenizer));
+        self.features.insert(key, features.clone()).await;
+        features
+    }
+
+    /// 获取上下文向量，未命中时调用向量化器并缓存
+    pub async fn get_embedding(
+        &self,
+        context: &LLMContext,
+        embedder: &dyn Embedder,
+    ) -> Result<Arc<Vec<f32>>, Box<dyn std::error::Error + Send + Sync>> {
+        let key = (context.id, context.version, embedder.model_name().to_string());
+        if let Some(embedding) = self.embeddings.get(&key).await {
+            self.embedding_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(embedding);
+        }
+
+        self.embedding_misses.fetch_add(1, Ordering::Relaxed);
+        let embedding = Arc::new(embedder.embed(&context.context_data).await?);
+        self.embeddings.insert(key, embedding.clone()).await;
+        Ok(embedding)
+    }
+
+    /// 写入预先计算的上下文向量（如从上下文包安装），之后的查询不再调用向量化器
+    pub async fn insert_embedding(&self, context: &LLMContext, model_name: &str, embedding: Vec<f32>) {
+        let key = (context.id, context.version, model_name.to_string());
+        self.embeddings.insert(key, Arc::new(embedding)).await;
+    }
+
+    /// 使指定上下文的所有缓存项失效
+    pub async fn invalidate_context(&self, context_id: Uuid) {
+        let _ = self.features.invalidate_entries_if(move |key, _| key.0 == context_id);
+        let _ = self.embeddings.invalidate_entries_if(move |key, _| key.0 == context_id);
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        self.features.invalidate_all();
+        self.embeddings.invalidate_all();
+    }
+
+    /// 获取缓存统计
+    pub async fn get_stats(&self) -> ScoringCacheStats {
+        self.features.run_pending_tasks().await;
+        self.embeddings.run_pending_tasks().await;
+        ScoringCacheStats {
+            feature_hits: self.feature_hits.load(Ordering::Relaxed),
+            feature_misses: self.feature_misses.load(Ordering::Relaxed),
+            embedding_hits: self.embedding_hits.load(Ordering::Relaxed),
+            embedding_misses: self.embedding_misses.load(Ordering::Relaxed),
+            entry_count: self.features.entry_count() + self.embeddings.entry_count(),
+            weighted_size_bytes: self.features.weighted_size() + self.embeddings.weighted_size(),
+        }
+    }
+}
+
+/// 规范化查询文本：去除首尾空白、转为小写并合并连续空白
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// 查询向量缓存统计
+#[derive(Debug, Clone, Default)]
+pub struct QueryEmbeddingCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entry_count: u64,
+    pub hit_rate: f64,  // 命中率（0-1）
+}
+
+/// 查询向量缓存 - 按(规范化查询文本, 模型名)缓存查询向量，按LRU淘汰
+pub struct QueryEmbeddingCache {
+    cache: Cache<(String, String), Arc<Vec<f32>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for QueryEmbeddingCache {
+    fn default() -> Self {
+        Self::new(1000)
+    }
+}
+
+impl QueryEmbeddingCache {
+    /// 创建最多缓存`max_entries`个查询向量的缓存
+    pub fn new(max_entries: u64) -> Self {
+        Self {
+            cache: Cache::builder()
+                .max_capacity(max_entries)
+                .eviction_policy(EvictionPolicy::lru())
+                .build(),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// 获取查询向量，未命中时调用向量化器并缓存；返回向量及是否命中缓存
+    pub async fn get_or_embed(
+        &self,
+        query: &str,
+        embedder: &dyn Embedder,
+    ) -> Result<(Arc<Vec<f32>>, bool), Box<dyn std::error::Error + Send + Sync>> {
+        let normalized = normalize_query(query);
+        let key = (normalized, embedder.model_name().to_string());
+        if let Some(embedding) = self.cache.get(&key).await {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok((embedding, true));
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let embedding = Arc::new(embedder.embed(&key.0).await?);
+        self.cache.insert(key, embedding.clone()).await;
+        Ok((embedding, false))
+    }
+
+    /// 当前命中率（无访问时为0）
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.hits.load(Ordering::Relaxed);
+        let total = hits + self.misses.load(Ordering::Relaxed);
+        if total == 0 {
+            0.0
+        } else {
+            hits as f64 / total as f64
+        }
+    }
+
+    /// 清空缓存
+    pub fn clear(&self) {
+        self.cache.invalidate_all();
+    }
+
+    /// 获取缓存统计
+    pub async fn get_stats(&self) -> QueryEmbeddingCacheStats {
+        self.cache.run_pending_tasks().await;
+        QueryEmbeddingCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entry_count: self.cache.entry_count(),
+            hit_rate: self.hit_rate(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::schema_migration::CURRENT_SCHEMA_VERSION;
+    use crate::selection::embedding::HashingEmbedder;
+use crate::utils::tokenizer::WhitespaceTokenizer;
+    use std::collections::HashMap;
+
+    fn make_context(data: &str, version: u32) -> LLMContext {
+        LLMContext {
+            id: Uuid::nil(),
+            session_id: "s1".to_string(),
+            user_id: "u1".to_string(),
+            domain: "medical".to_string(),
+            context_data: data.to_string(),
+            metadata: HashMap::new(),
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            expires_at: None,
+            priority: 5,
+            version,
+            tags: Vec::new(),
+            active: true,
+            approval_state: Default::default(),
+            visibility: Default::default(),
+            schema_version: CURRENT_SCHEMA_VERSION,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scoring_cache_versioning() {
+        let cache = ScoringCache::default();
+        let embedder = HashingEmbedder::new(16);
+
+        let v1 = make_context("Pneumonia treatment", 1);
+        let first = cache.get_features(&v1, &WhitespaceTokenizer).await;
+        let second = cache.get_features(&v1, &WhitespaceTokenizer).await;
+        assert!(Arc::ptr_eq(&first, &second));
+        assert!(first.words.contains("pneumonia"));
+
+        cache.get_embedding(&v1, &embedder).await.unwrap();
+        cache.get_embedding(&v1, &embedder).await.unwrap();
+
+        // 新版本重新计算
+        let v2 = make_context("Flu symptoms", 2);
+        let updated = cache.get_features(&v2, &WhitespaceTokenizer).await;
+        assert!(updated.words.contains("flu"));
+
+        let stats = cache.get_stats().await;
+        assert_eq!(stats.feature_hits, 1);
+        assert_eq!(stats.feature_misses, 2);
+        assert_eq!(stats.embedding_hits, 1);
+        assert_eq!(stats.embedding_misses, 1);
+        assert!(stats.weighted_size_bytes > 0);
+    }
+
+    #[tokio::test]
+    async fn test_query_embedding_cache_normalization() {
+        let cache = QueryEmbeddingCache::new(2);
+        let embedder = HashingEmbedder::new(16);
+
+        assert_eq!(normalize_query("  Pneumonia\t  TREATMENT \n"), "pneumonia treatment");
+
+        let (first, hit) = cache.get_or_embed("Pneumonia treatment", &embedder).await.unwrap();
+        assert!(!hit);
+        let (second, hit) = cache.get_or_embed("  pneumonia   TREATMENT ", &embedder).await.unwrap();
+        assert!(hit);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        cache.get_or_embed("flu symptoms", &embedder).await.unwrap();
+        let stats = cache.get_stats().await;
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 2);
+        assert!((stats.hit_rate - 1.0 / 3.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 校准配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationConfig {
+    pub max_samples_per_domain: usize,  // 每个领域保留的最大样本数
+    pub min_labeled_samples: usize,     // 基于反馈校准所需的最少带反馈样本数
+    pub min_samples: usize,             // 基于分布校准所需的最少样本数
+    pub positive_feedback_score: f64,   // 反馈分数不低于该值视为相关
+    pub fallback_keep_ratio: f64,       // 无反馈时希望保留的候选比例
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            max_samples_per_domain: 1000,
+            min_labeled_samples: 20,
+            min_samples: 50,
+            positive_feedback_score: 0.5,
+            fallback_keep_ratio: 0.5,
+        }
+    }
+}
+
+/// 一次候选上下文的相关性评分样本
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationSample {
+    pub context_id: Uuid,
+    pub relevance_score: f64,       // 相关性得分
+    pub feedback: Option<f64>,      // 用户反馈分数（0.0-1.0）
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 校准方法
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CalibrationMethod {
+    Feedback,       // 基于反馈，最大化F1
+    Distribution,   // 基于得分分布的分位数
+    Insufficient,   // 样本不足，保持当前阈值
+}
+
+/// 单个领域的校准结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DomainCalibration {
+    pub domain: String,
+    pub sample_count: usize,
+    pub labeled_count: usize,
+    pub current_threshold: f64,
+    pub recommended_threshold: f64,
+    pub method: CalibrationMethod,
+    pub expected_precision: Option<f64>,
+    pub expected_recall: Option<f64>,
+}
+
+/// 校准报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalibrationReport {
+    pub generated_at: DateTime<Utc>,
+    pub applied: bool,                      // 推荐阈值是否已应用
+    pub domains: Vec<DomainCalibration>,
+}
+
+/// 相关性阈值校准器 - 采样历史选择和反馈，为每个领域推荐相关性阈值
+pub struct RelevanceCalibrator {
+    config: Arc<RwLock<CalibrationConfig>>,
+    samples: Arc<RwLock<HashMap<String, VecDeque<CalibrationSample>>>>,
+}
+
+impl Default for RelevanceCalibrator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RelevanceCalibrator {
+    /// 创建新的校准器
+    pub fn new() -> Self {
+        Self {
+            config: Arc::new(RwLock::new(CalibrationConfig::default())),
+            samples: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 记录候选上下文的相关性得分
+    pub async fn record_score(&self, domain: &str, context_id: Uuid, relevance_score: f64) {
+        let max_samples = self.config.read().await.max_samples_per_domain;
+        let mut samples = self.samples.write().await;
+        let domain_samples = samples.entry(domain.to_string()).or_default();
+        domain_samples.push_back(CalibrationSample {
+            context_id,
+            relevance_score,
+            feedback: None,
+            recorded_at: Utc::now(),
+        });
+        while domain_samples.len() > max_samples {
+            domain_samples.pop_front();
+        }
+    }
+
+    /// 记录对上下文的反馈，关联到该上下文最近一次未带反馈的样本
+    pub async fn record_feedback(&self, context_id: Uuid, score: f64) {
+        let mut samples = self.samples.write().await;
+        let latest = samples
+            .values_mut()
+            .flat_map(|domain_samples| domain_samples.iter_mut())
+            .filter(|s| s.context_id == context_id && s.feedback.is_none())
+            .max_by_key(|s| s.recorded_at);
+        if let Some(sample) = latest {
+            sample.feedback = Some(score.clamp(0.0, 1.0));
+        }
+    }
+
+    /// 为各领域生成校准结果
+    pub async fn calibrate(
+        &self,
+        current_thresholds: &HashMap<String, f64>,
+        default_threshold: f64,
+    ) -> Vec<DomainCalibration> {
+        let config = self.config.read().await.clone();
+        let samples = self.samples.read().await;
+
+        let mut domains: Vec<&String> = samples.keys().collect();
+        domains.sort();
+
+        domains
+            .into_iter()
+            .map(|domain| {
+                let current = *current_thresholds.get(domain).unwrap_or(&default_threshold);
+                let domain_samples: Vec<&CalibrationSample> = samples[domain].iter().collect();
+                Self::calibrate_domain(domain, &domain_samples, current, &config)
+            })
+            .collect()
+    }
+
+    /// 校准单个领域
+    fn calibrate_domain(
+        domain: &str,
+        samples: &[&CalibrationSample],
+        current_threshold: f64,
+        config: &CalibrationConfig,
+    ) -> DomainCalibration {
+        let labeled: Vec<(f64, bool)> = samples
+            .iter()
+            .filter_map(|s| s.feedback.map(|f| (s.relevance_score, f >= config.positive_feedback_score)))
+            .collect();
+
+        let mut result = DomainCalibration {
+            domain: domain.to_string(),
+            sample_count: samples.len(),
+            labeled_count: labeled.len(),
+            current_threshold,
+            recommended_threshold: current_threshold,
+            method: CalibrationMethod::Insufficient,
+            expected_precision: None,
+            expected_recall: None,
+        };
+
+        let positives = labeled.iter().filter(|(_, relevant)| *relevant).count();
+        if labeled.len() >= config.min_labeled_samples && positives > 0 {
+            // 在所有候选阈值中选择F1最高的一个（相同F1时取较高阈值）
+            let mut candidates: Vec<f64> = labeled.iter().map(|(score, _)| *score).collect();
+            candidates.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            candidates.dedup();
+
+            let mut best: Option<(f64, f64, f64, f64)> = None; // (阈值, f1, 精确率, 召回率)
+            for threshold in candidates {
+                let true_positive = labeled.iter().filter(|(s, r)| *s >= threshold && *r).count() as f64;
+                let predicted = labeled.iter().filter(|(s, _)| *s >= threshold).count() as f64;
+                if predicted == 0.0 {
+                    continue;
+                }
+                let precision = true_positive / predicted;
+                let recall = true_positive / positives as f64;
+                let f1 = if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 };
+                if best.map(|(_, best_f1, _, _)| f1 >= best_f1).unwrap_or(true) {
+                    best = Some((threshold, f1, precision, recall));
+                }
+            }
+
+            if let Some((threshold, _, precision, recall)) = best {
+                result.recommended_threshold = threshold;
+                result.method = CalibrationMethod::Feedback;
+                result.expected_precision = Some(precision);
+                result.expected_recall = Some(recall);
+            }
+        } else if samples.len() >= config.min_samples {
+            // 没有足够反馈时，按得分分布保留指定比例的候选
+            let mut scores: Vec<f64> = samples.iter().map(|s| s.relevance_score).collect();
+            scores.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+            let keep = ((scores.len() as f64 * config.fallback_keep_ratio).ceil() as usize).clamp(1, scores.len());
+            result.recommended_threshold = scores[keep - 1];
+            result.method = CalibrationMethod::Distribution;
+        }
+
+        result
+    }
+
+    /// 获取领域样本数
+    pub async fn sample_count(&self, domain: &str) -> usize {
+        self.samples.read().await.get(domain).map(|s| s.len()).unwrap_or(0)
+    }
+
+    /// 清除所有样本
+    pub async fn clear_samples(&self) {
+        self.samples.write().await.clear();
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: CalibrationConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> CalibrationConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_feedback_calibration() {
+        let calibrator = RelevanceCalibrator::new();
+        let mut config = calibrator.get_config().await;
+        config.min_labeled_samples = 4;
+        calibrator.update_config(config).await;
+
+        // 高分样本被判定为相关，低分样本不相关
+        for (score, feedback) in [(0.9, 1.0), (0.7, 0.8), (0.6, 0.9), (0.4, 0.1), (0.2, 0.0), (0.1, 0.2)] {
+            let id = Uuid::new_v4();
+            calibrator.record_score("medical", id, score).await;
+            calibrator.record_feedback(id, feedback).await;
+        }
+        // 没有反馈的法律领域样本
+        for i in 0..3 {
+            calibrator.record_score("legal", Uuid::new_v4(), i as f64 / 10.0).await;
+        }
+
+        let report = calibrator.calibrate(&HashMap::new(), 0.3).await;
+        assert_eq!(report.len(), 2);
+
+        let legal = &report[0];
+        assert_eq!(legal.method, CalibrationMethod::Insufficient);
+        assert_eq!(legal.recommended_threshold, 0.3);
+
+        let medical = &report[1];
+        assert_eq!(medical.method, CalibrationMethod::Feedback);
+        assert_eq!(medical.recommended_threshold, 0.6);
+        assert_eq!(medical.expected_precision, Some(1.0));
+        assert_eq!(medical.expected_recall, Some(1.0));
+    }
+
+    #[tokio::test]
+    async fn test_distribution_calibration() {
+        let calibrator = RelevanceCalibrator::new();
+        let mut config = calibrator.get_config().await;
+        config.min_samples = 10;
+        config.fallback_keep_ratio = 0.3;
+        calibrator.update_config(config).await;
+
+        for i in 0..10 {
+            calibrator.record_score("finance", Uuid::new_v4(), i as f64 / 10.0).await;
+        }
+
+        let report = calibrator.calibrate(&HashMap::new(), 0.3).await;
+        assert_eq!(report[0].method, CalibrationMethod::Distribution);
+        assert!((report[0].recommended_threshold - 0.7).abs() < 1e-9);
+    }
+}
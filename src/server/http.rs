@@ -0,0 +1,1219 @@
+//! HTTP接口 - 提供健康检查、监控仪表盘和请求追踪，并在`/openapi.json`发布由处理函数生成的OpenAPI规范；
+//! 管理接口（`/api/admin/*`和用户交接、删除、按用户的配额用量等）需要`Authorization: Bearer <PENLAI_ADMIN_TOKEN>`
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use tokio::sync::RwLock;
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use uuid::Uuid;
+use crate::app::{CheckSeverity, CheckStatus, Penlai, ReadinessReport, SelfTestCheck};
+use crate::bundle::{bundle_signing_key, bundle_verifying_key, BundleError, BundleInstallOptions, BundleInstallReport, BundleManifest, BundleSpec, SignedBundle};
+use crate::context::access::Viewer;
+use crate::context::codec::JsonCodec;
+use crate::context::llm_context::InvalidTtlError;
+use crate::context::stats::{ContextCardinalityStats, SizeBucket};
+use crate::context::gc::{GcAggregate, GcMetrics, GcReason};
+use crate::context::lock_metrics::{LatencySummary, LockMetricsReport};
+use crate::context::replica::is_read_only_error;
+use crate::domain::domain_classifier::Domain;
+use crate::domain::keyword_store::WeightedKeyword;
+use crate::context::ingestion::{IngestDocument, IngestRequest, IngestionJob, IngestionJobStatus, WebhookRejection, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use crate::maintenance::{is_maintenance_error, resolve_snapshot_path, MaintenanceRejection, MaintenanceRequestPolicy, MaintenanceSettings, MaintenanceStatus, SnapshotReport};
+use crate::offboarding::{AuditRetention, PurgeOptions, PurgeReport, TransferReport};
+use crate::monitoring::api::{self, CacheAccessStat, DomainStat, ErrorRateBucket, LatencyBucket};
+use crate::monitoring::monitoring::{RequestStage, RequestTrace, TraceEntry};
+use crate::monitoring::quota::{QuotaKind, QuotaUsage};
+use crate::monitoring::webhook::{event_schemas, DeadLetter, EventSchema, WebhookEvent};
+use crate::monitoring::reports::{AlertSummary, LatencyPercentiles, ReportRange, RequestVolume, TokenSpend, UsageReport};
+use crate::selection::embedding::embedder_for_model;
+use crate::selection::embedding_migration::{MigrationProgress, MigrationStatus};
+use crate::utils::provider_health::{ProviderHealth, ProviderStatus};
+use crate::utils::search_budget::BudgetScope;
+
+/// HTTP服务共享状态
+#[derive(Clone)]
+pub struct HttpState {
+    pub app: Arc<Penlai>,
+    pub readiness: Arc<RwLock<Option<ReadinessReport>>>,  // 最近一次自检结果
+}
+
+impl HttpState {
+    /// 创建共享状态
+    pub fn new(app: Arc<Penlai>, readiness: Option<ReadinessReport>) -> Self {
+        Self {
+            app,
+            readiness: Arc::new(RwLock::new(readiness)),
+        }
+    }
+}
+
+/// 监控查询的时间窗口参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WindowParams {
+    /// 统计最近多少小时（默认1，最多720）
+    pub hours: Option<i64>,
+    /// 时间桶大小（秒，默认60；窗口内最多10000个桶）
+    pub bucket_seconds: Option<i64>,
+    /// 最多返回的条目数（默认10）
+    pub limit: Option<usize>,
+}
+
+/// 相似上下文查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SimilarParams {
+    /// 返回数量（默认5）
+    pub k: Option<usize>,
+    /// 查看者用户ID（用于可见范围检查，需要管理令牌；未指定查看者时按匿名身份只返回全局上下文）
+    pub user_id: Option<String>,
+    /// 查看者会话ID（需要管理令牌）
+    pub session_id: Option<String>,
+}
+
+/// 配额用量查询参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct QuotaParams {
+    /// 只返回该用户的配额
+    pub user_id: Option<String>,
+    /// 只返回该领域的配额
+    pub domain: Option<String>,
+}
+
+/// Webhook受理结果
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IngestAccepted {
+    pub job_id: Uuid,
+    pub documents: usize,
+}
+
+/// 上下文续期请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RenewRequest {
+    pub extra_ttl_seconds: u64,         // 在当前过期时间基础上延长的秒数
+}
+
+/// 添加领域关键词请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct KeywordRequest {
+    pub keyword: String,                // 关键词或多词短语
+    pub weight: Option<f64>,            // 得分倍数（默认1.0）
+}
+
+/// 上下文转交请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TransferRequest {
+    pub to_user: String,                // 接收上下文的用户
+    #[serde(default)]
+    pub dry_run: bool,                  // 只报告将被转交的上下文
+}
+
+/// 用户数据删除参数
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PurgeParams {
+    /// 只报告影响范围，不做修改（默认false）
+    pub dry_run: Option<bool>,
+    /// 审计记录的处理方式：retain、pseudonymize（默认）或delete
+    pub audit: Option<AuditRetention>,
+}
+
+/// 嵌入模型迁移请求
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MigrationRequest {
+    pub model: String,                  // 新嵌入模型名称（如hashing-512）
+    pub batch_size: Option<usize>,      // 每批重新计算的上下文数（默认100）
+}
+
+impl WindowParams {
+    /// 校验后的（小时数，桶大小）
+    fn window(&self, default_hours: i64) -> Result<(i64, i64), String> {
+        let hours = self.hours.unwrap_or(default_hours);
+        let bucket_seconds = match self.bucket_seconds {
+            Some(bucket_seconds) => bucket_seconds,
+            None => api::min_bucket_seconds(hours).max(60),
+        };
+        api::validate_window(hours, bucket_seconds)?;
+        Ok((hours, bucket_seconds))
+    }
+}
+
+/// OpenAPI规范（由处理函数和响应类型生成）
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Penlai API", description = "Penlai enterprise context management HTTP API"),
+    paths(
+        health, provider_health, refresh_provider_health, dashboard, latency, error_rates, top_domains, cache_stats, context_stats, gc_metrics, lock_metrics, request_trace,
+        ingest_webhook, ingest_job, usage_report, start_embedding_migration, embedding_migration, similar_contexts,
+        context_snapshot, renew_context, list_keywords, add_keyword, remove_keyword, transfer_user_contexts, purge_user,
+        webhook_schemas, webhook_dead_letters, redeliver_dead_letter,
+        maintenance_status, enter_maintenance, exit_maintenance, maintenance_snapshot, quota_usage, create_bundle, install_bundle
+    ),
+    components(schemas(
+        ReadinessReport, SelfTestCheck, CheckSeverity, CheckStatus,
+        ProviderHealth, ProviderStatus,
+        LatencyBucket, ErrorRateBucket, DomainStat, CacheAccessStat, ContextCardinalityStats, SizeBucket,
+        GcMetrics, GcAggregate, GcReason, LockMetricsReport, LatencySummary,
+        TransferRequest, TransferReport, PurgeReport, AuditRetention,
+        RequestTrace, TraceEntry, RequestStage,
+        IngestRequest, IngestDocument, IngestAccepted, IngestionJob, IngestionJobStatus,
+        UsageReport, ReportRange, RequestVolume, LatencyPercentiles, TokenSpend, AlertSummary,
+        MigrationRequest, MigrationProgress, MigrationStatus, RenewRequest,
+        KeywordRequest, WeightedKeyword,
+        EventSchema, WebhookEvent, DeadLetter,
+        MaintenanceRequest, MaintenanceSettings, MaintenanceRequestPolicy, MaintenanceStatus, SnapshotRequest, SnapshotReport,
+        QuotaUsage, QuotaKind, BudgetScope,
+        BundleSpec, SignedBundle, BundleManifest, BundleInstallReport
+    ))
+)]
+pub struct ApiDoc;
+
+/// 就绪检查：返回最近一次自检报告，未就绪或处于维护模式时返回503（维护时附带Retry-After）
+#[utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Service is ready", body = ReadinessReport),
+        (status = 503, description = "Service is not ready, in maintenance mode, or self-test has not run", body = ReadinessReport)
+    )
+)]
+pub async fn health(State(state): State<HttpState>) -> Response {
+    let maintenance = state.app.maintenance_status().await;
+    match state.readiness.read().await.clone() {
+        Some(mut report) if maintenance.active => {
+            let retry_after = maintenance.settings.as_ref().map(|settings| settings.retry_after_seconds).unwrap_or_default();
+            report.maintenance = Some(maintenance);
+            (StatusCode::SERVICE_UNAVAILABLE, [(axum::http::header::RETRY_AFTER, retry_after.to_string())], Json(report)).into_response()
+        }
+        Some(report) if report.ready => (StatusCode::OK, Json(report)).into_response(),
+        Some(report) => (StatusCode::SERVICE_UNAVAILABLE, Json(report)).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+/// 搜索服务健康状态（最近一次检查结果）
+#[utoipa::path(
+    get,
+    path = "/health/providers",
+    responses((status = 200, description = "Latest health status of each search provider", body = [ProviderHealth]))
+)]
+pub async fn provider_health(State(state): State<HttpState>) -> Json<Vec<ProviderHealth>> {
+    Json(state.app.provider_health.get_health().await)
+}
+
+/// 立即重新探测搜索服务（探测计入搜索预算和外呼配额，需要管理令牌）
+#[utoipa::path(
+    post,
+    path = "/api/admin/providers/refresh",
+    responses(
+        (status = 200, description = "Fresh health status of each search provider", body = [ProviderHealth]),
+        (status = 401, description = "Missing or invalid admin token")
+    )
+)]
+pub async fn refresh_provider_health(State(state): State<HttpState>) -> Json<Vec<ProviderHealth>> {
+    state.app.provider_health.check_all().await;
+    state.app.provider_health.report_to(&state.app.monitoring).await;
+    Json(state.app.provider_health.get_health().await)
+}
+
+/// 监控仪表盘汇总
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/dashboard",
+    params(WindowParams),
+    responses(
+        (status = 200, description = "Aggregated dashboard views", body = Object),
+        (status = 400, description = "hours or bucket_seconds is out of range")
+    )
+)]
+pub async fn dashboard(State(state): State<HttpState>, Query(params): Query<WindowParams>) -> Response {
+    match params.window(1) {
+        Ok((hours, bucket_seconds)) => Json(api::dashboard(&state.app.monitoring, hours, bucket_seconds).await).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// 请求延迟时间序列
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/latency",
+    params(WindowParams),
+    responses(
+        (status = 200, description = "Time-bucketed latency series", body = [LatencyBucket]),
+        (status = 400, description = "hours or bucket_seconds is out of range")
+    )
+)]
+pub async fn latency(State(state): State<HttpState>, Query(params): Query<WindowParams>) -> Response {
+    match params.window(1) {
+        Ok((hours, bucket_seconds)) => Json(api::latency_series(&state.app.monitoring, hours, bucket_seconds).await).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// 错误率时间序列
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/error_rates",
+    params(WindowParams),
+    responses(
+        (status = 200, description = "Time-bucketed error rates", body = [ErrorRateBucket]),
+        (status = 400, description = "hours or bucket_seconds is out of range")
+    )
+)]
+pub async fn error_rates(State(state): State<HttpState>, Query(params): Query<WindowParams>) -> Response {
+    match params.window(1) {
+        Ok((hours, bucket_seconds)) => Json(api::error_rates(&state.app.monitoring, hours, bucket_seconds).await).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// 按加载次数排序的领域
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/top_domains",
+    params(WindowParams),
+    responses(
+        (status = 200, description = "Most loaded domains", body = [DomainStat]),
+        (status = 400, description = "hours or bucket_seconds is out of range")
+    )
+)]
+pub async fn top_domains(State(state): State<HttpState>, Query(params): Query<WindowParams>) -> Response {
+    match params.window(1) {
+        Ok((hours, _)) => Json(api::top_domains(&state.app.monitoring, hours, params.limit.unwrap_or(10)).await).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// 按键类型统计的缓存命中率
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/cache_stats",
+    params(WindowParams),
+    responses(
+        (status = 200, description = "Cache hit rates by key type", body = [CacheAccessStat]),
+        (status = 400, description = "hours or bucket_seconds is out of range")
+    )
+)]
+pub async fn cache_stats(State(state): State<HttpState>, Query(params): Query<WindowParams>) -> Response {
+    match params.window(1) {
+        Ok((hours, _)) => Json(api::cache_stats(&state.app.monitoring, hours).await).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// 上下文基数统计（按领域、用户、标签、状态和大小）
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/contexts",
+    responses((status = 200, description = "Incrementally maintained context cardinality stats", body = ContextCardinalityStats))
+)]
+pub async fn context_stats(State(state): State<HttpState>) -> Json<ContextCardinalityStats> {
+    Json(state.app.context_manager.get_cardinality_stats().await)
+}
+
+/// 上下文回收指标（按回收原因和领域汇总）
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/gc",
+    responses((status = 200, description = "Aggregated expiry and deletion metrics", body = GcMetrics))
+)]
+pub async fn gc_metrics(State(state): State<HttpState>) -> Json<GcMetrics> {
+    Json(state.app.context_manager.get_gc_tracker().get_metrics().await)
+}
+
+/// 速率限制、令牌预算和搜索预算的当前用量（按用量百分比降序；结果按用户ID列出，需要管理令牌）
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/quotas",
+    params(QuotaParams),
+    responses(
+        (status = 200, description = "Current usage of each quota", body = [QuotaUsage]),
+        (status = 401, description = "Missing or invalid admin token")
+    )
+)]
+pub async fn quota_usage(State(state): State<HttpState>, Query(params): Query<QuotaParams>) -> Json<Vec<QuotaUsage>> {
+    Json(state.app.quota_usage(params.user_id.as_deref(), params.domain.as_deref()).await)
+}
+
+/// 上下文管理器的锁等待时间和操作耗时
+#[utoipa::path(
+    get,
+    path = "/api/monitoring/locks",
+    responses((status = 200, description = "Lock wait and per-operation latency summaries", body = LockMetricsReport))
+)]
+pub async fn lock_metrics(State(state): State<HttpState>) -> Json<LockMetricsReport> {
+    Json(state.app.context_manager.get_lock_metrics())
+}
+
+/// 单个请求的阶段时间线
+#[utoipa::path(
+    get,
+    path = "/api/requests/{request_id}/trace",
+    params(("request_id" = Uuid, Path, description = "Request ID returned by the processor")),
+    responses(
+        (status = 200, description = "Stage timeline", body = RequestTrace),
+        (status = 404, description = "No events recorded for the request")
+    )
+)]
+pub async fn request_trace(State(state): State<HttpState>, Path(request_id): Path<Uuid>) -> Response {
+    match state.app.monitoring.get_request_trace(request_id).await {
+        Some(trace) => Json(trace).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// 接收外部系统推送的文档，校验签名后异步入库并返回任务ID
+#[utoipa::path(
+    post,
+    path = "/api/ingest/webhook",
+    request_body = IngestRequest,
+    params(
+        ("x-penlai-timestamp" = i64, Header, description = "Unix seconds when the request was signed; must be within 5 minutes of the server clock"),
+        ("x-penlai-signature" = String, Header, description = "sha256=<hex HMAC-SHA256 of `<timestamp>.<body>` with the shared secret>")
+    ),
+    responses(
+        (status = 202, description = "Documents accepted for ingestion", body = IngestAccepted),
+        (status = 400, description = "Malformed payload"),
+        (status = 401, description = "Missing or invalid signature, or a timestamp outside the replay window"),
+        (status = 503, description = "Webhook secret not configured")
+    )
+)]
+pub async fn ingest_webhook(State(state): State<HttpState>, headers: HeaderMap, body: Bytes) -> Response {
+    let signature = headers.get(SIGNATURE_HEADER).and_then(|v| v.to_str().ok());
+    let timestamp = headers.get(TIMESTAMP_HEADER).and_then(|v| v.to_str().ok());
+    match state.app.ingestion.authenticate(&body, timestamp, signature).await {
+        Ok(request) => {
+            let documents = request.documents.len();
+            let job_id = state.app.ingestion.submit(request).await;
+            (StatusCode::ACCEPTED, Json(IngestAccepted { job_id, documents })).into_response()
+        }
+        Err(rejection) => {
+            let status = match rejection {
+                WebhookRejection::NotConfigured => StatusCode::SERVICE_UNAVAILABLE,
+                WebhookRejection::MissingSignature
+                | WebhookRejection::MissingTimestamp
+                | WebhookRejection::StaleTimestamp
+                | WebhookRejection::InvalidSignature => StatusCode::UNAUTHORIZED,
+                WebhookRejection::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            };
+            (status, rejection.to_string()).into_response()
+        }
+    }
+}
+
+/// 查询入库任务状态
+#[utoipa::path(
+    get,
+    path = "/api/ingest/jobs/{job_id}",
+    params(("job_id" = Uuid, Path, description = "Job ID returned by the webhook")),
+    responses(
+        (status = 200, description = "Job status", body = IngestionJob),
+        (status = 404, description = "Unknown or expired job")
+    )
+)]
+pub async fn ingest_job(State(state): State<HttpState>, Path(job_id): Path<Uuid>) -> Response {
+    match state.app.ingestion.get_job(job_id).await {
+        Some(job) => Json(job).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// 即时生成使用报告（不推送到通知渠道）
+#[utoipa::path(
+    get,
+    path = "/api/reports/usage",
+    params(WindowParams),
+    responses(
+        (status = 200, description = "Usage and quality digest for the window", body = UsageReport),
+        (status = 400, description = "hours or bucket_seconds is out of range")
+    )
+)]
+pub async fn usage_report(State(state): State<HttpState>, Query(params): Query<WindowParams>) -> Response {
+    match params.window(24) {
+        Ok((hours, _)) => Json(state.app.reports.generate_report(ReportRange::last_hours(hours)).await).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e).into_response(),
+    }
+}
+
+/// 在后台发起嵌入模型迁移
+#[utoipa::path(
+    post,
+    path = "/api/embeddings/migration",
+    request_body = MigrationRequest,
+    responses(
+        (status = 202, description = "Migration started", body = MigrationProgress),
+        (status = 400, description = "Unknown embedding model"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 409, description = "A migration is already running or the model is already active")
+    )
+)]
+pub async fn start_embedding_migration(State(state): State<HttpState>, Json(request): Json<MigrationRequest>) -> Response {
+    let Some(target) = embedder_for_model(&request.model) else {
+        return (StatusCode::BAD_REQUEST, format!("Unknown embedding model '{}'", request.model)).into_response();
+    };
+    let batch_size = request.batch_size.unwrap_or(100);
+    match state.app.context_selector.clone().start_embedding_migration(target, batch_size).await {
+        Ok(progress) => (StatusCode::ACCEPTED, Json(progress)).into_response(),
+        Err(e) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+    }
+}
+
+/// 查询进行中或最近一次嵌入模型迁移的进度
+#[utoipa::path(
+    get,
+    path = "/api/embeddings/migration",
+    responses(
+        (status = 200, description = "Migration progress", body = MigrationProgress),
+        (status = 404, description = "No migration has been started")
+    )
+)]
+pub async fn embedding_migration(State(state): State<HttpState>) -> Response {
+    match state.app.context_selector.get_migration_progress().await {
+        Some(progress) => Json(progress).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// 与指定上下文最相似的上下文（相关知识推荐）
+#[utoipa::path(
+    get,
+    path = "/api/contexts/{context_id}/similar",
+    params(("context_id" = Uuid, Path, description = "Source context ID"), SimilarParams),
+    responses(
+        (status = 200, description = "Nearest contexts with fused, lexical and vector scores", body = Object),
+        (status = 401, description = "user_id or session_id given without a valid admin token"),
+        (status = 404, description = "Unknown context or not visible to the viewer")
+    )
+)]
+pub async fn similar_contexts(
+    State(state): State<HttpState>,
+    Path(context_id): Path<Uuid>,
+    Query(params): Query<SimilarParams>,
+    headers: HeaderMap,
+) -> Response {
+    let viewer = if params.user_id.is_none() && params.session_id.is_none() {
+        Viewer::anonymous()
+    } else if state.app.admin_token.as_deref().is_some_and(|token| bearer_matches(&headers, token)) {
+        Viewer::new(params.user_id.as_deref(), params.session_id.as_deref())
+    } else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+    let k = params.k.unwrap_or(5).min(100);
+    match state.app.context_selector.find_similar_contexts_for(context_id, k, &viewer).await {
+        Ok(results) => Json(results).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// 续期上下文（收到过期提醒后由负责人调用）
+#[utoipa::path(
+    post,
+    path = "/api/contexts/{context_id}/renew",
+    params(("context_id" = Uuid, Path, description = "Context ID")),
+    request_body = RenewRequest,
+    responses(
+        (status = 200, description = "Renewed context with the extended expiry", body = Object),
+        (status = 400, description = "extra_ttl_seconds is out of range"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "Unknown context"),
+        (status = 409, description = "Instance is a read-only replica"),
+        (status = 503, description = "Maintenance mode is active; retry after the Retry-After interval")
+    )
+)]
+pub async fn renew_context(
+    State(state): State<HttpState>,
+    Path(context_id): Path<Uuid>,
+    Json(request): Json<RenewRequest>,
+) -> Response {
+    match state.app.context_manager.renew_context(context_id, request.extra_ttl_seconds).await {
+        Ok(context) => Json(context).into_response(),
+        Err(e) if e.downcast_ref::<InvalidTtlError>().is_some() => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) if is_read_only_error(e.as_ref()) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+        Err(e) if is_maintenance_error(e.as_ref()) => maintenance_unavailable(e.as_ref()),
+        Err(e) => (StatusCode::NOT_FOUND, e.to_string()).into_response(),
+    }
+}
+
+/// 各领域的关键词及权重
+#[utoipa::path(
+    get,
+    path = "/api/domains/keywords",
+    responses(
+        (status = 200, description = "Weighted keywords by domain", body = Object),
+        (status = 503, description = "Keyword store could not be loaded")
+    )
+)]
+pub async fn list_keywords(State(state): State<HttpState>) -> Response {
+    match state.app.keyword_store {
+        Some(ref store) => Json(store.list_keywords().await).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+/// 添加领域关键词或更新其权重，写回关键词文件
+#[utoipa::path(
+    post,
+    path = "/api/domains/{domain}/keywords",
+    params(("domain" = String, Path, description = "Domain name, e.g. medical")),
+    request_body = KeywordRequest,
+    responses(
+        (status = 200, description = "Stored keyword", body = WeightedKeyword),
+        (status = 400, description = "Unknown domain, empty keyword or non-positive weight"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 503, description = "Keyword store could not be loaded")
+    )
+)]
+pub async fn add_keyword(
+    State(state): State<HttpState>,
+    Path(domain): Path<String>,
+    Json(request): Json<KeywordRequest>,
+) -> Response {
+    let Some(ref store) = state.app.keyword_store else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let Some(domain) = Domain::parse(&domain) else {
+        return (StatusCode::BAD_REQUEST, format!("Unknown domain '{}'", domain)).into_response();
+    };
+    match store.add_keyword(domain, &request.keyword, request.weight.unwrap_or(1.0)).await {
+        Ok(keyword) => Json(keyword).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// 删除领域关键词，写回关键词文件
+#[utoipa::path(
+    delete,
+    path = "/api/domains/{domain}/keywords/{keyword}",
+    params(
+        ("domain" = String, Path, description = "Domain name, e.g. medical"),
+        ("keyword" = String, Path, description = "URL-encoded keyword or phrase")
+    ),
+    responses(
+        (status = 204, description = "Keyword removed"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "Unknown domain or keyword"),
+        (status = 503, description = "Keyword store could not be loaded")
+    )
+)]
+pub async fn remove_keyword(State(state): State<HttpState>, Path((domain, keyword)): Path<(String, String)>) -> Response {
+    let Some(ref store) = state.app.keyword_store else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    let Some(domain) = Domain::parse(&domain) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    match store.remove_keyword(domain, &keyword).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 将用户的全部上下文转交给其他用户（用户离职交接）
+#[utoipa::path(
+    post,
+    path = "/api/users/{user_id}/transfer",
+    params(("user_id" = String, Path, description = "User whose contexts are handed over")),
+    request_body = TransferRequest,
+    responses(
+        (status = 200, description = "Transferred (or, in a dry run, affected) contexts", body = TransferReport),
+        (status = 400, description = "Target user is empty or the same user"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 409, description = "Instance is a read-only replica"),
+        (status = 503, description = "Maintenance mode is active; retry after the Retry-After interval")
+    )
+)]
+pub async fn transfer_user_contexts(
+    State(state): State<HttpState>,
+    Path(user_id): Path<String>,
+    Json(request): Json<TransferRequest>,
+) -> Response {
+    match state.app.transfer_contexts(&user_id, &request.to_user, request.dry_run).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) if is_read_only_error(e.as_ref()) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+        Err(e) if is_maintenance_error(e.as_ref()) => maintenance_unavailable(e.as_ref()),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+/// 删除用户的全部数据（上下文、索引、缓存、限流状态、反馈记录、监控事件，审计记录按参数处理）
+#[utoipa::path(
+    delete,
+    path = "/api/users/{user_id}",
+    params(("user_id" = String, Path, description = "User to erase"), PurgeParams),
+    responses(
+        (status = 200, description = "Erased (or, in a dry run, affected) data", body = PurgeReport),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 409, description = "Instance is a read-only replica"),
+        (status = 500, description = "Feedback log could not be rewritten"),
+        (status = 503, description = "Maintenance mode is active; retry after the Retry-After interval")
+    )
+)]
+pub async fn purge_user(State(state): State<HttpState>, Path(user_id): Path<String>, Query(params): Query<PurgeParams>) -> Response {
+    let options = PurgeOptions {
+        dry_run: params.dry_run.unwrap_or(false),
+        audit: params.audit.unwrap_or_default(),
+    };
+    match state.app.purge_user(&user_id, options).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) if is_read_only_error(e.as_ref()) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+        Err(e) if is_maintenance_error(e.as_ref()) => maintenance_unavailable(e.as_ref()),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 出站Webhook事件的JSON模式（各事件类型的全部版本）
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/schemas",
+    responses((status = 200, description = "Published event schemas", body = [EventSchema]))
+)]
+pub async fn webhook_schemas() -> Json<Vec<EventSchema>> {
+    Json(event_schemas())
+}
+
+/// 重试耗尽或被消费方拒绝的Webhook事件
+#[utoipa::path(
+    get,
+    path = "/api/webhooks/dead_letters",
+    responses(
+        (status = 200, description = "Undelivered events, oldest first", body = [DeadLetter]),
+        (status = 401, description = "Missing or invalid admin token")
+    )
+)]
+pub async fn webhook_dead_letters(State(state): State<HttpState>) -> Json<Vec<DeadLetter>> {
+    Json(state.app.webhooks.get_dead_letters().list().await)
+}
+
+/// 将一条死信移出并排队重新投递
+#[utoipa::path(
+    post,
+    path = "/api/webhooks/dead_letters/{event_id}/retry",
+    params(("event_id" = Uuid, Path, description = "Event ID of the dead letter")),
+    responses(
+        (status = 202, description = "Redelivery queued; the event returns to dead letters if it fails again"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 404, description = "No dead letter with this event ID")
+    )
+)]
+pub async fn redeliver_dead_letter(State(state): State<HttpState>, Path(event_id): Path<Uuid>) -> Response {
+    match state.app.webhooks.enqueue_redelivery(event_id).await {
+        Some(_) => StatusCode::ACCEPTED.into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// 开启维护模式的请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MaintenanceRequest {
+    #[serde(flatten)]
+    pub settings: MaintenanceSettings,
+    pub drain_timeout_seconds: Option<u64>,   // 等待进行中请求处理完毕的时间（默认30秒）
+}
+
+/// 维护期间导出快照的请求
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SnapshotRequest {
+    pub name: String,   // 快照目录（PENLAI_SNAPSHOT_DIR）下的文件名，不能是绝对路径或包含`..`
+}
+
+/// 维护模式状态
+#[utoipa::path(
+    get,
+    path = "/api/admin/maintenance",
+    responses(
+        (status = 200, description = "Current maintenance mode state", body = MaintenanceStatus),
+        (status = 401, description = "Missing or invalid admin token")
+    )
+)]
+pub async fn maintenance_status(State(state): State<HttpState>) -> Json<MaintenanceStatus> {
+    Json(state.app.maintenance_status().await)
+}
+
+/// 开启维护模式，等待进行中的请求处理完毕后返回
+#[utoipa::path(
+    post,
+    path = "/api/admin/maintenance",
+    request_body = MaintenanceRequest,
+    responses(
+        (status = 200, description = "Maintenance mode enabled; in_flight_requests is 0 once drained", body = MaintenanceStatus),
+        (status = 401, description = "Missing or invalid admin token")
+    )
+)]
+pub async fn enter_maintenance(State(state): State<HttpState>, Json(request): Json<MaintenanceRequest>) -> Json<MaintenanceStatus> {
+    let drain_timeout = std::time::Duration::from_secs(request.drain_timeout_seconds.unwrap_or(30));
+    Json(state.app.enter_maintenance(request.settings, drain_timeout).await)
+}
+
+/// 关闭维护模式
+#[utoipa::path(
+    delete,
+    path = "/api/admin/maintenance",
+    responses(
+        (status = 200, description = "Maintenance mode disabled", body = MaintenanceStatus),
+        (status = 401, description = "Missing or invalid admin token")
+    )
+)]
+pub async fn exit_maintenance(State(state): State<HttpState>) -> Json<MaintenanceStatus> {
+    Json(state.app.exit_maintenance().await)
+}
+
+/// 在维护期间将全部上下文导出为JSON快照文件
+#[utoipa::path(
+    post,
+    path = "/api/admin/maintenance/snapshot",
+    request_body = SnapshotRequest,
+    responses(
+        (status = 200, description = "Snapshot written", body = SnapshotReport),
+        (status = 400, description = "Snapshot name is absolute or escapes the snapshot directory"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 409, description = "Not in maintenance mode or requests still in flight"),
+        (status = 500, description = "Snapshot could not be written")
+    )
+)]
+pub async fn maintenance_snapshot(State(state): State<HttpState>, Json(request): Json<SnapshotRequest>) -> Response {
+    if let Err(e) = resolve_snapshot_path(&state.app.snapshot_dir, &request.name) {
+        return (StatusCode::BAD_REQUEST, e).into_response();
+    }
+    let status = state.app.maintenance_status().await;
+    if !status.active || status.in_flight_requests > 0 {
+        return (StatusCode::CONFLICT, "Snapshots require maintenance mode with no requests in flight").into_response();
+    }
+    match state.app.maintenance_snapshot(&request.name).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 将选定领域的上下文、关键词、安全策略和向量打包为签名的上下文包
+#[utoipa::path(
+    post,
+    path = "/api/admin/bundles",
+    request_body = BundleSpec,
+    responses(
+        (status = 200, description = "Signed bundle file contents", body = SignedBundle),
+        (status = 400, description = "Unknown context id or embeddings could not be computed"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 503, description = "Bundle signing key not configured")
+    )
+)]
+pub async fn create_bundle(State(state): State<HttpState>, Json(spec): Json<BundleSpec>) -> Response {
+    let key = match bundle_signing_key() {
+        Ok(Some(key)) => key,
+        Ok(None) => return (StatusCode::SERVICE_UNAVAILABLE, "PENLAI_BUNDLE_SIGNING_KEY not configured").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let bundle = match state.app.create_bundle(&spec).await {
+        Ok(bundle) => bundle,
+        Err(e) => return (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    };
+    match bundle.sign(&key) {
+        Ok(signed) => Json(signed).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 用公钥校验上下文包签名后安装；本实例已有的不同安全策略默认保留，并在结果中列出
+#[utoipa::path(
+    post,
+    path = "/api/admin/bundles/install",
+    params(BundleInstallOptions),
+    request_body = SignedBundle,
+    responses(
+        (status = 200, description = "Bundle installed", body = BundleInstallReport),
+        (status = 400, description = "Malformed bundle, unsupported format version or invalid bundled keywords"),
+        (status = 401, description = "Missing or invalid admin token"),
+        (status = 403, description = "Bundle signature does not match"),
+        (status = 409, description = "Instance is a read-only replica"),
+        (status = 500, description = "Bundle public key is malformed or the bundle was only partially installed"),
+        (status = 503, description = "Bundle public key not configured or maintenance mode is active")
+    )
+)]
+pub async fn install_bundle(
+    State(state): State<HttpState>,
+    Query(options): Query<BundleInstallOptions>,
+    Json(signed): Json<SignedBundle>,
+) -> Response {
+    let key = match bundle_verifying_key() {
+        Ok(Some(key)) => key,
+        Ok(None) => return (StatusCode::SERVICE_UNAVAILABLE, "PENLAI_BUNDLE_PUBLIC_KEY not configured").into_response(),
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let bundle = match signed.verify(&key) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            let status = match e {
+                BundleError::InvalidSignature => StatusCode::FORBIDDEN,
+                _ => StatusCode::BAD_REQUEST,
+            };
+            return (status, e.to_string()).into_response();
+        }
+    };
+    match state.app.install_bundle(bundle, options).await {
+        Ok(report) => Json(report).into_response(),
+        Err(e) if e.downcast_ref::<BundleError>().is_some() => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+        Err(e) if is_read_only_error(e.as_ref()) => (StatusCode::CONFLICT, e.to_string()).into_response(),
+        Err(e) if is_maintenance_error(e.as_ref()) => maintenance_unavailable(e.as_ref()),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 导出全部上下文的JSON快照，供只读副本同步
+#[utoipa::path(
+    get,
+    path = "/api/contexts/snapshot",
+    params(("Authorization" = String, Header, description = "Bearer <replication token>")),
+    responses(
+        (status = 200, description = "JSON-encoded snapshot of all live contexts", body = Object),
+        (status = 401, description = "Missing or invalid replication token"),
+        (status = 503, description = "Replication token not configured")
+    )
+)]
+pub async fn context_snapshot(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    let Some(ref token) = state.app.replication_token else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+    if !bearer_matches(&headers, token) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    match state.app.context_manager.export_contexts(&JsonCodec).await {
+        Ok(bytes) => ([(axum::http::header::CONTENT_TYPE, "application/json")], bytes).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// 维护期间被拒绝的写操作：503并附带重试提示
+fn maintenance_unavailable(error: &(dyn std::error::Error + Send + Sync + 'static)) -> Response {
+    let retry_after = error.downcast_ref::<MaintenanceRejection>().map_or(30, |rejection| rejection.retry_after_seconds);
+    (StatusCode::SERVICE_UNAVAILABLE, [(axum::http::header::RETRY_AFTER, retry_after.to_string())], error.to_string()).into_response()
+}
+
+/// 请求头中的Bearer令牌是否与期望的令牌一致（常量时间比较）
+fn bearer_matches(headers: &HeaderMap, token: &str) -> bool {
+    headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|presented| bool::from(presented.as_bytes().ct_eq(token.as_bytes())))
+}
+
+/// 管理接口鉴权：未配置PENLAI_ADMIN_TOKEN时拒绝全部管理请求
+async fn require_admin<B>(State(state): State<HttpState>, request: Request<B>, next: Next<B>) -> Response {
+    let Some(ref token) = state.app.admin_token else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "PENLAI_ADMIN_TOKEN not configured").into_response();
+    };
+    if !bearer_matches(request.headers(), token) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    next.run(request).await
+}
+
+/// OpenAPI规范文档
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// 构建路由
+pub fn router(state: HttpState) -> Router {
+    let admin = Router::new()
+        .route("/api/admin/maintenance", get(maintenance_status).post(enter_maintenance).delete(exit_maintenance))
+        .route("/api/admin/maintenance/snapshot", post(maintenance_snapshot))
+        .route("/api/admin/bundles", post(create_bundle))
+        .route("/api/admin/bundles/install", post(install_bundle))
+        .route("/api/admin/providers/refresh", post(refresh_provider_health))
+        .route("/api/monitoring/quotas", get(quota_usage))
+        .route("/api/domains/:domain/keywords", post(add_keyword))
+        .route("/api/domains/:domain/keywords/:keyword", delete(remove_keyword))
+        .route("/api/users/:user_id", delete(purge_user))
+        .route("/api/users/:user_id/transfer", post(transfer_user_contexts))
+        .route("/api/contexts/:context_id/renew", post(renew_context))
+        .route("/api/embeddings/migration", post(start_embedding_migration))
+        .route("/api/webhooks/dead_letters", get(webhook_dead_letters))
+        .route("/api/webhooks/dead_letters/:event_id/retry", post(redeliver_dead_letter))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_admin));
+    Router::new()
+        .route("/health", get(health))
+        .route("/health/providers", get(provider_health))
+        .route("/openapi.json", get(openapi_json))
+        .route("/api/monitoring/dashboard", get(dashboard))
+        .route("/api/monitoring/latency", get(latency))
+        .route("/api/monitoring/error_rates", get(error_rates))
+        .route("/api/monitoring/top_domains", get(top_domains))
+        .route("/api/monitoring/cache_stats", get(cache_stats))
+        .route("/api/monitoring/contexts", get(context_stats))
+        .route("/api/monitoring/gc", get(gc_metrics))
+        .route("/api/monitoring/locks", get(lock_metrics))
+        .route("/api/requests/:request_id/trace", get(request_trace))
+        .route("/api/contexts/snapshot", get(context_snapshot))
+        .route("/api/contexts/:context_id/similar", get(similar_contexts))
+        .route("/api/domains/keywords", get(list_keywords))
+        .route("/api/ingest/webhook", post(ingest_webhook))
+        .route("/api/ingest/jobs/:job_id", get(ingest_job))
+        .route("/api/reports/usage", get(usage_report))
+        .route("/api/webhooks/schemas", get(webhook_schemas))
+        .route("/api/embeddings/migration", get(embedding_migration))
+        .merge(admin)
+        .with_state(state)
+}
+
+/// 启动HTTP服务
+pub async fn serve(state: HttpState, addr: SocketAddr) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    axum::Server::bind(&addr)
+        .serve(router(state).into_make_service())
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn test_openapi_and_routes() {
+        let mut penlai = Penlai::new(10, 3600);
+        penlai.admin_token = Some("admin-token".to_string());
+        let state = HttpState::new(Arc::new(penlai), None);
+        let mut ingestion_config = state.app.ingestion.get_config().await;
+        ingestion_config.shared_secret = Some("s3cret".to_string());
+        state.app.ingestion.update_config(ingestion_config).await;
+        let app = router(state);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let spec: Value = serde_json::from_slice(&body).unwrap();
+        assert!(spec["paths"]["/api/requests/{request_id}/trace"]["get"].is_object());
+        assert!(spec["components"]["schemas"]["ReadinessReport"].is_object());
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/health/providers").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/api/reports/usage?hours=24").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        // 超出范围的查询窗口返回400，而不是溢出或生成海量时间桶
+        for uri in [
+            "/api/reports/usage?hours=9223372036854775807",
+            "/api/monitoring/dashboard?hours=100000&bucket_seconds=1",
+            "/api/monitoring/latency?hours=720&bucket_seconds=1",
+            "/api/monitoring/error_rates?hours=0",
+        ] {
+            let response = app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", uri);
+        }
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/api/monitoring/latency?hours=720").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(format!("/api/requests/{}/trace", Uuid::new_v4())).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        // 管理接口需要管理令牌
+        for authorization in [None, Some("Bearer wrong-token")] {
+            let mut request = Request::builder().uri("/api/admin/maintenance");
+            if let Some(authorization) = authorization {
+                request = request.header(axum::http::header::AUTHORIZATION, authorization);
+            }
+            let response = app.clone().oneshot(request.body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/admin/maintenance")
+                    .header(axum::http::header::AUTHORIZATION, "Bearer admin-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/admin/maintenance/snapshot")
+                    .header(axum::http::header::AUTHORIZATION, "Bearer admin-token")
+                    .header(axum::http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"name":"../../etc/cron.d/penlai"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/embeddings/migration")
+                    .header(axum::http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"model":"hash-256"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/api/embeddings/migration").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        // 重新探测搜索服务会消耗搜索预算，需要管理令牌
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("POST").uri("/api/admin/providers/refresh").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        // 配额用量按用户ID列出，需要管理令牌
+        for uri in ["/api/monitoring/quotas", "/api/monitoring/quotas?user_id=alice"] {
+            let response = app.clone().oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap()).await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        // 以指定用户身份查询相似上下文需要管理令牌
+        let similar = format!("/api/contexts/{}/similar?user_id=alice", Uuid::new_v4());
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(&similar).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(&similar)
+                    .header(axum::http::header::AUTHORIZATION, "Bearer admin-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("DELETE").uri("/api/users/alice").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // 未配置复制令牌时不发布快照
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri("/api/contexts/snapshot").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/api/contexts/{}/renew", Uuid::new_v4()))
+                    .header(axum::http::header::AUTHORIZATION, "Bearer admin-token")
+                    .header(axum::http::header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(r#"{"extra_ttl_seconds":3600}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("DELETE").uri("/api/domains/astrology/keywords/horoscope").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/api/domains/astrology/keywords/horoscope")
+                    .header(axum::http::header::AUTHORIZATION, "Bearer admin-token")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let payload = r#"{"source":"cms","documents":[{"content":"Release notes","domain":"technical"}]}"#;
+        let response = app
+            .clone()
+            .oneshot(Request::builder().method("POST").uri("/api/ingest/webhook").body(Body::from(payload)).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        let signed_at = chrono::Utc::now().timestamp();
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/ingest/webhook")
+                    .header(TIMESTAMP_HEADER, signed_at.to_string())
+                    .header(SIGNATURE_HEADER, crate::context::ingestion::sign_timestamped("s3cret", signed_at, payload.as_bytes()))
+                    .body(Body::from(payload))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let accepted: IngestAccepted = serde_json::from_slice(&body).unwrap();
+
+        let response = app
+            .oneshot(Request::builder().uri(format!("/api/ingest/jobs/{}", accepted.job_id)).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
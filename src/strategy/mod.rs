@@ -1 +1,3 @@
-pub mod strategy;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod strategy;
+pub mod priority_tuner;
\ No newline at end of file
@@ -0,0 +1,356 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+use serde::{Deserialize, Serialize};
+use crate::context::llm_context::{ContextManager, LLMContext};
+use crate::maintenance::MaintenanceMode;
+
+/// 上下文元数据中用于关闭自动优先级调整的键（值为 "false" 时关闭）
+pub const PRIORITY_TUNING_METADATA_KEY: &str = "auto_priority_tuning";
+
+/// 优先级自动调整配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityTunerConfig {
+    pub min_priority: u8,                 // 自动调整的优先级下限
+    pub max_priority: u8,                 // 自动调整的优先级上限
+    pub max_step: u8,                     // 单轮最大调整幅度
+    pub tuning_interval_seconds: u64,     // 调整周期（秒）
+    pub min_total_selections: u64,        // 一轮中参与比较所需的最小总选择次数
+    pub high_frequency_ratio: f64,        // 选择次数高于平均值该倍数时提升优先级
+    pub low_frequency_ratio: f64,         // 选择次数低于平均值该倍数时降低优先级
+    pub min_feedback_samples: u32,        // 反馈信号生效所需的最小样本数
+    pub positive_feedback_threshold: f64, // 平均反馈分数高于该值时提升优先级
+    pub negative_feedback_threshold: f64, // 平均反馈分数低于该值时降低优先级
+}
+
+impl Default for PriorityTunerConfig {
+    fn default() -> Self {
+        Self {
+            min_priority: 1,
+            max_priority: 9,
+            max_step: 1,
+            tuning_interval_seconds: 3600, // 1小时
+            min_total_selections: 20,
+            high_frequency_ratio: 2.0,
+            low_frequency_ratio: 0.5,
+            min_feedback_samples: 3,
+            positive_feedback_threshold: 0.7,
+            negative_feedback_threshold: 0.3,
+        }
+    }
+}
+
+/// 单个上下文在当前调整窗口内的使用统计
+#[derive(Debug, Clone, Default)]
+pub struct ContextUsageStats {
+    pub selection_count: u64,   // 被选中次数
+    pub feedback_sum: f64,      // 反馈分数总和（0.0-1.0）
+    pub feedback_count: u32,    // 反馈次数
+}
+
+impl ContextUsageStats {
+    /// 平均反馈分数
+    pub fn average_feedback(&self) -> Option<f64> {
+        if self.feedback_count == 0 {
+            None
+        } else {
+            Some(self.feedback_sum / self.feedback_count as f64)
+        }
+    }
+}
+
+/// 一次优先级调整记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriorityAdjustment {
+    pub context_id: Uuid,
+    pub old_priority: u8,
+    pub new_priority: u8,
+    pub selection_count: u64,
+    pub average_feedback: Option<f64>,
+}
+
+/// 优先级自动调整器 - 根据选择频率和反馈分数周期性地调整上下文优先级
+pub struct PriorityTuner {
+    config: Arc<RwLock<PriorityTunerConfig>>,
+    context_manager: Arc<ContextManager>,
+    /// 当前窗口内的使用统计
+    usage_stats: Arc<RwLock<HashMap<Uuid, ContextUsageStats>>>,
+    /// 当前统计窗口的开始时间，窗口开始后才创建的上下文不因未被选中而降级
+    window_started_at: Arc<RwLock<DateTime<Utc>>>,
+    /// 可选的维护模式，维护期间暂停调整
+    maintenance: Option<Arc<MaintenanceMode>>,
+}
+
+impl PriorityTuner {
+    /// 创建新的优先级自动调整器
+    pub fn new(context_manager: Arc<ContextManager>) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(PriorityTunerConfig::default())),
+            context_manager,
+            usage_stats: Arc::new(RwLock::new(HashMap::new())),
+            window_started_at: Arc::new(RwLock::new(Utc::now())),
+            maintenance: None,
+        }
+    }
+
+    /// 关联维护模式，维护期间暂停周期调整
+    pub fn with_maintenance(mut self, maintenance: Arc<MaintenanceMode>) -> Self {
+        self.maintenance = Some(maintenance);
+        self
+    }
+
+    /// 记录一次选择结果中被选中的上下文
+    pub async fn record_selection(&self, contexts: &[LLMContext]) {
+        let mut stats = self.usage_stats.write().await;
+        for context in contexts {
+            stats.entry(context.id).or_default().selection_count += 1;
+        }
+    }
+
+    /// 记录对某个上下文的反馈分数（0.0-1.0，越高越好）
+    pub async fn record_feedback(&self, context_id: Uuid, score: f64) {
+        let mut stats = self.usage_stats.write().await;
+        let entry = stats.entry(context_id).or_default();
+        entry.feedback_sum += score.clamp(0.0, 1.0);
+        entry.feedback_count += 1;
+    }
+
+    /// 开启或关闭指定上下文的自动优先级调整
+    pub async fn set_tuning_enabled(
+        &self,
+        context_id: Uuid,
+        enabled: bool,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let context = self
+            .context_manager
+            .get_context(context_id)
+            .await
+            .ok_or("Context not found")?;
+
+        let mut metadata = context.metadata;
+        if enabled {
+            metadata.remove(PRIORITY_TUNING_METADATA_KEY);
+        } else {
+            metadata.insert(PRIORITY_TUNING_METADATA_KEY.to_string(), "false".to_string());
+        }
+
+        self.context_manager
+            .update_context(context_id, None, Some(metadata), None)
+            .await
+    }
+
+    /// 检查上下文是否允许自动调整优先级
+    pub fn is_tuning_enabled(context: &LLMContext) -> bool {
+        context
+            .metadata
+            .get(PRIORITY_TUNING_METADATA_KEY)
+            .map(|value| value != "false")
+            .unwrap_or(true)
+    }
+
+    /// 执行一轮优先级调整，返回实际生效的调整并重置统计窗口；
+    /// 整个窗口内都存在却从未被选中的上下文按选择次数为0参与频率比较
+    pub async fn run_tuning_cycle(&self) -> Vec<PriorityAdjustment> {
+        let config = self.config.read().await.clone();
+        let mut stats: HashMap<Uuid, ContextUsageStats> = {
+            let mut usage_stats = self.usage_stats.write().await;
+            std::mem::take(&mut *usage_stats)
+        };
+        let window_started_at = std::mem::replace(&mut *self.window_started_at.write().await, Utc::now());
+
+        // 关闭了自动调整，或者优先级被人工设置在调整范围之外的上下文保持不变，也不计入平均值
+        let eligible = |context: &LLMContext| {
+            Self::is_tuning_enabled(context) && (config.min_priority..=config.max_priority).contains(&context.priority)
+        };
+        let mut candidates: HashMap<Uuid, LLMContext> = HashMap::new();
+        for context in self.context_manager.get_all_contexts().await {
+            if !eligible(&context) {
+                continue;
+            }
+            if context.created_at <= window_started_at {
+                stats.entry(context.id).or_default();
+            }
+            candidates.insert(context.id, context);
+        }
+        stats.retain(|context_id, _| candidates.contains_key(context_id));
+
+        if stats.is_empty() {
+            return Vec::new();
+        }
+
+        let total_selections: u64 = stats.values().map(|s| s.selection_count).sum();
+        let average_selections = total_selections as f64 / stats.len() as f64;
+        let use_frequency = total_selections >= config.min_total_selections && average_selections > 0.0;
+
+        let mut adjustments = Vec::new();
+        for (context_id, usage) in stats {
+            let Some(context) = candidates.remove(&context_id) else {
+                continue;
+            };
+
+            let mut delta: i32 = 0;
+
+            // 选择频率信号
+            if use_frequency {
+                let ratio = usage.selection_count as f64 / average_selections;
+                if ratio >= config.high_frequency_ratio {
+                    delta += 1;
+                } else if ratio <= config.low_frequency_ratio {
+                    delta -= 1;
+                }
+            }
+
+            // 反馈分数信号
+            if usage.feedback_count >= config.min_feedback_samples {
+                if let Some(avg) = usage.average_feedback() {
+                    if avg >= config.positive_feedback_threshold {
+                        delta += 1;
+                    } else if avg <= config.negative_feedback_threshold {
+                        delta -= 1;
+                    }
+                }
+            }
+
+            let max_step = config.max_step as i32;
+            let delta = delta.clamp(-max_step, max_step);
+            if delta == 0 {
+                continue;
+            }
+
+            let new_priority = (context.priority as i32 + delta)
+                .clamp(config.min_priority as i32, config.max_priority as i32) as u8;
+            if new_priority == context.priority {
+                continue;
+            }
+
+            if self.context_manager.set_priority(context_id, new_priority).await.is_ok()
+            {
+                adjustments.push(PriorityAdjustment {
+                    context_id,
+                    old_priority: context.priority,
+                    new_priority,
+                    selection_count: usage.selection_count,
+                    average_feedback: usage.average_feedback(),
+                });
+            }
+        }
+
+        adjustments
+    }
+
+    /// 启动后台调整任务，按配置的周期执行调整
+    pub fn start(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let interval = self.config.read().await.tuning_interval_seconds.max(1);
+                tokio::time::sleep(tokio::time::Duration::from_secs(interval)).await;
+                if let Some(ref maintenance) = self.maintenance {
+                    maintenance.wait_until_inactive().await;
+                }
+                let adjustments = self.run_tuning_cycle().await;
+                if !adjustments.is_empty() {
+                    log::info!("Priority tuner adjusted {} contexts", adjustments.len());
+                }
+            }
+        })
+    }
+
+    /// 获取当前窗口内的使用统计
+    pub async fn get_usage_stats(&self, context_id: Uuid) -> Option<ContextUsageStats> {
+        self.usage_stats.read().await.get(&context_id).cloned()
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: PriorityTunerConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> PriorityTunerConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create(manager: &ContextManager, data: &str, priority: u8) -> LLMContext {
+        manager
+            .create_context(
+                "session1".to_string(),
+                "user1".to_string(),
+                "medical".to_string(),
+                data.to_string(),
+                priority,
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_priority_tuning() {
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        // 在统计窗口开始前创建、从未被选中的上下文
+        let unused = create(&manager, "Outdated triage checklist", 5).await;
+        let tuner = PriorityTuner::new(manager.clone());
+        let mut config = tuner.get_config().await;
+        config.min_total_selections = 1;
+        tuner.update_config(config).await;
+
+        let popular = create(&manager, "Pneumonia treatment guidelines", 5).await;
+        let rare = create(&manager, "Rare disease notes", 5).await;
+        let pinned = create(&manager, "Pinned hospital policy", 5).await;
+        tuner.set_tuning_enabled(pinned.id, false).await.unwrap();
+
+        for _ in 0..10 {
+            tuner.record_selection(std::slice::from_ref(&popular)).await;
+        }
+        tuner.record_selection(&[rare.clone(), pinned.clone()]).await;
+        for _ in 0..3 {
+            tuner.record_feedback(rare.id, 0.1).await;
+        }
+
+        let adjustments = tuner.run_tuning_cycle().await;
+        assert_eq!(adjustments.len(), 3);
+
+        // 自动调整不算内容修改：版本号和修改时间不变
+        let tuned = manager.get_context(popular.id).await.unwrap();
+        assert_eq!(tuned.priority, 6);
+        assert_eq!((tuned.version, tuned.updated_at), (popular.version, popular.updated_at));
+        assert_eq!(manager.get_context(unused.id).await.unwrap().priority, 4);
+        // 最大调整幅度为1，即使频率和反馈信号都为负
+        assert_eq!(manager.get_context(rare.id).await.unwrap().priority, 4);
+        assert_eq!(manager.get_context(pinned.id).await.unwrap().priority, 5);
+
+        // 统计窗口已重置；没有选择时不按频率调整
+        assert!(tuner.get_usage_stats(popular.id).await.is_none());
+        assert!(tuner.run_tuning_cycle().await.is_empty());
+
+        // 窗口开始后才创建的上下文不会因未被选中而降级
+        let fresh = create(&manager, "New sepsis protocol", 5).await;
+        tuner.record_selection(std::slice::from_ref(&popular)).await;
+        let adjustments = tuner.run_tuning_cycle().await;
+        assert!(adjustments.iter().all(|adjustment| adjustment.context_id != fresh.id));
+    }
+
+    #[tokio::test]
+    async fn test_priority_bounds() {
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let tuner = PriorityTuner::new(manager.clone());
+
+        let top = create(&manager, "Top priority context", 9).await;
+        let manual = create(&manager, "Manually pinned context", 10).await;
+        for _ in 0..5 {
+            tuner.record_feedback(top.id, 1.0).await;
+            tuner.record_feedback(manual.id, 1.0).await;
+        }
+
+        assert!(tuner.run_tuning_cycle().await.is_empty());
+        assert_eq!(manager.get_context(top.id).await.unwrap().priority, 9);
+        assert_eq!(manager.get_context(manual.id).await.unwrap().priority, 10);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::context::llm_context::LLMContext as Context;
+use crate::selection::ranking::{default_tie_breakers, sort_scored};
 use crate::domain::domain_classifier::Domain;
 
 /// 上下文管理策略枚举
@@ -58,6 +59,12 @@ pub struct StrategyManager {
     context_selection_strategy: ContextSelectionStrategy,
 }
 
+impl Default for StrategyManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl StrategyManager {
     /// 创建新的策略管理器
     pub fn new() -> Self {
@@ -115,7 +122,7 @@ impl StrategyManager {
     /// 基于优先级选择上下文
     fn select_by_priority(&self, contexts: &[Context], _query: &str) -> Vec<Context> {
         let mut contexts_with_priority = contexts.to_vec();
-        contexts_with_priority.sort_by(|a, b| b.priority.cmp(&a.priority));
+        contexts_with_priority.sort_by_key(|b| std::cmp::Reverse(b.priority));
         
         contexts_with_priority
             .into_iter()
@@ -127,7 +134,7 @@ impl StrategyManager {
     fn select_by_lru(&self, contexts: &[Context], _query: &str) -> Vec<Context> {
         let mut contexts_with_time = contexts.to_vec();
         // 按更新时间排序（最近更新的在前）
-        contexts_with_time.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        contexts_with_time.sort_by_key(|b| std::cmp::Reverse(b.updated_at));
         
         contexts_with_time
             .into_iter()
@@ -139,7 +146,7 @@ impl StrategyManager {
     fn select_by_frequency(&self, contexts: &[Context], _query: &str) -> Vec<Context> {
         let mut contexts_with_version = contexts.to_vec();
         // 按版本号排序（更新的版本在前，可视为更常用）
-        contexts_with_version.sort_by(|a, b| b.version.cmp(&a.version));
+        contexts_with_version.sort_by_key(|b| std::cmp::Reverse(b.version));
         
         contexts_with_version
             .into_iter()
@@ -160,10 +167,8 @@ impl StrategyManager {
             let mut score = 0.0;
 
             // 域匹配得分
-            if self.context_selection_strategy.use_domain_matching {
-                if context.domain == query_domain.to_string() {
-                    score += 0.4; // 域匹配权重
-                }
+            if self.context_selection_strategy.use_domain_matching && context.domain == query_domain.to_string() {
+                score += 0.4; // 域匹配权重
             }
 
             // 内容相似度得分
@@ -181,7 +186,7 @@ impl StrategyManager {
         }
 
         // 按得分排序
-        scored_contexts.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+        sort_scored(&mut scored_contexts, &default_tie_breakers());
 
         // 返回得分最高的上下文
         scored_contexts
@@ -245,6 +250,7 @@ impl std::fmt::Display for StrategySummary {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::context::schema_migration::CURRENT_SCHEMA_VERSION;
     use std::collections::HashMap;
 
     #[tokio::test]
@@ -267,6 +273,9 @@ mod tests {
                 version: 2,
                 tags: vec!["treatment".to_string(), "pneumonia".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             },
             Context {
                 id: uuid::Uuid::new_v4(),
@@ -282,6 +291,9 @@ mod tests {
                 version: 1,
                 tags: vec!["contract".to_string(), "law".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             },
             Context {
                 id: uuid::Uuid::new_v4(),
@@ -297,6 +309,9 @@ mod tests {
                 version: 3,
                 tags: vec!["symptoms".to_string(), "flu".to_string()],
                 active: true,
+                approval_state: Default::default(),
+                visibility: Default::default(),
+                schema_version: CURRENT_SCHEMA_VERSION,
             },
         ];
 
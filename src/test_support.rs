@@ -0,0 +1,111 @@
+//! 测试共用的替身实现 - 返回固定回答的文本生成器和记录收到通知的告警渠道
+
+use std::time::Duration;
+use async_trait::async_trait;
+use tokio::sync::watch;
+use crate::monitoring::notify::{AlertSink, Notification};
+use crate::utils::ai_client::TextGenerator;
+use crate::utils::outbound_scheduler::CallPriority;
+
+/// 等待通知送达的最长时间，超时视为测试失败
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 返回固定回答的文本生成器，可设置模型名、生成前的延迟和提示词中必须包含的片段
+#[derive(Default)]
+pub struct FixedGenerator {
+    answer: String,
+    model: Option<String>,
+    delay: Duration,
+    expected_prompt: Option<String>,
+}
+
+impl FixedGenerator {
+    /// 每次生成都返回answer
+    pub fn new(answer: impl Into<String>) -> Self {
+        Self { answer: answer.into(), ..Self::default() }
+    }
+
+    /// 报告的模型名（用于费用估算）
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = Some(model.to_string());
+        self
+    }
+
+    /// 生成前等待delay（用于超时测试）
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// 断言提示词包含fragment
+    pub fn expecting_prompt(mut self, fragment: &str) -> Self {
+        self.expected_prompt = Some(fragment.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl TextGenerator for FixedGenerator {
+    async fn generate(&self, prompt: &str, _model: Option<&str>, _priority: CallPriority) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        if !self.delay.is_zero() {
+            tokio::time::sleep(self.delay).await;
+        }
+        if let Some(ref fragment) = self.expected_prompt {
+            assert!(prompt.contains(fragment.as_str()), "prompt does not contain {:?}: {}", fragment, prompt);
+        }
+        Ok(self.answer.clone())
+    }
+
+    fn model_name(&self) -> Option<String> {
+        self.model.clone()
+    }
+}
+
+/// 记录收到的通知，测试可等待指定数量的通知送达
+pub struct RecordingSink {
+    sent: std::sync::Mutex<Vec<Notification>>,
+    count: watch::Sender<usize>,
+}
+
+impl Default for RecordingSink {
+    fn default() -> Self {
+        Self {
+            sent: std::sync::Mutex::new(Vec::new()),
+            count: watch::channel(0).0,
+        }
+    }
+}
+
+impl RecordingSink {
+    /// 已收到的通知
+    pub fn sent(&self) -> Vec<Notification> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 等到至少收到count条通知，返回已收到的通知
+    pub async fn wait_for(&self, count: usize) -> Vec<Notification> {
+        let mut received = self.count.subscribe();
+        tokio::time::timeout(DELIVERY_TIMEOUT, received.wait_for(|received| *received >= count))
+            .await
+            .unwrap_or_else(|_| panic!("expected {} notification(s), got {}", count, self.sent().len()))
+            .expect("recording sink dropped");
+        self.sent()
+    }
+}
+
+#[async_trait]
+impl AlertSink for RecordingSink {
+    async fn send(&self, notification: &Notification) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let received = {
+            let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+            sent.push(notification.clone());
+            sent.len()
+        };
+        self.count.send_replace(received);
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "recording"
+    }
+}
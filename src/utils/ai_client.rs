@@ -1,6 +1,11 @@
+use async_trait::async_trait;
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::sync::Arc;
+use crate::utils::outbound_scheduler::{CallPriority, OutboundScheduler, OutboundThrottled, AI_PROVIDER};
+use crate::utils::replay::{ReplayError, ReplayLayer};
+use crate::utils::request_context::RequestContext;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -16,7 +21,7 @@ pub struct ChatCompletionRequest {
     pub max_tokens: u32,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ChatCompletionResponse {
     pub id: String,
     pub choices: Vec<Choice>,
@@ -25,26 +30,65 @@ pub struct ChatCompletionResponse {
     pub usage: Usage,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Choice {
     pub index: u32,
     pub message: ChatMessage,
     pub finish_reason: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Usage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+#[derive(Debug)]
+pub enum AIClientError {
+    RequestError(reqwest::Error),
+    Throttled(OutboundThrottled),
+    Replay(ReplayError),
+}
+
+impl std::fmt::Display for AIClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AIClientError::RequestError(err) => write!(f, "AI request failed: {}", err),
+            AIClientError::Throttled(err) => write!(f, "{}", err),
+            AIClientError::Replay(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for AIClientError {}
+
+impl From<reqwest::Error> for AIClientError {
+    fn from(err: reqwest::Error) -> Self {
+        AIClientError::RequestError(err)
+    }
+}
+
+impl From<OutboundThrottled> for AIClientError {
+    fn from(err: OutboundThrottled) -> Self {
+        AIClientError::Throttled(err)
+    }
+}
+
+impl From<ReplayError> for AIClientError {
+    fn from(err: ReplayError) -> Self {
+        AIClientError::Replay(err)
+    }
+}
+
 pub struct AIClient {
     client: reqwest::Client,
     base_url: String,
     model: String,
     temperature: f64,
     max_tokens: u32,
+    scheduler: Option<Arc<OutboundScheduler>>,
+    replay: Option<Arc<ReplayLayer>>,   // 录制/回放层（PENLAI_REPLAY_MODE开启时）
 }
 
 impl AIClient {
@@ -71,17 +115,65 @@ impl AIClient {
             model,
             temperature,
             max_tokens,
+            scheduler: None,
+            replay: ReplayLayer::from_env(),
         })
     }
 
-    pub async fn chat_completion(&self, messages: Vec<ChatMessage>) -> Result<ChatCompletionResponse, reqwest::Error> {
+    /// 关联外呼调度器，每次调用前按优先级等待AI服务配额
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 关联录制/回放层，回放模式下不再访问AI服务
+    pub fn with_replay(mut self, replay: Arc<ReplayLayer>) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// 指定AI服务地址（覆盖AI_BASE_URL）
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+
+    pub async fn chat_completion(&self, messages: Vec<ChatMessage>) -> Result<ChatCompletionResponse, AIClientError> {
+        self.chat_completion_with_priority(messages, CallPriority::Interactive).await
+    }
+
+    /// 以指定优先级调用对话补全（后台任务使用`CallPriority::Background`，不挤占用户请求的配额）
+    pub async fn chat_completion_with_priority(
+        &self,
+        messages: Vec<ChatMessage>,
+        priority: CallPriority,
+    ) -> Result<ChatCompletionResponse, AIClientError> {
+        self.chat_completion_with_model(messages, None, priority).await
+    }
+
+    /// 使用指定模型调用对话补全，model为None时使用配置的模型（如令牌预算降级时换用低成本模型）
+    pub async fn chat_completion_with_model(
+        &self,
+        messages: Vec<ChatMessage>,
+        model: Option<&str>,
+        priority: CallPriority,
+    ) -> Result<ChatCompletionResponse, AIClientError> {
         let request = ChatCompletionRequest {
-            model: self.model.clone(),
+            model: model.unwrap_or(&self.model).to_string(),
             messages,
             temperature: self.temperature,
             max_tokens: self.max_tokens,
         };
 
+        // 回放的响应不访问服务，也不占用外呼配额
+        if let Some(replay) = self.replay.as_ref().filter(|replay| replay.is_replaying()) {
+            return Ok(replay.replay(AI_PROVIDER, &request).await?);
+        }
+
+        if let Some(ref scheduler) = self.scheduler {
+            scheduler.acquire(AI_PROVIDER, priority).await?;
+        }
+
         let url = format!("{}/chat/completions", self.base_url);
 
         let response = self.client
@@ -92,6 +184,71 @@ impl AIClient {
             .await?;
 
         let completion_response: ChatCompletionResponse = response.json().await?;
+        if let Some(ref replay) = self.replay {
+            replay.record(AI_PROVIDER, &request, &completion_response).await?;
+        }
         Ok(completion_response)
     }
+
+    /// 检查AI端点是否可用（请求模型列表接口）
+    pub async fn ping(&self) -> Result<(), reqwest::Error> {
+        let url = format!("{}/models", self.base_url);
+        self.client.get(&url).send().await?.error_for_status()?;
+        Ok(())
+    }
+
+    /// AI服务地址
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// 使用的模型名称
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+/// 文本生成接口 - 检索增强（查询改写、假设文档等）通过该接口调用大模型，便于替换和测试
+#[async_trait]
+pub trait TextGenerator: Send + Sync {
+    /// 根据提示生成文本，model为None时使用生成器配置的模型
+    async fn generate(&self, prompt: &str, model: Option<&str>, priority: CallPriority) -> Result<String, Box<dyn std::error::Error + Send + Sync>>;
+
+    /// 生成使用的模型名称（用于费用估算），未知时为None
+    fn model_name(&self) -> Option<String> {
+        None
+    }
+
+    /// 在请求的剩余时间内生成：已过截止时间时不再调用，否则以剩余时间为超时
+    async fn generate_for_request(
+        &self,
+        prompt: &str,
+        priority: CallPriority,
+        request: &RequestContext,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        match request.remaining() {
+            None => self.generate(prompt, None, priority).await,
+            Some(remaining) if remaining.is_zero() => Err("Request deadline exceeded".into()),
+            Some(remaining) => tokio::time::timeout(remaining, self.generate(prompt, None, priority))
+                .await
+                .map_err(|_| "Request deadline exceeded")?,
+        }
+    }
+}
+
+#[async_trait]
+impl TextGenerator for AIClient {
+    async fn generate(&self, prompt: &str, model: Option<&str>, priority: CallPriority) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let messages = vec![ChatMessage {
+            role: "user".to_string(),
+            content: prompt.to_string(),
+        }];
+        let response = self.chat_completion_with_model(messages, model, priority).await?;
+        let choice = response.choices.into_iter().next().ok_or("No response from AI")?;
+        Ok(choice.message.content)
+    }
+
+    fn model_name(&self) -> Option<String> {
+        Some(self.model.clone())
+    }
 }
\ No newline at end of file
@@ -1,6 +1,10 @@
 use tokio;
+use std::collections::HashMap;
+use std::future::Future;
 use std::sync::Arc;
+use futures::stream::{FuturesUnordered, StreamExt};
 use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use tokio::time::{timeout, Duration};
 use crate::context::llm_context::ContextManager;
 use crate::domain::domain_classifier::DomainClassifier;
@@ -27,6 +31,7 @@ impl Default for AsyncRuntimeConfig {
 }
 
 /// 异步运行时 - 管理并发请求和资源分配
+#[allow(dead_code)]
 pub struct AsyncRuntime {
     /// 信号量用于限制并发数
     concurrency_limiter: Arc<Semaphore>,
@@ -74,7 +79,7 @@ impl AsyncRuntime {
         let _permit = self.concurrency_limiter
             .acquire()
             .await
-            .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
+            .map_err(|e| Box::new(std::io::Error::other(e)))?;
 
         // 1. 识别领域
         let domain = timeout(
@@ -130,7 +135,7 @@ impl AsyncRuntime {
     async fn select_contexts(
         &self,
         contexts: &[crate::context::llm_context::LLMContext],
-        query: &str
+        _query: &str
     ) -> Vec<crate::context::llm_context::LLMContext> {
         // 在实际实现中，这里会调用真正的上下文选择逻辑
         // 为演示目的，我们返回前几个上下文
@@ -193,6 +198,147 @@ impl std::fmt::Display for RuntimeStats {
     }
 }
 
+
+/// 扇出中单个任务的失败
+#[derive(Debug)]
+pub enum TaskError<E> {
+    Failed(E),          // 任务返回错误
+    Panicked(String),   // 任务panic（只有spawn_fan_out在独立任务中运行时能捕获）
+    Cancelled,          // 其他任务出现致命错误后未启动或被中止
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for TaskError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaskError::Failed(e) => write!(f, "{}", e),
+            TaskError::Panicked(message) => write!(f, "task panicked: {}", message),
+            TaskError::Cancelled => write!(f, "task cancelled"),
+        }
+    }
+}
+
+/// 扇出的汇总错误：失败任务的序号和错误（按序号排列）
+#[derive(Debug)]
+pub struct FanOutError<E> {
+    pub failures: Vec<(usize, TaskError<E>)>,
+}
+
+impl<E: std::fmt::Display> std::fmt::Display for FanOutError<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} fan-out task(s) failed", self.failures.len())?;
+        for (index, error) in &self.failures {
+            write!(f, "; #{}: {}", index, error)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: std::fmt::Debug + std::fmt::Display> std::error::Error for FanOutError<E> {}
+
+impl<E> FanOutError<E> {
+    /// 全部任务成功时返回结果，否则汇总所有失败
+    pub fn collect<R>(outcomes: Vec<Result<R, TaskError<E>>>) -> Result<Vec<R>, Self> {
+        let mut results = Vec::with_capacity(outcomes.len());
+        let mut failures = Vec::new();
+        for (index, outcome) in outcomes.into_iter().enumerate() {
+            match outcome {
+                Ok(result) => results.push(result),
+                Err(error) => failures.push((index, error)),
+            }
+        }
+        if failures.is_empty() {
+            Ok(results)
+        } else {
+            Err(Self { failures })
+        }
+    }
+}
+
+/// 在当前任务中并发执行，同时最多max_concurrency个，结果按输入顺序返回；
+/// 某个任务的错误满足is_fatal时丢弃（即取消）仍在执行的任务，不再启动剩余任务。
+/// 任务可以借用调用方的数据，全部结束后才返回
+pub async fn fan_out<T, R, E, F, Fut>(
+    items: impl IntoIterator<Item = T>,
+    max_concurrency: usize,
+    is_fatal: impl Fn(&E) -> bool,
+    mut task: F,
+) -> Vec<Result<R, TaskError<E>>>
+where
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = Result<R, E>>,
+{
+    let items: Vec<T> = items.into_iter().collect();
+    let mut outcomes: Vec<Option<Result<R, TaskError<E>>>> = items.iter().map(|_| None).collect();
+    let mut pending = items.into_iter().enumerate();
+    let mut running = FuturesUnordered::new();
+    loop {
+        while running.len() < max_concurrency.max(1) {
+            let Some((index, item)) = pending.next() else { break };
+            let future = task(item);
+            running.push(async move { (index, future.await) });
+        }
+        let Some((index, result)) = running.next().await else { break };
+        let fatal = result.as_ref().err().is_some_and(&is_fatal);
+        outcomes[index] = Some(result.map_err(TaskError::Failed));
+        if fatal {
+            break;
+        }
+    }
+    drop(running);
+    outcomes.into_iter().map(|outcome| outcome.unwrap_or(Err(TaskError::Cancelled))).collect()
+}
+
+/// 在JoinSet的独立任务中并行执行，同时最多max_concurrency个，结果按输入顺序返回；
+/// 任务panic时记为失败而不影响其他任务，错误满足is_fatal时中止其余任务。返回时不会留下仍在运行的任务
+pub async fn spawn_fan_out<T, R, E, F, Fut>(
+    items: impl IntoIterator<Item = T>,
+    max_concurrency: usize,
+    is_fatal: impl Fn(&E) -> bool,
+    mut task: F,
+) -> Vec<Result<R, TaskError<E>>>
+where
+    R: Send + 'static,
+    E: Send + 'static,
+    F: FnMut(T) -> Fut,
+    Fut: Future<Output = Result<R, E>> + Send + 'static,
+{
+    let items: Vec<T> = items.into_iter().collect();
+    let mut outcomes: Vec<Option<Result<R, TaskError<E>>>> = items.iter().map(|_| None).collect();
+    let mut pending = items.into_iter().enumerate();
+    let mut running = JoinSet::new();
+    let mut indices = HashMap::new();
+    loop {
+        while running.len() < max_concurrency.max(1) {
+            let Some((index, item)) = pending.next() else { break };
+            let handle = running.spawn(task(item));
+            indices.insert(handle.id(), index);
+        }
+        let Some(joined) = running.join_next_with_id().await else { break };
+        let (index, outcome) = match joined {
+            Ok((id, result)) => (indices[&id], result.map_err(TaskError::Failed)),
+            Err(e) if e.is_panic() => {
+                let index = indices[&e.id()];
+                let payload = e.into_panic();
+                let message = payload
+                    .downcast_ref::<&str>()
+                    .map(|message| message.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                (index, Err(TaskError::Panicked(message)))
+            }
+            // 只有shutdown会取消任务，此处不会出现
+            Err(_) => continue,
+        };
+        let fatal = matches!(outcome, Err(TaskError::Failed(ref e)) if is_fatal(e));
+        outcomes[index] = Some(outcome);
+        if fatal {
+            break;
+        }
+    }
+    running.shutdown().await;
+    outcomes.into_iter().map(|outcome| outcome.unwrap_or(Err(TaskError::Cancelled))).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -228,7 +374,7 @@ mod tests {
         let stats = runtime.get_runtime_stats().await;
         println!("{}", stats);
         
-        assert!(stats.active_requests >= 0);
+        assert!(stats.active_requests <= stats.max_concurrent_requests);
         assert_eq!(stats.max_concurrent_requests, 100); // 默认值
     }
 
@@ -269,4 +415,65 @@ mod tests {
         let stats = runtime.get_runtime_stats().await;
         println!("Final stats: {}", stats);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_fan_out_bounds_concurrency_and_cancels_on_fatal_error() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let started = AtomicUsize::new(0);
+        let outcomes = fan_out(0..10u64, 3, |e: &String| e.starts_with("fatal"), |i| {
+            let (running, peak, started) = (&running, &peak, &started);
+            async move {
+                started.fetch_add(1, Ordering::SeqCst);
+                peak.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10 * (i % 3 + 1))).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                match i {
+                    1 => Err("recoverable".to_string()),
+                    5 => Err("fatal".to_string()),
+                    _ => Ok(i * 10),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+        assert_eq!(outcomes.len(), 10);
+        assert_eq!(outcomes[0].as_ref().unwrap(), &0);
+        assert!(matches!(outcomes[1], Err(TaskError::Failed(ref e)) if e == "recoverable"));
+        assert!(matches!(outcomes[5], Err(TaskError::Failed(ref e)) if e == "fatal"));
+        // 致命错误之后的任务没有启动
+        assert!(started.load(Ordering::SeqCst) < 10);
+        assert!(matches!(outcomes[9], Err(TaskError::Cancelled)));
+        let error = FanOutError::collect(outcomes).unwrap_err();
+        assert_eq!(error.failures[0].0, 1);
+        assert!(error.to_string().contains("#5: fatal"));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_fan_out_captures_panics_and_aborts_on_fatal_error() {
+        let outcomes = spawn_fan_out(0..4u32, 4, |_: &String| false, |i| async move {
+            if i == 2 {
+                panic!("task {} exploded", i);
+            }
+            Ok::<_, String>(i)
+        })
+        .await;
+        assert!(matches!(outcomes[2], Err(TaskError::Panicked(ref message)) if message == "task 2 exploded"));
+        assert_eq!(FanOutError::collect(outcomes).unwrap_err().failures.len(), 1);
+
+        // 致命错误中止仍在运行的任务
+        let outcomes = spawn_fan_out(0..3u32, 3, |_: &String| true, |i| async move {
+            if i == 0 {
+                return Err("fatal".to_string());
+            }
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            Ok(i)
+        })
+        .await;
+        assert!(matches!(outcomes[0], Err(TaskError::Failed(_))));
+        assert!(outcomes[1..].iter().all(|outcome| matches!(outcome, Err(TaskError::Cancelled))));
+    }
+}
@@ -1,6 +1,13 @@
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::utils::search_budget::{SearchBudget, SearchBudgetExceeded};
+use crate::utils::outbound_scheduler::{CallPriority, OutboundScheduler, OutboundThrottled};
+use crate::utils::search_options::SearchOptions;
+use crate::utils::provider_health::{ProviderHealth, ProviderStatus, GITHUB_PROVIDER};
+use crate::utils::replay::{ReplayError, ReplayLayer};
 use crate::utils::web_search::{SearchResult, WebSearchClient, WebSearchError};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +27,9 @@ pub enum IntelligentSearchError {
     GitHubSearchError(String),
     RequestError(reqwest::Error),
     ParseError(serde_json::Error),
+    BudgetExceeded(SearchBudgetExceeded),
+    Throttled(OutboundThrottled),
+    Replay(ReplayError),
 }
 
 impl From<WebSearchError> for IntelligentSearchError {
@@ -28,6 +38,24 @@ impl From<WebSearchError> for IntelligentSearchError {
     }
 }
 
+impl From<SearchBudgetExceeded> for IntelligentSearchError {
+    fn from(err: SearchBudgetExceeded) -> Self {
+        IntelligentSearchError::BudgetExceeded(err)
+    }
+}
+
+impl From<OutboundThrottled> for IntelligentSearchError {
+    fn from(err: OutboundThrottled) -> Self {
+        IntelligentSearchError::Throttled(err)
+    }
+}
+
+impl From<ReplayError> for IntelligentSearchError {
+    fn from(err: ReplayError) -> Self {
+        IntelligentSearchError::Replay(err)
+    }
+}
+
 impl From<reqwest::Error> for IntelligentSearchError {
     fn from(err: reqwest::Error) -> Self {
         IntelligentSearchError::RequestError(err)
@@ -50,7 +78,7 @@ impl IntelligentSearchClient {
         let web_search_client = match WebSearchClient::new() {
             Ok(client) => Some(client),
             Err(e) => {
-                eprintln!("Failed to initialize WebSearchClient: {:?}", e);
+                log::warn!("Failed to initialize WebSearchClient: {:?}", e);
                 None
             }
         };
@@ -58,7 +86,7 @@ impl IntelligentSearchClient {
         let github_search_client = match GitHubSearchClient::new() {
             Ok(client) => Some(client),
             Err(e) => {
-                eprintln!("Failed to initialize GitHubSearchClient: {:?}", e);
+                log::warn!("Failed to initialize GitHubSearchClient: {:?}", e);
                 None
             }
         };
@@ -69,24 +97,45 @@ impl IntelligentSearchClient {
         })
     }
 
+    /// 为网络搜索和GitHub搜索关联同一个搜索预算
+    pub fn with_budget(mut self, budget: Arc<SearchBudget>) -> Self {
+        self.web_search_client = self.web_search_client.map(|client| client.with_budget(budget.clone()));
+        self.github_search_client = self.github_search_client.map(|client| client.with_budget(budget));
+        self
+    }
+
+    /// 为网络搜索和GitHub搜索关联同一个外呼调度器
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.web_search_client = self.web_search_client.map(|client| client.with_scheduler(scheduler.clone()));
+        self.github_search_client = self.github_search_client.map(|client| client.with_scheduler(scheduler));
+        self
+    }
+
+    /// 为网络搜索和GitHub搜索关联同一个录制/回放层
+    pub fn with_replay(mut self, replay: Arc<ReplayLayer>) -> Self {
+        self.web_search_client = self.web_search_client.map(|client| client.with_replay(replay.clone()));
+        self.github_search_client = self.github_search_client.map(|client| client.with_replay(replay));
+        self
+    }
+
     /// 智能搜索 - 根据查询内容自动选择合适的搜索引擎
-    pub async fn intelligent_search(&self, query: &str, count: Option<u32>) -> Result<Vec<SearchResult>, IntelligentSearchError> {
+    pub async fn intelligent_search(&self, query: &str, count: Option<u32>, options: &SearchOptions) -> Result<Vec<SearchResult>, IntelligentSearchError> {
         let query_type = self.classify_query(query);
         
         match query_type {
             QueryType::Code | QueryType::Technical => {
                 if let Some(ref github_client) = self.github_search_client {
                     // 对技术查询使用GitHub搜索
-                    let github_results = github_client.search_repositories(query, count.unwrap_or(5)).await?;
+                    let github_results = github_client.search_repositories(query, count.unwrap_or(5), options).await?;
                     Ok(self.convert_github_results_to_search_results(github_results))
                 } else {
                     // 如果GitHub搜索不可用，回退到普通网络搜索
-                    self.fallback_search(query, count).await
+                    self.fallback_search(query, count, options).await
                 }
             },
             QueryType::General => {
                 // 对一般查询使用普通网络搜索
-                self.fallback_search(query, count).await
+                self.fallback_search(query, count, options).await
             }
         }
     }
@@ -199,9 +248,9 @@ impl IntelligentSearchClient {
     }
 
     /// 回退到普通网络搜索
-    async fn fallback_search(&self, query: &str, count: Option<u32>) -> Result<Vec<SearchResult>, IntelligentSearchError> {
+    async fn fallback_search(&self, query: &str, count: Option<u32>, options: &SearchOptions) -> Result<Vec<SearchResult>, IntelligentSearchError> {
         if let Some(ref web_client) = self.web_search_client {
-            let results = web_client.search_with_relevance_scoring(query, count).await?;
+            let results = web_client.search_with_relevance_scoring(query, count, options).await?;
             Ok(results)
         } else {
             Err(IntelligentSearchError::WebSearchError(WebSearchError::ApiKeyMissing))
@@ -236,6 +285,9 @@ enum QueryType {
 pub struct GitHubSearchClient {
     client: reqwest::Client,
     api_key: Option<String>,
+    budget: Option<Arc<SearchBudget>>,
+    scheduler: Option<Arc<OutboundScheduler>>,
+    replay: Option<Arc<ReplayLayer>>,
 }
 
 impl GitHubSearchClient {
@@ -248,15 +300,78 @@ impl GitHubSearchClient {
         Ok(Self {
             client: reqwest::Client::new(),
             api_key,
+            budget: None,
+            scheduler: None,
+            replay: ReplayLayer::from_env(),
         })
     }
 
+    /// 关联搜索预算，每次查询前检查并计入用量
+    pub fn with_budget(mut self, budget: Arc<SearchBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// 关联外呼调度器，每次查询前按优先级等待服务配额
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 关联录制/回放层，回放模式下不再访问GitHub
+    pub fn with_replay(mut self, replay: Arc<ReplayLayer>) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// 探测GitHub令牌是否可用（查询配额接口，不消耗搜索配额，但以后台优先级等待外呼配额）
+    pub async fn check_health(&self, timeout: Duration) -> ProviderHealth {
+        let Some(ref token) = self.api_key else {
+            return ProviderHealth::new(
+                GITHUB_PROVIDER,
+                ProviderStatus::Unconfigured,
+                None,
+                Some("GITHUB_API_KEY not configured, using anonymous rate limits".to_string()),
+            );
+        };
+
+        if let Some(ref scheduler) = self.scheduler {
+            if let Err(e) = scheduler.acquire(GITHUB_PROVIDER, CallPriority::Background).await {
+                return ProviderHealth::new(GITHUB_PROVIDER, ProviderStatus::RateLimited, None, Some(e.to_string()));
+            }
+        }
+
+        let response = self.client
+            .get("https://api.github.com/rate_limit")
+            .header("Authorization", format!("Bearer {}", token))
+            .header("User-Agent", "penlai-search-client")
+            .timeout(timeout)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => ProviderHealth::from_response(GITHUB_PROVIDER, &response),
+            Err(e) => ProviderHealth::new(GITHUB_PROVIDER, ProviderStatus::Unreachable, None, Some(e.to_string())),
+        }
+    }
+
     /// Search GitHub repositories
-    pub async fn search_repositories(&self, query: &str, count: u32) -> Result<Vec<GitHubSearchResult>, IntelligentSearchError> {
+    pub async fn search_repositories(&self, query: &str, count: u32, options: &SearchOptions) -> Result<Vec<GitHubSearchResult>, IntelligentSearchError> {
+        let query = format!("{}{}", query, options.github_qualifiers());
         let url = format!("https://api.github.com/search/repositories?q={}&sort=stars&order=desc&per_page={}", 
-                         urlencoding::encode(query), 
+                         urlencoding::encode(&query), 
                          std::cmp::min(count, 30)); // GitHub API limits to 30 per page for search
 
+        if let Some(replay) = self.replay.as_ref().filter(|replay| replay.is_replaying()) {
+            return Ok(replay.replay(GITHUB_PROVIDER, &(&url, count)).await?);
+        }
+        if let Some(ref budget) = self.budget {
+            budget.try_consume(GITHUB_PROVIDER, options.user_id.as_deref(), options.domain.as_deref(), options.request_id).await?;
+        }
+        if let Some(ref scheduler) = self.scheduler {
+            scheduler.acquire(GITHUB_PROVIDER, options.priority.unwrap_or_default()).await?;
+        }
+
         let mut request_builder = self.client.get(&url);
 
         // Add authorization header if token is available
@@ -290,8 +405,11 @@ impl GitHubSearchClient {
                 stars: item.stargazers_count,
                 forks: item.forks_count,
             })
-            .collect();
+            .collect::<Vec<_>>();
 
+        if let Some(ref replay) = self.replay {
+            replay.record(GITHUB_PROVIDER, &(&url, count), &results).await?;
+        }
         Ok(results)
     }
 }
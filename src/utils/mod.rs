@@ -1,6 +1,14 @@
+#[allow(clippy::module_inception)]
 pub mod utils;
 pub mod async_runtime;
 pub mod ai_client;
 pub mod ai_integration;
 pub mod web_search;
-pub mod intelligent_search;
\ No newline at end of file
+pub mod intelligent_search;
+pub mod provider_health;
+pub mod search_options;
+pub mod search_budget;
+pub mod outbound_scheduler;
+pub mod request_context;
+pub mod tokenizer;
+pub mod replay;
\ No newline at end of file
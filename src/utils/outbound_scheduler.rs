@@ -0,0 +1,307 @@
+//! 外呼调度 - 为AI和搜索服务的外部调用统一执行服务级QPS上限，交互请求优先于后台任务（刷新、摘要等）
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use crate::utils::provider_health::{BING_PROVIDER, GITHUB_PROVIDER};
+
+/// AI服务名（用于外呼调度和统计）
+pub const AI_PROVIDER: &str = "ai";
+/// 嵌入服务名（嵌入模型迁移等后台回填使用）
+pub const EMBEDDING_PROVIDER: &str = "embedding";
+/// 网页爬虫（按页面计）
+pub const CRAWLER_PROVIDER: &str = "crawler";
+/// Webhook入库（按文档计）
+pub const INGESTION_PROVIDER: &str = "ingestion";
+
+/// 外呼优先级
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CallPriority {
+    #[default]
+    Interactive,    // 用户请求，优先获得配额
+    Background,     // 后台任务，只使用交互请求剩余的配额
+}
+
+/// 等待配额超时
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboundThrottled {
+    pub provider: String,
+    pub priority: CallPriority,
+    pub waited_ms: u64,
+}
+
+impl std::fmt::Display for OutboundThrottled {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Outbound call to '{}' throttled after waiting {}ms ({:?})",
+            self.provider, self.waited_ms, self.priority
+        )
+    }
+}
+
+impl std::error::Error for OutboundThrottled {}
+
+/// 外呼调度配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboundSchedulerConfig {
+    pub provider_qps: HashMap<String, f64>, // 每个服务的QPS上限，未配置的服务不限速
+    pub background_share: f64,              // 后台任务最多占用的QPS比例（0-1）
+    pub interactive_max_wait_ms: u64,       // 交互请求最长等待时间
+    pub background_max_wait_ms: u64,        // 后台任务最长等待时间
+}
+
+impl Default for OutboundSchedulerConfig {
+    fn default() -> Self {
+        let mut provider_qps = HashMap::new();
+        provider_qps.insert(AI_PROVIDER.to_string(), 10.0);
+        provider_qps.insert(BING_PROVIDER.to_string(), 3.0);
+        provider_qps.insert(GITHUB_PROVIDER.to_string(), 0.5);
+
+        Self {
+            provider_qps,
+            background_share: 0.3,
+            interactive_max_wait_ms: 5_000,
+            background_max_wait_ms: 300_000,
+        }
+    }
+}
+
+/// 单个服务的外呼统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OutboundProviderStats {
+    pub granted: HashMap<CallPriority, u64>,    // 按优先级获得配额的调用数
+    pub throttled: HashMap<CallPriority, u64>,  // 按优先级等待超时的调用数
+    pub total_wait_ms: HashMap<CallPriority, u64>,
+    pub waiting_interactive: usize,             // 当前排队的交互请求
+    pub waiting_background: usize,              // 当前排队的后台任务
+}
+
+/// 单个服务的令牌桶
+struct ProviderBucket {
+    tokens: f64,                // 所有调用共享的令牌
+    background_tokens: f64,     // 后台任务额外需要的令牌（按background_share补充）
+    last_refill: Instant,
+    waiting_interactive: Arc<AtomicUsize>,
+    waiting_background: Arc<AtomicUsize>,
+    stats: OutboundProviderStats,
+}
+
+/// 排队占位，离开作用域（获得配额、超时或调用方取消）时自动出队
+struct WaitSlot(Arc<AtomicUsize>);
+
+impl WaitSlot {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter)
+    }
+}
+
+impl Drop for WaitSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl ProviderBucket {
+    fn new(qps: f64, background_share: f64) -> Self {
+        Self {
+            tokens: qps.max(1.0),
+            background_tokens: (qps * background_share).max(1.0),
+            last_refill: Instant::now(),
+            waiting_interactive: Arc::new(AtomicUsize::new(0)),
+            waiting_background: Arc::new(AtomicUsize::new(0)),
+            stats: OutboundProviderStats::default(),
+        }
+    }
+
+    /// 按经过的时间补充令牌，容量为1秒的配额
+    fn refill(&mut self, qps: f64, background_share: f64) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * qps).min(qps.max(1.0));
+        let background_qps = qps * background_share;
+        self.background_tokens = (self.background_tokens + elapsed * background_qps).min(background_qps.max(1.0));
+    }
+
+    fn waiting(&self, priority: CallPriority) -> Arc<AtomicUsize> {
+        match priority {
+            CallPriority::Interactive => self.waiting_interactive.clone(),
+            CallPriority::Background => self.waiting_background.clone(),
+        }
+    }
+}
+
+/// 外呼调度器 - 所有AI和搜索调用发出前调用`acquire`
+pub struct OutboundScheduler {
+    config: Arc<RwLock<OutboundSchedulerConfig>>,
+    buckets: Arc<RwLock<HashMap<String, ProviderBucket>>>,
+}
+
+impl Default for OutboundScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OutboundScheduler {
+    /// 使用默认配置创建调度器
+    pub fn new() -> Self {
+        Self::with_config(OutboundSchedulerConfig::default())
+    }
+
+    /// 使用指定配置创建调度器
+    pub fn with_config(config: OutboundSchedulerConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            buckets: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 等待指定服务的配额；有交互请求排队时后台任务让行，超过最长等待时间返回错误
+    pub async fn acquire(&self, provider: &str, priority: CallPriority) -> Result<(), OutboundThrottled> {
+        let config = self.config.read().await.clone();
+        let Some(&qps) = config.provider_qps.get(provider) else {
+            return Ok(());
+        };
+        let qps = qps.max(f64::MIN_POSITIVE);
+        let background_share = config.background_share.clamp(0.0, 1.0);
+        let max_wait = Duration::from_millis(match priority {
+            CallPriority::Interactive => config.interactive_max_wait_ms,
+            CallPriority::Background => config.background_max_wait_ms,
+        });
+        let started = Instant::now();
+        let mut slot: Option<WaitSlot> = None;
+
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.write().await;
+                let bucket = buckets
+                    .entry(provider.to_string())
+                    .or_insert_with(|| ProviderBucket::new(qps, background_share));
+                bucket.refill(qps, background_share);
+
+                let granted = match priority {
+                    CallPriority::Interactive => bucket.tokens >= 1.0,
+                    CallPriority::Background => {
+                        bucket.waiting_interactive.load(Ordering::SeqCst) == 0 && bucket.tokens >= 1.0 && bucket.background_tokens >= 1.0
+                    }
+                };
+                let waited_ms = started.elapsed().as_millis() as u64;
+                if granted {
+                    bucket.tokens -= 1.0;
+                    if priority == CallPriority::Background {
+                        bucket.background_tokens -= 1.0;
+                    }
+                    *bucket.stats.granted.entry(priority).or_insert(0) += 1;
+                    *bucket.stats.total_wait_ms.entry(priority).or_insert(0) += waited_ms;
+                    return Ok(());
+                }
+                if started.elapsed() >= max_wait {
+                    *bucket.stats.throttled.entry(priority).or_insert(0) += 1;
+                    let throttled = OutboundThrottled { provider: provider.to_string(), priority, waited_ms };
+                    log::warn!("{}", throttled);
+                    return Err(throttled);
+                }
+                if slot.is_none() {
+                    slot = Some(WaitSlot::new(bucket.waiting(priority)));
+                }
+
+                // 等到下一个令牌补充；后台任务还受自身配额限制
+                let mut deficit = (1.0 - bucket.tokens).max(0.0) / qps;
+                if priority == CallPriority::Background && background_share > 0.0 {
+                    deficit = deficit.max((1.0 - bucket.background_tokens).max(0.0) / (qps * background_share));
+                }
+                Duration::from_secs_f64(deficit.clamp(0.001, 0.1))
+            };
+            tokio::time::sleep(wait.min(max_wait.saturating_sub(started.elapsed())).max(Duration::from_millis(1))).await;
+        }
+    }
+
+    /// 获取配额后执行调用
+    pub async fn run<F, T>(&self, provider: &str, priority: CallPriority, call: F) -> Result<T, OutboundThrottled>
+    where
+        F: std::future::Future<Output = T>,
+    {
+        self.acquire(provider, priority).await?;
+        Ok(call.await)
+    }
+
+    /// 获取各服务的外呼统计
+    pub async fn get_stats(&self) -> HashMap<String, OutboundProviderStats> {
+        self.buckets
+            .read()
+            .await
+            .iter()
+            .map(|(provider, bucket)| {
+                let mut stats = bucket.stats.clone();
+                stats.waiting_interactive = bucket.waiting_interactive.load(Ordering::SeqCst);
+                stats.waiting_background = bucket.waiting_background.load(Ordering::SeqCst);
+                (provider.clone(), stats)
+            })
+            .collect()
+    }
+
+    /// 更新配置（已有令牌桶在下一次补充时按新QPS计算）
+    pub async fn update_config(&self, new_config: OutboundSchedulerConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> OutboundSchedulerConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_interactive_preempts_background() {
+        let mut provider_qps = HashMap::new();
+        provider_qps.insert(AI_PROVIDER.to_string(), 20.0);
+        let scheduler = Arc::new(OutboundScheduler::with_config(OutboundSchedulerConfig {
+            provider_qps,
+            background_share: 0.5,
+            interactive_max_wait_ms: 2_000,
+            background_max_wait_ms: 50,
+        }));
+
+        // 未配置QPS的服务不限速
+        for _ in 0..100 {
+            scheduler.acquire("unlimited", CallPriority::Background).await.unwrap();
+        }
+
+        // 后台任务最多用掉一半的突发配额
+        let mut background_granted = 0;
+        while scheduler.acquire(AI_PROVIDER, CallPriority::Background).await.is_ok() {
+            background_granted += 1;
+            assert!(background_granted <= 20);
+        }
+        assert!((10..=12).contains(&background_granted));
+
+        // 交互请求排队时后台任务让行
+        let interactive: Vec<_> = (0..15)
+            .map(|_| {
+                let scheduler = scheduler.clone();
+                tokio::spawn(async move { scheduler.acquire(AI_PROVIDER, CallPriority::Interactive).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(scheduler.acquire(AI_PROVIDER, CallPriority::Background).await.is_err());
+        for handle in interactive {
+            handle.await.unwrap().unwrap();
+        }
+
+        let stats = &scheduler.get_stats().await[AI_PROVIDER];
+        assert_eq!(stats.granted[&CallPriority::Interactive], 15);
+        assert!(stats.throttled[&CallPriority::Background] >= 2);
+        assert_eq!(stats.waiting_interactive, 0);
+    }
+}
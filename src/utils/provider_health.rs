@@ -0,0 +1,303 @@
+//! 搜索服务健康检查 - 探测Bing/GitHub密钥是否可用，区分未配置、密钥失效和被限流，并同步到监控警报
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use crate::monitoring::monitoring::{MonitoringEvent, MonitoringSystem};
+use crate::utils::intelligent_search::GitHubSearchClient;
+use crate::utils::outbound_scheduler::OutboundScheduler;
+use crate::utils::search_budget::SearchBudget;
+use crate::utils::web_search::{WebSearchClient, WebSearchError};
+
+/// 搜索服务名称
+pub const BING_PROVIDER: &str = "bing";
+pub const GITHUB_PROVIDER: &str = "github";
+
+/// 搜索服务状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum ProviderStatus {
+    Configured,     // 密钥有效，探测成功
+    Unconfigured,   // 未配置密钥
+    Unauthorized,   // 密钥无效或已过期（401/403）
+    RateLimited,    // 配额耗尽（429或剩余配额为0）
+    Unreachable,    // 网络错误、超时或服务端错误
+}
+
+impl ProviderStatus {
+    /// 根据HTTP状态码和剩余配额（如GitHub的x-ratelimit-remaining）判断状态
+    pub fn from_http_status(status: u16, rate_limit_remaining: Option<u64>) -> Self {
+        match status {
+            200..=299 => ProviderStatus::Configured,
+            429 => ProviderStatus::RateLimited,
+            // GitHub配额耗尽时返回403
+            403 if rate_limit_remaining == Some(0) => ProviderStatus::RateLimited,
+            401 | 403 => ProviderStatus::Unauthorized,
+            _ => ProviderStatus::Unreachable,
+        }
+    }
+
+    /// 是否需要触发警报（已配置但不可用）
+    pub fn is_alerting(&self) -> bool {
+        matches!(self, ProviderStatus::Unauthorized | ProviderStatus::RateLimited | ProviderStatus::Unreachable)
+    }
+}
+
+/// 单个搜索服务的健康状态
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ProviderHealth {
+    pub provider: String,
+    pub status: ProviderStatus,
+    pub http_status: Option<u16>,   // 探测请求返回的状态码
+    pub detail: Option<String>,     // 失败原因或附加信息
+    pub checked_at: DateTime<Utc>,
+}
+
+impl ProviderHealth {
+    /// 创建健康状态记录
+    pub fn new(provider: &str, status: ProviderStatus, http_status: Option<u16>, detail: Option<String>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            status,
+            http_status,
+            detail,
+            checked_at: Utc::now(),
+        }
+    }
+
+    /// 由探测响应生成健康状态
+    pub fn from_response(provider: &str, response: &reqwest::Response) -> Self {
+        let status = response.status();
+        let remaining = response
+            .headers()
+            .get("x-ratelimit-remaining")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse().ok());
+        let provider_status = ProviderStatus::from_http_status(status.as_u16(), remaining);
+        let detail = match provider_status {
+            ProviderStatus::Configured => None,
+            _ => Some(format!("{} API returned status: {}", provider, status)),
+        };
+        Self::new(provider, provider_status, Some(status.as_u16()), detail)
+    }
+
+    /// 警报使用的指标名
+    pub fn alert_metric(&self) -> String {
+        format!("provider_health.{}", self.provider)
+    }
+}
+
+/// 健康检查配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderHealthConfig {
+    pub timeout_ms: u64,                // 单次探测超时
+    pub check_interval_seconds: u64,    // 定期检查间隔
+}
+
+impl Default for ProviderHealthConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 5000,
+            check_interval_seconds: 300,
+        }
+    }
+}
+
+/// 搜索服务健康检查器 - 保存最近一次的检查结果
+pub struct ProviderHealthChecker {
+    results: Arc<RwLock<HashMap<String, ProviderHealth>>>,
+    config: Arc<RwLock<ProviderHealthConfig>>,
+    /// 可选的外呼调度器，探测以后台优先级等待配额
+    scheduler: Option<Arc<OutboundScheduler>>,
+    /// 可选的搜索预算，计费的探测查询计入用量
+    budget: Option<Arc<SearchBudget>>,
+    /// 热加载设置的Bing搜索地址，未设置时使用BING_SEARCH_URL
+    bing_search_url: Arc<RwLock<Option<String>>>,
+}
+
+impl Default for ProviderHealthChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProviderHealthChecker {
+    /// 创建健康检查器
+    pub fn new() -> Self {
+        Self {
+            results: Arc::new(RwLock::new(HashMap::new())),
+            config: Arc::new(RwLock::new(ProviderHealthConfig::default())),
+            scheduler: None,
+            budget: None,
+            bing_search_url: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    /// 关联外呼调度器，探测与搜索共用服务的QPS上限
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 关联搜索预算，Bing探测查询计入用量
+    pub fn with_budget(mut self, budget: Arc<SearchBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// 按配置的超时探测所有搜索服务并保存结果
+    pub async fn check_all(&self) -> Vec<ProviderHealth> {
+        let timeout = Duration::from_millis(self.config.read().await.timeout_ms);
+        self.check_all_with_timeout(timeout).await
+    }
+
+    /// 以指定的单次探测超时探测所有搜索服务并保存结果（不修改配置，如启动自检使用自己的超时）
+    pub async fn check_all_with_timeout(&self, timeout: Duration) -> Vec<ProviderHealth> {
+        let bing = match WebSearchClient::new() {
+            Ok(mut client) => {
+                if let Some(ref scheduler) = self.scheduler {
+                    client = client.with_scheduler(scheduler.clone());
+                }
+                if let Some(ref budget) = self.budget {
+                    client = client.with_budget(budget.clone());
+                }
+                if let Some(ref url) = *self.bing_search_url.read().await {
+                    client = client.with_search_url(url);
+                }
+                client.check_health(timeout).await
+            }
+            Err(WebSearchError::ApiKeyMissing) => ProviderHealth::new(
+                BING_PROVIDER,
+                ProviderStatus::Unconfigured,
+                None,
+                Some("BING_API_KEY not configured".to_string()),
+            ),
+            Err(e) => ProviderHealth::new(BING_PROVIDER, ProviderStatus::Unreachable, None, Some(format!("{:?}", e))),
+        };
+        let github = match GitHubSearchClient::new() {
+            Ok(mut client) => {
+                if let Some(ref scheduler) = self.scheduler {
+                    client = client.with_scheduler(scheduler.clone());
+                }
+                client.check_health(timeout).await
+            }
+            Err(e) => ProviderHealth::new(GITHUB_PROVIDER, ProviderStatus::Unreachable, None, Some(format!("{:?}", e))),
+        };
+
+        for health in [&bing, &github] {
+            self.record(health.clone()).await;
+        }
+        vec![bing, github]
+    }
+
+    /// 记录一次检查结果（搜索失败时也可由调用方直接上报）
+    pub async fn record(&self, health: ProviderHealth) {
+        if health.status.is_alerting() {
+            log::warn!(
+                "Search provider {} is {:?}: {}",
+                health.provider,
+                health.status,
+                health.detail.as_deref().unwrap_or("no detail")
+            );
+        }
+        self.results.write().await.insert(health.provider.clone(), health);
+    }
+
+    /// 获取最近一次的检查结果（按服务名排序）
+    pub async fn get_health(&self) -> Vec<ProviderHealth> {
+        let mut results: Vec<ProviderHealth> = self.results.read().await.values().cloned().collect();
+        results.sort_by(|a, b| a.provider.cmp(&b.provider));
+        results
+    }
+
+    /// 将检查结果同步到监控警报：不可用的服务触发警报，恢复后自动解决
+    pub async fn report_to(&self, monitor: &MonitoringSystem) {
+        let alert_manager = monitor.get_alert_manager();
+        for health in self.get_health().await {
+            let metric = health.alert_metric();
+            if health.status.is_alerting() {
+                let (_, is_new) = alert_manager.fire(&metric, 1.0, 0.0).await;
+                if is_new {
+                    monitor
+                        .log_event(MonitoringEvent::PerformanceAlert { metric, value: 1.0, threshold: 0.0 })
+                        .await;
+                }
+            } else {
+                alert_manager.resolve_metric(&metric).await;
+            }
+        }
+    }
+
+    /// 定期执行健康检查并同步警报
+    pub fn start_periodic_checks(self: Arc<Self>, monitor: Arc<MonitoringSystem>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                self.check_all().await;
+                self.report_to(&monitor).await;
+                let interval = self.config.read().await.check_interval_seconds.max(1);
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        })
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: ProviderHealthConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> ProviderHealthConfig {
+        self.config.read().await.clone()
+    }
+
+    /// 设置探测使用的Bing搜索地址
+    pub async fn set_bing_search_url(&self, url: Option<String>) {
+        *self.bing_search_url.write().await = url;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_classification() {
+        assert_eq!(ProviderStatus::from_http_status(200, None), ProviderStatus::Configured);
+        assert_eq!(ProviderStatus::from_http_status(401, None), ProviderStatus::Unauthorized);
+        assert_eq!(ProviderStatus::from_http_status(403, Some(12)), ProviderStatus::Unauthorized);
+        assert_eq!(ProviderStatus::from_http_status(403, Some(0)), ProviderStatus::RateLimited);
+        assert_eq!(ProviderStatus::from_http_status(429, None), ProviderStatus::RateLimited);
+        assert_eq!(ProviderStatus::from_http_status(503, None), ProviderStatus::Unreachable);
+        assert!(!ProviderStatus::Unconfigured.is_alerting());
+    }
+
+    #[tokio::test]
+    async fn test_alerts_follow_provider_status() {
+        let checker = ProviderHealthChecker::new();
+        let monitor = MonitoringSystem::new();
+
+        checker
+            .record(ProviderHealth::new(BING_PROVIDER, ProviderStatus::Unauthorized, Some(401), None))
+            .await;
+        checker
+            .record(ProviderHealth::new(GITHUB_PROVIDER, ProviderStatus::Unconfigured, None, None))
+            .await;
+        checker.report_to(&monitor).await;
+        checker.report_to(&monitor).await;
+
+        let open = monitor.get_alert_manager().get_open_alerts().await;
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].metric, "provider_health.bing");
+        assert_eq!(open[0].occurrence_count, 2);
+
+        checker
+            .record(ProviderHealth::new(BING_PROVIDER, ProviderStatus::Configured, Some(200), None))
+            .await;
+        checker.report_to(&monitor).await;
+        assert!(monitor.get_alert_manager().get_open_alerts().await.is_empty());
+    }
+}
@@ -0,0 +1,281 @@
+//! 录制/回放 - 录制模式下把AI和搜索服务的响应按请求哈希写入磁带文件，回放模式下直接返回录制的响应而不访问网络，
+//! 使完整处理流程的集成测试结果确定，且在本地和CI中无需真实的API密钥
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::sync::RwLock;
+
+/// 默认的磁带文件
+pub const DEFAULT_CASSETTE_PATH: &str = "penlai-replay.jsonl";
+
+/// 录制/回放模式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayMode {
+    #[default]
+    Off,        // 直接调用外部服务
+    Record,     // 调用外部服务并录制成功的响应
+    Replay,     // 只返回录制的响应，未录制的请求报错
+}
+
+impl ReplayMode {
+    /// 按名称解析（不区分大小写）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(ReplayMode::Off),
+            "record" => Some(ReplayMode::Record),
+            "replay" => Some(ReplayMode::Replay),
+            _ => None,
+        }
+    }
+}
+
+/// 一次录制的外部调用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedCall {
+    pub key: String,            // 服务名和请求内容的SHA-256
+    pub provider: String,
+    pub request: Value,
+    pub response: Value,
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 录制/回放错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    Miss { provider: String, key: String },    // 回放模式下没有该请求的录制
+    Storage(String),                            // 磁带文件读写或响应解析失败
+}
+
+impl std::fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ReplayError::Miss { provider, key } => {
+                write!(f, "No recorded {} response for request {} (re-run in record mode to capture it)", provider, key)
+            }
+            ReplayError::Storage(reason) => write!(f, "Replay cassette error: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// 计算请求键：服务名和请求JSON的SHA-256
+pub fn request_key(provider: &str, request: &Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(provider.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(request.to_string().as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// 录制/回放层 - 由AI客户端和搜索客户端共享，磁带为JSONL文件（每行一次调用，同一请求以最后一行为准）
+pub struct ReplayLayer {
+    mode: ReplayMode,
+    path: Option<PathBuf>,
+    calls: RwLock<HashMap<String, RecordedCall>>,
+}
+
+impl ReplayLayer {
+    /// 创建只在内存中保存录制的回放层
+    pub fn in_memory(mode: ReplayMode) -> Self {
+        Self {
+            mode,
+            path: None,
+            calls: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// 打开磁带文件（不存在时在首次录制时创建）
+    pub fn open(mode: ReplayMode, path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        let path = path.as_ref().to_path_buf();
+        let mut calls = HashMap::new();
+        if path.exists() {
+            let file = std::fs::File::open(&path).map_err(|e| ReplayError::Storage(format!("{}: {}", path.display(), e)))?;
+            for (number, line) in BufReader::new(file).lines().enumerate() {
+                let line = line.map_err(|e| ReplayError::Storage(e.to_string()))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let call: RecordedCall = serde_json::from_str(&line)
+                    .map_err(|e| ReplayError::Storage(format!("{} line {}: {}", path.display(), number + 1, e)))?;
+                calls.insert(call.key.clone(), call);
+            }
+        }
+        Ok(Self {
+            mode,
+            path: Some(path),
+            calls: RwLock::new(calls),
+        })
+    }
+
+    /// 按环境变量PENLAI_REPLAY_MODE（off/record/replay）和PENLAI_REPLAY_FILE创建；未开启或磁带无法打开时返回None
+    pub fn from_env() -> Option<Arc<Self>> {
+        let mode = match std::env::var("PENLAI_REPLAY_MODE") {
+            Ok(name) => match ReplayMode::parse(&name) {
+                Some(mode) => mode,
+                None => {
+                    log::warn!("Unknown PENLAI_REPLAY_MODE '{}', calling external services directly", name);
+                    return None;
+                }
+            },
+            Err(_) => return None,
+        };
+        if mode == ReplayMode::Off {
+            return None;
+        }
+        let path = std::env::var("PENLAI_REPLAY_FILE").unwrap_or_else(|_| DEFAULT_CASSETTE_PATH.to_string());
+        match Self::open(mode, &path) {
+            Ok(layer) => Some(Arc::new(layer)),
+            Err(e) => {
+                log::warn!("Failed to open replay cassette '{}', calling external services directly: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// 环境变量是否要求回放（回放模式下客户端不需要真实的API密钥）
+    pub fn replay_requested() -> bool {
+        std::env::var("PENLAI_REPLAY_MODE").ok().and_then(|name| ReplayMode::parse(&name)) == Some(ReplayMode::Replay)
+    }
+
+    /// 当前模式
+    pub fn mode(&self) -> ReplayMode {
+        self.mode
+    }
+
+    /// 是否处于回放模式
+    pub fn is_replaying(&self) -> bool {
+        self.mode == ReplayMode::Replay
+    }
+
+    /// 回放请求的录制响应
+    pub async fn replay<T: DeserializeOwned>(&self, provider: &str, request: &impl Serialize) -> Result<T, ReplayError> {
+        let request = serde_json::to_value(request).map_err(|e| ReplayError::Storage(e.to_string()))?;
+        let key = request_key(provider, &request);
+        let calls = self.calls.read().await;
+        let call = calls.get(&key).ok_or_else(|| ReplayError::Miss { provider: provider.to_string(), key: key.clone() })?;
+        serde_json::from_value(call.response.clone()).map_err(|e| ReplayError::Storage(format!("{} response {}: {}", provider, key, e)))
+    }
+
+    /// 录制模式下保存一次成功调用的响应（其他模式下不做任何事）
+    pub async fn record(&self, provider: &str, request: &impl Serialize, response: &impl Serialize) -> Result<(), ReplayError> {
+        if self.mode != ReplayMode::Record {
+            return Ok(());
+        }
+        let request = serde_json::to_value(request).map_err(|e| ReplayError::Storage(e.to_string()))?;
+        let call = RecordedCall {
+            key: request_key(provider, &request),
+            provider: provider.to_string(),
+            request,
+            response: serde_json::to_value(response).map_err(|e| ReplayError::Storage(e.to_string()))?,
+            recorded_at: Utc::now(),
+        };
+        let mut calls = self.calls.write().await;
+        if let Some(ref path) = self.path {
+            let line = serde_json::to_string(&call).map_err(|e| ReplayError::Storage(e.to_string()))?;
+            let mut file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|e| ReplayError::Storage(format!("{}: {}", path.display(), e)))?;
+            writeln!(file, "{}", line).map_err(|e| ReplayError::Storage(e.to_string()))?;
+        }
+        calls.insert(call.key.clone(), call);
+        Ok(())
+    }
+
+    /// 已录制的调用数
+    pub async fn len(&self) -> usize {
+        self.calls.read().await.len()
+    }
+
+    /// 是否还没有任何录制
+    pub async fn is_empty(&self) -> bool {
+        self.calls.read().await.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use uuid::Uuid;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::RequestProcessor;
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::utils::ai_client::{AIClient, TextGenerator};
+    use crate::utils::outbound_scheduler::CallPriority;
+
+    #[tokio::test]
+    async fn test_recorded_responses_replay_through_full_pipeline() {
+        assert_eq!(ReplayMode::parse(" Replay "), Some(ReplayMode::Replay));
+
+        // 模拟的AI服务，每次回答带上调用序号，未经回放时两次运行的回答不同
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new().route(
+            "/v1/chat/completions",
+            post(move || {
+                let counter = counter.clone();
+                async move {
+                    let n = counter.fetch_add(1, Ordering::SeqCst);
+                    Json(serde_json::json!({
+                        "id": format!("cmpl-{}", n),
+                        "choices": [{ "index": 0, "message": { "role": "assistant", "content": format!("Antibiotics (call {})", n) }, "finish_reason": "stop" }],
+                        "created": 0,
+                        "model": "mock",
+                        "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+                    }))
+                }
+            }),
+        );
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia is treated with antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        let run = |client: AIClient| {
+            let processor = RequestProcessor::new(manager.clone(), Arc::new(ContextSelector::new(manager.clone())))
+                .with_text_generator(Arc::new(client));
+            async move {
+                processor
+                    .process_request("u1".to_string(), "s1".to_string(), "pneumonia".to_string(), "medical".to_string())
+                    .await
+                    .map(|result| result.answer.unwrap())
+            }
+        };
+
+        // 录制：调用真实服务并写入磁带
+        let path = std::env::temp_dir().join(format!("penlai-replay-{}.jsonl", Uuid::new_v4()));
+        let recorder = Arc::new(ReplayLayer::open(ReplayMode::Record, &path).unwrap());
+        let recorded = run(AIClient::new().unwrap().with_base_url(&format!("http://{}/v1", addr)).with_replay(recorder)).await.unwrap();
+        assert!(recorded.starts_with("Antibiotics (call 0)"));
+
+        // 回放：服务地址不可达也得到同样的回答，且不再调用服务
+        let replayer = Arc::new(ReplayLayer::open(ReplayMode::Replay, &path).unwrap());
+        assert_eq!(replayer.len().await, 1);
+        let offline = || AIClient::new().unwrap().with_base_url("http://127.0.0.1:9/v1").with_replay(replayer.clone());
+        assert_eq!(run(offline()).await.unwrap(), recorded);
+        assert_eq!(run(offline()).await.unwrap(), recorded);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // 未录制的请求明确报错
+        let miss = offline().generate("Something never recorded", None, CallPriority::Interactive).await.unwrap_err();
+        assert!(miss.to_string().contains("re-run in record mode"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+//! 请求上下文 - 随请求从处理器传到选择器和大模型/搜索调用，携带截止时间、语言区域、租户和按请求的功能开关，
+//! 使下游阶段按剩余时间而不只是全局超时来限制自身耗时
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+use crate::context::consistency::ConsistencyLevel;
+
+/// 功能开关：检索阶段调用大模型（HyDE、多查询改写），默认开启
+pub const FLAG_LLM_RETRIEVAL: &str = "llm_retrieval";
+/// 功能开关：生成回答，默认开启；关闭时只返回选中的上下文
+pub const FLAG_ANSWER_GENERATION: &str = "answer_generation";
+
+/// 请求上下文
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub request_id: Uuid,                       // 所属请求，内部调用（非请求触发）为nil
+    pub deadline: Option<Instant>,              // 截止时间，None为不限制
+    pub locale: Option<String>,                 // 语言区域（如"zh-CN"），回答未指定语言时使用
+    pub tenant: Option<String>,                 // 租户
+    pub feature_flags: BTreeMap<String, bool>,  // 按请求覆盖的功能开关
+    pub consistency: ConsistencyLevel,          // 读一致性级别
+}
+
+impl RequestContext {
+    /// 为请求创建上下文（不限制截止时间）
+    pub fn new(request_id: Uuid) -> Self {
+        Self { request_id, ..Self::default() }
+    }
+
+    /// 在当前时间加上budget处设置截止时间；已有更早的截止时间时保留较早者
+    pub fn with_timeout(mut self, budget: Duration) -> Self {
+        let deadline = Instant::now() + budget;
+        self.deadline = Some(self.deadline.map_or(deadline, |existing| existing.min(deadline)));
+        self
+    }
+
+    /// 设置语言区域
+    pub fn with_locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// 设置租户
+    pub fn with_tenant(mut self, tenant: impl Into<String>) -> Self {
+        self.tenant = Some(tenant.into());
+        self
+    }
+
+    /// 覆盖一个功能开关
+    pub fn with_feature_flag(mut self, flag: impl Into<String>, enabled: bool) -> Self {
+        self.feature_flags.insert(flag.into(), enabled);
+        self
+    }
+
+    /// 距截止时间的剩余时间（已过期时为0），未设置截止时间时为None
+    pub fn remaining(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    /// 是否已过截止时间
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_some_and(|remaining| remaining.is_zero())
+    }
+
+    /// 阶段的实际超时：阶段默认超时与剩余时间中的较小者
+    pub fn stage_timeout(&self, stage_default: Duration) -> Duration {
+        self.remaining().map_or(stage_default, |remaining| remaining.min(stage_default))
+    }
+
+    /// 功能开关是否开启，请求未覆盖时使用default
+    pub fn feature_enabled(&self, flag: &str, default: bool) -> bool {
+        self.feature_flags.get(flag).copied().unwrap_or(default)
+    }
+
+    /// 请求是否覆盖了任何功能开关（此时结果与默认配置下的结果不同，不应共享缓存）
+    pub fn has_feature_overrides(&self) -> bool {
+        !self.feature_flags.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::concurrent_processor::{RequestError, RequestOptions, RequestProcessor};
+    use crate::selection::async_context_selector::ContextSelector;
+    use crate::test_support::FixedGenerator;
+
+    #[tokio::test]
+    async fn test_deadline_locale_and_flags_reach_downstream_stages() {
+        let context = RequestContext::new(Uuid::new_v4()).with_timeout(Duration::from_secs(10));
+        assert!(context.stage_timeout(Duration::from_secs(30)) <= Duration::from_secs(10));
+        assert_eq!(context.stage_timeout(Duration::from_secs(1)), Duration::from_secs(1));
+        assert!(!context.is_expired());
+        assert!(context.clone().with_timeout(Duration::ZERO).is_expired());
+        assert_eq!(RequestContext::default().stage_timeout(Duration::from_secs(5)), Duration::from_secs(5));
+
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        manager
+            .create_context("s1".to_string(), "u1".to_string(), "medical".to_string(), "Pneumonia is treated with antibiotics".to_string(), 5)
+            .await
+            .unwrap();
+        let selector = Arc::new(ContextSelector::new(manager.clone()));
+        let processor = RequestProcessor::new(manager, selector)
+            .with_text_generator(Arc::new(
+                // 生成前等待一段时间，并检查提示词中的语言要求
+                FixedGenerator::new("La pneumonie se traite par antibiotiques")
+                    .with_delay(Duration::from_millis(200))
+                    .expecting_prompt("- Answer in fr-FR."),
+            ));
+        let request = |options: RequestOptions| {
+            processor.process_request_with_options("u1".to_string(), "s1".to_string(), "pneumonia".to_string(), "medical".to_string(), options)
+        };
+
+        // 调用方的时间预算短于生成耗时：按剩余时间超时，而不是等满全局的生成超时
+        let started = Instant::now();
+        let result = request(RequestOptions { deadline_ms: Some(50), locale: Some("fr-FR".to_string()), ..RequestOptions::default() }).await;
+        assert!(matches!(result, Err(RequestError::StageTimeout { .. })));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        // 语言区域在未指定回答语言时生效
+        let result = request(RequestOptions { locale: Some("fr-FR".to_string()), ..RequestOptions::default() }).await.unwrap();
+        assert!(result.answer.unwrap().starts_with("La pneumonie"));
+
+        // 按请求关闭回答生成
+        let mut options = RequestOptions::default();
+        options.feature_flags.insert(FLAG_ANSWER_GENERATION.to_string(), false);
+        let result = request(options).await.unwrap();
+        assert!(result.answer.is_none());
+        assert_eq!(result.selected_contexts.len(), 1);
+    }
+}
@@ -0,0 +1,309 @@
+//! 搜索配额 - 按服务/用户/领域统计付费搜索API的查询次数和费用，并执行每日预算
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use crate::monitoring::quota::{QuotaKind, QuotaUsage, QuotaWarner};
+use crate::utils::provider_health::{BING_PROVIDER, GITHUB_PROVIDER};
+
+/// 预算范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+pub enum BudgetScope {
+    Provider,
+    User,
+    Domain,
+}
+
+/// 超出每日搜索预算
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchBudgetExceeded {
+    pub scope: BudgetScope,
+    pub key: String,    // 服务名、用户ID或领域
+    pub limit: u64,     // 每日上限
+    pub used: u64,      // 今日已用次数
+}
+
+impl std::fmt::Display for SearchBudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "Daily search budget exceeded for {:?} '{}': {}/{} queries",
+            self.scope, self.key, self.used, self.limit
+        )
+    }
+}
+
+impl std::error::Error for SearchBudgetExceeded {}
+
+/// 搜索预算配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchBudgetConfig {
+    pub provider_daily_limits: HashMap<String, u64>,    // 每个服务的每日查询上限
+    pub user_daily_limit: Option<u64>,                  // 每个用户的每日查询上限
+    pub domain_daily_limit: Option<u64>,                // 每个领域的每日查询上限
+    pub cost_per_query: HashMap<String, f64>,           // 每个服务的单次查询费用
+}
+
+impl Default for SearchBudgetConfig {
+    fn default() -> Self {
+        let mut provider_daily_limits = HashMap::new();
+        provider_daily_limits.insert(BING_PROVIDER.to_string(), 1000);
+        provider_daily_limits.insert(GITHUB_PROVIDER.to_string(), 5000);
+
+        let mut cost_per_query = HashMap::new();
+        cost_per_query.insert(BING_PROVIDER.to_string(), 0.007);
+        cost_per_query.insert(GITHUB_PROVIDER.to_string(), 0.0);
+
+        Self {
+            provider_daily_limits,
+            user_daily_limit: Some(100),
+            domain_daily_limit: None,
+            cost_per_query,
+        }
+    }
+}
+
+/// 当日搜索用量汇总
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchUsageSummary {
+    pub date: NaiveDate,                    // 统计日期（UTC）
+    pub total_queries: u64,
+    pub total_cost: f64,
+    pub rejected_queries: u64,              // 因超出预算被拒绝的查询
+    pub by_provider: HashMap<String, u64>,
+    pub by_user: HashMap<String, u64>,
+    pub by_domain: HashMap<String, u64>,
+    pub cost_by_provider: HashMap<String, f64>,
+}
+
+impl SearchUsageSummary {
+    fn empty(date: NaiveDate) -> Self {
+        Self {
+            date,
+            total_queries: 0,
+            total_cost: 0.0,
+            rejected_queries: 0,
+            by_provider: HashMap::new(),
+            by_user: HashMap::new(),
+            by_domain: HashMap::new(),
+            cost_by_provider: HashMap::new(),
+        }
+    }
+}
+
+/// 搜索预算 - 每次查询前调用`try_consume`，按UTC日期自动重置
+pub struct SearchBudget {
+    config: Arc<RwLock<SearchBudgetConfig>>,
+    usage: Arc<RwLock<SearchUsageSummary>>,
+    /// 按请求统计的各服务查询次数，请求结束时由请求处理器取出计入费用
+    request_usage: Arc<RwLock<HashMap<Uuid, HashMap<String, u64>>>>,
+    /// 可选的配额预警器（预警器依赖的监控系统又依赖搜索预算，因此在创建后关联）
+    quota_warner: std::sync::RwLock<Option<Arc<QuotaWarner>>>,
+}
+
+impl Default for SearchBudget {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchBudget {
+    /// 使用默认配置创建搜索预算
+    pub fn new() -> Self {
+        Self::with_config(SearchBudgetConfig::default())
+    }
+
+    /// 使用指定配置创建搜索预算
+    pub fn with_config(config: SearchBudgetConfig) -> Self {
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            usage: Arc::new(RwLock::new(SearchUsageSummary::empty(Utc::now().date_naive()))),
+            request_usage: Arc::new(RwLock::new(HashMap::new())),
+            quota_warner: std::sync::RwLock::new(None),
+        }
+    }
+
+    /// 关联配额预警器，每次查询后检查各项预算的用量
+    pub fn attach_quota_warner(&self, warner: Arc<QuotaWarner>) {
+        *self.quota_warner.write().unwrap_or_else(|e| e.into_inner()) = Some(warner);
+    }
+
+    /// 检查预算并记录一次查询，超出任一预算时拒绝且不计入用量；指定请求时同时计入该请求的查询次数
+    pub async fn try_consume(
+        &self,
+        provider: &str,
+        user_id: Option<&str>,
+        domain: Option<&str>,
+        request_id: Option<Uuid>,
+    ) -> Result<(), SearchBudgetExceeded> {
+        let config = self.config.read().await.clone();
+        let mut usage = self.usage.write().await;
+        let today = Utc::now().date_naive();
+        if usage.date != today {
+            *usage = SearchUsageSummary::empty(today);
+        }
+
+        let mut checks = Vec::new();
+        if let Some(&limit) = config.provider_daily_limits.get(provider) {
+            checks.push((BudgetScope::Provider, provider, limit, usage.by_provider.get(provider)));
+        }
+        if let (Some(limit), Some(user_id)) = (config.user_daily_limit, user_id) {
+            checks.push((BudgetScope::User, user_id, limit, usage.by_user.get(user_id)));
+        }
+        if let (Some(limit), Some(domain)) = (config.domain_daily_limit, domain) {
+            checks.push((BudgetScope::Domain, domain, limit, usage.by_domain.get(domain)));
+        }
+        let exceeded = checks.into_iter().find_map(|(scope, key, limit, used)| {
+            let used = used.copied().unwrap_or(0);
+            (used >= limit).then(|| SearchBudgetExceeded { scope, key: key.to_string(), limit, used })
+        });
+        if let Some(exceeded) = exceeded {
+            usage.rejected_queries += 1;
+            log::warn!("{}", exceeded);
+            return Err(exceeded);
+        }
+
+        let cost = config.cost_per_query.get(provider).copied().unwrap_or(0.0);
+        usage.total_queries += 1;
+        usage.total_cost += cost;
+        *usage.by_provider.entry(provider.to_string()).or_insert(0) += 1;
+        *usage.cost_by_provider.entry(provider.to_string()).or_insert(0.0) += cost;
+        if let Some(user_id) = user_id {
+            *usage.by_user.entry(user_id.to_string()).or_insert(0) += 1;
+        }
+        if let Some(domain) = domain {
+            *usage.by_domain.entry(domain.to_string()).or_insert(0) += 1;
+        }
+        if let Some(request_id) = request_id {
+            *self.request_usage.write().await.entry(request_id).or_default().entry(provider.to_string()).or_insert(0) += 1;
+        }
+
+        let warner = self.quota_warner.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if let Some(warner) = warner {
+            let usages = Self::usages_for(&config, &usage, Some(provider), user_id, domain);
+            drop(usage);
+            for quota in usages {
+                warner.observe(quota).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// 指定服务、用户和领域（为None时为全部）在已配置上限下的用量
+    fn usages_for(
+        config: &SearchBudgetConfig,
+        usage: &SearchUsageSummary,
+        provider: Option<&str>,
+        user_id: Option<&str>,
+        domain: Option<&str>,
+    ) -> Vec<QuotaUsage> {
+        let period = usage.date.to_string();
+        let mut usages = Vec::new();
+        for (name, &limit) in &config.provider_daily_limits {
+            if provider.is_none_or(|provider| provider == name) {
+                let used = usage.by_provider.get(name).copied().unwrap_or(0);
+                usages.push(QuotaUsage::new(QuotaKind::SearchBudget, BudgetScope::Provider, name, used, limit, &period));
+            }
+        }
+        if let Some(limit) = config.user_daily_limit {
+            for (user, &used) in usage.by_user.iter().filter(|(user, _)| user_id.is_none_or(|user_id| user_id == user.as_str())) {
+                usages.push(QuotaUsage::new(QuotaKind::SearchBudget, BudgetScope::User, user, used, limit, &period));
+            }
+        }
+        if let Some(limit) = config.domain_daily_limit {
+            for (name, &used) in usage.by_domain.iter().filter(|(name, _)| domain.is_none_or(|domain| domain == name.as_str())) {
+                usages.push(QuotaUsage::new(QuotaKind::SearchBudget, BudgetScope::Domain, name, used, limit, &period));
+            }
+        }
+        usages
+    }
+
+    /// 当日各项预算的用量（服务按配置列出，用户和领域只列出今日有查询的）
+    pub async fn quota_usage(&self) -> Vec<QuotaUsage> {
+        let config = self.config.read().await.clone();
+        let usage = self.get_usage().await;
+        Self::usages_for(&config, &usage, None, None, None)
+    }
+
+    /// 取出并清除请求发起的各服务查询次数
+    pub async fn take_request_queries(&self, request_id: Uuid) -> HashMap<String, u64> {
+        self.request_usage.write().await.remove(&request_id).unwrap_or_default()
+    }
+
+    /// 清除用户当日的搜索计数（用户数据删除），返回是否存在计数；dry_run时只检查
+    pub async fn purge_user(&self, user_id: &str, dry_run: bool) -> bool {
+        let mut usage = self.usage.write().await;
+        if dry_run {
+            usage.by_user.contains_key(user_id)
+        } else {
+            usage.by_user.remove(user_id).is_some()
+        }
+    }
+
+    /// 获取当日用量
+    pub async fn get_usage(&self) -> SearchUsageSummary {
+        let usage = self.usage.read().await;
+        if usage.date != Utc::now().date_naive() {
+            return SearchUsageSummary::empty(Utc::now().date_naive());
+        }
+        usage.clone()
+    }
+
+    /// 更新配置
+    pub async fn update_config(&self, new_config: SearchBudgetConfig) {
+        let mut config = self.config.write().await;
+        *config = new_config;
+    }
+
+    /// 获取当前配置
+    pub async fn get_config(&self) -> SearchBudgetConfig {
+        self.config.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_daily_budgets() {
+        let mut config = SearchBudgetConfig::default();
+        config.provider_daily_limits.insert(BING_PROVIDER.to_string(), 3);
+        config.user_daily_limit = Some(2);
+        let budget = SearchBudget::with_config(config);
+
+        let request_id = Uuid::new_v4();
+        budget.try_consume(BING_PROVIDER, Some("u1"), Some("medical"), Some(request_id)).await.unwrap();
+        budget.try_consume(BING_PROVIDER, Some("u1"), Some("medical"), Some(request_id)).await.unwrap();
+        let err = budget.try_consume(BING_PROVIDER, Some("u1"), None, Some(request_id)).await.unwrap_err();
+        assert_eq!(err.scope, BudgetScope::User);
+        assert_eq!(err.used, 2);
+
+        budget.try_consume(BING_PROVIDER, Some("u2"), None, None).await.unwrap();
+        let err = budget.try_consume(BING_PROVIDER, Some("u3"), None, None).await.unwrap_err();
+        assert_eq!(err.scope, BudgetScope::Provider);
+        budget.try_consume(GITHUB_PROVIDER, Some("u3"), None, Some(request_id)).await.unwrap();
+
+        // 按请求统计的次数不含被拒绝的查询，取出后清除
+        let request_queries = budget.take_request_queries(request_id).await;
+        assert_eq!((request_queries[BING_PROVIDER], request_queries[GITHUB_PROVIDER]), (2, 1));
+        assert!(budget.take_request_queries(request_id).await.is_empty());
+
+        // 删除用户数据时清除其计数，服务和领域的汇总不变
+        assert!(budget.purge_user("u1", true).await);
+        assert!(budget.purge_user("u1", false).await);
+        assert!(!budget.purge_user("u1", false).await);
+        assert_eq!(budget.get_usage().await.by_provider[BING_PROVIDER], 3);
+
+        let usage = budget.get_usage().await;
+        assert_eq!(usage.total_queries, 4);
+        assert_eq!(usage.rejected_queries, 2);
+        assert_eq!(usage.by_provider[BING_PROVIDER], 3);
+        assert_eq!(usage.by_domain["medical"], 2);
+        assert!((usage.total_cost - 3.0 * 0.007).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,252 @@
+//! 搜索选项 - 市场/语言、安全搜索和时效性参数，支持按领域从配置文件加载默认值
+
+use std::collections::HashMap;
+use std::fs;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::utils::outbound_scheduler::CallPriority;
+
+/// 默认配置文件路径（可通过SEARCH_CONFIG_PATH覆盖）
+pub const DEFAULT_SEARCH_CONFIG_PATH: &str = "src/utils/search_config.json";
+
+/// 安全搜索级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SafeSearch {
+    Off,
+    Moderate,
+    Strict,
+}
+
+impl SafeSearch {
+    /// Bing API参数值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SafeSearch::Off => "Off",
+            SafeSearch::Moderate => "Moderate",
+            SafeSearch::Strict => "Strict",
+        }
+    }
+}
+
+/// 结果时效性
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Freshness {
+    Day,
+    Week,
+    Month,
+}
+
+impl Freshness {
+    /// Bing API参数值
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Freshness::Day => "Day",
+            Freshness::Week => "Week",
+            Freshness::Month => "Month",
+        }
+    }
+
+    /// 对应的时间跨度
+    pub fn duration(&self) -> Duration {
+        match self {
+            Freshness::Day => Duration::days(1),
+            Freshness::Week => Duration::weeks(1),
+            Freshness::Month => Duration::days(30),
+        }
+    }
+}
+
+/// 搜索选项 - 未设置的字段使用领域默认值，再回退到全局默认值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchOptions {
+    #[serde(default)]
+    pub domain: Option<String>,             // 用于选择领域默认值和领域预算
+    #[serde(default)]
+    pub user_id: Option<String>,            // 发起搜索的用户（用于预算统计）
+    #[serde(default)]
+    pub request_id: Option<Uuid>,           // 发起搜索的请求（用于按请求计入搜索费用）
+    #[serde(default)]
+    pub market: Option<String>,             // 市场/地区（如zh-CN、en-US）
+    #[serde(default)]
+    pub language: Option<String>,           // 界面语言（如zh-hans、en）
+    #[serde(default)]
+    pub safe_search: Option<SafeSearch>,
+    #[serde(default)]
+    pub freshness: Option<Freshness>,
+    #[serde(default)]
+    pub priority: Option<CallPriority>,     // 外呼优先级（未设置时视为交互请求）
+}
+
+impl SearchOptions {
+    /// 使用指定领域的默认值
+    pub fn for_domain(domain: &str) -> Self {
+        Self {
+            domain: Some(domain.to_string()),
+            ..Self::default()
+        }
+    }
+
+    /// 设置发起搜索的用户
+    pub fn with_user(mut self, user_id: &str) -> Self {
+        self.user_id = Some(user_id.to_string());
+        self
+    }
+
+    /// 设置发起搜索的请求，查询次数计入该请求的费用
+    pub fn with_request(mut self, request_id: Uuid) -> Self {
+        self.request_id = Some(request_id);
+        self
+    }
+
+    /// 设置市场/地区
+    pub fn with_market(mut self, market: &str) -> Self {
+        self.market = Some(market.to_string());
+        self
+    }
+
+    /// 设置界面语言
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = Some(language.to_string());
+        self
+    }
+
+    /// 设置安全搜索级别
+    pub fn with_safe_search(mut self, safe_search: SafeSearch) -> Self {
+        self.safe_search = Some(safe_search);
+        self
+    }
+
+    /// 设置结果时效性
+    pub fn with_freshness(mut self, freshness: Freshness) -> Self {
+        self.freshness = Some(freshness);
+        self
+    }
+
+    /// 设置外呼优先级
+    pub fn with_priority(mut self, priority: CallPriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// 用默认值填充未设置的字段
+    pub fn or(&self, defaults: &SearchOptions) -> Self {
+        Self {
+            domain: self.domain.clone().or_else(|| defaults.domain.clone()),
+            user_id: self.user_id.clone().or_else(|| defaults.user_id.clone()),
+            request_id: self.request_id.or(defaults.request_id),
+            market: self.market.clone().or_else(|| defaults.market.clone()),
+            language: self.language.clone().or_else(|| defaults.language.clone()),
+            safe_search: self.safe_search.or(defaults.safe_search),
+            freshness: self.freshness.or(defaults.freshness),
+            priority: self.priority.or(defaults.priority),
+        }
+    }
+
+    /// 转换为Bing API查询参数
+    pub fn bing_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(ref market) = self.market {
+            params.push(("mkt", market.clone()));
+        }
+        if let Some(ref language) = self.language {
+            params.push(("setLang", language.clone()));
+        }
+        if let Some(safe_search) = self.safe_search {
+            params.push(("safeSearch", safe_search.as_str().to_string()));
+        }
+        if let Some(freshness) = self.freshness {
+            params.push(("freshness", freshness.as_str().to_string()));
+        }
+        params
+    }
+
+    /// 转换为GitHub搜索限定词（时效性映射为最近推送时间）
+    pub fn github_qualifiers(&self) -> String {
+        match self.freshness {
+            Some(freshness) => format!(" pushed:>={}", (Utc::now() - freshness.duration()).format("%Y-%m-%d")),
+            None => String::new(),
+        }
+    }
+}
+
+/// 搜索选项配置 - 全局默认值和按领域覆盖的默认值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchOptionsConfig {
+    #[serde(default)]
+    pub defaults: SearchOptions,
+    #[serde(default)]
+    pub domains: HashMap<String, SearchOptions>,
+}
+
+impl Default for SearchOptionsConfig {
+    fn default() -> Self {
+        Self {
+            defaults: SearchOptions {
+                market: Some("zh-CN".to_string()),
+                ..SearchOptions::default()
+            },
+            domains: HashMap::new(),
+        }
+    }
+}
+
+impl SearchOptionsConfig {
+    /// 从JSON文件加载配置
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let config_json = fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&config_json)?)
+    }
+
+    /// 加载配置文件，文件缺失或无效时使用内置默认值
+    pub fn load() -> Self {
+        let path = std::env::var("SEARCH_CONFIG_PATH").unwrap_or_else(|_| DEFAULT_SEARCH_CONFIG_PATH.to_string());
+        match Self::from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                log::warn!("Failed to load search config from {}: {}, using defaults", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// 解析最终生效的选项：显式选项 > 领域默认值 > 全局默认值
+    pub fn resolve(&self, options: &SearchOptions) -> SearchOptions {
+        let domain_defaults = options
+            .domain
+            .as_ref()
+            .and_then(|domain| self.domains.get(domain))
+            .map(|domain_options| domain_options.or(&self.defaults))
+            .unwrap_or_else(|| self.defaults.clone());
+        options.or(&domain_defaults)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_precedence() {
+        let config: SearchOptionsConfig = serde_json::from_str(
+            r#"{
+                "defaults": { "market": "zh-CN", "safe_search": "Moderate" },
+                "domains": { "medical": { "market": "en-US", "safe_search": "Strict" } }
+            }"#,
+        )
+        .unwrap();
+
+        let resolved = config.resolve(&SearchOptions::for_domain("medical").with_freshness(Freshness::Week));
+        assert_eq!(resolved.market.as_deref(), Some("en-US"));
+        assert_eq!(resolved.safe_search, Some(SafeSearch::Strict));
+        assert_eq!(resolved.freshness, Some(Freshness::Week));
+
+        let resolved = config.resolve(&SearchOptions::for_domain("legal").with_market("en-GB"));
+        assert_eq!(resolved.market.as_deref(), Some("en-GB"));
+        assert_eq!(resolved.safe_search, Some(SafeSearch::Moderate));
+        assert_eq!(
+            resolved.bing_params(),
+            vec![("mkt", "en-GB".to_string()), ("safeSearch", "Moderate".to_string())]
+        );
+    }
+}
@@ -0,0 +1,378 @@
+//! 分词器 - 检索评分、提示词装填和入库分块共用同一个分词器，保证各环节对令牌数的理解一致；
+//! 提供空白分词（默认）、中文字二元组和BPE三种实现，也可接入外部分词器
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use crate::selection::hybrid_search::tokenize;
+
+/// 分词器
+pub trait Tokenizer: Send + Sync {
+    /// 名称，区分不同分词器（及词表）下缓存的分词结果
+    fn name(&self) -> &str;
+
+    /// 切分为小写检索词项（BM25使用）
+    fn terms(&self, text: &str) -> Vec<String>;
+
+    /// 令牌数
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// 截断到max_tokens以内，保留完整的词（无法按词截断时按该分词器的最小单位截断）
+    fn truncate(&self, text: &str, max_tokens: usize) -> String;
+
+    /// 按词贪心切分为每段不超过max_tokens的文本块，单个词超长时按字符切开；max_tokens为0时不切分
+    fn chunk(&self, text: &str, max_tokens: usize) -> Vec<String> {
+        let mut chunks = Vec::new();
+        let mut current = String::new();
+        for word in text.split_whitespace() {
+            let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+            if max_tokens == 0 || self.count_tokens(&candidate) <= max_tokens {
+                current = candidate;
+                continue;
+            }
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            let mut rest = word;
+            while self.count_tokens(rest) > max_tokens {
+                let split = longest_prefix(self, rest, max_tokens);
+                chunks.push(rest[..split].to_string());
+                rest = &rest[split..];
+            }
+            current = rest.to_string();
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+    }
+}
+
+/// 不超过max_tokens的最长字符前缀（字节位置），至少包含一个字符
+fn longest_prefix<T: Tokenizer + ?Sized>(tokenizer: &T, word: &str, max_tokens: usize) -> usize {
+    let mut end = word.chars().next().map(char::len_utf8).unwrap_or(0);
+    for (i, c) in word.char_indices().skip(1) {
+        let next = i + c.len_utf8();
+        if tokenizer.count_tokens(&word[..next]) > max_tokens {
+            break;
+        }
+        end = next;
+    }
+    end
+}
+
+/// 是否为CJK字符（每个字符约一个令牌）
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF)
+}
+
+/// 单个词的令牌估算：CJK字符各算一个，其余字符每4个算一个
+fn word_tokens(word: &str) -> usize {
+    let cjk = word.chars().filter(|c| is_cjk(*c)).count();
+    let other = word.chars().count() - cjk;
+    cjk + other.div_ceil(4)
+}
+
+/// 空白分词器（默认）：按空白切词估算令牌数，检索词项按非字母数字字符切分
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WhitespaceTokenizer;
+
+impl Tokenizer for WhitespaceTokenizer {
+    fn name(&self) -> &str {
+        "whitespace"
+    }
+
+    fn terms(&self, text: &str) -> Vec<String> {
+        tokenize(text)
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        text.split_whitespace().map(word_tokens).sum()
+    }
+
+    /// CJK连续文本按字符截断
+    fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        let mut used = 0;
+        let mut words = Vec::new();
+        for word in text.split_whitespace() {
+            let tokens = word_tokens(word);
+            if used + tokens > max_tokens {
+                let remaining = max_tokens - used;
+                if remaining > 0 && word.chars().any(is_cjk) {
+                    words.push(word.chars().take(remaining).collect::<String>());
+                }
+                break;
+            }
+            used += tokens;
+            words.push(word.to_string());
+        }
+        words.join(" ")
+    }
+}
+
+/// 中文分词器：CJK连续文本按字二元组切分检索词项（单字成词），其余文本与空白分词器一致；令牌估算与空白分词器相同
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CjkTokenizer;
+
+impl CjkTokenizer {
+    /// 输出一段CJK连续文本的二元组
+    fn flush_run(run: &mut Vec<char>, terms: &mut Vec<String>) {
+        if run.len() == 1 {
+            terms.push(run[0].to_string());
+        } else {
+            terms.extend(run.windows(2).map(|pair| pair.iter().collect::<String>()));
+        }
+        run.clear();
+    }
+}
+
+impl Tokenizer for CjkTokenizer {
+    fn name(&self) -> &str {
+        "cjk"
+    }
+
+    fn terms(&self, text: &str) -> Vec<String> {
+        let mut terms = Vec::new();
+        let mut run: Vec<char> = Vec::new();
+        let mut word = String::new();
+        for c in text.to_lowercase().chars() {
+            if is_cjk(c) {
+                if !word.is_empty() {
+                    terms.push(std::mem::take(&mut word));
+                }
+                run.push(c);
+                continue;
+            }
+            if !run.is_empty() {
+                Self::flush_run(&mut run, &mut terms);
+            }
+            if c.is_alphanumeric() {
+                word.push(c);
+            } else if !word.is_empty() {
+                terms.push(std::mem::take(&mut word));
+            }
+        }
+        if !run.is_empty() {
+            Self::flush_run(&mut run, &mut terms);
+        }
+        if !word.is_empty() {
+            terms.push(word);
+        }
+        terms
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        WhitespaceTokenizer.count_tokens(text)
+    }
+
+    fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        WhitespaceTokenizer.truncate(text, max_tokens)
+    }
+}
+
+/// BPE分词器：每个空白切分的词从单个字符开始按合并规则的优先级合并，令牌数为合并后的片段数；检索词项与空白分词器一致
+#[derive(Debug, Clone)]
+pub struct BpeTokenizer {
+    ranks: HashMap<(String, String), usize>,   // 合并规则 -> 优先级（越小越先合并）
+    name: String,
+}
+
+impl BpeTokenizer {
+    /// 按给定顺序的合并规则创建
+    pub fn from_merges(merges: Vec<(String, String)>) -> Self {
+        let name = format!("bpe-{}", merges.len());
+        let ranks = merges.into_iter().enumerate().map(|(rank, pair)| (pair, rank)).collect();
+        Self { ranks, name }
+    }
+
+    /// 从合并规则文件加载：每行一条"左 右"，忽略空行和以#开头的行（兼容merges.txt格式）
+    pub fn load_merges(path: impl AsRef<Path>) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let content = std::fs::read_to_string(path)?;
+        let mut merges = Vec::new();
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(left), Some(right), None) => merges.push((left.to_string(), right.to_string())),
+                _ => return Err(format!("Invalid merge rule on line {}: {}", number + 1, line).into()),
+            }
+        }
+        Ok(Self::from_merges(merges))
+    }
+
+    /// 在语料上训练：每轮合并出现次数最多的相邻片段对（同频时取字典序最小），直到达到合并数或没有出现两次以上的片段对
+    pub fn train(corpus: &[&str], num_merges: usize) -> Self {
+        let mut words: HashMap<Vec<String>, usize> = HashMap::new();
+        for text in corpus {
+            for word in text.split_whitespace() {
+                *words.entry(word.chars().map(String::from).collect()).or_insert(0) += 1;
+            }
+        }
+
+        let mut merges = Vec::new();
+        while merges.len() < num_merges {
+            let mut pairs: HashMap<(String, String), usize> = HashMap::new();
+            for (symbols, count) in &words {
+                for pair in symbols.windows(2) {
+                    *pairs.entry((pair[0].clone(), pair[1].clone())).or_insert(0) += count;
+                }
+            }
+            let best = pairs
+                .into_iter()
+                .filter(|(_, count)| *count >= 2)
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then_with(|| b.cmp(a)));
+            let Some((pair, _)) = best else {
+                break;
+            };
+            words = words
+                .into_iter()
+                .map(|(symbols, count)| (merge_pair(symbols, &pair), count))
+                .collect();
+            merges.push(pair);
+        }
+        Self::from_merges(merges)
+    }
+
+    /// 将单个词编码为片段
+    pub fn encode_word(&self, word: &str) -> Vec<String> {
+        let mut symbols: Vec<String> = word.chars().map(String::from).collect();
+        loop {
+            let best = symbols
+                .windows(2)
+                .filter_map(|pair| self.ranks.get(&(pair[0].clone(), pair[1].clone())).map(|rank| (*rank, pair)))
+                .min_by_key(|(rank, _)| *rank)
+                .map(|(_, pair)| (pair[0].clone(), pair[1].clone()));
+            match best {
+                Some(pair) => symbols = merge_pair(symbols, &pair),
+                None => return symbols,
+            }
+        }
+    }
+
+    /// 将文本编码为片段（按空白切分后逐词编码）
+    pub fn encode(&self, text: &str) -> Vec<String> {
+        text.split_whitespace().flat_map(|word| self.encode_word(word)).collect()
+    }
+}
+
+/// 合并片段序列中所有相邻的指定片段对
+fn merge_pair(symbols: Vec<String>, pair: &(String, String)) -> Vec<String> {
+    let mut merged = Vec::with_capacity(symbols.len());
+    let mut i = 0;
+    while i < symbols.len() {
+        if i + 1 < symbols.len() && symbols[i] == pair.0 && symbols[i + 1] == pair.1 {
+            merged.push(format!("{}{}", pair.0, pair.1));
+            i += 2;
+        } else {
+            merged.push(symbols[i].clone());
+            i += 1;
+        }
+    }
+    merged
+}
+
+impl Tokenizer for BpeTokenizer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn terms(&self, text: &str) -> Vec<String> {
+        tokenize(text)
+    }
+
+    fn count_tokens(&self, text: &str) -> usize {
+        text.split_whitespace().map(|word| self.encode_word(word).len()).sum()
+    }
+
+    /// 最后一个放不下的词保留能放下的前若干片段
+    fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        let mut used = 0;
+        let mut words = Vec::new();
+        for word in text.split_whitespace() {
+            let pieces = self.encode_word(word);
+            if used + pieces.len() > max_tokens {
+                let partial: String = pieces.into_iter().take(max_tokens - used).collect();
+                if !partial.is_empty() {
+                    words.push(partial);
+                }
+                break;
+            }
+            used += pieces.len();
+            words.push(word.to_string());
+        }
+        words.join(" ")
+    }
+}
+
+/// 按名称创建分词器："whitespace"、"cjk"或"bpe:<合并规则文件>"
+pub fn tokenizer_from_spec(spec: &str) -> Result<Arc<dyn Tokenizer>, Box<dyn std::error::Error + Send + Sync>> {
+    match spec.trim() {
+        "" | "whitespace" => Ok(Arc::new(WhitespaceTokenizer)),
+        "cjk" => Ok(Arc::new(CjkTokenizer)),
+        other => match other.strip_prefix("bpe:") {
+            Some(path) => Ok(Arc::new(BpeTokenizer::load_merges(path)?)),
+            None => Err(format!("Unknown tokenizer: {}", other).into()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+    use crate::context::ingestion::{IngestDocument, IngestionConfig, IngestionService};
+    use crate::context::llm_context::ContextManager;
+    use crate::processing::context_packing::pack_contexts;
+
+    #[tokio::test]
+    async fn test_tokenizers_agree_across_chunking_and_packing() {
+        assert_eq!(WhitespaceTokenizer.count_tokens("肺炎 pneumonia"), 2 + 3);
+        assert_eq!(CjkTokenizer.terms("肺炎治疗, COVID-19 肺"), vec!["肺炎", "炎治", "治疗", "covid", "19", "肺"]);
+
+        let bpe = BpeTokenizer::train(&["lower lowest low low", "newer newest"], 10);
+        assert_eq!(bpe.encode_word("low"), vec!["low"]);
+        assert!(bpe.count_tokens("lowest slow") < WhitespaceTokenizer.count_tokens("lowest slow") + "slow".len());
+        assert_eq!(bpe.truncate("low lowest", 1), "low");
+
+        let path = std::env::temp_dir().join(format!("penlai-merges-{}.txt", Uuid::new_v4()));
+        std::fs::write(&path, "#version: 0.2\nl o\nlo w\n").unwrap();
+        let loaded = tokenizer_from_spec(&format!("bpe:{}", path.display())).unwrap();
+        assert_eq!((loaded.name(), loaded.count_tokens("low lot")), ("bpe-2", 1 + 2));
+        std::fs::remove_file(&path).unwrap();
+        assert!(tokenizer_from_spec("sentencepiece").is_err());
+
+        // 入库分块和提示词装填使用同一个分词器：每块都恰好能完整装入同样大小的预算
+        let tokenizer: Arc<dyn Tokenizer> = Arc::new(bpe);
+        let manager = Arc::new(ContextManager::new(10, 3600));
+        let service = IngestionService::new(manager.clone()).with_tokenizer(tokenizer.clone());
+        service.update_config(IngestionConfig { max_chunk_tokens: 6, ..IngestionConfig::default() }).await;
+        let document = IngestDocument {
+            external_id: Some("guide-1".to_string()),
+            title: None,
+            content: "The lowest dose is lower for newer patients. Newest guidance: follow the lowest dose.".to_string(),
+            domain: "medical".to_string(),
+            session_id: None,
+            user_id: None,
+            priority: None,
+            metadata: HashMap::new(),
+        };
+        let written = service.ingest_document("wiki", document.clone(), 5).await.unwrap();
+        assert!(written.len() > 1 && written.iter().all(|(_, created)| *created));
+        let ids: Vec<Uuid> = written.iter().map(|(id, _)| *id).collect();
+        let chunks: Vec<_> = futures::future::join_all(ids.iter().map(|id| manager.get_context(*id))).await.into_iter().map(Option::unwrap).collect();
+        for chunk in &chunks {
+            assert!(tokenizer.count_tokens(&chunk.context_data) <= 6);
+            let packed = pack_contexts(vec![chunk.clone()], 6, tokenizer.as_ref());
+            assert!(packed.truncated.is_empty() && packed.dropped.is_empty());
+        }
+
+        // 同一external_id再次入库时复用已有分块，多余的分块被删除
+        let shorter = IngestDocument { content: "low".to_string(), ..document };
+        let written = service.ingest_document("wiki", shorter, 5).await.unwrap();
+        assert_eq!(written, vec![(ids[0], false)]);
+        assert_eq!(manager.get_all_contexts().await.len(), 1);
+    }
+}
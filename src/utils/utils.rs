@@ -1,5 +1,4 @@
-
-/// 工具函数模块 - 提供异步上下文选择控制系统中的通用工具函数
+//! 工具函数模块 - 提供异步上下文选择控制系统中的通用工具函数
 
 /// 计算文本相似度的工具函数
 pub mod similarity {
@@ -110,7 +109,7 @@ pub mod string_utils {
             .collect();
 
         // 按频率排序
-        word_freq.sort_by(|a, b| b.1.cmp(&a.1));
+        word_freq.sort_by_key(|b| std::cmp::Reverse(b.1));
 
         word_freq
             .into_iter()
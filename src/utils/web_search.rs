@@ -1,6 +1,17 @@
 use reqwest;
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::utils::search_budget::{SearchBudget, SearchBudgetExceeded};
+use crate::utils::outbound_scheduler::{CallPriority, OutboundScheduler, OutboundThrottled};
+use crate::utils::search_options::{SearchOptions, SearchOptionsConfig};
+use crate::utils::provider_health::{ProviderHealth, ProviderStatus, BING_PROVIDER};
+use crate::utils::replay::{ReplayError, ReplayLayer};
+use crate::utils::async_runtime::{fan_out, TaskError};
+
+/// 聚合搜索同时进行的查询数
+const AGGREGATE_SEARCH_CONCURRENCY: usize = 4;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SearchResult {
@@ -32,6 +43,9 @@ pub enum WebSearchError {
     RequestError(reqwest::Error),
     ParseError(serde_json::Error),
     ApiError(String),
+    BudgetExceeded(SearchBudgetExceeded),
+    Throttled(OutboundThrottled),
+    Replay(ReplayError),
 }
 
 impl From<reqwest::Error> for WebSearchError {
@@ -40,6 +54,24 @@ impl From<reqwest::Error> for WebSearchError {
     }
 }
 
+impl From<SearchBudgetExceeded> for WebSearchError {
+    fn from(err: SearchBudgetExceeded) -> Self {
+        WebSearchError::BudgetExceeded(err)
+    }
+}
+
+impl From<OutboundThrottled> for WebSearchError {
+    fn from(err: OutboundThrottled) -> Self {
+        WebSearchError::Throttled(err)
+    }
+}
+
+impl From<ReplayError> for WebSearchError {
+    fn from(err: ReplayError) -> Self {
+        WebSearchError::Replay(err)
+    }
+}
+
 impl From<serde_json::Error> for WebSearchError {
     fn from(err: serde_json::Error) -> Self {
         WebSearchError::ParseError(err)
@@ -50,14 +82,22 @@ pub struct WebSearchClient {
     client: reqwest::Client,
     bing_search_url: String,
     bing_api_key: String,
+    search_config: SearchOptionsConfig,
+    budget: Option<Arc<SearchBudget>>,
+    scheduler: Option<Arc<OutboundScheduler>>,
+    replay: Option<Arc<ReplayLayer>>,
 }
 
 impl WebSearchClient {
     pub fn new() -> Result<Self, WebSearchError> {
         dotenv::dotenv().ok(); // Load .env file
 
-        let bing_api_key = env::var("BING_API_KEY")
-            .map_err(|_| WebSearchError::ApiKeyMissing)?;
+        // 回放模式下不访问Bing，不需要API密钥
+        let bing_api_key = match env::var("BING_API_KEY") {
+            Ok(key) => key,
+            Err(_) if ReplayLayer::replay_requested() => String::new(),
+            Err(_) => return Err(WebSearchError::ApiKeyMissing),
+        };
         
         let bing_search_url = env::var("BING_SEARCH_URL")
             .unwrap_or_else(|_| "https://api.bing.microsoft.com/v7.0/search".to_string());
@@ -66,20 +106,71 @@ impl WebSearchClient {
             client: reqwest::Client::new(),
             bing_search_url,
             bing_api_key,
+            search_config: SearchOptionsConfig::load(),
+            budget: None,
+            scheduler: None,
+            replay: ReplayLayer::from_env(),
         })
     }
 
+    /// 使用指定的搜索选项配置（替代配置文件）
+    pub fn with_search_config(mut self, search_config: SearchOptionsConfig) -> Self {
+        self.search_config = search_config;
+        self
+    }
+
+    /// 使用指定的Bing搜索地址（替代BING_SEARCH_URL）
+    pub fn with_search_url(mut self, bing_search_url: &str) -> Self {
+        self.bing_search_url = bing_search_url.to_string();
+        self
+    }
+
+    /// 关联搜索预算，每次查询前检查并计入用量
+    pub fn with_budget(mut self, budget: Arc<SearchBudget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// 关联外呼调度器，每次查询前按优先级等待服务配额
+    pub fn with_scheduler(mut self, scheduler: Arc<OutboundScheduler>) -> Self {
+        self.scheduler = Some(scheduler);
+        self
+    }
+
+    /// 关联录制/回放层，回放模式下不再访问Bing
+    pub fn with_replay(mut self, replay: Arc<ReplayLayer>) -> Self {
+        self.replay = Some(replay);
+        self
+    }
+
+    /// 获取搜索选项配置
+    pub fn get_search_config(&self) -> &SearchOptionsConfig {
+        &self.search_config
+    }
+
     /// Perform a web search using Bing Search API
-    pub async fn search(&self, query: &str, count: Option<u32>) -> Result<Vec<SearchResult>, WebSearchError> {
+    pub async fn search(&self, query: &str, count: Option<u32>, options: &SearchOptions) -> Result<Vec<SearchResult>, WebSearchError> {
         let count = count.unwrap_or(5);
         
-        let params = [
-            ("q", query),
-            ("count", &count.to_string()),
-            ("mkt", "zh-CN"),  // Market/region
-            ("textDecorations", "true"),
-            ("textFormat", "HTML"),
+        let mut params = vec![
+            ("q", query.to_string()),
+            ("count", count.to_string()),
+            ("textDecorations", "true".to_string()),
+            ("textFormat", "HTML".to_string()),
         ];
+        // Market, language, safe-search and freshness from options or configured defaults
+        let options = self.search_config.resolve(options);
+        params.extend(options.bing_params());
+
+        if let Some(replay) = self.replay.as_ref().filter(|replay| replay.is_replaying()) {
+            return Ok(replay.replay(BING_PROVIDER, &params).await?);
+        }
+        if let Some(ref budget) = self.budget {
+            budget.try_consume(BING_PROVIDER, options.user_id.as_deref(), options.domain.as_deref(), options.request_id).await?;
+        }
+        if let Some(ref scheduler) = self.scheduler {
+            scheduler.acquire(BING_PROVIDER, options.priority.unwrap_or_default()).await?;
+        }
 
         let response = self.client
             .get(&self.bing_search_url)
@@ -104,15 +195,46 @@ impl WebSearchClient {
                 url: item.url,
                 summary: item.snippet,
             })
-            .collect();
+            .collect::<Vec<_>>();
 
+        if let Some(ref replay) = self.replay {
+            replay.record(BING_PROVIDER, &params, &results).await?;
+        }
         Ok(results)
     }
 
+    /// 探测Bing API密钥是否可用。探测是一次真实查询，与搜索一样计入搜索预算，并以后台优先级等待外呼配额；
+    /// 预算耗尽或等待配额超时时不发出请求，视为被限流
+    pub async fn check_health(&self, timeout: Duration) -> ProviderHealth {
+        if let Some(ref budget) = self.budget {
+            if let Err(e) = budget.try_consume(BING_PROVIDER, None, None, None).await {
+                return ProviderHealth::new(BING_PROVIDER, ProviderStatus::RateLimited, None, Some(e.to_string()));
+            }
+        }
+        if let Some(ref scheduler) = self.scheduler {
+            if let Err(e) = scheduler.acquire(BING_PROVIDER, CallPriority::Background).await {
+                return ProviderHealth::new(BING_PROVIDER, ProviderStatus::RateLimited, None, Some(e.to_string()));
+            }
+        }
+
+        let response = self.client
+            .get(&self.bing_search_url)
+            .header("Ocp-Apim-Subscription-Key", &self.bing_api_key)
+            .query(&[("q", "health"), ("count", "1")])
+            .timeout(timeout)
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => ProviderHealth::from_response(BING_PROVIDER, &response),
+            Err(e) => ProviderHealth::new(BING_PROVIDER, ProviderStatus::Unreachable, None, Some(e.to_string())),
+        }
+    }
+
     /// Perform semantic search and aggregation across multiple queries
-    pub async fn semantic_search(&self, query: &str) -> Result<Vec<SearchResult>, WebSearchError> {
+    pub async fn semantic_search(&self, query: &str, options: &SearchOptions) -> Result<Vec<SearchResult>, WebSearchError> {
         // First, try the main query
-        let results = self.search(query, Some(5)).await?;
+        let results = self.search(query, Some(5), options).await?;
 
         // In a more advanced implementation, we might use LLM to analyze relevance
         // For now, we'll implement basic relevance scoring based on keyword matching
@@ -120,23 +242,38 @@ impl WebSearchClient {
         Ok(results)
     }
 
-    /// Aggregate search results from multiple queries with deduplication
-    pub async fn aggregate_search(&self, queries: &[&str], max_results: u32) -> Result<Vec<SearchResult>, WebSearchError> {
-        let mut all_results = Vec::new();
+    /// Aggregate search results from multiple queries with deduplication.
+    /// Queries run concurrently; a failed query is skipped unless every query fails, and the
+    /// remaining queries are cancelled once the search budget is exhausted.
+    pub async fn aggregate_search(&self, queries: &[&str], max_results: u32, options: &SearchOptions) -> Result<Vec<SearchResult>, WebSearchError> {
         let results_per_query = max_results / std::cmp::max(queries.len() as u32, 1);
+        let outcomes = fan_out(
+            queries.iter().copied(),
+            AGGREGATE_SEARCH_CONCURRENCY,
+            |e| matches!(e, WebSearchError::BudgetExceeded(_)),
+            |query| self.search(query, Some(results_per_query), options),
+        )
+        .await;
 
-        for query in queries {
-            match self.search(query, Some(results_per_query)).await {
+        let mut all_results = Vec::new();
+        let mut succeeded = false;
+        let mut first_error = None;
+        for (query, outcome) in queries.iter().zip(outcomes) {
+            match outcome {
                 Ok(results) => {
+                    succeeded = true;
                     all_results.extend(results);
-                },
-                Err(e) => {
-                    eprintln!("Search failed for query '{}': {:?}", query, e);
-                    // Continue with other queries
-                    continue;
                 }
+                Err(TaskError::Failed(e)) => {
+                    log::warn!("Search failed for query '{}': {:?}", query, e);
+                    first_error.get_or_insert(e);
+                }
+                Err(e) => log::warn!("Search for query '{}' did not complete: {:?}", query, e),
             }
         }
+        if let (false, Some(e)) = (succeeded, first_error) {
+            return Err(e);
+        }
 
         // Deduplicate results by URL
         let mut seen_urls = std::collections::HashSet::new();
@@ -150,8 +287,8 @@ impl WebSearchClient {
     }
 
     /// Enhanced search with result parsing and filtering
-    pub async fn enhanced_search(&self, query: &str, count: Option<u32>, filter_domains: Option<Vec<&str>>) -> Result<Vec<SearchResult>, WebSearchError> {
-        let results = self.search(query, count).await?;
+    pub async fn enhanced_search(&self, query: &str, count: Option<u32>, filter_domains: Option<Vec<&str>>, options: &SearchOptions) -> Result<Vec<SearchResult>, WebSearchError> {
+        let results = self.search(query, count, options).await?;
 
         // Apply domain filtering if specified
         let filtered_results = if let Some(domains) = filter_domains {
@@ -205,8 +342,8 @@ impl WebSearchClient {
     }
 
     /// Search and apply relevance scoring in one call
-    pub async fn search_with_relevance_scoring(&self, query: &str, count: Option<u32>) -> Result<Vec<SearchResult>, WebSearchError> {
-        let results = self.search(query, count).await?;
+    pub async fn search_with_relevance_scoring(&self, query: &str, count: Option<u32>, options: &SearchOptions) -> Result<Vec<SearchResult>, WebSearchError> {
+        let results = self.search(query, count, options).await?;
         Ok(self.score_relevance(results, query))
     }
 }
@@ -232,7 +369,7 @@ mod tests {
         let client = WebSearchClient::new();
         match client {
             Ok(search_client) => {
-                let results = search_client.search("test query", Some(3)).await;
+                let results = search_client.search("test query", Some(3), &SearchOptions::default()).await;
                 match results {
                     Ok(res) => {
                         assert!(res.len() <= 3);
@@ -275,6 +412,10 @@ mod tests {
                     client: reqwest::Client::new(),
                     bing_search_url: "https://api.bing.microsoft.com/v7.0/search".to_string(),
                     bing_api_key: "dummy_key".to_string(),
+                    search_config: SearchOptionsConfig::default(),
+                    budget: None,
+                    scheduler: None,
+                    replay: None,
                 }
             }
         };
@@ -292,7 +433,7 @@ mod tests {
         match client {
             Ok(search_client) => {
                 let queries = ["Rust", "programming", "language"];
-                let results = search_client.aggregate_search(&queries, 6).await;
+                let results = search_client.aggregate_search(&queries, 6, &SearchOptions::default()).await;
                 match results {
                     Ok(res) => {
                         assert!(res.len() <= 6);